@@ -0,0 +1,247 @@
+//! Resolve SIDs (like the ones returned by `Win32_UserProfile` or `Win32_LoggedOnUser`) into account names, and vice versa.
+//!
+//! The lookups are done by WMI (using `Win32_SID` and `Win32_Account`), so they are resolved by the computer
+//! the connection is made to, which knows about its local accounts.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # use serde::Deserialize;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! #[derive(Deserialize, Debug)]
+//! #[serde(rename = "Win32_UserProfile")]
+//! #[serde(rename_all = "PascalCase")]
+//! struct UserProfile {
+//!     #[serde(rename = "SID")]
+//!     sid: String,
+//!     local_path: Option<String>,
+//! }
+//!
+//! // Many profiles can share a SID lookup, so use a resolver which caches them.
+//! let resolver = con.sid_resolver();
+//!
+//! for profile in con.query::<UserProfile>()? {
+//!     match resolver.resolve(&profile.sid)? {
+//!         Some(account) => println!("{} ({:?})", account, profile.local_path),
+//!         None => println!("Unknown account {} ({:?})", profile.sid, profile.local_path),
+//!     }
+//! }
+//!
+//! assert_eq!(con.lookup_sid("S-1-5-18")?.unwrap().name, "SYSTEM");
+//! # Ok(())
+//! # }
+//! ```
+use crate::{connection::WMIConnection, query::quote_and_escape_wql_str, WMIError, WMIResult};
+use serde::Deserialize;
+use std::{cell::RefCell, collections::HashMap, fmt};
+use windows::Win32::System::Wmi::WBEM_E_NOT_FOUND;
+
+/// The name of an account, as resolved from its SID.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AccountName {
+    /// The domain of the account, like `CONTOSO`, `NT AUTHORITY` or the name of the computer for local accounts.
+    /// Empty for some well-known SIDs (like `S-1-1-0`, `Everyone`).
+    pub domain: String,
+    pub name: String,
+}
+
+impl fmt::Display for AccountName {
+    /// Formats the account as `DOMAIN\name` (or `name`, without a domain).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.domain.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{}\\{}", self.domain, self.name)
+        }
+    }
+}
+
+/// A security identifier, from `Win32_SID`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "Win32_SID")]
+#[serde(rename_all = "PascalCase")]
+pub struct Sid {
+    #[serde(rename = "SID")]
+    pub sid: String,
+    /// Empty if the SID cannot be resolved.
+    pub account_name: Option<String>,
+    pub referenced_domain_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename = "Win32_Account")]
+#[serde(rename_all = "PascalCase")]
+struct Account {
+    #[serde(rename = "SID")]
+    sid: String,
+}
+
+/// Check that `s` is a SID in the string format (like `S-1-5-21-3623811015-3361044348-30300820-1013`).
+pub fn is_sid(s: &str) -> bool {
+    match s.strip_prefix("S-1-") {
+        Some(rest) => rest
+            .split('-')
+            .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit())),
+        None => false,
+    }
+}
+
+///
+/// ### Additional SID and account methods
+///
+impl WMIConnection {
+    /// Resolve a SID into the name of its account.
+    ///
+    /// Returns `None` if `sid` is not a SID or if it cannot be resolved (like the SIDs of deleted accounts).
+    /// See the [module level documentation](crate::account) for an example.
+    pub fn lookup_sid(&self, sid: &str) -> WMIResult<Option<AccountName>> {
+        if !is_sid(sid) {
+            return Ok(None);
+        }
+
+        let sid: Sid = match self.get_by_path(&format!("Win32_SID.SID=\"{}\"", sid)) {
+            Ok(sid) => sid,
+            Err(WMIError::HResultError { hres }) if hres == WBEM_E_NOT_FOUND.0 => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        Ok(match sid.account_name {
+            Some(name) if !name.is_empty() => Some(AccountName {
+                domain: sid.referenced_domain_name.unwrap_or_default(),
+                name,
+            }),
+            _ => None,
+        })
+    }
+
+    /// Resolve an account (a user, group or system account) into its SID.
+    ///
+    /// The account can be in the `DOMAIN\name` format, or just a name (which may match accounts of several domains,
+    /// in which case the first one is used). Returns `None` if there is no such account.
+    pub fn lookup_account(&self, account: &str) -> WMIResult<Option<String>> {
+        let query = match account.split_once('\\') {
+            Some((domain, name)) => format!(
+                "SELECT SID FROM Win32_Account WHERE Domain = {} AND Name = {}",
+                quote_and_escape_wql_str(domain),
+                quote_and_escape_wql_str(name)
+            ),
+            None => format!(
+                "SELECT SID FROM Win32_Account WHERE Name = {}",
+                quote_and_escape_wql_str(account)
+            ),
+        };
+
+        let accounts: Vec<Account> = self.raw_query(query)?;
+
+        Ok(accounts.into_iter().next().map(|account| account.sid))
+    }
+
+    /// Create a [`SidResolver`], which caches the SIDs resolved using this connection.
+    pub fn sid_resolver(&self) -> SidResolver<'_> {
+        SidResolver {
+            con: self,
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+/// Resolves SIDs into account names using [`WMIConnection::lookup_sid`], caching the results
+/// (including SIDs which cannot be resolved), for post-processing query results which repeat the same SIDs.
+///
+/// Created using [`WMIConnection::sid_resolver`].
+pub struct SidResolver<'a> {
+    con: &'a WMIConnection,
+    cache: RefCell<HashMap<String, Option<AccountName>>>,
+}
+
+impl<'a> SidResolver<'a> {
+    /// Resolve a SID, see [`WMIConnection::lookup_sid`].
+    pub fn resolve(&self, sid: &str) -> WMIResult<Option<AccountName>> {
+        if let Some(account) = self.cache.borrow().get(sid) {
+            return Ok(account.clone());
+        }
+
+        let account = self.con.lookup_sid(sid)?;
+
+        self.cache
+            .borrow_mut()
+            .insert(sid.to_owned(), account.clone());
+
+        Ok(account)
+    }
+
+    /// Resolve a SID into a display name, falling back to the SID itself if it cannot be resolved
+    /// (like Explorer does for the owners of files).
+    pub fn display_name(&self, sid: &str) -> WMIResult<String> {
+        Ok(self
+            .resolve(sid)?
+            .map_or_else(|| sid.to_owned(), |account| account.to_string()))
+    }
+}
+
+impl<'a> fmt::Debug for SidResolver<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SidResolver")
+            .field("cache", &self.cache)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    #[test]
+    fn it_validates_sids() {
+        assert!(is_sid("S-1-5-18"));
+        assert!(is_sid("S-1-5-21-3623811015-3361044348-30300820-1013"));
+        assert!(is_sid("S-1-1-0"));
+
+        assert!(!is_sid("S-1"));
+        assert!(!is_sid("S-2-5-18"));
+        assert!(!is_sid("S-1-5-"));
+        assert!(!is_sid("S-1-5-18\" OR 1=1"));
+        assert!(!is_sid("NT AUTHORITY\\SYSTEM"));
+    }
+
+    #[test]
+    fn it_formats_account_names() {
+        let account = AccountName {
+            domain: "NT AUTHORITY".to_owned(),
+            name: "SYSTEM".to_owned(),
+        };
+        assert_eq!(account.to_string(), "NT AUTHORITY\\SYSTEM");
+
+        let account = AccountName {
+            domain: String::new(),
+            name: "Everyone".to_owned(),
+        };
+        assert_eq!(account.to_string(), "Everyone");
+    }
+
+    #[test]
+    fn it_resolves_sids_and_accounts() {
+        let wmi_con = wmi_con();
+
+        let system = wmi_con.lookup_sid("S-1-5-18").unwrap().unwrap();
+        assert_eq!(system.name, "SYSTEM");
+
+        assert_eq!(wmi_con.lookup_sid("not a sid").unwrap(), None);
+        assert_eq!(wmi_con.lookup_sid("S-1-5-21-1-2-3-4242").unwrap(), None);
+
+        let resolver = wmi_con.sid_resolver();
+        assert_eq!(resolver.resolve("S-1-5-18").unwrap(), Some(system));
+        assert_eq!(
+            resolver.display_name("S-1-5-21-1-2-3-4242").unwrap(),
+            "S-1-5-21-1-2-3-4242"
+        );
+
+        assert_eq!(
+            wmi_con
+                .lookup_account("this-account-does-not-exist")
+                .unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,304 @@
+//! Convert query results into Arrow [`RecordBatch`]es, for analytics pipelines.
+//!
+//! The schema is inferred from the definition of the queried class (and not from the returned values),
+//! so it is the same for every query of the class, even when no rows are returned:
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use arrow::datatypes::DataType;
+//!
+//! let batch = con.query_to_arrow("SELECT Name, ProcessId, ThreadCount FROM Win32_Process")?;
+//!
+//! assert_eq!(batch.schema().field(1).data_type(), &DataType::UInt32);
+//! assert!(batch.num_rows() > 0);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! CIM types are mapped as follows:
+//! * Integers, reals and booleans are mapped to the matching Arrow types (including 64-bit integers,
+//!   which WMI returns as strings).
+//! * Strings, datetimes, references and characters are mapped to `Utf8` (datetimes are kept as DMTF strings).
+//! * Arrays are mapped to `List`s of their item type.
+//! * Embedded objects are not supported, and their properties are not included.
+//!
+//! All the fields are nullable.
+use crate::{
+    connection::WMIConnection,
+    order::{as_float, as_integer},
+    query::{query_class, select_projection},
+    Variant, WMIError, WMIResult,
+};
+use ::arrow::array::{
+    ArrayRef, BooleanArray, Float32Array, Float64Array, Int16Array, Int32Array, Int64Array,
+    Int8Array, ListArray, StringArray, UInt16Array, UInt32Array, UInt64Array, UInt8Array,
+};
+use ::arrow::buffer::{NullBuffer, OffsetBuffer};
+use ::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use ::arrow::record_batch::RecordBatch;
+use std::sync::Arc;
+use windows::Win32::System::Wmi::WBEM_E_INVALID_QUERY;
+
+///
+/// ### Additional Arrow methods
+///
+impl WMIConnection {
+    /// Execute a `SELECT` query, and convert the results into a single [`RecordBatch`].
+    ///
+    /// See the [module level documentation](crate::arrow) for an example.
+    pub fn query_to_arrow(&self, query: impl AsRef<str>) -> WMIResult<RecordBatch> {
+        let query = query.as_ref();
+
+        // Only `SELECT` queries have a class to infer the schema from.
+        let class = query_class(query).ok_or(WMIError::HResultError {
+            hres: WBEM_E_INVALID_QUERY.0,
+        })?;
+
+        let schema = self.arrow_schema(class, select_projection(query).as_deref())?;
+
+        let mut columns: Vec<Vec<Variant>> = schema.fields().iter().map(|_| vec![]).collect();
+
+        for row in self.exec_query_native_wrapper(query)? {
+            let row = row?;
+
+            for (field, column) in schema.fields().iter().zip(&mut columns) {
+                column.push(row.get_property(field.name())?);
+            }
+        }
+
+        let arrays = schema
+            .fields()
+            .iter()
+            .zip(columns)
+            .map(|(field, values)| build_array(field.data_type(), values))
+            .collect::<WMIResult<Vec<_>>>()?;
+
+        Ok(RecordBatch::try_new(schema, arrays)?)
+    }
+
+    /// The Arrow schema of a class, or of the given properties of the class (in order).
+    pub fn arrow_schema(&self, class: &str, properties: Option<&[String]>) -> WMIResult<SchemaRef> {
+        let description = self.describe_class(class)?;
+
+        let mut fields = vec![];
+
+        let selected: Vec<_> = match properties {
+            Some(properties) => properties
+                .iter()
+                .filter_map(|name| {
+                    description
+                        .properties
+                        .iter()
+                        .find(|property| property.name.eq_ignore_ascii_case(name))
+                })
+                .collect(),
+            None => description.properties.iter().collect(),
+        };
+
+        for property in selected {
+            if let Some(data_type) = data_type(&property.cim_type) {
+                fields.push(Field::new(&property.name, data_type, true));
+            }
+        }
+
+        Ok(Arc::new(Schema::new(fields)))
+    }
+}
+
+/// The Arrow type of a CIM type (given by its MOF name, like `uint32` or `string[]`).
+fn data_type(cim_type: &str) -> Option<DataType> {
+    if let Some(item) = cim_type.strip_suffix("[]") {
+        let item = data_type(item)?;
+
+        return Some(DataType::List(Arc::new(Field::new("item", item, true))));
+    }
+
+    let data_type = match cim_type {
+        "sint8" => DataType::Int8,
+        "uint8" => DataType::UInt8,
+        "sint16" => DataType::Int16,
+        "uint16" => DataType::UInt16,
+        "sint32" => DataType::Int32,
+        "uint32" => DataType::UInt32,
+        "sint64" => DataType::Int64,
+        "uint64" => DataType::UInt64,
+        "real32" => DataType::Float32,
+        "real64" => DataType::Float64,
+        "boolean" => DataType::Boolean,
+        "object" => return None,
+        // Strings, datetimes, references and characters.
+        _ => DataType::Utf8,
+    };
+
+    Some(data_type)
+}
+
+fn string(value: Variant) -> Option<String> {
+    match value {
+        Variant::Null | Variant::Empty | Variant::Object(_) | Variant::Unknown(_) => None,
+        Variant::String(s) => Some(s),
+        other => Some(format!("{:?}", other)),
+    }
+}
+
+/// Build an array of the given type. Values which don't fit the type are converted to nulls.
+fn build_array(data_type: &DataType, values: Vec<Variant>) -> WMIResult<ArrayRef> {
+    macro_rules! integers {
+        ($array:ty, $native:ty) => {
+            Arc::new(
+                values
+                    .iter()
+                    .map(|value| as_integer(value).and_then(|n| <$native>::try_from(n).ok()))
+                    .collect::<$array>(),
+            )
+        };
+    }
+
+    let array: ArrayRef = match data_type {
+        DataType::Int8 => integers!(Int8Array, i8),
+        DataType::UInt8 => integers!(UInt8Array, u8),
+        DataType::Int16 => integers!(Int16Array, i16),
+        DataType::UInt16 => integers!(UInt16Array, u16),
+        DataType::Int32 => integers!(Int32Array, i32),
+        DataType::UInt32 => integers!(UInt32Array, u32),
+        DataType::Int64 => integers!(Int64Array, i64),
+        DataType::UInt64 => integers!(UInt64Array, u64),
+        DataType::Float32 => Arc::new(
+            values
+                .iter()
+                .map(|value| as_float(value).map(|n| n as f32))
+                .collect::<Float32Array>(),
+        ),
+        DataType::Float64 => Arc::new(values.iter().map(as_float).collect::<Float64Array>()),
+        DataType::Boolean => Arc::new(
+            values
+                .iter()
+                .map(|value| match value {
+                    Variant::Bool(b) => Some(*b),
+                    _ => None,
+                })
+                .collect::<BooleanArray>(),
+        ),
+        DataType::List(item) => {
+            let mut lengths = Vec::with_capacity(values.len());
+            let mut valid = Vec::with_capacity(values.len());
+            let mut items = vec![];
+
+            for value in values {
+                match value {
+                    Variant::Array(array) => {
+                        lengths.push(array.len());
+                        valid.push(true);
+                        items.extend(array);
+                    }
+                    _ => {
+                        lengths.push(0);
+                        valid.push(false);
+                    }
+                }
+            }
+
+            let items = build_array(item.data_type(), items)?;
+
+            Arc::new(ListArray::try_new(
+                item.clone(),
+                OffsetBuffer::from_lengths(lengths),
+                items,
+                Some(NullBuffer::from(valid)),
+            )?)
+        }
+        _ => Arc::new(values.into_iter().map(string).collect::<StringArray>()),
+    };
+
+    Ok(array)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use ::arrow::array::Array;
+
+    #[test]
+    fn it_maps_cim_types() {
+        assert_eq!(data_type("uint32"), Some(DataType::UInt32));
+        assert_eq!(data_type("datetime"), Some(DataType::Utf8));
+        assert_eq!(data_type("object"), None);
+        assert_eq!(data_type("object[]"), None);
+        assert_eq!(
+            data_type("string[]"),
+            Some(DataType::List(Arc::new(Field::new(
+                "item",
+                DataType::Utf8,
+                true
+            ))))
+        );
+    }
+
+    #[test]
+    fn it_builds_arrays() {
+        let numbers = build_array(
+            &DataType::UInt64,
+            vec![
+                Variant::String("18446744073709551615".to_owned()),
+                Variant::Null,
+                Variant::UI4(3),
+            ],
+        )
+        .unwrap();
+
+        let numbers = numbers.as_any().downcast_ref::<UInt64Array>().unwrap();
+        assert_eq!(numbers.value(0), u64::MAX);
+        assert!(numbers.is_null(1));
+        assert_eq!(numbers.value(2), 3);
+
+        let lists = build_array(
+            &data_type("uint16[]").unwrap(),
+            vec![
+                Variant::Array(vec![Variant::I4(1), Variant::I4(2)]),
+                Variant::Null,
+            ],
+        )
+        .unwrap();
+
+        let lists = lists.as_any().downcast_ref::<ListArray>().unwrap();
+        assert_eq!(lists.len(), 2);
+        assert_eq!(lists.value(0).len(), 2);
+        assert!(lists.is_null(1));
+    }
+
+    #[test]
+    fn it_converts_queries_to_arrow() {
+        let wmi_con = wmi_con();
+
+        let batch = wmi_con
+            .query_to_arrow("SELECT Name, ProcessId FROM Win32_Process")
+            .unwrap();
+
+        assert_eq!(batch.num_columns(), 2);
+        assert_eq!(batch.schema().field(0).name(), "Name");
+        assert_eq!(batch.schema().field(1).data_type(), &DataType::UInt32);
+        assert!(batch.num_rows() > 1);
+
+        let pids = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<UInt32Array>()
+            .unwrap();
+        assert!(pids.iter().any(|pid| pid == Some(std::process::id())));
+
+        let os = wmi_con
+            .query_to_arrow("SELECT * FROM Win32_OperatingSystem")
+            .unwrap();
+        assert_eq!(os.num_rows(), 1);
+        assert!(os.schema().field_with_name("MUILanguages").is_ok());
+
+        let empty = wmi_con
+            .query_to_arrow("SELECT Name FROM Win32_Process WHERE ProcessId = 4294967295")
+            .unwrap();
+        assert_eq!(empty.num_rows(), 0);
+        assert_eq!(empty.num_columns(), 1);
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     connection::WMIConnection,
-    query::{build_query, FilterValue},
+    query::{build_query, select_projection, FilterValue},
     query_sink::{AsyncQueryResultStream, AsyncQueryResultStreamInner, QuerySink},
     result_enumerator::IWbemClassWrapper,
     WMIResult,
@@ -23,6 +23,8 @@ impl WMIConnection {
         &self,
         query: impl AsRef<str>,
     ) -> WMIResult<impl Stream<Item = WMIResult<IWbemClassWrapper>>> {
+        self.check_win32_product(query.as_ref())?;
+
         let query_language = BSTR::from("WQL");
         let query = BSTR::from(query.as_ref());
 
@@ -40,7 +42,7 @@ impl WMIConnection {
                 &query_language,
                 &query,
                 WBEM_FLAG_BIDIRECTIONAL.0 as _,
-                None,
+                self.ctx(),
                 &p_sink_handle,
             )?;
         }
@@ -77,9 +79,13 @@ impl WMIConnection {
     where
         T: de::DeserializeOwned,
     {
+        let projection = select_projection(query.as_ref());
+
         self.exec_query_async_native_wrapper(query)?
             .map(|item| match item {
-                Ok(wbem_class_obj) => wbem_class_obj.into_desr(),
+                Ok(wbem_class_obj) => {
+                    wbem_class_obj.into_desr_with_options(&self.de_options, projection.as_deref())
+                }
                 Err(e) => Err(e),
             })
             .try_collect::<Vec<_>>()
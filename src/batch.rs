@@ -0,0 +1,333 @@
+//! Apply a batch of writes (puts, deletes and method calls) in order, and report the outcome of each one.
+//!
+//! WMI does not support transactions, so the operations which succeeded before a failure are not rolled back.
+//! Instead, the [`BatchReport`] tells exactly which operations were applied, which failed,
+//! and which were skipped (when stopping on the first error).
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use wmi::batch::{BatchOptions, WriteOp};
+//!
+//! let ops = vec![
+//!     WriteOp::delete(r#"__IntervalTimerInstruction.TimerId="old""#),
+//!     WriteOp::exec_method("StdRegProv", "CreateKey", vec![
+//!         ("hDefKey", Variant::I4(0x80000001_u32 as i32)),
+//!         ("sSubKeyName", "SOFTWARE\\wmi-rs-example".into()),
+//!     ]),
+//! ];
+//!
+//! let report = con.apply_batch_with(ops, &BatchOptions::new().stop_on_error(true));
+//!
+//! for outcome in report.failures() {
+//!     println!("#{} {} failed: {:?}", outcome.index, outcome.description, outcome.result);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    connection::WMIConnection, instance::PutOptions, result_enumerator::IWbemClassWrapper, Variant,
+    WMIError, WMIResult,
+};
+use log::debug;
+
+/// A single write, see the [module level documentation](crate::batch).
+#[derive(Debug)]
+pub enum WriteOp {
+    /// Write an instance, using [`WMIConnection::put_instance_with`].
+    Put {
+        instance: IWbemClassWrapper,
+        options: PutOptions,
+    },
+    /// Delete the instance at the given path.
+    Delete { path: String },
+    /// Execute a method, which fails if its `ReturnValue` is not `0`.
+    ExecMethod {
+        path: String,
+        method: String,
+        in_params: Vec<(String, Variant)>,
+    },
+}
+
+impl WriteOp {
+    pub fn put(instance: IWbemClassWrapper) -> Self {
+        Self::put_with(instance, PutOptions::default())
+    }
+
+    pub fn put_with(instance: IWbemClassWrapper, options: PutOptions) -> Self {
+        WriteOp::Put { instance, options }
+    }
+
+    pub fn delete(path: impl Into<String>) -> Self {
+        WriteOp::Delete { path: path.into() }
+    }
+
+    pub fn exec_method<S>(
+        path: impl Into<String>,
+        method: impl Into<String>,
+        in_params: Vec<(S, Variant)>,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        WriteOp::ExecMethod {
+            path: path.into(),
+            method: method.into(),
+            in_params: in_params
+                .into_iter()
+                .map(|(name, value)| (name.into(), value))
+                .collect(),
+        }
+    }
+
+    /// A short description of the operation, like `Delete Win32_Environment.Name="X"`.
+    pub fn description(&self) -> String {
+        match self {
+            WriteOp::Put { instance, .. } => {
+                // New instances don't have a path yet.
+                let target = instance
+                    .path()
+                    .or_else(|_| instance.class())
+                    .unwrap_or_default();
+
+                format!("Put {}", target)
+            }
+            WriteOp::Delete { path } => format!("Delete {}", path),
+            WriteOp::ExecMethod { path, method, .. } => format!("Execute {}.{}", path, method),
+        }
+    }
+}
+
+/// How a batch is applied, see [`WMIConnection::apply_batch_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BatchOptions {
+    stop_on_error: bool,
+}
+
+impl BatchOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skip the remaining operations after the first one which fails.
+    pub fn stop_on_error(mut self, stop_on_error: bool) -> Self {
+        self.stop_on_error = stop_on_error;
+        self
+    }
+}
+
+/// The outcome of a single operation of a batch.
+#[derive(Debug)]
+pub struct OpOutcome {
+    /// The position of the operation in the batch.
+    pub index: usize,
+    pub description: String,
+    /// The output parameters of method calls (`None` for puts and deletes).
+    pub result: WMIResult<Option<IWbemClassWrapper>>,
+}
+
+impl OpOutcome {
+    pub fn is_ok(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// The outcomes of the operations of a batch, in order.
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    /// The operations which were executed.
+    pub outcomes: Vec<OpOutcome>,
+    /// The descriptions of the operations which were skipped after a failure (when stopping on errors).
+    pub skipped: Vec<String>,
+}
+
+impl BatchReport {
+    /// Whether all the operations were executed successfully.
+    pub fn is_success(&self) -> bool {
+        self.skipped.is_empty() && self.outcomes.iter().all(OpOutcome::is_ok)
+    }
+
+    pub fn succeeded(&self) -> impl Iterator<Item = &OpOutcome> {
+        self.outcomes.iter().filter(|outcome| outcome.is_ok())
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &OpOutcome> {
+        self.outcomes.iter().filter(|outcome| !outcome.is_ok())
+    }
+}
+
+///
+/// ### Additional batch methods
+///
+impl WMIConnection {
+    /// Apply all the operations in order, even if some of them fail.
+    pub fn apply_batch(&self, ops: Vec<WriteOp>) -> BatchReport {
+        self.apply_batch_with(ops, &BatchOptions::default())
+    }
+
+    /// Apply the operations in order, according to `options`.
+    ///
+    /// See the [module level documentation](crate::batch) for an example.
+    pub fn apply_batch_with(&self, ops: Vec<WriteOp>, options: &BatchOptions) -> BatchReport {
+        let mut report = BatchReport::default();
+        let mut ops = ops.into_iter().enumerate();
+
+        for (index, op) in ops.by_ref() {
+            let description = op.description();
+
+            debug!("Applying #{}: {}", index, description);
+
+            let result = self.apply_op(op);
+            let failed = result.is_err();
+
+            report.outcomes.push(OpOutcome {
+                index,
+                description,
+                result,
+            });
+
+            if failed && options.stop_on_error {
+                break;
+            }
+        }
+
+        report.skipped = ops.map(|(_, op)| op.description()).collect();
+
+        report
+    }
+
+    fn apply_op(&self, op: WriteOp) -> WMIResult<Option<IWbemClassWrapper>> {
+        match op {
+            WriteOp::Put { instance, options } => {
+                self.put_instance_with(&instance, &options).map(|_| None)
+            }
+            WriteOp::Delete { path } => self.delete_instance(&path).map(|_| None),
+            WriteOp::ExecMethod {
+                path,
+                method,
+                in_params,
+            } => {
+                let (names, values): (Vec<String>, Vec<Variant>) = in_params.into_iter().unzip();
+                let in_params: Vec<(&str, Variant)> =
+                    names.iter().map(String::as_str).zip(values).collect();
+
+                let out = self.exec_method(&path, &method, &in_params)?;
+
+                if let Some(out) = &out {
+                    check_return_value(&method, out)?;
+                }
+
+                Ok(out)
+            }
+        }
+    }
+}
+
+/// Fail if the method has a non-zero `ReturnValue`.
+fn check_return_value(method: &str, out: &IWbemClassWrapper) -> WMIResult<()> {
+    let return_value = match out.get_property("ReturnValue") {
+        Ok(Variant::UI4(n)) => n,
+        Ok(Variant::I4(n)) => n as u32,
+        Ok(Variant::UI2(n)) => n.into(),
+        Ok(Variant::UI1(n)) => n.into(),
+        // Methods without a `ReturnValue` (or which return something else).
+        _ => return Ok(()),
+    };
+
+    if return_value != 0 {
+        return Err(WMIError::MethodFailed {
+            method: method.to_owned(),
+            return_value,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    const HKEY_LOCAL_MACHINE: u32 = 0x80000002;
+
+    fn ops() -> Vec<WriteOp> {
+        vec![
+            WriteOp::delete(r#"__IntervalTimerInstruction.TimerId="wmi-rs-does-not-exist""#),
+            WriteOp::exec_method(
+                "StdRegProv",
+                "EnumKey",
+                vec![
+                    ("hDefKey", Variant::I4(HKEY_LOCAL_MACHINE as i32)),
+                    ("sSubKeyName", "SOFTWARE".into()),
+                ],
+            ),
+            WriteOp::exec_method(
+                "StdRegProv",
+                "EnumKey",
+                vec![
+                    ("hDefKey", Variant::I4(HKEY_LOCAL_MACHINE as i32)),
+                    ("sSubKeyName", "SOFTWARE\\wmi-rs-does-not-exist".into()),
+                ],
+            ),
+        ]
+    }
+
+    #[test]
+    fn it_describes_ops() {
+        let ops = ops();
+
+        assert_eq!(
+            ops[0].description(),
+            r#"Delete __IntervalTimerInstruction.TimerId="wmi-rs-does-not-exist""#
+        );
+        assert_eq!(ops[1].description(), "Execute StdRegProv.EnumKey");
+    }
+
+    #[test]
+    fn it_applies_batches() {
+        let wmi_con = wmi_con();
+
+        let report = wmi_con.apply_batch(ops());
+
+        assert!(!report.is_success());
+        assert!(report.skipped.is_empty());
+        assert_eq!(report.outcomes.len(), 3);
+
+        // Deleting a missing instance fails, but the next ops are still applied.
+        assert!(!report.outcomes[0].is_ok());
+        assert!(report.outcomes[1].result.as_ref().unwrap().is_some());
+        // A missing key is reported using the `ReturnValue` of the method.
+        assert!(matches!(
+            report.outcomes[2].result,
+            Err(WMIError::MethodFailed { .. })
+        ));
+
+        assert_eq!(report.succeeded().count(), 1);
+        assert_eq!(
+            report
+                .failures()
+                .map(|outcome| outcome.index)
+                .collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+    }
+
+    #[test]
+    fn it_stops_batches_on_errors() {
+        let wmi_con = wmi_con();
+
+        let report = wmi_con.apply_batch_with(ops(), &BatchOptions::new().stop_on_error(true));
+
+        assert_eq!(report.outcomes.len(), 1);
+        assert_eq!(
+            report.skipped,
+            vec![
+                "Execute StdRegProv.EnumKey".to_owned(),
+                "Execute StdRegProv.EnumKey".to_owned()
+            ]
+        );
+        assert!(!report.is_success());
+    }
+}
@@ -107,6 +107,11 @@ fn get_services(con: &WMIConnection) {
     let _services: Vec<Service> = con.query().unwrap();
 }
 
+fn get_services_hash_map(con: &WMIConnection) {
+    let _services: Vec<HashMap<String, Variant>> =
+        con.raw_query("SELECT * FROM Win32_Service").unwrap();
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     let com = COMLibrary::new().unwrap();
 
@@ -158,6 +163,12 @@ fn criterion_benchmark(c: &mut Criterion) {
         let wmi_con = WMIConnection::new(com).unwrap();
         b.iter(|| get_services(&wmi_con))
     });
+
+    // Mostly string properties, which measures the conversion of BSTRs.
+    c.bench_function("get_services_hash_map", |b| {
+        let wmi_con = WMIConnection::new(com).unwrap();
+        b.iter(|| get_services_hash_map(&wmi_con))
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
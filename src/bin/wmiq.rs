@@ -1,28 +1,199 @@
-use std::{collections::HashMap, env::args};
-use wmi::{COMLibrary, Variant, WMIConnection, WMIResult};
-
-fn main() -> WMIResult<()> {
-    let wmi_con = WMIConnection::new(COMLibrary::new()?)?;
-    let args: Vec<String> = args().collect();
-    let query = match args.get(1) {
-        None => {
-            println!("Expected an argument with a WMI query");
-            return Ok(());
-        }
-        Some(query) => query,
+//! A small command line tool for running WMI queries.
+//!
+//! ```text
+//! wmiq [--namespace <path>] [--format table|json|csv] <query>
+//! wmiq [--namespace <path>] --namespaces
+//! wmiq [--namespace <path>] --classes [<pattern>]
+//! ```
+use std::{env::args, io, process::exit};
+use wmi::{export::Format, COMLibrary, Variant, WMIConnection, WMIResult};
+
+const USAGE: &str = "\
+Usage:
+    wmiq [--namespace <path>] [--format table|json|csv] <query>
+    wmiq [--namespace <path>] --namespaces
+    wmiq [--namespace <path>] --classes [<pattern>]
+
+Options:
+    -n, --namespace <path>  The namespace to connect to (default: ROOT\\CIMV2)
+    -f, --format <format>   The output format of a query: table (default), json (JSON Lines) or csv
+        --namespaces        List the namespaces under the namespace
+        --classes           List the classes of the namespace, optionally matching a pattern like `Win32_*`
+    -h, --help              Print this message";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Table,
+    Export(Format),
+}
+
+#[derive(Debug)]
+enum Command {
+    Query(String, Output),
+    Namespaces,
+    Classes(Option<String>),
+}
+
+#[derive(Debug)]
+struct Args {
+    namespace: Option<String>,
+    command: Command,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut namespace = None;
+    let mut output = Output::Table;
+    let mut command = None;
+    let mut query = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Err(String::new()),
+            "-n" | "--namespace" => {
+                namespace = Some(args.next().ok_or("Expected a namespace path")?);
+            }
+            "-f" | "--format" => {
+                output = match args.next().as_deref() {
+                    Some("table") => Output::Table,
+                    Some("json") => Output::Export(Format::JsonLines),
+                    Some("csv") => Output::Export(Format::Csv),
+                    other => return Err(format!("Unknown format {:?}", other.unwrap_or(""))),
+                };
+            }
+            "--namespaces" => command = Some(Command::Namespaces),
+            "--classes" => command = Some(Command::Classes(None)),
+            other if other.starts_with('-') => return Err(format!("Unknown option {:?}", other)),
+            _ => match &mut command {
+                Some(Command::Classes(pattern @ None)) => *pattern = Some(arg),
+                _ if query.is_none() => query = Some(arg),
+                _ => return Err(format!("Unexpected argument {:?}", arg)),
+            },
+        }
+    }
+
+    let command = match (command, query) {
+        (Some(command), None) => command,
+        (None, Some(query)) => Command::Query(query, output),
+        (None, None) => return Err("Expected a WMI query".into()),
+        (Some(_), Some(query)) => return Err(format!("Unexpected argument {:?}", query)),
     };
 
-    let results: Vec<HashMap<String, Variant>> = match wmi_con.raw_query(query) {
-        Err(e) => {
-            println!("Couldn't run query {} because of {:?}", query, e);
-            return Ok(());
+    Ok(Args { namespace, command })
+}
+
+fn main() {
+    let args = match parse_args(args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            if !message.is_empty() {
+                eprintln!("{}\n", message);
+            }
+            eprintln!("{}", USAGE);
+            exit(2);
         }
-        Ok(results) => results,
     };
 
-    for (i, res) in results.iter().enumerate() {
-        println!("Result {}", i);
-        println!("{:#?}", res);
+    if let Err(e) = run(args) {
+        eprintln!("Error: {}", e);
+        exit(1);
+    }
+}
+
+fn run(args: Args) -> WMIResult<()> {
+    let com_lib = COMLibrary::new()?;
+    let wmi_con = match &args.namespace {
+        Some(namespace) => WMIConnection::with_namespace_path(namespace, com_lib)?,
+        None => WMIConnection::new(com_lib)?,
+    };
+
+    match args.command {
+        Command::Query(query, Output::Export(format)) => {
+            let stdout = io::stdout();
+            wmi_con.export_query(&query, format, io::BufWriter::new(stdout.lock()))?;
+        }
+        Command::Query(query, Output::Table) => print_table(&wmi_con, &query)?,
+        Command::Namespaces => {
+            for name in names(&wmi_con, "SELECT Name FROM __NAMESPACE", "Name")? {
+                println!("{}", name);
+            }
+        }
+        Command::Classes(pattern) => {
+            for class in wmi_con.list_classes(pattern.as_deref())? {
+                println!("{}", class.name);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Run the query and return the (sorted) string values of `property`.
+fn names(wmi_con: &WMIConnection, query: &str, property: &str) -> WMIResult<Vec<String>> {
+    let mut names = vec![];
+
+    for obj in wmi_con.exec_query_native_wrapper(query)? {
+        if let Variant::String(name) = obj?.get_property(property)? {
+            names.push(name);
+        }
+    }
+
+    names.sort_unstable_by_key(|name| name.to_ascii_lowercase());
+
+    Ok(names)
+}
+
+fn print_table(wmi_con: &WMIConnection, query: &str) -> WMIResult<()> {
+    let mut columns = wmi::query::select_projection(query);
+    let mut rows: Vec<Vec<String>> = vec![];
+
+    for obj in wmi_con.exec_query_native_wrapper(query)? {
+        let obj = obj?;
+
+        let columns = match &columns {
+            Some(columns) => columns,
+            None => columns.insert(obj.list_properties()?),
+        };
+
+        let mut row = Vec::with_capacity(columns.len());
+
+        for column in columns.iter() {
+            let cell = match obj.get_property(column)?.to_json_value()? {
+                serde_json::Value::Null => String::new(),
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            };
+
+            row.push(cell);
+        }
+
+        rows.push(row);
+    }
+
+    let columns = columns.unwrap_or_default();
+
+    let mut widths: Vec<usize> = columns.iter().map(|c| c.chars().count()).collect();
+
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let print_row = |cells: &[String]| {
+        let line: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{:width$}", cell, width = width))
+            .collect();
+
+        println!("{}", line.join("  ").trim_end());
+    };
+
+    print_row(&columns);
+    print_row(&widths.iter().map(|&w| "-".repeat(w)).collect::<Vec<_>>());
+
+    for row in &rows {
+        print_row(row);
     }
 
     Ok(())
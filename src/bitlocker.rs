@@ -0,0 +1,260 @@
+//! BitLocker volumes, their protection status and key protectors, using the `Win32_EncryptableVolume` class.
+//!
+//! The `ROOT\CIMV2\Security\MicrosoftVolumeEncryption` namespace requires administrative rights,
+//! and only accepts connections using the `RPC_C_AUTHN_LEVEL_PKT_PRIVACY` authentication level
+//! (which [`WMIConnection::bitlocker`] sets).
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! let bitlocker = con.bitlocker()?;
+//!
+//! for volume in bitlocker.volumes()? {
+//!     println!("{:?}: {:?}", volume.drive_letter, bitlocker.protection_status(&volume)?);
+//!
+//!     for protector in bitlocker.key_protectors(&volume)? {
+//!         println!("  {} ({:?})", protector.id, protector.protector_type);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    connection::WMIConnection, result_enumerator::IWbemClassWrapper, Variant, WMIError, WMIResult,
+};
+use serde::Deserialize;
+
+/// The namespace of the BitLocker provider.
+pub const BITLOCKER_NAMESPACE: &str = "ROOT\\CIMV2\\Security\\MicrosoftVolumeEncryption";
+
+/// A volume which can be protected by BitLocker, from `Win32_EncryptableVolume`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "Win32_EncryptableVolume")]
+#[serde(rename_all = "PascalCase")]
+pub struct EncryptableVolume {
+    #[serde(rename = "__Path")]
+    pub path: String,
+    /// Like `\\?\Volume{...}\`.
+    #[serde(rename = "DeviceID")]
+    pub device_id: String,
+    #[serde(rename = "PersistentVolumeID")]
+    pub persistent_volume_id: Option<String>,
+    /// Like `C:`, if the volume has one.
+    pub drive_letter: Option<String>,
+    /// `0` for the operating system volume, `1` for fixed data volumes and `2` for removable volumes.
+    pub volume_type: Option<u32>,
+}
+
+/// The protection status of a volume, as returned by `GetProtectionStatus`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProtectionStatus {
+    /// The volume is not encrypted, partially encrypted, or its key is stored in the clear.
+    Off,
+    On,
+    /// The volume is locked, so the status cannot be determined.
+    Unknown,
+}
+
+/// The type of a key protector, as returned by `GetKeyProtectorType`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyProtectorType {
+    Tpm,
+    ExternalKey,
+    NumericalPassword,
+    TpmAndPin,
+    TpmAndStartupKey,
+    TpmAndPinAndStartupKey,
+    PublicKey,
+    Passphrase,
+    TpmCertificate,
+    /// A protector based on the credentials of an Active Directory account or group.
+    Sid,
+    /// A value not known to this crate.
+    Other(u32),
+}
+
+impl From<u32> for KeyProtectorType {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => KeyProtectorType::Tpm,
+            2 => KeyProtectorType::ExternalKey,
+            3 => KeyProtectorType::NumericalPassword,
+            4 => KeyProtectorType::TpmAndPin,
+            5 => KeyProtectorType::TpmAndStartupKey,
+            6 => KeyProtectorType::TpmAndPinAndStartupKey,
+            7 => KeyProtectorType::PublicKey,
+            8 => KeyProtectorType::Passphrase,
+            9 => KeyProtectorType::TpmCertificate,
+            10 => KeyProtectorType::Sid,
+            other => KeyProtectorType::Other(other),
+        }
+    }
+}
+
+/// A key protector of a volume.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyProtector {
+    /// Like `{0DA4C58F-E3A5-4C47-A8D3-10E8BCC9C3F5}`.
+    pub id: String,
+    pub protector_type: KeyProtectorType,
+}
+
+/// The output parameters of `GetKeyProtectors`.
+#[derive(Debug, Deserialize)]
+struct KeyProtectorIds {
+    #[serde(rename = "VolumeKeyProtectorID")]
+    volume_key_protector_id: Vec<String>,
+}
+
+/// A connection to the BitLocker namespace, created using [`WMIConnection::bitlocker`].
+#[derive(Debug, Clone)]
+pub struct BitLocker {
+    con: WMIConnection,
+}
+
+///
+/// ### Additional BitLocker methods
+///
+impl WMIConnection {
+    /// Connect to the BitLocker namespace on the same computer.
+    ///
+    /// See the [module level documentation](crate::bitlocker) for an example.
+    pub fn bitlocker(&self) -> WMIResult<BitLocker> {
+        let mut con = self.with_namespace(BITLOCKER_NAMESPACE)?;
+        con.require_packet_privacy()?;
+
+        Ok(BitLocker { con })
+    }
+}
+
+impl BitLocker {
+    /// The connection to the BitLocker namespace, to query other properties of `Win32_EncryptableVolume`.
+    pub fn connection(&self) -> &WMIConnection {
+        &self.con
+    }
+
+    pub fn volumes(&self) -> WMIResult<Vec<EncryptableVolume>> {
+        self.con.query()
+    }
+
+    pub fn protection_status(&self, volume: &EncryptableVolume) -> WMIResult<ProtectionStatus> {
+        let out = self.exec(volume, "GetProtectionStatus", &[])?;
+
+        match u32::try_from(out.get_property("ProtectionStatus")?)? {
+            0 => Ok(ProtectionStatus::Off),
+            1 => Ok(ProtectionStatus::On),
+            _ => Ok(ProtectionStatus::Unknown),
+        }
+    }
+
+    /// List the key protectors of a volume.
+    pub fn key_protectors(&self, volume: &EncryptableVolume) -> WMIResult<Vec<KeyProtector>> {
+        let out = self.exec(volume, "GetKeyProtectors", &[])?;
+
+        // A volume without protectors returns an empty array.
+        let ids: KeyProtectorIds = out.into_desr()?;
+
+        ids.volume_key_protector_id
+            .into_iter()
+            .map(|id| {
+                let out = self.exec(
+                    volume,
+                    "GetKeyProtectorType",
+                    &[("VolumeKeyProtectorID", id.as_str().into())],
+                )?;
+
+                let protector_type = u32::try_from(out.get_property("KeyProtectorType")?)?;
+
+                Ok(KeyProtector {
+                    id,
+                    protector_type: protector_type.into(),
+                })
+            })
+            .collect()
+    }
+
+    /// Read the recovery password of a [`KeyProtectorType::NumericalPassword`] protector,
+    /// like `123456-123456-123456-123456-123456-123456-123456-123456`.
+    pub fn recovery_password(
+        &self,
+        volume: &EncryptableVolume,
+        protector: &KeyProtector,
+    ) -> WMIResult<String> {
+        let out = self.exec(
+            volume,
+            "GetKeyProtectorNumericalPassword",
+            &[("VolumeKeyProtectorID", protector.id.as_str().into())],
+        )?;
+
+        out.get_property("NumericalPassword")?.try_into()
+    }
+
+    /// Execute a method of a volume, and fail if its return value is an error.
+    fn exec(
+        &self,
+        volume: &EncryptableVolume,
+        method: &str,
+        in_params: &[(&str, Variant)],
+    ) -> WMIResult<IWbemClassWrapper> {
+        let out = self
+            .con
+            .exec_method(&volume.path, method, in_params)?
+            .ok_or(WMIError::NullPointerResult)?;
+
+        // The return values of the BitLocker methods are `HRESULT`s.
+        match u32::try_from(out.get_property("ReturnValue")?)? {
+            0 => Ok(out),
+            hres => Err(WMIError::HResultError { hres: hres as i32 }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    #[test]
+    fn it_maps_key_protector_types() {
+        assert_eq!(KeyProtectorType::from(1), KeyProtectorType::Tpm);
+        assert_eq!(
+            KeyProtectorType::from(3),
+            KeyProtectorType::NumericalPassword
+        );
+        assert_eq!(KeyProtectorType::from(42), KeyProtectorType::Other(42));
+    }
+
+    #[test]
+    fn it_reads_bitlocker_volumes() {
+        let wmi_con = wmi_con();
+
+        // Requires administrative rights.
+        let bitlocker = match wmi_con.bitlocker() {
+            Ok(bitlocker) => bitlocker,
+            Err(WMIError::HResultError { .. }) => return,
+            Err(err) => panic!("{}", err),
+        };
+
+        let volumes = match bitlocker.volumes() {
+            Ok(volumes) => volumes,
+            Err(WMIError::HResultError { .. }) => return,
+            Err(err) => panic!("{}", err),
+        };
+
+        for volume in &volumes {
+            bitlocker.protection_status(volume).unwrap();
+
+            let protectors = bitlocker.key_protectors(volume).unwrap();
+
+            for protector in &protectors {
+                assert!(protector.id.starts_with('{'));
+            }
+
+            // Protected volumes always have at least one protector.
+            if bitlocker.protection_status(volume).unwrap() == ProtectionStatus::On {
+                assert!(!protectors.is_empty());
+            }
+        }
+    }
+}
@@ -0,0 +1,298 @@
+//! Get many objects by path at once, using concurrent `GetObjectAsync` calls.
+//!
+//! Dereferencing the results of an association (or a list of saved paths) using [`WMIConnection::get_by_path`]
+//! waits for a full round trip for each object. Instead, [`WMIConnection::get_many_by_paths`] starts all
+//! the calls at once, and collects their results (in the order of the paths) as they complete.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, Debug)]
+//! struct Win32_Service {
+//!     Name: String,
+//! }
+//!
+//! let services: Vec<WMIResult<Win32_Service>> = con.get_many_by_paths(&[
+//!     r#"Win32_Service.Name="Winmgmt""#,
+//!     r#"Win32_Service.Name="wmi-rs-does-not-exist""#,
+//! ])?;
+//!
+//! assert_eq!(services[0].as_ref().unwrap().Name, "Winmgmt");
+//! assert!(services[1].is_err());
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    connection::WMIConnection, prefetch::InMta, result_enumerator::IWbemClassWrapper, WMIError,
+    WMIResult,
+};
+use log::trace;
+use serde::de;
+use std::sync::{Arc, Condvar, Mutex};
+use windows::core::{implement, Result as WinResult, BSTR, HRESULT};
+use windows::Win32::System::Wmi::{
+    IWbemClassObject, IWbemObjectSink, IWbemObjectSink_Impl, WBEM_FLAG_RETURN_WBEM_COMPLETE,
+    WBEM_STATUS_COMPLETE,
+};
+
+/// The results of all the calls, filled by the sinks.
+///
+/// The sinks are called on RPC threads (in the MTA), so the objects are wrapped using [`InMta`]
+/// and unwrapped by the calling thread.
+struct BulkResults {
+    state: Mutex<BulkState>,
+    done: Condvar,
+}
+
+struct BulkState {
+    objects: Vec<Option<WMIResult<InMta<IWbemClassWrapper>>>>,
+    remaining: usize,
+}
+
+impl BulkResults {
+    fn new(len: usize) -> Self {
+        Self {
+            state: Mutex::new(BulkState {
+                objects: (0..len).map(|_| None).collect(),
+                remaining: len,
+            }),
+            done: Condvar::new(),
+        }
+    }
+
+    fn set_object(&self, index: usize, object: IWbemClassWrapper) {
+        let mut state = self.state.lock().unwrap();
+
+        state.objects[index] = Some(InMta::new(object));
+    }
+
+    fn complete(&self, index: usize, result: WMIResult<()>) {
+        let mut state = self.state.lock().unwrap();
+
+        match result {
+            Err(err) => state.objects[index] = Some(Err(err)),
+            Ok(()) if state.objects[index].is_none() => {
+                state.objects[index] = Some(Err(WMIError::NullPointerResult))
+            }
+            Ok(()) => {}
+        }
+
+        state.remaining -= 1;
+
+        if state.remaining == 0 {
+            self.done.notify_all();
+        }
+    }
+}
+
+/// Receives the object of a single `GetObjectAsync` call.
+#[implement(IWbemObjectSink)]
+struct GetObjectSink {
+    index: usize,
+    results: Arc<BulkResults>,
+}
+
+#[allow(non_snake_case)]
+impl IWbemObjectSink_Impl for GetObjectSink {
+    fn Indicate(
+        &self,
+        lObjectCount: i32,
+        apObjArray: *const Option<IWbemClassObject>,
+    ) -> WinResult<()> {
+        if lObjectCount <= 0 || apObjArray.is_null() {
+            return Ok(());
+        }
+
+        // Safety: `apObjArray` points to `lObjectCount` objects (see `QuerySink::Indicate`).
+        let objs = unsafe { std::slice::from_raw_parts(apObjArray, lObjectCount as usize) };
+
+        if let Some(Some(obj)) = objs.first() {
+            self.results
+                .set_object(self.index, IWbemClassWrapper::new(obj.clone()));
+        }
+
+        Ok(())
+    }
+
+    fn SetStatus(
+        &self,
+        lFlags: i32,
+        hResult: HRESULT,
+        _strParam: &BSTR,
+        _pObjParam: Option<&IWbemClassObject>,
+    ) -> WinResult<()> {
+        if lFlags == WBEM_STATUS_COMPLETE.0 {
+            trace!(
+                "GetObjectAsync #{} completed with {:#X}",
+                self.index,
+                hResult.0
+            );
+
+            self.results
+                .complete(self.index, hResult.ok().map_err(WMIError::from));
+        }
+
+        Ok(())
+    }
+}
+
+///
+/// ### Additional bulk methods
+///
+impl WMIConnection {
+    /// Get the objects at the given paths, see the [module level documentation](crate::bulk).
+    ///
+    /// The results are in the order of the paths, and objects which could not be read
+    /// (for example, because they don't exist) are returned as errors.
+    /// Fails with [`WMIError::Timeout`] if the connection has a timeout, and not all the calls completed in time.
+    pub fn get_many_raw_by_paths(
+        &self,
+        paths: &[impl AsRef<str>],
+    ) -> WMIResult<Vec<WMIResult<IWbemClassWrapper>>> {
+        let results = Arc::new(BulkResults::new(paths.len()));
+        let mut sinks = Vec::with_capacity(paths.len());
+
+        for (index, path) in paths.iter().enumerate() {
+            if let Err(err) = self.check_win32_product_path(path.as_ref()) {
+                results.complete(index, Err(err));
+                continue;
+            }
+
+            let sink: IWbemObjectSink = GetObjectSink {
+                index,
+                results: results.clone(),
+            }
+            .into();
+
+            let path = BSTR::from(path.as_ref());
+
+            let started = unsafe {
+                self.svc.GetObjectAsync(
+                    &path,
+                    WBEM_FLAG_RETURN_WBEM_COMPLETE.0 as _,
+                    self.ctx(),
+                    &sink,
+                )
+            };
+
+            match started {
+                Ok(()) => sinks.push(sink),
+                // The sink won't be called, so the call is completed here.
+                Err(err) => results.complete(index, Err(err.into())),
+            }
+        }
+
+        let state = results.state.lock().unwrap();
+
+        let mut state = match self.timeout {
+            Some(timeout) => {
+                let (state, wait) = results
+                    .done
+                    .wait_timeout_while(state, timeout, |state| state.remaining > 0)
+                    .unwrap();
+
+                if wait.timed_out() {
+                    drop(state);
+
+                    for sink in &sinks {
+                        let _r = unsafe { self.svc.CancelAsyncCall(sink) };
+                    }
+
+                    return Err(WMIError::Timeout);
+                }
+
+                state
+            }
+            None => results
+                .done
+                .wait_while(state, |state| state.remaining > 0)
+                .unwrap(),
+        };
+
+        Ok(state
+            .objects
+            .iter_mut()
+            .map(|object| {
+                object
+                    .take()
+                    .unwrap_or(Err(WMIError::NullPointerResult))
+                    .map(InMta::into_inner)
+            })
+            .collect())
+    }
+
+    /// Get and deserialize the objects at the given paths, see [`WMIConnection::get_many_raw_by_paths`].
+    pub fn get_many_by_paths<T>(&self, paths: &[impl AsRef<str>]) -> WMIResult<Vec<WMIResult<T>>>
+    where
+        T: de::DeserializeOwned,
+    {
+        Ok(self
+            .get_many_raw_by_paths(paths)?
+            .into_iter()
+            .map(|object| object?.into_desr_with_options(&self.de_options, None))
+            .collect())
+    }
+}
+
+#[allow(non_snake_case)]
+#[allow(non_camel_case_types)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use crate::Variant;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Win32_Service {
+        Name: String,
+    }
+
+    #[test]
+    fn it_gets_many_objects_by_path() {
+        let wmi_con = wmi_con();
+
+        let services: Vec<Win32_Service> =
+            wmi_con.raw_query("SELECT Name FROM Win32_Service").unwrap();
+        let paths: Vec<String> = services
+            .iter()
+            .map(|service| format!(r#"Win32_Service.Name="{}""#, service.Name))
+            .collect();
+
+        let results: Vec<WMIResult<Win32_Service>> = wmi_con.get_many_by_paths(&paths).unwrap();
+
+        assert_eq!(results.len(), services.len());
+
+        for (result, service) in results.into_iter().zip(services) {
+            assert_eq!(result.unwrap(), service);
+        }
+    }
+
+    #[test]
+    fn it_returns_errors_in_order() {
+        let wmi_con = wmi_con();
+
+        let results = wmi_con
+            .get_many_raw_by_paths(&[
+                r#"Win32_Service.Name="wmi-rs-does-not-exist""#,
+                "Win32_OperatingSystem=@",
+                "NotAClass_wmi_rs",
+            ])
+            .unwrap();
+
+        assert!(matches!(results[0], Err(WMIError::HResultError { .. })));
+        assert_eq!(
+            results[1].as_ref().unwrap().class().unwrap(),
+            "Win32_OperatingSystem"
+        );
+        assert!(results[2].is_err());
+
+        let empty: Vec<WMIResult<HashMap<String, Variant>>> =
+            wmi_con.get_many_by_paths(&[] as &[&str]).unwrap();
+        assert!(empty.is_empty());
+    }
+}
@@ -0,0 +1,279 @@
+//! An opt-in cache of query results, for data which rarely changes (like the BIOS or the OS information).
+//!
+//! A [`CachedConnection`] keeps the deserialized results of each query for a fixed time to live (TTL),
+//! and returns clones of them until they expire. Queries are keyed by their namespace, their normalized text
+//! (so `SELECT  *  FROM win32_bios` and `SELECT * FROM Win32_BIOS` share an entry) and the type they are deserialized into.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! use serde::Deserialize;
+//! use std::time::Duration;
+//! use wmi::cache::CachedConnection;
+//!
+//! #[derive(Deserialize, Debug, Clone)]
+//! #[serde(rename = "Win32_BIOS")]
+//! #[serde(rename_all = "PascalCase")]
+//! struct Bios {
+//!     manufacturer: String,
+//!     version: String,
+//! }
+//!
+//! let con = WMIConnection::new(COMLibrary::new()?)?;
+//! let cached = CachedConnection::new(con, Duration::from_secs(60));
+//!
+//! // Only the first call runs a query.
+//! for _ in 0..3 {
+//!     let bios: Bios = cached.get()?;
+//!     println!("{:?}", bios);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{connection::WMIConnection, query::build_query, FilterValue, WMIError, WMIResult};
+use serde::de::DeserializeOwned;
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    namespace: String,
+    query: String,
+    type_id: TypeId,
+}
+
+struct CacheEntry {
+    cached_at: Instant,
+    /// A `Vec<T>`, where `T` is the type of the key.
+    results: Box<dyn Any>,
+}
+
+/// A connection which caches the results of queries, see the [module level documentation](crate::cache).
+pub struct CachedConnection {
+    con: WMIConnection,
+    ttl: Duration,
+    entries: RefCell<HashMap<CacheKey, CacheEntry>>,
+}
+
+impl CachedConnection {
+    /// Cache the results of the queries of `con` for `ttl`.
+    pub fn new(con: WMIConnection, ttl: Duration) -> Self {
+        Self {
+            con,
+            ttl,
+            entries: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// The underlying connection, to run queries which bypass the cache.
+    pub fn connection(&self) -> &WMIConnection {
+        &self.con
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Execute a WQL query, or return the cached results of an identical query, see [`WMIConnection::raw_query`].
+    pub fn raw_query<T>(&self, query: impl AsRef<str>) -> WMIResult<Vec<T>>
+    where
+        T: DeserializeOwned + Clone + 'static,
+    {
+        let key = CacheKey {
+            namespace: self.con.options.path.to_ascii_lowercase(),
+            query: normalize_query(query.as_ref()),
+            type_id: TypeId::of::<T>(),
+        };
+
+        if let Some(entry) = self.entries.borrow().get(&key) {
+            if entry.cached_at.elapsed() < self.ttl {
+                if let Some(results) = entry.results.downcast_ref::<Vec<T>>() {
+                    return Ok(results.clone());
+                }
+            }
+        }
+
+        // Errors are not cached, so the next call tries again.
+        let results: Vec<T> = self.con.raw_query(query)?;
+
+        self.entries.borrow_mut().insert(
+            key,
+            CacheEntry {
+                cached_at: Instant::now(),
+                results: Box::new(results.clone()),
+            },
+        );
+
+        Ok(results)
+    }
+
+    /// Query all the objects of type T, see [`WMIConnection::query`].
+    pub fn query<T>(&self) -> WMIResult<Vec<T>>
+    where
+        T: DeserializeOwned + Clone + 'static,
+    {
+        let query_text = build_query::<T>(None)?;
+
+        self.raw_query(query_text)
+    }
+
+    /// Query all the objects of type T which match the filters, see [`WMIConnection::filtered_query`].
+    pub fn filtered_query<T>(&self, filters: &HashMap<String, FilterValue>) -> WMIResult<Vec<T>>
+    where
+        T: DeserializeOwned + Clone + 'static,
+    {
+        let query_text = build_query::<T>(Some(filters))?;
+
+        self.raw_query(query_text)
+    }
+
+    /// Get a single object of type T, see [`WMIConnection::get`].
+    pub fn get<T>(&self) -> WMIResult<T>
+    where
+        T: DeserializeOwned + Clone + 'static,
+    {
+        let results = self.query()?;
+
+        results.into_iter().next().ok_or(WMIError::ResultEmpty)
+    }
+
+    /// Remove the cached results of a query (for all the types it was deserialized into).
+    pub fn invalidate(&self, query: impl AsRef<str>) {
+        let query = normalize_query(query.as_ref());
+
+        self.entries
+            .borrow_mut()
+            .retain(|key, _| key.query != query);
+    }
+
+    /// Remove all the cached results.
+    pub fn clear(&self) {
+        self.entries.borrow_mut().clear();
+    }
+
+    /// Remove the expired results, which are otherwise only replaced when their query is run again.
+    pub fn purge_expired(&self) {
+        let ttl = self.ttl;
+
+        self.entries
+            .borrow_mut()
+            .retain(|_, entry| entry.cached_at.elapsed() < ttl);
+    }
+
+    /// The number of cached results (including expired ones).
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().is_empty()
+    }
+}
+
+/// Lowercase the query and collapse its whitespace, except inside string literals.
+fn normalize_query(query: &str) -> String {
+    let mut normalized = String::with_capacity(query.len());
+    let mut quote = None;
+    let mut escaped = false;
+    let mut pending_space = false;
+
+    for c in query.trim().chars() {
+        match quote {
+            Some(q) => {
+                normalized.push(c);
+
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None if c.is_whitespace() => pending_space = true,
+            None => {
+                if pending_space {
+                    normalized.push(' ');
+                    pending_space = false;
+                }
+
+                if c == '"' || c == '\'' {
+                    quote = Some(c);
+                }
+
+                normalized.push(c.to_ascii_lowercase());
+            }
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, Clone, PartialEq)]
+    #[serde(rename = "Win32_OperatingSystem")]
+    #[serde(rename_all = "PascalCase")]
+    struct OperatingSystem {
+        caption: String,
+    }
+
+    #[test]
+    fn it_normalizes_queries() {
+        assert_eq!(
+            normalize_query("  SELECT  *\n FROM Win32_BIOS "),
+            "select * from win32_bios"
+        );
+        assert_eq!(
+            normalize_query("SELECT * FROM Win32_Service WHERE Name = 'Spooler  X'"),
+            "select * from win32_service where name = 'Spooler  X'"
+        );
+        assert_eq!(
+            normalize_query(r#"SELECT * FROM Win32_Service WHERE Name = "A\"  B""#),
+            r#"select * from win32_service where name = "A\"  B""#
+        );
+    }
+
+    #[test]
+    fn it_caches_query_results() {
+        let cached = CachedConnection::new(wmi_con(), Duration::from_secs(60));
+
+        let first: Vec<OperatingSystem> = cached.query().unwrap();
+        let second: Vec<OperatingSystem> = cached
+            .raw_query("select   caption from WIN32_OPERATINGSYSTEM")
+            .unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(cached.len(), 1);
+
+        // The same query, deserialized into another type, is cached separately.
+        let _: Vec<(String,)> = cached
+            .raw_query("SELECT Caption FROM Win32_OperatingSystem")
+            .unwrap();
+        assert_eq!(cached.len(), 2);
+
+        cached.invalidate("SELECT Caption FROM Win32_OperatingSystem");
+        assert!(cached.is_empty());
+    }
+
+    #[test]
+    fn it_expires_query_results() {
+        let cached = CachedConnection::new(wmi_con(), Duration::ZERO);
+
+        let os: OperatingSystem = cached.get().unwrap();
+        assert_eq!(cached.len(), 1);
+
+        cached.purge_expired();
+        assert!(cached.is_empty());
+
+        assert_eq!(cached.get::<OperatingSystem>().unwrap(), os);
+    }
+}
@@ -0,0 +1,148 @@
+//! Check whether classes and properties exist on the connected system, to branch on capabilities.
+//!
+//! Classes like `MSFT_NetAdapter` (or properties added in newer versions of Windows) are not available everywhere,
+//! and using them fails with an `HRESULT` which depends on the provider. Instead, the capabilities can be checked upfront:
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! if con.supports_property("Win32_OperatingSystem", "MUILanguages")? {
+//!     // ...
+//! }
+//!
+//! assert!(con.supports_class("Win32_Process")?);
+//! assert!(!con.supports_class("Win32_NoSuchClass")?);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The definition of each class is only read once: the results are cached, and the cache is shared by clones
+//! of the connection (including connections to other namespaces or servers, since entries are keyed by both).
+use crate::{connection::WMIConnection, WMIError, WMIResult};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+use windows::Win32::System::Wmi::{WBEM_E_INVALID_CLASS, WBEM_E_NOT_FOUND};
+
+/// The namespace path and class name (both lowercase).
+type CacheKey = (String, String);
+
+/// The (non-system) properties of the classes which were checked, or `None` for classes which don't exist.
+type CachedClasses = HashMap<CacheKey, Option<Arc<[String]>>>;
+
+#[derive(Clone, Default, Debug)]
+pub(crate) struct CapabilityCache {
+    classes: Arc<Mutex<CachedClasses>>,
+}
+
+impl CapabilityCache {
+    fn lock(&self) -> MutexGuard<'_, CachedClasses> {
+        self.classes.lock().unwrap()
+    }
+}
+
+///
+/// ### Additional capability methods
+///
+impl WMIConnection {
+    /// Whether the class exists in the namespace of the connection.
+    ///
+    /// Errors other than a missing class (like access denied) are returned, and are not cached.
+    pub fn supports_class(&self, class: &str) -> WMIResult<bool> {
+        Ok(self.cached_class_properties(class)?.is_some())
+    }
+
+    /// Whether the class exists, and defines (or inherits) the property. Properties are matched case-insensitively.
+    ///
+    /// System properties (like `__PATH`) are supported by every existing class.
+    pub fn supports_property(&self, class: &str, property: &str) -> WMIResult<bool> {
+        let properties = match self.cached_class_properties(class)? {
+            Some(properties) => properties,
+            None => return Ok(false),
+        };
+
+        Ok(property.starts_with("__")
+            || properties
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(property)))
+    }
+
+    /// Forget the cached capabilities (for example, after a provider was registered or a class was changed).
+    pub fn clear_capabilities(&self) {
+        self.capabilities.lock().clear();
+    }
+
+    /// The properties of a class, from the cache if it was already checked.
+    fn cached_class_properties(&self, class: &str) -> WMIResult<Option<Arc<[String]>>> {
+        let key = (
+            self.options.path.to_ascii_lowercase(),
+            class.to_ascii_lowercase(),
+        );
+
+        if let Some(properties) = self.capabilities.lock().get(&key) {
+            return Ok(properties.clone());
+        }
+
+        let properties = match self.get_raw_by_path(class) {
+            Ok(class) => Some(class.list_properties()?.into()),
+            Err(WMIError::HResultError { hres })
+                if hres == WBEM_E_NOT_FOUND.0 || hres == WBEM_E_INVALID_CLASS.0 =>
+            {
+                None
+            }
+            Err(err) => return Err(err),
+        };
+
+        self.capabilities.lock().insert(key, properties.clone());
+
+        Ok(properties)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::fixtures::*;
+
+    #[test]
+    fn it_checks_classes() {
+        let wmi_con = wmi_con();
+
+        assert!(wmi_con.supports_class("Win32_Process").unwrap());
+        assert!(wmi_con.supports_class("win32_process").unwrap());
+        assert!(!wmi_con.supports_class("Win32_NoSuchClass_wmi_rs").unwrap());
+
+        // Both are cached.
+        assert_eq!(wmi_con.capabilities.lock().len(), 2);
+
+        wmi_con.clear_capabilities();
+        assert_eq!(wmi_con.capabilities.lock().len(), 0);
+    }
+
+    #[test]
+    fn it_checks_properties() {
+        let wmi_con = wmi_con();
+
+        assert!(wmi_con
+            .supports_property("Win32_Process", "ProcessId")
+            .unwrap());
+        assert!(wmi_con
+            .supports_property("Win32_Process", "processid")
+            .unwrap());
+        assert!(wmi_con
+            .supports_property("Win32_Process", "__PATH")
+            .unwrap());
+        assert!(!wmi_con
+            .supports_property("Win32_Process", "NoSuchProperty")
+            .unwrap());
+        assert!(!wmi_con
+            .supports_property("Win32_NoSuchClass_wmi_rs", "ProcessId")
+            .unwrap());
+
+        // The namespace is part of the key.
+        let other = wmi_con.with_namespace("ROOT\\StandardCimv2").unwrap();
+        assert!(!other.supports_class("Win32_Process").unwrap());
+        assert!(wmi_con.supports_class("Win32_Process").unwrap());
+    }
+}
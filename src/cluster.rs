@@ -0,0 +1,192 @@
+//! Failover clusters, from the `ROOT\MSCluster` namespace, and queries across all the nodes of a cluster.
+//!
+//! The namespace only accepts connections using the `RPC_C_AUTHN_LEVEL_PKT_PRIVACY` authentication level
+//! (which [`WMIConnection::cluster`] sets).
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # use std::collections::HashMap;
+//! let con = WMIConnection::builder()
+//!     .server("cluster01")
+//!     .build(COMLibrary::new()?)?;
+//!
+//! let cluster = con.cluster()?;
+//!
+//! for group in cluster.resource_groups()? {
+//!     println!("{} is owned by {:?}", group.name, group.owner_node);
+//! }
+//!
+//! // Runs in `ROOT\CIMV2` (the namespace of `con`) on every node, concurrently.
+//! for node in cluster.query_nodes::<HashMap<String, Variant>>("SELECT FreePhysicalMemory FROM Win32_OperatingSystem")? {
+//!     println!("{}: {:?}", node.node, node.result);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{connection::WMIConnection, WMIResult};
+use serde::{de, Deserialize};
+
+/// The namespace of the failover cluster provider.
+pub const CLUSTER_NAMESPACE: &str = "ROOT\\MSCluster";
+
+/// A node of the cluster, from `MSCluster_Node`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "MSCluster_Node")]
+#[serde(rename_all = "PascalCase")]
+pub struct ClusterNode {
+    /// The computer name of the node.
+    pub name: String,
+    /// `-1` unknown, `0` up, `1` down, `2` paused and `3` joining.
+    pub state: i32,
+}
+
+impl ClusterNode {
+    pub fn is_up(&self) -> bool {
+        self.state == 0
+    }
+}
+
+/// A resource group (a clustered role), from `MSCluster_ResourceGroup`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "MSCluster_ResourceGroup")]
+#[serde(rename_all = "PascalCase")]
+pub struct ClusterGroup {
+    pub name: String,
+    /// `-1` unknown, `0` online, `1` offline, `2` failed, `3` partially online and `4` pending.
+    pub state: i32,
+    /// The node which currently hosts the group.
+    pub owner_node: Option<String>,
+}
+
+/// A resource, from `MSCluster_Resource`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "MSCluster_Resource")]
+#[serde(rename_all = "PascalCase")]
+pub struct ClusterResource {
+    pub name: String,
+    /// Like `IP Address` or `Physical Disk`.
+    #[serde(rename = "Type")]
+    pub resource_type: String,
+    /// `-1` unknown, `0` inherited, `1` initializing, `2` online, `3` offline, `4` failed,
+    /// and `128` to `130` for pending states.
+    pub state: i32,
+    pub owner_group: Option<String>,
+    pub owner_node: Option<String>,
+}
+
+/// The result of a query on a single node, as returned by [`Cluster::query_nodes`].
+#[derive(Debug)]
+pub struct NodeResult<T> {
+    pub node: String,
+    pub result: WMIResult<Vec<T>>,
+}
+
+/// A connection to the cluster namespace, created using [`WMIConnection::cluster`].
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    con: WMIConnection,
+    /// Used to connect to the nodes.
+    base: WMIConnection,
+}
+
+///
+/// ### Additional failover cluster methods
+///
+impl WMIConnection {
+    /// Connect to the cluster namespace on the same computer (usually, a node or the name of the cluster).
+    ///
+    /// See the [module level documentation](crate::cluster) for an example.
+    pub fn cluster(&self) -> WMIResult<Cluster> {
+        let mut con = self.with_namespace(CLUSTER_NAMESPACE)?;
+        con.require_packet_privacy()?;
+
+        Ok(Cluster {
+            con,
+            base: self.clone(),
+        })
+    }
+}
+
+impl Cluster {
+    /// The connection to the cluster namespace, to use other `MSCluster_*` classes.
+    pub fn connection(&self) -> &WMIConnection {
+        &self.con
+    }
+
+    pub fn nodes(&self) -> WMIResult<Vec<ClusterNode>> {
+        self.con.query()
+    }
+
+    pub fn resource_groups(&self) -> WMIResult<Vec<ClusterGroup>> {
+        self.con.query()
+    }
+
+    pub fn resources(&self) -> WMIResult<Vec<ClusterResource>> {
+        self.con.query()
+    }
+
+    /// Run a query on every node of the cluster which is up, concurrently (see [`MultiHostQuery`](crate::multi_host::MultiHostQuery)).
+    ///
+    /// The query runs in the namespace of the connection used to create this [`Cluster`],
+    /// with the same credentials and settings. A failure to connect to (or query) a node
+    /// is reported in its [`NodeResult`], and does not affect the other nodes.
+    /// The results are ordered like [`nodes`](Self::nodes).
+    ///
+    /// The calling thread must be in the multithreaded apartment (as initialized by [`COMLibrary::new`](crate::COMLibrary::new)).
+    pub fn query_nodes<T>(&self, query: impl AsRef<str>) -> WMIResult<Vec<NodeResult<T>>>
+    where
+        T: de::DeserializeOwned,
+    {
+        let nodes: Vec<String> = self
+            .nodes()?
+            .into_iter()
+            .filter(ClusterNode::is_up)
+            .map(|node| node.name)
+            .collect();
+
+        let results = self
+            .base
+            .multi_host(nodes.clone())
+            .concurrency(nodes.len())
+            .raw_query(query)?;
+
+        Ok(results
+            .into_iter()
+            .map(|host| NodeResult {
+                node: host.host,
+                result: host.result,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::fixtures::*;
+    use crate::WMIError;
+
+    #[test]
+    fn it_reads_cluster_nodes() {
+        let wmi_con = wmi_con();
+
+        // Most machines are not part of a cluster.
+        let cluster = match wmi_con.cluster() {
+            Ok(cluster) => cluster,
+            Err(WMIError::HResultError { .. }) => return,
+            Err(err) => panic!("{}", err),
+        };
+
+        let nodes = cluster.nodes().unwrap();
+        let results = cluster
+            .query_nodes::<std::collections::HashMap<String, crate::Variant>>(
+                "SELECT Caption FROM Win32_OperatingSystem",
+            )
+            .unwrap();
+
+        assert_eq!(
+            results.len(),
+            nodes.iter().filter(|node| node.is_up()).count()
+        );
+    }
+}
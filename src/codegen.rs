@@ -0,0 +1,363 @@
+//! Generate typed Rust wrappers for the methods of a class, from the schema returned by
+//! [`WMIConnection::list_methods`].
+//!
+//! For each method, a function is generated which takes the input parameters as typed arguments
+//! (`Option`s for optional parameters), and returns a struct with the output parameters:
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! let code = con.generate_method_wrappers("Win32_Service")?;
+//!
+//! assert!(code.contains("pub mod win32_service {"));
+//! assert!(code.contains("pub fn change_start_mode("));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Which generates (among others):
+//!
+//! ```rust,ignore
+//! /// `Win32_Service.ChangeStartMode`.
+//! pub fn change_start_mode(
+//!     con: &WMIConnection,
+//!     object_path: &str,
+//!     start_mode: &str,
+//! ) -> WMIResult<ChangeStartModeOutput> {
+//!     let mut in_params: Vec<(&str, Variant)> = vec![];
+//!     in_params.push(("StartMode", to_variant(&start_mode)?));
+//!
+//!     con.exec_method_as(object_path, "ChangeStartMode", &in_params)
+//! }
+//! ```
+//!
+//! Static methods don't take an `object_path`, and are executed on the class.
+//! Embedded objects are returned as maps, and can't be passed as input parameters.
+use crate::{
+    connection::WMIConnection,
+    schema::{MethodSummary, ParameterSummary},
+    WMIResult,
+};
+use std::fmt::Write;
+
+///
+/// ### Additional code generation methods
+///
+impl WMIConnection {
+    /// Generate a module with typed wrappers for the methods of a class, see the [module level documentation](crate::codegen).
+    pub fn generate_method_wrappers(&self, class: &str) -> WMIResult<String> {
+        let methods = self.list_methods(class)?;
+
+        Ok(method_wrappers(class, &methods))
+    }
+}
+
+/// Generate a module with typed wrappers for the given methods of a class.
+pub fn method_wrappers(class: &str, methods: &[MethodSummary]) -> String {
+    let mut code = String::new();
+
+    // Writing to a `String` can't fail.
+    let _ = writeln!(code, "/// Typed wrappers for the methods of `{}`.", class);
+    let _ = writeln!(code, "pub mod {} {{", snake_case(class));
+    let _ = writeln!(code, "    #![allow(dead_code, clippy::too_many_arguments)]");
+    let _ = writeln!(code, "    use serde::Deserialize;");
+    let _ = writeln!(
+        code,
+        "    use wmi::{{ser::to_variant, Variant, WMIConnection, WMIResult}};"
+    );
+
+    for method in methods {
+        code.push('\n');
+        write_output_struct(&mut code, method);
+        code.push('\n');
+        write_method(&mut code, class, method);
+    }
+
+    code.push_str("}\n");
+
+    code
+}
+
+fn write_output_struct(code: &mut String, method: &MethodSummary) {
+    let _ = writeln!(code, "    #[derive(Debug, Deserialize)]");
+    let _ = writeln!(code, "    pub struct {} {{", output_struct_name(method));
+
+    for param in &method.out_params {
+        let field_type = if param.name == "ReturnValue" {
+            output_type(&param.cim_type)
+        } else {
+            // Output parameters are not always set.
+            format!("Option<{}>", output_type(&param.cim_type))
+        };
+
+        let _ = writeln!(code, "        #[serde(rename = \"{}\")]", param.name);
+        let _ = writeln!(
+            code,
+            "        pub {}: {},",
+            field_name(&param.name),
+            field_type
+        );
+    }
+
+    let _ = writeln!(code, "    }}");
+}
+
+fn write_method(code: &mut String, class: &str, method: &MethodSummary) {
+    let _ = writeln!(code, "    /// `{}.{}`.", class, method.name);
+    let _ = writeln!(code, "    pub fn {}(", field_name(&method.name));
+    let _ = writeln!(code, "        con: &WMIConnection,");
+
+    if !method.is_static {
+        let _ = writeln!(code, "        object_path: &str,");
+    }
+
+    for param in &method.in_params {
+        let _ = writeln!(
+            code,
+            "        {}: {},",
+            field_name(&param.name),
+            input_type(param)
+        );
+    }
+
+    let _ = writeln!(
+        code,
+        "    ) -> WMIResult<{}> {{",
+        output_struct_name(method)
+    );
+
+    if method.in_params.is_empty() {
+        let _ = writeln!(
+            code,
+            "        let in_params: Vec<(&str, Variant)> = vec![];"
+        );
+    } else {
+        let _ = writeln!(
+            code,
+            "        let mut in_params: Vec<(&str, Variant)> = vec![];"
+        );
+    }
+
+    for param in &method.in_params {
+        let name = field_name(&param.name);
+
+        if param.optional {
+            let _ = writeln!(code, "        if let Some({}) = {} {{", name, name);
+            let _ = writeln!(
+                code,
+                "            in_params.push((\"{}\", to_variant(&{})?));",
+                param.name, name
+            );
+            let _ = writeln!(code, "        }}");
+        } else {
+            let _ = writeln!(
+                code,
+                "        in_params.push((\"{}\", to_variant(&{})?));",
+                param.name, name
+            );
+        }
+    }
+
+    let target = if method.is_static {
+        format!("\"{}\"", class)
+    } else {
+        "object_path".to_owned()
+    };
+
+    code.push('\n');
+    let _ = writeln!(
+        code,
+        "        con.exec_method_as({}, \"{}\", &in_params)",
+        target, method.name
+    );
+    let _ = writeln!(code, "    }}");
+}
+
+fn output_struct_name(method: &MethodSummary) -> String {
+    format!("{}Output", method.name)
+}
+
+/// The Rust type of an input parameter (borrowed, when possible).
+fn input_type(param: &ParameterSummary) -> String {
+    let (base, is_array) = base_type(&param.cim_type);
+
+    let ty = match (rust_type(base), is_array) {
+        ("String", false) => "&str".to_owned(),
+        ("String", true) => "&[&str]".to_owned(),
+        (ty, false) => ty.to_owned(),
+        (ty, true) => format!("&[{}]", ty),
+    };
+
+    if param.optional {
+        format!("Option<{}>", ty)
+    } else {
+        ty
+    }
+}
+
+fn output_type(cim_type: &str) -> String {
+    let (base, is_array) = base_type(cim_type);
+
+    if is_array {
+        format!("Vec<{}>", rust_type(base))
+    } else {
+        rust_type(base).to_owned()
+    }
+}
+
+fn base_type(cim_type: &str) -> (&str, bool) {
+    match cim_type.strip_suffix("[]") {
+        Some(base) => (base, true),
+        None => (cim_type, false),
+    }
+}
+
+fn rust_type(cim_type: &str) -> &'static str {
+    match cim_type {
+        "sint8" => "i8",
+        "uint8" => "u8",
+        "sint16" => "i16",
+        "uint16" => "u16",
+        "sint32" => "i32",
+        "uint32" => "u32",
+        "sint64" => "i64",
+        "uint64" => "u64",
+        "real32" => "f32",
+        "real64" => "f64",
+        "boolean" => "bool",
+        // Embedded objects (which can only be used as output parameters).
+        "object" => "std::collections::HashMap<String, Variant>",
+        // Strings, datetimes, references and characters.
+        _ => "String",
+    }
+}
+
+/// A `snake_case` Rust identifier for a WMI name, like `volume_key_protector_id` for `VolumeKeyProtectorID`.
+fn field_name(name: &str) -> String {
+    let name = snake_case(name);
+
+    match name.as_str() {
+        "as" | "break" | "const" | "continue" | "crate" | "else" | "enum" | "extern" | "false"
+        | "fn" | "for" | "if" | "impl" | "in" | "let" | "loop" | "match" | "mod" | "move"
+        | "mut" | "pub" | "ref" | "return" | "static" | "struct" | "trait" | "true" | "type"
+        | "unsafe" | "use" | "where" | "while" | "async" | "await" | "dyn" => {
+            format!("r#{}", name)
+        }
+        _ => name,
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::with_capacity(name.len() + 4);
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = matches!(chars.get(i + 1), Some(c) if c.is_ascii_lowercase());
+
+            // A new word starts after a lowercase letter or a digit, or at the last capital of an acronym (`IDName`).
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next_is_lower)
+            {
+                snake.push('_');
+            }
+        }
+
+        snake.push(c.to_ascii_lowercase());
+    }
+
+    snake
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    fn param(name: &str, cim_type: &str, optional: bool) -> ParameterSummary {
+        ParameterSummary {
+            name: name.to_owned(),
+            cim_type: cim_type.to_owned(),
+            optional,
+        }
+    }
+
+    #[test]
+    fn it_converts_names_to_snake_case() {
+        assert_eq!(field_name("ChangeStartMode"), "change_start_mode");
+        assert_eq!(field_name("sSubKeyName"), "s_sub_key_name");
+        assert_eq!(
+            field_name("VolumeKeyProtectorID"),
+            "volume_key_protector_id"
+        );
+        assert_eq!(field_name("IPAddress"), "ip_address");
+        assert_eq!(field_name("Win32_Service"), "win32_service");
+        assert_eq!(field_name("Type"), "r#type");
+    }
+
+    #[test]
+    fn it_generates_method_wrappers() {
+        let methods = vec![
+            MethodSummary {
+                name: "Create".to_owned(),
+                is_static: true,
+                in_params: vec![
+                    param("CommandLine", "string", false),
+                    param("ProcessStartupInformation", "object", true),
+                ],
+                out_params: vec![
+                    param("ReturnValue", "uint32", false),
+                    param("ProcessId", "uint32", false),
+                ],
+            },
+            MethodSummary {
+                name: "GetOwner".to_owned(),
+                is_static: false,
+                in_params: vec![],
+                out_params: vec![
+                    param("ReturnValue", "uint32", false),
+                    param("Domain", "string", false),
+                ],
+            },
+        ];
+
+        let code = method_wrappers("Win32_Process", &methods);
+
+        assert!(code.starts_with(
+            "/// Typed wrappers for the methods of `Win32_Process`.\npub mod win32_process {\n"
+        ));
+        assert!(code.contains(
+            "    pub struct CreateOutput {\n        #[serde(rename = \"ReturnValue\")]\n        pub return_value: u32,\n        #[serde(rename = \"ProcessId\")]\n        pub process_id: Option<u32>,\n    }\n"
+        ));
+        assert!(code.contains(
+            "    pub fn create(\n        con: &WMIConnection,\n        command_line: &str,\n        process_startup_information: Option<std::collections::HashMap<String, Variant>>,\n    ) -> WMIResult<CreateOutput> {\n"
+        ));
+        assert!(code.contains(
+            "        if let Some(process_startup_information) = process_startup_information {\n"
+        ));
+        assert!(code
+            .contains("        con.exec_method_as(\"Win32_Process\", \"Create\", &in_params)\n"));
+        assert!(code.contains(
+            "    pub fn get_owner(\n        con: &WMIConnection,\n        object_path: &str,\n    ) -> WMIResult<GetOwnerOutput> {\n        let in_params: Vec<(&str, Variant)> = vec![];\n"
+        ));
+        assert!(
+            code.contains("        con.exec_method_as(object_path, \"GetOwner\", &in_params)\n")
+        );
+        assert!(code.ends_with("    }\n}\n"));
+    }
+
+    #[test]
+    fn it_generates_wrappers_for_classes() {
+        let wmi_con = wmi_con();
+
+        let code = wmi_con.generate_method_wrappers("Win32_Service").unwrap();
+
+        assert!(code.contains(
+            "pub fn start_service(\n        con: &WMIConnection,\n        object_path: &str,\n"
+        ));
+        assert!(code.contains("        start_mode: &str,\n"));
+    }
+}
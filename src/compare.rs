@@ -0,0 +1,141 @@
+//! Copying and comparing objects, using `IWbemClassObject::Clone` and `IWbemClassObject::CompareTo`.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use wmi::compare::CompareFlags;
+//!
+//! let os = con.get_raw_by_path("Win32_OperatingSystem=@")?;
+//!
+//! // A copy is independent of the original object.
+//! let copy = os.deep_clone()?;
+//! copy.put_property("Description", "Changed")?;
+//!
+//! assert!(!copy.compare_to(&os, CompareFlags::INCLUDE_ALL)?);
+//! assert_ne!(copy, os);
+//! # Ok(())
+//! # }
+//! ```
+use crate::{result_enumerator::IWbemClassWrapper, WMIResult};
+use std::ops::BitOr;
+use windows::core::Interface;
+use windows::Win32::System::Wmi::{
+    WBEM_FLAG_IGNORE_CASE, WBEM_FLAG_IGNORE_CLASS, WBEM_FLAG_IGNORE_DEFAULT_VALUES,
+    WBEM_FLAG_IGNORE_FLAVOR, WBEM_FLAG_IGNORE_OBJECT_SOURCE, WBEM_FLAG_IGNORE_QUALIFIERS,
+    WBEM_S_SAME,
+};
+
+/// What to ignore when comparing objects (the `WBEM_COMPARISON_FLAG` flags).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct CompareFlags(pub i32);
+
+impl CompareFlags {
+    /// Compare everything.
+    pub const INCLUDE_ALL: Self = Self(0);
+    /// Ignore the namespace and server the objects come from.
+    pub const IGNORE_OBJECT_SOURCE: Self = Self(WBEM_FLAG_IGNORE_OBJECT_SOURCE.0);
+    /// Ignore the qualifiers of the objects (and of their properties and methods).
+    pub const IGNORE_QUALIFIERS: Self = Self(WBEM_FLAG_IGNORE_QUALIFIERS.0);
+    /// Ignore properties which have their default value.
+    pub const IGNORE_DEFAULT_VALUES: Self = Self(WBEM_FLAG_IGNORE_DEFAULT_VALUES.0);
+    /// Only compare the values of the properties of instances (and not their class definitions).
+    pub const IGNORE_CLASS: Self = Self(WBEM_FLAG_IGNORE_CLASS.0);
+    /// Compare string values case-insensitively.
+    pub const IGNORE_CASE: Self = Self(WBEM_FLAG_IGNORE_CASE.0);
+    /// Ignore the flavors of qualifiers (like whether they are propagated to instances).
+    pub const IGNORE_FLAVOR: Self = Self(WBEM_FLAG_IGNORE_FLAVOR.0);
+
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for CompareFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl IWbemClassWrapper {
+    /// Create a new copy of the object, unlike [`Clone::clone`] which shares the same object.
+    ///
+    /// Changing the properties of the copy (for example, to write it back using
+    /// [`WMIConnection::put_instance`](crate::WMIConnection::put_instance)) does not change the original object.
+    pub fn deep_clone(&self) -> WMIResult<Self> {
+        let copy = unsafe { self.inner.Clone()? };
+
+        Ok(Self::new(copy))
+    }
+
+    /// Compare the object with another one, according to WMI.
+    pub fn compare_to(&self, other: &Self, flags: CompareFlags) -> WMIResult<bool> {
+        // Both `WBEM_S_SAME` and `WBEM_S_DIFFERENT` are success codes, which the generated
+        // `CompareTo` wrapper discards, so the method is called through the vtable.
+        let hres = unsafe {
+            (self.inner.vtable().CompareTo)(self.inner.as_raw(), flags.0, other.inner.as_raw())
+        };
+
+        hres.ok()?;
+
+        Ok(hres.0 == WBEM_S_SAME.0)
+    }
+}
+
+/// Objects are equal if they are the same object, or if WMI compares them as equal
+/// (while ignoring where they come from). Objects which fail to compare are not equal.
+impl PartialEq for IWbemClassWrapper {
+    fn eq(&self, other: &Self) -> bool {
+        if self.inner == other.inner {
+            return true;
+        }
+
+        self.compare_to(other, CompareFlags::IGNORE_OBJECT_SOURCE)
+            .unwrap_or(false)
+    }
+}
+
+impl Eq for IWbemClassWrapper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use crate::Variant;
+
+    #[test]
+    fn it_combines_compare_flags() {
+        let flags = CompareFlags::IGNORE_CASE | CompareFlags::IGNORE_QUALIFIERS;
+
+        assert!(flags.contains(CompareFlags::IGNORE_CASE));
+        assert!(flags.contains(CompareFlags::INCLUDE_ALL));
+        assert!(!flags.contains(CompareFlags::IGNORE_CLASS));
+    }
+
+    #[test]
+    fn it_clones_and_compares_objects() {
+        let wmi_con = wmi_con();
+
+        let os = wmi_con.get_raw_by_path("Win32_OperatingSystem=@").unwrap();
+        let same_os = wmi_con.get_raw_by_path("Win32_OperatingSystem=@").unwrap();
+
+        // Different objects, with the same values.
+        assert_eq!(os, same_os);
+
+        let copy = os.deep_clone().unwrap();
+        assert!(copy.compare_to(&os, CompareFlags::INCLUDE_ALL).unwrap());
+
+        copy.put_property("Description", "wmi-rs").unwrap();
+
+        assert_ne!(copy, os);
+        assert_ne!(
+            os.get_property("Description").unwrap(),
+            Variant::String("wmi-rs".to_owned())
+        );
+
+        let class = wmi_con.get_raw_by_path("Win32_OperatingSystem").unwrap();
+        assert_ne!(class, os);
+    }
+}
@@ -1,19 +1,29 @@
+use crate::capability::CapabilityCache;
+use crate::context::WbemContext;
+use crate::credentials::{Authority, Credentials};
+use crate::de::options::DeserializeOptions;
+use crate::transport::WbemTransport;
 use crate::utils::WMIResult;
 use crate::WMIError;
 use log::debug;
 use std::marker::PhantomData;
-use windows::core::BSTR;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::time::Duration;
+use windows::core::{ComInterface, IUnknown, BSTR, PCWSTR};
 use windows::Win32::Foundation::RPC_E_TOO_LATE;
 use windows::Win32::System::Com::{
     CoCreateInstance, CoSetProxyBlanket, CLSCTX_INPROC_SERVER, RPC_C_AUTHN_LEVEL_CALL,
 };
 use windows::Win32::System::Com::{
     CoInitializeEx, CoInitializeSecurity, COINIT_MULTITHREADED, EOAC_NONE,
-    RPC_C_AUTHN_LEVEL_DEFAULT, RPC_C_IMP_LEVEL_IMPERSONATE,
+    EOLE_AUTHENTICATION_CAPABILITIES, RPC_C_AUTHN_LEVEL, RPC_C_AUTHN_LEVEL_DEFAULT,
+    RPC_C_AUTHN_LEVEL_PKT_PRIVACY, RPC_C_IMP_LEVEL, RPC_C_IMP_LEVEL_IMPERSONATE,
 };
 use windows::Win32::System::Rpc::{RPC_C_AUTHN_WINNT, RPC_C_AUTHZ_NONE};
 use windows::Win32::System::Wmi::{
-    IWbemLocator, IWbemServices, WbemLocator, WBEM_FLAG_CONNECT_USE_MAX_WAIT,
+    IWbemLocator, IWbemRefresher, IWbemServices, WbemLocator, WbemRefresher,
+    WBEM_FLAG_CONNECT_USE_MAX_WAIT,
 };
 
 /// A marker to indicate that the current thread was `CoInitialize`d.
@@ -122,20 +132,80 @@ impl COMLibrary {
 /// ```
 fn _test_com_lib_not_send(_s: impl Send) {}
 
+/// A connection to WMI, which runs queries using a [`WbemTransport`] (COM/DCOM by default).
+///
+/// Queries are built and deserialized the same way for every transport, see the [`transport`](crate::transport) module.
+/// The connection dereferences to its transport, so the methods of [`ComTransport`] (like
+/// [`exec_query_native_wrapper`](ComTransport::exec_query_native_wrapper)) can be called on COM connections.
+#[derive(Clone, Debug)]
+pub struct WMIConnection<T = ComTransport> {
+    pub(crate) transport: T,
+}
+
+impl<T> WMIConnection<T>
+where
+    T: WbemTransport,
+{
+    /// Create a connection which uses the given transport (like a [`MockTransport`](crate::MockTransport)).
+    pub fn from_transport(transport: T) -> Self {
+        Self { transport }
+    }
+
+    pub fn into_transport(self) -> T {
+        self.transport
+    }
+}
+
+impl<T> Deref for WMIConnection<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.transport
+    }
+}
+
+impl<T> DerefMut for WMIConnection<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+}
+
+/// The default transport of a [`WMIConnection`], which uses the COM API (and DCOM for remote computers).
 #[derive(Clone, Debug)]
-pub struct WMIConnection {
+pub struct ComTransport {
     _com_con: COMLibrary,
     pub svc: IWbemServices,
+    pub(crate) ctx: Option<WbemContext>,
+    pub(crate) blanket: Option<ProxyBlanket>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) ensure_locatable: bool,
+    pub(crate) prefetch: Option<u32>,
+    pub(crate) allow_win32_product: bool,
+    pub(crate) options: ConnectOptions,
+    pub(crate) de_options: DeserializeOptions,
+    pub(crate) capabilities: CapabilityCache,
 }
 
-/// A connection to the local WMI provider, which provides querying capabilities.
+/// The arguments of `ConnectServer`, kept to allow reconnecting.
+#[derive(Clone, Debug)]
+pub(crate) struct ConnectOptions {
+    /// The full object path, including the server (if any).
+    pub(crate) path: String,
+    pub(crate) credentials: Option<Arc<Credentials>>,
+    pub(crate) authority: Option<Authority>,
+    pub(crate) locale: Option<String>,
+    pub(crate) flags: i32,
+}
+
+/// A connection to a WMI provider, which provides querying capabilities.
 ///
-/// Currently does not support remote providers (e.g connecting to other computers).
+/// Connections are local by default, use [`WMIConnection::builder`] to connect to other computers
+/// or to customize the connection.
 ///
 impl WMIConnection {
     /// Creates a connection with a default `CIMV2` namespace path.
     pub fn new(com_lib: COMLibrary) -> WMIResult<Self> {
-        Self::with_namespace_path("ROOT\\CIMV2", com_lib)
+        Self::builder().build(com_lib)
     }
 
     /// Creates a connection with the given namespace path.
@@ -148,40 +218,551 @@ impl WMIConnection {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn with_namespace_path(namespace_path: &str, com_lib: COMLibrary) -> WMIResult<Self> {
+    pub fn with_namespace_path(
+        namespace_path: impl AsRef<str>,
+        com_lib: COMLibrary,
+    ) -> WMIResult<Self> {
+        Self::builder().namespace(namespace_path).build(com_lib)
+    }
+
+    /// Creates a connection to the given namespace path on a remote computer, using the given credentials.
+    ///
+    /// The password is only copied into native buffers for the duration of each call that requires it,
+    /// and these buffers are zeroed afterwards.
+    ///
+    /// ```edition2018,no_run
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// let credentials = Credentials::new("CONTOSO\\Administrator", String::from("hunter2"));
+    /// let wmi_con = WMIConnection::with_credentials("server01", "ROOT\\CIMV2", credentials, COMLibrary::new()?)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_credentials(
+        server: &str,
+        namespace_path: impl AsRef<str>,
+        credentials: Credentials,
+        com_lib: COMLibrary,
+    ) -> WMIResult<Self> {
+        Self::builder()
+            .server(server)
+            .namespace(namespace_path)
+            .credentials(credentials)
+            .build(com_lib)
+    }
+
+    /// Creates a connection to the given namespace path on a remote computer,
+    /// forcing the given authentication [`Authority`].
+    ///
+    /// If `credentials` is `None`, the current user is used.
+    /// When using [`Authority::NtlmDomain`], the user should be given without a domain.
+    ///
+    /// ```edition2018,no_run
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// let authority = Authority::Kerberos("CONTOSO\\server01$".to_string());
+    /// let wmi_con = WMIConnection::with_authority("server01", "ROOT\\CIMV2", None, authority, COMLibrary::new()?)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_authority(
+        server: &str,
+        namespace_path: impl AsRef<str>,
+        credentials: Option<Credentials>,
+        authority: Authority,
+        com_lib: COMLibrary,
+    ) -> WMIResult<Self> {
+        let mut builder = Self::builder()
+            .server(server)
+            .namespace(namespace_path)
+            .authority(authority);
+
+        if let Some(credentials) = credentials {
+            builder = builder.credentials(credentials);
+        }
+
+        builder.build(com_lib)
+    }
+
+    /// Create a connection to another namespace, on the same computer and with the same settings
+    /// (credentials, context, options) as this connection.
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// # let con = WMIConnection::new(COMLibrary::new()?)?;
+    /// let root_con = con.with_namespace("ROOT")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_namespace(&self, namespace_path: impl AsRef<str>) -> WMIResult<Self> {
+        let mut options = self.options.clone();
+        options.path = replace_namespace(&options.path, namespace_path.as_ref());
+
+        self.connect_with(options)
+    }
+
+    /// Create a connection to the same namespace on another computer, with the same settings
+    /// (credentials, context, options) as this connection.
+    ///
+    /// ```edition2018,no_run
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// # let con = WMIConnection::new(COMLibrary::new()?)?;
+    /// let other_con = con.with_server("server02")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_server(&self, server: &str) -> WMIResult<Self> {
+        let mut options = self.options.clone();
+        options.path = replace_server(&options.path, server);
+
+        self.connect_with(options)
+    }
+
+    fn connect_with(&self, options: ConnectOptions) -> WMIResult<Self> {
         let loc = create_locator()?;
-        let svc = create_services(&loc, namespace_path)?;
 
-        let this = Self {
-            _com_con: com_lib,
-            svc,
-        };
+        let mut con = self.clone();
+        con.svc = create_services(&loc, &options)?;
+        con.options = options;
+        con.set_proxy()?;
 
-        this.set_proxy()?;
-        Ok(this)
+        Ok(con)
+    }
+
+    /// Create a copy of this connection which uses the given options when deserializing results.
+    ///
+    /// See [`DeserializeOptions`] for an example.
+    pub fn with_deserialize_options(&self, options: DeserializeOptions) -> Self {
+        let mut con = self.clone();
+        con.de_options = options;
+        con
     }
 
-    fn set_proxy(&self) -> WMIResult<()> {
-        debug!("Calling CoSetProxyBlanket");
+    pub fn deserialize_options(&self) -> &DeserializeOptions {
+        &self.de_options
+    }
 
-        unsafe {
+    /// Create a copy of this connection which passes `WBEM_FLAG_ENSURE_LOCATABLE` to queries.
+    ///
+    /// Providers will then always return the `__PATH` of objects (even when it is not selected),
+    /// so that individual results of an expensive query can later be refreshed using [`get_raw_by_path`](Self::get_raw_by_path)
+    /// instead of re-running the whole query.
+    pub fn with_ensure_locatable(&self, ensure_locatable: bool) -> Self {
+        let mut con = self.clone();
+        con.ensure_locatable = ensure_locatable;
+        con
+    }
+
+    /// Create a copy of this connection which reads the results of queries in batches of `batch_size` objects
+    /// (or one by one, if `None`).
+    ///
+    /// The next batch is read on a background thread while the current one is deserialized,
+    /// which improves the throughput of slow providers.
+    /// A batch is only returned when it is full (or when the results are exhausted),
+    /// so this is not useful for queries whose results should be handled as soon as they arrive.
+    ///
+    /// The background thread uses the multithreaded apartment, so COM must be initialized
+    /// as multithreaded on the calling thread (as [`COMLibrary::new`] does).
+    /// Otherwise, queries fail with `RPC_E_WRONG_THREAD`.
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// # use std::collections::HashMap;
+    /// # let con = WMIConnection::new(COMLibrary::new()?)?;
+    /// let results: Vec<HashMap<String, Variant>> = con
+    ///     .with_prefetch(Some(64))
+    ///     .raw_query("SELECT Name FROM Win32_Process")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_prefetch(&self, batch_size: Option<u32>) -> Self {
+        let mut con = self.clone();
+        con.prefetch = batch_size.map(|batch_size| batch_size.max(1));
+        con
+    }
+
+    /// Create a builder for a customized connection.
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// use std::time::Duration;
+    ///
+    /// let wmi_con = WMIConnection::builder()
+    ///     .namespace("ROOT\\CIMV2")
+    ///     .locale("MS_409")
+    ///     .timeout(Duration::from_secs(30))
+    ///     .build(COMLibrary::new()?)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder() -> WMIConnectionBuilder {
+        WMIConnectionBuilder::default()
+    }
+}
+
+impl ComTransport {
+    /// Whether this connection uses explicit credentials or authority, which must be applied to every proxy.
+    pub(crate) fn is_authenticated_remote(&self) -> bool {
+        self.options.credentials.is_some() || self.options.authority.is_some()
+    }
+
+    /// Whether this connection is to the local computer.
+    pub(crate) fn is_local(&self) -> bool {
+        match self.options.path.strip_prefix("\\\\") {
+            Some(rest) => {
+                let server = rest.split('\\').next().unwrap_or_default();
+                server == "." || server.eq_ignore_ascii_case("localhost")
+            }
+            None => true,
+        }
+    }
+
+    /// Whether newly obtained interface pointers need to have the proxy blanket applied to them.
+    pub(crate) fn needs_proxy_blanket(&self) -> bool {
+        self.is_authenticated_remote() || self.blanket.is_some()
+    }
+
+    /// The security settings applied to the proxies of this connection.
+    ///
+    /// Unless overridden using [`ComTransport::set_proxy_blanket`], these are derived from the
+    /// credentials and authority used to create the connection.
+    pub fn proxy_blanket(&self) -> ProxyBlanket {
+        if let Some(blanket) = &self.blanket {
+            return blanket.clone();
+        }
+
+        if !self.is_authenticated_remote() {
+            return ProxyBlanket::default();
+        }
+
+        let authority = self.options.authority.as_ref();
+
+        ProxyBlanket {
+            authn_service: authority.map_or(RPC_C_AUTHN_WINNT, Authority::authn_service),
+            principal: authority.and_then(Authority::principal).map(String::from),
+            authn_level: RPC_C_AUTHN_LEVEL_PKT_PRIVACY,
+            ..ProxyBlanket::default()
+        }
+    }
+
+    /// Override the security settings of this connection, and apply them to the service proxy.
+    ///
+    /// The new settings are also applied to every interface pointer obtained using this connection afterwards
+    /// (like query enumerators).
+    /// Note that clones of this connection share the same service proxy.
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// use windows::Win32::System::Com::RPC_C_AUTHN_LEVEL_PKT_INTEGRITY;
+    ///
+    /// let mut wmi_con = WMIConnection::new(COMLibrary::new()?)?;
+    /// let blanket = ProxyBlanket {
+    ///     authn_level: RPC_C_AUTHN_LEVEL_PKT_INTEGRITY,
+    ///     ..wmi_con.proxy_blanket()
+    /// };
+    /// wmi_con.set_proxy_blanket(blanket)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_proxy_blanket(&mut self, blanket: ProxyBlanket) -> WMIResult<()> {
+        self.blanket = Some(blanket);
+        self.set_proxy()
+    }
+
+    /// Raise the authentication level to `RPC_C_AUTHN_LEVEL_PKT_PRIVACY` (if it is lower),
+    /// which some providers (like BitLocker and IIS) require.
+    pub(crate) fn require_packet_privacy(&mut self) -> WMIResult<()> {
+        let blanket = self.proxy_blanket();
+
+        if blanket.authn_level.0 >= RPC_C_AUTHN_LEVEL_PKT_PRIVACY.0 {
+            return Ok(());
+        }
+
+        self.set_proxy_blanket(ProxyBlanket {
+            authn_level: RPC_C_AUTHN_LEVEL_PKT_PRIVACY,
+            ..blanket
+        })
+    }
+
+    pub(crate) fn set_proxy(&self) -> WMIResult<()> {
+        self.apply_proxy_blanket(&self.svc)
+    }
+
+    /// Apply the security settings of this connection to an interface pointer (like an enumerator or an event sink)
+    /// obtained outside of this crate.
+    ///
+    /// When connected with explicit credentials, every proxy must be given the client identity,
+    /// otherwise calls on it will be made as the current user.
+    pub fn apply_proxy_blanket(&self, iface: &impl ComInterface) -> WMIResult<()> {
+        self.apply_proxy_blanket_with(iface, &self.proxy_blanket())
+    }
+
+    /// Apply the given security settings to an interface pointer, using the client identity of this connection.
+    pub fn apply_proxy_blanket_with(
+        &self,
+        iface: &impl ComInterface,
+        blanket: &ProxyBlanket,
+    ) -> WMIResult<()> {
+        debug!("Calling CoSetProxyBlanket with {:?}", blanket);
+
+        let iface: IUnknown = iface.cast()?;
+
+        let principal: Option<Vec<u16>> = blanket
+            .principal
+            .as_ref()
+            .map(|principal| principal.encode_utf16().chain(Some(0)).collect());
+        let principal = principal.as_ref().map_or(PCWSTR::null(), |principal| {
+            PCWSTR::from_raw(principal.as_ptr())
+        });
+
+        let set_blanket = |identity: Option<*const std::ffi::c_void>| unsafe {
             CoSetProxyBlanket(
-                &self.svc,
-                RPC_C_AUTHN_WINNT, // RPC_C_AUTHN_xxx
-                RPC_C_AUTHZ_NONE,  // RPC_C_AUTHZ_xxx
-                None,
-                RPC_C_AUTHN_LEVEL_CALL,      // RPC_C_AUTHN_LEVEL_xxx
-                RPC_C_IMP_LEVEL_IMPERSONATE, // RPC_C_IMP_LEVEL_xxx
-                None,                        // client identity
-                EOAC_NONE,                   // proxy capabilities
-            )?;
+                &iface,
+                blanket.authn_service,
+                blanket.authz_service,
+                principal,
+                blanket.authn_level,
+                blanket.imp_level,
+                identity,
+                blanket.capabilities,
+            )
+        };
+
+        match &self.options.credentials {
+            None => set_blanket(None)?,
+            Some(credentials) => credentials.with_auth_identity(|identity| {
+                set_blanket(Some(identity as *const _ as *const _))
+            })?,
         }
 
         Ok(())
     }
 }
 
-fn create_locator() -> WMIResult<IWbemLocator> {
+/// Security settings passed to [CoSetProxyBlanket](https://docs.microsoft.com/en-us/windows/win32/api/combaseapi/nf-combaseapi-cosetproxyblanket).
+///
+/// The default matches the settings used for local connections.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProxyBlanket {
+    /// An `RPC_C_AUTHN_xxx` authentication service.
+    pub authn_service: u32,
+    /// An `RPC_C_AUTHZ_xxx` authorization service.
+    pub authz_service: u32,
+    /// The server principal name, if any.
+    pub principal: Option<String>,
+    pub authn_level: RPC_C_AUTHN_LEVEL,
+    pub imp_level: RPC_C_IMP_LEVEL,
+    pub capabilities: EOLE_AUTHENTICATION_CAPABILITIES,
+}
+
+impl Default for ProxyBlanket {
+    fn default() -> Self {
+        Self {
+            authn_service: RPC_C_AUTHN_WINNT,
+            authz_service: RPC_C_AUTHZ_NONE,
+            principal: None,
+            authn_level: RPC_C_AUTHN_LEVEL_CALL,
+            imp_level: RPC_C_IMP_LEVEL_IMPERSONATE,
+            capabilities: EOAC_NONE,
+        }
+    }
+}
+
+/// A builder for a [`WMIConnection`], created using [`WMIConnection::builder`].
+///
+/// By default, connects to the `ROOT\CIMV2` namespace of the local computer as the current user.
+#[derive(Debug, Default)]
+pub struct WMIConnectionBuilder {
+    namespace: Option<String>,
+    server: Option<String>,
+    locale: Option<String>,
+    flags: Option<i32>,
+    timeout: Option<Duration>,
+    ensure_locatable: bool,
+    prefetch: Option<u32>,
+    allow_win32_product: bool,
+    credentials: Option<Credentials>,
+    credential_target: Option<String>,
+    authority: Option<Authority>,
+    blanket: Option<ProxyBlanket>,
+    ctx: Option<WbemContext>,
+    de_options: DeserializeOptions,
+}
+
+impl WMIConnectionBuilder {
+    /// The namespace path to connect to (a string or a [`Namespace`](crate::Namespace)).
+    pub fn namespace(mut self, namespace_path: impl AsRef<str>) -> Self {
+        self.namespace = Some(namespace_path.as_ref().to_owned());
+        self
+    }
+
+    /// The remote computer to connect to.
+    pub fn server(mut self, server: &str) -> Self {
+        self.server = Some(server.to_owned());
+        self
+    }
+
+    /// The locale used to retrieve localized information, in the `MS_xxx` format (e.g. `MS_409` for English).
+    pub fn locale(mut self, locale: &str) -> Self {
+        self.locale = Some(locale.to_owned());
+        self
+    }
+
+    /// The `lSecurityFlags` passed to `ConnectServer`. Defaults to `WBEM_FLAG_CONNECT_USE_MAX_WAIT`.
+    pub fn flags(mut self, flags: i32) -> Self {
+        self.flags = Some(flags);
+        self
+    }
+
+    /// The maximum time to wait for each result of a query.
+    ///
+    /// When exceeded, iterating the results returns a [`WMIError::Timeout`] error.
+    /// By default, waits indefinitely.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Pass `WBEM_FLAG_ENSURE_LOCATABLE` to queries, so providers always return the `__PATH` of objects.
+    ///
+    /// See [`WMIConnection::with_ensure_locatable`].
+    pub fn ensure_locatable(mut self, ensure_locatable: bool) -> Self {
+        self.ensure_locatable = ensure_locatable;
+        self
+    }
+
+    /// Read query results in batches of `batch_size` objects on a background thread.
+    ///
+    /// See [`WMIConnection::with_prefetch`].
+    pub fn prefetch(mut self, batch_size: u32) -> Self {
+        self.prefetch = Some(batch_size.max(1));
+        self
+    }
+
+    /// Allow queries of `Win32_Product`, see [`WMIConnection::with_win32_product_allowed`].
+    pub fn allow_win32_product(mut self, allowed: bool) -> Self {
+        self.allow_win32_product = allowed;
+        self
+    }
+
+    /// The credentials to use, see [`Credentials`].
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Read the credentials to use from the Windows Credential Manager when connecting,
+    /// see [`Credentials::from_store`]. Ignored if [`credentials`](Self::credentials) are set.
+    pub fn credentials_from_store(mut self, target: &str) -> Self {
+        self.credential_target = Some(target.to_owned());
+        self
+    }
+
+    /// The authentication authority to use, see [`Authority`].
+    pub fn authority(mut self, authority: Authority) -> Self {
+        self.authority = Some(authority);
+        self
+    }
+
+    /// Override the security settings of the connection, see [`ComTransport::set_proxy_blanket`].
+    pub fn proxy_blanket(mut self, blanket: ProxyBlanket) -> Self {
+        self.blanket = Some(blanket);
+        self
+    }
+
+    /// The context passed to providers, see [`WMIConnection::with_context`].
+    pub fn context(mut self, ctx: WbemContext) -> Self {
+        self.ctx = Some(ctx);
+        self
+    }
+
+    /// The options used when deserializing results, see [`DeserializeOptions`].
+    pub fn deserialize_options(mut self, options: DeserializeOptions) -> Self {
+        self.de_options = options;
+        self
+    }
+
+    /// Connect to WMI.
+    pub fn build(self, com_lib: COMLibrary) -> WMIResult<WMIConnection> {
+        let namespace_path = self.namespace.as_deref().unwrap_or("ROOT\\CIMV2");
+
+        let path = match &self.server {
+            Some(server) => format!("\\\\{}\\{}", server, namespace_path),
+            None => namespace_path.to_owned(),
+        };
+
+        let credentials = match (self.credentials, &self.credential_target) {
+            (None, Some(target)) => Some(Credentials::from_store(target)?),
+            (credentials, _) => credentials,
+        };
+
+        // The client identity used by the proxies must contain the domain of the authority.
+        let credentials = match (credentials, &self.authority) {
+            (Some(credentials), Some(Authority::NtlmDomain(domain))) => {
+                Some(credentials.or_domain(domain))
+            }
+            (credentials, _) => credentials,
+        };
+
+        let options = ConnectOptions {
+            path,
+            credentials: credentials.map(Arc::new),
+            authority: self.authority,
+            locale: self.locale,
+            flags: self.flags.unwrap_or(WBEM_FLAG_CONNECT_USE_MAX_WAIT.0),
+        };
+
+        let loc = create_locator()?;
+        let svc = create_services(&loc, &options)?;
+
+        let this = ComTransport {
+            _com_con: com_lib,
+            svc,
+            ctx: self.ctx,
+            blanket: self.blanket,
+            timeout: self.timeout,
+            ensure_locatable: self.ensure_locatable,
+            prefetch: self.prefetch,
+            allow_win32_product: self.allow_win32_product,
+            options,
+            de_options: self.de_options,
+            capabilities: CapabilityCache::default(),
+        };
+
+        this.set_proxy()?;
+        Ok(WMIConnection::from_transport(this))
+    }
+}
+
+/// Replace the namespace of a connection path, keeping the server (if any).
+pub(crate) fn replace_namespace(path: &str, namespace_path: &str) -> String {
+    match path.strip_prefix("\\\\") {
+        Some(rest) => {
+            let server = rest.split('\\').next().unwrap_or_default();
+            format!("\\\\{}\\{}", server, namespace_path)
+        }
+        None => namespace_path.to_owned(),
+    }
+}
+
+/// Replace the server of a connection path (or add one, for local paths).
+pub(crate) fn replace_server(path: &str, server: &str) -> String {
+    let namespace_path = match path.strip_prefix("\\\\") {
+        Some(rest) => rest
+            .split_once('\\')
+            .map_or("ROOT\\CIMV2", |(_, namespace)| namespace),
+        None => path,
+    };
+
+    format!("\\\\{}\\{}", server, namespace_path)
+}
+
+pub(crate) fn create_locator() -> WMIResult<IWbemLocator> {
     debug!("Calling CoCreateInstance for CLSID_WbemLocator");
 
     let loc = unsafe { CoCreateInstance(&WbemLocator, None, CLSCTX_INPROC_SERVER)? };
@@ -191,21 +772,51 @@ fn create_locator() -> WMIResult<IWbemLocator> {
     Ok(loc)
 }
 
-fn create_services(loc: &IWbemLocator, path: &str) -> WMIResult<IWbemServices> {
+pub(crate) fn create_refresher() -> WMIResult<IWbemRefresher> {
+    debug!("Calling CoCreateInstance for CLSID_WbemRefresher");
+
+    let refresher = unsafe { CoCreateInstance(&WbemRefresher, None, CLSCTX_INPROC_SERVER)? };
+
+    Ok(refresher)
+}
+
+pub(crate) fn create_services(
+    loc: &IWbemLocator,
+    options: &ConnectOptions,
+) -> WMIResult<IWbemServices> {
     debug!("Calling ConnectServer");
 
-    let object_path_bstr = BSTR::from(path);
+    let object_path_bstr = BSTR::from(options.path.as_str());
+    let locale_bstr = options.locale.as_deref().map_or_else(BSTR::new, BSTR::from);
+    let authority_bstr = options
+        .authority
+        .as_ref()
+        .map_or_else(BSTR::new, |authority| {
+            BSTR::from(authority.to_connect_string())
+        });
 
-    let svc = unsafe {
+    let connect = |user: &BSTR, password: &BSTR| unsafe {
         loc.ConnectServer(
             &object_path_bstr,
-            &BSTR::new(),
-            &BSTR::new(),
-            &BSTR::new(),
-            WBEM_FLAG_CONNECT_USE_MAX_WAIT.0,
-            &BSTR::new(),
+            user,
+            password,
+            &locale_bstr,
+            options.flags,
+            &authority_bstr,
             None,
-        )?
+        )
+    };
+
+    let svc = match &options.credentials {
+        None => connect(&BSTR::new(), &BSTR::new())?,
+        Some(credentials) => {
+            // The domain must not be specified in both the user and the authority.
+            let user = match options.authority {
+                Some(Authority::NtlmDomain(_)) => BSTR::from(credentials.user()),
+                _ => BSTR::from(credentials.full_user()),
+            };
+            credentials.with_password_bstr(|password| connect(&user, password))?
+        }
     };
 
     debug!("Got service {:?}", svc);
@@ -218,6 +829,8 @@ fn create_services(loc: &IWbemLocator, path: &str) -> WMIResult<IWbemServices> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use windows::Win32::System::Com::RPC_C_IMP_LEVEL_DELEGATE;
+    use windows::Win32::System::Rpc::RPC_C_AUTHN_GSS_KERBEROS;
 
     #[test]
     fn it_can_create_multiple_connections() {
@@ -230,4 +843,101 @@ mod tests {
             let _ = WMIConnection::new(com_lib);
         }
     }
+
+    #[test]
+    fn it_replaces_the_namespace() {
+        assert_eq!(replace_namespace("ROOT\\CIMV2", "ROOT"), "ROOT");
+        assert_eq!(
+            replace_namespace("\\\\server01\\ROOT\\CIMV2", "ROOT\\WMI"),
+            "\\\\server01\\ROOT\\WMI"
+        );
+        assert_eq!(replace_namespace("\\\\.\\ROOT\\WMI", "ROOT"), "\\\\.\\ROOT");
+    }
+
+    #[test]
+    fn it_replaces_the_server() {
+        assert_eq!(
+            replace_server("ROOT\\CIMV2", "server01"),
+            "\\\\server01\\ROOT\\CIMV2"
+        );
+        assert_eq!(
+            replace_server("\\\\server01\\ROOT\\MSCluster", "server02"),
+            "\\\\server02\\ROOT\\MSCluster"
+        );
+    }
+
+    #[test]
+    fn it_can_connect_to_another_namespace() {
+        let wmi_con = crate::tests::fixtures::wmi_con();
+
+        let root_con = wmi_con.with_namespace("ROOT").unwrap();
+        assert_eq!(root_con.options.path, "ROOT");
+
+        let namespaces: Vec<std::collections::HashMap<String, crate::Variant>> =
+            root_con.raw_query("SELECT Name FROM __NAMESPACE").unwrap();
+        assert!(!namespaces.is_empty());
+    }
+
+    #[test]
+    fn it_derives_proxy_blanket_for_remote_connections() {
+        let mut wmi_con = crate::tests::fixtures::wmi_con();
+        assert_eq!(wmi_con.proxy_blanket(), ProxyBlanket::default());
+
+        wmi_con.options.authority = Some(Authority::Kerberos("CONTOSO\\server01$".to_owned()));
+        let blanket = wmi_con.proxy_blanket();
+        assert_eq!(blanket.authn_service, RPC_C_AUTHN_GSS_KERBEROS);
+        assert_eq!(blanket.principal.as_deref(), Some("CONTOSO\\server01$"));
+        assert_eq!(blanket.authn_level, RPC_C_AUTHN_LEVEL_PKT_PRIVACY);
+
+        let custom = ProxyBlanket {
+            imp_level: RPC_C_IMP_LEVEL_DELEGATE,
+            ..blanket
+        };
+        wmi_con.blanket = Some(custom.clone());
+        assert_eq!(wmi_con.proxy_blanket(), custom);
+        assert!(wmi_con.needs_proxy_blanket());
+    }
+
+    #[test]
+    fn it_can_override_proxy_blanket() {
+        let mut wmi_con = crate::tests::fixtures::wmi_con();
+
+        wmi_con.set_proxy_blanket(ProxyBlanket::default()).unwrap();
+
+        let enumerator = wmi_con
+            .exec_query_native_wrapper("SELECT Name FROM Win32_OperatingSystem")
+            .unwrap();
+        assert_eq!(enumerator.count(), 1);
+    }
+
+    #[test]
+    fn it_can_build_a_connection() {
+        let _ = crate::tests::fixtures::wmi_con();
+        let com_lib = unsafe { COMLibrary::assume_initialized() };
+
+        let wmi_con = WMIConnection::builder()
+            .namespace("ROOT\\CIMV2")
+            .locale("MS_409")
+            .timeout(Duration::from_secs(30))
+            .build(com_lib)
+            .unwrap();
+
+        assert_eq!(wmi_con.options.path, "ROOT\\CIMV2");
+        assert_eq!(wmi_con.timeout, Some(Duration::from_secs(30)));
+        wmi_con.ping().unwrap();
+    }
+
+    #[test]
+    fn it_builds_remote_paths() {
+        let builder = WMIConnection::builder()
+            .server("server01")
+            .authority(Authority::NtlmDomain("CONTOSO".to_owned()))
+            .credentials(Credentials::new("user", "pass".to_owned()));
+
+        assert_eq!(builder.server.as_deref(), Some("server01"));
+        assert_eq!(
+            builder.authority,
+            Some(Authority::NtlmDomain("CONTOSO".to_owned()))
+        );
+    }
 }
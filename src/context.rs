@@ -0,0 +1,234 @@
+use crate::{safe_variant::SafeVariant, ComTransport, Variant, WMIConnection, WMIResult};
+use log::debug;
+use windows::core::HSTRING;
+use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
+use windows::Win32::System::Wmi::{IWbemContext, WbemContext as CLSID_WbemContext};
+
+/// A wrapper around [IWbemContext], a set of named values which are passed to providers.
+///
+/// Some providers require a context to change their behavior. For example, the registry provider
+/// uses `__ProviderArchitecture` to select the 32-bit or 64-bit view of the registry.
+///
+/// A context is attached to a connection using [`WMIConnection::with_context`],
+/// and is then passed to every call made using the returned connection. Since that copy of the connection is cheap,
+/// it is also how a context is passed to a single query, get, put or method call:
+/// `wmi_con.with_context(ctx).get_by_path(path)`.
+///
+/// Cloning a context copies its values, so setting a value of a clone does not change the original
+/// (or the context of other connections).
+///
+/// ```edition2018
+/// # fn main() -> wmi::WMIResult<()> {
+/// # use std::collections::HashMap;
+/// use wmi::*;
+/// let wmi_con = WMIConnection::with_namespace_path("ROOT\\DEFAULT", COMLibrary::new()?)?;
+///
+/// let ctx = WbemContext::builder()
+///     .int("__ProviderArchitecture", 64)
+///     .bool("__RequiredArchitecture", true)
+///     .build()?;
+///
+/// let wmi_con = wmi_con.with_context(ctx);
+/// let results: Vec<HashMap<String, Variant>> = wmi_con.raw_query("SELECT * FROM StdRegProv")?;
+/// #   Ok(())
+/// # }
+/// ```
+///
+/// [IWbemContext]: https://docs.microsoft.com/en-us/windows/win32/api/wbemcli/nn-wbemcli-iwbemcontext
+#[derive(Debug, PartialEq, Eq)]
+pub struct WbemContext {
+    inner: IWbemContext,
+}
+
+impl WbemContext {
+    /// Create a new, empty context.
+    pub fn new() -> WMIResult<Self> {
+        debug!("Calling CoCreateInstance for CLSID_WbemContext");
+
+        let inner = unsafe { CoCreateInstance(&CLSID_WbemContext, None, CLSCTX_INPROC_SERVER)? };
+
+        Ok(Self { inner })
+    }
+
+    /// Create a builder for a new context.
+    pub fn builder() -> WbemContextBuilder {
+        WbemContextBuilder::default()
+    }
+
+    /// Set a named value. Only scalar values (numbers, strings and booleans) are supported.
+    pub fn set_value(&self, name: &str, value: impl Into<Variant>) -> WMIResult<()> {
+        let name = HSTRING::from(name);
+        let vt = SafeVariant::from_variant(&value.into())?;
+
+        unsafe { self.inner.SetValue(&name, 0, vt.as_raw())? };
+
+        Ok(())
+    }
+
+    /// Get a named value.
+    pub fn get_value(&self, name: &str) -> WMIResult<Variant> {
+        let name = HSTRING::from(name);
+
+        let vt = unsafe { SafeVariant::from_raw(self.inner.GetValue(&name, 0)?) };
+
+        vt.to_variant()
+    }
+
+    /// Remove a named value.
+    pub fn delete_value(&self, name: &str) -> WMIResult<()> {
+        let name = HSTRING::from(name);
+
+        unsafe { self.inner.DeleteValue(&name, 0)? };
+
+        Ok(())
+    }
+
+    /// Create a copy of this context, which can be modified independently.
+    pub fn try_clone(&self) -> WMIResult<Self> {
+        let inner = unsafe { self.inner.Clone()? };
+
+        Ok(Self { inner })
+    }
+
+    /// The raw interface of the context.
+    pub fn as_raw(&self) -> &IWbemContext {
+        &self.inner
+    }
+}
+
+/// Copies the values of the context, see [`try_clone`](WbemContext::try_clone).
+///
+/// # Panics
+///
+/// If the context can't be copied (which only fails when out of memory).
+impl Clone for WbemContext {
+    fn clone(&self) -> Self {
+        self.try_clone().expect("Failed to copy a WbemContext")
+    }
+}
+
+/// A builder for [`WbemContext`].
+#[derive(Debug, Default)]
+pub struct WbemContextBuilder {
+    values: Vec<(String, Variant)>,
+}
+
+impl WbemContextBuilder {
+    /// Add a string value.
+    pub fn string(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.value(name, Variant::String(value.into()))
+    }
+
+    /// Add an integer value.
+    pub fn int(self, name: impl Into<String>, value: i32) -> Self {
+        self.value(name, Variant::I4(value))
+    }
+
+    /// Add a boolean value.
+    pub fn bool(self, name: impl Into<String>, value: bool) -> Self {
+        self.value(name, Variant::Bool(value))
+    }
+
+    /// Add any scalar value.
+    pub fn value(mut self, name: impl Into<String>, value: impl Into<Variant>) -> Self {
+        self.values.push((name.into(), value.into()));
+        self
+    }
+
+    /// Create the context, setting all the values.
+    pub fn build(self) -> WMIResult<WbemContext> {
+        let ctx = WbemContext::new()?;
+
+        for (name, value) in self.values {
+            ctx.set_value(&name, value)?;
+        }
+
+        Ok(ctx)
+    }
+}
+
+impl WMIConnection {
+    /// Return a copy of this connection which passes the given context to every call.
+    ///
+    /// The underlying `IWbemServices` is shared, so this is cheap, and can be used to pass a context to a single call.
+    pub fn with_context(&self, ctx: WbemContext) -> Self {
+        let mut con = self.clone();
+        con.ctx = Some(ctx);
+        con
+    }
+
+    /// Return a copy of this connection which does not pass any context.
+    pub fn without_context(&self) -> Self {
+        let mut con = self.clone();
+        con.ctx = None;
+        con
+    }
+
+    /// The context attached to this connection, if any.
+    pub fn context(&self) -> Option<&WbemContext> {
+        self.ctx.as_ref()
+    }
+}
+
+impl ComTransport {
+    pub(crate) fn ctx(&self) -> Option<&IWbemContext> {
+        self.ctx.as_ref().map(|ctx| &ctx.inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn it_can_set_and_get_values() {
+        let _wmi_con = wmi_con();
+
+        let ctx = WbemContext::builder()
+            .string("a", "b")
+            .int("c", 1)
+            .bool("d", true)
+            .build()
+            .unwrap();
+
+        assert_eq!(ctx.get_value("a").unwrap(), Variant::String("b".to_owned()));
+        assert_eq!(ctx.get_value("c").unwrap(), Variant::I4(1));
+        assert_eq!(ctx.get_value("d").unwrap(), Variant::Bool(true));
+
+        let copy = ctx.try_clone().unwrap();
+        let clone = ctx.clone();
+        ctx.delete_value("a").unwrap();
+
+        assert!(ctx.get_value("a").is_err());
+        assert_eq!(
+            copy.get_value("a").unwrap(),
+            Variant::String("b".to_owned())
+        );
+        assert_eq!(
+            clone.get_value("a").unwrap(),
+            Variant::String("b".to_owned())
+        );
+    }
+
+    #[test]
+    fn it_can_query_with_context() {
+        let wmi_con = wmi_con();
+
+        let ctx = WbemContext::builder()
+            .int("__ProviderArchitecture", 64)
+            .build()
+            .unwrap();
+
+        let wmi_con = wmi_con.with_context(ctx);
+        assert!(wmi_con.context().is_some());
+
+        let results: Vec<HashMap<String, Variant>> = wmi_con
+            .raw_query("SELECT Caption FROM Win32_OperatingSystem")
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert!(wmi_con.without_context().context().is_none());
+    }
+}
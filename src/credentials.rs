@@ -0,0 +1,330 @@
+use crate::{WMIError, WMIResult};
+use std::{fmt, ptr, slice};
+use windows::core::{BSTR, HSTRING};
+use windows::Win32::Foundation::ERROR_NOT_FOUND;
+use windows::Win32::Security::Credentials::{CredFree, CredReadW, CREDENTIALW, CRED_TYPE_GENERIC};
+use windows::Win32::System::Com::COAUTHIDENTITY;
+use windows::Win32::System::Rpc::{
+    RPC_C_AUTHN_GSS_KERBEROS, RPC_C_AUTHN_WINNT, SEC_WINNT_AUTH_IDENTITY_UNICODE,
+};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Credentials used to connect to a remote computer.
+///
+/// The password is kept in a buffer which is zeroed when the credentials are dropped,
+/// and the native structures that require it (the `ConnectServer` arguments and the
+/// [COAUTHIDENTITY] used by `CoSetProxyBlanket`) are only constructed for the duration of each call,
+/// so the password does not linger in the memory of long-running processes.
+///
+/// ```edition2018
+/// use wmi::Credentials;
+///
+/// let credentials = Credentials::new("CONTOSO\\Administrator", String::from("hunter2"));
+/// assert_eq!(credentials.domain(), Some("CONTOSO"));
+/// assert_eq!(credentials.user(), "Administrator");
+/// ```
+///
+/// [COAUTHIDENTITY]: https://docs.microsoft.com/en-us/windows/win32/api/wtypesbase/ns-wtypesbase-coauthidentity
+pub struct Credentials {
+    domain: Option<String>,
+    user: String,
+    password: Zeroizing<Vec<u16>>,
+}
+
+impl Credentials {
+    /// Create credentials for the given user, which can be in the `DOMAIN\user` format.
+    ///
+    /// The given password is zeroed after it is copied.
+    pub fn new(user: &str, mut password: String) -> Self {
+        let credentials =
+            Self::from_wide_password(user, Zeroizing::new(password.encode_utf16().collect()));
+
+        password.zeroize();
+
+        credentials
+    }
+
+    /// Read generic credentials saved in the Windows Credential Manager,
+    /// so passwords do not have to be stored in configuration files.
+    ///
+    /// Such credentials can be saved using `cmdkey /generic:wmi:server01 /user:CONTOSO\Administrator /pass`
+    /// (or from the "Windows Credentials" section of the Control Panel).
+    /// Returns [`WMIError::CredentialNotFound`] if there are no generic credentials named `target`.
+    pub fn from_store(target: &str) -> WMIResult<Self> {
+        let mut credential: *mut CREDENTIALW = ptr::null_mut();
+
+        let found = unsafe {
+            CredReadW(
+                &HSTRING::from(target),
+                CRED_TYPE_GENERIC.0,
+                0,
+                &mut credential,
+            )
+        };
+
+        if !found.as_bool() {
+            let error = windows::core::Error::from_win32();
+
+            if error.code() == ERROR_NOT_FOUND.to_hresult() {
+                return Err(WMIError::CredentialNotFound(target.to_owned()));
+            }
+
+            return Err(error.into());
+        }
+
+        let credential = StoredCredential(credential);
+        // Safety: `CredReadW` succeeded, so the pointer is valid until it is freed by `StoredCredential`.
+        let credential = unsafe { &*credential.0 };
+
+        let user = if credential.UserName.is_null() {
+            String::new()
+        } else {
+            unsafe { credential.UserName.to_string() }?
+        };
+
+        let blob: &mut [u8] = if credential.CredentialBlob.is_null() {
+            &mut []
+        } else {
+            unsafe {
+                slice::from_raw_parts_mut(
+                    credential.CredentialBlob,
+                    credential.CredentialBlobSize as usize,
+                )
+            }
+        };
+
+        // Passwords of generic credentials are stored as (unterminated) UTF-16.
+        let password = Zeroizing::new(
+            blob.chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect(),
+        );
+        blob.zeroize();
+
+        Ok(Self::from_wide_password(&user, password))
+    }
+
+    fn from_wide_password(user: &str, password: Zeroizing<Vec<u16>>) -> Self {
+        let (domain, user) = match user.split_once('\\') {
+            Some((domain, user)) => (Some(domain.to_owned()), user.to_owned()),
+            None => (None, user.to_owned()),
+        };
+
+        Self {
+            domain,
+            user,
+            password,
+        }
+    }
+
+    /// Create credentials for a user in the given domain.
+    pub fn with_domain(domain: &str, user: &str, password: String) -> Self {
+        let mut credentials = Self::new(user, password);
+        credentials.domain = Some(domain.to_owned());
+        credentials
+    }
+
+    pub fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    /// Use `domain` if the credentials do not specify one already.
+    pub(crate) fn or_domain(mut self, domain: &str) -> Self {
+        if self.domain.is_none() {
+            self.domain = Some(domain.to_owned());
+        }
+        self
+    }
+
+    /// The user in the `DOMAIN\user` format, as expected by `ConnectServer`.
+    pub(crate) fn full_user(&self) -> String {
+        match &self.domain {
+            Some(domain) => format!("{}\\{}", domain, self.user),
+            None => self.user.clone(),
+        }
+    }
+
+    /// Call `f` with the password as a `BSTR`, which is zeroed before it is freed.
+    pub(crate) fn with_password_bstr<R>(&self, f: impl FnOnce(&BSTR) -> R) -> R {
+        let password = SecretBstr(BSTR::from_wide(&self.password).unwrap_or_default());
+
+        f(&password.0)
+    }
+
+    /// Call `f` with a `COAUTHIDENTITY` pointing to copies of the credentials,
+    /// which are zeroed once `f` returns.
+    pub(crate) fn with_auth_identity<R>(&self, f: impl FnOnce(&COAUTHIDENTITY) -> R) -> R {
+        let mut user = Zeroizing::new(self.user.encode_utf16().collect::<Vec<u16>>());
+        let mut domain = Zeroizing::new(
+            self.domain
+                .as_deref()
+                .unwrap_or_default()
+                .encode_utf16()
+                .collect::<Vec<u16>>(),
+        );
+        let mut password = self.password.clone();
+
+        let identity = COAUTHIDENTITY {
+            User: user.as_mut_ptr(),
+            UserLength: user.len() as u32,
+            Domain: domain.as_mut_ptr(),
+            DomainLength: domain.len() as u32,
+            Password: password.as_mut_ptr(),
+            PasswordLength: password.len() as u32,
+            Flags: SEC_WINNT_AUTH_IDENTITY_UNICODE.0,
+        };
+
+        f(&identity)
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("domain", &self.domain)
+            .field("user", &self.user)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+/// The authentication authority used when connecting to a remote computer.
+///
+/// In domain environments, forcing Kerberos is often required to avoid "double-hop" failures,
+/// in which case the `principal` is usually the name of the remote computer's account (e.g. `CONTOSO\server01$`).
+///
+/// See the `strAuthority` parameter of [ConnectServer](https://docs.microsoft.com/en-us/windows/win32/api/wbemcli/nf-wbemcli-iwbemlocator-connectserver).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Authority {
+    /// Use Kerberos authentication with the given principal name.
+    Kerberos(String),
+    /// Use NTLM authentication with the given domain.
+    NtlmDomain(String),
+}
+
+impl Authority {
+    /// The `strAuthority` string for `ConnectServer`.
+    pub(crate) fn to_connect_string(&self) -> String {
+        match self {
+            Authority::Kerberos(principal) => format!("Kerberos:{}", principal),
+            Authority::NtlmDomain(domain) => format!("NTLMDOMAIN:{}", domain),
+        }
+    }
+
+    /// The `RPC_C_AUTHN_xxx` authentication service for `CoSetProxyBlanket`.
+    pub(crate) fn authn_service(&self) -> u32 {
+        match self {
+            Authority::Kerberos(_) => RPC_C_AUTHN_GSS_KERBEROS,
+            Authority::NtlmDomain(_) => RPC_C_AUTHN_WINNT,
+        }
+    }
+
+    /// The server principal name for `CoSetProxyBlanket`, if any.
+    pub(crate) fn principal(&self) -> Option<&str> {
+        match self {
+            Authority::Kerberos(principal) => Some(principal),
+            Authority::NtlmDomain(_) => None,
+        }
+    }
+}
+
+/// A credential returned by `CredReadW`, which is freed when dropped.
+struct StoredCredential(*mut CREDENTIALW);
+
+impl Drop for StoredCredential {
+    fn drop(&mut self) {
+        unsafe { CredFree(self.0 as *const _) };
+    }
+}
+
+/// A `BSTR` which is zeroed when dropped.
+struct SecretBstr(BSTR);
+
+impl Drop for SecretBstr {
+    fn drop(&mut self) {
+        let data = self.0.as_wide();
+
+        if !data.is_empty() {
+            // Safety: the BSTR is owned by us, and is valid for `data.len()` UTF-16 code units.
+            let data =
+                unsafe { std::slice::from_raw_parts_mut(data.as_ptr() as *mut u16, data.len()) };
+            data.zeroize();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_splits_domain_and_user() {
+        let credentials = Credentials::new("CONTOSO\\Administrator", "pass".to_owned());
+
+        assert_eq!(credentials.domain(), Some("CONTOSO"));
+        assert_eq!(credentials.user(), "Administrator");
+        assert_eq!(credentials.full_user(), "CONTOSO\\Administrator");
+
+        let credentials = Credentials::new("Administrator", "pass".to_owned());
+
+        assert_eq!(credentials.domain(), None);
+        assert_eq!(credentials.full_user(), "Administrator");
+    }
+
+    #[test]
+    fn it_does_not_leak_password_in_debug() {
+        let credentials = Credentials::with_domain("CONTOSO", "user", "hunter2".to_owned());
+
+        assert!(!format!("{:?}", credentials).contains("hunter2"));
+    }
+
+    #[test]
+    fn it_builds_auth_identity() {
+        let credentials = Credentials::new("CONTOSO\\user", "pass".to_owned());
+
+        credentials.with_auth_identity(|identity| {
+            assert_eq!(identity.UserLength, 4);
+            assert_eq!(identity.DomainLength, 7);
+            assert_eq!(identity.PasswordLength, 4);
+            assert_eq!(identity.Flags, SEC_WINNT_AUTH_IDENTITY_UNICODE.0);
+        });
+
+        credentials.with_password_bstr(|password| assert_eq!(password, "pass"));
+    }
+
+    #[test]
+    fn it_formats_authority() {
+        let kerberos = Authority::Kerberos("CONTOSO\\server01$".to_owned());
+        assert_eq!(kerberos.to_connect_string(), "Kerberos:CONTOSO\\server01$");
+        assert_eq!(kerberos.authn_service(), RPC_C_AUTHN_GSS_KERBEROS);
+        assert_eq!(kerberos.principal(), Some("CONTOSO\\server01$"));
+
+        let ntlm = Authority::NtlmDomain("CONTOSO".to_owned());
+        assert_eq!(ntlm.to_connect_string(), "NTLMDOMAIN:CONTOSO");
+        assert_eq!(ntlm.authn_service(), RPC_C_AUTHN_WINNT);
+        assert_eq!(ntlm.principal(), None);
+    }
+
+    #[test]
+    fn it_reports_missing_stored_credentials() {
+        let target = "wmi:this-credential-does-not-exist";
+
+        assert!(matches!(
+            Credentials::from_store(target),
+            Err(WMIError::CredentialNotFound(name)) if name == target
+        ));
+    }
+
+    #[test]
+    fn it_uses_authority_domain_as_fallback() {
+        let credentials = Credentials::new("user", "pass".to_owned()).or_domain("CONTOSO");
+        assert_eq!(credentials.domain(), Some("CONTOSO"));
+
+        let credentials = Credentials::new("OTHER\\user", "pass".to_owned()).or_domain("CONTOSO");
+        assert_eq!(credentials.domain(), Some("OTHER"));
+    }
+}
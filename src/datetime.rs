@@ -3,7 +3,12 @@ use chrono::prelude::*;
 use serde::{de, ser};
 use std::{fmt, str::FromStr};
 
+pub mod raw;
+
 /// A wrapper type around `chrono`'s `DateTime` (if the `chrono` feature is active. ), which supports parsing from WMI-format strings.
+///
+/// The UTC offset reported by the provider is kept as-is.
+/// If the provider is known to report a meaningless offset, use [`WMILocalDateTime`] instead.
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct WMIDateTime(pub DateTime<FixedOffset>);
 
@@ -24,6 +29,33 @@ impl FromStr for WMIDateTime {
     }
 }
 
+/// A wrapper type around `chrono`'s `NaiveDateTime` (if the `chrono` feature is active. ), which supports parsing from WMI-format strings.
+///
+/// Unlike [`WMIDateTime`], the UTC offset part of the value is ignored (and not validated),
+/// which is useful for providers that report local times with a missing or invalid offset.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct WMILocalDateTime(pub NaiveDateTime);
+
+impl FromStr for WMILocalDateTime {
+    type Err = WMIError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() < 21 {
+            return Err(WMIError::ConvertDatetimeError(s.into()));
+        }
+
+        let dt = NaiveDateTime::parse_from_str(&s[..21], "%Y%m%d%H%M%S.%f")?;
+
+        Ok(Self(dt))
+    }
+}
+
+impl From<WMIDateTime> for WMILocalDateTime {
+    fn from(value: WMIDateTime) -> Self {
+        Self(value.0.naive_local())
+    }
+}
+
 #[derive(Debug, Clone)]
 struct DateTimeVisitor;
 
@@ -62,9 +94,47 @@ impl ser::Serialize for WMIDateTime {
     }
 }
 
+#[derive(Debug, Clone)]
+struct LocalDateTimeVisitor;
+
+impl<'de> de::Visitor<'de> for LocalDateTimeVisitor {
+    type Value = WMILocalDateTime;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a timestamp in WMI format")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        value.parse().map_err(|err| E::custom(format!("{}", err)))
+    }
+}
+
+impl<'de> de::Deserialize<'de> for WMILocalDateTime {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(LocalDateTimeVisitor)
+    }
+}
+
+impl ser::Serialize for WMILocalDateTime {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        let formatted = self.0.format("%Y-%m-%dT%H:%M:%S%.6f").to_string();
+
+        serializer.serialize_str(&formatted)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::WMIDateTime;
+    use super::{WMIDateTime, WMILocalDateTime};
     use serde_json;
 
     #[test]
@@ -106,4 +176,29 @@ mod tests {
         let v = serde_json::to_string(&dt).unwrap();
         assert_eq!(v, "\"2019-01-13T20:05:17.000500+01:00\"");
     }
+
+    #[test]
+    fn it_ignores_offset_for_local() {
+        let dt: WMILocalDateTime = "20190113200517.500000-180".parse().unwrap();
+        assert_eq!(dt.0.to_string(), "2019-01-13 20:05:17.000500");
+
+        let dt: WMILocalDateTime = "20190113200517.500000+***".parse().unwrap();
+        assert_eq!(dt.0.to_string(), "2019-01-13 20:05:17.000500");
+    }
+
+    #[test]
+    fn it_converts_offset_to_local() {
+        let dt: WMIDateTime = "20190113200517.500000+060".parse().unwrap();
+        let local: WMILocalDateTime = dt.into();
+
+        assert_eq!(local.0, dt.0.naive_local());
+    }
+
+    #[test]
+    fn it_serializes_local_without_offset() {
+        let dt: WMILocalDateTime = "20190113200517.500000+060".parse().unwrap();
+
+        let v = serde_json::to_string(&dt).unwrap();
+        assert_eq!(v, "\"2019-01-13T20:05:17.000500\"");
+    }
 }
@@ -0,0 +1,205 @@
+//! Serde helpers for keeping a WMI datetime as the original DMTF string.
+//!
+//! Parsing a datetime into [`WMIDateTime`](crate::WMIDateTime) (or a similar wrapper) loses the exact
+//! representation returned by the provider (for example, the precision of the sub-second part).
+//! When a value needs to be echoed back into a WQL filter verbatim, use this module with `#[serde(with)]`:
+//!
+//! ```edition2018
+//! # use serde::Deserialize;
+//! #[derive(Deserialize, Debug)]
+//! #[serde(rename = "Win32_OperatingSystem")]
+//! #[serde(rename_all = "PascalCase")]
+//! struct OperatingSystem {
+//!     #[serde(with = "wmi::datetime::raw")]
+//!     last_boot_up_time: String,
+//!     #[serde(with = "wmi::datetime::raw::option")]
+//!     install_date: Option<String>,
+//! }
+//! ```
+//!
+//! To get both the parsed value and the original string, use [`WithRaw`].
+//!
+use serde::{de, ser};
+use std::{fmt, str::FromStr};
+
+/// Checks that `s` has the shape of a DMTF datetime (`yyyymmddHHMMSS.mmmmmmsUUU`).
+///
+/// Any of the digits can be replaced by `*` to mark an unspecified field, as allowed by DMTF.
+pub fn is_dmtf_datetime(s: &str) -> bool {
+    let bytes = s.as_bytes();
+
+    if bytes.len() != 25 {
+        return false;
+    }
+
+    bytes.iter().enumerate().all(|(i, ch)| match i {
+        14 => *ch == b'.',
+        21 => *ch == b'+' || *ch == b'-',
+        _ => ch.is_ascii_digit() || *ch == b'*',
+    })
+}
+
+struct RawVisitor;
+
+impl<'de> de::Visitor<'de> for RawVisitor {
+    type Value = String;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a timestamp in WMI format")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.visit_string(value.to_owned())
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if is_dmtf_datetime(&value) {
+            Ok(value)
+        } else {
+            Err(E::invalid_value(de::Unexpected::Str(&value), &self))
+        }
+    }
+}
+
+/// Deserialize a DMTF datetime into its original string, after validating its format.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    deserializer.deserialize_str(RawVisitor)
+}
+
+/// Serialize the original DMTF string as-is.
+pub fn serialize<S>(value: &str, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: ser::Serializer,
+{
+    serializer.serialize_str(value)
+}
+
+/// Same as the parent module, but for `Option<String>` fields (WMI returns `NULL` for unset datetimes).
+pub mod option {
+    use serde::{de, ser, Deserialize};
+
+    struct Raw(String);
+
+    impl<'de> Deserialize<'de> for Raw {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            super::deserialize(deserializer).map(Raw)
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        Ok(Option::<Raw>::deserialize(deserializer)?.map(|raw| raw.0))
+    }
+
+    pub fn serialize<S>(value: &Option<String>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        match value {
+            Some(value) => serializer.serialize_some(value),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+/// A parsed value (like [`WMIDateTime`](crate::WMIDateTime)) together with the original DMTF string it was parsed from.
+///
+/// Serializes to the original string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WithRaw<T> {
+    pub value: T,
+    pub raw: String,
+}
+
+impl<'de, T> de::Deserialize<'de> for WithRaw<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let raw = deserialize(deserializer)?;
+        let value = raw.parse().map_err(de::Error::custom)?;
+
+        Ok(Self { value, raw })
+    }
+}
+
+impl<T> ser::Serialize for WithRaw<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serialize(&self.raw, serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug)]
+    struct Row {
+        #[serde(with = "crate::datetime::raw")]
+        time: String,
+        #[serde(with = "crate::datetime::raw::option")]
+        maybe_time: Option<String>,
+    }
+
+    #[test]
+    fn it_keeps_the_original_string() {
+        let row: Row =
+            serde_json::from_str(r#"{"time": "20190113200517.500000-180", "maybe_time": null}"#)
+                .unwrap();
+
+        assert_eq!(row.time, "20190113200517.500000-180");
+        assert_eq!(row.maybe_time, None);
+
+        let v = serde_json::to_string(&row).unwrap();
+        assert_eq!(
+            v,
+            r#"{"time":"20190113200517.500000-180","maybe_time":null}"#
+        );
+    }
+
+    #[test]
+    fn it_accepts_unspecified_fields() {
+        assert!(is_dmtf_datetime("2019011320****.******+***"));
+    }
+
+    #[test]
+    fn it_fails_with_malformed_str() {
+        let res: Result<Row, _> =
+            serde_json::from_str(r#"{"time": "20190113200517", "maybe_time": null}"#);
+        assert!(res.is_err());
+
+        assert!(!is_dmtf_datetime("00000005141436.100001:000"));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn it_keeps_both_parsed_and_raw() {
+        let dt: WithRaw<crate::WMIDateTime> =
+            serde_json::from_str(r#""20190113200517.500000+060""#).unwrap();
+
+        assert_eq!(dt.raw, "20190113200517.500000+060");
+        assert_eq!(dt.value.0.to_rfc3339(), "2019-01-13T20:05:17.000500+01:00");
+    }
+}
@@ -11,7 +11,15 @@ use time::{
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub struct WMIOffsetDateTime(pub time::OffsetDateTime);
 
-impl FromStr for WMIOffsetDateTime {
+/// A wrapper type around `time`'s `PrimitiveDateTime` (if the
+/// `time` feature is active), which supports parsing from WMI-format strings.
+///
+/// Unlike [`WMIOffsetDateTime`], the UTC offset part of the value is ignored (and not validated),
+/// which is useful for providers that report local times with a missing or invalid offset.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
+pub struct WMILocalDateTime(pub PrimitiveDateTime);
+
+impl FromStr for WMILocalDateTime {
     type Err = WMIError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -25,10 +33,6 @@ impl FromStr for WMIOffsetDateTime {
         const TIME_FORMAT: &[FormatItem<'static>] =
             format_description!("[month][day][hour][minute][second].[subsecond digits:6]");
 
-        let minutes_offset = s[21..].parse::<i32>()?;
-        let offset =
-            UtcOffset::from_whole_seconds(minutes_offset * 60).map_err(time::Error::from)?;
-
         let mut parser = Parsed::new();
 
         let naive_date_time = &s[4..21];
@@ -50,6 +54,29 @@ impl FromStr for WMIOffsetDateTime {
 
         let naive_date_time: PrimitiveDateTime =
             std::convert::TryInto::try_into(parser).map_err(time::Error::from)?;
+        Ok(Self(naive_date_time))
+    }
+}
+
+impl From<WMIOffsetDateTime> for WMILocalDateTime {
+    fn from(value: WMIOffsetDateTime) -> Self {
+        Self(PrimitiveDateTime::new(value.0.date(), value.0.time()))
+    }
+}
+
+impl FromStr for WMIOffsetDateTime {
+    type Err = WMIError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() < 21 {
+            return Err(WMIError::ConvertDatetimeError(s.into()));
+        }
+
+        let minutes_offset = s[21..].parse::<i32>()?;
+        let offset =
+            UtcOffset::from_whole_seconds(minutes_offset * 60).map_err(time::Error::from)?;
+
+        let WMILocalDateTime(naive_date_time) = s.parse()?;
         let dt = naive_date_time.assume_offset(offset);
         Ok(Self(dt))
     }
@@ -82,6 +109,33 @@ impl<'de> de::Deserialize<'de> for WMIOffsetDateTime {
     }
 }
 
+#[derive(Debug, Clone)]
+struct PrimitiveDateTimeVisitor;
+
+impl<'de> de::Visitor<'de> for PrimitiveDateTimeVisitor {
+    type Value = WMILocalDateTime;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a timestamp in WMI format")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        value.parse().map_err(|err| E::custom(format!("{}", err)))
+    }
+}
+
+impl<'de> de::Deserialize<'de> for WMILocalDateTime {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(PrimitiveDateTimeVisitor)
+    }
+}
+
 const RFC3339_WITH_6_DIGITS: &[FormatItem<'_>] =format_description!(
     "[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:6][offset_hour sign:mandatory]:[offset_minute]"
 );
@@ -98,9 +152,24 @@ impl ser::Serialize for WMIOffsetDateTime {
     }
 }
 
+const ISO8601_LOCAL_WITH_6_DIGITS: &[FormatItem<'_>] =
+    format_description!("[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:6]");
+
+impl ser::Serialize for WMILocalDateTime {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        // Unwrap: we passed a well known format, if it fails something has gone very wrong
+        let formatted = self.0.format(ISO8601_LOCAL_WITH_6_DIGITS).unwrap();
+
+        serializer.serialize_str(&formatted)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::WMIOffsetDateTime;
+    use super::{WMILocalDateTime, WMIOffsetDateTime};
     use serde_json;
 
     #[test]
@@ -142,4 +211,32 @@ mod tests {
         let v = serde_json::to_string(&dt).unwrap();
         assert_eq!(v, "\"2019-01-13T20:05:17.000500+01:00\"");
     }
+
+    #[test]
+    fn it_ignores_offset_for_local() {
+        let dt: WMILocalDateTime = "20190113200517.500000-180".parse().unwrap();
+        let formatted = dt.0.format(super::ISO8601_LOCAL_WITH_6_DIGITS).unwrap();
+        assert_eq!(formatted, "2019-01-13T20:05:17.000500");
+
+        let dt: WMILocalDateTime = "20190113200517.500000+***".parse().unwrap();
+        let formatted = dt.0.format(super::ISO8601_LOCAL_WITH_6_DIGITS).unwrap();
+        assert_eq!(formatted, "2019-01-13T20:05:17.000500");
+    }
+
+    #[test]
+    fn it_converts_offset_to_local() {
+        let dt: WMIOffsetDateTime = "20190113200517.500000+060".parse().unwrap();
+        let local: WMILocalDateTime = dt.into();
+
+        assert_eq!(local.0.hour(), 20);
+        assert_eq!(local.0.assume_offset(dt.0.offset()), dt.0);
+    }
+
+    #[test]
+    fn it_serializes_local_without_offset() {
+        let dt: WMILocalDateTime = "20190113200517.500000+060".parse().unwrap();
+
+        let v = serde_json::to_string(&dt).unwrap();
+        assert_eq!(v, "\"2019-01-13T20:05:17.000500\"");
+    }
 }
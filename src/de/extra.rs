@@ -0,0 +1,138 @@
+use crate::{
+    de::meta::{struct_name_and_fields, ALL_PROPERTIES},
+    Variant, WMIError,
+};
+use serde::de::{self, value::MapDeserializer, Deserialize, Deserializer, MapAccess, Visitor};
+use std::{collections::HashMap, fmt, marker::PhantomData};
+
+/// Deserialize a struct, while collecting all the properties which are not mapped by its fields.
+///
+/// Queries for `WithExtra<T>` select all the properties of the class of `T`.
+/// Properties are matched to the fields of `T` case-insensitively, like WMI does.
+/// Note that the extra properties also include the system properties (like `__CLASS` or `__PATH`).
+///
+/// ```edition2018
+/// # fn main() -> wmi::WMIResult<()> {
+/// # use wmi::*;
+/// # let con = WMIConnection::new(COMLibrary::new()?)?;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Win32_OperatingSystem {
+///     Caption: String,
+/// }
+///
+/// let os: WithExtra<Win32_OperatingSystem> = con.get()?;
+/// println!("{} {:?}", os.value.Caption, os.extra.get("BuildNumber"));
+/// #   Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct WithExtra<T> {
+    pub value: T,
+    pub extra: HashMap<String, Variant>,
+}
+
+impl<'de, T> Deserialize<'de> for WithExtra<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (name, fields) = struct_name_and_fields::<T>().map_err(de::Error::custom)?;
+
+        deserializer.deserialize_struct(
+            name,
+            ALL_PROPERTIES,
+            WithExtraVisitor {
+                fields,
+                marker: PhantomData,
+            },
+        )
+    }
+}
+
+struct WithExtraVisitor<T> {
+    fields: &'static [&'static str],
+    marker: PhantomData<T>,
+}
+
+impl<'de, T> Visitor<'de> for WithExtraVisitor<T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = WithExtra<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a WMI object")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut mapped = vec![];
+        let mut extra = HashMap::new();
+
+        while let Some((key, value)) = map.next_entry::<String, Variant>()? {
+            match self
+                .fields
+                .iter()
+                .find(|field| field.eq_ignore_ascii_case(&key))
+            {
+                Some(field) => mapped.push((*field, value)),
+                None => {
+                    extra.insert(key, value);
+                }
+            }
+        }
+
+        let value = T::deserialize(MapDeserializer::<_, WMIError>::new(mapped.into_iter()))
+            .map_err(de::Error::custom)?;
+
+        Ok(WithExtra { value, extra })
+    }
+}
+
+#[allow(non_snake_case)]
+#[allow(non_camel_case_types)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_query;
+    use crate::tests::fixtures::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug)]
+    struct Win32_OperatingSystem {
+        Caption: String,
+        buildnumber: String,
+    }
+
+    #[test]
+    fn it_builds_query_for_all_properties() {
+        let query = build_query::<WithExtra<Win32_OperatingSystem>>(None).unwrap();
+
+        assert_eq!(query, "SELECT * FROM Win32_OperatingSystem ");
+    }
+
+    #[test]
+    fn it_collects_unmapped_properties() {
+        let wmi_con = wmi_con();
+
+        let os: WithExtra<Win32_OperatingSystem> = wmi_con.get().unwrap();
+
+        assert!(os.value.Caption.contains("Microsoft Windows"));
+        assert_ne!(os.value.buildnumber, "");
+
+        assert!(os.extra.contains_key("Version"));
+        assert_eq!(
+            os.extra.get("__CLASS"),
+            Some(&Variant::String("Win32_OperatingSystem".to_owned()))
+        );
+        assert!(!os.extra.contains_key("Caption"));
+        assert!(!os.extra.contains_key("BuildNumber"));
+    }
+}
@@ -0,0 +1,279 @@
+use serde::{de, ser};
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+
+/// A set of strings which are shared between [`InternedStr`] fields.
+///
+/// Inventory queries often return the same values over and over (like `Status = "OK"`),
+/// so sharing them can save a lot of memory when keeping large snapshots.
+///
+/// ```edition2018
+/// # fn main() -> wmi::WMIResult<()> {
+/// # use wmi::*;
+/// # let con = WMIConnection::new(COMLibrary::new()?)?;
+/// use serde::Deserialize;
+/// use wmi::de::interned::{InternedStr, StringInterner};
+/// use wmi::de::options::DeserializeOptions;
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Win32_Service {
+///     Name: String,
+///     Status: InternedStr,
+///     StartMode: InternedStr,
+/// }
+///
+/// let interner = StringInterner::new();
+/// let con = con.with_deserialize_options(DeserializeOptions::new().interner(interner.clone()));
+///
+/// let services: Vec<Win32_Service> = con.query()?;
+/// assert!(interner.len() < services.len());
+/// #   Ok(())
+/// # }
+/// ```
+///
+/// The interner can be shared between connections (and threads), and is cheap to clone.
+#[derive(Clone, Default)]
+pub struct StringInterner {
+    strings: Arc<Mutex<HashSet<Arc<str>>>>,
+}
+
+impl StringInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the shared copy of `s`, adding it to the interner if needed.
+    pub fn intern(&self, s: &str) -> Arc<str> {
+        let mut strings = self.strings.lock().unwrap_or_else(|err| err.into_inner());
+
+        match strings.get(s) {
+            Some(interned) => interned.clone(),
+            None => {
+                let interned: Arc<str> = Arc::from(s);
+                strings.insert(interned.clone());
+                interned
+            }
+        }
+    }
+
+    /// The number of distinct strings in the interner.
+    pub fn len(&self) -> usize {
+        self.strings
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all the strings from the interner.
+    /// Strings which are still used by `InternedStr` values are not freed until they are dropped.
+    pub fn clear(&self) {
+        self.strings
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clear();
+    }
+}
+
+impl fmt::Debug for StringInterner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StringInterner")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Two interners are equal if they share the same strings.
+impl PartialEq for StringInterner {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.strings, &other.strings)
+    }
+}
+
+impl Eq for StringInterner {}
+
+/// A string which is shared using the [`StringInterner`] of the connection.
+///
+/// Without an interner (or when deserialized by another format), every value gets its own allocation.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct InternedStr(pub Arc<str>);
+
+impl Deref for InternedStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for InternedStr {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Hash for InternedStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl fmt::Display for InternedStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for InternedStr {
+    fn from(s: &str) -> Self {
+        Self(Arc::from(s))
+    }
+}
+
+/// The name used by `InternedStr` to request an interned value from the deserializer.
+pub(crate) const INTERNED_STR: &str = "$wmi::InternedStr";
+
+thread_local! {
+    /// An interned value, passed from the deserializer to the `InternedStr` visitor.
+    static INTERNED: RefCell<Option<Arc<str>>> = const { RefCell::new(None) };
+}
+
+/// Visit an interned value (which is passed to the visitor out-of-band).
+pub(crate) fn visit_interned<'de, V, E>(value: Arc<str>, visitor: V) -> Result<V::Value, E>
+where
+    V: de::Visitor<'de>,
+    E: de::Error,
+{
+    INTERNED.with(|interned| *interned.borrow_mut() = Some(value));
+
+    let res = visitor.visit_newtype_struct(de::value::UnitDeserializer::<E>::new());
+
+    // In case the visitor is not the `InternedStr` visitor.
+    INTERNED.with(|interned| interned.borrow_mut().take());
+
+    res
+}
+
+struct InternedStrVisitor;
+
+impl<'de> de::Visitor<'de> for InternedStrVisitor {
+    type Value = InternedStr;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a string")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match INTERNED.with(|interned| interned.borrow_mut().take()) {
+            Some(value) => Ok(InternedStr(value)),
+            None => {
+                de::Deserialize::deserialize(deserializer).map(|s: String| InternedStr::from(&*s))
+            }
+        }
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(InternedStr::from(value))
+    }
+}
+
+impl<'de> de::Deserialize<'de> for InternedStr {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(INTERNED_STR, InternedStrVisitor)
+    }
+}
+
+impl ser::Serialize for InternedStr {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn it_interns_strings() {
+        let interner = StringInterner::new();
+
+        let a = interner.intern("OK");
+        let b = interner.intern("OK");
+        let c = interner.intern("Degraded");
+
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert_eq!(interner.len(), 2);
+
+        interner.clear();
+        assert!(interner.is_empty());
+        assert_eq!(&*a, "OK");
+    }
+
+    #[test]
+    fn it_desr_without_an_interner() {
+        let s: InternedStr = serde_json::from_str("\"OK\"").unwrap();
+
+        assert_eq!(&*s, "OK");
+        assert_eq!(serde_json::to_string(&s).unwrap(), "\"OK\"");
+    }
+
+    #[test]
+    fn it_visits_interned_values() {
+        let interner = StringInterner::new();
+        let value = interner.intern("OK");
+
+        let s: InternedStr =
+            visit_interned::<_, serde::de::value::Error>(value.clone(), InternedStrVisitor)
+                .unwrap();
+
+        assert!(Arc::ptr_eq(&s.0, &value));
+    }
+
+    #[test]
+    fn it_shares_repeated_property_values() {
+        use crate::{de::options::DeserializeOptions, tests::fixtures::*};
+        use serde::Deserialize;
+
+        #[derive(Deserialize, Debug)]
+        struct Win32_Service {
+            StartMode: InternedStr,
+        }
+
+        let interner = StringInterner::new();
+        let wmi_con = wmi_con()
+            .with_deserialize_options(DeserializeOptions::new().interner(interner.clone()));
+
+        let services: Vec<Win32_Service> = wmi_con.query().unwrap();
+
+        assert!(interner.len() < services.len());
+        for service in &services {
+            assert!(Arc::ptr_eq(
+                &service.StartMode.0,
+                &interner.intern(&service.StartMode)
+            ));
+        }
+    }
+}
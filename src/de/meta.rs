@@ -1,6 +1,9 @@
 use serde::de::{self, value::Error, Deserialize, Deserializer, Visitor};
 use serde::forward_to_deserialize_any;
 
+/// A fields list which selects all of the properties of a class (used by [`WithExtra`](crate::WithExtra)).
+pub(crate) const ALL_PROPERTIES: &[&str] = &["*"];
+
 /// Return the fields of a struct.
 /// Taken directly from <https://github.com/serde-rs/serde/issues/1110>
 ///
@@ -48,9 +51,23 @@ where
             self.deserialize_any(visitor)
         }
 
+        // Unit structs only map a class name, without any properties.
+        fn deserialize_unit_struct<V>(
+            self,
+            name: &'static str,
+            visitor: V,
+        ) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            *self.name = Some(name);
+            *self.fields = Some(&[]);
+            self.deserialize_any(visitor)
+        }
+
         forward_to_deserialize_any! {
             bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
-            byte_buf option unit unit_struct seq tuple
+            byte_buf option unit seq tuple
             tuple_struct map enum identifier ignored_any
         }
     }
@@ -65,11 +82,14 @@ where
 
     match name {
         None =>  Err(de::Error::custom("Expected a named struct. \
-            Hint: You cannot use a HashMap<...> in this context because it requires the struct to have a name")),
+            Hint: You cannot use a HashMap<...> (or a struct with #[serde(flatten)] fields) in this context \
+            because it requires the struct to have a name. Use `raw_query` instead")),
         Some(name) => {
             validate_identifier(name)?;
-            for field in fields.into_iter().flatten() {
-                validate_identifier(field)?;
+            if fields != Some(ALL_PROPERTIES) {
+                for field in fields.into_iter().flatten() {
+                    validate_identifier(field)?;
+                }
             }
 
             Ok((name, fields.unwrap()))
@@ -87,7 +107,7 @@ where
 /// > All following characters must be in set S2 where S2 = S1 union {U+0030...U+0039} \[This is alphabetic, underscore, plus Arabic numerals 0 through 9.\]<br>
 ///
 /// [DMTF-DSP0004]:     https://www.dmtf.org/sites/default/files/standards/documents/DSP0004V2.3_final.pdf
-fn validate_identifier<E: de::Error>(s: &str) -> Result<&str, E> {
+pub(crate) fn validate_identifier<E: de::Error>(s: &str) -> Result<&str, E> {
     fn is_s1(ch: char) -> bool {
         match ch {
             '\u{005f}' => true,
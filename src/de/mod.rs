@@ -1,3 +1,11 @@
+pub mod extra;
+pub mod interned;
 pub mod meta;
+pub(crate) mod numeric;
+pub mod options;
+pub mod os_str;
+pub(crate) mod owned;
+pub mod property_cache;
+pub(crate) mod property_de;
 pub mod variant_de;
 pub mod wbem_class_de;
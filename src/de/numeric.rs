@@ -0,0 +1,112 @@
+use crate::Variant;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum NumberKind {
+    Signed,
+    Unsigned,
+    Float,
+}
+
+/// The type of a number, either of a property's value or of a field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct NumberType {
+    pub(crate) kind: NumberKind,
+    pub(crate) bits: u32,
+}
+
+impl NumberType {
+    pub(crate) const fn new(kind: NumberKind, bits: u32) -> Self {
+        NumberType { kind, bits }
+    }
+
+    /// Whether every value of this type can be represented by `target`.
+    pub(crate) fn widens_to(self, target: NumberType) -> bool {
+        use NumberKind::*;
+
+        // The number of bits which a float can represent exactly.
+        let mantissa_bits = match target.bits {
+            32 => 24,
+            _ => 53,
+        };
+
+        match (self.kind, target.kind) {
+            (Signed, Signed) | (Unsigned, Unsigned) | (Float, Float) => self.bits <= target.bits,
+            (Unsigned, Signed) => self.bits < target.bits,
+            (Signed, Unsigned) => false,
+            (Signed, Float) | (Unsigned, Float) => self.bits < mantissa_bits,
+            (Float, Signed) | (Float, Unsigned) => false,
+        }
+    }
+}
+
+/// The value of a numeric property.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Number {
+    Signed(i64, u32),
+    Unsigned(u64, u32),
+    Float(f64, u32),
+}
+
+impl Number {
+    /// Returns `None` if the variant is not a number.
+    pub(crate) fn from_variant(value: &Variant) -> Option<Number> {
+        let number = match *value {
+            Variant::I1(n) => Number::Signed(n.into(), 8),
+            Variant::I2(n) => Number::Signed(n.into(), 16),
+            Variant::I4(n) => Number::Signed(n.into(), 32),
+            Variant::I8(n) => Number::Signed(n, 64),
+            Variant::UI1(n) => Number::Unsigned(n.into(), 8),
+            Variant::UI2(n) => Number::Unsigned(n.into(), 16),
+            Variant::UI4(n) => Number::Unsigned(n.into(), 32),
+            Variant::UI8(n) => Number::Unsigned(n, 64),
+            Variant::R4(f) => Number::Float(f.into(), 32),
+            Variant::R8(f) => Number::Float(f, 64),
+            _ => return None,
+        };
+
+        Some(number)
+    }
+
+    pub(crate) fn number_type(self) -> NumberType {
+        match self {
+            Number::Signed(_, bits) => NumberType::new(NumberKind::Signed, bits),
+            Number::Unsigned(_, bits) => NumberType::new(NumberKind::Unsigned, bits),
+            Number::Float(_, bits) => NumberType::new(NumberKind::Float, bits),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use NumberKind::*;
+
+    #[test]
+    fn it_widens_numbers() {
+        let u32_ty = NumberType::new(Unsigned, 32);
+
+        assert!(u32_ty.widens_to(NumberType::new(Unsigned, 64)));
+        assert!(u32_ty.widens_to(NumberType::new(Signed, 64)));
+        assert!(u32_ty.widens_to(NumberType::new(Float, 64)));
+        assert!(!u32_ty.widens_to(NumberType::new(Signed, 32)));
+        assert!(!u32_ty.widens_to(NumberType::new(Unsigned, 16)));
+        assert!(!u32_ty.widens_to(NumberType::new(Float, 32)));
+
+        assert!(!NumberType::new(Signed, 8).widens_to(NumberType::new(Unsigned, 64)));
+        assert!(NumberType::new(Float, 32).widens_to(NumberType::new(Float, 64)));
+        assert!(!NumberType::new(Float, 32).widens_to(NumberType::new(Signed, 64)));
+    }
+
+    #[test]
+    fn it_reads_numbers_from_variants() {
+        assert_eq!(
+            Number::from_variant(&Variant::UI4(7)),
+            Some(Number::Unsigned(7, 32))
+        );
+        assert_eq!(
+            Number::from_variant(&Variant::I2(-7)),
+            Some(Number::Signed(-7, 16))
+        );
+        assert_eq!(Number::from_variant(&Variant::String("7".into())), None);
+    }
+}
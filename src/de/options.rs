@@ -0,0 +1,134 @@
+use crate::de::{interned::StringInterner, property_cache::PropertyCache};
+
+/// Options which control how WMI objects are deserialized.
+///
+/// Options are set per connection, and can be changed for a single query using
+/// [`WMIConnection::with_deserialize_options`](crate::WMIConnection::with_deserialize_options).
+///
+/// ```edition2018
+/// # fn main() -> wmi::WMIResult<()> {
+/// # use wmi::*;
+/// # use std::collections::HashMap;
+/// # let con = WMIConnection::new(COMLibrary::new()?)?;
+/// use wmi::de::options::DeserializeOptions;
+///
+/// let strict = con.with_deserialize_options(DeserializeOptions::new().strict(true));
+/// let results: Vec<HashMap<String, Variant>> = strict.raw_query("SELECT Name FROM Win32_OperatingSystem")?;
+/// #   Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DeserializeOptions {
+    pub(crate) strict: bool,
+    pub(crate) numeric_coercion: NumericCoercion,
+    pub(crate) utf16_conversion: Utf16Conversion,
+    pub(crate) interner: Option<StringInterner>,
+    pub(crate) property_cache: Option<PropertyCache>,
+}
+
+/// How string properties which are not valid UTF-16 (like file names with unpaired surrogates) are converted.
+///
+/// `OsString` fields always preserve the original data, regardless of this setting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Utf16Conversion {
+    /// Fail with a [`WMIError::ConvertStringError`](crate::WMIError::ConvertStringError)
+    /// (wrapped in a property error), so data is never silently changed. This is the default.
+    #[default]
+    Strict,
+    /// Replace invalid sequences with `U+FFFD REPLACEMENT CHARACTER`, like `String::from_utf16_lossy`.
+    Lossy,
+}
+
+/// How numeric properties are converted when their type differs from the type of the field.
+///
+/// Some providers report a different type than the one in the class' schema
+/// (or the struct simply uses a different type, like `u64` for a `uint32` property),
+/// and the policy makes the conversion predictable.
+///
+/// Under the lenient [`Lossy`](NumericCoercion::Lossy) policy, `bool` fields also accept integers
+/// (any non-zero value is `true`) and `"TRUE"` / `"FALSE"` strings, which some providers return instead of booleans.
+///
+/// ```edition2018
+/// # fn main() -> wmi::WMIResult<()> {
+/// # use wmi::*;
+/// # let con = WMIConnection::new(COMLibrary::new()?)?;
+/// use serde::Deserialize;
+/// use wmi::de::options::{DeserializeOptions, NumericCoercion};
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Win32_OperatingSystem {
+///     // `NumberOfProcesses` is a `uint32`.
+///     NumberOfProcesses: u64,
+/// }
+///
+/// let con = con.with_deserialize_options(
+///     DeserializeOptions::new().numeric_coercion(NumericCoercion::WidenOnly),
+/// );
+/// let os: Win32_OperatingSystem = con.get()?;
+/// #   Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NumericCoercion {
+    /// The type of the field must match the type of the property exactly (a `uint32` can only be a `u32`).
+    Strict,
+    /// Only conversions which never lose information are allowed (a `uint32` can be a `u64`, `i64` or `f64`, but not an `i32`).
+    WidenOnly,
+    /// Any conversion is allowed, as long as the specific value fits in the type of the field
+    /// (a `uint32` can be an `i32` if it is smaller than `i32::MAX`).
+    /// This is the default, which keeps how numbers were converted before the policy could be set.
+    #[default]
+    InRange,
+    /// Any conversion is allowed, and values which do not fit are converted using `as`,
+    /// which wraps integers and saturates floats. `bool` fields also accept integers and `"TRUE"` / `"FALSE"` strings.
+    Lossy,
+}
+
+impl DeserializeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// In strict mode, deserializing a struct fails if:
+    /// - The object has (non-null, non-system) properties which are not mapped by the struct's fields,
+    ///   with a [`WMIError::UnmappedPropertiesError`](crate::WMIError::UnmappedPropertiesError).
+    /// - A field is not a property of the object,
+    ///   with a [`WMIError::PropertyDeserializationError`](crate::WMIError::PropertyDeserializationError).
+    ///
+    /// Properties which cannot be converted into the type of their field are an error in any mode.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Set how invalid UTF-16 data in string properties is converted, see [`Utf16Conversion`].
+    pub fn utf16_conversion(mut self, utf16_conversion: Utf16Conversion) -> Self {
+        self.utf16_conversion = utf16_conversion;
+        self
+    }
+
+    /// Share the values of [`InternedStr`](crate::de::interned::InternedStr) fields using the given interner.
+    pub fn interner(mut self, interner: StringInterner) -> Self {
+        self.interner = Some(interner);
+        self
+    }
+
+    /// Cache the property names of classes using the given cache, see [`PropertyCache`].
+    pub fn property_cache(mut self, property_cache: PropertyCache) -> Self {
+        self.property_cache = Some(property_cache);
+        self
+    }
+
+    /// Set the policy for converting numeric properties, see [`NumericCoercion`].
+    pub fn numeric_coercion(mut self, numeric_coercion: NumericCoercion) -> Self {
+        self.numeric_coercion = numeric_coercion;
+        self
+    }
+}
@@ -0,0 +1,40 @@
+use serde::de::{Deserialize, Deserializer};
+use std::{ffi::OsString, path::PathBuf};
+
+/// Deserialize a string property into a `PathBuf`, preserving data which is not valid UTF-16
+/// (like file names with unpaired surrogates).
+///
+/// `OsString` fields preserve such data by default, but `serde` deserializes `PathBuf` from a `String`,
+/// so `PathBuf` fields should use this function:
+///
+/// ```edition2018
+/// # fn main() -> wmi::WMIResult<()> {
+/// # use wmi::*;
+/// # let con = WMIConnection::new(COMLibrary::new()?)?;
+/// use serde::Deserialize;
+/// use std::path::PathBuf;
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Win32_Process {
+///     #[serde(default, deserialize_with = "wmi::de::os_str::deserialize_path_buf_opt")]
+///     ExecutablePath: Option<PathBuf>,
+/// }
+///
+/// let procs: Vec<Win32_Process> = con.query()?;
+/// #   Ok(())
+/// # }
+/// ```
+pub fn deserialize_path_buf<'de, D>(deserializer: D) -> Result<PathBuf, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    OsString::deserialize(deserializer).map(PathBuf::from)
+}
+
+/// Like [`deserialize_path_buf`], for optional properties.
+pub fn deserialize_path_buf_opt<'de, D>(deserializer: D) -> Result<Option<PathBuf>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<OsString>::deserialize(deserializer).map(|path| path.map(PathBuf::from))
+}
@@ -0,0 +1,693 @@
+//! Owned instances, which are deserialized like the objects returned by the COM API.
+use crate::{
+    de::{
+        interned::{self, INTERNED_STR},
+        meta::ALL_PROPERTIES,
+        numeric::{Number, NumberKind, NumberType},
+        options::{DeserializeOptions, NumericCoercion},
+        property_de::coerce_bool_str,
+    },
+    utils::PropertyError,
+    Variant, WMIError, WMIResult,
+};
+use serde::{
+    de::{self, IntoDeserializer},
+    forward_to_deserialize_any,
+};
+use std::vec::IntoIter;
+
+/// The value of a property of an owned [`Object`].
+#[derive(Debug, PartialEq)]
+pub(crate) enum Value {
+    Null,
+    /// A value from a protocol which does not carry the CIM types of properties (like WS-Management),
+    /// which is parsed into the type requested by the deserializer.
+    #[cfg_attr(not(feature = "wsman"), allow(dead_code))]
+    Text(String),
+    /// A typed value, which is deserialized like the values returned by the COM API.
+    Variant(Variant),
+    /// A property which appeared more than once. Arrays with a single item are indistinguishable
+    /// from scalars, which is why scalars can also be deserialized as sequences.
+    #[cfg_attr(not(any(feature = "wsman", feature = "mi")), allow(dead_code))]
+    Array(Vec<Value>),
+    Object(Object),
+}
+
+/// An instance (or an embedded instance) which is not backed by a COM object,
+/// as returned by the WS-Management, MI and mock transports. Properties are kept in the order they were returned.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Object {
+    pub(crate) class: String,
+    pub(crate) properties: Vec<(String, Value)>,
+}
+
+impl Object {
+    /// Copy the object, see [`Variant::duplicate`].
+    pub(crate) fn duplicate(&self) -> Self {
+        Self {
+            class: self.class.clone(),
+            properties: self
+                .properties
+                .iter()
+                .map(|(name, value)| (name.clone(), value.duplicate()))
+                .collect(),
+        }
+    }
+
+    /// Order the properties like the projection of the query (which is used to deserialize tuples).
+    /// Properties which are not part of the projection are dropped.
+    pub(crate) fn project(mut self, projection: &[String]) -> Self {
+        self.properties = projection
+            .iter()
+            .map(|name| {
+                let value = self
+                    .properties
+                    .iter_mut()
+                    .find(|(property, _)| property.eq_ignore_ascii_case(name))
+                    .map_or(Value::Null, |(_, value)| {
+                        std::mem::replace(value, Value::Null)
+                    });
+
+                (name.clone(), value)
+            })
+            .collect();
+
+        self
+    }
+}
+
+/// Deserializes an owned [`Value`] using the options of a connection, like the COM deserializer.
+///
+/// Numeric coercion and bool coercion only apply to typed values: text values (from WS-Management)
+/// are parsed into the requested type. UTF-16 conversion doesn't apply, since the strings are already valid.
+pub(crate) struct ValueDeserializer<'a> {
+    value: Value,
+    options: &'a DeserializeOptions,
+}
+
+impl<'a> ValueDeserializer<'a> {
+    pub(crate) fn new(value: Value, options: &'a DeserializeOptions) -> Self {
+        Self { value, options }
+    }
+}
+
+struct SeqAccess<'a> {
+    data: IntoIter<Value>,
+    options: &'a DeserializeOptions,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for SeqAccess<'a> {
+    type Error = WMIError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.data.next() {
+            Some(value) => seed
+                .deserialize(ValueDeserializer::new(value, self.options))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+struct MapAccess<'a> {
+    data: IntoIter<(String, Value)>,
+    value: Option<Value>,
+    options: &'a DeserializeOptions,
+}
+
+impl<'a> MapAccess<'a> {
+    fn new(object: Object, options: &'a DeserializeOptions) -> Self {
+        Self {
+            data: object.properties.into_iter(),
+            value: None,
+            options,
+        }
+    }
+}
+
+impl<'de, 'a> de::MapAccess<'de> for MapAccess<'a> {
+    type Error = WMIError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match self.data.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(value) => seed.deserialize(ValueDeserializer::new(value, self.options)),
+            None => Err(de::Error::custom(
+                "next_value_seed called before next_key_seed",
+            )),
+        }
+    }
+}
+
+/// Deserializes an object as an enum variant named after its class, like the COM deserializer.
+struct EnumAccess<'a> {
+    object: Object,
+    options: &'a DeserializeOptions,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for EnumAccess<'a> {
+    type Error = WMIError;
+    type Variant = ValueDeserializer<'a>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(IntoDeserializer::<'_, WMIError>::into_deserializer(
+            self.object.class.clone(),
+        ))?;
+
+        Ok((
+            variant,
+            ValueDeserializer::new(Value::Object(self.object), self.options),
+        ))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for ValueDeserializer<'a> {
+    type Error = WMIError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+/// Parse text into the requested type, and fall back to visiting the text
+/// (which is required by types like `WMIDateTime`, which are deserialized from strings using any hint).
+///
+/// Typed numbers are converted using the [`NumericCoercion`] policy, like the COM deserializer.
+macro_rules! deserialize_number {
+    ($($method:ident => $ty:ty, $visit:ident, $kind:ident, $bits:expr;)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: de::Visitor<'de>,
+            {
+                let Self { value, options } = self;
+
+                let variant = match value {
+                    Value::Text(text) => {
+                        return match text.trim().parse() {
+                            Ok(value) => visitor.$visit(value),
+                            Err(_) => visitor.visit_string(text),
+                        }
+                    }
+                    Value::Variant(variant) => variant,
+                    value => return Self::new(value, options).deserialize_any(visitor),
+                };
+
+                let number = match Number::from_variant(&variant) {
+                    Some(number) => number,
+                    None => return variant.$method(visitor),
+                };
+
+                let policy = options.numeric_coercion;
+                let target = NumberType::new(NumberKind::$kind, $bits);
+
+                let allowed = match policy {
+                    NumericCoercion::Strict => number.number_type() == target,
+                    NumericCoercion::WidenOnly => number.number_type().widens_to(target),
+                    NumericCoercion::InRange => true,
+                    NumericCoercion::Lossy => {
+                        let value = match number {
+                            Number::Signed(n, _) => n as $ty,
+                            Number::Unsigned(n, _) => n as $ty,
+                            Number::Float(f, _) => f as $ty,
+                        };
+
+                        return visitor.$visit(value);
+                    }
+                };
+
+                if !allowed {
+                    return Err(<WMIError as de::Error>::custom(format_args!(
+                        "The conversion is not allowed by the {:?} numeric coercion policy",
+                        policy
+                    )));
+                }
+
+                variant.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl Object {
+    /// Fail like the COM deserializer does in strict mode, see [`DeserializeOptions::strict`].
+    fn check_strict(&self, fields: &[&str]) -> WMIResult<()> {
+        let unmapped: Vec<String> = self
+            .properties
+            .iter()
+            .filter(|(property, value)| {
+                !matches!(
+                    value,
+                    Value::Null | Value::Variant(Variant::Null | Variant::Empty)
+                ) && !fields
+                    .iter()
+                    .any(|field| field.eq_ignore_ascii_case(property))
+            })
+            .map(|(property, _)| property.clone())
+            .collect();
+
+        if !unmapped.is_empty() {
+            return Err(WMIError::UnmappedPropertiesError {
+                class: self.class.clone(),
+                properties: unmapped,
+            });
+        }
+
+        let missing = fields.iter().find(|field| {
+            !self
+                .properties
+                .iter()
+                .any(|(property, _)| field.eq_ignore_ascii_case(property))
+        });
+
+        match missing {
+            Some(field) => Err(WMIError::PropertyDeserializationError(Box::new(
+                PropertyError {
+                    class: self.class.clone(),
+                    property: field.to_string(),
+                    cim_type: String::from("none"),
+                    variant_type: "none",
+                    expected: "an existing property",
+                    message: String::from("The property does not exist"),
+                },
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Value {
+    /// Copy the value, see [`Variant::duplicate`].
+    pub(crate) fn duplicate(&self) -> Self {
+        match self {
+            Value::Null => Value::Null,
+            Value::Text(text) => Value::Text(text.clone()),
+            Value::Variant(variant) => Value::Variant(variant.duplicate()),
+            Value::Array(values) => Value::Array(values.iter().map(Value::duplicate).collect()),
+            Value::Object(object) => Value::Object(object.duplicate()),
+        }
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ValueDeserializer<'a> {
+    type Error = WMIError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            Value::Text(text) => visitor.visit_string(text),
+            Value::Array(items) => visitor.visit_seq(SeqAccess {
+                data: items.into_iter(),
+                options: self.options,
+            }),
+            Value::Object(object) => visitor.visit_map(MapAccess::new(object, self.options)),
+            Value::Variant(variant) => variant.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let Self { value, options } = self;
+
+        // Like the COM deserializer, only the lenient policy coerces other values.
+        let lenient = options.numeric_coercion == NumericCoercion::Lossy;
+
+        match value {
+            Value::Text(text) if text.eq_ignore_ascii_case("true") => visitor.visit_bool(true),
+            Value::Text(text) if text.eq_ignore_ascii_case("false") => visitor.visit_bool(false),
+            Value::Text(text) | Value::Variant(Variant::String(text)) if lenient => {
+                match coerce_bool_str(&text) {
+                    Some(b) => visitor.visit_bool(b),
+                    None => visitor.visit_string(text),
+                }
+            }
+            Value::Variant(variant) if lenient => match Number::from_variant(&variant) {
+                Some(Number::Signed(n, _)) => visitor.visit_bool(n != 0),
+                Some(Number::Unsigned(n, _)) => visitor.visit_bool(n != 0),
+                _ => variant.deserialize_bool(visitor),
+            },
+            other => Self::new(other, options).deserialize_any(visitor),
+        }
+    }
+
+    deserialize_number! {
+        deserialize_i8 => i8, visit_i8, Signed, 8;
+        deserialize_i16 => i16, visit_i16, Signed, 16;
+        deserialize_i32 => i32, visit_i32, Signed, 32;
+        deserialize_i64 => i64, visit_i64, Signed, 64;
+        deserialize_u8 => u8, visit_u8, Unsigned, 8;
+        deserialize_u16 => u16, visit_u16, Unsigned, 16;
+        deserialize_u32 => u32, visit_u32, Unsigned, 32;
+        deserialize_u64 => u64, visit_u64, Unsigned, 64;
+        deserialize_f32 => f32, visit_f32, Float, 32;
+        deserialize_f64 => f64, visit_f64, Float, 64;
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.value {
+            Value::Null | Value::Variant(Variant::Null | Variant::Empty) => visitor.visit_none(),
+            some => visitor.visit_some(Self::new(some, self.options)),
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.value {
+            Value::Null => visitor.visit_unit(),
+            other => Self::new(other, self.options).deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if let (INTERNED_STR, Some(interner), Value::Text(s) | Value::Variant(Variant::String(s))) =
+            (name, &self.options.interner, &self.value)
+        {
+            let value = interner.intern(s);
+            return interned::visit_interned(value, visitor);
+        }
+
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let items = match self.value {
+            // Elements are deserialized with the same options as the property.
+            Value::Variant(Variant::Array(items)) => {
+                items.into_iter().map(Value::Variant).collect()
+            }
+            Value::Variant(variant) => return variant.deserialize_seq(visitor),
+            Value::Null => vec![],
+            Value::Array(items) => items,
+            // The properties of an object, in the order of the query's projection.
+            Value::Object(object) => object
+                .properties
+                .into_iter()
+                .map(|(_, value)| value)
+                .collect(),
+            single => vec![single],
+        };
+
+        visitor.visit_seq(SeqAccess {
+            data: items.into_iter(),
+            options: self.options,
+        })
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.value {
+            Value::Object(mut object) => {
+                if self.options.strict && fields != ALL_PROPERTIES {
+                    object.check_strict(fields)?;
+                }
+
+                // Property names are case-insensitive, so rename them to the fields they match.
+                for (property, _) in object.properties.iter_mut() {
+                    if let Some(field) = fields
+                        .iter()
+                        .find(|field| field.eq_ignore_ascii_case(property))
+                    {
+                        *property = field.to_string();
+                    }
+                }
+
+                visitor.visit_map(MapAccess::new(object, self.options))
+            }
+            other => Self::new(other, self.options).deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.value {
+            Value::Text(text) | Value::Variant(Variant::String(text)) => {
+                visitor.visit_enum(text.into_deserializer())
+            }
+            Value::Object(object) => visitor.visit_enum(EnumAccess {
+                object,
+                options: self.options,
+            }),
+            other => Self::new(other, self.options).deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit_struct map identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    fn deserialize<T: de::DeserializeOwned>(value: Value) -> WMIResult<T> {
+        T::deserialize(ValueDeserializer::new(
+            value,
+            &DeserializeOptions::default(),
+        ))
+    }
+
+    fn process() -> Value {
+        Value::Object(Object {
+            class: "Win32_Process".to_owned(),
+            properties: vec![
+                (
+                    "CreationDate".to_owned(),
+                    Value::Text("20240301100000.500000+120".to_owned()),
+                ),
+                ("ExecutablePath".to_owned(), Value::Null),
+                ("Name".to_owned(), Value::Text("System".to_owned())),
+                ("ProcessId".to_owned(), Value::Text("4".to_owned())),
+                (
+                    "WorkingSetSize".to_owned(),
+                    Value::Variant(Variant::UI8(155648)),
+                ),
+                (
+                    "Handles".to_owned(),
+                    Value::Variant(Variant::Array(vec![Variant::UI4(1), Variant::UI4(2)])),
+                ),
+            ],
+        })
+    }
+
+    #[test]
+    fn it_deserializes_structs() {
+        #[derive(Deserialize, Debug)]
+        #[serde(rename = "Win32_Process")]
+        #[serde(rename_all = "PascalCase")]
+        struct Process {
+            name: String,
+            process_id: u32,
+            working_set_size: u64,
+            executable_path: Option<String>,
+            #[serde(with = "crate::datetime::raw")]
+            creation_date: String,
+            handles: Vec<u32>,
+        }
+
+        let typed: Process = deserialize(process()).unwrap();
+
+        assert_eq!(typed.name, "System");
+        assert_eq!(typed.process_id, 4);
+        assert_eq!(typed.working_set_size, 155648);
+        assert_eq!(typed.executable_path, None);
+        assert_eq!(typed.creation_date, "20240301100000.500000+120");
+        assert_eq!(typed.handles, vec![1, 2]);
+
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "lowercase")]
+        struct LowercaseProcess {
+            name: String,
+            processid: u32,
+        }
+
+        let lowercase = deserialize::<LowercaseProcess>(process()).unwrap();
+        assert_eq!(
+            (lowercase.name.as_str(), lowercase.processid),
+            ("System", 4)
+        );
+
+        // A single item is also a sequence.
+        let names = deserialize::<Vec<String>>(Value::Text("System".to_owned())).unwrap();
+        assert_eq!(names, vec!["System"]);
+    }
+
+    #[test]
+    fn it_deserializes_maps_tuples_and_enums() {
+        let map = deserialize::<HashMap<String, Variant>>(process()).unwrap();
+        assert_eq!(map["Name"], Variant::String("System".to_owned()));
+        assert_eq!(map["ExecutablePath"], Variant::Null);
+        assert_eq!(map["WorkingSetSize"], Variant::UI8(155648));
+
+        let object = match process() {
+            Value::Object(object) => object,
+            _ => unreachable!(),
+        };
+        let projected = Value::Object(object.project(&["ProcessId".to_owned(), "name".to_owned()]));
+        let (pid, name) = deserialize::<(u32, String)>(projected).unwrap();
+        assert_eq!((pid, name.as_str()), (4, "System"));
+
+        #[allow(non_camel_case_types)]
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "PascalCase")]
+        struct Win32_Process {
+            process_id: u32,
+        }
+
+        #[allow(non_camel_case_types)]
+        #[derive(Deserialize, Debug)]
+        enum Instance {
+            Win32_Process(Win32_Process),
+        }
+
+        let Instance::Win32_Process(process) = deserialize::<Instance>(process()).unwrap();
+        assert_eq!(process.process_id, 4);
+
+        assert!(deserialize::<u32>(Value::Text("System".to_owned())).is_err());
+    }
+
+    #[test]
+    fn it_uses_the_deserialize_options() {
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "PascalCase")]
+        struct Process {
+            name: String,
+        }
+
+        let strict = DeserializeOptions::new().strict(true);
+        assert!(matches!(
+            Process::deserialize(ValueDeserializer::new(process(), &strict)),
+            Err(WMIError::UnmappedPropertiesError { .. })
+        ));
+        assert_eq!(deserialize::<Process>(process()).unwrap().name, "System");
+
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "PascalCase")]
+        struct Sizes {
+            working_set_size: i32,
+            handles: Vec<u64>,
+        }
+
+        let sizes = |numeric_coercion| {
+            let options = DeserializeOptions::new().numeric_coercion(numeric_coercion);
+            Sizes::deserialize(ValueDeserializer::new(process(), &options))
+        };
+
+        let in_range = sizes(NumericCoercion::InRange).unwrap();
+        assert_eq!(
+            (in_range.working_set_size, in_range.handles),
+            (155648, vec![1, 2])
+        );
+        assert!(sizes(NumericCoercion::WidenOnly).is_err());
+
+        let flag = |value, numeric_coercion| {
+            let options = DeserializeOptions::new().numeric_coercion(numeric_coercion);
+            bool::deserialize(ValueDeserializer::new(value, &options))
+        };
+
+        assert!(flag(Value::Text("true".to_owned()), NumericCoercion::InRange).unwrap());
+        assert!(flag(Value::Variant(Variant::UI1(1)), NumericCoercion::InRange).is_err());
+        assert!(flag(Value::Variant(Variant::UI1(1)), NumericCoercion::Lossy).unwrap());
+    }
+}
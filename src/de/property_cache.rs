@@ -0,0 +1,147 @@
+use crate::{result_enumerator::IWbemClassWrapper, Variant, WMIResult};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+/// A cache of the property names of classes, shared between queries.
+///
+/// Deserializing maps (and checking for unmapped properties in strict mode) requires the names of the properties
+/// of every object, which are otherwise listed using `GetNames` for each one.
+/// With a cache, they are only listed once per class (and set of selected properties), which helps tight polling loops.
+///
+/// ```edition2018
+/// # fn main() -> wmi::WMIResult<()> {
+/// # use wmi::*;
+/// # use std::collections::HashMap;
+/// # let con = WMIConnection::new(COMLibrary::new()?)?;
+/// use wmi::de::options::DeserializeOptions;
+/// use wmi::de::property_cache::PropertyCache;
+///
+/// let cache = PropertyCache::new();
+/// let con = con.with_deserialize_options(DeserializeOptions::new().property_cache(cache.clone()));
+///
+/// for _ in 0..3 {
+///     let procs: Vec<HashMap<String, Variant>> = con.raw_query("SELECT Name, ProcessId FROM Win32_Process")?;
+/// }
+///
+/// assert_eq!(cache.len(), 1);
+/// #   Ok(())
+/// # }
+/// ```
+///
+/// Entries are never invalidated, so the cache should be cleared if the schema of a cached class is changed.
+/// Like the [`StringInterner`](crate::de::interned::StringInterner), the cache can be shared between connections (and threads),
+/// and is cheap to clone.
+#[derive(Clone, Default)]
+pub struct PropertyCache {
+    classes: Arc<Mutex<HashMap<CacheKey, Arc<[String]>>>>,
+}
+
+/// The namespace and class of the objects, and the properties selected by the query (if any).
+type CacheKey = (String, String, Option<Vec<String>>);
+
+impl PropertyCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the property names of the object, listing them only if its class was not seen yet.
+    pub(crate) fn property_names(
+        &self,
+        obj: &IWbemClassWrapper,
+        projection: Option<&[String]>,
+    ) -> WMIResult<Arc<[String]>> {
+        let namespace = match obj.get_property("__NAMESPACE")? {
+            Variant::String(namespace) => namespace.to_ascii_lowercase(),
+            _ => String::new(),
+        };
+        let key = (
+            namespace,
+            obj.class()?.to_ascii_lowercase(),
+            projection.map(|projection| projection.to_vec()),
+        );
+
+        if let Some(names) = self.lock().get(&key) {
+            return Ok(names.clone());
+        }
+
+        let names: Arc<[String]> = obj.list_properties()?.into();
+        self.lock().insert(key, names.clone());
+
+        Ok(names)
+    }
+
+    /// The number of cached classes.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all the cached classes.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<CacheKey, Arc<[String]>>> {
+        self.classes.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl fmt::Debug for PropertyCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PropertyCache")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Two caches are equal if they share the same entries.
+impl PartialEq for PropertyCache {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.classes, &other.classes)
+    }
+}
+
+impl Eq for PropertyCache {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::de::options::DeserializeOptions;
+    use crate::tests::fixtures::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn it_caches_property_names_per_class_and_projection() {
+        let cache = PropertyCache::new();
+        let cached_con = wmi_con()
+            .with_deserialize_options(DeserializeOptions::new().property_cache(cache.clone()));
+
+        let uncached: Vec<HashMap<String, Variant>> = wmi_con()
+            .raw_query("SELECT * FROM Win32_OperatingSystem")
+            .unwrap();
+        assert!(cache.is_empty());
+
+        for _ in 0..2 {
+            let results: Vec<HashMap<String, Variant>> = cached_con
+                .raw_query("SELECT * FROM Win32_OperatingSystem")
+                .unwrap();
+            assert_eq!(results[0].len(), uncached[0].len());
+        }
+        assert_eq!(cache.len(), 1);
+
+        let results: Vec<HashMap<String, Variant>> = cached_con
+            .raw_query("SELECT Caption FROM Win32_OperatingSystem")
+            .unwrap();
+        assert_eq!(results[0].len(), 1);
+        assert_eq!(cache.len(), 2);
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
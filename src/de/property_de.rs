@@ -0,0 +1,678 @@
+use crate::{
+    de::{
+        interned::{self, INTERNED_STR},
+        numeric::{Number, NumberKind, NumberType},
+        options::{DeserializeOptions, NumericCoercion, Utf16Conversion},
+        wbem_class_de::Deserializer,
+    },
+    result_enumerator::IWbemClassWrapper,
+    utils::PropertyError,
+    Variant, WMIError,
+};
+use serde::de::{self, Visitor};
+use std::fmt::Display;
+use windows::Win32::System::Wmi::{self, CIMTYPE_ENUMERATION};
+
+/// Deserializes the value of a single property, adding the property's details to errors.
+pub(crate) struct PropertyDeserializer<'a> {
+    pub(crate) value: Variant,
+    /// The raw data of a string property which is not valid UTF-16 (`value` is `Null` in that case).
+    ///
+    /// `OsString` fields (and `PathBuf` fields, using [`deserialize_path_buf`](crate::de::os_str::deserialize_path_buf))
+    /// keep the raw data, and other fields use the [`Utf16Conversion`] option.
+    pub(crate) invalid_utf16: Option<Vec<u16>>,
+    pub(crate) cim_type: CIMTYPE_ENUMERATION,
+    pub(crate) obj: &'a IWbemClassWrapper,
+    pub(crate) property: &'a str,
+    pub(crate) options: &'a DeserializeOptions,
+}
+
+macro_rules! forward_with_context {
+    ($($method:ident => $expected:expr),* $(,)?) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                let (value, context) = self.take($expected)?;
+                value.$method(visitor).map_err(|err| context.wrap(err))
+            }
+        )*
+    };
+}
+
+macro_rules! deserialize_number {
+    ($($method:ident => $ty:ty, $visit:ident, $kind:ident, $bits:expr);* $(;)?) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                let (value, context) = self.take(stringify!($ty))?;
+                let target = NumberType::new(NumberKind::$kind, $bits);
+
+                let number = match Number::from_variant(&value) {
+                    Some(number) => number,
+                    None => return value.$method(visitor).map_err(|err| context.wrap(err)),
+                };
+
+                let policy = context.options.numeric_coercion;
+
+                let allowed = match policy {
+                    NumericCoercion::Strict => number.number_type() == target,
+                    NumericCoercion::WidenOnly => number.number_type().widens_to(target),
+                    NumericCoercion::InRange => true,
+                    NumericCoercion::Lossy => {
+                        let value = match number {
+                            Number::Signed(n, _) => n as $ty,
+                            Number::Unsigned(n, _) => n as $ty,
+                            Number::Float(f, _) => f as $ty,
+                        };
+
+                        return visitor.$visit(value).map_err(|err| context.wrap(err));
+                    }
+                };
+
+                if !allowed {
+                    return Err(context.wrap(<WMIError as de::Error>::custom(format_args!(
+                        "The conversion is not allowed by the {:?} numeric coercion policy",
+                        policy
+                    ))));
+                }
+
+                value.$method(visitor).map_err(|err| context.wrap(err))
+            }
+        )*
+    };
+}
+
+/// Deserializes the elements of an array property.
+struct ElementsAccess<'a> {
+    elements: std::vec::IntoIter<Variant>,
+    cim_type: CIMTYPE_ENUMERATION,
+    obj: &'a IWbemClassWrapper,
+    property: &'a str,
+    options: &'a DeserializeOptions,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for ElementsAccess<'a> {
+    type Error = WMIError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.elements.next() {
+            Some(value) => seed
+                .deserialize(PropertyDeserializer {
+                    value,
+                    invalid_utf16: None,
+                    cim_type: self.cim_type,
+                    obj: self.obj,
+                    property: self.property,
+                    options: self.options,
+                })
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.len())
+    }
+}
+
+/// The details of a property, used to create errors after the value was consumed.
+struct Context<'a> {
+    obj: &'a IWbemClassWrapper,
+    property: &'a str,
+    cim_type: CIMTYPE_ENUMERATION,
+    options: &'a DeserializeOptions,
+    variant_type: &'static str,
+    expected: &'static str,
+}
+
+impl<'a> Context<'a> {
+    fn wrap(self, err: WMIError) -> WMIError {
+        match err {
+            // Keep the context of the innermost property.
+            err @ WMIError::PropertyDeserializationError(_) => err,
+            err @ WMIError::UnmappedPropertiesError { .. } => err,
+            err => WMIError::PropertyDeserializationError(Box::new(PropertyError {
+                class: self.obj.class().unwrap_or_default(),
+                property: self.property.to_owned(),
+                cim_type: cim_type_name(self.cim_type),
+                variant_type: self.variant_type,
+                expected: self.expected,
+                message: err.to_string(),
+            })),
+        }
+    }
+}
+
+impl<'a> PropertyDeserializer<'a> {
+    fn context(&self, expected: &'static str) -> Context<'a> {
+        Context {
+            obj: self.obj,
+            property: self.property,
+            cim_type: self.cim_type,
+            options: self.options,
+            variant_type: match self.invalid_utf16 {
+                Some(_) => "String",
+                None => variant_type_name(&self.value),
+            },
+            expected,
+        }
+    }
+
+    /// Split the value from its context, converting strings which are not valid UTF-16
+    /// according to the [`Utf16Conversion`] option.
+    fn take(self, expected: &'static str) -> Result<(Variant, Context<'a>), WMIError> {
+        let context = self.context(expected);
+
+        match self.invalid_utf16 {
+            Some(wide) if self.options.utf16_conversion == Utf16Conversion::Lossy => {
+                Ok((Variant::String(String::from_utf16_lossy(&wide)), context))
+            }
+            Some(wide) => Err(context.wrap(match String::from_utf16(&wide) {
+                Err(err) => WMIError::ConvertStringError(err),
+                Ok(_) => WMIError::SerdeError("Expected invalid UTF-16 data".into()),
+            })),
+            None => Ok((self.value, context)),
+        }
+    }
+
+    fn custom_error(&self, expected: &'static str, msg: impl Display) -> WMIError {
+        self.context(expected)
+            .wrap(<WMIError as de::Error>::custom(msg))
+    }
+}
+
+/// The way `serde` represents an `OsString`: An enum with a `Windows(Vec<u16>)` variant.
+const OS_STRING: &str = "OsString";
+const OS_STRING_VARIANTS: &[&str] = &["Unix", "Windows"];
+
+impl<'a> PropertyDeserializer<'a> {
+    /// Deserialize the raw UTF-16 data of a string, which preserves data which is not valid UTF-16.
+    fn deserialize_os_string<'de, V>(self, visitor: V) -> Result<V::Value, WMIError>
+    where
+        V: Visitor<'de>,
+    {
+        let context = self.context("OsString");
+
+        let wide = match (self.invalid_utf16, self.value) {
+            (Some(wide), _) => wide,
+            (None, Variant::String(s)) => s.encode_utf16().collect(),
+            (None, value) => {
+                return de::Deserializer::deserialize_enum(
+                    value,
+                    OS_STRING,
+                    OS_STRING_VARIANTS,
+                    visitor,
+                )
+                .map_err(|err| context.wrap(err))
+            }
+        };
+
+        visitor
+            .visit_enum(WideStringAccess(wide))
+            .map_err(|err| context.wrap(err))
+    }
+}
+
+/// Provides the `Windows` variant of an `OsString`.
+struct WideStringAccess(Vec<u16>);
+
+impl<'de> de::EnumAccess<'de> for WideStringAccess {
+    type Error = WMIError;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(de::value::StrDeserializer::<WMIError>::new("Windows"))?;
+
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for WideStringAccess {
+    type Error = WMIError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Err(de::Error::invalid_type(
+            de::Unexpected::NewtypeVariant,
+            &"unit variant",
+        ))
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(de::value::SeqDeserializer::<_, WMIError>::new(
+            self.0.into_iter(),
+        ))
+    }
+
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::invalid_type(
+            de::Unexpected::NewtypeVariant,
+            &"tuple variant",
+        ))
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::invalid_type(
+            de::Unexpected::NewtypeVariant,
+            &"struct variant",
+        ))
+    }
+}
+
+fn object_deserializer(o: IWbemClassWrapper, options: &DeserializeOptions) -> Deserializer {
+    Deserializer::from_wbem_class_obj(o).with_options(options.clone())
+}
+
+impl<'de, 'a> de::Deserializer<'de> for PropertyDeserializer<'a> {
+    type Error = WMIError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (value, context) = self.take("any value")?;
+
+        match value {
+            Variant::Object(o) => {
+                let mut de = object_deserializer(o, context.options);
+                de::Deserializer::deserialize_map(&mut de, visitor)
+            }
+            value => value.deserialize_any(visitor),
+        }
+        .map_err(|err| context.wrap(err))
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Variant::Null | Variant::Empty if self.invalid_utf16.is_none() => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if let (INTERNED_STR, Some(interner), Variant::String(s)) =
+            (name, &self.options.interner, &self.value)
+        {
+            let value = interner.intern(s);
+            return interned::visit_interned(value, visitor);
+        }
+
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (value, context) = self.take("struct")?;
+
+        match value {
+            Variant::Object(o) => {
+                let mut de = object_deserializer(o, context.options);
+                de::Deserializer::deserialize_struct(&mut de, name, fields, visitor)
+            }
+            value => value.deserialize_struct(name, fields, visitor),
+        }
+        .map_err(|err| context.wrap(err))
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if name == OS_STRING && variants == OS_STRING_VARIANTS {
+            return self.deserialize_os_string(visitor);
+        }
+
+        let (value, context) = self.take("enum")?;
+
+        match value {
+            Variant::Object(o) => {
+                let mut de = object_deserializer(o, context.options);
+                de::Deserializer::deserialize_enum(&mut de, name, variants, visitor)
+            }
+            value => value.deserialize_enum(name, variants, visitor),
+        }
+        .map_err(|err| context.wrap(err))
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Variant::Object(o) => {
+                let mut de = object_deserializer(o, self.options);
+                de::Deserializer::deserialize_map(&mut de, visitor)
+            }
+            _ => Err(self.custom_error("map", "Only objects can be deserialized into maps")),
+        }
+    }
+
+    deserialize_number! {
+        deserialize_i8 => i8, visit_i8, Signed, 8;
+        deserialize_i16 => i16, visit_i16, Signed, 16;
+        deserialize_i32 => i32, visit_i32, Signed, 32;
+        deserialize_i64 => i64, visit_i64, Signed, 64;
+        deserialize_i128 => i128, visit_i128, Signed, 128;
+        deserialize_u8 => u8, visit_u8, Unsigned, 8;
+        deserialize_u16 => u16, visit_u16, Unsigned, 16;
+        deserialize_u32 => u32, visit_u32, Unsigned, 32;
+        deserialize_u64 => u64, visit_u64, Unsigned, 64;
+        deserialize_u128 => u128, visit_u128, Unsigned, 128;
+        deserialize_f32 => f32, visit_f32, Float, 32;
+        deserialize_f64 => f64, visit_f64, Float, 64;
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (value, context) = self.take("sequence")?;
+
+        match value {
+            // Elements are deserialized with the same options (and context) as the property.
+            Variant::Array(elements) => visitor.visit_seq(ElementsAccess {
+                elements: elements.into_iter(),
+                cim_type: CIMTYPE_ENUMERATION(context.cim_type.0 & !Wmi::CIM_FLAG_ARRAY.0),
+                obj: context.obj,
+                property: context.property,
+                options: context.options,
+            }),
+            value => value.deserialize_seq(visitor),
+        }
+        .map_err(|err| context.wrap(err))
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (value, context) = self.take("bool")?;
+
+        // Only the lenient policy coerces other values, so the default behavior is unchanged.
+        let lenient = context.options.numeric_coercion == NumericCoercion::Lossy;
+
+        let coerced = match &value {
+            Variant::Bool(_) => None,
+            _ if !lenient => None,
+            Variant::String(s) => coerce_bool_str(s),
+            value => match Number::from_variant(value) {
+                Some(Number::Signed(n, _)) => Some(n != 0),
+                Some(Number::Unsigned(n, _)) => Some(n != 0),
+                _ => None,
+            },
+        };
+
+        match coerced {
+            Some(b) => visitor.visit_bool(b),
+            None => value.deserialize_bool(visitor),
+        }
+        .map_err(|err| context.wrap(err))
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (value, context) = self.take("char")?;
+
+        // A `char16` is converted to a single character string, but some providers report them as `uint16`.
+        let code_unit = match value {
+            Variant::UI2(n) => Some(u32::from(n)),
+            Variant::I2(n) => Some(u32::from(n as u16)),
+            _ => None,
+        };
+
+        match code_unit {
+            Some(n) => match char::from_u32(n) {
+                Some(ch) => visitor.visit_char(ch),
+                None => Err(<WMIError as de::Error>::custom(format_args!(
+                    "{:#X} is not a valid character",
+                    n
+                ))),
+            },
+            None => value.deserialize_char(visitor),
+        }
+        .map_err(|err| context.wrap(err))
+    }
+
+    forward_with_context! {
+        deserialize_str => "string",
+        deserialize_string => "string",
+        deserialize_bytes => "bytes",
+        deserialize_byte_buf => "bytes",
+        deserialize_unit => "unit",
+        deserialize_ignored_any => "any value",
+    }
+
+    // Numbers and booleans are visited as strings, so they can select variants by name
+    // (like `#[serde(rename = "3")]` for the tag of an internally tagged enum), and not by index.
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (value, context) = self.take("identifier")?;
+
+        match value {
+            Variant::I1(n) => visitor.visit_string(n.to_string()),
+            Variant::I2(n) => visitor.visit_string(n.to_string()),
+            Variant::I4(n) => visitor.visit_string(n.to_string()),
+            Variant::I8(n) => visitor.visit_string(n.to_string()),
+            Variant::UI1(n) => visitor.visit_string(n.to_string()),
+            Variant::UI2(n) => visitor.visit_string(n.to_string()),
+            Variant::UI4(n) => visitor.visit_string(n.to_string()),
+            Variant::UI8(n) => visitor.visit_string(n.to_string()),
+            Variant::Bool(b) => visitor.visit_string(b.to_string()),
+            value => value.deserialize_identifier(visitor),
+        }
+        .map_err(|err| context.wrap(err))
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (value, context) = self.take("unit struct")?;
+        value
+            .deserialize_unit_struct(name, visitor)
+            .map_err(|err| context.wrap(err))
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (value, context) = self.take("tuple")?;
+        value
+            .deserialize_tuple(len, visitor)
+            .map_err(|err| context.wrap(err))
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (value, context) = self.take("tuple struct")?;
+        value
+            .deserialize_tuple_struct(name, len, visitor)
+            .map_err(|err| context.wrap(err))
+    }
+}
+
+/// Coerce `TRUE` / `FALSE` (in any case), `1` and `0` into a bool.
+pub(crate) fn coerce_bool_str(s: &str) -> Option<bool> {
+    let s = s.trim();
+
+    if s.eq_ignore_ascii_case("true") || s == "1" {
+        Some(true)
+    } else if s.eq_ignore_ascii_case("false") || s == "0" {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// The name of the type of a variant, like `I4` or `String`.
+pub(crate) fn variant_type_name(value: &Variant) -> &'static str {
+    match value {
+        Variant::Empty => "Empty",
+        Variant::Null => "Null",
+        Variant::String(_) => "String",
+        Variant::I1(_) => "I1",
+        Variant::I2(_) => "I2",
+        Variant::I4(_) => "I4",
+        Variant::I8(_) => "I8",
+        Variant::R4(_) => "R4",
+        Variant::R8(_) => "R8",
+        Variant::Bool(_) => "Bool",
+        Variant::UI1(_) => "UI1",
+        Variant::UI2(_) => "UI2",
+        Variant::UI4(_) => "UI4",
+        Variant::UI8(_) => "UI8",
+        Variant::Array(_) => "Array",
+        Variant::Unknown(_) => "Unknown",
+        Variant::Object(_) => "Object",
+    }
+}
+
+/// The MOF name of a CIM type, like `uint32` or `string[]`.
+pub(crate) fn cim_type_name(cim_type: CIMTYPE_ENUMERATION) -> String {
+    let is_array = cim_type.0 & Wmi::CIM_FLAG_ARRAY.0 != 0;
+
+    let name = match CIMTYPE_ENUMERATION(cim_type.0 & !Wmi::CIM_FLAG_ARRAY.0) {
+        Wmi::CIM_SINT8 => "sint8",
+        Wmi::CIM_UINT8 => "uint8",
+        Wmi::CIM_SINT16 => "sint16",
+        Wmi::CIM_UINT16 => "uint16",
+        Wmi::CIM_SINT32 => "sint32",
+        Wmi::CIM_UINT32 => "uint32",
+        Wmi::CIM_SINT64 => "sint64",
+        Wmi::CIM_UINT64 => "uint64",
+        Wmi::CIM_REAL32 => "real32",
+        Wmi::CIM_REAL64 => "real64",
+        Wmi::CIM_BOOLEAN => "boolean",
+        Wmi::CIM_STRING => "string",
+        Wmi::CIM_DATETIME => "datetime",
+        Wmi::CIM_REFERENCE => "ref",
+        Wmi::CIM_CHAR16 => "char16",
+        Wmi::CIM_OBJECT => "object",
+        Wmi::CIM_EMPTY => "empty",
+        _ => "unknown",
+    };
+
+    if is_array {
+        format!("{}[]", name)
+    } else {
+        name.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::{ffi::OsString, os::windows::ffi::OsStringExt};
+
+    /// Provides raw UTF-16 data like a property which is not valid UTF-16.
+    struct WideDeserializer(Vec<u16>);
+
+    impl<'de> de::Deserializer<'de> for WideDeserializer {
+        type Error = WMIError;
+
+        fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.visit_enum(WideStringAccess(self.0))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map struct enum identifier ignored_any
+        }
+    }
+
+    #[test]
+    fn it_preserves_invalid_utf16_in_os_strings() {
+        // An unpaired surrogate.
+        let wide = vec![0x61, 0xD800, 0x62];
+
+        let s = OsString::deserialize(WideDeserializer(wide.clone())).unwrap();
+
+        assert_eq!(s, OsString::from_wide(&wide));
+        assert!(s.to_str().is_none());
+    }
+
+    #[test]
+    fn it_coerces_bools() {
+        assert_eq!(coerce_bool_str("TRUE"), Some(true));
+        assert_eq!(coerce_bool_str("false"), Some(false));
+        assert_eq!(coerce_bool_str(" 1 "), Some(true));
+        assert_eq!(coerce_bool_str("yes"), None);
+    }
+
+    #[test]
+    fn it_formats_cim_types() {
+        assert_eq!(cim_type_name(Wmi::CIM_UINT32), "uint32");
+        assert_eq!(
+            cim_type_name(CIMTYPE_ENUMERATION(
+                Wmi::CIM_STRING.0 | Wmi::CIM_FLAG_ARRAY.0
+            )),
+            "string[]"
+        );
+        assert_eq!(cim_type_name(CIMTYPE_ENUMERATION(0x7ff)), "unknown");
+    }
+}
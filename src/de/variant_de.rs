@@ -46,6 +46,8 @@ impl<'de> serde::Deserializer<'de> for Variant {
             Variant::Array(v) => visitor.visit_seq(SeqAccess {
                 data: v.into_iter(),
             }),
+            // Embedded objects are deserialized as maps, which allows buffering them (e.g. for `#[serde(flatten)]`).
+            Variant::Object(o) => Deserializer::from_wbem_class_obj(o).deserialize_map(visitor),
             _ => Err(WMIError::InvalidDeserializationVariantError(format!(
                 "{:?}",
                 self
@@ -105,6 +107,14 @@ impl<'de> serde::Deserializer<'de> for Variant {
     }
 }
 
+impl<'de> de::IntoDeserializer<'de, WMIError> for Variant {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
 impl<'de> Deserialize<'de> for Variant {
     #[inline]
     fn deserialize<D>(deserializer: D) -> Result<Variant, D::Error>
@@ -220,12 +230,20 @@ impl<'de> Deserialize<'de> for Variant {
                 Ok(Variant::Array(vec))
             }
 
-            fn visit_map<V>(self, mut _visitor: V) -> Result<Self::Value, V::Error>
+            #[inline]
+            fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E> {
+                // Arrays of `UI1` are serialized as bytes.
+                Ok(Variant::Array(
+                    value.iter().copied().map(Variant::UI1).collect(),
+                ))
+            }
+
+            fn visit_map<V>(self, _visitor: V) -> Result<Self::Value, V::Error>
             where
                 V: de::MapAccess<'de>,
             {
-                // TODO: Add support for map type
-                unimplemented!()
+                // A `Variant` cannot hold a map, and objects can only be created by WMI.
+                Err(de::Error::invalid_type(de::Unexpected::Map, &self))
             }
         }
 
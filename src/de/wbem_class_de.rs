@@ -1,20 +1,164 @@
-use crate::{result_enumerator::IWbemClassWrapper, WMIError, WMIResult};
+use crate::{
+    de::{
+        meta::ALL_PROPERTIES,
+        options::{DeserializeOptions, Utf16Conversion},
+        property_de::PropertyDeserializer,
+    },
+    result_enumerator::IWbemClassWrapper,
+    utils::PropertyError,
+    Variant, WMIError, WMIResult,
+};
 use serde::{
     de::{
         self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess,
-        Unexpected, VariantAccess, Visitor,
+        SeqAccess, Unexpected, VariantAccess, Visitor,
     },
     forward_to_deserialize_any,
 };
-use std::iter::Peekable;
+use std::{iter::Peekable, sync::Arc};
+use windows::Win32::System::Wmi::{self, WBEM_E_NOT_FOUND};
 
 pub struct Deserializer {
     pub wbem_class_obj: IWbemClassWrapper,
+    /// The properties selected by the query, in order. Used to deserialize tuples.
+    pub(crate) projection: Option<Vec<String>>,
+    /// Fields which are not properties of the class, and are left to their defaults.
+    pub(crate) absent: Option<Arc<[String]>>,
+    pub(crate) options: DeserializeOptions,
 }
 
 impl Deserializer {
     pub fn from_wbem_class_obj(wbem_class_obj: IWbemClassWrapper) -> Self {
-        Deserializer { wbem_class_obj }
+        Deserializer {
+            wbem_class_obj,
+            projection: None,
+            absent: None,
+            options: DeserializeOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: DeserializeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    fn property_deserializer<'a>(
+        &'a self,
+        property: &'a str,
+    ) -> WMIResult<PropertyDeserializer<'a>> {
+        let (value, cim_type) = match self.wbem_class_obj.get_property_with_type(property) {
+            // Keep the raw data, which can still be deserialized into an `OsString`.
+            Err(err @ WMIError::ConvertStringError(_)) => {
+                if let Some(wide) = self.wbem_class_obj.get_property_utf16(property)? {
+                    return Ok(PropertyDeserializer {
+                        value: Variant::Null,
+                        invalid_utf16: Some(wide),
+                        cim_type: Wmi::CIM_STRING,
+                        obj: &self.wbem_class_obj,
+                        property,
+                        options: &self.options,
+                    });
+                }
+
+                // Arrays of strings are only converted when invalid data can be replaced.
+                if self.options.utf16_conversion == Utf16Conversion::Lossy {
+                    if let Some(items) = self.wbem_class_obj.get_property_utf16_array(property)? {
+                        let items = items
+                            .iter()
+                            .map(|wide| Variant::String(String::from_utf16_lossy(wide)))
+                            .collect();
+
+                        return Ok(PropertyDeserializer {
+                            value: Variant::Array(items),
+                            invalid_utf16: None,
+                            cim_type: Wmi::CIMTYPE_ENUMERATION(
+                                Wmi::CIM_STRING.0 | Wmi::CIM_FLAG_ARRAY.0,
+                            ),
+                            obj: &self.wbem_class_obj,
+                            property,
+                            options: &self.options,
+                        });
+                    }
+                }
+
+                return Err(err);
+            }
+            res => res,
+        }
+        .map_err(|err| match err {
+            WMIError::HResultError { hres }
+                if self.options.strict && hres == WBEM_E_NOT_FOUND.0 =>
+            {
+                WMIError::PropertyDeserializationError(Box::new(PropertyError {
+                    class: self.wbem_class_obj.class().unwrap_or_default(),
+                    property: property.to_owned(),
+                    cim_type: String::from("none"),
+                    variant_type: "none",
+                    expected: "an existing property",
+                    message: String::from("The property does not exist"),
+                }))
+            }
+            err => err,
+        })?;
+
+        Ok(PropertyDeserializer {
+            value,
+            invalid_utf16: None,
+            cim_type,
+            obj: &self.wbem_class_obj,
+            property,
+            options: &self.options,
+        })
+    }
+
+    /// The names of the (non-system) properties of the object, from the property cache if there is one.
+    fn property_names(&self) -> WMIResult<Arc<[String]>> {
+        match &self.options.property_cache {
+            Some(cache) => cache.property_names(&self.wbem_class_obj, self.projection.as_deref()),
+            None => Ok(self.wbem_class_obj.list_properties()?.into()),
+        }
+    }
+
+    /// Check that all the (non-null) properties of the object are mapped by the given fields.
+    fn check_unmapped(&self, fields: &[&str]) -> WMIResult<()> {
+        let mut unmapped = vec![];
+
+        for property in self.property_names()?.iter() {
+            if fields
+                .iter()
+                .any(|field| field.eq_ignore_ascii_case(property))
+            {
+                continue;
+            }
+
+            if !matches!(
+                self.wbem_class_obj.get_property(property)?,
+                Variant::Null | Variant::Empty
+            ) {
+                unmapped.push(property.clone());
+            }
+        }
+
+        if unmapped.is_empty() {
+            Ok(())
+        } else {
+            Err(WMIError::UnmappedPropertiesError {
+                class: self.wbem_class_obj.class()?,
+                properties: unmapped,
+            })
+        }
+    }
+
+    /// Use the given property names when deserializing tuples (and tuple structs).
+    pub fn with_projection(mut self, projection: Vec<String>) -> Self {
+        self.projection = Some(projection);
+        self
+    }
+
+    /// Skip the given fields when deserializing structs, so serde uses their defaults.
+    pub(crate) fn with_absent_fields(mut self, absent: Arc<[String]>) -> Self {
+        self.absent = Some(absent);
+        self
     }
 }
 
@@ -26,13 +170,27 @@ where
     T::deserialize(&mut deserializer)
 }
 
+/// Deserialize the object, mapping the elements of tuples to the given properties.
+pub fn from_wbem_class_obj_with_projection<T>(
+    wbem_class_obj: IWbemClassWrapper,
+    projection: &[String],
+) -> WMIResult<T>
+where
+    T: DeserializeOwned,
+{
+    let mut deserializer =
+        Deserializer::from_wbem_class_obj(wbem_class_obj).with_projection(projection.to_vec());
+    T::deserialize(&mut deserializer)
+}
+
 struct WMIEnum<'a> {
     de: &'a mut Deserializer,
+    variants: &'static [&'static str],
 }
 
 impl<'a> WMIEnum<'a> {
-    pub fn new(de: &'a mut Deserializer) -> Self {
-        Self { de }
+    pub fn new(de: &'a mut Deserializer, variants: &'static [&'static str]) -> Self {
+        Self { de, variants }
     }
 }
 
@@ -44,7 +202,16 @@ impl<'de, 'a> EnumAccess<'de> for WMIEnum<'a> {
     where
         V: DeserializeSeed<'de>,
     {
-        let val = seed.deserialize(&mut *self.de)?;
+        let class_name = self.de.wbem_class_obj.class()?;
+
+        // Class names are case-insensitive, so match them to the variants in the same way.
+        let variant = self
+            .variants
+            .iter()
+            .find(|variant| variant.eq_ignore_ascii_case(&class_name))
+            .map_or(class_name, |variant| variant.to_string());
+
+        let val = seed.deserialize(IntoDeserializer::<WMIError>::into_deserializer(variant))?;
         Ok((val, self))
     }
 }
@@ -137,25 +304,73 @@ where
             .next()
             .ok_or_else(|| WMIError::SerdeError("Expected current field to not be None".into()))?;
 
-        let property_value = self
-            .de
-            .wbem_class_obj
-            .get_property(current_field.as_ref())?;
+        seed.deserialize(self.de.property_deserializer(current_field.as_ref())?)
+    }
+}
+
+struct WMISeqAccess<'a, I>
+where
+    I: Iterator<Item = &'a String>,
+{
+    fields: I,
+    de: &'a Deserializer,
+}
+
+impl<'de, 'a, I> SeqAccess<'de> for WMISeqAccess<'a, I>
+where
+    I: Iterator<Item = &'a String>,
+{
+    type Error = WMIError;
 
-        seed.deserialize(property_value)
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.fields.next() {
+            Some(field) => seed
+                .deserialize(self.de.property_deserializer(field)?)
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Deserializer {
+    fn deserialize_projection<'de, V>(&self, len: usize, visitor: V) -> WMIResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let projection = self.projection.as_ref().ok_or_else(|| {
+            WMIError::SerdeError(
+                "Tuples can only be deserialized from queries which select explicit properties"
+                    .into(),
+            )
+        })?;
+
+        if projection.len() != len {
+            return Err(de::Error::invalid_length(
+                projection.len(),
+                &format!("a query selecting {} properties", len).as_str(),
+            ));
+        }
+
+        visitor.visit_seq(WMISeqAccess {
+            fields: projection.iter(),
+            de: self,
+        })
     }
 }
 
 impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer {
     type Error = WMIError;
 
-    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    // Objects are maps of all their properties, which allows buffering them
+    // (e.g. for internally tagged enums, like `#[serde(tag = "DriveType")]`).
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        Err(WMIError::SerdeError(
-            "Only structs and maps can be deserialized from WMI objects".into(),
-        ))
+        self.deserialize_map(visitor)
     }
 
     // Support for deserializing `Wrapper(Win32_OperatingSystem)`.
@@ -174,7 +389,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer {
     where
         V: Visitor<'de>,
     {
-        let fields = self.wbem_class_obj.list_properties()?;
+        let fields = self.property_names()?;
 
         visitor.visit_map(WMIMapAccess::new(fields.iter(), self))
     }
@@ -188,19 +403,38 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_map(WMIMapAccess::new(fields.iter(), self))
+        if fields == ALL_PROPERTIES {
+            return self.deserialize_map(visitor);
+        }
+
+        if self.options.strict {
+            self.check_unmapped(fields)?;
+        }
+
+        // Properties are looked up by the field names, which WMI matches case-insensitively.
+        match self.absent.clone() {
+            Some(absent) => visitor.visit_map(WMIMapAccess::new(
+                fields.iter().filter(|field| {
+                    !absent
+                        .iter()
+                        .any(|absent| absent.eq_ignore_ascii_case(field))
+                }),
+                self,
+            )),
+            None => visitor.visit_map(WMIMapAccess::new(fields.iter(), self)),
+        }
     }
 
     fn deserialize_enum<V>(
         self,
         _name: &'static str,
-        _variants: &'static [&'static str],
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_enum(WMIEnum::new(self))
+        visitor.visit_enum(WMIEnum::new(self, variants))
     }
 
     // When deserializing enums, return the object's class name as the expected enum variant.
@@ -212,10 +446,41 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer {
         visitor.visit_string(class_name)
     }
 
+    // Unit structs are used to only check for existence, so no properties are read.
+    fn deserialize_unit_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    // Support for deserializing `(String, u32)`, using the order of the properties in the query.
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_projection(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_projection(len, visitor)
+    }
+
     forward_to_deserialize_any! {
         bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
-        byte_buf option unit unit_struct seq tuple
-        tuple_struct ignored_any
+        byte_buf option unit seq ignored_any
     }
 }
 
@@ -225,9 +490,11 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer {
 mod tests {
     use super::*;
 
+    use crate::de::options::{DeserializeOptions, NumericCoercion, Utf16Conversion};
     use crate::duration::WMIDuration;
     use crate::variant::Variant;
     use crate::FilterValue;
+    use crate::WMIError;
     use serde::Deserialize;
     use std::collections::HashMap;
     use std::time::Duration;
@@ -399,7 +666,8 @@ mod tests {
 
         assert_eq!(
             format!("{}", err),
-            "invalid type: Option value, expected a string"
+            "Cannot deserialize property \"CommandLine\" of \"Win32_Process\" (CIM type string, variant Null) \
+            into string: invalid type: Option value, expected a string"
         )
     }
 
@@ -436,6 +704,66 @@ mod tests {
         assert_ne!(&wrapped_service.0.Name, "")
     }
 
+    #[test]
+    fn it_can_desr_internally_tagged_enum() {
+        let wmi_con = wmi_con();
+
+        #[derive(Deserialize, Debug)]
+        #[serde(tag = "DriveType")]
+        enum Win32_LogicalDisk {
+            #[serde(rename = "3")]
+            Local { DeviceID: String, Size: Option<u64> },
+            #[serde(rename = "4")]
+            Network {
+                DeviceID: String,
+                ProviderName: Option<String>,
+            },
+            #[serde(other)]
+            Other,
+        }
+
+        let disks: Vec<Win32_LogicalDisk> = wmi_con
+            .raw_query("SELECT DeviceID, DriveType, Size, ProviderName FROM Win32_LogicalDisk")
+            .unwrap();
+
+        assert!(disks.iter().any(|disk| matches!(
+            disk,
+            Win32_LogicalDisk::Local { DeviceID, Size: Some(size) } if DeviceID == "C:" && *size > 0
+        )));
+        assert!(disks.iter().all(|disk| match disk {
+            Win32_LogicalDisk::Local { DeviceID, .. } => DeviceID.ends_with(':'),
+            Win32_LogicalDisk::Network {
+                DeviceID,
+                ProviderName,
+            } =>
+                DeviceID.ends_with(':')
+                    && ProviderName
+                        .as_ref()
+                        .is_none_or(|name| name.starts_with(r"\\")),
+            Win32_LogicalDisk::Other => true,
+        }));
+
+        // Untagged enums are buffered the same way.
+        #[derive(Deserialize, Debug)]
+        #[serde(untagged)]
+        enum Named {
+            Name { Name: String },
+            Caption { Caption: String },
+        }
+
+        let named: Vec<Named> = wmi_con
+            .raw_query("SELECT Caption FROM Win32_OperatingSystem")
+            .unwrap();
+
+        assert!(matches!(&named[0], Named::Caption { Caption } if Caption.contains("Windows")));
+
+        let named: Vec<Named> = wmi_con
+            .raw_query("SELECT Name FROM Win32_OperatingSystem")
+            .unwrap();
+
+        assert!(matches!(&named[0], Named::Name { Name } if Name.contains("Windows")));
+    }
+
     #[test]
     fn it_can_desr_newtype_enum_field() {
         let wmi_con = wmi_con();
@@ -487,4 +815,427 @@ mod tests {
 
         assert!(matches!(proc.TargetInstance, Instance::Process(..)))
     }
+
+    #[test]
+    fn it_can_desr_flattened_structs() {
+        let wmi_con = wmi_con();
+
+        #[derive(Deserialize, Debug)]
+        struct CimBase {
+            Caption: String,
+            Name: String,
+            #[allow(dead_code)]
+            Description: Option<String>,
+        }
+
+        #[derive(Deserialize, Debug)]
+        struct Win32_OperatingSystem {
+            #[serde(flatten)]
+            base: CimBase,
+            BuildNumber: String,
+        }
+
+        let results: Vec<Win32_OperatingSystem> = wmi_con
+            .raw_query("SELECT * FROM Win32_OperatingSystem")
+            .unwrap();
+
+        for os in results {
+            assert!(os.base.Caption.contains("Microsoft Windows"));
+            assert_ne!(os.base.Name, "");
+            assert_ne!(os.BuildNumber, "");
+        }
+
+        // The query can't be built from a struct with flattened fields (as documented by `query`).
+        let err = wmi_con.query::<Win32_OperatingSystem>().unwrap_err();
+        assert!(err.to_string().contains("Use `raw_query` instead"));
+    }
+
+    #[test]
+    fn it_can_desr_flattened_embedded_objects() {
+        let wmi_con = wmi_con();
+
+        #[derive(Deserialize, Debug)]
+        struct ProcessBase {
+            Name: String,
+            ProcessId: u32,
+        }
+
+        #[derive(Deserialize, Debug)]
+        struct Win32_Process {
+            #[serde(flatten)]
+            base: ProcessBase,
+        }
+
+        #[derive(Deserialize, Debug)]
+        struct __InstanceCreationEvent {
+            TargetInstance: Win32_Process,
+        }
+
+        let mut filters = HashMap::new();
+
+        filters.insert(
+            "TargetInstance".to_owned(),
+            FilterValue::IsA("Win32_Process"),
+        );
+        filters.insert(
+            "TargetInstance.Name".to_owned(),
+            FilterValue::String("ping.exe".to_owned()),
+        );
+
+        let mut instances_iter = wmi_con
+            .filtered_notification::<__InstanceCreationEvent>(
+                &filters,
+                Some(Duration::from_secs(1)),
+            )
+            .unwrap();
+
+        std::process::Command::new("ping.exe")
+            .arg("127.0.0.1")
+            .status()
+            .unwrap();
+
+        let event = instances_iter.next().unwrap().unwrap();
+
+        assert_eq!(event.TargetInstance.base.Name, "ping.exe");
+        assert_ne!(event.TargetInstance.base.ProcessId, 0);
+    }
+
+    #[test]
+    fn it_matches_property_names_case_insensitively() {
+        let wmi_con = wmi_con();
+
+        #[derive(Deserialize, Debug)]
+        #[serde(rename = "Win32_OperatingSystem")]
+        #[serde(rename_all = "lowercase")]
+        struct OperatingSystem {
+            caption: String,
+            buildnumber: String,
+        }
+
+        let os: OperatingSystem = wmi_con.get().unwrap();
+
+        assert!(os.caption.contains("Microsoft Windows"));
+        assert_ne!(os.buildnumber, "");
+    }
+
+    #[test]
+    fn it_matches_class_names_case_insensitively() {
+        let wmi_con = wmi_con();
+
+        #[derive(Deserialize, Debug)]
+        struct Win32_OperatingSystem {
+            Caption: String,
+        }
+
+        #[derive(Deserialize, Debug)]
+        enum Instance {
+            #[serde(rename = "WIN32_OPERATINGSYSTEM")]
+            OperatingSystem(Win32_OperatingSystem),
+        }
+
+        let results: Vec<Instance> = wmi_con
+            .raw_query("SELECT Caption FROM Win32_OperatingSystem")
+            .unwrap();
+
+        for Instance::OperatingSystem(os) in results {
+            assert!(os.Caption.contains("Microsoft Windows"));
+        }
+    }
+
+    #[test]
+    fn it_fails_on_unmapped_properties_in_strict_mode() {
+        let wmi_con = wmi_con().with_deserialize_options(DeserializeOptions::new().strict(true));
+
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Win32_OperatingSystem {
+            Caption: String,
+        }
+
+        let err = wmi_con
+            .raw_query::<Win32_OperatingSystem>(
+                "SELECT Caption, BuildNumber FROM Win32_OperatingSystem",
+            )
+            .unwrap_err();
+
+        match err {
+            WMIError::UnmappedPropertiesError { class, properties } => {
+                assert_eq!(class, "Win32_OperatingSystem");
+                assert_eq!(properties, ["BuildNumber"]);
+            }
+            err => panic!("Unexpected error {:?}", err),
+        }
+
+        // Properties which are not selected are null, and are not considered unmapped.
+        let os: Win32_OperatingSystem = wmi_con
+            .raw_query("SELECT Caption FROM Win32_OperatingSystem")
+            .unwrap()
+            .pop()
+            .unwrap();
+
+        assert!(os.Caption.contains("Microsoft Windows"));
+    }
+
+    #[test]
+    fn it_fails_on_missing_and_mistyped_properties_in_strict_mode() {
+        let wmi_con = wmi_con().with_deserialize_options(DeserializeOptions::new().strict(true));
+
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        #[serde(rename = "Win32_OperatingSystem")]
+        struct Missing {
+            NoSuchProperty: Option<String>,
+        }
+
+        let err = wmi_con.get::<Missing>().unwrap_err();
+
+        match err {
+            WMIError::PropertyDeserializationError(err) => {
+                assert_eq!(err.property, "NoSuchProperty");
+            }
+            err => panic!("Unexpected error {:?}", err),
+        }
+
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        #[serde(rename = "Win32_OperatingSystem")]
+        struct Mistyped {
+            Caption: u32,
+        }
+
+        let err = wmi_con
+            .raw_query::<Mistyped>("SELECT Caption FROM Win32_OperatingSystem")
+            .unwrap_err();
+
+        match err {
+            WMIError::PropertyDeserializationError(err) => {
+                assert_eq!(err.class, "Win32_OperatingSystem");
+                assert_eq!(err.property, "Caption");
+                assert_eq!(err.cim_type, "string");
+                assert_eq!(err.variant_type, "String");
+                assert_eq!(err.expected, "u32");
+            }
+            err => panic!("Unexpected error {:?}", err),
+        }
+
+        // Without strict mode, missing properties are the original WMI error.
+        let err = wmi_con
+            .with_deserialize_options(DeserializeOptions::new())
+            .get::<Missing>()
+            .unwrap_err();
+
+        assert!(matches!(err, WMIError::HResultError { .. }));
+    }
+
+    #[test]
+    fn it_applies_the_numeric_coercion_policy() {
+        let wmi_con = wmi_con();
+
+        #[derive(Deserialize, Debug)]
+        #[serde(rename = "Win32_OperatingSystem")]
+        struct Wide {
+            NumberOfProcesses: u64,
+        }
+
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        #[serde(rename = "Win32_OperatingSystem")]
+        struct Signed {
+            NumberOfProcesses: i32,
+        }
+
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        #[serde(rename = "Win32_OperatingSystem")]
+        struct Narrow {
+            NumberOfProcesses: u8,
+        }
+
+        let policy = |numeric_coercion| {
+            wmi_con.with_deserialize_options(
+                DeserializeOptions::new().numeric_coercion(numeric_coercion),
+            )
+        };
+
+        let strict = policy(NumericCoercion::Strict);
+        let err = strict.get::<Wide>().unwrap_err();
+        assert!(err.to_string().contains("Strict numeric coercion policy"));
+
+        let widen_only = policy(NumericCoercion::WidenOnly);
+        let os = widen_only.get::<Wide>().unwrap();
+        assert!(os.NumberOfProcesses > 0);
+        widen_only.get::<Signed>().unwrap_err();
+
+        policy(NumericCoercion::InRange).get::<Signed>().unwrap();
+
+        // Values which don't fit are wrapped instead of failing.
+        policy(NumericCoercion::Lossy).get::<Narrow>().unwrap();
+
+        #[derive(Deserialize, Debug)]
+        #[serde(rename = "Win32_OperatingSystem")]
+        struct Flag {
+            NumberOfProcesses: bool,
+        }
+
+        // Only the lossy policy coerces integers into bools.
+        wmi_con.get::<Flag>().unwrap_err();
+        assert!(
+            policy(NumericCoercion::Lossy)
+                .get::<Flag>()
+                .unwrap()
+                .NumberOfProcesses
+        );
+    }
+
+    #[test]
+    fn it_desr_os_string_and_path_buf() {
+        let wmi_con = wmi_con();
+
+        #[derive(Deserialize, Debug)]
+        struct Win32_Process {
+            Name: std::ffi::OsString,
+            #[serde(deserialize_with = "crate::de::os_str::deserialize_path_buf")]
+            ExecutablePath: std::path::PathBuf,
+        }
+
+        let mut filters = HashMap::new();
+        filters.insert("ProcessID".into(), i64::from(process::id()).into());
+
+        let current_proc: Win32_Process = wmi_con.filtered_query(&filters).unwrap().pop().unwrap();
+
+        let current_exe = std::env::current_exe().unwrap();
+        assert_eq!(current_proc.ExecutablePath, current_exe);
+        assert_eq!(Some(current_proc.Name.as_os_str()), current_exe.file_name());
+    }
+
+    /// Create a `Win32_Process` instance whose `Name` is not valid UTF-16.
+    fn spawn_with_invalid_name(wide: &[u16]) -> IWbemClassWrapper {
+        use crate::safe_variant::{raw_variant, SafeVariant};
+        use std::mem::ManuallyDrop;
+        use windows::core::{BSTR, HSTRING, PCWSTR};
+        use windows::Win32::System::Com::{VARIANT_0_0_0, VT_BSTR};
+
+        let class = wmi_con().get_raw_by_path("Win32_Process").unwrap();
+        let instance = unsafe { class.inner.SpawnInstance(0) }.unwrap();
+
+        let value = unsafe {
+            SafeVariant::from_raw(raw_variant(
+                VT_BSTR,
+                VARIANT_0_0_0 {
+                    bstrVal: ManuallyDrop::new(BSTR::from_wide(wide).unwrap()),
+                },
+            ))
+        };
+
+        let name = HSTRING::from("Name");
+        unsafe {
+            instance
+                .Put(PCWSTR::from_raw(name.as_ptr()), 0, value.as_raw(), 0)
+                .unwrap();
+        }
+
+        IWbemClassWrapper::new(instance)
+    }
+
+    #[test]
+    fn it_converts_invalid_utf16_according_to_options() {
+        #[derive(Deserialize, Debug)]
+        struct Win32_Process {
+            Name: String,
+        }
+
+        #[derive(Deserialize, Debug)]
+        #[serde(rename = "Win32_Process")]
+        struct RawProcess {
+            Name: std::ffi::OsString,
+        }
+
+        // An unpaired surrogate.
+        let wide = [0x61, 0xD800, 0x62];
+
+        let err = from_wbem_class_obj::<Win32_Process>(spawn_with_invalid_name(&wide)).unwrap_err();
+        assert!(matches!(err, WMIError::PropertyDeserializationError(_)));
+
+        let mut de = Deserializer::from_wbem_class_obj(spawn_with_invalid_name(&wide))
+            .with_options(DeserializeOptions::new().utf16_conversion(Utf16Conversion::Lossy));
+        let proc = Win32_Process::deserialize(&mut de).unwrap();
+        assert_eq!(proc.Name, "a\u{FFFD}b");
+
+        let proc: RawProcess = from_wbem_class_obj(spawn_with_invalid_name(&wide)).unwrap();
+        assert_eq!(
+            proc.Name,
+            <std::ffi::OsString as std::os::windows::ffi::OsStringExt>::from_wide(&wide)
+        );
+    }
+
+    #[test]
+    fn it_converts_invalid_utf16_arrays_according_to_options() {
+        use crate::safe_variant::{raw_variant, SafeVariant};
+        use windows::core::{BSTR, HSTRING, PCWSTR};
+        use windows::Win32::System::Com::{VARENUM, VARIANT_0_0_0, VT_ARRAY, VT_BSTR};
+        use windows::Win32::System::Ole::{SafeArrayCreateVector, SafeArrayPutElement};
+
+        #[derive(Deserialize, Debug)]
+        struct Win32_NetworkAdapterConfiguration {
+            IPAddress: Vec<String>,
+        }
+
+        let class = wmi_con()
+            .get_raw_by_path("Win32_NetworkAdapterConfiguration")
+            .unwrap();
+        let instance = unsafe { class.inner.SpawnInstance(0) }.unwrap();
+
+        // The second address has an unpaired surrogate.
+        let addresses = [
+            BSTR::from("10.0.0.1"),
+            BSTR::from_wide(&[0x61, 0xD800, 0x62]).unwrap(),
+        ];
+
+        let value = unsafe {
+            let arr = SafeArrayCreateVector(VT_BSTR, 0, addresses.len() as u32);
+
+            for (index, address) in addresses.iter().enumerate() {
+                let address: *const std::ffi::c_void = std::mem::transmute_copy(address);
+                SafeArrayPutElement(arr, &(index as i32), address).unwrap();
+            }
+
+            SafeVariant::from_raw(raw_variant(
+                VARENUM(VT_ARRAY.0 | VT_BSTR.0),
+                VARIANT_0_0_0 { parray: arr },
+            ))
+        };
+
+        let name = HSTRING::from("IPAddress");
+        unsafe {
+            instance
+                .Put(PCWSTR::from_raw(name.as_ptr()), 0, value.as_raw(), 0)
+                .unwrap();
+        }
+
+        let instance = IWbemClassWrapper::new(instance);
+
+        let err =
+            from_wbem_class_obj::<Win32_NetworkAdapterConfiguration>(instance.clone()).unwrap_err();
+        assert!(matches!(err, WMIError::ConvertStringError(_)));
+
+        let mut de = Deserializer::from_wbem_class_obj(instance)
+            .with_options(DeserializeOptions::new().utf16_conversion(Utf16Conversion::Lossy));
+        let config = Win32_NetworkAdapterConfiguration::deserialize(&mut de).unwrap();
+        assert_eq!(config.IPAddress, ["10.0.0.1", "a\u{FFFD}b"]);
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn it_desr_uuid() {
+        let wmi_con = wmi_con();
+
+        #[derive(Deserialize, Debug)]
+        struct Win32_ComputerSystemProduct {
+            UUID: uuid::Uuid,
+        }
+
+        let product: Win32_ComputerSystemProduct = wmi_con.get().unwrap();
+
+        assert!(!product.UUID.is_nil());
+    }
 }
@@ -0,0 +1,324 @@
+//! Watch Plug and Play devices (like USB devices) arrive and leave, with debouncing.
+//!
+//! Devices are watched using the `__InstanceCreationEvent`s and `__InstanceDeletionEvent`s of `Win32_PnPEntity`,
+//! which WMI polls every [`poll_interval`](DeviceWatchOptions::poll_interval).
+//! Plugging a device often creates (and removes) several entities in quick succession, and a loose connector can make a device
+//! flap, so events are only delivered once the device was stable for the [`debounce`](DeviceWatchOptions::debounce) delay:
+//! a device which arrives and leaves within the delay is not reported at all.
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use wmi::device_watch::{DeviceEvent, DeviceWatchOptions};
+//!
+//! for event in con.device_watcher(DeviceWatchOptions::new().pnp_class("USB"))? {
+//!     match event? {
+//!         DeviceEvent::Arrived(device) => println!("Plugged {:?}", device.name),
+//!         DeviceEvent::Removed(device) => println!("Unplugged {:?}", device.name),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    connection::WMIConnection, query::quote_and_escape_wql_str,
+    result_enumerator::QueryResultEnumerator, WMIError, WMIResult,
+};
+use serde::Deserialize;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// A Plug and Play device, from `Win32_PnPEntity`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "Win32_PnPEntity")]
+#[serde(rename_all = "PascalCase")]
+pub struct PnpDevice {
+    /// Like `USB\VID_046D&PID_C52B\5&2A3B4C5D&0&2`.
+    #[serde(rename = "DeviceID")]
+    pub device_id: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub manufacturer: Option<String>,
+    /// The setup class of the device, like `USB`, `HIDClass` or `DiskDrive`.
+    #[serde(rename = "PNPClass")]
+    pub pnp_class: Option<String>,
+    /// The driver service of the device.
+    pub service: Option<String>,
+    pub status: Option<String>,
+}
+
+/// A device arrived or was removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceEvent {
+    Arrived(PnpDevice),
+    Removed(PnpDevice),
+}
+
+impl DeviceEvent {
+    pub fn device(&self) -> &PnpDevice {
+        match self {
+            DeviceEvent::Arrived(device) | DeviceEvent::Removed(device) => device,
+        }
+    }
+}
+
+/// The options of [`WMIConnection::device_watcher`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DeviceWatchOptions {
+    pub poll_interval: Duration,
+    pub debounce: Duration,
+    pub pnp_class: Option<String>,
+}
+
+impl Default for DeviceWatchOptions {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(2),
+            debounce: Duration::from_secs(1),
+            pnp_class: None,
+        }
+    }
+}
+
+impl DeviceWatchOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How often WMI checks for new and removed devices (2 seconds by default).
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// How long a device must be stable before its event is delivered (1 second by default).
+    ///
+    /// Use [`Duration::ZERO`] to deliver all the events as soon as they are received.
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Only watch the devices of a setup class, like `USB` or `DiskDrive`.
+    pub fn pnp_class(mut self, pnp_class: impl Into<String>) -> Self {
+        self.pnp_class = Some(pnp_class.into());
+        self
+    }
+
+    fn query(&self) -> String {
+        let mut query = format!(
+            "SELECT * FROM __InstanceOperationEvent WITHIN {} WHERE TargetInstance ISA 'Win32_PnPEntity' \
+            AND (__CLASS = '__InstanceCreationEvent' OR __CLASS = '__InstanceDeletionEvent')",
+            self.poll_interval.as_secs_f64()
+        );
+
+        if let Some(pnp_class) = &self.pnp_class {
+            query.push_str(&format!(
+                " AND TargetInstance.PNPClass = {}",
+                quote_and_escape_wql_str(pnp_class)
+            ));
+        }
+
+        query
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InstanceEvent {
+    target_instance: PnpDevice,
+}
+
+#[derive(Deserialize)]
+enum RawDeviceEvent {
+    #[serde(rename = "__InstanceCreationEvent")]
+    Creation(InstanceEvent),
+    #[serde(rename = "__InstanceDeletionEvent")]
+    Deletion(InstanceEvent),
+}
+
+/// Holds the events of each device until the device is stable.
+#[derive(Debug)]
+struct Debouncer {
+    delay: Duration,
+    /// The pending events, in the order they are due.
+    pending: VecDeque<(Instant, DeviceEvent)>,
+}
+
+impl Debouncer {
+    fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, event: DeviceEvent, now: Instant) {
+        let previous = self
+            .pending
+            .iter()
+            .position(|(_, pending)| pending.device().device_id == event.device().device_id);
+
+        if let Some(index) = previous {
+            let (_, previous) = self.pending.remove(index).unwrap();
+
+            // The device flapped, and ended up as it was before the first event.
+            if std::mem::discriminant(&previous) != std::mem::discriminant(&event) {
+                return;
+            }
+        }
+
+        self.pending.push_back((now + self.delay, event));
+    }
+
+    /// The next event which is due at `now`.
+    fn pop_due(&mut self, now: Instant) -> Option<DeviceEvent> {
+        match self.pending.front() {
+            Some((due, _)) if *due <= now => self.pending.pop_front().map(|(_, event)| event),
+            _ => None,
+        }
+    }
+
+    fn next_due(&self) -> Option<Instant> {
+        self.pending.front().map(|(due, _)| *due)
+    }
+}
+
+/// An iterator of debounced [`DeviceEvent`]s, created using [`WMIConnection::device_watcher`].
+pub struct DeviceWatcher<'a> {
+    events: QueryResultEnumerator<'a>,
+    debouncer: Debouncer,
+    timeout: Option<Duration>,
+}
+
+impl<'a> Iterator for DeviceWatcher<'a> {
+    type Item = WMIResult<DeviceEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let now = Instant::now();
+
+            if let Some(event) = self.debouncer.pop_due(now) {
+                return Some(Ok(event));
+            }
+
+            // Wait until the next pending event is due, but not longer than the timeout of the connection.
+            let wait = match (self.debouncer.next_due(), self.timeout) {
+                (Some(due), Some(timeout)) => Some((due - now).min(timeout)),
+                (Some(due), None) => Some(due - now),
+                (None, timeout) => timeout,
+            };
+            self.events.set_timeout(wait);
+
+            let event = match self.events.next()? {
+                Ok(event) => event,
+                Err(WMIError::Timeout) if self.debouncer.next_due().is_some() => continue,
+                Err(err) => return Some(Err(err)),
+            };
+
+            let event = match event.into_desr() {
+                Ok(RawDeviceEvent::Creation(event)) => DeviceEvent::Arrived(event.target_instance),
+                Ok(RawDeviceEvent::Deletion(event)) => DeviceEvent::Removed(event.target_instance),
+                Err(err) => return Some(Err(err)),
+            };
+
+            self.debouncer.push(event, Instant::now());
+        }
+    }
+}
+
+///
+/// ### Additional device watch methods
+///
+impl WMIConnection {
+    /// Subscribe to the devices which arrive and are removed.
+    ///
+    /// See the [module level documentation](crate::device_watch) for an example.
+    pub fn device_watcher(&self, options: DeviceWatchOptions) -> WMIResult<DeviceWatcher<'_>> {
+        Ok(DeviceWatcher {
+            events: self.notification_native_wrapper(options.query())?,
+            debouncer: Debouncer::new(options.debounce),
+            timeout: self.timeout,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    fn device(device_id: &str) -> PnpDevice {
+        PnpDevice {
+            device_id: device_id.to_owned(),
+            name: None,
+            description: None,
+            manufacturer: None,
+            pnp_class: None,
+            service: None,
+            status: None,
+        }
+    }
+
+    #[test]
+    fn it_builds_device_queries() {
+        let options = DeviceWatchOptions::new()
+            .poll_interval(Duration::from_millis(500))
+            .pnp_class("USB");
+
+        assert_eq!(
+            options.query(),
+            "SELECT * FROM __InstanceOperationEvent WITHIN 0.5 WHERE TargetInstance ISA 'Win32_PnPEntity' \
+            AND (__CLASS = '__InstanceCreationEvent' OR __CLASS = '__InstanceDeletionEvent') \
+            AND TargetInstance.PNPClass = \"USB\""
+        );
+    }
+
+    #[test]
+    fn it_debounces_device_events() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(Duration::from_secs(1));
+
+        debouncer.push(DeviceEvent::Arrived(device("A")), start);
+        debouncer.push(DeviceEvent::Arrived(device("B")), start);
+        assert_eq!(debouncer.pop_due(start), None);
+
+        // `A` flapped, so it is not reported.
+        debouncer.push(
+            DeviceEvent::Removed(device("A")),
+            start + Duration::from_millis(500),
+        );
+        // A repeated event delays the device.
+        debouncer.push(
+            DeviceEvent::Arrived(device("B")),
+            start + Duration::from_millis(800),
+        );
+        assert_eq!(debouncer.pop_due(start + Duration::from_secs(1)), None);
+
+        assert_eq!(
+            debouncer.next_due(),
+            Some(start + Duration::from_millis(1800))
+        );
+        assert_eq!(
+            debouncer.pop_due(start + Duration::from_secs(2)),
+            Some(DeviceEvent::Arrived(device("B")))
+        );
+        assert_eq!(debouncer.next_due(), None);
+    }
+
+    #[test]
+    fn it_times_out_without_devices() {
+        let mut wmi_con = wmi_con();
+        wmi_con.timeout = Some(Duration::from_secs(1));
+
+        let mut watcher = wmi_con
+            .device_watcher(DeviceWatchOptions::new().pnp_class("wmi-rs-no-such-class"))
+            .unwrap();
+
+        assert!(matches!(watcher.next(), Some(Err(WMIError::Timeout))));
+    }
+}
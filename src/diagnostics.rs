@@ -0,0 +1,214 @@
+//! Classify WMI failures, to help distinguish a broken WMI installation from application bugs.
+//!
+//! This module only reports the detected scenario, it does not attempt to repair the host
+//! (which usually requires `winmgmt /verifyrepository` or restarting the `Winmgmt` service).
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! use wmi::*;
+//! use wmi::diagnostics::check_host;
+//!
+//! if let Some(diagnostic) = check_host(COMLibrary::new()?) {
+//!     if diagnostic.host_issue {
+//!         eprintln!("WMI is broken on this host: {}", diagnostic);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{health::RPC_S_SERVER_UNAVAILABLE_HRESULT, COMLibrary, WMIConnection, WMIError};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use windows::Win32::Foundation::{
+    CO_E_SERVER_EXEC_FAILURE, REGDB_E_CLASSNOTREG, RPC_E_DISCONNECTED,
+};
+use windows::Win32::System::Wmi::{
+    WBEM_E_CRITICAL_ERROR, WBEM_E_INITIALIZATION_FAILURE, WBEM_E_PROVIDER_LOAD_FAILURE,
+    WBEM_E_SHUTTING_DOWN,
+};
+
+/// The `HRESULT` for `ERROR_SERVICE_DISABLED`.
+const SERVICE_DISABLED_HRESULT: i32 = 0x80070422_u32 as i32;
+
+/// The scenario detected from a WMI failure.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[non_exhaustive]
+pub enum FailureKind {
+    /// The WMI repository is likely corrupted (core components failed to initialize).
+    RepositoryCorrupted,
+    /// A provider failed to load, which can indicate a broken provider registration.
+    ProviderLoadFailure,
+    /// The WMI COM classes are not registered on this host.
+    ClassNotRegistered,
+    /// The `Winmgmt` service failed to start.
+    ServiceStartFailure,
+    /// The `Winmgmt` service is disabled.
+    ServiceDisabled,
+    /// The `Winmgmt` service is shutting down.
+    ServiceShuttingDown,
+    /// The WMI service (or the remote computer) cannot be reached.
+    ServiceUnavailable,
+    /// The connection to the WMI service was lost.
+    Disconnected,
+    /// The error is not known to indicate a problem with the host.
+    Other,
+}
+
+/// A structured report of a WMI failure.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// The `HRESULT` of the failure, if there is one.
+    pub hres: Option<i32>,
+    pub kind: FailureKind,
+    /// Whether the failure indicates that WMI is broken on the host, rather than a bug in the application.
+    pub host_issue: bool,
+    pub description: &'static str,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.hres {
+            Some(hres) => write!(f, "{:?} ({:#X}): {}", self.kind, hres, self.description),
+            None => write!(f, "{:?}: {}", self.kind, self.description),
+        }
+    }
+}
+
+impl Diagnostic {
+    /// Classify the given `HRESULT`.
+    pub fn from_hres(hres: i32) -> Self {
+        let (kind, description) = match hres {
+            h if h == WBEM_E_INITIALIZATION_FAILURE.0 || h == WBEM_E_CRITICAL_ERROR.0 => (
+                FailureKind::RepositoryCorrupted,
+                "WMI failed to initialize, the repository might be corrupted",
+            ),
+            h if h == WBEM_E_PROVIDER_LOAD_FAILURE.0 => (
+                FailureKind::ProviderLoadFailure,
+                "A WMI provider failed to load",
+            ),
+            h if h == REGDB_E_CLASSNOTREG.0 => (
+                FailureKind::ClassNotRegistered,
+                "The WMI COM classes are not registered",
+            ),
+            h if h == CO_E_SERVER_EXEC_FAILURE.0 => (
+                FailureKind::ServiceStartFailure,
+                "The Winmgmt service failed to start",
+            ),
+            SERVICE_DISABLED_HRESULT => (
+                FailureKind::ServiceDisabled,
+                "The Winmgmt service is disabled",
+            ),
+            h if h == WBEM_E_SHUTTING_DOWN.0 => (
+                FailureKind::ServiceShuttingDown,
+                "The Winmgmt service is shutting down",
+            ),
+            RPC_S_SERVER_UNAVAILABLE_HRESULT => (
+                FailureKind::ServiceUnavailable,
+                "The WMI service is unavailable",
+            ),
+            h if h == RPC_E_DISCONNECTED.0 => (
+                FailureKind::Disconnected,
+                "The connection to the WMI service was lost",
+            ),
+            _ => (
+                FailureKind::Other,
+                "The error does not indicate a problem with the host",
+            ),
+        };
+
+        Self {
+            hres: Some(hres),
+            host_issue: kind != FailureKind::Other,
+            kind,
+            description,
+        }
+    }
+
+    /// Classify the given error.
+    pub fn from_error(err: &WMIError) -> Self {
+        match err {
+            WMIError::HResultError { hres } => Self::from_hres(*hres),
+            _ => Self {
+                hres: None,
+                kind: FailureKind::Other,
+                host_issue: false,
+                description: "The error does not indicate a problem with the host",
+            },
+        }
+    }
+}
+
+impl WMIError {
+    /// Classify this error, see [`Diagnostic`].
+    pub fn diagnose(&self) -> Diagnostic {
+        Diagnostic::from_error(self)
+    }
+}
+
+/// Check that WMI is usable on the local host, by connecting to the `CIMV2` namespace and running a trivial query.
+///
+/// Returns `None` if everything works, or a [`Diagnostic`] of the first failure.
+pub fn check_host(com_lib: COMLibrary) -> Option<Diagnostic> {
+    let result = WMIConnection::new(com_lib).and_then(|con| {
+        con.ping()?;
+
+        let _: Vec<HashMap<String, crate::Variant>> =
+            con.raw_query("SELECT Caption FROM Win32_OperatingSystem")?;
+
+        Ok(())
+    });
+
+    result.err().map(|err| err.diagnose())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use windows::Win32::System::Wmi::WBEM_E_INVALID_CLASS;
+
+    #[test]
+    fn it_classifies_host_failures() {
+        let diagnostic = Diagnostic::from_hres(WBEM_E_INITIALIZATION_FAILURE.0);
+        assert_eq!(diagnostic.kind, FailureKind::RepositoryCorrupted);
+        assert!(diagnostic.host_issue);
+
+        let diagnostic = Diagnostic::from_hres(0x80080005_u32 as i32);
+        assert_eq!(diagnostic.kind, FailureKind::ServiceStartFailure);
+
+        let diagnostic = Diagnostic::from_hres(0x800706BA_u32 as i32);
+        assert_eq!(diagnostic.kind, FailureKind::ServiceUnavailable);
+    }
+
+    #[test]
+    fn it_classifies_application_failures() {
+        let diagnostic = WMIError::HResultError {
+            hres: WBEM_E_INVALID_CLASS.0,
+        }
+        .diagnose();
+        assert_eq!(diagnostic.kind, FailureKind::Other);
+        assert!(!diagnostic.host_issue);
+
+        let diagnostic = WMIError::ResultEmpty.diagnose();
+        assert_eq!(diagnostic.hres, None);
+        assert!(!diagnostic.host_issue);
+    }
+
+    #[test]
+    fn it_serializes_diagnostics() {
+        let diagnostic = Diagnostic::from_hres(WBEM_E_PROVIDER_LOAD_FAILURE.0);
+        let json = serde_json::to_value(&diagnostic).unwrap();
+
+        assert_eq!(json["kind"], "ProviderLoadFailure");
+        assert_eq!(json["host_issue"], true);
+    }
+
+    #[test]
+    fn it_finds_no_issue_on_healthy_host() {
+        let _ = wmi_con();
+        let com_lib = unsafe { COMLibrary::assume_initialized() };
+
+        assert_eq!(check_host(com_lib), None);
+    }
+}
@@ -0,0 +1,437 @@
+//! Run callbacks for the events of many subscriptions, on a single thread.
+//!
+//! Each event iterator (or stream) needs its own thread (or task) to wait for events.
+//! Agents with many subscriptions can instead register a callback for each of them with a [`Dispatcher`]:
+//! all the sinks send their events to a single dispatch thread, which deserializes each event
+//! into the type of its subscription and calls the matching callback.
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use serde::Deserialize;
+//! use std::{collections::HashMap, time::Duration};
+//!
+//! #[derive(Deserialize, Debug)]
+//! #[serde(rename_all = "PascalCase")]
+//! struct Win32_Process {
+//!     name: String,
+//! }
+//!
+//! #[derive(Deserialize, Debug)]
+//! #[serde(rename_all = "PascalCase")]
+//! struct __InstanceCreationEvent {
+//!     target_instance: Win32_Process,
+//! }
+//!
+//! let mut dispatcher = con.dispatcher()?;
+//!
+//! let mut filters = HashMap::new();
+//! filters.insert("TargetInstance".to_owned(), FilterValue::is_a::<Win32_Process>()?);
+//!
+//! dispatcher.subscribe_filtered(&filters, Some(Duration::from_secs(1)), |event: WMIResult<__InstanceCreationEvent>| {
+//!     match event {
+//!         Ok(event) => println!("{} started", event.target_instance.name),
+//!         Err(err) => eprintln!("Subscription failed: {}", err),
+//!     }
+//! })?;
+//!
+//! dispatcher.subscribe("SELECT * FROM Win32_PowerManagementEvent", |event: WMIResult<HashMap<String, Variant>>| {
+//!     println!("Power event: {:?}", event);
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Callbacks are called one at a time, so a slow callback delays the events of the other subscriptions.
+//! Dropping the dispatcher cancels all the subscriptions, and waits for the current callback to return.
+use crate::{
+    build_notification_query, connection::WMIConnection, prefetch::InMta,
+    result_enumerator::IWbemClassWrapper, COMLibrary, FilterValue, WMIError, WMIResult,
+};
+use log::{debug, trace};
+use serde::de;
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use windows::core::{implement, Result as WinResult, BSTR, HRESULT};
+use windows::Win32::Foundation::E_POINTER;
+use windows::Win32::System::Wmi::{
+    IWbemClassObject, IWbemObjectSink, IWbemObjectSink_Impl, WBEM_E_CALL_CANCELLED,
+    WBEM_STATUS_COMPLETE,
+};
+
+/// Identifies a subscription of a [`Dispatcher`], to [`unsubscribe`](Dispatcher::unsubscribe) it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// Deserializes the events of a subscription, and calls its callback.
+type Route = Box<dyn FnMut(WMIResult<IWbemClassWrapper>) + Send>;
+
+type Routes = Arc<Mutex<HashMap<SubscriptionId, Route>>>;
+
+enum Message {
+    Event(SubscriptionId, WMIResult<InMta<IWbemClassWrapper>>),
+    Stop,
+}
+
+/// Multiplexes many subscriptions on a single dispatch thread, see the [module level documentation](crate::dispatcher).
+pub struct Dispatcher {
+    con: WMIConnection,
+    routes: Routes,
+    sinks: HashMap<SubscriptionId, IWbemObjectSink>,
+    sender: Sender<Message>,
+    thread: Option<JoinHandle<()>>,
+    next_id: u64,
+}
+
+///
+/// ### Additional dispatcher methods
+///
+impl WMIConnection {
+    /// Start a dispatch thread for the callbacks of subscriptions made using this connection.
+    ///
+    /// The dispatch thread uses the multithreaded apartment, so subscriptions must be made from it
+    /// (as initialized by [`COMLibrary::new`]). Otherwise, the callbacks receive `RPC_E_WRONG_THREAD` errors.
+    ///
+    /// See the [module level documentation](crate::dispatcher) for an example.
+    pub fn dispatcher(&self) -> WMIResult<Dispatcher> {
+        let routes = Routes::default();
+        let (sender, receiver) = channel();
+
+        let thread = {
+            let routes = routes.clone();
+
+            thread::Builder::new()
+                .name("wmi-dispatcher".to_owned())
+                .spawn(move || dispatch(receiver, routes))?
+        };
+
+        Ok(Dispatcher {
+            con: self.clone(),
+            routes,
+            sinks: HashMap::new(),
+            sender,
+            thread: Some(thread),
+            next_id: 0,
+        })
+    }
+}
+
+impl Dispatcher {
+    /// Subscribe to the events of a notification query, and call `callback` with each of them (deserialized into `T`).
+    ///
+    /// Errors of the subscription (like a provider failure after the subscription was made)
+    /// are also passed to the callback, after which no more events are received.
+    pub fn subscribe<T, F>(
+        &mut self,
+        query: impl AsRef<str>,
+        mut callback: F,
+    ) -> WMIResult<SubscriptionId>
+    where
+        T: de::DeserializeOwned,
+        F: FnMut(WMIResult<T>) + Send + 'static,
+    {
+        let options = self.con.de_options.clone();
+
+        let route: Route = Box::new(move |event: WMIResult<IWbemClassWrapper>| {
+            callback(event.and_then(|event| event.into_desr_with_options(&options, None)))
+        });
+
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+
+        // Register the route first, so that no event is missed.
+        self.routes.lock().unwrap().insert(id, route);
+
+        let sink: IWbemObjectSink = DispatchSink {
+            id,
+            sender: Mutex::new(self.sender.clone()),
+        }
+        .into();
+
+        let query_language = BSTR::from("WQL");
+        let query = BSTR::from(query.as_ref());
+
+        let res = unsafe {
+            self.con.svc.ExecNotificationQueryAsync(
+                &query_language,
+                &query,
+                0,
+                self.con.ctx(),
+                &sink,
+            )
+        };
+
+        if let Err(err) = res {
+            self.routes.lock().unwrap().remove(&id);
+            return Err(err.into());
+        }
+
+        debug!("Subscribed {:?} to {}", id, query);
+        self.sinks.insert(id, sink);
+
+        Ok(id)
+    }
+
+    /// Like [`subscribe`](Dispatcher::subscribe), building the query from the name of `T` and the filters
+    /// (like [`WMIConnection::filtered_notification`]).
+    pub fn subscribe_filtered<T, F>(
+        &mut self,
+        filters: &HashMap<String, FilterValue>,
+        within: Option<Duration>,
+        callback: F,
+    ) -> WMIResult<SubscriptionId>
+    where
+        T: de::DeserializeOwned,
+        F: FnMut(WMIResult<T>) + Send + 'static,
+    {
+        let query = build_notification_query::<T>(Some(filters), within)?;
+
+        self.subscribe(query, callback)
+    }
+
+    /// Cancel a subscription. Returns `false` if it was already cancelled.
+    ///
+    /// The events of the subscription which were not dispatched yet are dropped.
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let sink = match self.sinks.remove(&id) {
+            Some(sink) => sink,
+            None => return false,
+        };
+
+        let _r = unsafe { self.con.svc.CancelAsyncCall(&sink) };
+        self.routes.lock().unwrap().remove(&id);
+
+        true
+    }
+
+    /// The number of active subscriptions.
+    pub fn len(&self) -> usize {
+        self.sinks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+}
+
+impl Drop for Dispatcher {
+    fn drop(&mut self) {
+        for sink in self.sinks.values() {
+            let _r = unsafe { self.con.svc.CancelAsyncCall(sink) };
+        }
+
+        let _r = self.sender.send(Message::Stop);
+
+        if let Some(thread) = self.thread.take() {
+            let _r = thread.join();
+        }
+    }
+}
+
+fn dispatch(receiver: Receiver<Message>, routes: Routes) {
+    // Deserializing events uses COM.
+    if let Err(e) = COMLibrary::without_security() {
+        debug!("Dispatcher failed to initialize COM: {}", e);
+        return;
+    }
+
+    while let Ok(message) = receiver.recv() {
+        let (id, event) = match message {
+            Message::Event(id, event) => (id, event),
+            Message::Stop => break,
+        };
+
+        let mut routes = routes.lock().unwrap();
+
+        match routes.get_mut(&id) {
+            Some(route) => route(event.map(InMta::into_inner)),
+            None => trace!("Dropping event of cancelled {:?}", id),
+        }
+    }
+
+    debug!("Dispatcher stopped");
+}
+
+/// Sends the events of a subscription to the dispatch thread.
+#[implement(IWbemObjectSink)]
+struct DispatchSink {
+    id: SubscriptionId,
+    sender: Mutex<Sender<Message>>,
+}
+
+impl DispatchSink {
+    fn send(&self, event: WMIResult<IWbemClassWrapper>) {
+        // Events are received in the apartment of the subscribing thread, so they can only be dispatched from the MTA.
+        let event = event.and_then(InMta::new);
+
+        // The dispatcher was dropped.
+        let _r = self
+            .sender
+            .lock()
+            .unwrap()
+            .send(Message::Event(self.id, event));
+    }
+}
+
+impl IWbemObjectSink_Impl for DispatchSink {
+    fn Indicate(
+        &self,
+        lObjectCount: i32,
+        apObjArray: *const Option<IWbemClassObject>,
+    ) -> WinResult<()> {
+        if lObjectCount <= 0 {
+            return Ok(());
+        }
+
+        // Safety: see `QuerySink::Indicate`.
+        let objs = unsafe { std::slice::from_raw_parts(apObjArray, lObjectCount as usize) };
+        let mut res = Ok(());
+
+        for obj in objs {
+            match obj {
+                Some(obj) => self.send(Ok(IWbemClassWrapper::new(obj.clone()))),
+                None => {
+                    res = Err(E_POINTER.into());
+                    self.send(Err(WMIError::NullPointerResult));
+                }
+            }
+        }
+
+        res
+    }
+
+    fn SetStatus(
+        &self,
+        lFlags: i32,
+        hResult: HRESULT,
+        _strParam: &BSTR,
+        _pObjParam: Option<&IWbemClassObject>,
+    ) -> WinResult<()> {
+        // Notification queries only complete when they are cancelled, or when they fail.
+        if lFlags == WBEM_STATUS_COMPLETE.0
+            && hResult.is_err()
+            && hResult.0 != WBEM_E_CALL_CANCELLED.0
+        {
+            self.send(Err(WMIError::HResultError { hres: hResult.0 }));
+        }
+
+        Ok(())
+    }
+}
+
+#[allow(non_snake_case)]
+#[allow(non_camel_case_types)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{fixtures::*, start_test_program};
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug)]
+    struct Win32_Process {
+        Name: String,
+        ParentProcessId: u32,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct __InstanceCreationEvent {
+        TargetInstance: Win32_Process,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct __InstanceDeletionEvent {
+        TargetInstance: Win32_Process,
+    }
+
+    fn is_test_program(process: &Win32_Process) -> bool {
+        process.Name.eq_ignore_ascii_case("cmd.exe")
+            && process.ParentProcessId == std::process::id()
+    }
+
+    #[test]
+    fn it_routes_events_to_callbacks() {
+        let wmi_con = wmi_con();
+        let mut dispatcher = wmi_con.dispatcher().unwrap();
+        let (sender, receiver) = channel();
+
+        let mut filters = HashMap::new();
+        filters.insert(
+            "TargetInstance".to_owned(),
+            FilterValue::is_a::<Win32_Process>().unwrap(),
+        );
+
+        let created = sender.clone();
+        dispatcher
+            .subscribe_filtered(
+                &filters,
+                Some(Duration::from_millis(500)),
+                move |event: WMIResult<__InstanceCreationEvent>| {
+                    if is_test_program(&event.unwrap().TargetInstance) {
+                        let _r = created.send("created");
+                    }
+                },
+            )
+            .unwrap();
+
+        let deleted = sender;
+        dispatcher
+            .subscribe_filtered(
+                &filters,
+                Some(Duration::from_millis(500)),
+                move |event: WMIResult<__InstanceDeletionEvent>| {
+                    if is_test_program(&event.unwrap().TargetInstance) {
+                        let _r = deleted.send("deleted");
+                    }
+                },
+            )
+            .unwrap();
+
+        assert_eq!(dispatcher.len(), 2);
+
+        start_test_program();
+
+        let timeout = Duration::from_secs(10);
+        assert_eq!(receiver.recv_timeout(timeout), Ok("created"));
+        assert_eq!(receiver.recv_timeout(timeout), Ok("deleted"));
+    }
+
+    #[test]
+    fn it_unsubscribes() {
+        let wmi_con = wmi_con();
+        let mut dispatcher = wmi_con.dispatcher().unwrap();
+
+        let id = dispatcher
+            .subscribe(
+                "SELECT * FROM __InstanceCreationEvent WITHIN 1 WHERE TargetInstance ISA 'Win32_Process'",
+                |_: WMIResult<HashMap<String, crate::Variant>>| {},
+            )
+            .unwrap();
+
+        assert!(dispatcher.unsubscribe(id));
+        assert!(!dispatcher.unsubscribe(id));
+        assert!(dispatcher.is_empty());
+        assert!(dispatcher.routes.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_fails_to_subscribe_to_invalid_queries() {
+        let wmi_con = wmi_con();
+        let mut dispatcher = wmi_con.dispatcher().unwrap();
+
+        let res = dispatcher.subscribe(
+            "SELECT * FROM Win32_NoSuchEvent_wmi_rs",
+            |_: WMIResult<HashMap<String, crate::Variant>>| {},
+        );
+
+        assert!(matches!(res, Err(WMIError::HResultError { .. })));
+        assert!(dispatcher.routes.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,226 @@
+//! Timestamps of events: when the provider created them, and when they were received.
+//!
+//! Every event has a `TIME_CREATED` property, set by the provider (or by WMI for polled intrinsic events) as a `FILETIME`
+//! (in 100-nanosecond intervals since January 1, 1601 UTC), using the clock of the computer which created the event.
+//! [`WMIConnection::timed_notification`] also records when each event was received using the local clock,
+//! which tells how late an event was delivered (or, for remote connections, how skewed the clocks are):
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # use std::collections::HashMap;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! let events = con.timed_notification::<HashMap<String, Variant>>(
+//!     "SELECT * FROM __InstanceCreationEvent WITHIN 1 WHERE TargetInstance ISA 'Win32_Process'",
+//! )?;
+//!
+//! for event in events {
+//!     let event = event?;
+//!
+//!     match event.delay() {
+//!         Some(Ok(delay)) => println!("Received {:?} after its creation", delay),
+//!         Some(Err(ahead)) => println!("The clock of the provider is {:?} ahead", ahead),
+//!         None => println!("The event has no creation time"),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Typed events which deserialize `TIME_CREATED` themselves can convert it using [`filetime_to_system_time`].
+use crate::{connection::WMIConnection, result_enumerator::IWbemClassWrapper, Variant, WMIResult};
+use futures::{Stream, StreamExt};
+use serde::de;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The number of 100-nanosecond intervals between 1601-01-01 (the `FILETIME` epoch) and 1970-01-01.
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+const FILETIME_TICKS_PER_SECOND: u64 = 10_000_000;
+
+/// Convert a `FILETIME` (like the `TIME_CREATED` of an event) to a [`SystemTime`].
+///
+/// ```edition2018
+/// # use wmi::event_time::filetime_to_system_time;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// assert_eq!(filetime_to_system_time(116_444_736_010_000_000), UNIX_EPOCH + Duration::from_secs(1));
+/// ```
+pub fn filetime_to_system_time(filetime: u64) -> SystemTime {
+    let ticks_to_duration = |ticks: u64| {
+        Duration::new(
+            ticks / FILETIME_TICKS_PER_SECOND,
+            (ticks % FILETIME_TICKS_PER_SECOND) as u32 * 100,
+        )
+    };
+
+    if filetime >= FILETIME_UNIX_EPOCH {
+        UNIX_EPOCH + ticks_to_duration(filetime - FILETIME_UNIX_EPOCH)
+    } else {
+        UNIX_EPOCH - ticks_to_duration(FILETIME_UNIX_EPOCH - filetime)
+    }
+}
+
+/// An event, with the time it was created (by the provider) and received (locally).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedEvent<T> {
+    pub event: T,
+    /// When the event was received, using the local clock.
+    pub received: SystemTime,
+    /// The raw `TIME_CREATED` of the event, see [`TimedEvent::created`].
+    pub time_created: Option<u64>,
+}
+
+impl<T> TimedEvent<T> {
+    /// When the event was created, using the clock of the computer which created it.
+    pub fn created(&self) -> Option<SystemTime> {
+        self.time_created.map(filetime_to_system_time)
+    }
+
+    /// How long after its creation the event was received.
+    ///
+    /// If the event was created after it was received (according to the clocks), the clock of the provider is ahead
+    /// of the local clock, and `Err` holds the difference.
+    pub fn delay(&self) -> Option<Result<Duration, Duration>> {
+        let created = self.created()?;
+
+        Some(
+            self.received
+                .duration_since(created)
+                .map_err(|err| err.duration()),
+        )
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn created_datetime(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.created().map(Into::into)
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn received_datetime(&self) -> chrono::DateTime<chrono::Utc> {
+        self.received.into()
+    }
+
+    /// Convert the event, keeping its timestamps.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> TimedEvent<U> {
+        TimedEvent {
+            event: f(self.event),
+            received: self.received,
+            time_created: self.time_created,
+        }
+    }
+}
+
+///
+/// ### Additional event time methods
+///
+impl WMIConnection {
+    /// Like [`raw_notification`](WMIConnection::raw_notification), with the creation and receipt times of each event.
+    ///
+    /// See the [module level documentation](crate::event_time) for an example.
+    pub fn timed_notification<'a, T>(
+        &'a self,
+        query: impl AsRef<str>,
+    ) -> WMIResult<impl Iterator<Item = WMIResult<TimedEvent<T>>> + 'a>
+    where
+        T: de::DeserializeOwned + 'a,
+    {
+        Ok(self
+            .notification_native_wrapper(query)?
+            .map(move |event| timed_event(event?, SystemTime::now(), self)))
+    }
+
+    /// Like [`timed_notification`](WMIConnection::timed_notification), returning a stream of events.
+    pub fn async_timed_notification<T>(
+        &self,
+        query: impl AsRef<str>,
+    ) -> WMIResult<impl Stream<Item = WMIResult<TimedEvent<T>>>>
+    where
+        T: de::DeserializeOwned,
+    {
+        let con = self.clone();
+
+        Ok(self
+            .async_notification_native_wrapper(query)?
+            .map(move |event| timed_event(event?, SystemTime::now(), &con)))
+    }
+}
+
+fn timed_event<T>(
+    event: IWbemClassWrapper,
+    received: SystemTime,
+    con: &WMIConnection,
+) -> WMIResult<TimedEvent<T>>
+where
+    T: de::DeserializeOwned,
+{
+    // `TIME_CREATED` is defined by `__Event`, but can be excluded by the query (or be null).
+    let time_created = match event.get_property("TIME_CREATED") {
+        Ok(Variant::UI8(time_created)) => Some(time_created),
+        _ => None,
+    };
+
+    Ok(TimedEvent {
+        event: event.into_desr_with_options(&con.de_options, None)?,
+        received,
+        time_created,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn it_converts_filetimes() {
+        assert_eq!(filetime_to_system_time(FILETIME_UNIX_EPOCH), UNIX_EPOCH);
+        assert_eq!(
+            filetime_to_system_time(FILETIME_UNIX_EPOCH + 15),
+            UNIX_EPOCH + Duration::from_nanos(1500)
+        );
+        assert_eq!(
+            filetime_to_system_time(0),
+            UNIX_EPOCH - Duration::from_secs(11_644_473_600)
+        );
+    }
+
+    #[test]
+    fn it_measures_delays() {
+        let event = TimedEvent {
+            event: (),
+            received: UNIX_EPOCH + Duration::from_secs(10),
+            time_created: Some(FILETIME_UNIX_EPOCH + 8 * FILETIME_TICKS_PER_SECOND),
+        };
+        assert_eq!(event.delay(), Some(Ok(Duration::from_secs(2))));
+
+        let event = TimedEvent {
+            time_created: Some(FILETIME_UNIX_EPOCH + 13 * FILETIME_TICKS_PER_SECOND),
+            ..event
+        };
+        assert_eq!(event.delay(), Some(Err(Duration::from_secs(3))));
+
+        let event = TimedEvent {
+            time_created: None,
+            ..event
+        };
+        assert_eq!(event.delay(), None);
+        assert_eq!(event.map(|()| 1).event, 1);
+    }
+
+    #[test]
+    fn it_times_events() {
+        let wmi_con = wmi_con();
+
+        let mut events = wmi_con
+            .timed_notification::<HashMap<String, Variant>>(
+                "SELECT * FROM __InstanceModificationEvent WHERE TargetInstance ISA 'Win32_LocalTime'",
+            )
+            .unwrap();
+
+        let event = events.next().unwrap().unwrap();
+
+        assert!(event.event.contains_key("TargetInstance"));
+        assert!(matches!(event.delay(), Some(Ok(delay)) if delay < Duration::from_secs(60)));
+    }
+}
@@ -0,0 +1,235 @@
+//! Export query results as CSV or JSON Lines, for data-collection tools.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use wmi::export::Format;
+//!
+//! let mut out = Vec::new();
+//! let rows = con.export_query("SELECT Name, ProcessId FROM Win32_Process", Format::Csv, &mut out)?;
+//!
+//! let csv = String::from_utf8(out).unwrap();
+//! assert!(csv.starts_with("Name,ProcessId\r\n"));
+//! assert_eq!(csv.lines().count(), rows + 1);
+//! #   Ok(())
+//! # }
+//! ```
+//!
+//! Values are converted like [`Variant::to_json_value`](crate::Variant::to_json_value) does.
+use crate::{
+    query::{query_class, select_projection},
+    result_enumerator::IWbemClassWrapper,
+    WMIConnection, WMIError, WMIResult,
+};
+use serde_json::Value;
+use std::io::Write;
+use windows::Win32::System::Wmi::WBEM_E_NOT_FOUND;
+
+/// The output format of [`WMIConnection::export_query`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Format {
+    /// Comma separated values (RFC 4180), with a header row.
+    ///
+    /// The columns are the selected properties, or the properties of the first row
+    /// (or of the queried class, if there are no rows) when selecting `*`.
+    /// Properties missing from a row, as well as null values, are written as empty cells.
+    /// Arrays and embedded objects are written as JSON.
+    Csv,
+    /// A JSON object per line, holding all the properties of the row.
+    JsonLines,
+}
+
+impl WMIConnection {
+    /// Execute a query and write the results to `writer`, one row at a time.
+    ///
+    /// Returns the number of rows which were written.
+    /// Since rows are written as they arrive, consider wrapping the writer with a [`std::io::BufWriter`].
+    pub fn export_query(
+        &self,
+        query: impl AsRef<str>,
+        format: Format,
+        mut writer: impl Write,
+    ) -> WMIResult<usize> {
+        let query = query.as_ref();
+        let mut rows = self.exec_query_native_wrapper(query)?.peekable();
+
+        let mut count = 0;
+
+        match format {
+            Format::Csv => {
+                let columns = match select_projection(query) {
+                    Some(projection) => projection,
+                    None => match rows.peek() {
+                        Some(Ok(first)) => first.list_properties()?,
+                        Some(Err(_)) => vec![],
+                        None => self.class_properties(query),
+                    },
+                };
+
+                let header: Vec<String> = columns.iter().map(|column| csv_cell(column)).collect();
+                write!(writer, "{}\r\n", header.join(","))?;
+
+                for row in rows {
+                    let row = row?;
+
+                    let mut cells = Vec::with_capacity(columns.len());
+
+                    for column in &columns {
+                        cells.push(csv_cell(&csv_value(&row, column)?));
+                    }
+
+                    write!(writer, "{}\r\n", cells.join(","))?;
+                    count += 1;
+                }
+            }
+            Format::JsonLines => {
+                for row in rows {
+                    let value = row?.to_json_value()?;
+
+                    serde_json::to_writer(&mut writer, &value)
+                        .map_err(|e| WMIError::SerdeError(e.to_string()))?;
+                    writer.write_all(b"\n")?;
+                    count += 1;
+                }
+            }
+        }
+
+        writer.flush()?;
+
+        Ok(count)
+    }
+
+    /// The properties of the class the query selects from, or none if it cannot be found.
+    fn class_properties(&self, query: &str) -> Vec<String> {
+        let class = query_class(query);
+
+        class
+            .and_then(|class| self.get_raw_by_path(class).ok())
+            .and_then(|class| class.list_properties().ok())
+            .unwrap_or_default()
+    }
+}
+
+/// The text of a cell, before quoting.
+fn csv_value(row: &IWbemClassWrapper, column: &str) -> WMIResult<String> {
+    let value = match row.get_property(column) {
+        Ok(value) => value.to_json_value()?,
+        Err(WMIError::HResultError { hres }) if hres == WBEM_E_NOT_FOUND.0 => Value::Null,
+        Err(e) => return Err(e),
+    };
+
+    let text = match value {
+        Value::Null => String::new(),
+        Value::String(s) => s,
+        other => other.to_string(),
+    };
+
+    Ok(text)
+}
+
+/// Quote the value if it contains a separator, a quote or a line break.
+fn csv_cell(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    #[test]
+    fn it_quotes_csv_cells() {
+        assert_eq!(csv_cell("plain"), "plain");
+        assert_eq!(csv_cell("a,b"), "\"a,b\"");
+        assert_eq!(csv_cell("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_cell("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn it_exports_csv() {
+        let wmi_con = wmi_con();
+
+        let mut out = Vec::new();
+        let rows = wmi_con
+            .export_query(
+                "SELECT Caption, MUILanguages FROM Win32_OperatingSystem",
+                Format::Csv,
+                &mut out,
+            )
+            .unwrap();
+
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = csv.split("\r\n").collect();
+
+        assert_eq!(rows, 1);
+        assert_eq!(lines[0], "Caption,MUILanguages");
+        // The languages are written as a quoted JSON array.
+        assert!(lines[1].ends_with("\"]\""));
+        assert_eq!(lines[2], "");
+    }
+
+    #[test]
+    fn it_infers_csv_columns() {
+        let wmi_con = wmi_con();
+
+        let mut out = Vec::new();
+        wmi_con
+            .export_query("SELECT * FROM Win32_OperatingSystem", Format::Csv, &mut out)
+            .unwrap();
+
+        let header = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap()
+            .to_owned();
+        assert!(header.split(',').any(|column| column == "Caption"));
+
+        // Without any rows, the columns are taken from the class.
+        let mut out = Vec::new();
+        let rows = wmi_con
+            .export_query(
+                "SELECT * FROM Win32_Process WHERE ProcessId = 4294967295",
+                Format::Csv,
+                &mut out,
+            )
+            .unwrap();
+
+        let csv = String::from_utf8(out).unwrap();
+        assert_eq!(rows, 0);
+        assert!(csv
+            .lines()
+            .next()
+            .unwrap()
+            .split(',')
+            .any(|c| c == "ProcessId"));
+    }
+
+    #[test]
+    fn it_exports_json_lines() {
+        let wmi_con = wmi_con();
+
+        let mut out = Vec::new();
+        let rows = wmi_con
+            .export_query(
+                "SELECT Name, ProcessId FROM Win32_Process",
+                Format::JsonLines,
+                &mut out,
+            )
+            .unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.lines().count(), rows);
+
+        for line in text.lines() {
+            let value: Value = serde_json::from_str(line).unwrap();
+            assert!(value["ProcessId"].is_u64());
+        }
+    }
+}
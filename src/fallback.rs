@@ -0,0 +1,190 @@
+//! Fall back to other queries (or namespaces) when a class is not available.
+//!
+//! Newer classes (like the `MSFT_*` classes of `ROOT\StandardCimv2`) are not available on every version of Windows,
+//! and are often read with a fallback to a legacy class of `ROOT\CIMV2`.
+//! [`WMIConnection::query_first_available`] tries each query in order, and returns the results of the first one which succeeds:
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, Debug)]
+//! #[serde(rename_all = "PascalCase")]
+//! struct Adapter {
+//!     name: String,
+//! }
+//!
+//! let adapters: Vec<Adapter> = con.query_first_available(&[
+//!     ("ROOT\\StandardCimv2", "SELECT Name FROM MSFT_NetAdapter"),
+//!     ("ROOT\\CIMV2", "SELECT Name FROM Win32_NetworkAdapter WHERE NetEnabled = TRUE"),
+//! ])?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Queries given without a namespace use the namespace of the connection.
+//! Since the results of all the queries are deserialized into the same type, the queries should select
+//! properties with the same names (or the type can use `#[serde(alias = "...")]`).
+use crate::{connection::WMIConnection, WMIError, WMIResult};
+use log::debug;
+use serde::de;
+
+/// A query to try, optionally in another namespace than the one of the connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FallbackQuery {
+    pub namespace: Option<String>,
+    pub query: String,
+}
+
+impl FallbackQuery {
+    /// A query in the namespace of the connection.
+    pub fn new(query: impl Into<String>) -> Self {
+        Self {
+            namespace: None,
+            query: query.into(),
+        }
+    }
+
+    /// Run the query in the given namespace (like `ROOT\StandardCimv2`), on the same computer as the connection.
+    pub fn namespace(mut self, namespace_path: impl Into<String>) -> Self {
+        self.namespace = Some(namespace_path.into());
+        self
+    }
+}
+
+impl From<&str> for FallbackQuery {
+    fn from(query: &str) -> Self {
+        Self::new(query)
+    }
+}
+
+impl From<String> for FallbackQuery {
+    fn from(query: String) -> Self {
+        Self::new(query)
+    }
+}
+
+/// A `(namespace, query)` pair.
+impl From<(&str, &str)> for FallbackQuery {
+    fn from((namespace_path, query): (&str, &str)) -> Self {
+        Self::new(query).namespace(namespace_path)
+    }
+}
+
+///
+/// ### Additional fallback methods
+///
+impl WMIConnection {
+    /// Run each query in order, and return the results of the first one which succeeds
+    /// (even if it returns no results).
+    ///
+    /// A query fails if its namespace or class doesn't exist, or if its results cannot be deserialized into `T`.
+    /// If all of them fail, the error of the last one is returned (and [`WMIError::ResultEmpty`] if no queries are given).
+    ///
+    /// See the [module level documentation](crate::fallback) for an example.
+    pub fn query_first_available<T, Q>(&self, queries: &[Q]) -> WMIResult<Vec<T>>
+    where
+        T: de::DeserializeOwned,
+        Q: Clone + Into<FallbackQuery>,
+    {
+        let mut last_err = WMIError::ResultEmpty;
+
+        for (index, query) in queries.iter().enumerate() {
+            let query: FallbackQuery = query.clone().into();
+
+            match self.try_fallback_query(&query) {
+                Ok(results) => {
+                    debug!("Using query #{}: {:?}", index, query);
+                    return Ok(results);
+                }
+                Err(err) => {
+                    debug!("Query #{} {:?} failed: {}", index, query, err);
+                    last_err = err;
+                }
+            }
+        }
+
+        Err(last_err)
+    }
+
+    fn try_fallback_query<T>(&self, query: &FallbackQuery) -> WMIResult<Vec<T>>
+    where
+        T: de::DeserializeOwned,
+    {
+        match &query.namespace {
+            Some(namespace_path) => self.with_namespace(namespace_path)?.raw_query(&query.query),
+            None => self.raw_query(&query.query),
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+#[allow(non_camel_case_types)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug)]
+    struct Process {
+        ProcessId: u32,
+    }
+
+    #[test]
+    fn it_converts_fallback_queries() {
+        assert_eq!(
+            FallbackQuery::from("SELECT * FROM Win32_Process"),
+            FallbackQuery::new("SELECT * FROM Win32_Process")
+        );
+        assert_eq!(
+            FallbackQuery::from(("ROOT\\StandardCimv2", "SELECT * FROM MSFT_NetAdapter")),
+            FallbackQuery {
+                namespace: Some("ROOT\\StandardCimv2".to_owned()),
+                query: "SELECT * FROM MSFT_NetAdapter".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn it_falls_back_to_the_first_available_query() {
+        let wmi_con = wmi_con();
+
+        let processes: Vec<Process> = wmi_con
+            .query_first_available(&[
+                "SELECT ProcessId FROM Win32_NoSuchClass_wmi_rs",
+                "SELECT ProcessId FROM Win32_Process",
+            ])
+            .unwrap();
+        assert!(processes
+            .iter()
+            .any(|process| process.ProcessId == std::process::id()));
+
+        let processes: Vec<Process> = wmi_con
+            .query_first_available(&[
+                (
+                    "ROOT\\NoSuchNamespace_wmi_rs",
+                    "SELECT ProcessId FROM Win32_Process",
+                ),
+                ("ROOT\\CIMV2", "SELECT ProcessId FROM Win32_Process"),
+            ])
+            .unwrap();
+        assert!(!processes.is_empty());
+    }
+
+    #[test]
+    fn it_returns_the_last_error() {
+        let wmi_con = wmi_con();
+
+        let res: WMIResult<Vec<Process>> = wmi_con.query_first_available(&[
+            "SELECT ProcessId FROM Win32_NoSuchClass_wmi_rs",
+            "SELECT NoSuchProperty FROM Win32_Process",
+        ]);
+        assert!(matches!(res, Err(WMIError::HResultError { .. })));
+
+        let res: WMIResult<Vec<Process>> = wmi_con.query_first_available::<_, &str>(&[]);
+        assert!(matches!(res, Err(WMIError::ResultEmpty)));
+    }
+}
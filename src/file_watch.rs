@@ -0,0 +1,320 @@
+//! Watch the files of a directory being created, deleted and modified.
+//!
+//! WMI polls the `CIM_DataFile` instances of the directory (using `__InstanceOperationEvent`s), which are selected
+//! by their `Drive` and `Path` properties. These have an unusual format (`C:` and `\Logs\`, with a trailing backslash),
+//! and the backslashes must be escaped in the query, so [`FileWatch`] builds the query from a regular path:
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use std::time::Duration;
+//! use wmi::file_watch::{FileEvent, FileWatch};
+//!
+//! let watch = FileWatch::new(r"C:\Logs")
+//!     .extension("log")
+//!     .interval(Duration::from_secs(5));
+//!
+//! for event in con.file_events(&watch)? {
+//!     match event? {
+//!         FileEvent::Created(file) => println!("New log file {}", file.name),
+//!         FileEvent::Deleted(file) => println!("Deleted {}", file.name),
+//!         FileEvent::Modified { new, .. } => println!("{} is now {:?} bytes", new.name, new.file_size),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Only the files directly in the directory are watched (not the files of its subdirectories),
+//! and the directory must be on a local drive. Use [`FileWatch::directories`] to watch the subdirectories
+//! (from `Win32_Directory`) instead of the files.
+use crate::{connection::WMIConnection, query::quote_and_escape_wql_str, WMIError, WMIResult};
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use std::{path::Path, time::Duration};
+
+/// A file (or directory), from `CIM_DataFile` (or `Win32_Directory`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "CIM_DataFile")]
+#[serde(rename_all = "PascalCase")]
+pub struct FileEntry {
+    /// The full path, like `c:\logs\app.log` (WMI returns paths in lowercase).
+    pub name: String,
+    /// The name without the extension, like `app`.
+    pub file_name: Option<String>,
+    /// The extension without a dot, like `log`.
+    pub extension: Option<String>,
+    /// The size in bytes (not set for directories).
+    pub file_size: Option<u64>,
+}
+
+/// A file was created, deleted or modified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileEvent {
+    Created(FileEntry),
+    Deleted(FileEntry),
+    /// Any property of the file changed, including its last access time.
+    Modified {
+        old: FileEntry,
+        new: FileEntry,
+    },
+}
+
+/// The files to watch, see [`WMIConnection::file_events`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileWatch {
+    directory: String,
+    interval: Duration,
+    extension: Option<String>,
+    directories: bool,
+    modifications: bool,
+}
+
+impl FileWatch {
+    /// Watch the files of a directory, like `C:\Logs` (forward slashes are also accepted).
+    pub fn new(directory: impl AsRef<Path>) -> Self {
+        Self {
+            directory: directory.as_ref().to_string_lossy().into_owned(),
+            interval: Duration::from_secs(2),
+            extension: None,
+            directories: false,
+            modifications: true,
+        }
+    }
+
+    /// How often WMI checks the directory for changes (2 seconds by default).
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Only watch the files with an extension (given without a dot, like `log`).
+    pub fn extension(mut self, extension: impl Into<String>) -> Self {
+        self.extension = Some(extension.into());
+        self
+    }
+
+    /// Watch the subdirectories of the directory instead of its files.
+    pub fn directories(mut self, directories: bool) -> Self {
+        self.directories = directories;
+        self
+    }
+
+    /// Whether to report modified files (`true` by default).
+    ///
+    /// Reading a file can update its last access time, which is also reported as a modification.
+    pub fn modifications(mut self, modifications: bool) -> Self {
+        self.modifications = modifications;
+        self
+    }
+
+    fn query(&self) -> WMIResult<String> {
+        let (drive, path) = split_directory(&self.directory)?;
+
+        let mut query = format!(
+            "SELECT * FROM __InstanceOperationEvent WITHIN {} WHERE TargetInstance ISA '{}' \
+            AND TargetInstance.Drive = {} AND TargetInstance.Path = {}",
+            self.interval.as_secs_f64(),
+            if self.directories {
+                "Win32_Directory"
+            } else {
+                "CIM_DataFile"
+            },
+            quote_and_escape_wql_str(drive),
+            quote_and_escape_wql_str(path),
+        );
+
+        if let Some(extension) = &self.extension {
+            query.push_str(&format!(
+                " AND TargetInstance.Extension = {}",
+                quote_and_escape_wql_str(extension.trim_start_matches('.'))
+            ));
+        }
+
+        if !self.modifications {
+            query.push_str(" AND __CLASS <> '__InstanceModificationEvent'");
+        }
+
+        Ok(query)
+    }
+}
+
+/// Split a directory into the `Drive` and `Path` of the files it contains, like `C:` and `\Logs\`.
+fn split_directory(directory: &str) -> WMIResult<(String, String)> {
+    let invalid = || WMIError::InvalidWatchPath(directory.to_owned());
+
+    let directory = directory.replace('/', "\\");
+    let mut chars = directory.chars();
+
+    let drive = match (chars.next(), chars.next()) {
+        (Some(letter), Some(':')) if letter.is_ascii_alphabetic() => {
+            format!("{}:", letter.to_ascii_uppercase())
+        }
+        _ => return Err(invalid()),
+    };
+
+    let rest = chars.as_str();
+    if !rest.is_empty() && !rest.starts_with('\\') {
+        // A relative path, like `C:Logs`.
+        return Err(invalid());
+    }
+
+    let mut path = String::from("\\");
+    for segment in rest.split('\\').filter(|segment| !segment.is_empty()) {
+        if segment == "." || segment == ".." {
+            return Err(invalid());
+        }
+        path.push_str(segment);
+        path.push('\\');
+    }
+
+    Ok((drive, path))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InstanceEvent {
+    target_instance: FileEntry,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ModificationEvent {
+    target_instance: FileEntry,
+    previous_instance: FileEntry,
+}
+
+#[derive(Deserialize)]
+enum RawFileEvent {
+    #[serde(rename = "__InstanceCreationEvent")]
+    Creation(InstanceEvent),
+    #[serde(rename = "__InstanceDeletionEvent")]
+    Deletion(InstanceEvent),
+    #[serde(rename = "__InstanceModificationEvent")]
+    Modification(ModificationEvent),
+}
+
+impl From<RawFileEvent> for FileEvent {
+    fn from(event: RawFileEvent) -> Self {
+        match event {
+            RawFileEvent::Creation(event) => FileEvent::Created(event.target_instance),
+            RawFileEvent::Deletion(event) => FileEvent::Deleted(event.target_instance),
+            RawFileEvent::Modification(event) => FileEvent::Modified {
+                old: event.previous_instance,
+                new: event.target_instance,
+            },
+        }
+    }
+}
+
+///
+/// ### Additional file watch methods
+///
+impl WMIConnection {
+    /// Subscribe to the changes of the files of a directory.
+    ///
+    /// Returns [`WMIError::InvalidWatchPath`] if the directory is not an absolute path on a drive.
+    /// See the [module level documentation](crate::file_watch) for an example.
+    pub fn file_events<'a>(
+        &'a self,
+        watch: &FileWatch,
+    ) -> WMIResult<impl Iterator<Item = WMIResult<FileEvent>> + 'a> {
+        Ok(self
+            .raw_notification::<RawFileEvent>(watch.query()?)?
+            .map(|event| event.map(FileEvent::from)))
+    }
+
+    /// Like [`file_events`](WMIConnection::file_events), returning a stream of events.
+    pub fn async_file_events(
+        &self,
+        watch: &FileWatch,
+    ) -> WMIResult<impl Stream<Item = WMIResult<FileEvent>>> {
+        Ok(self
+            .async_raw_notification::<RawFileEvent>(watch.query()?)?
+            .map(|event| event.map(FileEvent::from)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    #[test]
+    fn it_splits_directories() {
+        assert_eq!(
+            split_directory(r"C:\Logs").unwrap(),
+            ("C:".to_owned(), r"\Logs\".to_owned())
+        );
+        assert_eq!(
+            split_directory("d:/Program Files/App/").unwrap(),
+            ("D:".to_owned(), r"\Program Files\App\".to_owned())
+        );
+        assert_eq!(
+            split_directory(r"C:\").unwrap(),
+            ("C:".to_owned(), r"\".to_owned())
+        );
+
+        for invalid in [
+            r"\\server\share",
+            r"Logs",
+            r"C:Logs",
+            r"C:\Logs\..\Windows",
+            "",
+        ] {
+            assert!(matches!(
+                split_directory(invalid),
+                Err(WMIError::InvalidWatchPath(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn it_builds_file_queries() {
+        let watch = FileWatch::new(r"C:\Logs\App")
+            .extension(".log")
+            .modifications(false);
+
+        assert_eq!(
+            watch.query().unwrap(),
+            r#"SELECT * FROM __InstanceOperationEvent WITHIN 2 WHERE TargetInstance ISA 'CIM_DataFile' AND TargetInstance.Drive = "C:" AND TargetInstance.Path = "\\Logs\\App\\" AND TargetInstance.Extension = "log" AND __CLASS <> '__InstanceModificationEvent'"#
+        );
+
+        let watch = FileWatch::new(r"C:\").directories(true);
+
+        assert_eq!(
+            watch.query().unwrap(),
+            r#"SELECT * FROM __InstanceOperationEvent WITHIN 2 WHERE TargetInstance ISA 'Win32_Directory' AND TargetInstance.Drive = "C:" AND TargetInstance.Path = "\\""#
+        );
+    }
+
+    #[test]
+    fn it_watches_files() {
+        let wmi_con = wmi_con();
+
+        let directory =
+            std::env::temp_dir().join(format!("wmi-rs-file-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let watch = FileWatch::new(&directory)
+            .extension("txt")
+            .interval(Duration::from_millis(500));
+        let mut events = wmi_con.file_events(&watch).unwrap();
+
+        let file = directory.join("created.txt");
+        std::fs::write(&file, "wmi-rs").unwrap();
+
+        let event = events.next().unwrap().unwrap();
+
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        match event {
+            FileEvent::Created(entry) => {
+                assert!(entry.name.eq_ignore_ascii_case(file.to_str().unwrap()));
+                assert_eq!(entry.extension.as_deref(), Some("txt"));
+            }
+            other => panic!("Unexpected event {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{
+    connection::{create_locator, create_services, WMIConnection},
+    WMIError, WMIResult,
+};
+use log::debug;
+use windows::Win32::Foundation::{RPC_E_DISCONNECTED, RPC_E_SERVER_DIED, RPC_E_SERVER_DIED_DNE};
+
+/// The `HRESULT` for `RPC_S_SERVER_UNAVAILABLE` ("The RPC server is unavailable").
+pub const RPC_S_SERVER_UNAVAILABLE_HRESULT: i32 = 0x800706BA_u32 as i32;
+
+impl WMIError {
+    /// Whether this error indicates that the connection to the WMI service was lost
+    /// (for example, because the `Winmgmt` service was restarted), and that reconnecting might help.
+    pub fn is_disconnected(&self) -> bool {
+        match self {
+            WMIError::HResultError { hres } => [
+                RPC_E_DISCONNECTED.0,
+                RPC_E_SERVER_DIED.0,
+                RPC_E_SERVER_DIED_DNE.0,
+                RPC_S_SERVER_UNAVAILABLE_HRESULT,
+            ]
+            .contains(hres),
+            _ => false,
+        }
+    }
+}
+
+///
+/// ### Additional health check methods
+///
+impl WMIConnection {
+    /// Check that the connection to the WMI service is still usable,
+    /// by requesting an empty class object (which doesn't involve any provider).
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// let wmi_con = WMIConnection::new(COMLibrary::new()?)?;
+    /// wmi_con.ping()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn ping(&self) -> WMIResult<()> {
+        self.get_raw_by_path("")?;
+
+        Ok(())
+    }
+
+    /// Re-establish the connection to the WMI service, using the same namespace, credentials and security settings.
+    ///
+    /// Existing clones of this connection are not affected.
+    pub fn reconnect(&mut self) -> WMIResult<()> {
+        debug!("Reconnecting to {}", self.options.path);
+
+        let loc = create_locator()?;
+        self.svc = create_services(&loc, &self.options)?;
+
+        self.set_proxy()
+    }
+
+    /// Run `f`, reconnecting and retrying it once if it failed because the connection was lost.
+    ///
+    /// This is useful for long-running agents which can outlive restarts of the `Winmgmt` service.
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// # use std::collections::HashMap;
+    /// let mut wmi_con = WMIConnection::new(COMLibrary::new()?)?;
+    ///
+    /// let results: Vec<HashMap<String, Variant>> =
+    ///     wmi_con.with_reconnect(|con| con.raw_query("SELECT Name FROM Win32_OperatingSystem"))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_reconnect<T>(
+        &mut self,
+        mut f: impl FnMut(&WMIConnection) -> WMIResult<T>,
+    ) -> WMIResult<T> {
+        match f(self) {
+            Err(err) if err.is_disconnected() => {
+                debug!("Connection lost ({}), reconnecting", err);
+
+                self.reconnect()?;
+                f(self)
+            }
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::fixtures::*;
+    use crate::WMIError;
+    use windows::Win32::Foundation::RPC_E_DISCONNECTED;
+    use windows::Win32::System::Wmi::WBEM_E_ACCESS_DENIED;
+
+    #[test]
+    fn it_can_ping() {
+        let wmi_con = wmi_con();
+
+        wmi_con.ping().unwrap();
+    }
+
+    #[test]
+    fn it_can_reconnect() {
+        let mut wmi_con = wmi_con();
+
+        wmi_con.reconnect().unwrap();
+        wmi_con.ping().unwrap();
+    }
+
+    #[test]
+    fn it_retries_after_disconnect() {
+        let mut wmi_con = wmi_con();
+        let mut calls = 0;
+
+        let result = wmi_con.with_reconnect(|con| {
+            calls += 1;
+
+            if calls == 1 {
+                Err(WMIError::HResultError {
+                    hres: RPC_E_DISCONNECTED.0,
+                })
+            } else {
+                con.ping()
+            }
+        });
+
+        assert!(result.is_ok());
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn it_detects_disconnect_errors() {
+        let disconnected = WMIError::HResultError {
+            hres: super::RPC_S_SERVER_UNAVAILABLE_HRESULT,
+        };
+        assert!(disconnected.is_disconnected());
+
+        let access_denied = WMIError::HResultError {
+            hres: WBEM_E_ACCESS_DENIED.0,
+        };
+        assert!(!access_denied.is_disconnected());
+        assert!(!WMIError::ResultEmpty.is_disconnected());
+    }
+}
@@ -0,0 +1,178 @@
+//! Per-row hooks, which run on the raw objects of a query before they are deserialized.
+//!
+//! A hook can skip rows (for predicates WQL cannot express), change their properties,
+//! or stop the query early (which releases the enumerator without reading the remaining rows).
+//! Since skipped rows are never deserialized, this is cheaper than filtering the deserialized results.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use std::collections::HashMap;
+//! use std::ops::ControlFlow;
+//! use wmi::hook::RowAction;
+//!
+//! // WQL's `LIKE` has no case-sensitive or suffix-anchored matching.
+//! let services: Vec<HashMap<String, Variant>> = con.raw_query_with_hook(
+//!     "SELECT Name FROM Win32_Service",
+//!     |obj| match obj.get_property("Name") {
+//!         Ok(Variant::String(name)) if name.ends_with("Svc") => ControlFlow::Continue(RowAction::Keep),
+//!         _ => ControlFlow::Continue(RowAction::Skip),
+//!     },
+//! )?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    connection::WMIConnection,
+    query::{build_query, select_projection},
+    result_enumerator::IWbemClassWrapper,
+    WMIResult,
+};
+use serde::de;
+use std::ops::ControlFlow;
+
+/// What to do with a row, as returned by a hook.
+///
+/// Returned as `ControlFlow::Continue` to go on with the next rows, or as `ControlFlow::Break` to stop the query
+/// after this row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RowAction {
+    /// Deserialize the row, and return it.
+    Keep,
+    /// Drop the row, without deserializing it.
+    Skip,
+}
+
+///
+/// ### Additional hook methods
+///
+impl WMIConnection {
+    /// Like [`raw_query`](WMIConnection::raw_query), calling `hook` on each row before deserializing it.
+    ///
+    /// The hook can change the properties of the object (using [`IWbemClassWrapper::put_property`]),
+    /// which are then deserialized.
+    ///
+    /// See the [module level documentation](crate::hook) for an example.
+    pub fn raw_query_with_hook<T, F>(
+        &self,
+        query: impl AsRef<str>,
+        mut hook: F,
+    ) -> WMIResult<Vec<T>>
+    where
+        T: de::DeserializeOwned,
+        F: FnMut(&IWbemClassWrapper) -> ControlFlow<RowAction, RowAction>,
+    {
+        let projection = select_projection(query.as_ref());
+        let mut results = vec![];
+
+        for item in self.exec_query_native_wrapper(query)? {
+            let obj = item?;
+
+            let (action, stop) = match hook(&obj) {
+                ControlFlow::Continue(action) => (action, false),
+                ControlFlow::Break(action) => (action, true),
+            };
+
+            if action == RowAction::Keep {
+                results.push(obj.into_desr_with_options(&self.de_options, projection.as_deref())?);
+            }
+
+            if stop {
+                // The enumerator is released here, without reading the remaining rows.
+                break;
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Like [`query`](WMIConnection::query), calling `hook` on each row before deserializing it.
+    pub fn query_with_hook<T, F>(&self, hook: F) -> WMIResult<Vec<T>>
+    where
+        T: de::DeserializeOwned,
+        F: FnMut(&IWbemClassWrapper) -> ControlFlow<RowAction, RowAction>,
+    {
+        let query_text = build_query::<T>(None)?;
+
+        self.raw_query_with_hook(query_text, hook)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use crate::Variant;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug)]
+    #[serde(rename = "Win32_Process")]
+    #[serde(rename_all = "PascalCase")]
+    struct Process {
+        process_id: u32,
+        name: String,
+    }
+
+    #[test]
+    fn it_skips_rows_using_hooks() {
+        let wmi_con = wmi_con();
+
+        let mut seen = 0;
+
+        let multiples: Vec<Process> = wmi_con
+            .query_with_hook(|obj| {
+                seen += 1;
+
+                match obj.get_property("ProcessId") {
+                    Ok(Variant::UI4(pid)) if pid % 8 == 0 => ControlFlow::Continue(RowAction::Keep),
+                    _ => ControlFlow::Continue(RowAction::Skip),
+                }
+            })
+            .unwrap();
+
+        assert!(!multiples.is_empty());
+        assert!(multiples.len() < seen);
+        assert!(multiples.iter().all(|process| process.process_id % 8 == 0));
+    }
+
+    #[test]
+    fn it_stops_queries_using_hooks() {
+        let wmi_con = wmi_con();
+
+        let mut seen = 0;
+
+        let first: Vec<Process> = wmi_con
+            .query_with_hook(|_| {
+                seen += 1;
+
+                match seen {
+                    1 => ControlFlow::Continue(RowAction::Skip),
+                    _ => ControlFlow::Break(RowAction::Keep),
+                }
+            })
+            .unwrap();
+
+        assert_eq!(seen, 2);
+        assert_eq!(first.len(), 1);
+    }
+
+    #[test]
+    fn it_transforms_rows_using_hooks() {
+        let wmi_con = wmi_con();
+
+        let processes: Vec<Process> = wmi_con
+            .raw_query_with_hook("SELECT ProcessId, Name FROM Win32_Process", |obj| {
+                if let Ok(Variant::String(name)) = obj.get_property("Name") {
+                    obj.put_property("Name", name.to_uppercase()).unwrap();
+                }
+
+                ControlFlow::Continue(RowAction::Keep)
+            })
+            .unwrap();
+
+        assert!(processes
+            .iter()
+            .all(|process| process.name == process.name.to_uppercase()));
+    }
+}
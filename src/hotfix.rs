@@ -0,0 +1,355 @@
+//! Typed access to installed hotfixes and the update status reported by the Configuration Manager client.
+//!
+//! Unlike most classes, `Win32_QuickFixEngineering` does not return DMTF datetimes:
+//! `InstalledOn` is a `MM/DD/YYYY` string (regardless of the locale), and older versions of Windows
+//! return a hex-encoded `FILETIME` instead. [`InstallDate`] accepts all of these formats.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! for hotfix in con.hotfixes()? {
+//!     println!("{} installed on {:?}", hotfix.hot_fix_id, hotfix.installed_on);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{connection::WMIConnection, WMIError, WMIResult};
+use serde::{de, Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// The namespace of the update status of the Configuration Manager (SCCM) client.
+pub const CCM_UPDATES_STORE_NAMESPACE: &str = "ROOT\\ccm\\SoftwareUpdates\\UpdatesStore";
+/// The namespace of the client SDK of the Configuration Manager (SCCM) client.
+pub const CCM_CLIENT_SDK_NAMESPACE: &str = "ROOT\\ccm\\ClientSDK";
+
+/// The number of days between 1601-01-01 (the `FILETIME` epoch) and 1970-01-01.
+const FILETIME_EPOCH_DAYS: i64 = 134_774;
+const FILETIME_TICKS_PER_DAY: i64 = 864_000_000_000;
+
+/// A date without a time, normalized from the formats used by installation dates.
+///
+/// Accepts:
+/// - `MM/DD/YYYY` (also with single-digit months and days), as used by `Win32_QuickFixEngineering`.
+/// - `YYYYMMDD`, as used by the registry and `Win32_Product`.
+/// - DMTF datetimes (only the date is kept).
+/// - 16 hex digits, which encode a `FILETIME` (as returned by Windows XP and Server 2003).
+///
+/// Displays (and serializes) as `YYYY-MM-DD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InstallDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl InstallDate {
+    fn new(year: u32, month: u32, day: u32) -> Option<Self> {
+        let days_in_month = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if year.is_multiple_of(4)
+                && (!year.is_multiple_of(100) || year.is_multiple_of(400)) =>
+            {
+                29
+            }
+            2 => 28,
+            _ => return None,
+        };
+
+        if !(1601..=9999).contains(&year) || day == 0 || day > days_in_month {
+            return None;
+        }
+
+        Some(Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+        })
+    }
+
+    fn from_filetime(ticks: i64) -> Option<Self> {
+        // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = ticks / FILETIME_TICKS_PER_DAY - FILETIME_EPOCH_DAYS + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        Self::new(year.try_into().ok()?, month as u32, day as u32)
+    }
+}
+
+fn parse_digits(s: &str) -> Option<u32> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    s.parse().ok()
+}
+
+impl FromStr for InstallDate {
+    type Err = WMIError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let err = || WMIError::ConvertInstallDateError(s.to_owned());
+
+        let date = if trimmed.contains('/') {
+            let mut parts = trimmed.split('/');
+
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(month), Some(day), Some(year), None) if year.len() == 4 => {
+                    match (parse_digits(month), parse_digits(day), parse_digits(year)) {
+                        (Some(month), Some(day), Some(year)) => Self::new(year, month, day),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            }
+        } else if trimmed.len() == 16 && trimmed.bytes().all(|b| b.is_ascii_hexdigit()) {
+            i64::from_str_radix(trimmed, 16)
+                .ok()
+                .and_then(Self::from_filetime)
+        } else if (trimmed.len() == 8 && trimmed.is_ascii())
+            || crate::datetime::raw::is_dmtf_datetime(trimmed)
+        {
+            match (
+                parse_digits(&trimmed[..4]),
+                parse_digits(&trimmed[4..6]),
+                parse_digits(&trimmed[6..8]),
+            ) {
+                (Some(year), Some(month), Some(day)) => Self::new(year, month, day),
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        date.ok_or_else(err)
+    }
+}
+
+impl fmt::Display for InstallDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl<'de> Deserialize<'de> for InstallDate {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for InstallDate {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// Serde helper for `Option<InstallDate>` fields, which treats empty strings like `NULL`
+/// (as providers return either for unknown dates).
+///
+/// ```edition2018
+/// # use serde::Deserialize;
+/// use wmi::hotfix::InstallDate;
+///
+/// #[derive(Deserialize, Debug)]
+/// #[serde(rename = "Win32_QuickFixEngineering")]
+/// #[serde(rename_all = "PascalCase")]
+/// struct QuickFix {
+///     #[serde(rename = "HotFixID")]
+///     hot_fix_id: String,
+///     #[serde(deserialize_with = "wmi::hotfix::install_date::deserialize")]
+///     installed_on: Option<InstallDate>,
+/// }
+/// ```
+pub mod install_date {
+    use super::InstallDate;
+    use serde::{de, Deserialize};
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<InstallDate>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(s) if !s.trim().is_empty() => s.parse().map(Some).map_err(de::Error::custom),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// An installed hotfix, from `Win32_QuickFixEngineering`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "Win32_QuickFixEngineering")]
+#[serde(rename_all = "PascalCase")]
+pub struct HotFix {
+    /// Like `KB5034441`.
+    #[serde(rename = "HotFixID")]
+    pub hot_fix_id: String,
+    /// Like `Security Update`.
+    pub description: Option<String>,
+    /// The user who installed the hotfix (like `NT AUTHORITY\SYSTEM`).
+    pub installed_by: Option<String>,
+    #[serde(deserialize_with = "install_date::deserialize")]
+    pub installed_on: Option<InstallDate>,
+    /// The support URL of the hotfix.
+    pub caption: Option<String>,
+}
+
+/// The status of an update, as evaluated by the last scan of the Configuration Manager client.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "CCM_UpdateStatus")]
+#[serde(rename_all = "PascalCase")]
+pub struct UpdateStatus {
+    /// The id of the update (a GUID).
+    pub unique_id: String,
+    pub revision_number: u32,
+    /// The KB number (without the `KB` prefix).
+    pub article: Option<String>,
+    /// Like `MS17-010`.
+    pub bulletin: Option<String>,
+    pub title: Option<String>,
+    /// `Installed`, `Missing` or `Unknown`.
+    pub status: String,
+    /// A DMTF datetime, see [`datetime::raw`](crate::datetime::raw).
+    #[serde(with = "crate::datetime::raw::option")]
+    pub scan_time: Option<String>,
+}
+
+/// An update deployed to this computer which is not installed yet, from `CCM_SoftwareUpdate`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "CCM_SoftwareUpdate")]
+#[serde(rename_all = "PascalCase")]
+pub struct PendingUpdate {
+    #[serde(rename = "UpdateID")]
+    pub update_id: String,
+    /// The KB number (without the `KB` prefix).
+    #[serde(rename = "ArticleID")]
+    pub article_id: Option<String>,
+    pub name: Option<String>,
+    /// See [`CCM_SoftwareUpdate`](https://learn.microsoft.com/en-us/mem/configmgr/develop/reference/core/clients/sdk/ccm_softwareupdate-client-wmi-class)
+    /// for the possible values (like `8` for "pending soft reboot").
+    pub evaluation_state: u32,
+    /// `0` if the update is not compliant (is required), `1` if it is.
+    pub compliance_state: u32,
+    /// A DMTF datetime, see [`datetime::raw`](crate::datetime::raw).
+    #[serde(with = "crate::datetime::raw::option")]
+    pub deadline: Option<String>,
+}
+
+///
+/// ### Additional hotfix and update methods
+///
+impl WMIConnection {
+    /// List the installed hotfixes, sorted by installation date (hotfixes without one come first).
+    ///
+    /// See the [module level documentation](crate::hotfix) for an example.
+    pub fn hotfixes(&self) -> WMIResult<Vec<HotFix>> {
+        let mut hotfixes: Vec<HotFix> = self.query()?;
+
+        hotfixes
+            .sort_by(|a, b| (a.installed_on, &a.hot_fix_id).cmp(&(b.installed_on, &b.hot_fix_id)));
+
+        Ok(hotfixes)
+    }
+
+    /// List the status of updates, as evaluated by the Configuration Manager client.
+    ///
+    /// Connects to the [`CCM_UPDATES_STORE_NAMESPACE`] namespace on the same computer,
+    /// which fails with `WBEM_E_INVALID_NAMESPACE` if the client is not installed.
+    pub fn ccm_update_status(&self) -> WMIResult<Vec<UpdateStatus>> {
+        self.with_namespace(CCM_UPDATES_STORE_NAMESPACE)?.query()
+    }
+
+    /// List the updates which were deployed by Configuration Manager but are not installed yet.
+    ///
+    /// Connects to the [`CCM_CLIENT_SDK_NAMESPACE`] namespace on the same computer,
+    /// which fails with `WBEM_E_INVALID_NAMESPACE` if the client is not installed.
+    pub fn ccm_pending_updates(&self) -> WMIResult<Vec<PendingUpdate>> {
+        self.with_namespace(CCM_CLIENT_SDK_NAMESPACE)?
+            .raw_query("SELECT * FROM CCM_SoftwareUpdate WHERE ComplianceState = 0")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    fn date(year: u16, month: u8, day: u8) -> InstallDate {
+        InstallDate { year, month, day }
+    }
+
+    #[test]
+    fn it_parses_install_dates() {
+        assert_eq!(
+            "3/14/2024".parse::<InstallDate>().unwrap(),
+            date(2024, 3, 14)
+        );
+        assert_eq!(
+            "12/01/2023".parse::<InstallDate>().unwrap(),
+            date(2023, 12, 1)
+        );
+        assert_eq!(
+            "20240314".parse::<InstallDate>().unwrap(),
+            date(2024, 3, 14)
+        );
+        assert_eq!(
+            "20240314101500.000000+060".parse::<InstallDate>().unwrap(),
+            date(2024, 3, 14)
+        );
+        // 2011-09-16 as a FILETIME.
+        assert_eq!(
+            "01cc7458d7f07600".parse::<InstallDate>().unwrap(),
+            date(2011, 9, 16)
+        );
+
+        assert!("2/30/2024".parse::<InstallDate>().is_err());
+        assert!("14/3/2024".parse::<InstallDate>().is_err());
+        assert!("3/14/24".parse::<InstallDate>().is_err());
+        assert!("2024-03-14".parse::<InstallDate>().is_err());
+
+        assert_eq!(date(2024, 3, 4).to_string(), "2024-03-04");
+        assert!(date(2023, 12, 31) < date(2024, 1, 1));
+    }
+
+    #[test]
+    fn it_treats_empty_install_dates_as_null() {
+        #[derive(Deserialize)]
+        struct Row {
+            #[serde(deserialize_with = "install_date::deserialize")]
+            installed_on: Option<InstallDate>,
+        }
+
+        let row: Row = serde_json::from_str(r#"{"installed_on": ""}"#).unwrap();
+        assert_eq!(row.installed_on, None);
+
+        let row: Row = serde_json::from_str(r#"{"installed_on": null}"#).unwrap();
+        assert_eq!(row.installed_on, None);
+
+        let row: Row = serde_json::from_str(r#"{"installed_on": "1/2/2020"}"#).unwrap();
+        assert_eq!(row.installed_on, Some(date(2020, 1, 2)));
+    }
+
+    #[test]
+    fn it_lists_hotfixes() {
+        let wmi_con = wmi_con();
+
+        let hotfixes = wmi_con.hotfixes().unwrap();
+
+        assert!(hotfixes.iter().all(|hotfix| !hotfix.hot_fix_id.is_empty()));
+    }
+}
@@ -0,0 +1,211 @@
+//! Hyper-V virtual machines, from the `ROOT\virtualization\v2` namespace.
+//!
+//! Most Hyper-V methods start a `Msvm_ConcreteJob` and return immediately,
+//! so the methods of [`HyperV`] wait for these jobs (see the [`job`](crate::job) module).
+//! Requires membership in the "Hyper-V Administrators" group (or administrative rights).
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use wmi::hyperv::RequestedState;
+//!
+//! let hyperv = con.hyperv()?;
+//!
+//! for vm in hyperv.virtual_machines()? {
+//!     println!("{} ({:?})", vm.element_name, vm.enabled_state());
+//! }
+//!
+//! if let Some(vm) = hyperv.virtual_machine("build-agent-01")? {
+//!     hyperv.request_state_change(&vm, RequestedState::Running)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{connection::WMIConnection, FilterValue, Variant, WMIResult};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// The namespace of the Hyper-V provider.
+pub const HYPERV_NAMESPACE: &str = "ROOT\\virtualization\\v2";
+
+/// A virtual machine, from `Msvm_ComputerSystem`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "Msvm_ComputerSystem")]
+#[serde(rename_all = "PascalCase")]
+pub struct VirtualMachine {
+    #[serde(rename = "__Path")]
+    pub path: String,
+    /// The id of the virtual machine (a GUID).
+    pub name: String,
+    /// The display name of the virtual machine.
+    pub element_name: String,
+    /// See [`VirtualMachine::enabled_state`].
+    #[serde(rename = "EnabledState")]
+    pub enabled_state_raw: u16,
+    /// `5` if healthy, `20` for a major failure and `25` for a critical failure.
+    pub health_state: Option<u16>,
+    /// Zero if the virtual machine is off.
+    pub on_time_in_milliseconds: Option<u64>,
+}
+
+/// The state of a virtual machine, from `EnabledState`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EnabledState {
+    Running,
+    Off,
+    ShuttingDown,
+    /// The state was saved to disk.
+    Saved,
+    Paused,
+    Starting,
+    Saving,
+    Stopping,
+    Pausing,
+    Resuming,
+    /// A value not known to this crate.
+    Other(u16),
+}
+
+impl From<u16> for EnabledState {
+    fn from(value: u16) -> Self {
+        match value {
+            2 => EnabledState::Running,
+            3 => EnabledState::Off,
+            4 => EnabledState::ShuttingDown,
+            6 => EnabledState::Saved,
+            9 => EnabledState::Paused,
+            10 => EnabledState::Starting,
+            32773 => EnabledState::Saving,
+            32774 => EnabledState::Stopping,
+            32776 => EnabledState::Pausing,
+            32777 => EnabledState::Resuming,
+            other => EnabledState::Other(other),
+        }
+    }
+}
+
+impl VirtualMachine {
+    pub fn enabled_state(&self) -> EnabledState {
+        self.enabled_state_raw.into()
+    }
+}
+
+/// A state to move a virtual machine to, using [`HyperV::request_state_change`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RequestedState {
+    Running,
+    /// Turn off the virtual machine (like pulling the plug).
+    Off,
+    /// Shut down the guest operating system (requires the integration services).
+    ShutDown,
+    /// Save the state of the virtual machine to disk.
+    Saved,
+    Paused,
+    Reset,
+}
+
+impl RequestedState {
+    fn value(self) -> i32 {
+        match self {
+            RequestedState::Running => 2,
+            RequestedState::Off => 3,
+            RequestedState::ShutDown => 4,
+            RequestedState::Saved => 6,
+            RequestedState::Paused => 9,
+            RequestedState::Reset => 11,
+        }
+    }
+}
+
+/// A connection to the Hyper-V namespace, created using [`WMIConnection::hyperv`].
+#[derive(Debug, Clone)]
+pub struct HyperV {
+    con: WMIConnection,
+}
+
+///
+/// ### Additional Hyper-V methods
+///
+impl WMIConnection {
+    /// Connect to the Hyper-V namespace on the same computer.
+    ///
+    /// See the [module level documentation](crate::hyperv) for an example.
+    pub fn hyperv(&self) -> WMIResult<HyperV> {
+        Ok(HyperV {
+            con: self.with_namespace(HYPERV_NAMESPACE)?,
+        })
+    }
+}
+
+impl HyperV {
+    /// The connection to the Hyper-V namespace, to use other `Msvm_*` classes.
+    pub fn connection(&self) -> &WMIConnection {
+        &self.con
+    }
+
+    /// List the virtual machines (`Msvm_ComputerSystem` also includes the host itself, which is excluded).
+    pub fn virtual_machines(&self) -> WMIResult<Vec<VirtualMachine>> {
+        let mut filters = HashMap::new();
+        filters.insert("Caption".to_owned(), FilterValue::from("Virtual Machine"));
+
+        self.con.filtered_query(&filters)
+    }
+
+    /// Find a virtual machine by its display name.
+    pub fn virtual_machine(&self, element_name: &str) -> WMIResult<Option<VirtualMachine>> {
+        let mut filters = HashMap::new();
+        filters.insert("Caption".to_owned(), FilterValue::from("Virtual Machine"));
+        filters.insert(
+            "ElementName".to_owned(),
+            FilterValue::from(element_name.to_owned()),
+        );
+
+        Ok(self.con.filtered_query(&filters)?.into_iter().next())
+    }
+
+    /// Change the state of a virtual machine, and wait for the change to complete.
+    pub fn request_state_change(
+        &self,
+        vm: &VirtualMachine,
+        state: RequestedState,
+    ) -> WMIResult<()> {
+        self.con.exec_method_and_wait(
+            &vm.path,
+            "RequestStateChange",
+            &[("RequestedState", Variant::I4(state.value()))],
+        )?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use crate::WMIError;
+
+    #[test]
+    fn it_maps_enabled_states() {
+        assert_eq!(EnabledState::from(2), EnabledState::Running);
+        assert_eq!(EnabledState::from(32773), EnabledState::Saving);
+        assert_eq!(EnabledState::from(1), EnabledState::Other(1));
+    }
+
+    #[test]
+    fn it_lists_virtual_machines() {
+        let wmi_con = wmi_con();
+
+        // Hyper-V might not be installed.
+        let hyperv = match wmi_con.hyperv() {
+            Ok(hyperv) => hyperv,
+            Err(WMIError::HResultError { .. }) => return,
+            Err(err) => panic!("{}", err),
+        };
+
+        for vm in hyperv.virtual_machines().unwrap() {
+            assert!(!vm.name.is_empty());
+        }
+    }
+}
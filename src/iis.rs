@@ -0,0 +1,189 @@
+//! IIS sites and application pools, from the `ROOT\WebAdministration` namespace.
+//!
+//! The namespace is only available when the "IIS Management Scripts and Tools" feature is installed,
+//! and only accepts connections using the `RPC_C_AUTHN_LEVEL_PKT_PRIVACY` authentication level
+//! (which [`WMIConnection::iis`] sets). The state of sites and application pools is not a property,
+//! and must be read using the `GetState` method ([`Iis::site_state`] and [`Iis::app_pool_state`]).
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use wmi::iis::ObjectState;
+//!
+//! let iis = con.iis()?;
+//!
+//! for pool in iis.app_pools()? {
+//!     if iis.app_pool_state(&pool)? == ObjectState::Stopped {
+//!         iis.start_app_pool(&pool)?;
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{connection::WMIConnection, WMIError, WMIResult};
+use serde::Deserialize;
+
+/// The namespace of the IIS provider.
+pub const IIS_NAMESPACE: &str = "ROOT\\WebAdministration";
+
+/// A web site, from `Site`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "Site")]
+#[serde(rename_all = "PascalCase")]
+pub struct Site {
+    #[serde(rename = "__Path")]
+    pub path: String,
+    pub name: String,
+    pub id: u32,
+    /// Whether the site is started when IIS starts.
+    pub server_auto_start: bool,
+}
+
+/// An application pool, from `ApplicationPool`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "ApplicationPool")]
+#[serde(rename_all = "PascalCase")]
+pub struct ApplicationPool {
+    #[serde(rename = "__Path")]
+    pub path: String,
+    pub name: String,
+    /// Whether the application pool is started when IIS starts.
+    pub auto_start: bool,
+    /// Like `v4.0`, or empty for "No Managed Code".
+    pub managed_runtime_version: Option<String>,
+    #[serde(rename = "Enable32BitAppOnWin64")]
+    pub enable_32_bit_app_on_win64: bool,
+}
+
+/// The state of a site or an application pool, as returned by `GetState`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ObjectState {
+    Starting,
+    Started,
+    Stopping,
+    Stopped,
+    Unknown,
+}
+
+impl From<u32> for ObjectState {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => ObjectState::Starting,
+            1 => ObjectState::Started,
+            2 => ObjectState::Stopping,
+            3 => ObjectState::Stopped,
+            _ => ObjectState::Unknown,
+        }
+    }
+}
+
+/// A connection to the IIS namespace, created using [`WMIConnection::iis`].
+#[derive(Debug, Clone)]
+pub struct Iis {
+    con: WMIConnection,
+}
+
+///
+/// ### Additional IIS methods
+///
+impl WMIConnection {
+    /// Connect to the IIS namespace on the same computer.
+    ///
+    /// See the [module level documentation](crate::iis) for an example.
+    pub fn iis(&self) -> WMIResult<Iis> {
+        let mut con = self.with_namespace(IIS_NAMESPACE)?;
+        con.require_packet_privacy()?;
+
+        Ok(Iis { con })
+    }
+}
+
+impl Iis {
+    /// The connection to the IIS namespace, to use other classes (like `Application` or `VirtualDirectory`).
+    pub fn connection(&self) -> &WMIConnection {
+        &self.con
+    }
+
+    pub fn sites(&self) -> WMIResult<Vec<Site>> {
+        self.con.query()
+    }
+
+    pub fn app_pools(&self) -> WMIResult<Vec<ApplicationPool>> {
+        self.con.query()
+    }
+
+    pub fn site_state(&self, site: &Site) -> WMIResult<ObjectState> {
+        self.state(&site.path)
+    }
+
+    pub fn start_site(&self, site: &Site) -> WMIResult<()> {
+        self.exec(&site.path, "Start")
+    }
+
+    pub fn stop_site(&self, site: &Site) -> WMIResult<()> {
+        self.exec(&site.path, "Stop")
+    }
+
+    pub fn app_pool_state(&self, pool: &ApplicationPool) -> WMIResult<ObjectState> {
+        self.state(&pool.path)
+    }
+
+    pub fn start_app_pool(&self, pool: &ApplicationPool) -> WMIResult<()> {
+        self.exec(&pool.path, "Start")
+    }
+
+    pub fn stop_app_pool(&self, pool: &ApplicationPool) -> WMIResult<()> {
+        self.exec(&pool.path, "Stop")
+    }
+
+    /// Recycle the worker processes of the application pool.
+    pub fn recycle_app_pool(&self, pool: &ApplicationPool) -> WMIResult<()> {
+        self.exec(&pool.path, "Recycle")
+    }
+
+    fn state(&self, path: &str) -> WMIResult<ObjectState> {
+        let out = self
+            .con
+            .exec_method(path, "GetState", &[])?
+            .ok_or(WMIError::NullPointerResult)?;
+
+        Ok(u32::try_from(out.get_property("ReturnValue")?)?.into())
+    }
+
+    /// Execute a method without parameters or a return value. Failures are reported by `ExecMethod` itself.
+    fn exec(&self, path: &str, method: &str) -> WMIResult<()> {
+        self.con.exec_method(path, method, &[])?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    #[test]
+    fn it_maps_object_states() {
+        assert_eq!(ObjectState::from(1), ObjectState::Started);
+        assert_eq!(ObjectState::from(3), ObjectState::Stopped);
+        assert_eq!(ObjectState::from(7), ObjectState::Unknown);
+    }
+
+    #[test]
+    fn it_reads_iis_sites() {
+        let wmi_con = wmi_con();
+
+        // IIS might not be installed.
+        let iis = match wmi_con.iis() {
+            Ok(iis) => iis,
+            Err(WMIError::HResultError { .. }) => return,
+            Err(err) => panic!("{}", err),
+        };
+
+        for site in iis.sites().unwrap() {
+            iis.site_state(&site).unwrap();
+        }
+    }
+}
@@ -0,0 +1,522 @@
+//! Creating, updating and deleting instances, using `SpawnInstance`, `PutInstance` and `DeleteInstance`.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! let instruction = con.spawn_instance("__IntervalTimerInstruction")?;
+//! instruction.put_property("TimerId", "example")?;
+//! instruction.put_property("IntervalBetweenEvents", Variant::I4(60_000))?;
+//!
+//! # // Creating timer instructions can require administrative rights.
+//! # if con.put_instance(&instruction).is_ok() {
+//! con.delete_instance(r#"__IntervalTimerInstruction.TimerId="example""#)?;
+//! # }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Existing instances can be changed using their typed representation with [`WMIConnection::modify_instance`],
+//! which only writes back the properties which were changed:
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use serde::{Deserialize, Serialize};
+//! use std::collections::HashMap;
+//!
+//! #[derive(Deserialize, Serialize, Debug)]
+//! #[serde(rename = "Win32_Environment")]
+//! #[serde(rename_all = "PascalCase")]
+//! struct Environment {
+//!     name: String,
+//!     user_name: String,
+//!     variable_value: String,
+//! }
+//!
+//! let mut filters = HashMap::new();
+//! filters.insert("Name".to_owned(), FilterValue::Str("WMI_RS_EXAMPLE"));
+//!
+//! for result in con.modify_instance(&filters, |env: &mut Environment| {
+//!     env.variable_value.push_str(";C:\\Tools");
+//! })? {
+//!     println!("{}: changed {:?}, {:?}", result.path, result.changed, result.result);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    connection::WMIConnection, query::build_select_all_query, result_enumerator::IWbemClassWrapper,
+    ser::to_properties, FilterValue, Variant, WMIResult, WbemContext,
+};
+use log::debug;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::HashMap;
+use windows::core::BSTR;
+use windows::Win32::System::Wmi::{
+    WBEM_CHANGE_FLAG_TYPE, WBEM_FLAG_CREATE_ONLY, WBEM_FLAG_CREATE_OR_UPDATE, WBEM_FLAG_UPDATE_ONLY,
+};
+
+/// Whether [`WMIConnection::put_instance_with`] creates new instances, updates existing ones, or both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PutMode {
+    #[default]
+    CreateOrUpdate,
+    /// Fail with `WBEM_E_NOT_FOUND` if the instance does not exist.
+    UpdateOnly,
+    /// Fail with `WBEM_E_ALREADY_EXISTS` if the instance exists.
+    CreateOnly,
+}
+
+impl PutMode {
+    fn flags(self) -> WBEM_CHANGE_FLAG_TYPE {
+        match self {
+            PutMode::CreateOrUpdate => WBEM_FLAG_CREATE_OR_UPDATE,
+            PutMode::UpdateOnly => WBEM_FLAG_UPDATE_ONLY,
+            PutMode::CreateOnly => WBEM_FLAG_CREATE_ONLY,
+        }
+    }
+}
+
+/// How an instance is written by [`WMIConnection::put_instance_with`].
+///
+/// Selecting the properties to write makes a partial-instance update (using the `__PUT_EXTENSIONS` and
+/// `__PUT_EXT_PROPERTIES` context values), so read-only properties of an instance which was fetched
+/// from WMI are not written back. Providers which do not support partial-instance updates fail
+/// with `WBEM_E_PROVIDER_NOT_CAPABLE`.
+///
+/// ```edition2018
+/// # fn main() -> wmi::WMIResult<()> {
+/// # use wmi::*;
+/// # let con = WMIConnection::new(COMLibrary::new()?)?;
+/// use wmi::instance::{PutMode, PutOptions};
+///
+/// let options = PutOptions::new()
+///     .mode(PutMode::UpdateOnly)
+///     .properties(["VariableValue"]);
+///
+/// # let path = r#"Win32_Environment.Name="WMI_RS_EXAMPLE",UserName="<SYSTEM>""#;
+/// # if let Ok(env) = con.get_raw_by_path(path) {
+/// env.put_property("VariableValue", "1")?;
+/// con.put_instance_with(&env, &options)?;
+/// # }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct PutOptions {
+    mode: PutMode,
+    properties: Option<Vec<String>>,
+    strict_nulls: bool,
+    atomic: bool,
+}
+
+impl PutOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mode(mut self, mode: PutMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Only write the given properties (at least one).
+    pub fn properties<I, S>(mut self, properties: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.properties = Some(properties.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Also write the selected properties which are `NULL` (which are otherwise left unchanged),
+    /// using `__PUT_EXT_STRICT_NULLS`.
+    pub fn strict_nulls(mut self, strict_nulls: bool) -> Self {
+        self.strict_nulls = strict_nulls;
+        self
+    }
+
+    /// Fail if the provider can't write all the selected properties, using `__PUT_EXT_ATOMIC`.
+    pub fn atomic(mut self, atomic: bool) -> Self {
+        self.atomic = atomic;
+        self
+    }
+
+    fn uses_extensions(&self) -> bool {
+        self.properties.is_some() || self.strict_nulls || self.atomic
+    }
+
+    /// The context to pass to `PutInstance`: a copy of the context of the connection, with the put extensions.
+    fn context(&self, base: Option<&WbemContext>) -> WMIResult<Option<WbemContext>> {
+        if !self.uses_extensions() {
+            return base.map(WbemContext::try_clone).transpose();
+        }
+
+        let ctx = match base {
+            Some(base) => base.try_clone()?,
+            None => WbemContext::new()?,
+        };
+
+        ctx.set_value("__PUT_EXTENSIONS", true)?;
+
+        if let Some(properties) = &self.properties {
+            let names = properties
+                .iter()
+                .map(|property| Variant::String(property.clone()))
+                .collect();
+
+            ctx.set_value("__PUT_EXT_PROPERTIES", Variant::Array(names))?;
+        }
+
+        if self.strict_nulls {
+            ctx.set_value("__PUT_EXT_STRICT_NULLS", true)?;
+        }
+
+        if self.atomic {
+            ctx.set_value("__PUT_EXT_ATOMIC", true)?;
+        }
+
+        Ok(Some(ctx))
+    }
+}
+
+/// The outcome of modifying a single instance, see [`WMIConnection::modify_instance`].
+#[derive(Debug)]
+pub struct ModifyResult {
+    pub path: String,
+    /// The properties which were changed (and written back).
+    pub changed: Vec<String>,
+    /// Whether the instance was written back successfully (instances which were not changed are not written).
+    pub result: WMIResult<()>,
+}
+
+impl ModifyResult {
+    pub fn is_ok(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+///
+/// ### Additional instance methods
+///
+impl WMIConnection {
+    /// Create a new (local) instance of a class, to set its properties and write it using [`put_instance`](Self::put_instance).
+    pub fn spawn_instance(&self, class: &str) -> WMIResult<IWbemClassWrapper> {
+        let class = self.get_raw_by_path(class)?;
+
+        let instance = unsafe { class.inner.SpawnInstance(0)? };
+
+        Ok(IWbemClassWrapper::new(instance))
+    }
+
+    /// Write an instance, creating it or updating an existing instance with the same keys.
+    pub fn put_instance(&self, instance: &IWbemClassWrapper) -> WMIResult<()> {
+        self.put_instance_with(instance, &PutOptions::default())
+    }
+
+    /// Write an instance according to `options`, which can limit the written properties.
+    ///
+    /// See [`PutOptions`] for an example.
+    pub fn put_instance_with(
+        &self,
+        instance: &IWbemClassWrapper,
+        options: &PutOptions,
+    ) -> WMIResult<()> {
+        debug!(
+            "Writing an instance of {} ({:?})",
+            instance.class()?,
+            options
+        );
+
+        let ctx = options.context(self.context())?;
+
+        unsafe {
+            self.svc.PutInstance(
+                &instance.inner,
+                options.mode.flags().0 as _,
+                ctx.as_ref().map(WbemContext::as_raw),
+                None,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Fetch the instances of `T` matching the filters, pass each of them to `mutator`,
+    /// and write back only the properties which were changed (using a partial-instance update with
+    /// `WBEM_FLAG_UPDATE_ONLY`, see [`PutOptions`]).
+    ///
+    /// Values are converted using [`ser`](crate::ser). System properties (like `__Path`) are never written.
+    ///
+    /// Failing to query the instances is an error, but a failure to update an instance
+    /// is reported in its [`ModifyResult`], and the other instances are still updated.
+    ///
+    /// See the [module level documentation](crate::instance) for an example.
+    pub fn modify_instance<T, F>(
+        &self,
+        filters: &HashMap<String, FilterValue>,
+        mut mutator: F,
+    ) -> WMIResult<Vec<ModifyResult>>
+    where
+        T: DeserializeOwned + Serialize,
+        F: FnMut(&mut T),
+    {
+        let query_text = build_select_all_query::<T>(Some(filters))?;
+
+        let mut results = vec![];
+
+        for item in self.exec_query_native_wrapper(query_text)? {
+            let instance = item?;
+
+            let mut modified = ModifyResult {
+                path: instance.path()?,
+                changed: vec![],
+                result: Ok(()),
+            };
+
+            modified.result = self.modify_one(instance, &mut mutator, &mut modified.changed);
+
+            results.push(modified);
+        }
+
+        Ok(results)
+    }
+
+    fn modify_one<T, F>(
+        &self,
+        instance: IWbemClassWrapper,
+        mutator: &mut F,
+        changed: &mut Vec<String>,
+    ) -> WMIResult<()>
+    where
+        T: DeserializeOwned + Serialize,
+        F: FnMut(&mut T),
+    {
+        let mut value: T = instance
+            .clone()
+            .into_desr_with_options(&self.de_options, None)?;
+
+        let before = to_properties(&value)?;
+        mutator(&mut value);
+        let after = to_properties(&value)?;
+
+        let changes = changed_properties(&before, after);
+
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        for (name, value) in &changes {
+            instance.put_variant(name, value)?;
+        }
+
+        changed.extend(changes.into_iter().map(|(name, _)| name));
+
+        debug!("Updating {:?} of {}", changed, instance.path()?);
+
+        // Properties which were changed to `None` are cleared, so nulls are written too.
+        let options = PutOptions::new()
+            .mode(PutMode::UpdateOnly)
+            .properties(changed.iter())
+            .strict_nulls(true);
+
+        self.put_instance_with(&instance, &options)
+    }
+
+    /// Delete the instance at the given path.
+    pub fn delete_instance(&self, object_path: &str) -> WMIResult<()> {
+        debug!("Deleting {}", object_path);
+
+        unsafe {
+            self.svc.DeleteInstance(
+                &BSTR::from(object_path),
+                Default::default(),
+                self.ctx(),
+                None,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The (non-system) properties whose values are different after the change.
+fn changed_properties(
+    before: &[(String, Variant)],
+    after: Vec<(String, Variant)>,
+) -> Vec<(String, Variant)> {
+    after
+        .into_iter()
+        .filter(|(name, _)| !name.starts_with("__"))
+        .filter(|(name, value)| {
+            !before
+                .iter()
+                .any(|(old_name, old_value)| old_name == name && old_value == value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use crate::WMIError;
+    use serde::Deserialize;
+    use std::time::Duration;
+    use windows::Win32::System::Wmi::{WBEM_E_ACCESS_DENIED, WBEM_E_NOT_FOUND};
+
+    #[test]
+    fn it_spawns_instances() {
+        let wmi_con = wmi_con();
+
+        let instance = wmi_con
+            .spawn_instance("__IntervalTimerInstruction")
+            .unwrap();
+
+        assert_eq!(instance.class().unwrap(), "__IntervalTimerInstruction");
+        assert_eq!(instance.get_property("TimerId").unwrap(), Variant::Null);
+
+        instance.put_property("TimerId", "wmi-rs-spawn").unwrap();
+        assert_eq!(
+            instance.get_property("TimerId").unwrap(),
+            Variant::String("wmi-rs-spawn".to_owned())
+        );
+    }
+
+    #[test]
+    fn it_fails_to_delete_missing_instances() {
+        let wmi_con = wmi_con();
+
+        let result = wmi_con
+            .delete_instance(r#"__IntervalTimerInstruction.TimerId="wmi-rs-does-not-exist""#);
+
+        match result {
+            Err(WMIError::HResultError { hres }) => {
+                // Without administrative rights, deleting fails with `WBEM_E_ACCESS_DENIED` instead.
+                assert!(hres == WBEM_E_NOT_FOUND.0 || hres == WBEM_E_ACCESS_DENIED.0);
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_finds_changed_properties() {
+        let before = vec![
+            ("__Path".to_owned(), Variant::String("a".to_owned())),
+            ("Name".to_owned(), Variant::String("a".to_owned())),
+            ("Size".to_owned(), Variant::I4(1)),
+        ];
+        let after = vec![
+            ("__Path".to_owned(), Variant::String("b".to_owned())),
+            ("Name".to_owned(), Variant::String("a".to_owned())),
+            ("Size".to_owned(), Variant::I4(2)),
+        ];
+
+        assert_eq!(
+            changed_properties(&before, after),
+            vec![("Size".to_owned(), Variant::I4(2))]
+        );
+    }
+
+    #[test]
+    fn it_builds_put_contexts() {
+        let _wmi_con = wmi_con();
+
+        assert_eq!(PutOptions::new().context(None).unwrap(), None);
+
+        let ctx = PutOptions::new()
+            .mode(PutMode::UpdateOnly)
+            .properties(["TimerId", "IntervalBetweenEvents"])
+            .atomic(true)
+            .context(None)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            ctx.get_value("__PUT_EXTENSIONS").unwrap(),
+            Variant::Bool(true)
+        );
+        assert_eq!(
+            ctx.get_value("__PUT_EXT_PROPERTIES").unwrap(),
+            Variant::Array(vec![
+                Variant::String("TimerId".to_owned()),
+                Variant::String("IntervalBetweenEvents".to_owned())
+            ])
+        );
+        assert_eq!(
+            ctx.get_value("__PUT_EXT_ATOMIC").unwrap(),
+            Variant::Bool(true)
+        );
+
+        // The context of the connection is not changed.
+        let base = WbemContext::builder()
+            .int("__ProviderArchitecture", 64)
+            .build()
+            .unwrap();
+        let ctx = PutOptions::new()
+            .strict_nulls(true)
+            .context(Some(&base))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            ctx.get_value("__ProviderArchitecture").unwrap(),
+            Variant::I4(64)
+        );
+        assert!(base.get_value("__PUT_EXTENSIONS").is_err());
+    }
+
+    #[test]
+    fn it_modifies_instances() {
+        #[derive(Deserialize, Serialize, Debug)]
+        #[serde(rename = "__IntervalTimerInstruction")]
+        #[serde(rename_all = "PascalCase")]
+        struct IntervalTimer {
+            #[serde(rename = "__Path")]
+            path: String,
+            timer_id: String,
+            interval_between_events: u32,
+        }
+
+        let wmi_con = wmi_con();
+
+        let timer = match wmi_con.create_interval_timer("wmi-rs-modify", Duration::from_secs(60)) {
+            Ok(timer) => timer,
+            // Creating timer instructions can require administrative rights.
+            Err(WMIError::HResultError { .. }) => return,
+            Err(err) => panic!("{}", err),
+        };
+
+        let mut filters = HashMap::new();
+        filters.insert("TimerId".to_owned(), FilterValue::Str("wmi-rs-modify"));
+
+        let results = wmi_con
+            .modify_instance(&filters, |timer: &mut IntervalTimer| {
+                timer.interval_between_events = 120_000;
+            })
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_ok(), "{:?}", results[0]);
+        assert_eq!(results[0].changed, vec!["IntervalBetweenEvents"]);
+
+        // Nothing is changed (or written) the second time.
+        let results = wmi_con
+            .modify_instance(&filters, |timer: &mut IntervalTimer| {
+                timer.interval_between_events = 120_000;
+            })
+            .unwrap();
+
+        assert!(results[0].is_ok());
+        assert!(results[0].changed.is_empty());
+
+        let updated: Vec<IntervalTimer> = wmi_con.filtered_query(&filters).unwrap();
+
+        wmi_con.delete_timer(&timer).unwrap();
+
+        assert_eq!(updated[0].interval_between_events, 120_000);
+    }
+}
@@ -0,0 +1,397 @@
+//! Wait for the `CIM_ConcreteJob`s started by long-running methods.
+//!
+//! Many providers (like Hyper-V and Storage) return from methods immediately with a `ReturnValue` of
+//! [`JOB_STARTED`], and a reference to a job in the `Job` output parameter.
+//! The result of the method is only known once the job completes.
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! use std::time::Duration;
+//!
+//! let con = WMIConnection::with_namespace_path("ROOT\\virtualization\\v2", COMLibrary::new()?)?;
+//!
+//! // Waits for the job (if one was started), and fails if it did not complete successfully.
+//! con.exec_method_and_wait(
+//!     "Msvm_ComputerSystem.CreationClassName=\"Msvm_ComputerSystem\",Name=\"...\"",
+//!     "RequestStateChange",
+//!     &[("RequestedState", Variant::I4(2))],
+//! )?;
+//!
+//! // Or, to control how the job is waited for:
+//! let job = con
+//!     .job_waiter("Msvm_ConcreteJob.InstanceID=\"...\"")
+//!     .poll_interval(Duration::from_millis(250))
+//!     .timeout(Duration::from_secs(60))
+//!     .wait()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Jobs can also be awaited without blocking a thread, using the events WMI delivers when a job changes:
+//!
+//! ```edition2018,no_run
+//! # use wmi::*;
+//! # async fn exec_async_method(con: &WMIConnection) -> WMIResult<()> {
+//! let out = con
+//!     .exec_method_async(
+//!         "Msvm_ComputerSystem.CreationClassName=\"Msvm_ComputerSystem\",Name=\"...\"",
+//!         "RequestStateChange",
+//!         &[("RequestedState", Variant::I4(2))],
+//!     )
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    connection::WMIConnection, query::quote_and_escape_wql_str,
+    result_enumerator::IWbemClassWrapper, Variant, WMIError, WMIResult,
+};
+use futures::StreamExt;
+use log::debug;
+use serde::Deserialize;
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The `ReturnValue` of a method which completed synchronously.
+pub const COMPLETED: u32 = 0;
+/// The `ReturnValue` of a method which started a job ("Method Parameters Checked - Job Started").
+pub const JOB_STARTED: u32 = 4096;
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The state of a job, from the `JobState` property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JobState {
+    New,
+    Starting,
+    Running,
+    Suspended,
+    ShuttingDown,
+    Completed,
+    Terminated,
+    Killed,
+    Exception,
+    Service,
+    /// A value not known to this crate.
+    Other(u16),
+}
+
+impl JobState {
+    /// Whether the job has finished (successfully or not).
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobState::Completed | JobState::Terminated | JobState::Killed | JobState::Exception
+        )
+    }
+}
+
+impl From<u16> for JobState {
+    fn from(value: u16) -> Self {
+        match value {
+            2 => JobState::New,
+            3 => JobState::Starting,
+            4 => JobState::Running,
+            5 => JobState::Suspended,
+            6 => JobState::ShuttingDown,
+            7 => JobState::Completed,
+            8 => JobState::Terminated,
+            9 => JobState::Killed,
+            10 => JobState::Exception,
+            11 => JobState::Service,
+            other => JobState::Other(other),
+        }
+    }
+}
+
+/// A job, from `CIM_ConcreteJob` (or one of its subclasses, like `Msvm_ConcreteJob`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "CIM_ConcreteJob")]
+#[serde(rename_all = "PascalCase")]
+pub struct ConcreteJob {
+    #[serde(rename = "InstanceID")]
+    pub instance_id: String,
+    /// Like `Turning on virtual machine`.
+    pub caption: Option<String>,
+    #[serde(deserialize_with = "deserialize_job_state")]
+    pub job_state: JobState,
+    pub percent_complete: Option<u16>,
+    /// `0` if the job did not fail.
+    pub error_code: Option<u16>,
+    pub error_description: Option<String>,
+}
+
+/// An `__InstanceModificationEvent` of a job, used by [`JobWaiter::wait_async`].
+#[derive(Debug, Deserialize)]
+#[serde(rename = "__InstanceModificationEvent")]
+#[serde(rename_all = "PascalCase")]
+struct JobModification {
+    target_instance: ConcreteJob,
+}
+
+fn deserialize_job_state<'de, D>(deserializer: D) -> Result<JobState, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    u16::deserialize(deserializer).map(JobState::from)
+}
+
+/// Waits for a job to finish, created using [`WMIConnection::job_waiter`].
+///
+/// See the [module level documentation](crate::job) for an example.
+#[derive(Debug, Clone)]
+pub struct JobWaiter<'a> {
+    con: &'a WMIConnection,
+    path: String,
+    poll_interval: Duration,
+    timeout: Option<Duration>,
+}
+
+impl<'a> JobWaiter<'a> {
+    /// How often to read the state of the job. Defaults to 500 milliseconds.
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// The maximum time to wait for the job, after which [`WMIError::Timeout`] is returned
+    /// (the job keeps running). By default, waits indefinitely.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The path of the job.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Read the current state of the job.
+    pub fn poll(&self) -> WMIResult<ConcreteJob> {
+        self.con.get_by_path(&self.path)
+    }
+
+    /// Wait for the job to finish, and return its final state.
+    ///
+    /// Fails with [`WMIError::JobFailed`] if the job did not complete successfully.
+    pub fn wait(self) -> WMIResult<ConcreteJob> {
+        let start = Instant::now();
+
+        loop {
+            if let Some(outcome) = self.outcome(self.poll()?) {
+                return outcome;
+            }
+
+            if let Some(timeout) = self.timeout {
+                if start.elapsed() >= timeout {
+                    return Err(WMIError::Timeout);
+                }
+            }
+
+            thread::sleep(self.poll_interval);
+        }
+    }
+
+    /// Like [`wait`](Self::wait), but without blocking: WMI checks the job every `poll_interval`,
+    /// and the returned future is woken when the job changes.
+    ///
+    /// The timeout is not used (use the timer of your runtime instead, like `tokio::time::timeout`).
+    pub async fn wait_async(self) -> WMIResult<ConcreteJob> {
+        let job = self.poll()?;
+
+        if let Some(outcome) = self.outcome(job.clone()) {
+            return outcome;
+        }
+
+        let mut events = self
+            .con
+            .async_raw_notification::<JobModification>(job_events_query(
+                &job.instance_id,
+                self.poll_interval,
+            ))?;
+
+        // The job might have finished before the subscription was created.
+        if let Some(outcome) = self.outcome(self.poll()?) {
+            return outcome;
+        }
+
+        while let Some(event) = events.next().await {
+            if let Some(outcome) = self.outcome(event?.target_instance) {
+                return outcome;
+            }
+        }
+
+        // The subscription ended without the job finishing.
+        self.outcome(self.poll()?)
+            .unwrap_or(Err(WMIError::ResultEmpty))
+    }
+
+    /// The result of waiting for the job, or `None` if it is still running.
+    fn outcome(&self, job: ConcreteJob) -> Option<WMIResult<ConcreteJob>> {
+        debug!(
+            "Job {} is {:?} ({:?}%)",
+            self.path, job.job_state, job.percent_complete
+        );
+
+        if job.job_state == JobState::Completed {
+            return Some(Ok(job));
+        }
+
+        if job.job_state.is_finished() {
+            return Some(Err(WMIError::JobFailed {
+                path: self.path.clone(),
+                error_code: job.error_code.unwrap_or_default().into(),
+                description: job.error_description.unwrap_or_default(),
+            }));
+        }
+
+        None
+    }
+}
+
+fn job_events_query(instance_id: &str, poll_interval: Duration) -> String {
+    format!(
+        "SELECT * FROM __InstanceModificationEvent WITHIN {} WHERE TargetInstance ISA 'CIM_ConcreteJob' AND TargetInstance.InstanceID = {}",
+        poll_interval.as_secs_f64(),
+        quote_and_escape_wql_str(instance_id)
+    )
+}
+
+///
+/// ### Additional job methods
+///
+impl WMIConnection {
+    /// Create a [`JobWaiter`] for the job at the given path.
+    pub fn job_waiter(&self, job_path: impl Into<String>) -> JobWaiter<'_> {
+        JobWaiter {
+            con: self,
+            path: job_path.into(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            timeout: None,
+        }
+    }
+
+    /// Execute a method (see [`exec_method`](Self::exec_method)), and wait for the job it started (if any).
+    ///
+    /// Fails with [`WMIError::MethodFailed`] if the `ReturnValue` is neither [`COMPLETED`] nor [`JOB_STARTED`],
+    /// and with [`WMIError::JobFailed`] if the job did not complete successfully.
+    /// Returns the output parameters of the method.
+    pub fn exec_method_and_wait(
+        &self,
+        object_path: &str,
+        method: &str,
+        in_params: &[(&str, Variant)],
+    ) -> WMIResult<IWbemClassWrapper> {
+        let out = self.exec_method_with_job(object_path, method, in_params)?;
+
+        if let Some(job_path) = job_path(&out)? {
+            self.job_waiter(job_path).wait()?;
+        }
+
+        Ok(out)
+    }
+
+    /// Like [`exec_method_and_wait`](Self::exec_method_and_wait), but the job (if any)
+    /// is awaited without blocking, see [`JobWaiter::wait_async`].
+    pub async fn exec_method_async(
+        &self,
+        object_path: &str,
+        method: &str,
+        in_params: &[(&str, Variant)],
+    ) -> WMIResult<IWbemClassWrapper> {
+        let out = self.exec_method_with_job(object_path, method, in_params)?;
+
+        if let Some(job_path) = job_path(&out)? {
+            self.job_waiter(job_path).wait_async().await?;
+        }
+
+        Ok(out)
+    }
+
+    /// Execute a method, and fail if its `ReturnValue` is neither [`COMPLETED`] nor [`JOB_STARTED`].
+    fn exec_method_with_job(
+        &self,
+        object_path: &str,
+        method: &str,
+        in_params: &[(&str, Variant)],
+    ) -> WMIResult<IWbemClassWrapper> {
+        let out = self
+            .exec_method(object_path, method, in_params)?
+            .ok_or(WMIError::NullPointerResult)?;
+
+        match u32::try_from(out.get_property("ReturnValue")?)? {
+            COMPLETED | JOB_STARTED => Ok(out),
+            return_value => Err(WMIError::MethodFailed {
+                method: method.to_owned(),
+                return_value,
+            }),
+        }
+    }
+}
+
+/// The path of the job started by a method, if it started one.
+fn job_path(out: &IWbemClassWrapper) -> WMIResult<Option<String>> {
+    if u32::try_from(out.get_property("ReturnValue")?)? != JOB_STARTED {
+        return Ok(None);
+    }
+
+    String::try_from(out.get_property("Job")?).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    #[test]
+    fn it_maps_job_states() {
+        assert_eq!(JobState::from(4), JobState::Running);
+        assert!(!JobState::from(4).is_finished());
+        assert_eq!(JobState::from(7), JobState::Completed);
+        assert!(JobState::from(10).is_finished());
+        assert_eq!(JobState::from(32768), JobState::Other(32768));
+    }
+
+    #[test]
+    fn it_builds_job_events_queries() {
+        assert_eq!(
+            job_events_query(r#"Job "1""#, Duration::from_millis(500)),
+            r#"SELECT * FROM __InstanceModificationEvent WITHIN 0.5 WHERE TargetInstance ISA 'CIM_ConcreteJob' AND TargetInstance.InstanceID = "Job \"1\"""#
+        );
+    }
+
+    #[async_std::test]
+    async fn async_it_execs_methods_without_jobs() {
+        let wmi_con = wmi_con();
+
+        let out = wmi_con
+            .exec_method_async(
+                "StdRegProv",
+                "EnumKey",
+                &[
+                    ("hDefKey", Variant::I4(0x80000002_u32 as i32)),
+                    ("sSubKeyName", "SOFTWARE".into()),
+                ],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(out.get_property("ReturnValue").unwrap(), Variant::UI4(0));
+
+        let result = wmi_con
+            .exec_method_async(
+                "StdRegProv",
+                "EnumKey",
+                &[
+                    ("hDefKey", Variant::I4(0x80000002_u32 as i32)),
+                    ("sSubKeyName", "SOFTWARE\\wmi-rs-does-not-exist".into()),
+                ],
+            )
+            .await;
+
+        assert!(matches!(result, Err(WMIError::MethodFailed { .. })));
+    }
+}
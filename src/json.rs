@@ -0,0 +1,199 @@
+//! Conversion of [`Variant`]s and WMI objects into [`serde_json::Value`]s.
+//!
+//! This is useful for generic exporters, which need to serialize any class without a struct definition.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! let os = con
+//!     .exec_query_native_wrapper("SELECT * FROM Win32_OperatingSystem")?
+//!     .next()
+//!     .unwrap()?;
+//!
+//! let value = os.to_json_value()?;
+//! assert!(value["Caption"].is_string());
+//! #   Ok(())
+//! # }
+//! ```
+//!
+//! Values are converted as follows:
+//! * Numbers, strings and booleans are kept as they are (`NaN` and infinite floats become `null`).
+//! * Datetimes (`yyyymmddHHMMSS.mmmmmm+UUU`) become RFC 3339 strings, like `2023-03-15T18:00:00.000000+02:00`.
+//!   Intervals and datetimes with wildcards (`*`) are kept as they are.
+//! * References are kept as object path strings.
+//! * Arrays become JSON arrays, and embedded objects become JSON objects.
+//! * Empty and null values become `null`.
+use crate::{result_enumerator::IWbemClassWrapper, Variant, WMIResult};
+use serde_json::{Map, Number, Value};
+
+impl Variant {
+    /// Convert this value into a [`serde_json::Value`]. See the [module docs](crate::json) for details.
+    ///
+    /// Fails only if the properties of an embedded object cannot be read.
+    pub fn to_json_value(&self) -> WMIResult<Value> {
+        let value = match self {
+            Variant::Empty | Variant::Null | Variant::Unknown(_) => Value::Null,
+            Variant::String(s) => match dmtf_to_rfc3339(s) {
+                Some(datetime) => Value::String(datetime),
+                None => Value::String(s.clone()),
+            },
+            Variant::I1(n) => Value::from(*n),
+            Variant::I2(n) => Value::from(*n),
+            Variant::I4(n) => Value::from(*n),
+            Variant::I8(n) => Value::from(*n),
+            Variant::UI1(n) => Value::from(*n),
+            Variant::UI2(n) => Value::from(*n),
+            Variant::UI4(n) => Value::from(*n),
+            Variant::UI8(n) => Value::from(*n),
+            Variant::R4(f) => float_value((*f).into()),
+            Variant::R8(f) => float_value(*f),
+            Variant::Bool(b) => Value::Bool(*b),
+            Variant::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .map(Variant::to_json_value)
+                    .collect::<WMIResult<_>>()?,
+            ),
+            Variant::Object(obj) => obj.to_json_value()?,
+        };
+
+        Ok(value)
+    }
+}
+
+impl IWbemClassWrapper {
+    /// Convert all the (non-system) properties of this object into a JSON object.
+    pub fn to_json_value(&self) -> WMIResult<Value> {
+        let mut map = Map::new();
+
+        for property in self.list_properties()? {
+            let value = self.get_property(&property)?.to_json_value()?;
+            map.insert(property, value);
+        }
+
+        Ok(Value::Object(map))
+    }
+}
+
+/// Embedded objects whose properties cannot be read are converted to `null`.
+/// Use [`Variant::to_json_value`] to handle these errors.
+impl From<Variant> for Value {
+    fn from(value: Variant) -> Self {
+        value.to_json_value().unwrap_or(Value::Null)
+    }
+}
+
+fn float_value(f: f64) -> Value {
+    Number::from_f64(f).map_or(Value::Null, Value::Number)
+}
+
+/// Convert a DMTF datetime (like `20230315180000.000000+120`) into an RFC 3339 string.
+///
+/// Returns `None` if the string is not a (fully specified) datetime.
+fn dmtf_to_rfc3339(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+
+    let is_digits = |range: std::ops::Range<usize>| bytes[range].iter().all(u8::is_ascii_digit);
+
+    if bytes.len() != 25
+        || !is_digits(0..14)
+        || bytes[14] != b'.'
+        || !is_digits(15..21)
+        || !matches!(bytes[21], b'+' | b'-')
+        || !is_digits(22..25)
+    {
+        return None;
+    }
+
+    let offset: u32 = s[22..25].parse().ok()?;
+
+    Some(format!(
+        "{}-{}-{}T{}:{}:{}.{}{}{:02}:{:02}",
+        &s[0..4],
+        &s[4..6],
+        &s[6..8],
+        &s[8..10],
+        &s[10..12],
+        &s[12..14],
+        &s[15..21],
+        &s[21..22],
+        offset / 60,
+        offset % 60,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use serde_json::json;
+
+    #[test]
+    fn it_converts_scalars() {
+        assert_eq!(Variant::I1(-1).to_json_value().unwrap(), json!(-1));
+        assert_eq!(
+            Variant::UI8(u64::MAX).to_json_value().unwrap(),
+            json!(u64::MAX)
+        );
+        assert_eq!(Variant::R4(0.5).to_json_value().unwrap(), json!(0.5));
+        assert_eq!(Variant::R8(f64::NAN).to_json_value().unwrap(), Value::Null);
+        assert_eq!(Variant::Bool(true).to_json_value().unwrap(), json!(true));
+        assert_eq!(Variant::Null.to_json_value().unwrap(), Value::Null);
+        assert_eq!(Variant::Empty.to_json_value().unwrap(), Value::Null);
+        assert_eq!(Value::from(Variant::String("Hello".into())), json!("Hello"));
+    }
+
+    #[test]
+    fn it_converts_arrays() {
+        let value = Variant::Array(vec![
+            Variant::UI2(1),
+            Variant::Array(vec![Variant::String("a".into())]),
+        ]);
+
+        assert_eq!(value.to_json_value().unwrap(), json!([1, ["a"]]));
+    }
+
+    #[test]
+    fn it_converts_datetimes() {
+        assert_eq!(
+            dmtf_to_rfc3339("20230315180000.123456+120").unwrap(),
+            "2023-03-15T18:00:00.123456+02:00"
+        );
+        assert_eq!(
+            dmtf_to_rfc3339("19980525133015.000000-300").unwrap(),
+            "1998-05-25T13:30:15.000000-05:00"
+        );
+
+        // Intervals, wildcards and references are kept as they are.
+        for s in [
+            "00000001132312.000000:000",
+            "2023****180000.000000+000",
+            r#"\\HOST\root\cimv2:Win32_ComputerSystem.Name="HOST""#,
+        ] {
+            assert_eq!(dmtf_to_rfc3339(s), None);
+            assert_eq!(Variant::String(s.into()).to_json_value().unwrap(), json!(s));
+        }
+    }
+
+    #[test]
+    fn it_converts_objects() {
+        let wmi_con = wmi_con();
+
+        let os = wmi_con
+            .exec_query_native_wrapper("SELECT * FROM Win32_OperatingSystem")
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+
+        let value = os.to_json_value().unwrap();
+
+        assert!(value["Caption"].is_string());
+        assert!(value["MUILanguages"].is_array());
+        assert_eq!(value["LastBootUpTime"].as_str().unwrap().len(), 32);
+
+        let object = Variant::Object(os);
+        assert_eq!(object.to_json_value().unwrap(), value);
+    }
+}
@@ -0,0 +1,188 @@
+//! Instrumentation for detecting leaked COM objects, enabled by the `leak-check` feature.
+//!
+//! Every wrapped interface ([`IWbemClassWrapper`](crate::result_enumerator::IWbemClassWrapper),
+//! [`IUnknownWrapper`](crate::variant::IUnknownWrapper) and the enumerator of a
+//! [`QueryResultEnumerator`](crate::result_enumerator::QueryResultEnumerator)) holds a single reference
+//! to its object, so counting the live wrappers counts the `AddRef` / `Release` calls done by this crate.
+//!
+//! Objects are counted for the thread which created them, so tests running in parallel do not interfere.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # use std::collections::HashMap;
+//! use wmi::leak_check::LeakCheck;
+//!
+//! let con = WMIConnection::new(COMLibrary::new()?)?;
+//!
+//! let check = LeakCheck::start();
+//! let results: Vec<HashMap<String, Variant>> = con.raw_query("SELECT Name FROM Win32_Process")?;
+//! drop(results);
+//! check.finish();
+//! #   Ok(())
+//! # }
+//! ```
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicIsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+thread_local! {
+    static LIVE_OBJECTS: Arc<AtomicIsize> = Arc::new(AtomicIsize::new(0));
+}
+
+/// A token which is held by every wrapped interface, and counts it as a live object.
+pub(crate) struct Tracked {
+    counter: Arc<AtomicIsize>,
+}
+
+impl Tracked {
+    pub(crate) fn new() -> Self {
+        let counter = LIVE_OBJECTS.with(|counter| counter.clone());
+        counter.fetch_add(1, Ordering::SeqCst);
+
+        Self { counter }
+    }
+}
+
+impl Clone for Tracked {
+    fn clone(&self) -> Self {
+        self.counter.fetch_add(1, Ordering::SeqCst);
+
+        Self {
+            counter: self.counter.clone(),
+        }
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl fmt::Debug for Tracked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Tracked")
+    }
+}
+
+/// Tokens do not affect the equality of the wrappers which hold them.
+impl PartialEq for Tracked {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Tracked {}
+
+/// The number of live COM objects which were created by the current thread.
+pub fn live_objects() -> isize {
+    LIVE_OBJECTS.with(|counter| counter.load(Ordering::SeqCst))
+}
+
+/// Fails (panics) if the number of live COM objects at the end of a scope is not the same as at its start.
+///
+/// The check runs on [`finish`](LeakCheck::finish), or when the guard is dropped (unless the thread is already panicking).
+#[derive(Debug)]
+#[must_use]
+pub struct LeakCheck {
+    start: isize,
+}
+
+impl LeakCheck {
+    pub fn start() -> Self {
+        Self {
+            start: live_objects(),
+        }
+    }
+
+    /// The number of objects which were created since the start of the check, and are still alive.
+    pub fn leaked(&self) -> isize {
+        live_objects() - self.start
+    }
+
+    pub fn finish(self) {
+        // The check is done in `drop`.
+    }
+}
+
+impl Drop for LeakCheck {
+    fn drop(&mut self) {
+        let leaked = self.leaked();
+
+        if leaked != 0 && !thread::panicking() {
+            panic!(
+                "{} COM object(s) were leaked (or released too many times)",
+                leaked
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests::fixtures::*, Variant};
+    use std::collections::HashMap;
+
+    #[test]
+    fn it_counts_wrapped_objects() {
+        let wmi_con = wmi_con();
+
+        let check = LeakCheck::start();
+
+        let os = wmi_con
+            .get_raw_by_path(r#"\\.\root\cimv2:Win32_OperatingSystem=@"#)
+            .unwrap();
+        assert_eq!(check.leaked(), 1);
+
+        let os2 = os.clone();
+        assert_eq!(check.leaked(), 2);
+
+        drop(os);
+        drop(os2);
+        check.finish();
+    }
+
+    #[test]
+    fn it_has_no_leaks_in_queries() {
+        let wmi_con = wmi_con();
+
+        let check = LeakCheck::start();
+
+        let results: Vec<HashMap<String, Variant>> = wmi_con
+            .raw_query("SELECT Name, ParentProcessId FROM Win32_Process")
+            .unwrap();
+        drop(results);
+
+        let mut enumerator = wmi_con
+            .exec_query_native_wrapper("SELECT Name FROM Win32_Process")
+            .unwrap();
+        let _first = enumerator.next().unwrap().unwrap();
+        drop(enumerator);
+        drop(_first);
+
+        check.finish();
+    }
+
+    #[test]
+    #[should_panic(expected = "1 COM object(s) were leaked")]
+    fn it_detects_leaks() {
+        let wmi_con = wmi_con();
+
+        let check = LeakCheck::start();
+
+        let os = wmi_con
+            .get_raw_by_path(r#"\\.\root\cimv2:Win32_OperatingSystem=@"#)
+            .unwrap();
+        std::mem::forget(os);
+
+        check.finish();
+    }
+}
@@ -206,6 +206,68 @@
 //! The deserializer will either use the field names defined on the output struct,
 //! or retrieve all field names from WMI if the output is a `HashMap`.
 //!
+//! Objects can also be deserialized into internally tagged (or untagged) enums, which buffer all the properties selected by the query.
+//! Tags are compared to the variant names as strings, so numeric properties (like `DriveType`) select variants renamed to their values.
+//! Note that the properties are then matched to the fields case-sensitively.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, Debug)]
+//! #[serde(tag = "DriveType")]
+//! enum Disk {
+//!     #[serde(rename = "3")]
+//!     Local {
+//!         #[serde(rename = "DeviceID")]
+//!         device_id: String,
+//!     },
+//!     #[serde(rename = "5")]
+//!     Optical {
+//!         #[serde(rename = "VolumeName")]
+//!         volume_name: Option<String>,
+//!     },
+//!     #[serde(other)]
+//!     Other,
+//! }
+//!
+//! let disks: Vec<Disk> = con.raw_query("SELECT DeviceID, DriveType, VolumeName FROM Win32_LogicalDisk")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Common properties can be factored out using `#[serde(flatten)]`, which also buffers all the properties selected by the query
+//! (and matches them case-sensitively). Since serde doesn't expose the name and fields of such structs,
+//! they can't be used to build queries (like with [`query`](WMIConnection::query)), only with [`raw_query`](WMIConnection::raw_query):
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, Debug)]
+//! #[serde(rename_all = "PascalCase")]
+//! struct CimBase {
+//!     caption: String,
+//!     name: String,
+//! }
+//!
+//! #[derive(Deserialize, Debug)]
+//! #[serde(rename_all = "PascalCase")]
+//! struct OperatingSystem {
+//!     #[serde(flatten)]
+//!     base: CimBase,
+//!     build_number: String,
+//! }
+//!
+//! let results: Vec<OperatingSystem> = con.raw_query("SELECT Caption, Name, BuildNumber FROM Win32_OperatingSystem")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! [writing a data format]: https://serde.rs/data-format.html
 //!
 //! There are two main data structures (other than pointers to object) which convert native data to Rust data structures:
@@ -264,42 +326,141 @@
 #![allow(unused_unsafe)]
 #![cfg(windows)]
 
+pub mod account;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod batch;
+pub mod bitlocker;
+pub mod bulk;
+pub mod cache;
+pub mod capability;
+pub mod cluster;
+pub mod codegen;
+pub mod compare;
 pub mod connection;
+pub mod context;
+pub mod credentials;
 
 #[cfg(feature = "chrono")]
 pub mod datetime;
 
+/// Datetime helpers which do not depend on `chrono`.
+#[cfg(not(feature = "chrono"))]
+pub mod datetime {
+    pub mod raw;
+}
+
+/// Datetime wrappers based on `time`.
 #[cfg(feature = "time")]
-mod datetime_time;
+pub mod datetime_time;
 
 pub mod de;
+pub mod device_watch;
+pub mod diagnostics;
+pub mod dispatcher;
 pub mod duration;
+pub mod event_time;
+#[cfg(feature = "json")]
+pub mod export;
+pub mod fallback;
+pub mod file_watch;
+pub mod health;
+pub mod hook;
+pub mod hotfix;
+pub mod hyperv;
+pub mod iis;
+pub mod instance;
+pub mod job;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "leak-check")]
+pub mod leak_check;
+pub mod mdm;
+pub mod method;
+#[cfg(feature = "mi")]
+pub mod mi;
+pub mod multi_host;
+pub mod namespace;
+#[cfg(feature = "net")]
+pub mod net;
+pub mod order;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod perf;
+pub mod persistent;
+#[cfg(feature = "polars")]
+pub mod polars;
+pub mod power_watch;
+pub mod printer;
+pub mod process;
+pub mod process_watch;
 pub mod query;
+pub mod query_stats;
+pub mod registry_watch;
 pub mod result_enumerator;
+pub mod return_code;
+pub mod safe_variant;
 pub mod safearray;
+pub mod schema;
+pub mod security;
+pub mod security_center;
+pub mod sensors;
+pub mod ser;
+pub mod shadow_copy;
+pub mod snapshot;
+pub mod software;
+pub mod startup;
+pub mod storage;
+pub mod sysinfo;
+pub mod timer;
+pub mod tpm;
+pub mod transport;
+pub mod units;
 pub mod utils;
+pub mod validate;
 pub mod variant;
+pub mod versioned;
+pub mod watch;
+#[cfg(feature = "wsman")]
+pub mod wsman;
+pub mod xml;
 
 pub mod async_query;
 // Keep QuerySink implementation private
 pub(crate) mod query_sink;
 
+pub(crate) mod prefetch;
+
 pub mod notification;
 
 #[cfg(any(test, feature = "test"))]
 pub mod tests;
 
-pub use connection::{COMLibrary, WMIConnection};
+pub use connection::{COMLibrary, ComTransport, ProxyBlanket, WMIConnection, WMIConnectionBuilder};
+pub use context::WbemContext;
+pub use credentials::{Authority, Credentials};
 
 #[cfg(feature = "chrono")]
-pub use datetime::WMIDateTime;
+pub use datetime::{WMIDateTime, WMILocalDateTime};
 
 #[cfg(feature = "time")]
 pub use datetime_time::WMIOffsetDateTime;
 
+// With both features enabled, the `time` variant is only reachable as `datetime_time::WMILocalDateTime`.
+#[cfg(all(feature = "time", not(feature = "chrono")))]
+pub use datetime_time::WMILocalDateTime;
+
+pub use de::extra::WithExtra;
 pub use duration::WMIDuration;
-pub use query::{build_notification_query, build_query, FilterValue};
-pub use utils::{WMIError, WMIResult};
+pub use namespace::Namespace;
+#[cfg(feature = "net")]
+pub use net::WMIIpAddr;
+pub use query::{
+    build_aggregate_notification_query, build_notification_query, build_query, FilterValue,
+    GroupWithin,
+};
+pub use transport::{MockTransport, WbemTransport};
+pub use utils::{PropertyError, WMIError, WMIResult};
 pub use variant::Variant;
 
 #[doc = include_str!("../README.md")]
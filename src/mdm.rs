@@ -0,0 +1,192 @@
+//! Device management providers: the MDM Bridge (`ROOT\cimv2\mdm\dmmap`) and the Configuration Manager client (`ROOT\ccm`).
+//!
+//! The MDM Bridge exposes the Configuration Service Providers (CSPs) as `MDM_*` classes.
+//! It only serves callers running as the LocalSystem account: other callers get `WBEM_E_ACCESS_DENIED`,
+//! or (for some classes) an empty result. [`WMIConnection::mdm`] checks this upfront, and fails with
+//! [`WMIError::MdmRequiresLocalSystem`] instead.
+//!
+//! Instances of `MDM_*` classes are identified by their `InstanceID` and `ParentID`,
+//! which mirror the OMA-URI of the CSP node (for example, `./Vendor/MSFT/Policy/Config`).
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # use std::collections::HashMap;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! // Must run as LocalSystem, for example from a service.
+//! let mdm = con.mdm()?;
+//!
+//! let detail = mdm.dev_detail()?;
+//! println!("{} {}", detail.oem.unwrap_or_default(), detail.sw_v.unwrap_or_default());
+//!
+//! // Other CSPs can be read using the connection.
+//! let policies: Vec<HashMap<String, Variant>> = mdm.connection().raw_query(
+//!     "SELECT * FROM MDM_Policy_Result01_Update02 WHERE InstanceID = 'Update' AND ParentID = './Vendor/MSFT/Policy/Result'",
+//! )?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::{connection::WMIConnection, Variant, WMIError, WMIResult};
+use serde::Deserialize;
+use windows::Win32::Foundation::E_ACCESSDENIED;
+use windows::Win32::System::Wmi::WBEM_E_ACCESS_DENIED;
+
+/// The namespace of the MDM Bridge provider.
+pub const MDM_NAMESPACE: &str = "ROOT\\cimv2\\mdm\\dmmap";
+/// The namespace of the Configuration Manager (SCCM) client.
+pub const CCM_NAMESPACE: &str = "ROOT\\ccm";
+
+/// The SID of the LocalSystem account.
+const LOCAL_SYSTEM_SID: &str = "S-1-5-18";
+
+impl WMIError {
+    /// Whether this error was caused by insufficient rights (`WBEM_E_ACCESS_DENIED` or `E_ACCESSDENIED`).
+    pub fn is_access_denied(&self) -> bool {
+        matches!(
+            self,
+            WMIError::HResultError { hres } if *hres == WBEM_E_ACCESS_DENIED.0 || *hres == E_ACCESSDENIED.0
+        )
+    }
+}
+
+/// Details of the device, from the `DevDetail` CSP.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "MDM_DevDetail_Ext01")]
+#[serde(rename_all = "PascalCase")]
+pub struct DevDetail {
+    #[serde(rename = "OEM")]
+    pub oem: Option<String>,
+    pub dev_typ: Option<String>,
+    /// The firmware version.
+    #[serde(rename = "FwV")]
+    pub fw_v: Option<String>,
+    /// The version of Windows, like `10.0.22631.3296`.
+    #[serde(rename = "SwV")]
+    pub sw_v: Option<String>,
+    /// The hardware version.
+    #[serde(rename = "HwV")]
+    pub hw_v: Option<String>,
+    /// The hardware hash used by Windows Autopilot.
+    pub device_hardware_data: Option<String>,
+}
+
+/// The Configuration Manager client, from `SMS_Client` and `CCM_Client`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CcmClient {
+    /// Like `GUID:3A7B...`.
+    pub client_id: String,
+    /// Like `5.00.9122.1000`.
+    pub client_version: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "CCM_Client")]
+#[serde(rename_all = "PascalCase")]
+struct CcmClientId {
+    client_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "SMS_Client")]
+#[serde(rename_all = "PascalCase")]
+struct SmsClient {
+    client_version: String,
+}
+
+/// A connection to the MDM Bridge namespace, created using [`WMIConnection::mdm`].
+#[derive(Debug, Clone)]
+pub struct Mdm {
+    con: WMIConnection,
+}
+
+///
+/// ### Additional device management methods
+///
+impl WMIConnection {
+    /// Whether the current process runs as the LocalSystem account.
+    pub fn is_local_system(&self) -> WMIResult<bool> {
+        let path = format!("Win32_Process.Handle=\"{}\"", std::process::id());
+
+        let out = self
+            .with_namespace("ROOT\\CIMV2")?
+            .exec_method(&path, "GetOwnerSid", &[])?
+            .ok_or(WMIError::NullPointerResult)?;
+
+        Ok(out.get_property("Sid")? == Variant::String(LOCAL_SYSTEM_SID.to_owned()))
+    }
+
+    /// Connect to the MDM Bridge namespace on the same computer.
+    ///
+    /// For local connections, fails with [`WMIError::MdmRequiresLocalSystem`] unless the current process
+    /// runs as LocalSystem. This is not checked for remote connections.
+    ///
+    /// See the [module level documentation](crate::mdm) for an example.
+    pub fn mdm(&self) -> WMIResult<Mdm> {
+        if self.is_local() && !self.is_local_system()? {
+            return Err(WMIError::MdmRequiresLocalSystem);
+        }
+
+        Ok(Mdm {
+            con: self.with_namespace(MDM_NAMESPACE)?,
+        })
+    }
+
+    /// Read the identity and version of the Configuration Manager client.
+    ///
+    /// Connects to [`CCM_NAMESPACE`] on the same computer,
+    /// which fails with `WBEM_E_INVALID_NAMESPACE` if the client is not installed.
+    pub fn ccm_client(&self) -> WMIResult<CcmClient> {
+        let ccm_con = self.with_namespace(CCM_NAMESPACE)?;
+
+        let id: CcmClientId = ccm_con.get()?;
+        let sms: SmsClient = ccm_con.get()?;
+
+        Ok(CcmClient {
+            client_id: id.client_id,
+            client_version: sms.client_version,
+        })
+    }
+}
+
+impl Mdm {
+    /// The connection to the MDM Bridge namespace, to use other `MDM_*` classes.
+    pub fn connection(&self) -> &WMIConnection {
+        &self.con
+    }
+
+    pub fn dev_detail(&self) -> WMIResult<DevDetail> {
+        self.con.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::fixtures::*;
+    use crate::WMIError;
+
+    #[test]
+    fn it_requires_local_system_for_mdm() {
+        let wmi_con = wmi_con();
+
+        match wmi_con.is_local_system().unwrap() {
+            true => {
+                wmi_con.mdm().unwrap().dev_detail().unwrap();
+            }
+            false => {
+                assert!(matches!(
+                    wmi_con.mdm(),
+                    Err(WMIError::MdmRequiresLocalSystem)
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn it_detects_access_denied() {
+        let err = WMIError::HResultError {
+            hres: windows::Win32::System::Wmi::WBEM_E_ACCESS_DENIED.0,
+        };
+        assert!(err.is_access_denied());
+        assert!(!WMIError::ResultEmpty.is_access_denied());
+    }
+}
@@ -0,0 +1,495 @@
+use crate::{
+    connection::WMIConnection, result_enumerator::IWbemClassWrapper, safe_variant::SafeVariant,
+    schema::method_qualifier, Variant, WMIError, WMIResult,
+};
+use log::debug;
+use serde::de::DeserializeOwned;
+use windows::core::{BSTR, HSTRING, PCWSTR};
+use windows::Win32::System::Wmi::IWbemClassObject;
+
+impl IWbemClassWrapper {
+    /// Set the value of a property. Scalar values (numbers, strings and booleans)
+    /// and non-empty arrays of them (with items of the same type) are supported.
+    ///
+    /// Note that WMI expects `uint32` (and smaller) properties to be set using signed values (`Variant::I4`),
+    /// and 64-bit integers to be set using strings.
+    pub fn put_property(&self, property_name: &str, value: impl Into<Variant>) -> WMIResult<()> {
+        self.put_variant(property_name, &value.into())
+    }
+
+    pub(crate) fn put_variant(&self, property_name: &str, value: &Variant) -> WMIResult<()> {
+        let name = HSTRING::from(property_name);
+        let value = SafeVariant::from_variant(value)?;
+
+        unsafe {
+            self.inner
+                .Put(PCWSTR::from_raw(name.as_ptr()), 0, value.as_raw(), 0)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How a method is executed, see [`WMIConnection::method_kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MethodKind {
+    /// A method with the `Static` qualifier, executed on the class (like `Win32_Process.Create`).
+    Static,
+    /// A method executed on an instance (like `Win32_Process.Terminate`).
+    Instance,
+}
+
+///
+/// ### Additional method execution methods
+///
+impl WMIConnection {
+    /// Execute a method of the object at the given path, returning its output parameters (if it has any).
+    ///
+    /// To execute a static method, use the path of the class (like `Win32_Process`).
+    /// The `ReturnValue` of the method is one of the output parameters, and is not checked.
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// # let con = WMIConnection::new(COMLibrary::new()?)?;
+    /// const HKEY_LOCAL_MACHINE: u32 = 0x80000002;
+    ///
+    /// let out = con
+    ///     .exec_method(
+    ///         "StdRegProv",
+    ///         "GetStringValue",
+    ///         &[
+    ///             ("hDefKey", Variant::I4(HKEY_LOCAL_MACHINE as i32)),
+    ///             ("sSubKeyName", "SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion".into()),
+    ///             ("sValueName", "ProductName".into()),
+    ///         ],
+    ///     )?
+    ///     .unwrap();
+    ///
+    /// assert_eq!(out.get_property("ReturnValue")?, Variant::UI4(0));
+    /// assert!(matches!(out.get_property("sValue")?, Variant::String(_)));
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn exec_method(
+        &self,
+        object_path: &str,
+        method: &str,
+        in_params: &[(&str, Variant)],
+    ) -> WMIResult<Option<IWbemClassWrapper>> {
+        let class = self.get_raw_by_path(class_of_path(object_path))?;
+
+        self.exec_method_of(&class, object_path, method, in_params)
+    }
+
+    /// Execute a static method of a class, like `Win32_Process.Create`.
+    ///
+    /// Fails with [`WMIError::NotStaticMethod`] if the method is not static,
+    /// and with [`WMIError::StaticMethod`] if `class` is the path of an instance.
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// # let con = WMIConnection::new(COMLibrary::new()?)?;
+    /// let out = con
+    ///     .exec_static_method(
+    ///         "StdRegProv",
+    ///         "EnumKey",
+    ///         &[
+    ///             ("hDefKey", Variant::I4(0x80000002_u32 as i32)),
+    ///             ("sSubKeyName", "SOFTWARE".into()),
+    ///         ],
+    ///     )?
+    ///     .unwrap();
+    ///
+    /// assert_eq!(out.get_property("ReturnValue")?, Variant::UI4(0));
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn exec_static_method(
+        &self,
+        class: &str,
+        method: &str,
+        in_params: &[(&str, Variant)],
+    ) -> WMIResult<Option<IWbemClassWrapper>> {
+        self.exec_method_checked(class, method, in_params, MethodKind::Static)
+    }
+
+    /// Execute a (non-static) method of the instance at the given path, like `Win32_Process.Terminate`.
+    ///
+    /// Fails with [`WMIError::StaticMethod`] if the method is static,
+    /// and with [`WMIError::NotStaticMethod`] if `instance_path` is the path of a class.
+    pub fn exec_instance_method(
+        &self,
+        instance_path: &str,
+        method: &str,
+        in_params: &[(&str, Variant)],
+    ) -> WMIResult<Option<IWbemClassWrapper>> {
+        self.exec_method_checked(instance_path, method, in_params, MethodKind::Instance)
+    }
+
+    /// Whether a method of a class is static (has the `Static` qualifier).
+    pub fn method_kind(&self, class: &str, method: &str) -> WMIResult<MethodKind> {
+        let class = self.get_raw_by_path(class)?;
+
+        kind_of_method(&class, method)
+    }
+
+    fn exec_method_checked(
+        &self,
+        object_path: &str,
+        method: &str,
+        in_params: &[(&str, Variant)],
+        expected: MethodKind,
+    ) -> WMIResult<Option<IWbemClassWrapper>> {
+        let class_name = class_of_path(object_path);
+        let class = self.get_raw_by_path(class_name)?;
+
+        let kind = kind_of_method(&class, method)?;
+        let is_class_path = object_path.ends_with(class_name);
+
+        // Static methods are executed on the class, and other methods on an instance.
+        let matches_path = match kind {
+            MethodKind::Static => is_class_path,
+            MethodKind::Instance => !is_class_path,
+        };
+
+        if kind != expected || !matches_path {
+            let class = class_name.to_owned();
+            let method = method.to_owned();
+
+            return Err(match kind {
+                MethodKind::Static => WMIError::StaticMethod { class, method },
+                MethodKind::Instance => WMIError::NotStaticMethod { class, method },
+            });
+        }
+
+        self.exec_method_of(&class, object_path, method, in_params)
+    }
+
+    fn exec_method_of(
+        &self,
+        class: &IWbemClassWrapper,
+        object_path: &str,
+        method: &str,
+        in_params: &[(&str, Variant)],
+    ) -> WMIResult<Option<IWbemClassWrapper>> {
+        let in_params = method_in_params(class, method, in_params)?;
+
+        debug!("Executing {}.{}", object_path, method);
+
+        let mut out_params = None;
+
+        unsafe {
+            self.svc.ExecMethod(
+                &BSTR::from(object_path),
+                &BSTR::from(method),
+                Default::default(),
+                self.ctx(),
+                in_params.as_ref().map(|in_params| &in_params.inner),
+                Some(&mut out_params),
+                None,
+            )?;
+        }
+
+        Ok(out_params.map(IWbemClassWrapper::new))
+    }
+
+    /// Like [`exec_method`](Self::exec_method), but deserialize the output parameters into `Out`.
+    ///
+    /// Output parameters are deserialized like the properties of query results, so arrays (`Vec<T>`)
+    /// and embedded objects (structs, or `Vec`s of structs) are supported.
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// # let con = WMIConnection::new(COMLibrary::new()?)?;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize, Debug)]
+    /// struct EnumKeyOutput {
+    ///     #[serde(rename = "ReturnValue")]
+    ///     return_value: u32,
+    ///     #[serde(rename = "sNames")]
+    ///     names: Vec<String>,
+    /// }
+    ///
+    /// let out: EnumKeyOutput = con.exec_method_as(
+    ///     "StdRegProv",
+    ///     "EnumKey",
+    ///     &[
+    ///         ("hDefKey", Variant::I4(0x80000002_u32 as i32)),
+    ///         ("sSubKeyName", "SOFTWARE".into()),
+    ///     ],
+    /// )?;
+    ///
+    /// assert_eq!(out.return_value, 0);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn exec_method_as<Out>(
+        &self,
+        object_path: &str,
+        method: &str,
+        in_params: &[(&str, Variant)],
+    ) -> WMIResult<Out>
+    where
+        Out: DeserializeOwned,
+    {
+        self.exec_method(object_path, method, in_params)?
+            .ok_or(WMIError::NullPointerResult)?
+            .into_desr_with_options(&self.de_options, None)
+    }
+}
+
+fn kind_of_method(class: &IWbemClassWrapper, method: &str) -> WMIResult<MethodKind> {
+    match method_qualifier(class, method, "Static")? {
+        Some(value) if value != Variant::Bool(false) => Ok(MethodKind::Static),
+        _ => Ok(MethodKind::Instance),
+    }
+}
+
+/// Create an instance of the input parameters of the method, and set the given values.
+fn method_in_params(
+    class: &IWbemClassWrapper,
+    method: &str,
+    in_params: &[(&str, Variant)],
+) -> WMIResult<Option<IWbemClassWrapper>> {
+    let method_name = HSTRING::from(method);
+
+    let mut in_signature: Option<IWbemClassObject> = None;
+    let mut out_signature: Option<IWbemClassObject> = None;
+
+    unsafe {
+        class.inner.GetMethod(
+            PCWSTR::from_raw(method_name.as_ptr()),
+            0,
+            &mut in_signature,
+            &mut out_signature,
+        )?;
+    }
+
+    let in_signature = match in_signature {
+        Some(in_signature) => in_signature,
+        None if in_params.is_empty() => return Ok(None),
+        None => return Err(WMIError::NoInputParameters(method.to_owned())),
+    };
+
+    let instance = IWbemClassWrapper::new(unsafe { in_signature.SpawnInstance(0)? });
+
+    for (name, value) in in_params {
+        instance.put_variant(name, value)?;
+    }
+
+    Ok(Some(instance))
+}
+
+/// Return the class of an object path, like `Win32_Process` for `\\.\root\cimv2:Win32_Process.Handle="4"`.
+pub(crate) fn class_of_path(object_path: &str) -> &str {
+    // Key values (which come after the first `=`) can contain any character.
+    let class_and_key = &object_path[..object_path.find('=').unwrap_or(object_path.len())];
+
+    let class_and_key = match class_and_key.rfind(':') {
+        Some(idx) => &class_and_key[idx + 1..],
+        None => class_and_key,
+    };
+
+    let end = class_and_key.find('.').unwrap_or(class_and_key.len());
+
+    &class_and_key[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use serde::Deserialize;
+
+    #[test]
+    fn it_extracts_the_class_of_a_path() {
+        assert_eq!(class_of_path("StdRegProv"), "StdRegProv");
+        assert_eq!(
+            class_of_path("Win32_OperatingSystem=@"),
+            "Win32_OperatingSystem"
+        );
+        assert_eq!(
+            class_of_path(r#"\\.\root\cimv2:Win32_Process.Handle="4""#),
+            "Win32_Process"
+        );
+        assert_eq!(
+            class_of_path(r#"Win32_Directory.Name="C:\\Windows""#),
+            "Win32_Directory"
+        );
+    }
+
+    #[test]
+    fn it_can_exec_methods() {
+        let wmi_con = wmi_con();
+
+        let out = wmi_con
+            .exec_method(
+                "StdRegProv",
+                "EnumKey",
+                &[
+                    ("hDefKey", Variant::I4(0x80000002_u32 as i32)),
+                    ("sSubKeyName", "SOFTWARE".into()),
+                ],
+            )
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(out.get_property("ReturnValue").unwrap(), Variant::UI4(0));
+
+        match out.get_property("sNames").unwrap() {
+            Variant::Array(names) => {
+                assert!(names.contains(&Variant::String("Microsoft".to_owned())))
+            }
+            other => panic!("Unexpected sNames {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_deserializes_array_out_params() {
+        #[derive(Deserialize, Debug)]
+        struct EnumValuesOutput {
+            #[serde(rename = "ReturnValue")]
+            return_value: u32,
+            #[serde(rename = "sNames")]
+            names: Vec<String>,
+            #[serde(rename = "Types")]
+            types: Vec<u32>,
+        }
+
+        let wmi_con = wmi_con();
+
+        let out: EnumValuesOutput = wmi_con
+            .exec_method_as(
+                "StdRegProv",
+                "EnumValues",
+                &[
+                    ("hDefKey", Variant::I4(0x80000002_u32 as i32)),
+                    (
+                        "sSubKeyName",
+                        "SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion".into(),
+                    ),
+                ],
+            )
+            .unwrap();
+
+        assert_eq!(out.return_value, 0);
+        assert_eq!(out.names.len(), out.types.len());
+        assert!(out.names.iter().any(|name| name == "ProductName"));
+    }
+
+    #[test]
+    fn it_deserializes_embedded_object_out_params() {
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "PascalCase")]
+        struct GetSecurityDescriptorOutput {
+            return_value: u32,
+            descriptor: Option<SecurityDescriptor>,
+        }
+
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "PascalCase")]
+        struct SecurityDescriptor {
+            control_flags: u32,
+            owner: Option<Trustee>,
+            // An array of embedded `Win32_ACE` objects.
+            #[serde(rename = "DACL")]
+            dacl: Option<Vec<Ace>>,
+        }
+
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "PascalCase")]
+        struct Ace {
+            access_mask: u32,
+            ace_type: u32,
+            trustee: Trustee,
+        }
+
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "PascalCase")]
+        struct Trustee {
+            #[serde(rename = "SIDString")]
+            sid_string: String,
+        }
+
+        let wmi_con = wmi_con();
+
+        let out: GetSecurityDescriptorOutput = wmi_con
+            .exec_method_as(
+                r#"Win32_LogicalFileSecuritySetting.Path="C:\\Windows""#,
+                "GetSecurityDescriptor",
+                &[],
+            )
+            .unwrap();
+
+        assert_eq!(out.return_value, 0);
+
+        let descriptor = out.descriptor.unwrap();
+        assert_ne!(descriptor.control_flags, 0);
+        assert!(descriptor
+            .owner
+            .is_some_and(|owner| owner.sid_string.starts_with("S-1-")));
+
+        let dacl = descriptor.dacl.unwrap();
+        assert!(!dacl.is_empty());
+        assert!(dacl
+            .iter()
+            .all(|ace| ace.trustee.sid_string.starts_with("S-1-")));
+        assert!(dacl
+            .iter()
+            .any(|ace| ace.ace_type == 0 && ace.access_mask != 0));
+    }
+
+    #[test]
+    fn it_dispatches_static_and_instance_methods() {
+        let wmi_con = wmi_con();
+
+        assert_eq!(
+            wmi_con.method_kind("Win32_Process", "Create").unwrap(),
+            MethodKind::Static
+        );
+        assert_eq!(
+            wmi_con.method_kind("Win32_Process", "Terminate").unwrap(),
+            MethodKind::Instance
+        );
+
+        let out = wmi_con
+            .exec_static_method(
+                "StdRegProv",
+                "EnumKey",
+                &[
+                    ("hDefKey", Variant::I4(0x80000002_u32 as i32)),
+                    ("sSubKeyName", "SOFTWARE".into()),
+                ],
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(out.get_property("ReturnValue").unwrap(), Variant::UI4(0));
+
+        let out = wmi_con
+            .exec_instance_method("Win32_OperatingSystem=@", "GetOwner", &[])
+            .map(|_| ());
+        // `Win32_OperatingSystem` doesn't have a `GetOwner` method.
+        assert!(matches!(out, Err(WMIError::HResultError { .. })));
+
+        assert!(matches!(
+            wmi_con.exec_static_method("Win32_Process", "Terminate", &[]),
+            Err(WMIError::NotStaticMethod { .. })
+        ));
+        assert!(matches!(
+            wmi_con.exec_instance_method("Win32_Process", "Terminate", &[]),
+            Err(WMIError::NotStaticMethod { .. })
+        ));
+        assert!(matches!(
+            wmi_con.exec_instance_method(r#"Win32_Process.Handle="0""#, "Create", &[]),
+            Err(WMIError::StaticMethod { .. })
+        ));
+        assert!(matches!(
+            wmi_con.exec_static_method(r#"Win32_Process.Handle="0""#, "Create", &[]),
+            Err(WMIError::StaticMethod { .. })
+        ));
+    }
+}
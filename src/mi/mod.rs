@@ -0,0 +1,648 @@
+//! Query WMI using the Management Infrastructure (MI) API (`mi.dll`), which is used by PowerShell's CIM cmdlets.
+//!
+//! Unlike the COM transport ([`ComTransport`](crate::ComTransport)), an [`MiConnection`] does not require COM to be initialized,
+//! and can be shared between threads. It can connect using DCOM (the default for local connections) or WinRM
+//! (the default for remote connections), see [`MiProtocol`]. Linking requires `mi.lib` (from the Windows SDK).
+//!
+//! Results are deserialized using the same serde surface as the COM transport,
+//! and property values have the same types as the values returned by the COM API
+//! (datetimes and intervals are returned as DMTF strings, so [`WMIDateTime`](crate::WMIDateTime) works as usual).
+//! Embedded instances and references are deserialized as maps, and system properties (like `__Path`) are not returned.
+//! The [`DeserializeOptions`] of the connection are set using [`MiConnectionBuilder::deserialize_options`].
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! use serde::Deserialize;
+//! use wmi::WMIConnection;
+//!
+//! #[derive(Deserialize, Debug)]
+//! #[serde(rename = "Win32_Process")]
+//! #[serde(rename_all = "PascalCase")]
+//! struct Process {
+//!     process_id: u32,
+//!     name: String,
+//! }
+//!
+//! let con = WMIConnection::mi()?;
+//!
+//! std::thread::scope(|scope| {
+//!     scope.spawn(|| {
+//!         let processes: Vec<Process> = con.query().unwrap();
+//!         println!("{} processes", processes.len());
+//!     });
+//! });
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    connection::WMIConnection,
+    de::{
+        options::DeserializeOptions,
+        owned::{Object, Value},
+    },
+    software::{check_win32_product, is_win32_product_query},
+    transport::{deserialize_objects, WbemTransport},
+    variant::string_from_wide,
+    Credentials, Variant, WMIError, WMIResult,
+};
+use log::debug;
+use serde::de::DeserializeOwned;
+use std::{ffi::c_void, ptr, sync::Arc};
+use zeroize::Zeroizing;
+
+mod sys;
+
+use sys::*;
+
+/// The protocol used by an [`MiConnection`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MiProtocol {
+    Dcom,
+    /// WS-Management (like the `wsman` module, which requires the `wsman` feature).
+    WinRm,
+}
+
+impl MiProtocol {
+    fn name(self) -> &'static str {
+        match self {
+            MiProtocol::Dcom => "WMIDCOM",
+            MiProtocol::WinRm => "WINRM",
+        }
+    }
+}
+
+/// The description of an `MI_Result` code.
+fn result_description(result: MI_Result) -> &'static str {
+    match result {
+        1 => "failed",
+        2 => "access denied",
+        3 => "invalid namespace",
+        4 => "invalid parameter",
+        5 => "invalid class",
+        6 => "not found",
+        7 => "not supported",
+        12 => "invalid query",
+        _ => "unknown error",
+    }
+}
+
+fn check(result: MI_Result) -> WMIResult<()> {
+    match result {
+        MI_RESULT_OK => Ok(()),
+        result => Err(WMIError::MiError {
+            result,
+            message: result_description(result).to_owned(),
+        }),
+    }
+}
+
+/// A nul-terminated UTF-16 string.
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(Some(0)).collect()
+}
+
+/// Read a nul-terminated UTF-16 string.
+unsafe fn string_from_ptr(ptr: *const MI_Char) -> WMIResult<String> {
+    if ptr.is_null() {
+        return Ok(String::new());
+    }
+
+    let len = (0..).take_while(|&idx| *ptr.add(idx) != 0).count();
+
+    Ok(string_from_wide(std::slice::from_raw_parts(ptr, len))?)
+}
+
+/// Delete the extended error returned by some functions, which is not used.
+unsafe fn delete_extended_error(error: *mut MI_Instance) {
+    if !error.is_null() {
+        ((*(*error).ft).Delete)(error);
+    }
+}
+
+struct Application(MI_Application);
+
+// The MI API is free-threaded: handles can be used (and closed) from any thread.
+unsafe impl Send for Application {}
+unsafe impl Sync for Application {}
+
+impl Drop for Application {
+    fn drop(&mut self) {
+        unsafe { ((*self.0.ft).Close)(&mut self.0) };
+    }
+}
+
+struct Session {
+    raw: MI_Session,
+    // Keep the application open for as long as the session.
+    _app: Arc<Application>,
+}
+
+unsafe impl Send for Session {}
+unsafe impl Sync for Session {}
+
+impl Session {
+    /// The MI API takes mutable pointers to handles, but does not modify them.
+    fn as_ptr(&self) -> *mut MI_Session {
+        &self.raw as *const MI_Session as *mut MI_Session
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        // Without a callback, `Close` waits for the operations of the session to complete.
+        unsafe { ((*self.raw.ft).Close)(&mut self.raw, ptr::null_mut(), None) };
+    }
+}
+
+/// Closes the operation (which also frees the instances it returned) when dropped.
+struct Operation(MI_Operation);
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        unsafe { ((*self.0.ft).Close)(&mut self.0) };
+    }
+}
+
+/// A connection to WMI using the MI API, see the [module level documentation](crate::mi).
+pub type MiConnection = WMIConnection<MiTransport>;
+
+/// The transport of an [`MiConnection`].
+#[derive(Clone)]
+pub struct MiTransport {
+    session: Arc<Session>,
+    namespace: String,
+    allow_win32_product: bool,
+    de_options: DeserializeOptions,
+}
+
+impl std::fmt::Debug for MiTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MiTransport")
+            .field("namespace", &self.namespace)
+            .finish_non_exhaustive()
+    }
+}
+
+///
+/// ### Additional MI methods
+///
+impl WMIConnection<MiTransport> {
+    /// Creates a connection to the `ROOT\CIMV2` namespace of the local computer using the MI API.
+    pub fn mi() -> WMIResult<Self> {
+        Self::mi_builder().build()
+    }
+
+    pub fn mi_builder() -> MiConnectionBuilder {
+        MiConnectionBuilder::default()
+    }
+}
+
+impl MiTransport {
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    fn query_instances(&self, query: &str) -> WMIResult<Vec<Object>> {
+        let namespace = wide(&self.namespace);
+        let dialect = wide("WQL");
+        let query_wide = wide(query);
+
+        debug!("Querying {:?} using MI", query);
+
+        let mut operation = Operation(MI_Operation::zeroed());
+
+        // Without callbacks, the operation is synchronous and results are pulled using `GetInstance`.
+        unsafe {
+            ((*self.session.raw.ft).QueryInstances)(
+                self.session.as_ptr(),
+                0,
+                ptr::null(),
+                namespace.as_ptr(),
+                dialect.as_ptr(),
+                query_wide.as_ptr(),
+                ptr::null(),
+                &mut operation.0,
+            )
+        };
+
+        let mut objects = vec![];
+
+        loop {
+            let mut instance: *const MI_Instance = ptr::null();
+            let mut more_results: MI_Boolean = 0;
+            let mut result = MI_RESULT_OK;
+            let mut error_message: *const MI_Char = ptr::null();
+            let mut completion_details: *const MI_Instance = ptr::null();
+
+            unsafe {
+                check(((*operation.0.ft).GetInstance)(
+                    &mut operation.0,
+                    &mut instance,
+                    &mut more_results,
+                    &mut result,
+                    &mut error_message,
+                    &mut completion_details,
+                ))?;
+            }
+
+            // The instance is only valid until the next call to `GetInstance`.
+            if !instance.is_null() {
+                objects.push(unsafe { object_from_instance(instance)? });
+            }
+
+            if more_results == 0 {
+                if result != MI_RESULT_OK {
+                    let message = unsafe { string_from_ptr(error_message)? };
+
+                    return Err(WMIError::MiError {
+                        result,
+                        message: if message.is_empty() {
+                            result_description(result).to_owned()
+                        } else {
+                            message
+                        },
+                    });
+                }
+
+                return Ok(objects);
+            }
+        }
+    }
+}
+
+unsafe fn object_from_instance(instance: *const MI_Instance) -> WMIResult<Object> {
+    let ft = &*(*instance).ft;
+
+    let mut class: *const MI_Char = ptr::null();
+    check((ft.GetClassName)(instance, &mut class))?;
+
+    let mut count = 0;
+    check((ft.GetElementCount)(instance, &mut count))?;
+
+    let mut properties = Vec::with_capacity(count as usize);
+
+    for idx in 0..count {
+        let mut name: *const MI_Char = ptr::null();
+        let mut value = MI_Value { uint64: 0 };
+        let mut element_type = 0;
+        let mut flags = 0;
+
+        check((ft.GetElementAt)(
+            instance,
+            idx,
+            &mut name,
+            &mut value,
+            &mut element_type,
+            &mut flags,
+        ))?;
+
+        let value = if flags & MI_FLAG_NULL != 0 {
+            Value::Null
+        } else if element_type & MI_ARRAY != 0 {
+            let item_type = element_type & !MI_ARRAY;
+            let array = value.array;
+
+            let items = (0..array.size as usize)
+                .map(|idx| {
+                    let item = (array.data as *const u8).add(idx * item_size(item_type)?);
+                    read_scalar(item as *const c_void, item_type)
+                })
+                .collect::<WMIResult<Vec<Value>>>()?;
+
+            Value::Array(items)
+        } else {
+            read_scalar(&value as *const MI_Value as *const c_void, element_type)?
+        };
+
+        properties.push((string_from_ptr(name)?, value));
+    }
+
+    Ok(Object {
+        class: string_from_ptr(class)?,
+        properties,
+    })
+}
+
+fn unsupported_type(element_type: MI_Type) -> WMIError {
+    WMIError::ConvertVariantError(format!("Unsupported MI type {}", element_type))
+}
+
+fn item_size(item_type: MI_Type) -> WMIResult<usize> {
+    Ok(match item_type {
+        MI_BOOLEAN | MI_UINT8 | MI_SINT8 => 1,
+        MI_UINT16 | MI_SINT16 | MI_CHAR16 => 2,
+        MI_UINT32 | MI_SINT32 | MI_REAL32 => 4,
+        MI_UINT64 | MI_SINT64 | MI_REAL64 => 8,
+        MI_DATETIME => std::mem::size_of::<MI_Datetime>(),
+        MI_STRING | MI_REFERENCE | MI_INSTANCE => std::mem::size_of::<*const c_void>(),
+        other => return Err(unsupported_type(other)),
+    })
+}
+
+/// Read a scalar value, using the same `Variant` types as the COM API.
+unsafe fn read_scalar(ptr: *const c_void, element_type: MI_Type) -> WMIResult<Value> {
+    let variant = match element_type {
+        MI_BOOLEAN => Variant::Bool(*(ptr as *const MI_Boolean) != 0),
+        MI_UINT8 => Variant::UI1(*(ptr as *const u8)),
+        MI_SINT8 => Variant::I1(*(ptr as *const i8)),
+        MI_UINT16 => Variant::UI2(*(ptr as *const u16)),
+        MI_SINT16 => Variant::I2(*(ptr as *const i16)),
+        MI_UINT32 => Variant::UI4(*(ptr as *const u32)),
+        MI_SINT32 => Variant::I4(*(ptr as *const i32)),
+        MI_UINT64 => Variant::UI8(*(ptr as *const u64)),
+        MI_SINT64 => Variant::I8(*(ptr as *const i64)),
+        MI_REAL32 => Variant::R4(*(ptr as *const f32)),
+        MI_REAL64 => Variant::R8(*(ptr as *const f64)),
+        // `char16` properties are returned as `VT_I2` by the COM API.
+        MI_CHAR16 => Variant::I2(*(ptr as *const i16)),
+        MI_DATETIME => Variant::String(datetime_to_dmtf(&*(ptr as *const MI_Datetime))),
+        MI_STRING => Variant::String(string_from_ptr(*(ptr as *const *const MI_Char))?),
+        MI_REFERENCE | MI_INSTANCE => {
+            let instance = *(ptr as *const *const MI_Instance);
+
+            return match instance.is_null() {
+                true => Ok(Value::Null),
+                false => Ok(Value::Object(object_from_instance(instance)?)),
+            };
+        }
+        other => return Err(unsupported_type(other)),
+    };
+
+    Ok(Value::Variant(variant))
+}
+
+/// Format a datetime (or an interval) like the COM API does.
+fn datetime_to_dmtf(datetime: &MI_Datetime) -> String {
+    unsafe {
+        if datetime.is_timestamp != 0 {
+            let ts = datetime.u.timestamp;
+
+            format!(
+                "{:04}{:02}{:02}{:02}{:02}{:02}.{:06}{}{:03}",
+                ts.year,
+                ts.month,
+                ts.day,
+                ts.hour,
+                ts.minute,
+                ts.second,
+                ts.microseconds,
+                if ts.utc < 0 { '-' } else { '+' },
+                ts.utc.abs()
+            )
+        } else {
+            let interval = datetime.u.interval;
+
+            format!(
+                "{:08}{:02}{:02}{:02}.{:06}:000",
+                interval.days,
+                interval.hours,
+                interval.minutes,
+                interval.seconds,
+                interval.microseconds
+            )
+        }
+    }
+}
+
+impl WbemTransport for MiTransport {
+    fn raw_query<T>(&self, query: &str) -> WMIResult<Vec<T>>
+    where
+        T: DeserializeOwned,
+    {
+        check_win32_product(is_win32_product_query(query), self.allow_win32_product)?;
+
+        let objects = self.query_instances(query)?;
+
+        deserialize_objects(objects, query, &self.de_options)
+    }
+}
+
+/// A builder for an [`MiConnection`], created using [`WMIConnection::mi_builder`].
+///
+/// By default, connects to the `ROOT\CIMV2` namespace of the local computer as the current user.
+#[derive(Debug, Default)]
+pub struct MiConnectionBuilder {
+    server: Option<String>,
+    namespace: Option<String>,
+    protocol: Option<MiProtocol>,
+    credentials: Option<Credentials>,
+    allow_win32_product: bool,
+    de_options: DeserializeOptions,
+}
+
+impl MiConnectionBuilder {
+    /// The remote computer to connect to.
+    pub fn server(mut self, server: &str) -> Self {
+        self.server = Some(server.to_owned());
+        self
+    }
+
+    /// The namespace path to connect to (a string or a [`Namespace`](crate::Namespace)).
+    pub fn namespace(mut self, namespace_path: impl AsRef<str>) -> Self {
+        self.namespace = Some(namespace_path.as_ref().to_owned());
+        self
+    }
+
+    /// The protocol to use. Defaults to DCOM for local connections, and to WinRM for remote connections.
+    pub fn protocol(mut self, protocol: MiProtocol) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    /// The credentials to use, see [`Credentials`].
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Allow queries of `Win32_Product`, see the [`software`](crate::software) module.
+    pub fn allow_win32_product(mut self, allowed: bool) -> Self {
+        self.allow_win32_product = allowed;
+        self
+    }
+
+    /// The options used when deserializing results, see [`DeserializeOptions`].
+    pub fn deserialize_options(mut self, options: DeserializeOptions) -> Self {
+        self.de_options = options;
+        self
+    }
+
+    pub fn build(self) -> WMIResult<MiConnection> {
+        let mut raw_app = MI_Application::zeroed();
+        let mut extended_error: *mut MI_Instance = ptr::null_mut();
+
+        unsafe {
+            let result =
+                MI_Application_InitializeV1(0, ptr::null(), &mut extended_error, &mut raw_app);
+            delete_extended_error(extended_error);
+            check(result)?;
+        }
+
+        let app = Arc::new(Application(raw_app));
+
+        let protocol = self.protocol.map(|protocol| wide(protocol.name()));
+        let destination = self.server.as_deref().map(wide);
+
+        let mut options = MI_DestinationOptions::zeroed();
+        let mut has_options = false;
+
+        if let Some(credentials) = &self.credentials {
+            let app_ptr = &app.0 as *const MI_Application as *mut MI_Application;
+
+            unsafe {
+                check(((*app.0.ft).NewDestinationOptions)(app_ptr, &mut options))?;
+            }
+            has_options = true;
+
+            let mut add_credentials = || -> WMIResult<()> {
+                let auth_type = wide("Default");
+                let domain = wide(credentials.domain().unwrap_or_default());
+                let user = wide(credentials.user());
+
+                credentials.with_password_bstr(|password| {
+                    let password = Zeroizing::new(
+                        password
+                            .as_wide()
+                            .iter()
+                            .copied()
+                            .chain(Some(0))
+                            .collect::<Vec<u16>>(),
+                    );
+
+                    let user_credentials = MI_UserCredentials {
+                        authentication_type: auth_type.as_ptr(),
+                        domain: domain.as_ptr(),
+                        username: user.as_ptr(),
+                        password: password.as_ptr(),
+                    };
+
+                    // The options keep a copy of the credentials.
+                    unsafe {
+                        check(((*options.ft).AddCredentials)(
+                            &mut options,
+                            wide("__MI_DESTINATIONOPTIONS_DESTINATION_CREDENTIALS").as_ptr(),
+                            &user_credentials,
+                            0,
+                        ))
+                    }
+                })
+            };
+
+            if let Err(err) = add_credentials() {
+                unsafe { ((*options.ft).Delete)(&mut options) };
+                return Err(err);
+            }
+        }
+
+        debug!(
+            "Creating an MI session to {}",
+            self.server.as_deref().unwrap_or("the local computer")
+        );
+
+        let mut raw_session = MI_Session::zeroed();
+        let mut extended_error: *mut MI_Instance = ptr::null_mut();
+
+        let result = unsafe {
+            let result = ((*app.0.ft).NewSession)(
+                &app.0 as *const MI_Application as *mut MI_Application,
+                protocol
+                    .as_ref()
+                    .map_or(ptr::null(), |protocol| protocol.as_ptr()),
+                destination
+                    .as_ref()
+                    .map_or(ptr::null(), |destination| destination.as_ptr()),
+                if has_options {
+                    &mut options
+                } else {
+                    ptr::null_mut()
+                },
+                ptr::null(),
+                &mut extended_error,
+                &mut raw_session,
+            );
+
+            delete_extended_error(extended_error);
+
+            // The session keeps a copy of the options.
+            if has_options {
+                ((*options.ft).Delete)(&mut options);
+            }
+
+            result
+        };
+
+        check(result)?;
+
+        Ok(WMIConnection::from_transport(MiTransport {
+            session: Arc::new(Session {
+                raw: raw_session,
+                _app: app,
+            }),
+            namespace: self.namespace.unwrap_or_else(|| "ROOT\\CIMV2".to_owned()),
+            allow_win32_product: self.allow_win32_product,
+            de_options: self.de_options,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[test]
+    fn it_formats_datetimes() {
+        let datetime = MI_Datetime {
+            is_timestamp: 1,
+            u: MI_DatetimeValue {
+                timestamp: MI_Timestamp {
+                    year: 2024,
+                    month: 3,
+                    day: 1,
+                    hour: 10,
+                    minute: 0,
+                    second: 0,
+                    microseconds: 500000,
+                    utc: -300,
+                },
+            },
+        };
+
+        assert_eq!(datetime_to_dmtf(&datetime), "20240301100000.500000-300");
+    }
+
+    #[test]
+    fn it_matches_the_com_connection() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(rename = "Win32_OperatingSystem")]
+        #[serde(rename_all = "PascalCase")]
+        struct OperatingSystem {
+            caption: String,
+            number_of_processes: u32,
+            #[serde(with = "crate::datetime::raw")]
+            last_boot_up_time: String,
+            #[serde(rename = "MUILanguages")]
+            mui_languages: Vec<String>,
+        }
+
+        let mi_con = WMIConnection::mi().unwrap();
+        let os: OperatingSystem = mi_con.get().unwrap();
+        let expected: OperatingSystem = wmi_con().get().unwrap();
+
+        assert_eq!(os.caption, expected.caption);
+        assert_eq!(os.last_boot_up_time, expected.last_boot_up_time);
+        assert_eq!(os.mui_languages, expected.mui_languages);
+
+        let processes: Vec<HashMap<String, Variant>> = mi_con
+            .raw_query("SELECT ProcessId FROM Win32_Process WHERE ProcessId = 4")
+            .unwrap();
+        assert_eq!(processes[0]["ProcessId"], Variant::UI4(4));
+
+        assert!(matches!(
+            mi_con.raw_query::<HashMap<String, Variant>>("SELECT * FROM Win32_NoSuchClass"),
+            Err(WMIError::MiError { .. })
+        ));
+    }
+}
@@ -0,0 +1,294 @@
+//! The subset of the MI API (`mi.h`) used by this crate.
+//!
+//! Most of the API is exposed through function tables (the `ft` field of each handle).
+//! Functions which are not used are declared as opaque pointers, to keep the layout of the tables.
+#![allow(non_camel_case_types, non_snake_case, dead_code)]
+use std::ffi::c_void;
+
+pub type MI_Char = u16;
+pub type MI_Boolean = u8;
+pub type MI_Result = u32;
+pub type MI_Type = u32;
+
+pub const MI_RESULT_OK: MI_Result = 0;
+
+pub const MI_BOOLEAN: MI_Type = 0;
+pub const MI_UINT8: MI_Type = 1;
+pub const MI_SINT8: MI_Type = 2;
+pub const MI_UINT16: MI_Type = 3;
+pub const MI_SINT16: MI_Type = 4;
+pub const MI_UINT32: MI_Type = 5;
+pub const MI_SINT32: MI_Type = 6;
+pub const MI_UINT64: MI_Type = 7;
+pub const MI_SINT64: MI_Type = 8;
+pub const MI_REAL32: MI_Type = 9;
+pub const MI_REAL64: MI_Type = 10;
+pub const MI_CHAR16: MI_Type = 11;
+pub const MI_DATETIME: MI_Type = 12;
+pub const MI_STRING: MI_Type = 13;
+pub const MI_REFERENCE: MI_Type = 14;
+pub const MI_INSTANCE: MI_Type = 15;
+/// Added to a scalar type for arrays of it (like `MI_UINT32A`).
+pub const MI_ARRAY: MI_Type = 16;
+
+/// Set in the flags of an element whose value is null.
+pub const MI_FLAG_NULL: u32 = 0x2000_0000;
+
+type Opaque = *const c_void;
+
+#[repr(C)]
+pub struct MI_Application {
+    reserved1: u64,
+    reserved2: isize,
+    pub ft: *const MI_ApplicationFT,
+}
+
+#[repr(C)]
+pub struct MI_ApplicationFT {
+    pub Close: unsafe extern "system" fn(application: *mut MI_Application) -> MI_Result,
+    pub NewSession: unsafe extern "system" fn(
+        application: *mut MI_Application,
+        protocol: *const MI_Char,
+        destination: *const MI_Char,
+        options: *mut MI_DestinationOptions,
+        callbacks: *const c_void,
+        extended_error: *mut *mut MI_Instance,
+        session: *mut MI_Session,
+    ) -> MI_Result,
+    NewHostedProvider: Opaque,
+    NewInstance: Opaque,
+    pub NewDestinationOptions: unsafe extern "system" fn(
+        application: *mut MI_Application,
+        options: *mut MI_DestinationOptions,
+    ) -> MI_Result,
+    NewOperationOptions: Opaque,
+    NewSubscriptionDeliveryOptions: Opaque,
+    NewSerializer: Opaque,
+    NewDeserializer: Opaque,
+    NewInstanceFromClass: Opaque,
+    NewClass: Opaque,
+}
+
+#[repr(C)]
+pub struct MI_Session {
+    reserved1: u64,
+    reserved2: isize,
+    pub ft: *const MI_SessionFT,
+}
+
+#[repr(C)]
+pub struct MI_SessionFT {
+    pub Close: unsafe extern "system" fn(
+        session: *mut MI_Session,
+        completion_context: *mut c_void,
+        completion_callback: Option<unsafe extern "system" fn(*mut c_void)>,
+    ) -> MI_Result,
+    GetApplication: Opaque,
+    GetInstance: Opaque,
+    ModifyInstance: Opaque,
+    CreateInstance: Opaque,
+    DeleteInstance: Opaque,
+    Invoke: Opaque,
+    EnumerateInstances: Opaque,
+    pub QueryInstances: unsafe extern "system" fn(
+        session: *mut MI_Session,
+        flags: u32,
+        options: *const c_void,
+        namespace_name: *const MI_Char,
+        query_dialect: *const MI_Char,
+        query_expression: *const MI_Char,
+        callbacks: *const c_void,
+        operation: *mut MI_Operation,
+    ),
+    AssociatorInstances: Opaque,
+    ReferenceInstances: Opaque,
+    Subscribe: Opaque,
+    GetClass: Opaque,
+    EnumerateClasses: Opaque,
+    TestConnection: Opaque,
+}
+
+#[repr(C)]
+pub struct MI_Operation {
+    reserved1: u64,
+    reserved2: isize,
+    pub ft: *const MI_OperationFT,
+}
+
+#[repr(C)]
+pub struct MI_OperationFT {
+    pub Close: unsafe extern "system" fn(operation: *mut MI_Operation) -> MI_Result,
+    Cancel: Opaque,
+    GetSession: Opaque,
+    pub GetInstance: unsafe extern "system" fn(
+        operation: *mut MI_Operation,
+        instance: *mut *const MI_Instance,
+        more_results: *mut MI_Boolean,
+        result: *mut MI_Result,
+        error_message: *mut *const MI_Char,
+        completion_details: *mut *const MI_Instance,
+    ) -> MI_Result,
+    GetIndication: Opaque,
+    GetClass: Opaque,
+}
+
+#[repr(C)]
+pub struct MI_Instance {
+    pub ft: *const MI_InstanceFT,
+    class_decl: Opaque,
+    server_name: *const MI_Char,
+    name_space: *const MI_Char,
+    reserved: [isize; 4],
+}
+
+#[repr(C)]
+pub struct MI_InstanceFT {
+    Clone: Opaque,
+    Destruct: Opaque,
+    pub Delete: unsafe extern "system" fn(instance: *mut MI_Instance) -> MI_Result,
+    IsA: Opaque,
+    pub GetClassName: unsafe extern "system" fn(
+        instance: *const MI_Instance,
+        class_name: *mut *const MI_Char,
+    ) -> MI_Result,
+    SetNameSpace: Opaque,
+    GetNameSpace: Opaque,
+    pub GetElementCount:
+        unsafe extern "system" fn(instance: *const MI_Instance, count: *mut u32) -> MI_Result,
+    AddElement: Opaque,
+    SetElement: Opaque,
+    SetElementAt: Opaque,
+    GetElement: Opaque,
+    pub GetElementAt: unsafe extern "system" fn(
+        instance: *const MI_Instance,
+        index: u32,
+        name: *mut *const MI_Char,
+        value: *mut MI_Value,
+        element_type: *mut MI_Type,
+        flags: *mut u32,
+    ) -> MI_Result,
+    ClearElement: Opaque,
+    ClearElementAt: Opaque,
+    GetServerName: Opaque,
+    SetServerName: Opaque,
+    GetClass: Opaque,
+}
+
+#[repr(C)]
+pub struct MI_DestinationOptions {
+    reserved1: u64,
+    reserved2: isize,
+    pub ft: *const MI_DestinationOptionsFT,
+}
+
+#[repr(C)]
+pub struct MI_DestinationOptionsFT {
+    pub Delete: unsafe extern "system" fn(options: *mut MI_DestinationOptions),
+    SetString: Opaque,
+    SetNumber: Opaque,
+    pub AddCredentials: unsafe extern "system" fn(
+        options: *mut MI_DestinationOptions,
+        option_name: *const MI_Char,
+        credentials: *const MI_UserCredentials,
+        flags: u32,
+    ) -> MI_Result,
+}
+
+/// `MI_UserCredentials`, with the `usernamePassword` member of its union
+/// (which is also the largest one).
+#[repr(C)]
+pub struct MI_UserCredentials {
+    pub authentication_type: *const MI_Char,
+    pub domain: *const MI_Char,
+    pub username: *const MI_Char,
+    pub password: *const MI_Char,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct MI_Timestamp {
+    pub year: u32,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub microseconds: u32,
+    /// The UTC offset, in minutes.
+    pub utc: i32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct MI_Interval {
+    pub days: u32,
+    pub hours: u32,
+    pub minutes: u32,
+    pub seconds: u32,
+    pub microseconds: u32,
+    padding: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union MI_DatetimeValue {
+    pub timestamp: MI_Timestamp,
+    pub interval: MI_Interval,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct MI_Datetime {
+    pub is_timestamp: u32,
+    pub u: MI_DatetimeValue,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct MI_Array {
+    pub data: *const c_void,
+    pub size: u32,
+}
+
+/// All the members of `MI_Value` start at offset 0, so a pointer to the value can be read as any of them.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union MI_Value {
+    pub datetime: MI_Datetime,
+    pub array: MI_Array,
+    pub uint64: u64,
+}
+
+#[link(name = "mi")]
+extern "C" {
+    pub fn MI_Application_InitializeV1(
+        flags: u32,
+        application_id: *const MI_Char,
+        extended_error: *mut *mut MI_Instance,
+        application: *mut MI_Application,
+    ) -> MI_Result;
+}
+
+macro_rules! zeroed_handle {
+    ($($handle:ident),*) => {
+        $(
+            impl $handle {
+                /// An uninitialized handle, to be filled by the MI API.
+                pub fn zeroed() -> Self {
+                    Self {
+                        reserved1: 0,
+                        reserved2: 0,
+                        ft: std::ptr::null(),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+zeroed_handle!(
+    MI_Application,
+    MI_Session,
+    MI_Operation,
+    MI_DestinationOptions
+);
@@ -0,0 +1,227 @@
+//! Run the same query against many computers, with bounded concurrency.
+//!
+//! Each computer is queried on a worker thread, using a connection with the same namespace, credentials
+//! and settings as the connection the [`MultiHostQuery`] was created from.
+//! A failure to connect to (or query) a computer is reported in its [`HostResult`], and does not affect the others.
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, Debug)]
+//! #[serde(rename = "Win32_OperatingSystem")]
+//! #[serde(rename_all = "PascalCase")]
+//! struct OperatingSystem {
+//!     caption: String,
+//!     version: String,
+//! }
+//!
+//! let credentials = Credentials::new("CONTOSO\\inventory", String::from("hunter2"));
+//! let con = WMIConnection::builder()
+//!     .credentials(credentials)
+//!     .build(COMLibrary::new()?)?;
+//!
+//! let results = con
+//!     .multi_host(["server01", "server02", "server03"])
+//!     .concurrency(2)
+//!     .query::<OperatingSystem>()?;
+//!
+//! for host in results {
+//!     match host.result {
+//!         Ok(os) => println!("{}: {:?}", host.host, os),
+//!         Err(err) => eprintln!("{}: {}", host.host, err),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    connection::WMIConnection,
+    prefetch::InMta,
+    query::{build_query, select_projection},
+    COMLibrary, WMIError, WMIResult,
+};
+use log::debug;
+use serde::de;
+use std::{
+    collections::VecDeque,
+    sync::{mpsc::channel, Arc, Mutex},
+    thread,
+};
+use windows::Win32::Foundation::E_FAIL;
+
+/// The default maximum number of computers queried at the same time.
+const DEFAULT_CONCURRENCY: usize = 8;
+
+/// The result of a query on a single computer.
+#[derive(Debug)]
+pub struct HostResult<T> {
+    pub host: String,
+    pub result: WMIResult<Vec<T>>,
+}
+
+/// A query to run against many computers, created using [`WMIConnection::multi_host`].
+///
+/// See the [module level documentation](crate::multi_host) for an example.
+#[derive(Debug, Clone)]
+pub struct MultiHostQuery<'a> {
+    base: &'a WMIConnection,
+    hosts: Vec<String>,
+    concurrency: usize,
+}
+
+///
+/// ### Additional multi-host methods
+///
+impl WMIConnection {
+    /// Create a [`MultiHostQuery`] for the given computers.
+    pub fn multi_host<I>(&self, hosts: I) -> MultiHostQuery<'_>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        MultiHostQuery {
+            base: self,
+            hosts: hosts.into_iter().map(Into::into).collect(),
+            concurrency: DEFAULT_CONCURRENCY,
+        }
+    }
+}
+
+impl<'a> MultiHostQuery<'a> {
+    /// The maximum number of computers queried at the same time. Defaults to 8.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Query all the instances of `T` on every computer, see [`WMIConnection::query`].
+    pub fn query<T>(&self) -> WMIResult<Vec<HostResult<T>>>
+    where
+        T: de::DeserializeOwned,
+    {
+        let query = build_query::<T>(None)?;
+
+        self.raw_query(query)
+    }
+
+    /// Run the query on every computer, see [`WMIConnection::raw_query`].
+    ///
+    /// The results are ordered like the computers, and are deserialized on the calling thread
+    /// (so `T` doesn't have to be `Send`).
+    /// The calling thread must be in the multithreaded apartment (as initialized by [`COMLibrary::new`]).
+    pub fn raw_query<T>(&self, query: impl AsRef<str>) -> WMIResult<Vec<HostResult<T>>>
+    where
+        T: de::DeserializeOwned,
+    {
+        let projection = select_projection(query.as_ref());
+        let query: Arc<str> = Arc::from(query.as_ref());
+        let queue = Arc::new(Mutex::new(
+            self.hosts
+                .iter()
+                .cloned()
+                .enumerate()
+                .collect::<VecDeque<_>>(),
+        ));
+        let (sender, receiver) = channel();
+
+        let workers = self.concurrency.min(self.hosts.len());
+
+        for _ in 0..workers {
+            let base = InMta::new(self.base.clone())?;
+            let query = query.clone();
+            let queue = queue.clone();
+            let sender = sender.clone();
+
+            thread::Builder::new()
+                .name("wmi-multi-host".to_owned())
+                .spawn(move || {
+                    // `CoInitializeEx` only fails with an `HRESULT`, which is reported for every host.
+                    let com_init = match COMLibrary::without_security() {
+                        Ok(_) => Ok(()),
+                        Err(WMIError::HResultError { hres }) => Err(hres),
+                        Err(_) => Err(E_FAIL.0),
+                    };
+
+                    let base = base.into_inner();
+
+                    loop {
+                        let next = queue.lock().unwrap().pop_front();
+
+                        let (idx, host) = match next {
+                            Some(next) => next,
+                            None => break,
+                        };
+
+                        debug!("Querying {}", host);
+
+                        let objects = match com_init {
+                            Ok(()) => base
+                                .with_server(&host)
+                                .and_then(|con| {
+                                    con.exec_query_native_wrapper(&*query)?
+                                        .collect::<WMIResult<Vec<_>>>()
+                                })
+                                .and_then(InMta::new),
+                            Err(hres) => Err(WMIError::HResultError { hres }),
+                        };
+
+                        if sender.send((idx, host, objects)).is_err() {
+                            break;
+                        }
+                    }
+                })?;
+        }
+
+        // The workers hold the remaining senders.
+        drop(sender);
+
+        let mut results: Vec<_> = receiver.into_iter().collect();
+        results.sort_by_key(|(idx, _, _)| *idx);
+
+        Ok(results
+            .into_iter()
+            .map(|(_, host, objects)| {
+                let result = objects.and_then(|objects| {
+                    objects
+                        .into_inner()
+                        .into_iter()
+                        .map(|object| {
+                            object.into_desr_with_options(
+                                &self.base.de_options,
+                                projection.as_deref(),
+                            )
+                        })
+                        .collect()
+                });
+
+                HostResult { host, result }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::fixtures::*;
+    use crate::Variant;
+    use std::collections::HashMap;
+
+    #[test]
+    fn it_queries_multiple_hosts() {
+        let wmi_con = wmi_con();
+
+        let results = wmi_con
+            .multi_host([".", "localhost", "no-such-host.invalid"])
+            .concurrency(2)
+            .raw_query::<HashMap<String, Variant>>("SELECT Caption FROM Win32_OperatingSystem")
+            .unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].host, ".");
+        assert_eq!(results[0].result.as_ref().unwrap().len(), 1);
+        assert_eq!(results[1].result.as_ref().unwrap().len(), 1);
+        assert!(results[2].result.is_err());
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{de::meta::validate_identifier, query::quote_and_escape_wql_str};
+use crate::{COMLibrary, Variant, WMIConnection, WMIError, WMIResult};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// A validated and normalized WMI namespace path, like `ROOT\CIMV2`.
+///
+/// Parsing accepts both `/` and `\` as separators (as well as a leading `\\.\` for the local computer),
+/// and normalizes the root segment to `ROOT`.
+/// Like in WMI, namespaces are compared case-insensitively.
+///
+/// A `Namespace` can be used everywhere a namespace path is expected:
+///
+/// ```edition2018
+/// # fn main() -> wmi::WMIResult<()> {
+/// # use wmi::*;
+/// let namespace = Namespace::parse("root/Microsoft/Windows/Storage")?;
+/// assert_eq!(namespace.as_str(), "ROOT\\Microsoft\\Windows\\Storage");
+///
+/// let wmi_con = WMIConnection::with_namespace_path(&namespace, COMLibrary::new()?)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Namespace {
+    path: String,
+}
+
+impl Namespace {
+    /// Parse and normalize a namespace path.
+    ///
+    /// This only validates the syntax of the path, use [`Namespace::exists`] to check it against the live namespace tree.
+    pub fn parse(path: impl AsRef<str>) -> WMIResult<Self> {
+        let original = path.as_ref();
+        let invalid =
+            |reason: &str| WMIError::InvalidNamespace(original.to_owned(), reason.to_owned());
+
+        let normalized = original.trim().replace('/', "\\");
+        let normalized = normalized.strip_prefix("\\\\.\\").unwrap_or(&normalized);
+
+        if normalized.starts_with("\\\\") {
+            return Err(invalid("A namespace path must not contain a server name"));
+        }
+
+        let mut segments = normalized.trim_matches('\\').split('\\');
+
+        match segments.next() {
+            Some(root) if root.eq_ignore_ascii_case("ROOT") => {}
+            _ => return Err(invalid("A namespace path must start with ROOT")),
+        }
+
+        let mut path = String::from("ROOT");
+
+        for segment in segments {
+            validate_identifier::<serde::de::value::Error>(segment)
+                .map_err(|e| invalid(&e.to_string()))?;
+
+            path.push('\\');
+            path.push_str(segment);
+        }
+
+        Ok(Self { path })
+    }
+
+    /// The `ROOT\CIMV2` namespace.
+    pub fn cimv2() -> Self {
+        Self {
+            path: String::from("ROOT\\CIMV2"),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.path
+    }
+
+    /// The segments of the path, starting with `ROOT`.
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        self.path.split('\\')
+    }
+
+    /// The name of the last segment of the path.
+    pub fn name(&self) -> &str {
+        self.segments().last().unwrap_or_default()
+    }
+
+    /// The parent namespace, or `None` for `ROOT`.
+    pub fn parent(&self) -> Option<Self> {
+        self.path.rsplit_once('\\').map(|(parent, _)| Self {
+            path: parent.to_owned(),
+        })
+    }
+
+    /// Create a child namespace of this one.
+    pub fn child(&self, name: &str) -> WMIResult<Self> {
+        Self::parse(format!("{}\\{}", self.path, name))
+    }
+
+    /// Check if this namespace exists on the local computer, by looking for it
+    /// in the `__NAMESPACE` instances of its parent.
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// let com_lib = COMLibrary::new()?;
+    ///
+    /// assert!(Namespace::parse("root/cimv2")?.exists(com_lib)?);
+    /// assert!(!Namespace::parse("root/nonexistent")?.exists(com_lib)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn exists(&self, com_lib: COMLibrary) -> WMIResult<bool> {
+        let parent = match self.parent() {
+            Some(parent) => parent,
+            None => return Ok(true),
+        };
+
+        let con = WMIConnection::with_namespace_path(&parent, com_lib)?;
+
+        let query = format!(
+            "SELECT Name FROM __NAMESPACE WHERE Name = {}",
+            quote_and_escape_wql_str(self.name())
+        );
+        let results: Vec<HashMap<String, Variant>> = con.raw_query(query)?;
+
+        Ok(!results.is_empty())
+    }
+
+    /// Like [`Namespace::exists`], but returns an error if the namespace does not exist.
+    pub fn validate(&self, com_lib: COMLibrary) -> WMIResult<()> {
+        if self.exists(com_lib)? {
+            Ok(())
+        } else {
+            Err(WMIError::InvalidNamespace(
+                self.path.clone(),
+                String::from("The namespace does not exist"),
+            ))
+        }
+    }
+}
+
+impl Default for Namespace {
+    fn default() -> Self {
+        Self::cimv2()
+    }
+}
+
+impl FromStr for Namespace {
+    type Err = WMIError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl AsRef<str> for Namespace {
+    fn as_ref(&self) -> &str {
+        &self.path
+    }
+}
+
+impl fmt::Display for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.path)
+    }
+}
+
+impl PartialEq for Namespace {
+    fn eq(&self, other: &Self) -> bool {
+        self.path.to_lowercase() == other.path.to_lowercase()
+    }
+}
+
+impl Eq for Namespace {}
+
+impl Hash for Namespace {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.path.to_lowercase().hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    #[test]
+    fn it_normalizes_namespaces() {
+        for path in [
+            "ROOT\\CIMV2",
+            "root/cimv2",
+            "\\\\.\\root\\CIMV2",
+            "//./Root/CIMV2/",
+            " root\\cimv2 ",
+        ] {
+            let namespace = Namespace::parse(path).unwrap();
+
+            assert_eq!(namespace, Namespace::cimv2(), "{}", path);
+            assert!(namespace.as_str().starts_with("ROOT\\"));
+        }
+
+        let namespace = Namespace::parse("root/Microsoft/Windows/Storage").unwrap();
+        assert_eq!(namespace.as_str(), "ROOT\\Microsoft\\Windows\\Storage");
+        assert_eq!(namespace.name(), "Storage");
+        assert_eq!(
+            namespace.parent().unwrap().as_str(),
+            "ROOT\\Microsoft\\Windows"
+        );
+        assert_eq!(Namespace::parse("ROOT").unwrap().parent(), None);
+    }
+
+    #[test]
+    fn it_rejects_invalid_namespaces() {
+        for path in [
+            "",
+            "CIMV2",
+            "\\\\server\\root\\cimv2",
+            "root\\\\cimv2",
+            "root\\1cimv2",
+            "root\\cim v2",
+        ] {
+            assert!(
+                matches!(
+                    Namespace::parse(path),
+                    Err(WMIError::InvalidNamespace(_, _))
+                ),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn it_validates_against_live_namespaces() {
+        let _ = wmi_con();
+        let com_lib = unsafe { COMLibrary::assume_initialized() };
+
+        assert!(Namespace::parse("root").unwrap().exists(com_lib).unwrap());
+        assert!(Namespace::parse("root/cimv2")
+            .unwrap()
+            .exists(com_lib)
+            .unwrap());
+        assert!(Namespace::parse("root/cimv2/nonexistent")
+            .unwrap()
+            .validate(com_lib)
+            .is_err());
+    }
+
+    #[test]
+    fn it_can_connect_with_namespace() {
+        let _ = wmi_con();
+        let com_lib = unsafe { COMLibrary::assume_initialized() };
+
+        let namespace = Namespace::parse("root/cimv2").unwrap();
+        let wmi_con = WMIConnection::with_namespace_path(&namespace, com_lib).unwrap();
+
+        wmi_con.ping().unwrap();
+    }
+}
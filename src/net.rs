@@ -0,0 +1,149 @@
+use crate::WMIError;
+use serde::{de, ser};
+use std::{fmt, net::IpAddr, str::FromStr};
+
+/// A wrapper type around IpAddr, which supports parsing from WMI-format strings.
+///
+/// Plain `IpAddr`, `Ipv4Addr` and `Ipv6Addr` fields can be used as well,
+/// but some providers (like `MSFT_NetIPAddress`) append a zone index to link-local IPv6 addresses (`fe80::1%12`),
+/// which `IpAddr` cannot parse. The zone index is kept in a separate field.
+///
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct WMIIpAddr {
+    pub addr: IpAddr,
+    pub zone: Option<String>,
+}
+
+impl FromStr for WMIIpAddr {
+    type Err = WMIError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, zone) = match s.split_once('%') {
+            Some((addr, zone)) => (addr, Some(zone.to_owned())),
+            None => (s, None),
+        };
+
+        let addr = addr
+            .parse()
+            .map_err(|_| WMIError::ConvertIpAddrError(s.into()))?;
+
+        Ok(Self { addr, zone })
+    }
+}
+
+impl From<WMIIpAddr> for IpAddr {
+    fn from(value: WMIIpAddr) -> Self {
+        value.addr
+    }
+}
+
+impl fmt::Display for WMIIpAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.zone {
+            Some(zone) => write!(f, "{}%{}", self.addr, zone),
+            None => write!(f, "{}", self.addr),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct IpAddrVisitor;
+
+impl<'de> de::Visitor<'de> for IpAddrVisitor {
+    type Value = WMIIpAddr;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "an IP address")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        value.parse().map_err(|err| E::custom(format!("{}", err)))
+    }
+}
+
+impl<'de> de::Deserialize<'de> for WMIIpAddr {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(IpAddrVisitor)
+    }
+}
+
+impl ser::Serialize for WMIIpAddr {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WMIIpAddr;
+    use serde_json;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn it_works() {
+        let addr: WMIIpAddr = "192.168.1.10".parse().unwrap();
+
+        assert_eq!(addr.addr, IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)));
+        assert_eq!(addr.zone, None);
+    }
+
+    #[test]
+    fn it_works_with_zone_index() {
+        let addr: WMIIpAddr = "fe80::1c2d:3e4f:5a6b:7c8d%12".parse().unwrap();
+
+        assert_eq!(
+            addr.addr,
+            "fe80::1c2d:3e4f:5a6b:7c8d".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(addr.zone.as_deref(), Some("12"));
+        assert_eq!(addr.to_string(), "fe80::1c2d:3e4f:5a6b:7c8d%12");
+    }
+
+    #[test]
+    fn it_fails_on_invalid_addresses() {
+        "not an address".parse::<WMIIpAddr>().unwrap_err();
+        "".parse::<WMIIpAddr>().unwrap_err();
+    }
+
+    #[test]
+    fn it_serializes_to_string() {
+        let addr: WMIIpAddr = "10.0.0.1".parse().unwrap();
+
+        let v = serde_json::to_string(&addr).unwrap();
+        assert_eq!(v, "\"10.0.0.1\"");
+    }
+
+    #[test]
+    fn it_desr_adapter_addresses() {
+        use crate::tests::fixtures::*;
+        use serde::Deserialize;
+
+        let wmi_con = wmi_con();
+
+        #[derive(Deserialize, Debug)]
+        struct Win32_NetworkAdapterConfiguration {
+            IPAddress: Option<Vec<IpAddr>>,
+            DefaultIPGateway: Option<Vec<WMIIpAddr>>,
+        }
+
+        let adapters: Vec<Win32_NetworkAdapterConfiguration> = wmi_con.query().unwrap();
+
+        assert!(adapters
+            .iter()
+            .flat_map(|adapter| adapter.IPAddress.iter().flatten())
+            .all(|addr| !addr.is_unspecified()));
+        assert!(adapters
+            .iter()
+            .flat_map(|adapter| adapter.DefaultIPGateway.iter().flatten())
+            .all(|gateway| !gateway.addr.is_unspecified()));
+    }
+}
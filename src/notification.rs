@@ -1,16 +1,31 @@
 use crate::{
     build_notification_query,
+    query::{build_aggregate_notification_query, GroupWithin},
     query_sink::{AsyncQueryResultStream, AsyncQueryResultStreamInner, QuerySink},
     result_enumerator::{IWbemClassWrapper, QueryResultEnumerator},
     FilterValue, WMIConnection, WMIResult,
 };
 use futures::{Stream, StreamExt};
+use serde::Deserialize;
 use std::{collections::HashMap, time::Duration};
 use windows::core::BSTR;
 use windows::Win32::System::Wmi::{
-    IWbemObjectSink, WBEM_FLAG_FORWARD_ONLY, WBEM_FLAG_RETURN_IMMEDIATELY,
+    IEnumWbemClassObject, IWbemObjectSink, WBEM_FLAG_FORWARD_ONLY, WBEM_FLAG_RETURN_IMMEDIATELY,
 };
 
+/// A group of events, as delivered by an aggregate event query (see [`GroupWithin`]).
+///
+/// `T` is the type of the grouped events, like an `__InstanceCreationEvent` struct.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename = "__AggregateEvent")]
+#[serde(rename_all = "PascalCase")]
+pub struct AggregateEvent<T> {
+    /// The number of events in the group.
+    pub number_of_events: u32,
+    /// One of the events of the group.
+    pub representative: T,
+}
+
 ///
 /// ### Additional notification query methods
 ///
@@ -22,6 +37,16 @@ impl WMIConnection {
         &self,
         query: impl AsRef<str>,
     ) -> WMIResult<QueryResultEnumerator> {
+        let enumerator = self.exec_notification_query(query)?;
+
+        Ok(QueryResultEnumerator::new(self, enumerator))
+    }
+
+    /// Run `ExecNotificationQuery`, and apply the proxy blanket of the connection to the enumerator.
+    pub(crate) fn exec_notification_query(
+        &self,
+        query: impl AsRef<str>,
+    ) -> WMIResult<IEnumWbemClassObject> {
         let query_language = BSTR::from("WQL");
         let query = BSTR::from(query.as_ref());
 
@@ -30,12 +55,16 @@ impl WMIConnection {
                 &query_language,
                 &query,
                 (WBEM_FLAG_FORWARD_ONLY | WBEM_FLAG_RETURN_IMMEDIATELY).0 as _,
-                None,
+                self.ctx(),
             )?
         };
         log::trace!("Got enumerator {:?}", enumerator);
 
-        Ok(QueryResultEnumerator::new(self, enumerator))
+        if self.needs_proxy_blanket() {
+            self.apply_proxy_blanket(&enumerator)?;
+        }
+
+        Ok(enumerator)
     }
 
     /// Execute a free-text query and deserialize the incoming events.
@@ -67,7 +96,7 @@ impl WMIConnection {
     {
         let enumerator = self.notification_native_wrapper(query)?;
         let iter = enumerator.map(|item| match item {
-            Ok(wbem_class_obj) => wbem_class_obj.into_desr(),
+            Ok(wbem_class_obj) => wbem_class_obj.into_desr_with_options(&self.de_options, None),
             Err(e) => Err(e),
         });
         Ok(iter)
@@ -167,8 +196,13 @@ impl WMIConnection {
         unsafe {
             // As p_sink's RefCount = 1 before this call,
             // p_sink won't be dropped at the end of ExecNotificationQueryAsync
-            self.svc
-                .ExecNotificationQueryAsync(&query_language, &query, 0, None, &p_sink_handle)?
+            self.svc.ExecNotificationQueryAsync(
+                &query_language,
+                &query,
+                0,
+                self.ctx(),
+                &p_sink_handle,
+            )?
         };
 
         Ok(AsyncQueryResultStream::new(
@@ -209,10 +243,11 @@ impl WMIConnection {
     where
         T: serde::de::DeserializeOwned,
     {
+        let options = self.de_options.clone();
         let stream = self
             .async_notification_native_wrapper(query)?
-            .map(|item| match item {
-                Ok(wbem_class_obj) => wbem_class_obj.into_desr(),
+            .map(move |item| match item {
+                Ok(wbem_class_obj) => wbem_class_obj.into_desr_with_options(&options, None),
                 Err(e) => Err(e),
             });
         Ok(stream)
@@ -307,11 +342,73 @@ impl WMIConnection {
         let query_text = build_notification_query::<T>(Some(filters), within)?;
         self.async_raw_notification(query_text)
     }
+
+    /// Subscribe to the T event, while filtering according to `filters` and grouping according to `group`.
+    /// Returns an iterator of WMIResult\<AggregateEvent\<T\>\>, with an item for each group.
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use std::{collections::HashMap, time::Duration};
+    /// # use wmi::*;
+    /// # let con = WMIConnection::new(COMLibrary::new()?)?;
+    /// use serde::Deserialize;
+    /// use wmi::notification::AggregateEvent;
+    ///
+    /// #[derive(Deserialize, Debug)]
+    /// struct __InstanceCreationEvent {
+    ///     TargetInstance: Win32_Process,
+    /// }
+    ///
+    /// #[derive(Deserialize, Debug)]
+    /// struct Win32_Process {
+    ///     Name: String,
+    /// }
+    ///
+    /// let mut filters = HashMap::new();
+    /// filters.insert("TargetInstance".to_owned(), FilterValue::is_a::<Win32_Process>()?);
+    ///
+    /// // At most one event every 10 seconds for each process name.
+    /// let group = GroupWithin::new(Duration::from_secs(10)).by("TargetInstance.Name");
+    ///
+    /// let iterator = con.aggregate_notification::<__InstanceCreationEvent>(
+    ///     &filters,
+    ///     Some(Duration::from_secs(1)),
+    ///     &group,
+    /// )?;
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn aggregate_notification<'a, T>(
+        &'a self,
+        filters: &HashMap<String, FilterValue>,
+        within: Option<Duration>,
+        group: &GroupWithin,
+    ) -> WMIResult<impl Iterator<Item = WMIResult<AggregateEvent<T>>> + 'a>
+    where
+        T: serde::de::DeserializeOwned + 'a,
+    {
+        let query_text = build_aggregate_notification_query::<T>(Some(filters), within, group)?;
+        self.raw_notification(query_text)
+    }
+
+    /// Like [`aggregate_notification`](WMIConnection::aggregate_notification), but returns a stream.
+    pub fn async_aggregate_notification<T>(
+        &self,
+        filters: &HashMap<String, FilterValue>,
+        within: Option<Duration>,
+        group: &GroupWithin,
+    ) -> WMIResult<impl Stream<Item = WMIResult<AggregateEvent<T>>>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let query_text = build_aggregate_notification_query::<T>(Some(filters), within, group)?;
+        self.async_raw_notification(query_text)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{tests::fixtures::*, FilterValue, WMIError};
+    use crate::{query::GroupWithin, tests::fixtures::*, FilterValue, WMIError};
     use futures::StreamExt;
     use serde::Deserialize;
     use std::{collections::HashMap, time::Duration};
@@ -590,4 +687,36 @@ mod tests {
             time::OffsetDateTime::now_utc().year()
         )
     }
+
+    #[test]
+    fn it_provides_aggregate_notification_results() {
+        let wmi_con = wmi_con();
+
+        let group = GroupWithin::new(Duration::from_secs(2));
+
+        let mut iterator = wmi_con
+            .aggregate_notification::<InstanceModification>(&notification_filters(), None, &group)
+            .unwrap();
+
+        let aggregate = iterator.next().unwrap().unwrap();
+
+        assert!(aggregate.number_of_events >= 1);
+        assert!(aggregate.representative.target_instance.year > 2000);
+    }
+
+    #[test]
+    fn it_filters_aggregate_notification_results_with_having() {
+        let wmi_con = wmi_con();
+
+        // `Win32_LocalTime` is modified every second, so groups of 3 seconds have at least 2 events.
+        let group = GroupWithin::new(Duration::from_secs(3)).having_at_least(2);
+
+        let mut iterator = wmi_con
+            .aggregate_notification::<InstanceModification>(&notification_filters(), None, &group)
+            .unwrap();
+
+        let aggregate = iterator.next().unwrap().unwrap();
+
+        assert!(aggregate.number_of_events >= 2);
+    }
 }
@@ -0,0 +1,569 @@
+//! Client-side `ORDER BY`, `TOP`/`OFFSET` and `DISTINCT`, which WQL does not support.
+//!
+//! [`QueryOptions`] sorts the results of a query by one or more properties, and keeps only a page of the results.
+//! It can also skip duplicated objects, using the key properties of their class.
+//! The results are sorted using the property values returned by WMI (before deserialization), so the properties
+//! do not need to be fields of the deserialized type:
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use serde::Deserialize;
+//! use wmi::order::{OrderBy, QueryOptions};
+//!
+//! #[derive(Deserialize, Debug)]
+//! #[serde(rename = "Win32_Process")]
+//! #[serde(rename_all = "PascalCase")]
+//! struct Process {
+//!     name: String,
+//!     process_id: u32,
+//! }
+//!
+//! // The five processes using the most memory.
+//! let options = QueryOptions::new()
+//!     .order_by(OrderBy::desc("WorkingSetSize"))
+//!     .order_by(OrderBy::asc("Name"))
+//!     .top(5);
+//!
+//! for process in con.query_with::<Process>(&options)? {
+//!     println!("{} ({})", process.name, process.process_id);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Values are compared like this:
+//! - Numbers are compared numerically, including 64-bit integers (which WMI returns as strings).
+//! - Strings are compared case-insensitively (like WQL comparisons), which also orders DMTF datetimes.
+//! - `NULL` values come after all other values, for both ascending and descending orders.
+use crate::{
+    connection::WMIConnection,
+    query::{build_query, find_from_keyword, query_class, select_projection},
+    result_enumerator::IWbemClassWrapper,
+    FilterValue, Variant, WMIResult,
+};
+use serde::de::DeserializeOwned;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SortOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+/// A property to sort the results by.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OrderBy {
+    pub property: String,
+    pub order: SortOrder,
+}
+
+impl OrderBy {
+    pub fn asc(property: impl Into<String>) -> Self {
+        Self {
+            property: property.into(),
+            order: SortOrder::Ascending,
+        }
+    }
+
+    pub fn desc(property: impl Into<String>) -> Self {
+        Self {
+            property: property.into(),
+            order: SortOrder::Descending,
+        }
+    }
+}
+
+/// How to sort and limit the results of a query, see the [module level documentation](crate::order).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct QueryOptions {
+    order_by: Vec<OrderBy>,
+    top: Option<usize>,
+    skip: usize,
+    dedup_by_keys: bool,
+}
+
+impl QueryOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sort by a property. Results which are equal are sorted by the next properties.
+    pub fn order_by(mut self, order_by: OrderBy) -> Self {
+        self.order_by.push(order_by);
+        self
+    }
+
+    /// Only return the first `n` results (after sorting and skipping), like [`Iterator::take`].
+    ///
+    /// Without sorting, the query is stopped (and its enumerator released) as soon as `n` results were read.
+    pub fn top(mut self, n: usize) -> Self {
+        self.top = Some(n);
+        self
+    }
+
+    /// Skip the first `n` results (after sorting), like [`Iterator::skip`].
+    ///
+    /// Combined with [`top`](QueryOptions::top), this emulates `LIMIT` and `OFFSET` for paging.
+    /// Skipped results are not deserialized.
+    pub fn skip(mut self, n: usize) -> Self {
+        self.skip = n;
+        self
+    }
+
+    /// Skip the results which have the same class and key property values as a previous result
+    /// (some providers return the same object more than once).
+    ///
+    /// The key properties are read from the schema, and are added to the query if it selects specific properties.
+    pub fn dedup_by_keys(mut self, dedup_by_keys: bool) -> Self {
+        self.dedup_by_keys = dedup_by_keys;
+        self
+    }
+}
+
+///
+/// ### Additional sorting methods
+///
+impl WMIConnection {
+    /// Query all the objects of type T, sorted and limited according to `options`.
+    ///
+    /// See the [module level documentation](crate::order) for an example.
+    pub fn query_with<T>(&self, options: &QueryOptions) -> WMIResult<Vec<T>>
+    where
+        T: DeserializeOwned,
+    {
+        let query_text = build_query::<T>(None)?;
+
+        self.raw_query_with(query_text, options)
+    }
+
+    /// Like [`filtered_query`](WMIConnection::filtered_query), with the results sorted and limited according to `options`.
+    pub fn filtered_query_with<T>(
+        &self,
+        filters: &HashMap<String, FilterValue>,
+        options: &QueryOptions,
+    ) -> WMIResult<Vec<T>>
+    where
+        T: DeserializeOwned,
+    {
+        let query_text = build_query::<T>(Some(filters))?;
+
+        self.raw_query_with(query_text, options)
+    }
+
+    /// Like [`raw_query`](WMIConnection::raw_query), with the results sorted and limited according to `options`.
+    ///
+    /// If the query selects specific properties, the properties to sort by are added to the query.
+    pub fn raw_query_with<T>(
+        &self,
+        query: impl AsRef<str>,
+        options: &QueryOptions,
+    ) -> WMIResult<Vec<T>>
+    where
+        T: DeserializeOwned,
+    {
+        let query = query.as_ref();
+        let top = options.top.unwrap_or(usize::MAX);
+        let projection = select_projection(query);
+
+        let key_properties = match (options.dedup_by_keys, query_class(query)) {
+            (true, Some(class)) => self.key_properties(class)?,
+            _ => vec![],
+        };
+
+        let mut properties: Vec<&str> = vec![];
+
+        for property in options
+            .order_by
+            .iter()
+            .map(|order_by| order_by.property.as_str())
+            .chain(key_properties.iter().map(String::as_str))
+        {
+            if !properties.iter().any(|p| p.eq_ignore_ascii_case(property)) {
+                properties.push(property);
+            }
+        }
+
+        let query = with_properties(query, projection.as_deref(), &properties);
+
+        // Without sorting, the rows are skipped (and limited) while reading them.
+        let is_sorted = !options.order_by.is_empty();
+        let mut to_skip = if is_sorted { 0 } else { options.skip };
+
+        if !is_sorted && top == 0 {
+            return Ok(vec![]);
+        }
+
+        let mut dedup = KeyDedup::default();
+        let mut rows = vec![];
+
+        for item in self.exec_query_native_wrapper(query)? {
+            let obj = item?;
+
+            if options.dedup_by_keys && !dedup.insert(self, &obj)? {
+                continue;
+            }
+
+            if !is_sorted {
+                if to_skip > 0 {
+                    to_skip -= 1;
+                    continue;
+                }
+
+                rows.push((vec![], obj));
+
+                // Stop calling `Next`: breaking out of the loop releases the enumerator.
+                if rows.len() >= top {
+                    break;
+                }
+
+                continue;
+            }
+
+            let keys = options
+                .order_by
+                .iter()
+                .map(|order_by| obj.get_property(&order_by.property))
+                .collect::<WMIResult<Vec<_>>>()?;
+
+            rows.push((keys, obj));
+        }
+
+        // A stable sort, so results which are equal keep the order returned by WMI.
+        rows.sort_by(|(a, _), (b, _)| compare_keys(a, b, &options.order_by));
+
+        let skip = if is_sorted { options.skip } else { 0 };
+
+        rows.into_iter()
+            .skip(skip)
+            .take(top)
+            .map(|(_, obj)| obj.into_desr_with_options(&self.de_options, projection.as_deref()))
+            .collect()
+    }
+}
+
+/// The key values of the objects which were already returned, for [`QueryOptions::dedup_by_keys`].
+#[derive(Default)]
+struct KeyDedup {
+    /// The key properties of each class, read from the schema.
+    key_properties: HashMap<String, Vec<String>>,
+    seen: HashSet<(String, Vec<String>)>,
+}
+
+impl KeyDedup {
+    /// Returns `false` if an object with the same class and key values was already returned.
+    ///
+    /// Objects of classes without key properties are never duplicates.
+    fn insert(&mut self, con: &WMIConnection, obj: &IWbemClassWrapper) -> WMIResult<bool> {
+        let class = obj.class()?.to_lowercase();
+
+        if !self.key_properties.contains_key(&class) {
+            let key_properties = con.key_properties(&class)?;
+            self.key_properties.insert(class.clone(), key_properties);
+        }
+
+        let key_properties = &self.key_properties[&class];
+
+        if key_properties.is_empty() {
+            return Ok(true);
+        }
+
+        let values = key_properties
+            .iter()
+            .map(|property| Ok(key_value(obj.get_property(property)?)))
+            .collect::<WMIResult<Vec<_>>>()?;
+
+        Ok(self.seen.insert((class, values)))
+    }
+}
+
+/// A comparable key value. Like in object paths, strings are compared case-insensitively.
+fn key_value(value: Variant) -> String {
+    match value {
+        Variant::String(s) => s.to_lowercase(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Add the properties which are not selected by the query to its projection.
+fn with_properties(query: &str, projection: Option<&[String]>, properties: &[&str]) -> String {
+    let projection = match projection {
+        Some(projection) => projection,
+        // `SELECT *` (or a query which is not a `SELECT`).
+        None => return query.to_owned(),
+    };
+
+    let missing: Vec<&str> = properties
+        .iter()
+        .copied()
+        .filter(|property| {
+            !projection
+                .iter()
+                .any(|selected| selected.eq_ignore_ascii_case(property))
+        })
+        .collect();
+
+    if missing.is_empty() {
+        return query.to_owned();
+    }
+
+    // `select_projection` found the `FROM` keyword after the `SELECT`, so this can't fail.
+    let from = find_from_keyword(query).unwrap_or(query.len());
+    // Insert the properties before the whitespace which precedes `FROM`.
+    let from = query[..from].trim_end().len();
+
+    format!("{},{}{}", &query[..from], missing.join(","), &query[from..])
+}
+
+fn compare_keys(a: &[Variant], b: &[Variant], order_by: &[OrderBy]) -> Ordering {
+    a.iter()
+        .zip(b)
+        .zip(order_by)
+        .map(|((a, b), order_by)| compare_values(a, b, order_by.order))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+fn compare_values(a: &Variant, b: &Variant, order: SortOrder) -> Ordering {
+    let is_null = |value: &Variant| matches!(value, Variant::Null | Variant::Empty);
+
+    // Nulls are last, regardless of the order.
+    match (is_null(a), is_null(b)) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Greater,
+        (false, true) => return Ordering::Less,
+        (false, false) => {}
+    }
+
+    let ordering = compare_non_null(a, b);
+
+    match order {
+        SortOrder::Ascending => ordering,
+        SortOrder::Descending => ordering.reverse(),
+    }
+}
+
+fn compare_non_null(a: &Variant, b: &Variant) -> Ordering {
+    if let (Some(a), Some(b)) = (as_integer(a), as_integer(b)) {
+        return a.cmp(&b);
+    }
+
+    if let (Some(a), Some(b)) = (as_float(a), as_float(b)) {
+        return a.total_cmp(&b);
+    }
+
+    match (a, b) {
+        (Variant::String(a), Variant::String(b)) => a
+            .to_lowercase()
+            .cmp(&b.to_lowercase())
+            .then_with(|| a.cmp(b)),
+        (Variant::Bool(a), Variant::Bool(b)) => a.cmp(b),
+        _ => Ordering::Equal,
+    }
+}
+
+/// The value of an integer, including 64-bit integers (which WMI returns as strings).
+pub(crate) fn as_integer(value: &Variant) -> Option<i128> {
+    match *value {
+        Variant::I1(n) => Some(n.into()),
+        Variant::I2(n) => Some(n.into()),
+        Variant::I4(n) => Some(n.into()),
+        Variant::I8(n) => Some(n.into()),
+        Variant::UI1(n) => Some(n.into()),
+        Variant::UI2(n) => Some(n.into()),
+        Variant::UI4(n) => Some(n.into()),
+        Variant::UI8(n) => Some(n.into()),
+        // 64-bit integers are returned as strings.
+        Variant::String(ref s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+pub(crate) fn as_float(value: &Variant) -> Option<f64> {
+    match *value {
+        Variant::R4(n) => Some(n.into()),
+        Variant::R8(n) => Some(n),
+        _ => as_integer(value).map(|n| n as f64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use serde::Deserialize;
+
+    #[test]
+    fn it_compares_values() {
+        let asc = SortOrder::Ascending;
+        let desc = SortOrder::Descending;
+
+        assert_eq!(
+            compare_values(&Variant::UI4(2), &Variant::UI4(10), asc),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_values(&Variant::UI4(2), &Variant::UI4(10), desc),
+            Ordering::Greater
+        );
+        // 64-bit integers are strings, but compared as numbers.
+        assert_eq!(
+            compare_values(
+                &Variant::String("9".to_owned()),
+                &Variant::String("10".to_owned()),
+                asc
+            ),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_values(
+                &Variant::String("apple".to_owned()),
+                &Variant::String("Banana".to_owned()),
+                asc
+            ),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_values(&Variant::R8(0.5), &Variant::I4(1), asc),
+            Ordering::Less
+        );
+
+        // Nulls are always last.
+        assert_eq!(
+            compare_values(&Variant::Null, &Variant::UI4(1), asc),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_values(&Variant::Null, &Variant::UI4(1), desc),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn it_adds_sort_properties_to_queries() {
+        let query = "SELECT Name FROM Win32_Process WHERE Name = 'a'";
+        let projection = select_projection(query);
+
+        assert_eq!(
+            with_properties(query, projection.as_deref(), &["name", "WorkingSetSize"]),
+            "SELECT Name,WorkingSetSize FROM Win32_Process WHERE Name = 'a'"
+        );
+        assert_eq!(
+            with_properties(query, projection.as_deref(), &["Name"]),
+            query
+        );
+        assert_eq!(
+            with_properties("SELECT * FROM Win32_Process", None, &["Name"]),
+            "SELECT * FROM Win32_Process"
+        );
+
+        let query = "SELECT Name\nFROM Win32_Process";
+        assert_eq!(
+            with_properties(query, select_projection(query).as_deref(), &["ProcessId"]),
+            "SELECT Name,ProcessId\nFROM Win32_Process"
+        );
+    }
+
+    #[test]
+    fn it_dedups_by_keys() {
+        let wmi_con = wmi_con();
+
+        let mut dedup = KeyDedup::default();
+
+        let service = wmi_con
+            .get_raw_by_path(r#"Win32_Service.Name="Winmgmt""#)
+            .unwrap();
+        let same_service = wmi_con
+            .get_raw_by_path(r#"Win32_Service.Name="winmgmt""#)
+            .unwrap();
+        let other_service = wmi_con
+            .get_raw_by_path(r#"Win32_Service.Name="EventLog""#)
+            .unwrap();
+
+        assert!(dedup.insert(&wmi_con, &service).unwrap());
+        assert!(!dedup.insert(&wmi_con, &same_service).unwrap());
+        assert!(dedup.insert(&wmi_con, &other_service).unwrap());
+
+        let options = QueryOptions::new().dedup_by_keys(true);
+
+        // The key (`Name`) is added to the query.
+        let services: Vec<HashMap<String, Variant>> = wmi_con
+            .raw_query_with("SELECT Caption FROM Win32_Service", &options)
+            .unwrap();
+        let all: Vec<HashMap<String, Variant>> = wmi_con
+            .raw_query("SELECT Caption FROM Win32_Service")
+            .unwrap();
+        assert_eq!(services.len(), all.len());
+    }
+
+    #[test]
+    fn it_sorts_and_limits_query_results() {
+        #[derive(Deserialize, Debug)]
+        #[serde(rename = "Win32_Process")]
+        #[serde(rename_all = "PascalCase")]
+        struct Process {
+            process_id: u32,
+        }
+
+        let wmi_con = wmi_con();
+
+        let options = QueryOptions::new().order_by(OrderBy::desc("ProcessId"));
+        let processes: Vec<Process> = wmi_con.query_with(&options).unwrap();
+
+        assert!(processes.len() > 1);
+        assert!(processes
+            .windows(2)
+            .all(|pair| pair[0].process_id >= pair[1].process_id));
+
+        let top: Vec<Process> = wmi_con.query_with(&options.clone().top(3)).unwrap();
+        assert_eq!(top.len(), 3);
+        assert_eq!(top[0].process_id, processes[0].process_id);
+
+        // Sorting by a property which is not a field of the struct.
+        let by_name: Vec<Process> = wmi_con
+            .query_with(&QueryOptions::new().order_by(OrderBy::asc("Name")).top(5))
+            .unwrap();
+        assert_eq!(by_name.len(), 5);
+
+        let unsorted: Vec<Process> = wmi_con.query_with(&QueryOptions::new().top(2)).unwrap();
+        assert_eq!(unsorted.len(), 2);
+    }
+
+    #[test]
+    fn it_skips_query_results() {
+        #[derive(Deserialize, Debug)]
+        #[serde(rename = "Win32_Process")]
+        #[serde(rename_all = "PascalCase")]
+        struct Process {
+            process_id: u32,
+        }
+
+        let wmi_con = wmi_con();
+
+        let sorted = QueryOptions::new().order_by(OrderBy::asc("ProcessId"));
+        let processes: Vec<Process> = wmi_con.query_with(&sorted).unwrap();
+
+        let page: Vec<Process> = wmi_con.query_with(&sorted.clone().skip(2).top(3)).unwrap();
+        assert_eq!(page.len(), 3);
+        assert_eq!(page[0].process_id, processes[2].process_id);
+        assert_eq!(page[2].process_id, processes[4].process_id);
+
+        let past_the_end: Vec<Process> = wmi_con
+            .query_with(&sorted.skip(processes.len() + 10))
+            .unwrap();
+        assert!(past_the_end.is_empty());
+
+        let unsorted: Vec<Process> = wmi_con
+            .query_with(&QueryOptions::new().skip(1).top(2))
+            .unwrap();
+        assert_eq!(unsorted.len(), 2);
+
+        let none: Vec<Process> = wmi_con.query_with(&QueryOptions::new().top(0)).unwrap();
+        assert!(none.is_empty());
+    }
+}
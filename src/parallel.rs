@@ -0,0 +1,132 @@
+//! Deserialize the results of queries in parallel, using `rayon`.
+//!
+//! Objects returned by WMI are local copies of the data (even for remote connections), and are free-threaded.
+//! Deserializing wide classes is CPU bound, so splitting it between threads helps when queries return many objects.
+//!
+//! This is most effective together with [`WMIConnection::with_prefetch`], in which case the next batch is read
+//! while the current one is being deserialized.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, Debug)]
+//! struct Win32_Process {
+//!     Name: String,
+//!     ProcessId: u32,
+//!     CommandLine: Option<String>,
+//! }
+//!
+//! let procs: Vec<Win32_Process> = con.with_prefetch(Some(256)).par_query()?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    connection::WMIConnection,
+    prefetch::InMta,
+    query::{build_query, select_projection},
+    result_enumerator::IWbemClassWrapper,
+    WMIResult,
+};
+use rayon::prelude::*;
+use serde::de;
+
+/// The number of objects deserialized together, when the connection does not use prefetching.
+const DEFAULT_BATCH_SIZE: usize = 256;
+
+///
+/// ### Additional parallel query methods
+///
+impl WMIConnection {
+    /// Like [`raw_query`](Self::raw_query), but the results are deserialized in parallel.
+    ///
+    /// The results are returned in the order in which they were received.
+    /// The calling thread must be in the multithreaded apartment (as initialized by [`COMLibrary::new`](crate::COMLibrary::new)).
+    pub fn par_raw_query<T>(&self, query: impl AsRef<str>) -> WMIResult<Vec<T>>
+    where
+        T: de::DeserializeOwned + Send,
+    {
+        let projection = select_projection(query.as_ref());
+        let options = &self.de_options;
+        let batch_size = self
+            .prefetch
+            .map_or(DEFAULT_BATCH_SIZE, |batch_size| batch_size as usize);
+
+        let mut enumerator = self.exec_query_native_wrapper(query)?;
+        let mut results = vec![];
+
+        loop {
+            let batch = enumerator
+                .by_ref()
+                .take(batch_size)
+                .map(|item| item.and_then(InMta::new))
+                .collect::<WMIResult<Vec<InMta<IWbemClassWrapper>>>>()?;
+
+            if batch.is_empty() {
+                break;
+            }
+
+            let batch = batch
+                .into_par_iter()
+                .map(|wbem_class_obj| {
+                    wbem_class_obj
+                        .into_inner()
+                        .into_desr_with_options(options, projection.as_deref())
+                })
+                .collect::<WMIResult<Vec<T>>>()?;
+
+            results.extend(batch);
+        }
+
+        Ok(results)
+    }
+
+    /// Like [`query`](Self::query), but the results are deserialized in parallel.
+    pub fn par_query<T>(&self) -> WMIResult<Vec<T>>
+    where
+        T: de::DeserializeOwned + Send,
+    {
+        let query_text = build_query::<T>(None)?;
+
+        self.par_raw_query(query_text)
+    }
+}
+
+#[allow(non_snake_case)]
+#[allow(non_camel_case_types)]
+#[cfg(test)]
+mod tests {
+    use crate::tests::fixtures::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Win32_Process {
+        Name: String,
+        ProcessId: u32,
+    }
+
+    #[test]
+    fn it_deserializes_in_parallel() {
+        let wmi_con = wmi_con();
+
+        let par_procs: Vec<Win32_Process> = wmi_con.par_query().unwrap();
+        assert!(par_procs
+            .iter()
+            .any(|proc| proc.ProcessId == std::process::id()));
+
+        let par_procs: Vec<Win32_Process> = wmi_con.with_prefetch(Some(4)).par_query().unwrap();
+        assert!(par_procs
+            .iter()
+            .any(|proc| proc.ProcessId == std::process::id()));
+    }
+
+    #[test]
+    fn it_fails_gracefully_in_parallel() {
+        let wmi_con = wmi_con();
+
+        let result = wmi_con.par_raw_query::<(String, u32)>("SELECT * FROM Win32_Process");
+        assert!(result.is_err());
+    }
+}
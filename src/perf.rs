@@ -0,0 +1,330 @@
+//! Performance counters, using a WMI refresher (`IWbemRefresher`).
+//!
+//! A [`Refresher`] keeps the instances of a performance class (like `Win32_PerfFormattedData_PerfOS_Processor`)
+//! up to date, which is much cheaper than running the same query in a loop.
+//! The formatted ("cooked") counters are computed from the two last refreshes,
+//! so the first refresh only returns zeros.
+//!
+//! The most common use is [`WMIConnection::processor_load_stream`], which samples
+//! the utilization of each logical processor on a background thread:
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use futures::StreamExt;
+//! use std::time::Duration;
+//!
+//! let stream = con.processor_load_stream(Duration::from_millis(500))?;
+//!
+//! for sample in futures::executor::block_on_stream(stream.take(2)) {
+//!     let sample = sample?;
+//!     println!("Total: {}%, per core: {:?}", sample.total, sample.cores);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    connection::{create_refresher, WMIConnection},
+    prefetch::InMta,
+    result_enumerator::IWbemClassWrapper,
+    COMLibrary, WMIError, WMIResult,
+};
+use futures::{channel::mpsc, executor::block_on, SinkExt, Stream};
+use log::debug;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{thread, time::Duration};
+use windows::core::{ComInterface, HSTRING, PCWSTR};
+use windows::Win32::System::Wmi::{
+    IWbemClassObject, IWbemConfigureRefresher, IWbemHiPerfEnum, IWbemObjectAccess, IWbemRefresher,
+    WBEM_E_BUFFER_TOO_SMALL,
+};
+
+/// A WMI refresher, created using [`WMIConnection::refresher`].
+///
+/// Classes are added using [`Refresher::add_enum`], and all of them are updated by [`Refresher::refresh`].
+pub struct Refresher<'a> {
+    con: &'a WMIConnection,
+    refresher: IWbemRefresher,
+    config: IWbemConfigureRefresher,
+}
+
+/// The instances of a class added to a [`Refresher`].
+pub struct RefresherEnum {
+    inner: IWbemHiPerfEnum,
+}
+
+///
+/// ### Additional performance counter methods
+///
+impl WMIConnection {
+    /// Create a refresher for the namespace of this connection.
+    pub fn refresher(&self) -> WMIResult<Refresher<'_>> {
+        let refresher = create_refresher()?;
+        let config = refresher.cast::<IWbemConfigureRefresher>()?;
+
+        Ok(Refresher {
+            con: self,
+            refresher,
+            config,
+        })
+    }
+
+    /// Sample the utilization of each logical processor every `interval`, on a background thread.
+    ///
+    /// Sampling stops when the stream is dropped.
+    /// See the [module level documentation](crate::perf) for an example.
+    pub fn processor_load_stream(
+        &self,
+        interval: Duration,
+    ) -> WMIResult<impl Stream<Item = WMIResult<ProcessorLoad>>> {
+        // A single sample is kept ahead of the consumer.
+        let (sender, samples) = mpsc::channel(0);
+        let con = InMta::new(self.clone())?;
+
+        thread::Builder::new()
+            .name("wmi-processor-load".to_owned())
+            .spawn(move || {
+                let mut sender = sender;
+
+                if let Err(e) = COMLibrary::without_security() {
+                    let _ = block_on(sender.send(Err(e)));
+                    return;
+                }
+
+                let con = con.into_inner();
+
+                let sampler = match ProcessorSampler::new(&con) {
+                    Ok(sampler) => sampler,
+                    Err(e) => {
+                        let _ = block_on(sender.send(Err(e)));
+                        return;
+                    }
+                };
+
+                loop {
+                    thread::sleep(interval);
+
+                    let sample = sampler.sample();
+                    let failed = sample.is_err();
+
+                    if block_on(sender.send(sample)).is_err() || failed {
+                        break;
+                    }
+                }
+
+                debug!("Processor load stream dropped, releasing refresher");
+            })?;
+
+        Ok(samples)
+    }
+}
+
+impl<'a> Refresher<'a> {
+    /// Add all the instances of `class` to the refresher.
+    ///
+    /// The returned enum is empty until the next call to [`Refresher::refresh`].
+    pub fn add_enum(&self, class: &str) -> WMIResult<RefresherEnum> {
+        let class = HSTRING::from(class);
+        let mut inner = None;
+        let mut id = 0;
+
+        unsafe {
+            self.config.AddEnum(
+                &self.con.svc,
+                PCWSTR::from_raw(class.as_ptr()),
+                0,
+                self.con.ctx(),
+                &mut inner,
+                &mut id,
+            )?;
+        }
+
+        Ok(RefresherEnum {
+            inner: inner.ok_or(WMIError::NullPointerResult)?,
+        })
+    }
+
+    /// Update all the enums added to this refresher.
+    pub fn refresh(&self) -> WMIResult<()> {
+        unsafe { self.refresher.Refresh(0)? };
+
+        Ok(())
+    }
+}
+
+impl RefresherEnum {
+    /// The instances, as of the last refresh.
+    pub fn objects(&self) -> WMIResult<Vec<IWbemClassWrapper>> {
+        let mut objs: Vec<Option<IWbemObjectAccess>> = vec![None; 64];
+
+        loop {
+            let mut returned = 0;
+
+            match unsafe { self.inner.GetObjects(0, &mut objs, &mut returned) } {
+                Ok(()) => {
+                    objs.truncate(returned as usize);
+                    break;
+                }
+                // `returned` is the number of objects needed.
+                Err(e) if e.code().0 == WBEM_E_BUFFER_TOO_SMALL.0 => {
+                    objs = vec![None; returned as usize];
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        objs.into_iter()
+            .map(|obj| {
+                let obj = obj.ok_or(WMIError::NullPointerResult)?;
+
+                Ok(IWbemClassWrapper::new(obj.cast::<IWbemClassObject>()?))
+            })
+            .collect()
+    }
+
+    /// The instances, as of the last refresh, deserialized into `T`.
+    pub fn objects_desr<T>(&self) -> WMIResult<Vec<T>>
+    where
+        T: DeserializeOwned,
+    {
+        self.objects()?
+            .into_iter()
+            .map(IWbemClassWrapper::into_desr)
+            .collect()
+    }
+}
+
+/// The utilization of the processors, as returned by [`WMIConnection::processor_load_stream`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessorLoad {
+    /// The utilization of all the processors, in percent.
+    pub total: u64,
+    /// The utilization of each logical processor, in percent, ordered by processor number.
+    pub cores: Vec<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename = "Win32_PerfFormattedData_PerfOS_Processor")]
+#[serde(rename_all = "PascalCase")]
+struct ProcessorCounter {
+    /// The processor number, or `_Total`.
+    name: String,
+    percent_processor_time: u64,
+}
+
+impl ProcessorLoad {
+    fn from_counters(counters: Vec<ProcessorCounter>) -> Self {
+        let mut total = 0;
+        let mut cores = Vec::with_capacity(counters.len());
+
+        for counter in counters {
+            match counter.name.parse::<u32>() {
+                Ok(number) => cores.push((number, counter.percent_processor_time)),
+                Err(_) if counter.name == "_Total" => total = counter.percent_processor_time,
+                Err(_) => {}
+            }
+        }
+
+        cores.sort_unstable_by_key(|(number, _)| *number);
+
+        ProcessorLoad {
+            total,
+            cores: cores.into_iter().map(|(_, percent)| percent).collect(),
+        }
+    }
+}
+
+/// Keeps a refresher of the processor counters, which must live on the sampling thread.
+struct ProcessorSampler<'a> {
+    refresher: Refresher<'a>,
+    processors: RefresherEnum,
+}
+
+impl<'a> ProcessorSampler<'a> {
+    fn new(con: &'a WMIConnection) -> WMIResult<Self> {
+        let refresher = con.refresher()?;
+        let processors = refresher.add_enum("Win32_PerfFormattedData_PerfOS_Processor")?;
+
+        // The first refresh only sets the baseline of the counters.
+        refresher.refresh()?;
+
+        Ok(Self {
+            refresher,
+            processors,
+        })
+    }
+
+    fn sample(&self) -> WMIResult<ProcessorLoad> {
+        self.refresher.refresh()?;
+
+        Ok(ProcessorLoad::from_counters(
+            self.processors.objects_desr()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use futures::StreamExt;
+
+    fn counter(name: &str, percent_processor_time: u64) -> ProcessorCounter {
+        ProcessorCounter {
+            name: name.to_owned(),
+            percent_processor_time,
+        }
+    }
+
+    #[test]
+    fn it_orders_processor_counters() {
+        let load = ProcessorLoad::from_counters(vec![
+            counter("_Total", 30),
+            counter("10", 5),
+            counter("2", 40),
+            counter("0", 75),
+        ]);
+
+        assert_eq!(load.total, 30);
+        assert_eq!(load.cores, vec![75, 40, 5]);
+    }
+
+    #[test]
+    fn it_refreshes_processor_counters() {
+        let wmi_con = wmi_con();
+
+        let refresher = wmi_con.refresher().unwrap();
+        let processors = refresher
+            .add_enum("Win32_PerfFormattedData_PerfOS_Processor")
+            .unwrap();
+
+        refresher.refresh().unwrap();
+        refresher.refresh().unwrap();
+
+        let load = ProcessorLoad::from_counters(processors.objects_desr().unwrap());
+
+        assert!(!load.cores.is_empty());
+        assert!(load.cores.iter().all(|percent| *percent <= 100));
+    }
+
+    #[test]
+    fn it_streams_processor_load() {
+        let wmi_con = wmi_con();
+
+        let stream = wmi_con
+            .processor_load_stream(Duration::from_millis(100))
+            .unwrap();
+
+        let samples: Vec<_> = block_on(stream.take(3).collect());
+
+        assert_eq!(samples.len(), 3);
+
+        for sample in samples {
+            let sample = sample.unwrap();
+
+            assert!(!sample.cores.is_empty());
+            assert!(sample.total <= 100);
+        }
+    }
+}
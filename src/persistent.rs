@@ -0,0 +1,326 @@
+//! Event subscriptions which survive restarts of the WMI service.
+//!
+//! An event iterator ends (or fails) when its subscription is cancelled, for example when the `Winmgmt` service
+//! is restarted. A [`PersistentNotification`] instead reconnects and resubscribes, waiting longer between each
+//! failed attempt (see [`ResubscribeOptions`]). Events which happened while the subscription was lost are not delivered,
+//! so each gap is reported to the [`on_gap`](PersistentNotification::on_gap) callback (to re-read the current state, for example):
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # use std::collections::HashMap;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use wmi::persistent::ResubscribeOptions;
+//!
+//! let events = con
+//!     .persistent_notification::<HashMap<String, Variant>>(
+//!         "SELECT * FROM __InstanceCreationEvent WITHIN 1 WHERE TargetInstance ISA 'Win32_Process'",
+//!         ResubscribeOptions::new(),
+//!     )?
+//!     .on_gap(|gap| eprintln!("Events may have been missed for {:?}: {}", gap.duration, gap.error));
+//!
+//! for event in events {
+//!     println!("{:?}", event?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{connection::WMIConnection, result_enumerator::next_object, WMIError, WMIResult};
+use log::debug;
+use serde::de;
+use std::{
+    marker::PhantomData,
+    thread,
+    time::{Duration, Instant},
+};
+use windows::Win32::System::Wmi::{
+    IEnumWbemClassObject, WBEM_E_CALL_CANCELLED, WBEM_E_SHUTTING_DOWN,
+};
+
+/// How to resubscribe after a subscription was lost, see [`WMIConnection::persistent_notification`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ResubscribeOptions {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ResubscribeOptions {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(60),
+            max_attempts: None,
+        }
+    }
+}
+
+impl ResubscribeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How long to wait before the first attempt (500 milliseconds by default), which is doubled after each failed attempt.
+    pub fn initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// The longest wait between two attempts (60 seconds by default).
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// After this many failed attempts, the error of the last attempt is returned (unlimited by default).
+    ///
+    /// Calling `next` again starts another series of attempts.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// The wait before an attempt (the first attempt is `0`).
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2_u32.saturating_pow(attempt);
+
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+
+/// A period during which a subscription was lost, and events may have been missed.
+#[derive(Debug)]
+pub struct SubscriptionGap {
+    /// When the subscription was lost.
+    pub started: Instant,
+    /// How long it took to resubscribe.
+    pub duration: Duration,
+    /// Why the subscription was lost.
+    pub error: WMIError,
+    /// The number of attempts it took to resubscribe.
+    pub attempts: u32,
+}
+
+/// The subscription was lost, and is being restored.
+struct Lost {
+    started: Instant,
+    error: WMIError,
+    attempts: u32,
+}
+
+/// Called with each gap, see [`PersistentNotification::on_gap`].
+type OnGap = Box<dyn FnMut(&SubscriptionGap)>;
+
+/// An iterator of events which resubscribes when its subscription is lost, created using [`WMIConnection::persistent_notification`].
+pub struct PersistentNotification<T> {
+    con: WMIConnection,
+    query: String,
+    options: ResubscribeOptions,
+    enumerator: Option<IEnumWbemClassObject>,
+    lost: Option<Lost>,
+    on_gap: Option<OnGap>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+///
+/// ### Additional persistent notification methods
+///
+impl WMIConnection {
+    /// Subscribe to the events of a query (deserialized into `T`), resubscribing whenever the subscription is lost.
+    ///
+    /// The first subscription is made immediately, and its errors are returned.
+    /// The iterator uses its own copy of the connection, which it reconnects as needed.
+    ///
+    /// See the [module level documentation](crate::persistent) for an example.
+    pub fn persistent_notification<T>(
+        &self,
+        query: impl Into<String>,
+        options: ResubscribeOptions,
+    ) -> WMIResult<PersistentNotification<T>>
+    where
+        T: de::DeserializeOwned,
+    {
+        let query = query.into();
+        let enumerator = self.exec_notification_query(&query)?;
+
+        Ok(PersistentNotification {
+            con: self.clone(),
+            query,
+            options,
+            enumerator: Some(enumerator),
+            lost: None,
+            on_gap: None,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T> PersistentNotification<T> {
+    /// Call `on_gap` after each resubscription, with the period during which events may have been missed.
+    pub fn on_gap(mut self, on_gap: impl FnMut(&SubscriptionGap) + 'static) -> Self {
+        self.on_gap = Some(Box::new(on_gap));
+        self
+    }
+
+    fn lose(&mut self, error: WMIError) {
+        debug!("Subscription to {:?} lost: {}", self.query, error);
+
+        self.enumerator = None;
+        self.lost = Some(Lost {
+            started: Instant::now(),
+            error,
+            attempts: 0,
+        });
+    }
+
+    /// Resubscribe, waiting between the attempts.
+    fn resubscribe(&mut self, lost: &mut Lost) -> WMIResult<IEnumWbemClassObject> {
+        let mut failed = 0;
+
+        loop {
+            thread::sleep(self.options.backoff(lost.attempts));
+            lost.attempts += 1;
+
+            let res = self
+                .con
+                .reconnect()
+                .and_then(|()| self.con.exec_notification_query(&self.query));
+
+            match res {
+                Ok(enumerator) => return Ok(enumerator),
+                Err(err) => {
+                    debug!("Resubscribing to {:?} failed: {}", self.query, err);
+
+                    failed += 1;
+                    if self.options.max_attempts.is_some_and(|max| failed >= max) {
+                        return Err(err);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Whether the subscription was lost, and might be restored.
+fn is_lost(err: &WMIError) -> bool {
+    match err {
+        WMIError::HResultError { hres } => {
+            err.is_disconnected()
+                || *hres == WBEM_E_CALL_CANCELLED.0
+                || *hres == WBEM_E_SHUTTING_DOWN.0
+        }
+        _ => false,
+    }
+}
+
+impl<T> Iterator for PersistentNotification<T>
+where
+    T: de::DeserializeOwned,
+{
+    type Item = WMIResult<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(mut lost) = self.lost.take() {
+                let res = self.resubscribe(&mut lost);
+
+                match res {
+                    Ok(enumerator) => {
+                        self.enumerator = Some(enumerator);
+
+                        let gap = SubscriptionGap {
+                            started: lost.started,
+                            duration: lost.started.elapsed(),
+                            error: lost.error,
+                            attempts: lost.attempts,
+                        };
+                        debug!("Resubscribed to {:?} after {:?}", self.query, gap.duration);
+
+                        if let Some(on_gap) = self.on_gap.as_mut() {
+                            on_gap(&gap);
+                        }
+                    }
+                    Err(err) => {
+                        self.lost = Some(lost);
+                        return Some(Err(err));
+                    }
+                }
+            }
+
+            let enumerator = self.enumerator.as_ref()?;
+
+            match next_object(enumerator, self.con.timeout) {
+                Some(Ok(event)) => {
+                    return Some(event.into_desr_with_options(&self.con.de_options, None))
+                }
+                Some(Err(err)) if is_lost(&err) => self.lose(err),
+                Some(Err(err)) => return Some(Err(err)),
+                // A notification query only ends when it is cancelled.
+                None => self.lose(WMIError::HResultError {
+                    hres: WBEM_E_CALL_CANCELLED.0,
+                }),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+    #[test]
+    fn it_backs_off_exponentially() {
+        let options = ResubscribeOptions::new()
+            .initial_backoff(Duration::from_secs(1))
+            .max_backoff(Duration::from_secs(10));
+
+        assert_eq!(options.backoff(0), Duration::from_secs(1));
+        assert_eq!(options.backoff(1), Duration::from_secs(2));
+        assert_eq!(options.backoff(3), Duration::from_secs(8));
+        assert_eq!(options.backoff(4), Duration::from_secs(10));
+        assert_eq!(options.backoff(100), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn it_detects_lost_subscriptions() {
+        assert!(is_lost(&WMIError::HResultError {
+            hres: WBEM_E_CALL_CANCELLED.0
+        }));
+        assert!(is_lost(&WMIError::HResultError {
+            hres: crate::health::RPC_S_SERVER_UNAVAILABLE_HRESULT
+        }));
+        assert!(!is_lost(&WMIError::Timeout));
+    }
+
+    #[test]
+    fn it_resubscribes_after_losing_the_subscription() {
+        let mut wmi_con = wmi_con();
+        wmi_con.timeout = Some(Duration::from_secs(1));
+
+        let gaps = Rc::new(RefCell::new(vec![]));
+
+        let mut events = {
+            let gaps = gaps.clone();
+
+            wmi_con
+                .persistent_notification::<HashMap<String, crate::Variant>>(
+                    "SELECT * FROM __InstanceCreationEvent WITHIN 1 WHERE TargetInstance ISA 'Win32_Process' AND TargetInstance.Name = 'wmi-rs-no-such-process'",
+                    ResubscribeOptions::new().initial_backoff(Duration::from_millis(10)),
+                )
+                .unwrap()
+                .on_gap(move |gap| gaps.borrow_mut().push(gap.attempts))
+        };
+
+        events.lose(WMIError::HResultError {
+            hres: WBEM_E_CALL_CANCELLED.0,
+        });
+
+        assert!(matches!(events.next(), Some(Err(WMIError::Timeout))));
+        assert_eq!(*gaps.borrow(), [1]);
+    }
+}
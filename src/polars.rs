@@ -0,0 +1,278 @@
+//! Convert query results into Polars [`DataFrame`]s, for ad-hoc analysis and reporting.
+//!
+//! Like the [`arrow`](crate::arrow) module, the column types are inferred from the definition of the queried class.
+//! Results can be read from a free-text query, or from the fields of a struct (like [`WMIConnection::query`]):
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! #[serde(rename = "Win32_Process")]
+//! #[serde(rename_all = "PascalCase")]
+//! struct Process {
+//!     name: String,
+//!     working_set_size: u64,
+//! }
+//!
+//! let df = con.query_dataframe::<Process>()?;
+//! assert_eq!(df.get_column_names(), ["Name", "WorkingSetSize"]);
+//!
+//! let df = con.raw_query_dataframe("SELECT Name, ProcessId FROM Win32_Process")?;
+//! assert!(df.height() > 0);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! CIM types are mapped like in the [`arrow`](crate::arrow) module: integers, reals and booleans are mapped
+//! to the matching Polars types, strings, datetimes and references to `String` columns,
+//! and arrays to `List` columns. Embedded objects are not included.
+use crate::{
+    connection::WMIConnection,
+    order::{as_float, as_integer},
+    query::{build_query, query_class, select_projection},
+    Variant, WMIError, WMIResult,
+};
+use ::polars::prelude::{DataFrame, DataType, NamedFrom, Series};
+use serde::de;
+use windows::Win32::System::Wmi::WBEM_E_INVALID_QUERY;
+
+///
+/// ### Additional Polars methods
+///
+impl WMIConnection {
+    /// Execute a `SELECT` query, and convert the results into a [`DataFrame`].
+    ///
+    /// See the [module level documentation](crate::polars) for an example.
+    pub fn raw_query_dataframe(&self, query: impl AsRef<str>) -> WMIResult<DataFrame> {
+        let query = query.as_ref();
+
+        // Only `SELECT` queries have a class to infer the column types from.
+        let class = query_class(query).ok_or(WMIError::HResultError {
+            hres: WBEM_E_INVALID_QUERY.0,
+        })?;
+
+        let columns = self.dataframe_columns(class, select_projection(query).as_deref())?;
+        let mut values: Vec<Vec<Variant>> = columns.iter().map(|_| vec![]).collect();
+
+        for row in self.exec_query_native_wrapper(query)? {
+            let row = row?;
+
+            for ((name, _), column) in columns.iter().zip(&mut values) {
+                column.push(row.get_property(name)?);
+            }
+        }
+
+        let series = columns
+            .iter()
+            .zip(values)
+            .map(|((name, data_type), values)| build_series(name, data_type, values))
+            .collect::<WMIResult<Vec<_>>>()?;
+
+        Ok(DataFrame::new(series)?)
+    }
+
+    /// Query the properties of type T (its fields), and convert the results into a [`DataFrame`].
+    pub fn query_dataframe<T>(&self) -> WMIResult<DataFrame>
+    where
+        T: de::DeserializeOwned,
+    {
+        let query_text = build_query::<T>(None)?;
+
+        self.raw_query_dataframe(query_text)
+    }
+
+    /// The names and types of the columns of a class, or of the given properties of the class (in order).
+    fn dataframe_columns(
+        &self,
+        class: &str,
+        properties: Option<&[String]>,
+    ) -> WMIResult<Vec<(String, DataType)>> {
+        let description = self.describe_class(class)?;
+
+        let selected: Vec<_> = match properties {
+            Some(properties) => properties
+                .iter()
+                .filter_map(|name| {
+                    description
+                        .properties
+                        .iter()
+                        .find(|property| property.name.eq_ignore_ascii_case(name))
+                })
+                .collect(),
+            None => description.properties.iter().collect(),
+        };
+
+        Ok(selected
+            .into_iter()
+            .filter_map(|property| Some((property.name.clone(), data_type(&property.cim_type)?)))
+            .collect())
+    }
+}
+
+/// The Polars type of a CIM type (given by its MOF name, like `uint32` or `string[]`).
+fn data_type(cim_type: &str) -> Option<DataType> {
+    if let Some(item) = cim_type.strip_suffix("[]") {
+        return Some(DataType::List(Box::new(data_type(item)?)));
+    }
+
+    let data_type = match cim_type {
+        "sint8" => DataType::Int8,
+        "uint8" => DataType::UInt8,
+        "sint16" => DataType::Int16,
+        "uint16" => DataType::UInt16,
+        "sint32" => DataType::Int32,
+        "uint32" => DataType::UInt32,
+        "sint64" => DataType::Int64,
+        "uint64" => DataType::UInt64,
+        "real32" => DataType::Float32,
+        "real64" => DataType::Float64,
+        "boolean" => DataType::Boolean,
+        "object" => return None,
+        // Strings, datetimes, references and characters.
+        _ => DataType::String,
+    };
+
+    Some(data_type)
+}
+
+/// Build a series of the given type. Values which don't fit the type are converted to nulls.
+fn build_series(name: &str, data_type: &DataType, values: Vec<Variant>) -> WMIResult<Series> {
+    macro_rules! integers {
+        ($native:ty) => {
+            Series::new(
+                name,
+                values
+                    .iter()
+                    .map(|value| as_integer(value).and_then(|n| <$native>::try_from(n).ok()))
+                    .collect::<Vec<_>>(),
+            )
+        };
+    }
+
+    let series = match data_type {
+        DataType::Int8 => integers!(i8),
+        DataType::UInt8 => integers!(u8),
+        DataType::Int16 => integers!(i16),
+        DataType::UInt16 => integers!(u16),
+        DataType::Int32 => integers!(i32),
+        DataType::UInt32 => integers!(u32),
+        DataType::Int64 => integers!(i64),
+        DataType::UInt64 => integers!(u64),
+        DataType::Float32 => Series::new(
+            name,
+            values
+                .iter()
+                .map(|value| as_float(value).map(|n| n as f32))
+                .collect::<Vec<_>>(),
+        ),
+        DataType::Float64 => Series::new(name, values.iter().map(as_float).collect::<Vec<_>>()),
+        DataType::Boolean => Series::new(
+            name,
+            values
+                .iter()
+                .map(|value| match value {
+                    Variant::Bool(b) => Some(*b),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+        ),
+        DataType::List(item) => {
+            let lists = values
+                .into_iter()
+                .map(|value| match value {
+                    Variant::Array(items) => build_series("", item, items).map(Some),
+                    _ => Ok(None),
+                })
+                .collect::<WMIResult<Vec<_>>>()?;
+
+            // Without any list, the item type cannot be inferred from the values.
+            Series::new(name, lists).cast(data_type)?
+        }
+        _ => Series::new(
+            name,
+            values
+                .into_iter()
+                .map(|value| match value {
+                    Variant::String(s) => Some(s),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+        ),
+    };
+
+    Ok(series)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use serde::Deserialize;
+
+    #[test]
+    fn it_maps_cim_types() {
+        assert_eq!(data_type("uint64"), Some(DataType::UInt64));
+        assert_eq!(data_type("reference"), Some(DataType::String));
+        assert_eq!(
+            data_type("uint16[]"),
+            Some(DataType::List(Box::new(DataType::UInt16)))
+        );
+        assert_eq!(data_type("object"), None);
+    }
+
+    #[test]
+    fn it_builds_series() {
+        let series = build_series(
+            "Size",
+            &DataType::UInt64,
+            vec![
+                Variant::String("18446744073709551615".to_owned()),
+                Variant::Null,
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(series.dtype(), &DataType::UInt64);
+        assert_eq!(series.u64().unwrap().get(0), Some(u64::MAX));
+        assert_eq!(series.null_count(), 1);
+
+        let lists = build_series(
+            "Languages",
+            &DataType::List(Box::new(DataType::String)),
+            vec![Variant::Null],
+        )
+        .unwrap();
+
+        assert_eq!(lists.dtype(), &DataType::List(Box::new(DataType::String)));
+    }
+
+    #[test]
+    fn it_converts_queries_to_dataframes() {
+        #[derive(Deserialize)]
+        #[serde(rename = "Win32_Process")]
+        #[serde(rename_all = "PascalCase")]
+        struct Process {
+            #[allow(dead_code)]
+            process_id: u32,
+        }
+
+        let wmi_con = wmi_con();
+
+        let df = wmi_con.query_dataframe::<Process>().unwrap();
+        assert_eq!(df.get_column_names(), ["ProcessId"]);
+        assert_eq!(df.column("ProcessId").unwrap().dtype(), &DataType::UInt32);
+        assert!(df.height() > 1);
+
+        let os = wmi_con
+            .raw_query_dataframe("SELECT Caption, MUILanguages FROM Win32_OperatingSystem")
+            .unwrap();
+        assert_eq!(os.height(), 1);
+        assert_eq!(
+            os.column("MUILanguages").unwrap().dtype(),
+            &DataType::List(Box::new(DataType::String))
+        );
+    }
+}
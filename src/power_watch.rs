@@ -0,0 +1,218 @@
+//! Watch the computer sleep and resume, and users log on and off.
+//!
+//! Power events are delivered by `Win32_PowerManagementEvent` as soon as they happen.
+//! WMI has no extrinsic event for logon sessions, so [`SessionEvent`]s are polled `__InstanceCreationEvent`s
+//! and `__InstanceDeletionEvent`s of `Win32_LogonSession`.
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use wmi::power_watch::PowerEventType;
+//!
+//! for event in con.power_events()? {
+//!     match event?.event_type() {
+//!         PowerEventType::Suspending => println!("Pausing work"),
+//!         PowerEventType::Resumed | PowerEventType::ResumedAutomatically => println!("Resuming work"),
+//!         _ => {}
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Both are also available as streams, using [`WMIConnection::async_power_events`] and [`WMIConnection::async_session_events`].
+use crate::{connection::WMIConnection, WMIResult};
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use std::time::Duration;
+
+const POWER_EVENT_QUERY: &str = "SELECT * FROM Win32_PowerManagementEvent";
+
+/// A power management event, from `Win32_PowerManagementEvent`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "Win32_PowerManagementEvent")]
+#[serde(rename_all = "PascalCase")]
+pub struct PowerEvent {
+    /// See [`PowerEvent::event_type`].
+    #[serde(rename = "EventType")]
+    pub event_type_raw: u16,
+    /// Set for [`PowerEventType::Oem`] events.
+    #[serde(rename = "OEMEventCode")]
+    pub oem_event_code: Option<u16>,
+    /// When the event was created, in 100-nanosecond intervals since January 1, 1601 (UTC).
+    /// See [`filetime_to_system_time`](crate::event_time::filetime_to_system_time).
+    #[serde(rename = "TIME_CREATED")]
+    pub time_created: Option<u64>,
+}
+
+/// The type of a power management event, from `EventType`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerEventType {
+    /// The computer is about to sleep (or hibernate).
+    Suspending,
+    /// The computer resumed from sleep because of the user.
+    Resumed,
+    /// The computer resumed from sleep (for example, on a timer), which is also sent before [`Resumed`](PowerEventType::Resumed).
+    ResumedAutomatically,
+    /// Like a switch to the battery, or a low battery.
+    PowerStatusChanged,
+    /// An event defined by the manufacturer, see [`PowerEvent::oem_event_code`].
+    Oem,
+    /// A value not known to this crate.
+    Other(u16),
+}
+
+impl From<u16> for PowerEventType {
+    fn from(value: u16) -> Self {
+        match value {
+            4 => PowerEventType::Suspending,
+            7 => PowerEventType::Resumed,
+            10 => PowerEventType::PowerStatusChanged,
+            11 => PowerEventType::Oem,
+            18 => PowerEventType::ResumedAutomatically,
+            other => PowerEventType::Other(other),
+        }
+    }
+}
+
+impl PowerEvent {
+    pub fn event_type(&self) -> PowerEventType {
+        self.event_type_raw.into()
+    }
+}
+
+/// A logon session, from `Win32_LogonSession`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "Win32_LogonSession")]
+#[serde(rename_all = "PascalCase")]
+pub struct LogonSession {
+    /// The locally unique id of the session.
+    pub logon_id: String,
+    /// Like `2` for interactive logons, `3` for network logons and `10` for remote desktop logons.
+    pub logon_type: Option<u32>,
+    /// Like `Negotiate`, `Kerberos` or `NTLM`.
+    pub authentication_package: Option<String>,
+}
+
+/// A user logged on or off.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionEvent {
+    LoggedOn(LogonSession),
+    LoggedOff(LogonSession),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InstanceEvent {
+    target_instance: LogonSession,
+}
+
+#[derive(Deserialize)]
+enum RawSessionEvent {
+    #[serde(rename = "__InstanceCreationEvent")]
+    Creation(InstanceEvent),
+    #[serde(rename = "__InstanceDeletionEvent")]
+    Deletion(InstanceEvent),
+}
+
+impl From<RawSessionEvent> for SessionEvent {
+    fn from(event: RawSessionEvent) -> Self {
+        match event {
+            RawSessionEvent::Creation(event) => SessionEvent::LoggedOn(event.target_instance),
+            RawSessionEvent::Deletion(event) => SessionEvent::LoggedOff(event.target_instance),
+        }
+    }
+}
+
+fn session_query(interval: Duration) -> String {
+    format!(
+        "SELECT * FROM __InstanceOperationEvent WITHIN {} WHERE TargetInstance ISA 'Win32_LogonSession' \
+        AND (__CLASS = '__InstanceCreationEvent' OR __CLASS = '__InstanceDeletionEvent')",
+        interval.as_secs_f64()
+    )
+}
+
+///
+/// ### Additional power and session watch methods
+///
+impl WMIConnection {
+    /// Subscribe to the power management events (like sleep and resume).
+    ///
+    /// See the [module level documentation](crate::power_watch) for an example.
+    pub fn power_events<'a>(
+        &'a self,
+    ) -> WMIResult<impl Iterator<Item = WMIResult<PowerEvent>> + 'a> {
+        self.raw_notification(POWER_EVENT_QUERY)
+    }
+
+    /// Like [`power_events`](WMIConnection::power_events), returning a stream of events.
+    pub fn async_power_events(&self) -> WMIResult<impl Stream<Item = WMIResult<PowerEvent>>> {
+        self.async_raw_notification(POWER_EVENT_QUERY)
+    }
+
+    /// Subscribe to the logon sessions which are created and deleted, polled every `interval`.
+    ///
+    /// Every logon creates a session, including network logons and service logons.
+    pub fn session_events<'a>(
+        &'a self,
+        interval: Duration,
+    ) -> WMIResult<impl Iterator<Item = WMIResult<SessionEvent>> + 'a> {
+        Ok(self
+            .raw_notification::<RawSessionEvent>(session_query(interval))?
+            .map(|event| event.map(SessionEvent::from)))
+    }
+
+    /// Like [`session_events`](WMIConnection::session_events), returning a stream of events.
+    pub fn async_session_events(
+        &self,
+        interval: Duration,
+    ) -> WMIResult<impl Stream<Item = WMIResult<SessionEvent>>> {
+        Ok(self
+            .async_raw_notification::<RawSessionEvent>(session_query(interval))?
+            .map(|event| event.map(SessionEvent::from)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests::fixtures::*, WMIError};
+
+    #[test]
+    fn it_converts_power_event_types() {
+        assert_eq!(PowerEventType::from(4), PowerEventType::Suspending);
+        assert_eq!(
+            PowerEventType::from(18),
+            PowerEventType::ResumedAutomatically
+        );
+        assert_eq!(PowerEventType::from(1), PowerEventType::Other(1));
+    }
+
+    #[test]
+    fn it_subscribes_to_power_events() {
+        let mut wmi_con = wmi_con();
+        wmi_con.timeout = Some(Duration::from_secs(1));
+
+        let mut events = wmi_con.power_events().unwrap();
+
+        // The computer is not expected to sleep while testing.
+        assert!(matches!(events.next(), Some(Err(WMIError::Timeout))));
+    }
+
+    #[test]
+    fn it_subscribes_to_session_events() {
+        let mut wmi_con = wmi_con();
+        wmi_con.timeout = Some(Duration::from_secs(1));
+
+        assert_eq!(
+            session_query(Duration::from_secs(5)),
+            "SELECT * FROM __InstanceOperationEvent WITHIN 5 WHERE TargetInstance ISA 'Win32_LogonSession' \
+            AND (__CLASS = '__InstanceCreationEvent' OR __CLASS = '__InstanceDeletionEvent')"
+        );
+
+        let mut events = wmi_con.session_events(Duration::from_secs(1)).unwrap();
+
+        assert!(matches!(events.next(), Some(Err(WMIError::Timeout))));
+    }
+}
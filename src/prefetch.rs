@@ -0,0 +1,152 @@
+use crate::{COMLibrary, WMIError, WMIResult};
+use log::{debug, trace};
+use std::collections::VecDeque;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread;
+use windows::Win32::Foundation::RPC_E_WRONG_THREAD;
+use windows::Win32::System::Com::{CoGetApartmentType, APTTYPE, APTTYPEQUALIFIER, APTTYPE_MTA};
+use windows::Win32::System::Wmi::{IEnumWbemClassObject, IWbemClassObject, WBEM_S_TIMEDOUT};
+
+/// Moves COM pointers between threads of the multithreaded apartment (MTA), which can use them without marshaling.
+///
+/// # Safety
+///
+/// The pointers must belong to the MTA, and must only be used by threads which are in it.
+/// [`InMta::new`] checks that the thread which wraps them is in the MTA, so pointers of a single-threaded apartment
+/// (like the ones of a connection created using [`COMLibrary::assume_initialized`] on an STA thread) are never moved.
+/// The threads which unwrap them must also be in the MTA: either by joining it (like using [`COMLibrary::without_security`]),
+/// or implicitly (for threads which didn't initialize COM), while the wrapping thread keeps the MTA alive.
+pub(crate) struct InMta<T>(T);
+
+unsafe impl<T> Send for InMta<T> {}
+
+impl<T> InMta<T> {
+    /// Wrap the pointers, failing with `RPC_E_WRONG_THREAD` if the current thread is not in the MTA.
+    pub(crate) fn new(value: T) -> WMIResult<Self> {
+        if !is_in_mta() {
+            return Err(WMIError::HResultError {
+                hres: RPC_E_WRONG_THREAD.0,
+            });
+        }
+
+        Ok(Self(value))
+    }
+
+    pub(crate) fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+/// Whether the current thread is in the MTA (explicitly, or implicitly).
+fn is_in_mta() -> bool {
+    let mut apartment = APTTYPE::default();
+    let mut qualifier = APTTYPEQUALIFIER::default();
+
+    let res = unsafe { CoGetApartmentType(&mut apartment, &mut qualifier) };
+
+    res.is_ok() && apartment == APTTYPE_MTA
+}
+
+type Batch = WMIResult<InMta<Vec<IWbemClassObject>>>;
+
+/// Reads the results of a query in batches on a background thread,
+/// so that waiting for the provider overlaps with the consumer's work.
+pub(crate) struct Prefetcher {
+    batches: Receiver<Batch>,
+    buffer: VecDeque<IWbemClassObject>,
+}
+
+impl Prefetcher {
+    /// Start reading batches of `batch_size` objects. The enumerator is released by the worker
+    /// when the results are exhausted, or after the prefetcher is dropped.
+    ///
+    /// Fails with `RPC_E_WRONG_THREAD` if the current thread is not in the MTA.
+    pub(crate) fn spawn(
+        p_enumerator: IEnumWbemClassObject,
+        batch_size: u32,
+        timeout: i32,
+    ) -> WMIResult<Self> {
+        // A single batch is read ahead, while the previous one is consumed.
+        let (sender, batches) = sync_channel(1);
+        let p_enumerator = InMta::new(p_enumerator)?;
+
+        thread::Builder::new()
+            .name("wmi-prefetch".to_owned())
+            .spawn(move || {
+                if let Err(e) = COMLibrary::without_security() {
+                    let _ = sender.send(Err(e));
+                    return;
+                }
+
+                let p_enumerator = p_enumerator.into_inner();
+
+                loop {
+                    let batch = next_batch(&p_enumerator, batch_size, timeout);
+
+                    // The enumerator is kept after a timeout, so it is possible to continue waiting for results.
+                    let done = match &batch {
+                        Ok(objs) => objs.is_empty(),
+                        Err(e) => !matches!(e, WMIError::Timeout),
+                    };
+
+                    if sender.send(batch.and_then(InMta::new)).is_err() || done {
+                        break;
+                    }
+                }
+
+                debug!("Prefetching done, releasing enumerator");
+            })?;
+
+        Ok(Self {
+            batches,
+            buffer: VecDeque::new(),
+        })
+    }
+
+    /// Return the next object, or `None` when the results are exhausted.
+    pub(crate) fn next(&mut self) -> Option<WMIResult<IWbemClassObject>> {
+        loop {
+            if let Some(obj) = self.buffer.pop_front() {
+                return Some(Ok(obj));
+            }
+
+            match self.batches.recv() {
+                Ok(Ok(objs)) => {
+                    let objs = objs.into_inner();
+
+                    if objs.is_empty() {
+                        return None;
+                    }
+
+                    self.buffer.extend(objs);
+                }
+                Ok(Err(e)) => return Some(Err(e)),
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+fn next_batch(
+    p_enumerator: &IEnumWbemClassObject,
+    batch_size: u32,
+    timeout: i32,
+) -> WMIResult<Vec<IWbemClassObject>> {
+    let mut objs = vec![None; batch_size as usize];
+    let mut return_value = 0;
+
+    let res = unsafe { p_enumerator.Next(timeout, &mut objs, &mut return_value) };
+
+    res.ok()?;
+
+    if res.0 == WBEM_S_TIMEDOUT.0 && return_value == 0 {
+        return Err(WMIError::Timeout);
+    }
+
+    trace!("Prefetched {} objects", return_value);
+
+    objs.into_iter()
+        .take(return_value as usize)
+        .map(|obj| obj.ok_or(WMIError::NullPointerResult))
+        .collect()
+}
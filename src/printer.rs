@@ -0,0 +1,268 @@
+//! Printers and their print jobs, using the `Win32_Printer` and `Win32_PrintJob` classes.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! for printer in con.printers()? {
+//!     let jobs = con.print_jobs_of(&printer)?;
+//!     println!("{} (default: {}): {} jobs", printer.name, printer.default, jobs.len());
+//! }
+//!
+//! if let Some(printer) = con.default_printer()? {
+//!     println!("Default printer: {} on {:?}", printer.name, printer.port_name);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{connection::WMIConnection, query::quote_and_escape_wql_str, WMIError, WMIResult};
+use serde::Deserialize;
+
+/// The status of a printer, from the `PrinterStatus` property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrinterStatus {
+    Other,
+    Unknown,
+    Idle,
+    Printing,
+    WarmingUp,
+    StoppedPrinting,
+    Offline,
+}
+
+impl From<u16> for PrinterStatus {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => PrinterStatus::Other,
+            3 => PrinterStatus::Idle,
+            4 => PrinterStatus::Printing,
+            5 => PrinterStatus::WarmingUp,
+            6 => PrinterStatus::StoppedPrinting,
+            7 => PrinterStatus::Offline,
+            _ => PrinterStatus::Unknown,
+        }
+    }
+}
+
+fn deserialize_printer_status<'de, D>(deserializer: D) -> Result<PrinterStatus, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let status = Option::<u16>::deserialize(deserializer)?;
+
+    Ok(status.map_or(PrinterStatus::Unknown, PrinterStatus::from))
+}
+
+/// A printer, from `Win32_Printer`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "Win32_Printer")]
+#[serde(rename_all = "PascalCase")]
+pub struct Printer {
+    #[serde(rename = "__Path")]
+    pub path: String,
+    /// Like `Microsoft Print to PDF` or `\\printserver\Floor 2`.
+    pub name: String,
+    /// Whether this is the default printer of the current user.
+    pub default: bool,
+    pub local: bool,
+    pub network: bool,
+    pub shared: bool,
+    pub share_name: Option<String>,
+    /// Like `PORTPROMPT:` or `192.168.1.20`.
+    pub port_name: Option<String>,
+    pub driver_name: Option<String>,
+    pub location: Option<String>,
+    pub comment: Option<String>,
+    pub work_offline: bool,
+    #[serde(deserialize_with = "deserialize_printer_status")]
+    pub printer_status: PrinterStatus,
+}
+
+/// A print job, from `Win32_PrintJob`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "Win32_PrintJob")]
+#[serde(rename_all = "PascalCase")]
+pub struct PrintJob {
+    #[serde(rename = "__Path")]
+    pub path: String,
+    /// The name of the printer and the id of the job, like `Microsoft Print to PDF, 12`.
+    pub name: String,
+    pub job_id: u32,
+    pub document: Option<String>,
+    /// The user who submitted the job.
+    pub owner: Option<String>,
+    pub host_print_queue: Option<String>,
+    /// Like `Printing` or `Error | Offline`.
+    pub job_status: Option<String>,
+    pub total_pages: Option<u32>,
+    pub pages_printed: Option<u32>,
+    /// The size of the job, in bytes.
+    pub size: Option<u32>,
+    pub priority: Option<u32>,
+    /// A DMTF datetime, see [`datetime::raw`](crate::datetime::raw).
+    #[serde(with = "crate::datetime::raw::option")]
+    pub time_submitted: Option<String>,
+}
+
+impl PrintJob {
+    /// The name of the printer of the job.
+    pub fn printer_name(&self) -> &str {
+        // Printer names can contain commas, so split at the last one.
+        self.name
+            .rsplit_once(", ")
+            .map_or(self.name.as_str(), |(printer, _)| printer)
+    }
+}
+
+///
+/// ### Additional printer methods
+///
+impl WMIConnection {
+    pub fn printers(&self) -> WMIResult<Vec<Printer>> {
+        self.query()
+    }
+
+    /// Get a printer by name, or `None` if there is no such printer.
+    pub fn printer(&self, name: &str) -> WMIResult<Option<Printer>> {
+        let printers: Vec<Printer> = self.raw_query(format!(
+            "SELECT * FROM Win32_Printer WHERE Name = {}",
+            quote_and_escape_wql_str(name)
+        ))?;
+
+        Ok(printers.into_iter().next())
+    }
+
+    /// The default printer of the current user, if there is one.
+    pub fn default_printer(&self) -> WMIResult<Option<Printer>> {
+        let printers: Vec<Printer> =
+            self.raw_query("SELECT * FROM Win32_Printer WHERE Default = TRUE")?;
+
+        Ok(printers.into_iter().next())
+    }
+
+    /// Make the printer the default printer of the current user.
+    pub fn set_default_printer(&self, printer: &Printer) -> WMIResult<()> {
+        self.exec_printer_method(&printer.path, "SetDefaultPrinter")
+    }
+
+    /// Cancel all the jobs of the printer.
+    pub fn cancel_all_jobs(&self, printer: &Printer) -> WMIResult<()> {
+        self.exec_printer_method(&printer.path, "CancelAllJobs")
+    }
+
+    /// Pause the printer's queue.
+    pub fn pause_printer(&self, printer: &Printer) -> WMIResult<()> {
+        self.exec_printer_method(&printer.path, "Pause")
+    }
+
+    /// Resume a paused printer's queue.
+    pub fn resume_printer(&self, printer: &Printer) -> WMIResult<()> {
+        self.exec_printer_method(&printer.path, "Resume")
+    }
+
+    pub fn print_test_page(&self, printer: &Printer) -> WMIResult<()> {
+        self.exec_printer_method(&printer.path, "PrintTestPage")
+    }
+
+    /// List the jobs of all the printers.
+    pub fn print_jobs(&self) -> WMIResult<Vec<PrintJob>> {
+        self.query()
+    }
+
+    /// List the jobs of a printer.
+    pub fn print_jobs_of(&self, printer: &Printer) -> WMIResult<Vec<PrintJob>> {
+        let mut jobs = self.print_jobs()?;
+
+        jobs.retain(|job| job.printer_name().eq_ignore_ascii_case(&printer.name));
+
+        Ok(jobs)
+    }
+
+    pub fn pause_print_job(&self, job: &PrintJob) -> WMIResult<()> {
+        self.exec_printer_method(&job.path, "Pause")
+    }
+
+    pub fn resume_print_job(&self, job: &PrintJob) -> WMIResult<()> {
+        self.exec_printer_method(&job.path, "Resume")
+    }
+
+    /// Execute a method of a printer or a job, and fail if its return value (a Win32 error code) is not `0`.
+    fn exec_printer_method(&self, path: &str, method: &str) -> WMIResult<()> {
+        let out = self
+            .exec_method(path, method, &[])?
+            .ok_or(WMIError::NullPointerResult)?;
+
+        match u32::try_from(out.get_property("ReturnValue")?)? {
+            0 => Ok(()),
+            return_value => Err(WMIError::MethodFailed {
+                method: method.to_owned(),
+                return_value,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    fn job(name: &str) -> PrintJob {
+        PrintJob {
+            path: String::new(),
+            name: name.to_owned(),
+            job_id: 12,
+            document: None,
+            owner: None,
+            host_print_queue: None,
+            job_status: None,
+            total_pages: None,
+            pages_printed: None,
+            size: None,
+            priority: None,
+            time_submitted: None,
+        }
+    }
+
+    #[test]
+    fn it_parses_print_job_printer_names() {
+        assert_eq!(
+            job("Microsoft Print to PDF, 12").printer_name(),
+            "Microsoft Print to PDF"
+        );
+        assert_eq!(job("Floor 2, East, 12").printer_name(), "Floor 2, East");
+        assert_eq!(job("Printer").printer_name(), "Printer");
+    }
+
+    #[test]
+    fn it_maps_printer_statuses() {
+        assert_eq!(PrinterStatus::from(3), PrinterStatus::Idle);
+        assert_eq!(PrinterStatus::from(7), PrinterStatus::Offline);
+        assert_eq!(PrinterStatus::from(42), PrinterStatus::Unknown);
+    }
+
+    #[test]
+    fn it_lists_printers_and_jobs() {
+        let wmi_con = wmi_con();
+
+        let printers = wmi_con.printers().unwrap();
+
+        for printer in &printers {
+            let found = wmi_con.printer(&printer.name).unwrap().unwrap();
+            assert_eq!(found.path, printer.path);
+
+            for job in wmi_con.print_jobs_of(printer).unwrap() {
+                assert_eq!(job.printer_name(), printer.name);
+            }
+        }
+
+        if let Some(default) = wmi_con.default_printer().unwrap() {
+            assert!(default.default);
+        }
+
+        assert_eq!(
+            wmi_con.printer("This printer does not exist").unwrap(),
+            None
+        );
+    }
+}
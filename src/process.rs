@@ -0,0 +1,454 @@
+//! A typed process list, with parent/child relations and optional owners and command lines.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use wmi::process::{ProcessOptions, ProcessTree};
+//!
+//! let processes = con.processes(ProcessOptions::new().command_line(true))?;
+//! let tree = ProcessTree::new(processes);
+//!
+//! for child in tree.children(std::process::id()) {
+//!     println!("{} {:?}", child.process_id, child.command_line);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    connection::WMIConnection,
+    return_code::{MethodError, ProcessError},
+    Variant, WMIError, WMIResult,
+};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use windows::Win32::System::Wmi::WBEM_E_NOT_FOUND;
+
+/// A process, from `Win32_Process`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub process_id: u32,
+    /// The process which created this one. It might have exited (and its id might have been reused).
+    pub parent_process_id: u32,
+    pub name: String,
+    pub executable_path: Option<String>,
+    /// A DMTF datetime, see [`datetime::raw`](crate::datetime::raw). `None` for system processes.
+    pub creation_date: Option<String>,
+    /// Only read when requested using [`ProcessOptions::command_line`].
+    pub command_line: Option<String>,
+    /// Only read when requested using [`ProcessOptions::owner`].
+    pub owner: Option<ProcessOwner>,
+}
+
+/// The user running a process, as returned by `Win32_Process.GetOwner`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProcessOwner {
+    pub domain: String,
+    pub user: String,
+}
+
+/// The priority class of a process, as used by `Win32_Process.SetPriority`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProcessPriority {
+    Idle,
+    BelowNormal,
+    Normal,
+    AboveNormal,
+    High,
+    /// Requires the `SeIncreaseBasePriorityPrivilege` privilege (otherwise, `High` is used instead).
+    Realtime,
+}
+
+impl ProcessPriority {
+    /// The `*_PRIORITY_CLASS` value of the priority.
+    pub fn value(&self) -> u32 {
+        match self {
+            ProcessPriority::Idle => 0x40,
+            ProcessPriority::BelowNormal => 0x4000,
+            ProcessPriority::Normal => 0x20,
+            ProcessPriority::AboveNormal => 0x8000,
+            ProcessPriority::High => 0x80,
+            ProcessPriority::Realtime => 0x100,
+        }
+    }
+}
+
+/// Which optional (and more expensive) information [`WMIConnection::processes`] reads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ProcessOptions {
+    pub command_line: bool,
+    pub owner: bool,
+}
+
+impl ProcessOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the command line of processes.
+    pub fn command_line(mut self, command_line: bool) -> Self {
+        self.command_line = command_line;
+        self
+    }
+
+    /// Read the owner of processes, which requires calling `GetOwner` for each one.
+    pub fn owner(mut self, owner: bool) -> Self {
+        self.owner = owner;
+        self
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Win32_Process")]
+#[serde(rename_all = "PascalCase")]
+struct Win32Process {
+    process_id: u32,
+    parent_process_id: u32,
+    name: String,
+    executable_path: Option<String>,
+    #[serde(with = "crate::datetime::raw::option")]
+    creation_date: Option<String>,
+}
+
+///
+/// ### Additional process methods
+///
+impl WMIConnection {
+    /// List the running processes.
+    ///
+    /// See the [module level documentation](crate::process) for an example.
+    pub fn processes(&self, options: ProcessOptions) -> WMIResult<Vec<ProcessInfo>> {
+        let mut query = String::from(
+            "SELECT ProcessId, ParentProcessId, Name, ExecutablePath, CreationDate, Handle",
+        );
+
+        if options.command_line {
+            query.push_str(", CommandLine");
+        }
+
+        query.push_str(" FROM Win32_Process");
+
+        let mut processes = vec![];
+
+        for obj in self.exec_query_native_wrapper(query)? {
+            let obj = obj?;
+
+            let command_line = match options.command_line {
+                true => match obj.get_property("CommandLine")? {
+                    Variant::String(command_line) => Some(command_line),
+                    _ => None,
+                },
+                false => None,
+            };
+
+            let process: Win32Process = obj.into_desr_with_options(&self.de_options, None)?;
+
+            let owner = match options.owner {
+                true => self.process_owner(process.process_id)?,
+                false => None,
+            };
+
+            processes.push(ProcessInfo {
+                process_id: process.process_id,
+                parent_process_id: process.parent_process_id,
+                name: process.name,
+                executable_path: process.executable_path,
+                creation_date: process.creation_date,
+                command_line,
+                owner,
+            });
+        }
+
+        Ok(processes)
+    }
+
+    /// Get the owner of a process, or `None` if it is not available
+    /// (for example, because the process has exited, or for system processes).
+    pub fn process_owner(&self, process_id: u32) -> WMIResult<Option<ProcessOwner>> {
+        let out = match self.exec_method(&process_path(process_id), "GetOwner", &[]) {
+            Ok(Some(out)) => out,
+            Ok(None) => return Ok(None),
+            // The process has exited.
+            Err(WMIError::HResultError { hres }) if hres == WBEM_E_NOT_FOUND.0 => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        if out.get_property("ReturnValue")? != Variant::UI4(0) {
+            return Ok(None);
+        }
+
+        match (out.get_property("Domain")?, out.get_property("User")?) {
+            (Variant::String(domain), Variant::String(user)) => {
+                Ok(Some(ProcessOwner { domain, user }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Get the SID of the user running a process (like `S-1-5-18`), or `None` if it is not available.
+    pub fn process_owner_sid(&self, process_id: u32) -> WMIResult<Option<String>> {
+        let out = match self.exec_method(&process_path(process_id), "GetOwnerSid", &[]) {
+            Ok(Some(out)) => out,
+            Ok(None) => return Ok(None),
+            // The process has exited.
+            Err(WMIError::HResultError { hres }) if hres == WBEM_E_NOT_FOUND.0 => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        if out.get_property("ReturnValue")? != Variant::UI4(0) {
+            return Ok(None);
+        }
+
+        match out.get_property("Sid")? {
+            Variant::String(sid) => Ok(Some(sid)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Change the priority class of a process.
+    ///
+    /// ```edition2018
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # use wmi::*;
+    /// # let con = WMIConnection::new(COMLibrary::new()?)?;
+    /// use wmi::process::ProcessPriority;
+    ///
+    /// con.set_process_priority(std::process::id(), ProcessPriority::BelowNormal)?;
+    /// # con.set_process_priority(std::process::id(), ProcessPriority::Normal)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_process_priority(
+        &self,
+        process_id: u32,
+        priority: ProcessPriority,
+    ) -> Result<(), MethodError<ProcessError>> {
+        self.exec_method_mapped::<ProcessError>(
+            &process_path(process_id),
+            "SetPriority",
+            &[("Priority", Variant::I4(priority.value() as i32))],
+        )?;
+
+        Ok(())
+    }
+}
+
+pub(crate) fn process_path(process_id: u32) -> String {
+    format!("Win32_Process.Handle=\"{}\"", process_id)
+}
+
+/// The parent/child relations of a list of processes.
+///
+/// Process ids are reused, so a process is only considered the parent of another
+/// if it was created before it.
+#[derive(Debug, Clone)]
+pub struct ProcessTree {
+    processes: HashMap<u32, ProcessInfo>,
+    children: HashMap<u32, Vec<u32>>,
+}
+
+impl ProcessTree {
+    pub fn new(processes: impl IntoIterator<Item = ProcessInfo>) -> Self {
+        let processes: HashMap<u32, ProcessInfo> = processes
+            .into_iter()
+            .map(|process| (process.process_id, process))
+            .collect();
+
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+
+        for process in processes.values() {
+            if let Some(parent) = parent_of(&processes, process) {
+                children
+                    .entry(parent.process_id)
+                    .or_default()
+                    .push(process.process_id);
+            }
+        }
+
+        for pids in children.values_mut() {
+            pids.sort_unstable();
+        }
+
+        Self {
+            processes,
+            children,
+        }
+    }
+
+    pub fn get(&self, process_id: u32) -> Option<&ProcessInfo> {
+        self.processes.get(&process_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.processes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.processes.is_empty()
+    }
+
+    /// The parent of the process, if it is still running.
+    pub fn parent(&self, process_id: u32) -> Option<&ProcessInfo> {
+        parent_of(&self.processes, self.get(process_id)?)
+    }
+
+    /// The direct children of the process, ordered by process id.
+    pub fn children(&self, process_id: u32) -> impl Iterator<Item = &ProcessInfo> {
+        self.children
+            .get(&process_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|pid| self.processes.get(pid))
+    }
+
+    /// All the descendants of the process, depth first.
+    pub fn descendants(&self, process_id: u32) -> Vec<&ProcessInfo> {
+        let mut descendants = vec![];
+        let mut stack: Vec<u32> = vec![process_id];
+        let mut seen = HashSet::from([process_id]);
+
+        while let Some(pid) = stack.pop() {
+            for child in self.children(pid) {
+                if seen.insert(child.process_id) {
+                    descendants.push(child);
+                    stack.push(child.process_id);
+                }
+            }
+        }
+
+        descendants
+    }
+
+    /// The parent of the process, its parent, and so on.
+    pub fn ancestors(&self, process_id: u32) -> Vec<&ProcessInfo> {
+        let mut ancestors = vec![];
+        let mut seen = HashSet::from([process_id]);
+        let mut current = process_id;
+
+        while let Some(parent) = self.parent(current) {
+            if !seen.insert(parent.process_id) {
+                break;
+            }
+
+            ancestors.push(parent);
+            current = parent.process_id;
+        }
+
+        ancestors
+    }
+
+    /// The processes whose parent is not running, ordered by process id.
+    pub fn roots(&self) -> Vec<&ProcessInfo> {
+        let mut roots: Vec<&ProcessInfo> = self
+            .processes
+            .values()
+            .filter(|process| parent_of(&self.processes, process).is_none())
+            .collect();
+
+        roots.sort_unstable_by_key(|process| process.process_id);
+
+        roots
+    }
+}
+
+fn parent_of<'a>(
+    processes: &'a HashMap<u32, ProcessInfo>,
+    process: &ProcessInfo,
+) -> Option<&'a ProcessInfo> {
+    if process.parent_process_id == process.process_id {
+        return None;
+    }
+
+    let parent = processes.get(&process.parent_process_id)?;
+
+    // DMTF datetimes of the same host share the same UTC offset, so they can be compared as strings.
+    match (&parent.creation_date, &process.creation_date) {
+        (Some(parent_created), Some(created)) if parent_created > created => None,
+        _ => Some(parent),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    fn process(process_id: u32, parent_process_id: u32, creation_date: &str) -> ProcessInfo {
+        ProcessInfo {
+            process_id,
+            parent_process_id,
+            name: format!("{}.exe", process_id),
+            executable_path: None,
+            creation_date: Some(creation_date.to_owned()),
+            command_line: None,
+            owner: None,
+        }
+    }
+
+    #[test]
+    fn it_builds_a_process_tree() {
+        let tree = ProcessTree::new([
+            process(4, 0, "20240101000000.000000+000"),
+            process(100, 4, "20240101000001.000000+000"),
+            process(200, 100, "20240101000002.000000+000"),
+            process(300, 100, "20240101000003.000000+000"),
+            // The original parent (pid 300) exited, and its id was reused.
+            process(400, 300, "20240101000002.500000+000"),
+        ]);
+
+        let pids = |processes: Vec<&ProcessInfo>| -> Vec<u32> {
+            processes.iter().map(|process| process.process_id).collect()
+        };
+
+        assert_eq!(pids(tree.children(100).collect()), [200, 300]);
+        assert_eq!(pids(tree.descendants(4)), [100, 300, 200]);
+        assert_eq!(pids(tree.ancestors(200)), [100, 4]);
+        assert_eq!(pids(tree.roots()), [4, 400]);
+        assert!(tree.parent(400).is_none());
+    }
+
+    #[test]
+    fn it_lists_processes() {
+        let wmi_con = wmi_con();
+
+        let processes = wmi_con
+            .processes(ProcessOptions::new().command_line(true).owner(true))
+            .unwrap();
+
+        let current = processes
+            .iter()
+            .find(|process| process.process_id == std::process::id())
+            .unwrap();
+
+        assert!(current.command_line.is_some());
+        assert!(current.owner.is_some());
+
+        let tree = ProcessTree::new(processes.clone());
+        assert!(!tree.ancestors(std::process::id()).is_empty());
+    }
+
+    #[test]
+    fn it_reads_process_owner_sids() {
+        let wmi_con = wmi_con();
+
+        let sid = wmi_con.process_owner_sid(std::process::id()).unwrap();
+        assert!(sid.unwrap().starts_with("S-1-5-"));
+
+        assert_eq!(wmi_con.process_owner_sid(u32::MAX - 1).unwrap(), None);
+    }
+
+    #[test]
+    fn it_sets_process_priorities() {
+        let wmi_con = wmi_con();
+
+        wmi_con
+            .set_process_priority(std::process::id(), ProcessPriority::BelowNormal)
+            .unwrap();
+        wmi_con
+            .set_process_priority(std::process::id(), ProcessPriority::Normal)
+            .unwrap();
+
+        assert_eq!(ProcessPriority::AboveNormal.value(), 32768);
+    }
+}
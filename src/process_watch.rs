@@ -0,0 +1,260 @@
+//! Watch processes start and stop, using the `Win32_ProcessStartTrace` and `Win32_ProcessStopTrace` events.
+//!
+//! Trace events are delivered as soon as a process starts (unlike polled `__InstanceCreationEvent`s),
+//! but they don't include the command line or the owner of the process. These can be read when the event is received
+//! (see [`ProcessOptions`]), which only works if the process is still running by then.
+//!
+//! Subscribing to trace events requires administrative rights.
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use wmi::process::ProcessOptions;
+//! use wmi::process_watch::ProcessEvent;
+//!
+//! for event in con.process_events(ProcessOptions::new().command_line(true))? {
+//!     match event? {
+//!         ProcessEvent::Started(start) => println!("{} started: {:?}", start.process_id, start.command_line),
+//!         ProcessEvent::Stopped(stop) => println!("{} exited with {}", stop.process_id, stop.exit_status),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The same events can be received asynchronously using a [`ProcessWatcher`] stream.
+use crate::{
+    connection::WMIConnection,
+    process::{process_path, ProcessOptions, ProcessOwner},
+    Variant, WMIError, WMIResult,
+};
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use windows::Win32::System::Wmi::WBEM_E_NOT_FOUND;
+
+/// Both `Win32_ProcessStartTrace` and `Win32_ProcessStopTrace` are subclasses of `Win32_ProcessTrace`.
+const PROCESS_TRACE_QUERY: &str = "SELECT * FROM Win32_ProcessTrace";
+
+/// A process started or stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessEvent {
+    Started(ProcessStart),
+    Stopped(ProcessStop),
+}
+
+/// A process started, from `Win32_ProcessStartTrace`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessStart {
+    pub process_id: u32,
+    pub parent_process_id: u32,
+    /// The name of the executable, like `notepad.exe`.
+    pub process_name: String,
+    pub session_id: u32,
+    /// Only read when requested using [`ProcessOptions::command_line`] (with the command line).
+    pub executable_path: Option<String>,
+    /// Only read when requested using [`ProcessOptions::command_line`].
+    pub command_line: Option<String>,
+    /// Only read when requested using [`ProcessOptions::owner`].
+    pub owner: Option<ProcessOwner>,
+}
+
+/// A process stopped, from `Win32_ProcessStopTrace`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessStop {
+    pub process_id: u32,
+    pub parent_process_id: u32,
+    pub process_name: String,
+    pub session_id: u32,
+    pub exit_status: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ProcessTrace {
+    #[serde(rename = "ProcessID")]
+    process_id: u32,
+    #[serde(rename = "ParentProcessID")]
+    parent_process_id: u32,
+    process_name: String,
+    #[serde(rename = "SessionID")]
+    session_id: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ProcessStopTrace {
+    #[serde(flatten)]
+    trace: ProcessTrace,
+    exit_status: u32,
+}
+
+/// The subclasses of `Win32_ProcessTrace`, selected by their `__CLASS`.
+#[derive(Deserialize)]
+enum ProcessTraceEvent {
+    #[serde(rename = "Win32_ProcessStartTrace")]
+    Start(ProcessTrace),
+    #[serde(rename = "Win32_ProcessStopTrace")]
+    Stop(ProcessStopTrace),
+}
+
+/// A stream of [`ProcessEvent`]s, created using [`WMIConnection::process_watcher`].
+///
+/// The optional information is read when the event is polled, which blocks until the calls complete.
+pub struct ProcessWatcher {
+    inner: Pin<Box<dyn Stream<Item = WMIResult<ProcessEvent>>>>,
+}
+
+impl Stream for ProcessWatcher {
+    type Item = WMIResult<ProcessEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+///
+/// ### Additional process watch methods
+///
+impl WMIConnection {
+    /// Subscribe to the processes which start and stop, reading the requested optional information of started processes.
+    ///
+    /// See the [module level documentation](crate::process_watch) for an example.
+    pub fn process_events<'a>(
+        &'a self,
+        options: ProcessOptions,
+    ) -> WMIResult<impl Iterator<Item = WMIResult<ProcessEvent>> + 'a> {
+        Ok(self
+            .raw_notification::<ProcessTraceEvent>(PROCESS_TRACE_QUERY)?
+            .map(move |event| self.process_event(event?, options)))
+    }
+
+    /// Like [`process_events`](WMIConnection::process_events), returning a stream of events.
+    pub fn process_watcher(&self, options: ProcessOptions) -> WMIResult<ProcessWatcher> {
+        let con = self.clone();
+
+        let stream = self
+            .async_raw_notification::<ProcessTraceEvent>(PROCESS_TRACE_QUERY)?
+            .map(move |event| con.process_event(event?, options));
+
+        Ok(ProcessWatcher {
+            inner: Box::pin(stream),
+        })
+    }
+
+    fn process_event(
+        &self,
+        event: ProcessTraceEvent,
+        options: ProcessOptions,
+    ) -> WMIResult<ProcessEvent> {
+        let trace = match event {
+            ProcessTraceEvent::Stop(stop) => {
+                return Ok(ProcessEvent::Stopped(ProcessStop {
+                    process_id: stop.trace.process_id,
+                    parent_process_id: stop.trace.parent_process_id,
+                    process_name: stop.trace.process_name,
+                    session_id: stop.trace.session_id,
+                    exit_status: stop.exit_status,
+                }))
+            }
+            ProcessTraceEvent::Start(trace) => trace,
+        };
+
+        let mut start = ProcessStart {
+            process_id: trace.process_id,
+            parent_process_id: trace.parent_process_id,
+            process_name: trace.process_name,
+            session_id: trace.session_id,
+            executable_path: None,
+            command_line: None,
+            owner: None,
+        };
+
+        if options.command_line {
+            match self.get_raw_by_path(process_path(start.process_id)) {
+                Ok(process) => {
+                    if let Variant::String(path) = process.get_property("ExecutablePath")? {
+                        start.executable_path = Some(path);
+                    }
+                    if let Variant::String(command_line) = process.get_property("CommandLine")? {
+                        start.command_line = Some(command_line);
+                    }
+                }
+                // The process has already exited.
+                Err(WMIError::HResultError { hres }) if hres == WBEM_E_NOT_FOUND.0 => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        if options.owner {
+            start.owner = self.process_owner(start.process_id)?;
+        }
+
+        Ok(ProcessEvent::Started(start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{fixtures::*, ignore_access_denied, start_test_program};
+    use futures::executor::block_on;
+
+    fn is_test_program(event: &ProcessEvent) -> bool {
+        matches!(event, ProcessEvent::Started(start) if start.process_name.eq_ignore_ascii_case("cmd.exe"))
+    }
+
+    #[test]
+    fn it_watches_processes() {
+        let wmi_con = wmi_con();
+
+        ignore_access_denied((|| -> WMIResult<()> {
+            let mut events = wmi_con.process_events(ProcessOptions::new().command_line(true))?;
+
+            start_test_program();
+
+            let event = events
+                .find(|event| event.as_ref().map_or(true, is_test_program))
+                .unwrap()?;
+
+            match event {
+                ProcessEvent::Started(start) => {
+                    assert_eq!(start.parent_process_id, std::process::id());
+
+                    // The test program might have exited before its command line was read.
+                    if let Some(command_line) = start.command_line {
+                        assert!(command_line.contains("timeout"));
+                    }
+                }
+                other => panic!("Unexpected event {:?}", other),
+            }
+
+            Ok(())
+        })())
+        .unwrap();
+    }
+
+    #[test]
+    fn it_watches_processes_async() {
+        let wmi_con = wmi_con();
+
+        ignore_access_denied(block_on(async {
+            let mut watcher = wmi_con.process_watcher(ProcessOptions::new())?;
+
+            start_test_program();
+
+            while let Some(event) = watcher.next().await {
+                if is_test_program(&event?) {
+                    break;
+                }
+            }
+
+            Ok::<_, WMIError>(())
+        }))
+        .unwrap();
+    }
+}
@@ -1,8 +1,8 @@
 use crate::{
-    connection::WMIConnection,
+    connection::{ComTransport, WMIConnection},
     de::meta::struct_name_and_fields,
-    de::wbem_class_de::from_wbem_class_obj,
     result_enumerator::{IWbemClassWrapper, QueryResultEnumerator},
+    transport::WbemTransport,
     WMIError, WMIResult,
 };
 use log::trace;
@@ -10,7 +10,8 @@ use serde::de;
 use std::{collections::HashMap, time::Duration};
 use windows::core::BSTR;
 use windows::Win32::System::Wmi::{
-    WBEM_FLAG_FORWARD_ONLY, WBEM_FLAG_RETURN_IMMEDIATELY, WBEM_FLAG_RETURN_WBEM_COMPLETE,
+    WBEM_FLAG_ENSURE_LOCATABLE, WBEM_FLAG_FORWARD_ONLY, WBEM_FLAG_RETURN_IMMEDIATELY,
+    WBEM_FLAG_RETURN_WBEM_COMPLETE,
 };
 
 #[non_exhaustive]
@@ -104,16 +105,34 @@ where
 {
     let (name, fields, optional_where_clause) = get_query_segments::<T>(filters)?;
 
+    // Structs without fields (like unit structs) still need to select something.
+    let projection = if fields.is_empty() {
+        "__CLASS".to_owned()
+    } else {
+        fields.join(",")
+    };
+
     let query_text = format!(
         "SELECT {} FROM {} {}",
-        fields.join(","),
-        name,
-        optional_where_clause
+        projection, name, optional_where_clause
     );
 
     Ok(query_text)
 }
 
+/// Like [`build_query`], but select all of the properties of the class of `T`,
+/// so the returned objects are complete (and can be written back using `PutInstance`).
+pub(crate) fn build_select_all_query<'de, T>(
+    filters: Option<&HashMap<String, FilterValue>>,
+) -> WMIResult<String>
+where
+    T: de::Deserialize<'de>,
+{
+    let (name, _, optional_where_clause) = get_query_segments::<T>(filters)?;
+
+    Ok(format!("SELECT * FROM {} {}", name, optional_where_clause))
+}
+
 /// Build an SQL query for an event notification subscription with the given filters and within polling time, over the given type (using its fields).
 /// For example, for:
 ///
@@ -165,7 +184,95 @@ where
     Ok(query_text)
 }
 
-fn get_query_segments<'de, T>(
+/// The `GROUP WITHIN ... BY ...` clause of an aggregate event query, see [`build_aggregate_notification_query`].
+///
+/// Events are grouped over `interval`, and a single `__AggregateEvent` is delivered for each group
+/// (with the number of events and one of them, the representative).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupWithin {
+    pub interval: Duration,
+    /// The properties used to group the events, like `TargetInstance.Name`.
+    /// Without them, all the events of the interval are in a single group.
+    pub by: Vec<String>,
+    /// The conditions of the `HAVING` clause, which must all be true for a group to be delivered.
+    pub having: Vec<String>,
+}
+
+impl GroupWithin {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            by: vec![],
+            having: vec![],
+        }
+    }
+
+    /// Group the events by a property (in addition to the previous ones).
+    pub fn by(mut self, property: impl Into<String>) -> Self {
+        self.by.push(property.into());
+        self
+    }
+
+    /// Only deliver the groups matching a condition on the properties of `__AggregateEvent`,
+    /// like `NumberOfEvents > 10` (in addition to the previous conditions).
+    ///
+    /// The groups are filtered by WMI, so the events of the other groups are never delivered to the client.
+    pub fn having(mut self, condition: impl Into<String>) -> Self {
+        self.having.push(condition.into());
+        self
+    }
+
+    /// Only deliver the groups with at least `n` events.
+    pub fn having_at_least(self, n: u32) -> Self {
+        self.having(format!("NumberOfEvents >= {}", n))
+    }
+}
+
+/// Build an aggregate event query, which groups the events of a notification query
+/// (see [`build_notification_query`]) using a `GROUP WITHIN` clause.
+///
+/// For example, with a `TargetInstance ISA 'Win32_Process'` filter and
+/// `GroupWithin::new(Duration::from_secs(10)).by("TargetInstance.Name")`, the query will look like:
+/// ```
+/// "SELECT * FROM __InstanceCreationEvent WITHIN 1 WHERE TargetInstance ISA 'Win32_Process' GROUP WITHIN 10 BY TargetInstance.Name";
+/// ```
+///
+/// With `.having_at_least(5)`, only the groups of at least 5 events are delivered:
+/// ```
+/// "SELECT * FROM __InstanceModificationEvent WITHIN 1 WHERE TargetInstance ISA 'Win32_Service' GROUP WITHIN 60 HAVING NumberOfEvents >= 5";
+/// ```
+///
+/// The results can be deserialized using [`AggregateEvent`](crate::notification::AggregateEvent).
+pub fn build_aggregate_notification_query<'de, T>(
+    filters: Option<&HashMap<String, FilterValue>>,
+    within: Option<Duration>,
+    group: &GroupWithin,
+) -> WMIResult<String>
+where
+    T: de::Deserialize<'de>,
+{
+    let query_text = build_notification_query::<T>(filters, within)?;
+
+    let mut query_text = format!(
+        "{} GROUP WITHIN {}",
+        query_text.trim_end(),
+        group.interval.as_secs_f64()
+    );
+
+    if !group.by.is_empty() {
+        query_text.push_str(" BY ");
+        query_text.push_str(&group.by.join(", "));
+    }
+
+    if !group.having.is_empty() {
+        query_text.push_str(" HAVING ");
+        query_text.push_str(&group.having.join(" AND "));
+    }
+
+    Ok(query_text)
+}
+
+pub(crate) fn get_query_segments<'de, T>(
     filters: Option<&HashMap<String, FilterValue>>,
 ) -> WMIResult<(&'static str, &'static [&'static str], String)>
 where
@@ -263,7 +370,64 @@ pub fn quote_and_escape_wql_str(s: impl AsRef<str>) -> String {
     o
 }
 
-impl WMIConnection {
+/// Return the properties selected by a `SELECT` query, in order.
+///
+/// Returns `None` for `SELECT *` and for other kinds of queries (like `ASSOCIATORS OF`).
+///
+/// ```edition2018
+/// # use wmi::query::select_projection;
+/// assert_eq!(
+///     select_projection("SELECT Name, ProcessId FROM Win32_Process"),
+///     Some(vec!["Name".to_string(), "ProcessId".to_string()])
+/// );
+/// assert_eq!(select_projection("SELECT * FROM Win32_Process"), None);
+/// ```
+pub fn select_projection(query: &str) -> Option<Vec<String>> {
+    let query = query.trim_start();
+
+    let rest = query
+        .get(..6)
+        .filter(|select| select.eq_ignore_ascii_case("SELECT"))
+        .map(|_| &query[6..])
+        .filter(|rest| rest.starts_with(|c: char| c.is_ascii_whitespace()))?;
+
+    // WQL property names are not quoted, so the first `FROM` keyword must end the projection.
+    let from = find_from_keyword(rest)?;
+
+    let projection: Vec<String> = rest[..from]
+        .split(',')
+        .map(|property| property.trim().to_owned())
+        .collect();
+
+    if projection
+        .iter()
+        .any(|property| property.is_empty() || property == "*")
+    {
+        return None;
+    }
+
+    Some(projection)
+}
+
+/// The name of the class after the `FROM` keyword of a WQL query.
+pub(crate) fn query_class(query: &str) -> Option<&str> {
+    let from = find_from_keyword(query)?;
+
+    query[from + 4..].split_whitespace().next()
+}
+
+/// The offset of the first `FROM` keyword (in any case, and surrounded by any ASCII whitespace) of a WQL query.
+pub(crate) fn find_from_keyword(query: &str) -> Option<usize> {
+    let bytes = query.as_bytes();
+
+    (1..bytes.len().saturating_sub(4)).find(|&idx| {
+        bytes[idx - 1].is_ascii_whitespace()
+            && bytes[idx..idx + 4].eq_ignore_ascii_case(b"FROM")
+            && bytes[idx + 4].is_ascii_whitespace()
+    })
+}
+
+impl ComTransport {
     /// Execute the given query and return an iterator of WMI pointers.
     /// It's better to use the other query methods, since this is relatively low level.
     ///
@@ -271,23 +435,58 @@ impl WMIConnection {
         &self,
         query: impl AsRef<str>,
     ) -> WMIResult<QueryResultEnumerator> {
+        self.check_win32_product(query.as_ref())?;
+
         let query_language = BSTR::from("WQL");
         let query = BSTR::from(query.as_ref());
 
+        let mut flags = WBEM_FLAG_FORWARD_ONLY | WBEM_FLAG_RETURN_IMMEDIATELY;
+
+        if self.ensure_locatable {
+            flags |= WBEM_FLAG_ENSURE_LOCATABLE;
+        }
+
         let enumerator = unsafe {
-            self.svc.ExecQuery(
-                &query_language,
-                &query,
-                WBEM_FLAG_FORWARD_ONLY | WBEM_FLAG_RETURN_IMMEDIATELY,
-                None,
-            )?
+            self.svc
+                .ExecQuery(&query_language, &query, flags, self.ctx())?
         };
 
         trace!("Got enumerator {:?}", enumerator);
 
-        Ok(QueryResultEnumerator::new(self, enumerator))
+        if self.needs_proxy_blanket() {
+            self.apply_proxy_blanket(&enumerator)?;
+        }
+
+        match self.prefetch {
+            Some(batch_size) => QueryResultEnumerator::with_prefetch(self, enumerator, batch_size),
+            None => Ok(QueryResultEnumerator::new(self, enumerator)),
+        }
+    }
+}
+
+impl WbemTransport for ComTransport {
+    fn raw_query<T>(&self, query: &str) -> WMIResult<Vec<T>>
+    where
+        T: de::DeserializeOwned,
+    {
+        let projection = select_projection(query);
+        let enumerator = self.exec_query_native_wrapper(query)?;
+
+        enumerator
+            .map(|item| match item {
+                Ok(wbem_class_obj) => {
+                    wbem_class_obj.into_desr_with_options(&self.de_options, projection.as_deref())
+                }
+                Err(e) => Err(e),
+            })
+            .collect()
     }
+}
 
+impl<C> WMIConnection<C>
+where
+    C: WbemTransport,
+{
     /// Execute a free-text query and deserialize the results.
     /// Can be used either with a struct (like `query` and `filtered_query`),
     /// but also with a generic map.
@@ -301,18 +500,22 @@ impl WMIConnection {
     /// #   Ok(())
     /// # }
     /// ```
+    ///
+    /// Tuples are also supported, in which case the elements are mapped to the selected properties in order:
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// # let con = WMIConnection::new(COMLibrary::new()?)?;
+    /// let procs: Vec<(String, u32)> = con.raw_query("SELECT Name, ProcessId FROM Win32_Process")?;
+    /// #   Ok(())
+    /// # }
+    /// ```
     pub fn raw_query<T>(&self, query: impl AsRef<str>) -> WMIResult<Vec<T>>
     where
         T: de::DeserializeOwned,
     {
-        let enumerator = self.exec_query_native_wrapper(query)?;
-
-        enumerator
-            .map(|item| match item {
-                Ok(wbem_class_obj) => wbem_class_obj.into_desr(),
-                Err(e) => Err(e),
-            })
-            .collect()
+        self.transport.raw_query(query.as_ref())
     }
 
     /// Query all the objects of type T.
@@ -333,6 +536,9 @@ impl WMIConnection {
     /// #   Ok(())
     /// # }
     /// ```
+    ///
+    /// The query is built from the name and fields of the struct, which serde doesn't expose for structs
+    /// with `#[serde(flatten)]` fields: these return an error, and must be used with [`raw_query`](WMIConnection::raw_query) instead.
     pub fn query<T>(&self) -> WMIResult<Vec<T>>
     where
         T: de::DeserializeOwned,
@@ -400,6 +606,59 @@ impl WMIConnection {
 
         results.into_iter().next().ok_or(WMIError::ResultEmpty)
     }
+}
+
+impl WMIConnection {
+    /// Count the instances of the class of `T` (which is usually a unit struct),
+    /// without reading any of their properties.
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// # let con = WMIConnection::new(COMLibrary::new()?)?;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// #[serde(rename = "Win32_Process")]
+    /// struct Process;
+    ///
+    /// let count = con.count::<Process>()?;
+    /// assert!(count > 0);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn count<T>(&self) -> WMIResult<usize>
+    where
+        T: de::DeserializeOwned,
+    {
+        self.filtered_count::<T>(&HashMap::new())
+    }
+
+    /// Count the instances of the class of `T` which match the given filters.
+    pub fn filtered_count<T>(&self, filters: &HashMap<String, FilterValue>) -> WMIResult<usize>
+    where
+        T: de::DeserializeOwned,
+    {
+        let query_text = build_query::<T>(Some(filters))?;
+
+        self.exec_query_native_wrapper(query_text)?
+            .try_fold(0, |count, item| item.map(|_| count + 1))
+    }
+
+    /// Check if there are any instances of the class of `T` which match the given filters.
+    ///
+    /// Stops the query after the first instance is returned.
+    pub fn exists<T>(&self, filters: &HashMap<String, FilterValue>) -> WMIResult<bool>
+    where
+        T: de::DeserializeOwned,
+    {
+        let query_text = build_query::<T>(Some(filters))?;
+
+        self.exec_query_native_wrapper(query_text)?
+            .next()
+            .transpose()
+            .map(|first| first.is_some())
+    }
 
     /// Get a WMI object by path, and return a wrapper around a WMI pointer.
     /// It's better to use the `get_by_path` method, since this function is more low level.
@@ -423,6 +682,8 @@ impl WMIConnection {
     /// # }
     /// ```
     pub fn get_raw_by_path(&self, object_path: impl AsRef<str>) -> WMIResult<IWbemClassWrapper> {
+        self.check_win32_product_path(object_path.as_ref())?;
+
         let object_path = BSTR::from(object_path.as_ref());
 
         let mut pcls_obj = None;
@@ -431,7 +692,7 @@ impl WMIConnection {
             self.svc.GetObject(
                 &object_path,
                 WBEM_FLAG_RETURN_WBEM_COMPLETE.0 as _,
-                None,
+                self.ctx(),
                 Some(&mut pcls_obj),
                 None,
             )?;
@@ -531,7 +792,7 @@ impl WMIConnection {
     {
         let wbem_class_obj = self.get_raw_by_path(object_path)?;
 
-        from_wbem_class_obj(wbem_class_obj)
+        wbem_class_obj.into_desr_with_options(&self.de_options, None)
     }
 
     /// Query all the associators of type T of the given object.
@@ -703,6 +964,111 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn it_builds_correct_query_for_unit_struct() {
+        #[derive(Deserialize, Debug)]
+        struct Win32_Process;
+
+        let query = build_query::<Win32_Process>(None).unwrap();
+
+        assert_eq!(query, "SELECT __CLASS FROM Win32_Process ");
+    }
+
+    #[test]
+    fn it_can_count_with_unit_struct() {
+        let wmi_con = wmi_con();
+
+        #[derive(Deserialize, Debug)]
+        struct Win32_OperatingSystem;
+
+        #[derive(Deserialize, Debug)]
+        struct Win32_Process;
+
+        assert_eq!(wmi_con.count::<Win32_OperatingSystem>().unwrap(), 1);
+
+        let results: Vec<Win32_Process> = wmi_con.query().unwrap();
+        assert!(!results.is_empty());
+
+        let mut filters = HashMap::new();
+        filters.insert(
+            "ProcessId".to_owned(),
+            FilterValue::Number(std::process::id() as i64),
+        );
+        assert!(wmi_con.exists::<Win32_Process>(&filters).unwrap());
+
+        filters.insert("Name".to_owned(), FilterValue::Str("no-such-process.exe"));
+        assert!(!wmi_con.exists::<Win32_Process>(&filters).unwrap());
+        assert_eq!(
+            wmi_con.filtered_count::<Win32_Process>(&filters).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn it_finds_the_query_class() {
+        assert_eq!(
+            query_class("SELECT * FROM Win32_Process WHERE ProcessId = 4"),
+            Some("Win32_Process")
+        );
+        assert_eq!(
+            query_class("select Name from Win32_Service"),
+            Some("Win32_Service")
+        );
+        assert_eq!(
+            query_class("SELECT *\n\tFROM\tWin32_Service\nWHERE Name = 'a'"),
+            Some("Win32_Service")
+        );
+        assert_eq!(query_class("SELECT *"), None);
+        assert_eq!(query_class("SELECT * FROMWin32_Service"), None);
+    }
+
+    #[test]
+    fn it_parses_select_projection() {
+        assert_eq!(
+            select_projection("SELECT Name,ProcessId FROM Win32_Process WHERE Name = 'a, b'"),
+            Some(vec!["Name".to_owned(), "ProcessId".to_owned()])
+        );
+        assert_eq!(
+            select_projection("  select Caption from Win32_OperatingSystem"),
+            Some(vec!["Caption".to_owned()])
+        );
+        assert_eq!(
+            select_projection("SELECT\n    Name,\n    ProcessId\nFROM\n    Win32_Process"),
+            Some(vec!["Name".to_owned(), "ProcessId".to_owned()])
+        );
+        assert_eq!(select_projection("SELECT * FROM Win32_Process"), None);
+        assert_eq!(
+            select_projection("ASSOCIATORS OF {Win32_Group.Name='a'}"),
+            None
+        );
+        assert_eq!(select_projection("SELECT Name"), None);
+    }
+
+    #[test]
+    fn it_can_query_tuples() {
+        let wmi_con = wmi_con();
+
+        let results: Vec<(String, u32)> = wmi_con
+            .raw_query("SELECT Name, ProcessId FROM Win32_Process")
+            .unwrap();
+        assert!(results.iter().any(|(_, pid)| *pid == std::process::id()));
+
+        #[allow(dead_code)]
+        #[derive(Deserialize, Debug)]
+        struct NameAndCaption(String, String);
+
+        let results: Vec<NameAndCaption> = wmi_con
+            .raw_query("SELECT Name, Caption FROM Win32_OperatingSystem")
+            .unwrap();
+        assert_eq!(results.len(), 1);
+
+        let result = wmi_con.raw_query::<(String, u32)>("SELECT * FROM Win32_Process");
+        assert!(result.is_err());
+
+        let result = wmi_con.raw_query::<(String,)>("SELECT Name, ProcessId FROM Win32_Process");
+        assert!(result.is_err());
+    }
+
     #[test]
     fn it_builds_correct_query_without_filters() {
         #[derive(Deserialize, Debug)]
@@ -802,6 +1168,70 @@ mod tests {
         assert_eq!(query, select_part + within_part + where_part);
     }
 
+    #[test]
+    fn it_builds_correct_aggregate_notification_query() {
+        #[derive(Deserialize, Debug)]
+        struct __InstanceCreationEvent {
+            #[allow(dead_code)]
+            TargetInstance: HashMap<String, Variant>,
+        }
+
+        let mut filters = HashMap::new();
+        filters.insert(
+            "TargetInstance".to_owned(),
+            FilterValue::IsA("Win32_Process"),
+        );
+
+        let group = GroupWithin::new(Duration::from_secs(10)).by("TargetInstance.Name");
+
+        let query = build_aggregate_notification_query::<__InstanceCreationEvent>(
+            Some(&filters),
+            Some(Duration::from_secs(1)),
+            &group,
+        )
+        .unwrap();
+
+        assert_eq!(
+            query,
+            r#"SELECT * FROM __InstanceCreationEvent WITHIN 1 WHERE TargetInstance ISA "Win32_Process" GROUP WITHIN 10 BY TargetInstance.Name"#
+        );
+
+        let query = build_aggregate_notification_query::<__InstanceCreationEvent>(
+            None,
+            None,
+            &GroupWithin::new(Duration::from_millis(2500)),
+        )
+        .unwrap();
+
+        assert_eq!(
+            query,
+            "SELECT * FROM __InstanceCreationEvent GROUP WITHIN 2.5"
+        );
+    }
+
+    #[test]
+    fn it_builds_correct_aggregate_notification_query_with_having() {
+        #[derive(Deserialize, Debug)]
+        struct __InstanceModificationEvent {
+            #[allow(dead_code)]
+            TargetInstance: HashMap<String, Variant>,
+        }
+
+        let group = GroupWithin::new(Duration::from_secs(60))
+            .by("TargetInstance.Name")
+            .having_at_least(5)
+            .having("NumberOfEvents < 100");
+
+        let query =
+            build_aggregate_notification_query::<__InstanceModificationEvent>(None, None, &group)
+                .unwrap();
+
+        assert_eq!(
+            query,
+            "SELECT * FROM __InstanceModificationEvent GROUP WITHIN 60 BY TargetInstance.Name HAVING NumberOfEvents >= 5 AND NumberOfEvents < 100"
+        );
+    }
+
     #[test]
     fn it_can_filter() {
         let wmi_con = wmi_con();
@@ -826,7 +1256,7 @@ mod tests {
 
     #[test]
     fn it_can_query_all_classes() {
-        let wmi_con = wmi_con();
+        let wmi_con = wmi_con().with_win32_product_allowed(true);
         let classes = [
             "CIM_ComputerSystem",
             "Win32_Service",
@@ -0,0 +1,168 @@
+//! Measure the cost of queries, and detect queries which are too heavy for the provider host.
+//!
+//! Providers run in a `WmiPrvSE.exe` host process, which is limited by the quotas of
+//! [`__ProviderHostQuotaConfiguration`]. A query which exceeds these limits fails with `WBEM_E_QUOTA_VIOLATION`
+//! (see [`WMIError::is_quota_violation`]).
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # use std::collections::HashMap;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! let (results, stats): (Vec<HashMap<String, Variant>>, _) =
+//!     con.raw_query_with_stats("SELECT Name FROM Win32_Process")?;
+//!
+//! assert_eq!(stats.rows, results.len());
+//! println!("Enumerated in {:?}, deserialized in {:?}", stats.enumeration_time, stats.deserialize_time);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`__ProviderHostQuotaConfiguration`]: https://docs.microsoft.com/en-us/windows/win32/wmisdk/--providerhostquotaconfiguration
+use crate::{connection::WMIConnection, query::select_projection, WMIError, WMIResult};
+use serde::{de, Deserialize};
+use std::time::{Duration, Instant};
+use windows::Win32::System::Wmi::WBEM_E_QUOTA_VIOLATION;
+
+/// Measurements of a single query, as returned by [`WMIConnection::raw_query_with_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueryStats {
+    /// The number of returned objects.
+    pub rows: usize,
+    /// The time spent in `ExecQuery` and waiting for the provider to return objects.
+    pub enumeration_time: Duration,
+    /// The time spent deserializing the returned objects.
+    pub deserialize_time: Duration,
+}
+
+impl QueryStats {
+    pub fn total_time(&self) -> Duration {
+        self.enumeration_time + self.deserialize_time
+    }
+}
+
+/// The limits of the provider host processes, read from `__ProviderHostQuotaConfiguration`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename = "__ProviderHostQuotaConfiguration")]
+#[serde(rename_all = "PascalCase")]
+pub struct ProviderHostQuotas {
+    /// The maximum private memory (in bytes) of a single provider host.
+    pub memory_per_host: u64,
+    /// The maximum private memory (in bytes) of all the provider hosts combined.
+    pub memory_all_hosts: u64,
+    /// The maximum number of threads of a single provider host.
+    pub threads_per_host: u32,
+    /// The maximum number of handles of a single provider host.
+    pub handles_per_host: u32,
+    /// The maximum number of provider host processes.
+    pub process_limit_all_hosts: u32,
+}
+
+impl WMIError {
+    /// Whether this error was caused by a provider host exceeding its quotas
+    /// (see [`WMIConnection::provider_host_quotas`]), usually because the query returns too much data.
+    pub fn is_quota_violation(&self) -> bool {
+        matches!(self, WMIError::HResultError { hres } if *hres == WBEM_E_QUOTA_VIOLATION.0)
+    }
+}
+
+///
+/// ### Additional query measurement methods
+///
+impl WMIConnection {
+    /// Like [`raw_query`](Self::raw_query), but also measure the query.
+    ///
+    /// See the [module level documentation](crate::query_stats) for an example.
+    pub fn raw_query_with_stats<T>(&self, query: impl AsRef<str>) -> WMIResult<(Vec<T>, QueryStats)>
+    where
+        T: de::DeserializeOwned,
+    {
+        let projection = select_projection(query.as_ref());
+        let mut stats = QueryStats::default();
+
+        let start = Instant::now();
+        let mut enumerator = self.exec_query_native_wrapper(query)?;
+        stats.enumeration_time += start.elapsed();
+
+        let mut results = vec![];
+
+        loop {
+            let start = Instant::now();
+            let item = enumerator.next();
+            stats.enumeration_time += start.elapsed();
+
+            let wbem_class_obj = match item {
+                Some(item) => item?,
+                None => break,
+            };
+
+            let start = Instant::now();
+            let result =
+                wbem_class_obj.into_desr_with_options(&self.de_options, projection.as_deref())?;
+            stats.deserialize_time += start.elapsed();
+
+            results.push(result);
+        }
+
+        stats.rows = results.len();
+
+        Ok((results, stats))
+    }
+
+    /// Read the quotas of the provider host processes, which are configured in the `ROOT` namespace.
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// # let con = WMIConnection::new(COMLibrary::new()?)?;
+    /// let quotas = con.provider_host_quotas()?;
+    /// assert!(quotas.memory_per_host > 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn provider_host_quotas(&self) -> WMIResult<ProviderHostQuotas> {
+        self.with_namespace("ROOT")?
+            .get_by_path("__ProviderHostQuotaConfiguration=@")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::fixtures::*;
+    use crate::Variant;
+    use std::collections::HashMap;
+
+    #[test]
+    fn it_measures_queries() {
+        let wmi_con = wmi_con();
+
+        let (results, stats): (Vec<HashMap<String, Variant>>, _) = wmi_con
+            .raw_query_with_stats("SELECT Name FROM Win32_Process")
+            .unwrap();
+
+        assert!(stats.rows > 0);
+        assert_eq!(stats.rows, results.len());
+        assert!(stats.total_time() >= stats.enumeration_time);
+    }
+
+    #[test]
+    fn it_can_query_with_ensure_locatable() {
+        let wmi_con = wmi_con().with_ensure_locatable(true);
+
+        let results: Vec<HashMap<String, Variant>> = wmi_con
+            .raw_query("SELECT Name FROM Win32_OperatingSystem")
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn it_can_read_provider_host_quotas() {
+        let wmi_con = wmi_con();
+
+        let quotas = wmi_con.provider_host_quotas().unwrap();
+
+        assert!(quotas.memory_all_hosts >= quotas.memory_per_host);
+        assert!(quotas.process_limit_all_hosts > 0);
+    }
+}
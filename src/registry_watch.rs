@@ -0,0 +1,337 @@
+//! Watch registry keys and values change, using the registry events of the `ROOT\DEFAULT` namespace.
+//!
+//! The registry provider can only watch the `HKEY_LOCAL_MACHINE`, `HKEY_USERS` and `HKEY_CURRENT_CONFIG` hives
+//! (the keys of the current user are under `HKEY_USERS\<SID>`). Events only tell which key or value changed, so the new
+//! values must be read separately (for example, using the `StdRegProv` class).
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use wmi::registry_watch::{RegistryHive, RegistryWatch};
+//!
+//! let watcher = con.registry_watcher()?;
+//!
+//! let watch = RegistryWatch::new(RegistryHive::LocalMachine, r"SOFTWARE\Policies").recursive(true);
+//!
+//! for change in watcher.changes(&watch)? {
+//!     println!("{} changed", change?.key_path);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Watching a key requires the permission to read it: [`WMIError::RegistryAccessDenied`] is returned when a
+//! key (like `HKEY_LOCAL_MACHINE\SECURITY`) can only be read by an elevated process, and
+//! [`WMIError::RegistryKeyNotFound`] when the key doesn't exist (yet).
+use crate::{connection::WMIConnection, query::quote_and_escape_wql_str, WMIError, WMIResult};
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use windows::Win32::System::Wmi::{WBEM_E_ACCESS_DENIED, WBEM_E_NOT_FOUND};
+
+/// The namespace of the registry events.
+pub const REGISTRY_EVENTS_NAMESPACE: &str = "ROOT\\DEFAULT";
+
+/// A registry hive which can be watched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RegistryHive {
+    LocalMachine,
+    Users,
+    CurrentConfig,
+}
+
+impl RegistryHive {
+    /// The name of the hive, like `HKEY_LOCAL_MACHINE`.
+    pub fn name(self) -> &'static str {
+        match self {
+            RegistryHive::LocalMachine => "HKEY_LOCAL_MACHINE",
+            RegistryHive::Users => "HKEY_USERS",
+            RegistryHive::CurrentConfig => "HKEY_CURRENT_CONFIG",
+        }
+    }
+}
+
+/// The registry key (or value) to watch, see [`RegistryWatcher::changes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryWatch {
+    hive: RegistryHive,
+    key_path: String,
+    recursive: bool,
+    value_name: Option<String>,
+}
+
+impl RegistryWatch {
+    /// Watch the values of a key, like `SOFTWARE\Microsoft\Windows\CurrentVersion\Run`.
+    pub fn new(hive: RegistryHive, key_path: impl Into<String>) -> Self {
+        Self {
+            hive,
+            key_path: key_path.into().trim_matches('\\').to_owned(),
+            recursive: false,
+            value_name: None,
+        }
+    }
+
+    /// Also watch the subkeys of the key, and their values (using `RegistryTreeChangeEvent`).
+    ///
+    /// Ignored when watching a single [`value`](RegistryWatch::value).
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Only watch a value of the key (using `RegistryValueChangeEvent`).
+    pub fn value(mut self, value_name: impl Into<String>) -> Self {
+        self.value_name = Some(value_name.into());
+        self
+    }
+
+    /// The full path of the key, like `HKEY_LOCAL_MACHINE\SOFTWARE`.
+    pub fn full_key_path(&self) -> String {
+        if self.key_path.is_empty() {
+            self.hive.name().to_owned()
+        } else {
+            format!("{}\\{}", self.hive.name(), self.key_path)
+        }
+    }
+
+    fn query(&self) -> String {
+        let hive = quote_and_escape_wql_str(self.hive.name());
+        let key_path = quote_and_escape_wql_str(&self.key_path);
+
+        match (&self.value_name, self.recursive) {
+            (Some(value_name), _) => format!(
+                "SELECT * FROM RegistryValueChangeEvent WHERE Hive = {} AND KeyPath = {} AND ValueName = {}",
+                hive,
+                key_path,
+                quote_and_escape_wql_str(value_name)
+            ),
+            (None, true) => format!(
+                "SELECT * FROM RegistryTreeChangeEvent WHERE Hive = {} AND RootPath = {}",
+                hive, key_path
+            ),
+            (None, false) => format!(
+                "SELECT * FROM RegistryKeyChangeEvent WHERE Hive = {} AND KeyPath = {}",
+                hive, key_path
+            ),
+        }
+    }
+
+    /// Replace the errors of the registry provider with structured errors.
+    fn map_err(&self, err: WMIError) -> WMIError {
+        match err {
+            WMIError::HResultError { hres } if hres == WBEM_E_ACCESS_DENIED.0 => {
+                WMIError::RegistryAccessDenied(self.full_key_path())
+            }
+            WMIError::HResultError { hres } if hres == WBEM_E_NOT_FOUND.0 => {
+                WMIError::RegistryKeyNotFound(self.full_key_path())
+            }
+            err => err,
+        }
+    }
+}
+
+/// A change in the registry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryChange {
+    /// Like `HKEY_LOCAL_MACHINE`.
+    pub hive: String,
+    /// The watched key (the root key for recursive watches, since the changed subkey is not reported).
+    pub key_path: String,
+    /// The changed value, when watching a single value.
+    pub value_name: Option<String>,
+    /// When the change happened, in 100-nanosecond intervals since January 1, 1601 (UTC).
+    /// See [`filetime_to_system_time`](crate::event_time::filetime_to_system_time).
+    pub time_created: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TreeChange {
+    hive: String,
+    root_path: String,
+    #[serde(rename = "TIME_CREATED")]
+    time_created: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct KeyChange {
+    hive: String,
+    key_path: String,
+    #[serde(rename = "TIME_CREATED")]
+    time_created: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ValueChange {
+    hive: String,
+    key_path: String,
+    value_name: String,
+    #[serde(rename = "TIME_CREATED")]
+    time_created: Option<u64>,
+}
+
+#[derive(Deserialize)]
+enum RawRegistryEvent {
+    #[serde(rename = "RegistryTreeChangeEvent")]
+    Tree(TreeChange),
+    #[serde(rename = "RegistryKeyChangeEvent")]
+    Key(KeyChange),
+    #[serde(rename = "RegistryValueChangeEvent")]
+    Value(ValueChange),
+}
+
+impl From<RawRegistryEvent> for RegistryChange {
+    fn from(event: RawRegistryEvent) -> Self {
+        match event {
+            RawRegistryEvent::Tree(change) => RegistryChange {
+                hive: change.hive,
+                key_path: change.root_path,
+                value_name: None,
+                time_created: change.time_created,
+            },
+            RawRegistryEvent::Key(change) => RegistryChange {
+                hive: change.hive,
+                key_path: change.key_path,
+                value_name: None,
+                time_created: change.time_created,
+            },
+            RawRegistryEvent::Value(change) => RegistryChange {
+                hive: change.hive,
+                key_path: change.key_path,
+                value_name: Some(change.value_name),
+                time_created: change.time_created,
+            },
+        }
+    }
+}
+
+/// Watches the registry, using a connection to the `ROOT\DEFAULT` namespace.
+pub struct RegistryWatcher {
+    con: WMIConnection,
+}
+
+///
+/// ### Additional registry watch methods
+///
+impl WMIConnection {
+    /// Connect to the namespace of the registry events on the same computer.
+    ///
+    /// See the [module level documentation](crate::registry_watch) for an example.
+    pub fn registry_watcher(&self) -> WMIResult<RegistryWatcher> {
+        Ok(RegistryWatcher {
+            con: self.with_namespace(REGISTRY_EVENTS_NAMESPACE)?,
+        })
+    }
+}
+
+impl RegistryWatcher {
+    /// The connection to the `ROOT\DEFAULT` namespace, to use other classes (like `StdRegProv`).
+    pub fn connection(&self) -> &WMIConnection {
+        &self.con
+    }
+
+    /// Subscribe to the changes of a registry key (or value).
+    pub fn changes<'a>(
+        &'a self,
+        watch: &RegistryWatch,
+    ) -> WMIResult<impl Iterator<Item = WMIResult<RegistryChange>> + 'a> {
+        let watch = watch.clone();
+
+        let changes = self
+            .con
+            .raw_notification::<RawRegistryEvent>(watch.query())
+            .map_err(|err| watch.map_err(err))?;
+
+        Ok(changes.map(move |change| {
+            change
+                .map(RegistryChange::from)
+                .map_err(|err| watch.map_err(err))
+        }))
+    }
+
+    /// Like [`changes`](RegistryWatcher::changes), returning a stream of changes.
+    pub fn async_changes(
+        &self,
+        watch: &RegistryWatch,
+    ) -> WMIResult<impl Stream<Item = WMIResult<RegistryChange>>> {
+        let watch = watch.clone();
+
+        let changes = self
+            .con
+            .async_raw_notification::<RawRegistryEvent>(watch.query())
+            .map_err(|err| watch.map_err(err))?;
+
+        Ok(changes.map(move |change| {
+            change
+                .map(RegistryChange::from)
+                .map_err(|err| watch.map_err(err))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use std::time::Duration;
+
+    #[test]
+    fn it_builds_registry_queries() {
+        let watch = RegistryWatch::new(RegistryHive::LocalMachine, r"\SOFTWARE\Policies\");
+        assert_eq!(
+            watch.full_key_path(),
+            r"HKEY_LOCAL_MACHINE\SOFTWARE\Policies"
+        );
+        assert_eq!(
+            watch.query(),
+            r#"SELECT * FROM RegistryKeyChangeEvent WHERE Hive = "HKEY_LOCAL_MACHINE" AND KeyPath = "SOFTWARE\\Policies""#
+        );
+
+        assert_eq!(
+            watch.clone().recursive(true).query(),
+            r#"SELECT * FROM RegistryTreeChangeEvent WHERE Hive = "HKEY_LOCAL_MACHINE" AND RootPath = "SOFTWARE\\Policies""#
+        );
+
+        assert_eq!(
+            watch.recursive(true).value("Enabled").query(),
+            r#"SELECT * FROM RegistryValueChangeEvent WHERE Hive = "HKEY_LOCAL_MACHINE" AND KeyPath = "SOFTWARE\\Policies" AND ValueName = "Enabled""#
+        );
+
+        let watch = RegistryWatch::new(RegistryHive::Users, "");
+        assert_eq!(watch.full_key_path(), "HKEY_USERS");
+    }
+
+    #[test]
+    fn it_maps_registry_errors() {
+        let watch = RegistryWatch::new(RegistryHive::LocalMachine, "SECURITY");
+
+        assert!(matches!(
+            watch.map_err(WMIError::HResultError {
+                hres: WBEM_E_ACCESS_DENIED.0
+            }),
+            WMIError::RegistryAccessDenied(key) if key == r"HKEY_LOCAL_MACHINE\SECURITY"
+        ));
+        assert!(matches!(
+            watch.map_err(WMIError::Timeout),
+            WMIError::Timeout
+        ));
+    }
+
+    #[test]
+    fn it_watches_registry_keys() {
+        let mut wmi_con = wmi_con();
+        wmi_con.timeout = Some(Duration::from_secs(1));
+
+        let watcher = wmi_con.registry_watcher().unwrap();
+        let watch = RegistryWatch::new(
+            RegistryHive::LocalMachine,
+            r"SOFTWARE\Microsoft\Windows NT\CurrentVersion",
+        );
+
+        let mut changes = watcher.changes(&watch).unwrap();
+
+        assert!(matches!(changes.next(), Some(Err(WMIError::Timeout))));
+    }
+}
@@ -1,6 +1,13 @@
+#[cfg(feature = "leak-check")]
+use crate::leak_check::Tracked;
 use crate::{
-    connection::WMIConnection, de::wbem_class_de::from_wbem_class_obj,
-    safearray::safe_array_to_vec_of_strings, Variant, WMIError, WMIResult,
+    connection::ComTransport,
+    de::options::DeserializeOptions,
+    de::wbem_class_de::{from_wbem_class_obj, from_wbem_class_obj_with_projection, Deserializer},
+    prefetch::Prefetcher,
+    safe_variant::SafeVariant,
+    safearray::{safe_array_to_vec_of_strings, safe_array_to_vec_of_wide},
+    Variant, WMIError, WMIResult,
 };
 use log::trace;
 use serde::{
@@ -8,27 +15,39 @@ use serde::{
     ser::{Error, SerializeMap},
     Serialize,
 };
-use std::{convert::TryInto, ptr};
+use std::{convert::TryInto, ptr, time::Duration};
 use windows::core::{HSTRING, PCWSTR};
-use windows::Win32::System::Com::VARIANT;
-use windows::Win32::System::Ole::{SafeArrayDestroy, VariantClear};
+use windows::Win32::System::Com::{VARENUM, VT_ARRAY, VT_BSTR};
+use windows::Win32::System::Ole::SafeArrayDestroy;
 use windows::Win32::System::Wmi::{
     IEnumWbemClassObject, IWbemClassObject, CIMTYPE_ENUMERATION, WBEM_FLAG_ALWAYS,
-    WBEM_FLAG_NONSYSTEM_ONLY, WBEM_INFINITE,
+    WBEM_FLAG_NONSYSTEM_ONLY, WBEM_INFINITE, WBEM_S_TIMEDOUT,
 };
 
 /// A wrapper around a raw pointer to IWbemClassObject, which also takes care of releasing
 /// the object when dropped.
 ///
-#[repr(transparent)]
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Cloning the wrapper shares the same object, see [`IWbemClassWrapper::deep_clone`] for a copy.
+/// Wrappers are equal when their objects are equal according to WMI, see [`IWbemClassWrapper::compare_to`].
+///
+/// The wrapper is `repr(transparent)` over [`IWbemClassObject`] unless the `leak-check` feature is enabled,
+/// in which case it also holds a token which counts the live objects.
+///
+#[cfg_attr(not(feature = "leak-check"), repr(transparent))]
+#[derive(Clone, Debug)]
 pub struct IWbemClassWrapper {
     pub inner: IWbemClassObject,
+    #[cfg(feature = "leak-check")]
+    _tracked: Tracked,
 }
 
 impl IWbemClassWrapper {
     pub fn new(inner: IWbemClassObject) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            #[cfg(feature = "leak-check")]
+            _tracked: Tracked::new(),
+        }
     }
 
     /// Return the names of all the properties of the given object.
@@ -50,9 +69,18 @@ impl IWbemClassWrapper {
     }
 
     pub fn get_property(&self, property_name: &str) -> WMIResult<Variant> {
+        self.get_property_with_type(property_name)
+            .map(|(value, _)| value)
+    }
+
+    /// Like [`get_property`](Self::get_property), but also returns the CIM type of the property.
+    pub fn get_property_with_type(
+        &self,
+        property_name: &str,
+    ) -> WMIResult<(Variant, CIMTYPE_ENUMERATION)> {
         let name_prop = HSTRING::from(property_name);
 
-        let mut vt_prop = VARIANT::default();
+        let mut vt_prop = SafeVariant::new();
 
         let mut cim_type = 0;
 
@@ -60,18 +88,84 @@ impl IWbemClassWrapper {
             self.inner.Get(
                 PCWSTR::from_raw(name_prop.as_ptr()),
                 0,
-                &mut vt_prop,
+                vt_prop.as_mut_ptr(),
                 Some(&mut cim_type),
                 None,
             )?;
+        }
+
+        let cim_type = CIMTYPE_ENUMERATION(cim_type);
+        let property_value = vt_prop.to_variant()?.convert_into_cim_type(cim_type)?;
+
+        Ok((property_value, cim_type))
+    }
+
+    /// Return the raw UTF-16 data of a string property, which might not be valid UTF-16.
+    ///
+    /// Returns `None` if the property is not a string (for example, if it is null).
+    pub fn get_property_utf16(&self, property_name: &str) -> WMIResult<Option<Vec<u16>>> {
+        let name_prop = HSTRING::from(property_name);
+
+        let mut vt_prop = SafeVariant::new();
+
+        unsafe {
+            self.inner.Get(
+                PCWSTR::from_raw(name_prop.as_ptr()),
+                0,
+                vt_prop.as_mut_ptr(),
+                None,
+                None,
+            )?;
+        }
+
+        let wide = if vt_prop.vt() == VT_BSTR {
+            Some(
+                unsafe {
+                    vt_prop
+                        .as_raw()
+                        .Anonymous
+                        .Anonymous
+                        .Anonymous
+                        .bstrVal
+                        .as_wide()
+                }
+                .to_vec(),
+            )
+        } else {
+            None
+        };
+
+        Ok(wide)
+    }
+
+    /// Like [`get_property_utf16`](Self::get_property_utf16), for a property which is an array of strings.
+    ///
+    /// Returns `None` if the property is not an array of strings.
+    pub fn get_property_utf16_array(
+        &self,
+        property_name: &str,
+    ) -> WMIResult<Option<Vec<Vec<u16>>>> {
+        let name_prop = HSTRING::from(property_name);
 
-            let property_value = Variant::from_variant(&vt_prop)?
-                .convert_into_cim_type(CIMTYPE_ENUMERATION(cim_type))?;
+        let mut vt_prop = SafeVariant::new();
 
-            VariantClear(&mut vt_prop)?;
+        unsafe {
+            self.inner.Get(
+                PCWSTR::from_raw(name_prop.as_ptr()),
+                0,
+                vt_prop.as_mut_ptr(),
+                None,
+                None,
+            )?;
+        }
 
-            Ok(property_value)
+        if vt_prop.vt() != VARENUM(VT_ARRAY.0 | VT_BSTR.0) {
+            return Ok(None);
         }
+
+        let array = unsafe { &*vt_prop.as_raw().Anonymous.Anonymous.Anonymous.parray };
+
+        safe_array_to_vec_of_wide(array).map(Some)
     }
 
     pub fn path(&self) -> WMIResult<String> {
@@ -88,6 +182,33 @@ impl IWbemClassWrapper {
     {
         from_wbem_class_obj(self).map_err(WMIError::from)
     }
+
+    /// Like [`into_desr`](Self::into_desr), but tuples are deserialized from the given properties (in order).
+    pub fn into_desr_with_projection<T>(self, projection: &[String]) -> WMIResult<T>
+    where
+        T: de::DeserializeOwned,
+    {
+        from_wbem_class_obj_with_projection(self, projection)
+    }
+
+    /// Deserialize the object using the given options, and the given projection (if any) for tuples.
+    pub fn into_desr_with_options<T>(
+        self,
+        options: &DeserializeOptions,
+        projection: Option<&[String]>,
+    ) -> WMIResult<T>
+    where
+        T: de::DeserializeOwned,
+    {
+        let mut deserializer =
+            Deserializer::from_wbem_class_obj(self).with_options(options.clone());
+
+        if let Some(projection) = projection {
+            deserializer = deserializer.with_projection(projection.to_vec());
+        }
+
+        T::deserialize(&mut deserializer)
+    }
 }
 
 impl Serialize for IWbemClassWrapper {
@@ -98,23 +219,75 @@ impl Serialize for IWbemClassWrapper {
         let properties = self.list_properties().map_err(Error::custom)?;
         let mut s = serializer.serialize_map(Some(properties.len()))?;
         for property in properties.iter() {
-            let value = self.get_property(property).unwrap();
+            let value = self.get_property(property).map_err(Error::custom)?;
             s.serialize_entry(property, &value)?;
         }
         s.end()
     }
 }
 
+/// An iterator over the results of a query.
+///
+/// Each object is owned by the returned [`IWbemClassWrapper`] (and is released when it is dropped),
+/// and the enumerator itself is released as soon as it is exhausted or fails, or when the iterator is dropped.
 pub struct QueryResultEnumerator<'a> {
-    _wmi_con: &'a WMIConnection,
-    p_enumerator: IEnumWbemClassObject,
+    _wmi_con: &'a ComTransport,
+    p_enumerator: Option<IEnumWbemClassObject>,
+    prefetcher: Option<Prefetcher>,
+    /// How long to wait for each object, the timeout of the connection unless it is overridden.
+    timeout: Option<Duration>,
+    #[cfg(feature = "leak-check")]
+    _tracked: Option<Tracked>,
 }
 
 impl<'a> QueryResultEnumerator<'a> {
-    pub fn new(wmi_con: &'a WMIConnection, p_enumerator: IEnumWbemClassObject) -> Self {
+    pub fn new(wmi_con: &'a ComTransport, p_enumerator: IEnumWbemClassObject) -> Self {
         Self {
             _wmi_con: wmi_con,
-            p_enumerator,
+            p_enumerator: Some(p_enumerator),
+            prefetcher: None,
+            timeout: wmi_con.timeout,
+            #[cfg(feature = "leak-check")]
+            _tracked: Some(Tracked::new()),
+        }
+    }
+
+    /// Like [`new`](Self::new), but the results are read in batches of `batch_size` objects on a background thread.
+    ///
+    /// See [`WMIConnection::with_prefetch`](crate::WMIConnection::with_prefetch).
+    pub fn with_prefetch(
+        wmi_con: &'a ComTransport,
+        p_enumerator: IEnumWbemClassObject,
+        batch_size: u32,
+    ) -> WMIResult<Self> {
+        let prefetcher =
+            Prefetcher::spawn(p_enumerator, batch_size, timeout_millis(wmi_con.timeout))?;
+
+        Ok(Self {
+            _wmi_con: wmi_con,
+            p_enumerator: None,
+            prefetcher: Some(prefetcher),
+            timeout: wmi_con.timeout,
+            #[cfg(feature = "leak-check")]
+            _tracked: Some(Tracked::new()),
+        })
+    }
+
+    /// Wait at most `timeout` for each of the next objects (or forever, if `None`), instead of the timeout of the connection.
+    ///
+    /// Like the timeout of the connection, a [`WMIError::Timeout`] is then returned, and the enumerator is kept.
+    /// This has no effect on prefetched results.
+    pub(crate) fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    fn release(&mut self) {
+        self.p_enumerator = None;
+        self.prefetcher = None;
+
+        #[cfg(feature = "leak-check")]
+        {
+            self._tracked = None;
         }
     }
 }
@@ -123,34 +296,173 @@ impl<'a> Iterator for QueryResultEnumerator<'a> {
     type Item = WMIResult<IWbemClassWrapper>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut objs = [None; 1];
-        let mut return_value = 0;
+        if let Some(prefetcher) = self.prefetcher.as_mut() {
+            return match prefetcher.next() {
+                Some(Ok(pcls_ptr)) => Some(Ok(IWbemClassWrapper::new(pcls_ptr))),
+                Some(Err(WMIError::Timeout)) => Some(Err(WMIError::Timeout)),
+                Some(Err(e)) => {
+                    self.release();
+                    Some(Err(e))
+                }
+                None => {
+                    self.release();
+                    None
+                }
+            };
+        }
 
-        let res = unsafe {
-            self.p_enumerator
-                .Next(WBEM_INFINITE, &mut objs, &mut return_value)
-        };
+        let p_enumerator = self.p_enumerator.as_ref()?;
 
-        if let Err(e) = res.ok() {
-            return Some(Err(e.into()));
-        }
+        let next = next_object(p_enumerator, self.timeout);
 
-        if return_value == 0 {
-            return None;
+        // The enumerator is kept after a timeout, so it is possible to continue waiting for results.
+        if matches!(next, None | Some(Err(WMIError::HResultError { .. }))) {
+            self.release();
         }
 
-        trace!(
-            "Got enumerator {:?} and obj {:?}",
-            self.p_enumerator,
-            &objs[0]
-        );
+        next
+    }
+}
+
+/// Wait for the next object of an enumerator, or return `None` when the results are exhausted.
+pub(crate) fn next_object(
+    p_enumerator: &IEnumWbemClassObject,
+    timeout: Option<Duration>,
+) -> Option<WMIResult<IWbemClassWrapper>> {
+    let mut objs = [None; 1];
+    let mut return_value = 0;
+
+    let res = unsafe { p_enumerator.Next(timeout_millis(timeout), &mut objs, &mut return_value) };
+
+    if let Err(e) = res.ok() {
+        return Some(Err(e.into()));
+    }
+
+    if res.0 == WBEM_S_TIMEDOUT.0 {
+        return Some(Err(WMIError::Timeout));
+    }
+
+    if return_value == 0 {
+        return None;
+    }
+
+    trace!("Got enumerator {:?} and obj {:?}", p_enumerator, &objs[0]);
 
-        let mut objs = objs.into_iter();
-        let pcls_ptr = objs.next().unwrap().ok_or(WMIError::NullPointerResult);
+    let mut objs = objs.into_iter();
+    let pcls_ptr = objs.next().unwrap().ok_or(WMIError::NullPointerResult);
 
-        match pcls_ptr {
-            Err(e) => Some(Err(e)),
-            Ok(pcls_ptr) => Some(Ok(IWbemClassWrapper::new(pcls_ptr))),
+    match pcls_ptr {
+        Err(e) => Some(Err(e)),
+        Ok(pcls_ptr) => Some(Ok(IWbemClassWrapper::new(pcls_ptr))),
+    }
+}
+
+/// A timeout, as passed to `IEnumWbemClassObject::Next`.
+fn timeout_millis(timeout: Option<Duration>) -> i32 {
+    timeout.map_or(WBEM_INFINITE, |timeout| {
+        timeout.as_millis().min(i32::MAX as u128) as i32
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{fixtures::*, ref_count};
+    use crate::Variant;
+    use std::collections::HashMap;
+
+    #[test]
+    fn it_releases_the_enumerator_when_exhausted() {
+        let wmi_con = wmi_con();
+
+        let mut enumerator = wmi_con
+            .exec_query_native_wrapper("SELECT Name FROM Win32_OperatingSystem")
+            .unwrap();
+        let p_enumerator = enumerator.p_enumerator.clone().unwrap();
+        let refs = ref_count(&p_enumerator);
+
+        let os = enumerator.next().unwrap().unwrap();
+        assert_eq!(ref_count(&p_enumerator), refs);
+
+        assert!(enumerator.next().is_none());
+        assert_eq!(ref_count(&p_enumerator), refs - 1);
+        assert!(enumerator.next().is_none());
+
+        let inner = os.inner.clone();
+        let _os: HashMap<String, Variant> = os.into_desr().unwrap();
+        assert_eq!(ref_count(&inner), 1);
+    }
+
+    #[test]
+    fn it_releases_the_enumerator_on_early_exit() {
+        let wmi_con = wmi_con();
+
+        let mut enumerator = wmi_con
+            .exec_query_native_wrapper("SELECT Name FROM Win32_Process")
+            .unwrap();
+        let p_enumerator = enumerator.p_enumerator.clone().unwrap();
+        let refs = ref_count(&p_enumerator);
+
+        let proc = enumerator.next().unwrap().unwrap();
+        let inner = proc.inner.clone();
+        drop(proc);
+        assert_eq!(ref_count(&inner), 1);
+
+        drop(enumerator);
+        assert_eq!(ref_count(&p_enumerator), refs - 1);
+    }
+
+    #[test]
+    fn it_can_prefetch_results() {
+        let wmi_con = wmi_con();
+
+        let expected: Vec<HashMap<String, Variant>> = wmi_con
+            .raw_query("SELECT ProcessId FROM Win32_Process WHERE ProcessId = 0 OR ProcessId = 4")
+            .unwrap();
+
+        for batch_size in [1, 2, 64] {
+            let results: Vec<HashMap<String, Variant>> = wmi_con
+                .with_prefetch(Some(batch_size))
+                .raw_query(
+                    "SELECT ProcessId FROM Win32_Process WHERE ProcessId = 0 OR ProcessId = 4",
+                )
+                .unwrap();
+
+            assert_eq!(results, expected);
         }
+
+        let procs: Vec<HashMap<String, Variant>> = wmi_con
+            .with_prefetch(Some(16))
+            .raw_query("SELECT Name FROM Win32_Process")
+            .unwrap();
+        assert!(procs.len() > 2);
+    }
+
+    #[test]
+    fn it_serializes_objects_deterministically() {
+        let wmi_con = wmi_con();
+
+        let os = wmi_con
+            .exec_query_native_wrapper("SELECT * FROM Win32_OperatingSystem")
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+
+        let mut first = vec![];
+        ciborium::ser::into_writer(&os, &mut first).unwrap();
+        let mut second = vec![];
+        ciborium::ser::into_writer(&os, &mut second).unwrap();
+        assert_eq!(first, second);
+
+        let value: ciborium::value::Value = ciborium::de::from_reader(first.as_slice()).unwrap();
+        let entries = value.into_map().unwrap();
+        assert_eq!(entries.len(), os.list_properties().unwrap().len());
+
+        // Datetimes are kept as DMTF strings.
+        let (_, last_boot) = entries
+            .iter()
+            .find(|(name, _)| name.as_text() == Some("LastBootUpTime"))
+            .unwrap();
+        assert!(last_boot.as_text().unwrap().contains('.'));
     }
 }
@@ -0,0 +1,447 @@
+//! Map the `ReturnValue` of methods to errors.
+//!
+//! Most methods report failures using a non-zero `ReturnValue` (and not using an `HRESULT`),
+//! whose meaning is documented by the `ValueMap` and `Values` qualifiers of the method.
+//!
+//! Typed errors are provided for common classes ([`ProcessError`], [`ServiceError`] and [`ShadowCopyError`]),
+//! and can be used with [`WMIConnection::exec_method_mapped`]:
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use wmi::return_code::{MethodError, ReturnCode, ServiceError};
+//!
+//! // The `Winmgmt` service is always running.
+//! let res = con.exec_method_mapped::<ServiceError>(r#"Win32_Service.Name="Winmgmt""#, "StartService", &[]);
+//!
+//! match res {
+//!     Err(MethodError::ReturnCode(ServiceError::AlreadyRunning)) => println!("Already running"),
+//!     Err(MethodError::ReturnCode(err)) => println!("Failed with {}: {}", err.return_value(), err),
+//!     Err(MethodError::Wmi(err)) => return Err(err),
+//!     Ok(_) => println!("Started"),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! For other classes, a [`ReturnCodes`] registry maps return values to messages,
+//! either registered manually or read from the schema using [`WMIConnection::return_value_map`].
+use crate::{
+    connection::WMIConnection, result_enumerator::IWbemClassWrapper, schema::method_qualifier,
+    Variant, WMIError, WMIResult,
+};
+use std::collections::HashMap;
+use thiserror::Error;
+use windows::core::BSTR;
+use windows::Win32::System::Wmi::{
+    WBEM_FLAG_RETURN_WBEM_COMPLETE, WBEM_FLAG_USE_AMENDED_QUALIFIERS,
+};
+
+/// A typed error for the (non-zero) return values of the methods of a class.
+pub trait ReturnCode: std::error::Error + Sized {
+    /// The class whose methods return these codes, like `Win32_Service`.
+    const CLASS: &'static str;
+    /// The known return values.
+    const CODES: &'static [u32];
+
+    /// Map a non-zero return value. Unknown values are kept as is.
+    fn from_return_value(return_value: u32) -> Self;
+
+    fn return_value(&self) -> u32;
+}
+
+/// The error of [`WMIConnection::exec_method_mapped`].
+#[derive(Debug, Error)]
+pub enum MethodError<E> {
+    /// The method was executed, and returned a non-zero `ReturnValue`.
+    #[error(transparent)]
+    ReturnCode(E),
+    /// The method could not be executed.
+    #[error(transparent)]
+    Wmi(#[from] WMIError),
+}
+
+macro_rules! return_codes {
+    (
+        $(#[$meta:meta])*
+        $name:ident for $class:literal {
+            $($variant:ident = $code:literal => $message:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Error)]
+        #[non_exhaustive]
+        pub enum $name {
+            $(
+                #[error($message)]
+                $variant,
+            )*
+            #[error("Unknown return value {0}")]
+            Other(u32),
+        }
+
+        impl ReturnCode for $name {
+            const CLASS: &'static str = $class;
+            const CODES: &'static [u32] = &[$($code),*];
+
+            fn from_return_value(return_value: u32) -> Self {
+                match return_value {
+                    $($code => $name::$variant,)*
+                    other => $name::Other(other),
+                }
+            }
+
+            fn return_value(&self) -> u32 {
+                match self {
+                    $($name::$variant => $code,)*
+                    $name::Other(other) => *other,
+                }
+            }
+        }
+    };
+}
+
+return_codes! {
+    /// The return values of the methods of `Win32_Process` (like `Create` and `Terminate`).
+    ProcessError for "Win32_Process" {
+        AccessDenied = 2 => "Access denied",
+        InsufficientPrivilege = 3 => "Insufficient privilege",
+        UnknownFailure = 8 => "Unknown failure",
+        PathNotFound = 9 => "Path not found",
+        InvalidParameter = 21 => "Invalid parameter",
+    }
+}
+
+return_codes! {
+    /// The return values of the methods of `Win32_Service` (like `StartService` and `ChangeStartMode`).
+    ServiceError for "Win32_Service" {
+        NotSupported = 1 => "The request is not supported",
+        AccessDenied = 2 => "The user did not have the necessary access",
+        DependentServicesRunning = 3 => "The service cannot be stopped because other services that are running are dependent on it",
+        InvalidServiceControl = 4 => "The requested control code is not valid, or it is unacceptable to the service",
+        ServiceCannotAcceptControl = 5 => "The requested control code cannot be sent to the service because the state of the service is not compatible with it",
+        ServiceNotActive = 6 => "The service has not been started",
+        ServiceRequestTimeout = 7 => "The service did not respond to the start request in a timely fashion",
+        UnknownFailure = 8 => "Unknown failure when starting the service",
+        PathNotFound = 9 => "The directory path to the service executable file was not found",
+        AlreadyRunning = 10 => "The service is already running",
+        DatabaseLocked = 11 => "The database to add a new service is locked",
+        DependencyDeleted = 12 => "A dependency this service relies on has been removed from the system",
+        DependencyFailure = 13 => "The service failed to find the service needed from a dependent service",
+        ServiceDisabled = 14 => "The service has been disabled from the system",
+        LogonFailed = 15 => "The service does not have the correct authentication to run on the system",
+        MarkedForDeletion = 16 => "This service is being removed from the system",
+        NoThread = 17 => "The service has no execution thread",
+        CircularDependency = 18 => "The service has circular dependencies when it starts",
+        DuplicateName = 19 => "A service is running under the same name",
+        InvalidName = 20 => "The service name has invalid characters",
+        InvalidParameter = 21 => "Invalid parameters have been passed to the service",
+        InvalidServiceAccount = 22 => "The account under which this service runs is either invalid or lacks the permissions to run the service",
+        ServiceExists = 23 => "The service exists in the database of services available from the system",
+        AlreadyPaused = 24 => "The service is currently paused in the system",
+    }
+}
+
+return_codes! {
+    /// The return values of the methods of `Win32_ShadowCopy` (like `Create`).
+    ShadowCopyError for "Win32_ShadowCopy" {
+        AccessDenied = 1 => "Access denied",
+        InvalidArgument = 2 => "Invalid argument",
+        VolumeNotFound = 3 => "Specified volume not found",
+        VolumeNotSupported = 4 => "Specified volume not supported",
+        UnsupportedContext = 5 => "Unsupported shadow copy context",
+        InsufficientStorage = 6 => "Insufficient storage",
+        VolumeInUse = 7 => "Volume is in use",
+        MaximumReached = 8 => "Maximum number of shadow copies reached",
+        OperationInProgress = 9 => "Another shadow copy operation is already in progress",
+        ProviderVetoed = 10 => "Shadow copy provider vetoed the operation",
+        ProviderNotRegistered = 11 => "Shadow copy provider not registered",
+        ProviderFailure = 12 => "Shadow copy provider failure",
+        UnknownError = 13 => "Unknown error",
+    }
+}
+
+/// A registry of messages for the return values of methods, keyed by class (and optionally by method).
+///
+/// [`ReturnCodes::new`] is seeded with the typed errors of this module.
+///
+/// ```edition2018
+/// # fn main() -> wmi::WMIResult<()> {
+/// # use wmi::*;
+/// # let con = WMIConnection::new(COMLibrary::new()?)?;
+/// use wmi::return_code::ReturnCodes;
+///
+/// let mut codes = ReturnCodes::new();
+/// codes.register("Win32_Printer", Some("PrintTestPage"), con.return_value_map("Win32_Printer", "PrintTestPage")?);
+///
+/// assert_eq!(codes.describe("Win32_Service", "StartService", 10), Some("The service is already running"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReturnCodes {
+    /// Keyed by the lowercase class name, and the lowercase method name (`None` for all the methods of the class).
+    codes: HashMap<(String, Option<String>), HashMap<u32, String>>,
+}
+
+impl Default for ReturnCodes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReturnCodes {
+    /// A registry with the return values of [`ProcessError`], [`ServiceError`] and [`ShadowCopyError`].
+    pub fn new() -> Self {
+        let mut codes = Self::empty();
+
+        codes.register_enum::<ProcessError>();
+        codes.register_enum::<ServiceError>();
+        codes.register_enum::<ShadowCopyError>();
+
+        codes
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            codes: HashMap::new(),
+        }
+    }
+
+    /// Register messages for the return values of a method (or of all the methods of a class, when `method` is `None`).
+    ///
+    /// Existing messages for the same return values are replaced.
+    pub fn register<S: Into<String>>(
+        &mut self,
+        class: &str,
+        method: Option<&str>,
+        codes: impl IntoIterator<Item = (u32, S)>,
+    ) {
+        let key = (class.to_lowercase(), method.map(str::to_lowercase));
+
+        self.codes.entry(key).or_default().extend(
+            codes
+                .into_iter()
+                .map(|(return_value, message)| (return_value, message.into())),
+        );
+    }
+
+    /// Register the messages of a typed error, for all the methods of its class.
+    pub fn register_enum<E: ReturnCode>(&mut self) {
+        let codes = E::CODES
+            .iter()
+            .map(|&return_value| (return_value, E::from_return_value(return_value).to_string()));
+
+        self.register(E::CLASS, None, codes);
+    }
+
+    /// The message of a return value, preferring the messages registered for the method over the ones of its class.
+    pub fn describe(&self, class: &str, method: &str, return_value: u32) -> Option<&str> {
+        let class = class.to_lowercase();
+
+        [Some(method.to_lowercase()), None]
+            .into_iter()
+            .find_map(|method| self.codes.get(&(class.clone(), method))?.get(&return_value))
+            .map(String::as_str)
+    }
+
+    /// Fail if the return value is not `0`, with a [`WMIError::ExtendedStatusError`] if its message is known,
+    /// and a [`WMIError::MethodFailed`] otherwise.
+    pub fn check(&self, class: &str, method: &str, return_value: u32) -> WMIResult<()> {
+        if return_value == 0 {
+            return Ok(());
+        }
+
+        match self.describe(class, method, return_value) {
+            Some(message) => Err(WMIError::ExtendedStatusError {
+                method: method.to_owned(),
+                return_value,
+                message: message.to_owned(),
+            }),
+            None => Err(WMIError::MethodFailed {
+                method: method.to_owned(),
+                return_value,
+            }),
+        }
+    }
+}
+
+///
+/// ### Additional return value methods
+///
+impl WMIConnection {
+    /// Execute a method (see [`WMIConnection::exec_method`]), and map a non-zero `ReturnValue` to a typed error.
+    ///
+    /// See the [module level documentation](crate::return_code) for an example.
+    pub fn exec_method_mapped<E: ReturnCode>(
+        &self,
+        object_path: &str,
+        method: &str,
+        in_params: &[(&str, Variant)],
+    ) -> Result<Option<IWbemClassWrapper>, MethodError<E>> {
+        let out = self.exec_method(object_path, method, in_params)?;
+
+        if let Some(out) = &out {
+            match out.get_property("ReturnValue")? {
+                Variant::Null | Variant::Empty => {}
+                value => match u32::try_from(value)? {
+                    0 => {}
+                    return_value => {
+                        return Err(MethodError::ReturnCode(E::from_return_value(return_value)))
+                    }
+                },
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Read the messages of the return values of a method, from its `ValueMap` and `Values` qualifiers.
+    ///
+    /// Ranges (like `..` or `4096..32767`) are not included.
+    pub fn return_value_map(&self, class: &str, method: &str) -> WMIResult<HashMap<u32, String>> {
+        // The `Values` qualifiers are localized, and are only returned when using amended qualifiers.
+        let path = BSTR::from(class);
+        let mut class_obj = None;
+
+        unsafe {
+            self.svc.GetObject(
+                &path,
+                (WBEM_FLAG_RETURN_WBEM_COMPLETE.0 | WBEM_FLAG_USE_AMENDED_QUALIFIERS.0) as _,
+                self.ctx(),
+                Some(&mut class_obj),
+                None,
+            )?;
+        }
+
+        let class_obj = IWbemClassWrapper::new(class_obj.ok_or(WMIError::NullPointerResult)?);
+
+        let value_map = method_qualifier(&class_obj, method, "ValueMap")?;
+        let values = method_qualifier(&class_obj, method, "Values")?;
+
+        Ok(parse_value_map(value_map, values))
+    }
+}
+
+fn parse_value_map(value_map: Option<Variant>, values: Option<Variant>) -> HashMap<u32, String> {
+    let strings = |variant: Option<Variant>| -> Vec<String> {
+        match variant {
+            Some(Variant::Array(items)) => items
+                .into_iter()
+                .map(|item| match item {
+                    Variant::String(s) => s,
+                    _ => String::new(),
+                })
+                .collect(),
+            _ => vec![],
+        }
+    };
+
+    strings(value_map)
+        .into_iter()
+        .zip(strings(values))
+        .filter_map(|(value, message)| Some((value.trim().parse().ok()?, message)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    #[test]
+    fn it_maps_return_values() {
+        assert_eq!(
+            ServiceError::from_return_value(10),
+            ServiceError::AlreadyRunning
+        );
+        assert_eq!(ServiceError::AlreadyRunning.return_value(), 10);
+        assert_eq!(ServiceError::from_return_value(99), ServiceError::Other(99));
+        assert_eq!(ProcessError::AccessDenied.to_string(), "Access denied");
+        assert_eq!(
+            ShadowCopyError::from_return_value(3),
+            ShadowCopyError::VolumeNotFound
+        );
+    }
+
+    #[test]
+    fn it_registers_return_codes() {
+        let mut codes = ReturnCodes::new();
+
+        assert_eq!(
+            codes.describe("win32_service", "StopService", 6),
+            Some("The service has not been started")
+        );
+        assert_eq!(codes.describe("Win32_Service", "StopService", 99), None);
+
+        codes.register("Win32_Service", Some("StopService"), [(6, "Not started")]);
+
+        assert_eq!(
+            codes.describe("Win32_Service", "StopService", 6),
+            Some("Not started")
+        );
+        assert_eq!(
+            codes.describe("Win32_Service", "PauseService", 6),
+            Some("The service has not been started")
+        );
+
+        assert!(codes.check("Win32_Service", "StopService", 0).is_ok());
+        assert!(matches!(
+            codes.check("Win32_Service", "StopService", 6),
+            Err(WMIError::ExtendedStatusError {
+                return_value: 6,
+                ..
+            })
+        ));
+        assert!(matches!(
+            codes.check("Win32_Service", "StopService", 99),
+            Err(WMIError::MethodFailed {
+                return_value: 99,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn it_parses_value_maps() {
+        let value_map = Variant::Array(vec!["0".into(), "2".into(), "..".into()]);
+        let values = Variant::Array(vec!["Success".into(), "Denied".into(), "Other".into()]);
+
+        let map = parse_value_map(Some(value_map), Some(values));
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[&2], "Denied");
+        assert!(parse_value_map(None, None).is_empty());
+    }
+
+    #[test]
+    fn it_reads_return_value_maps() {
+        let wmi_con = wmi_con();
+
+        let map = wmi_con
+            .return_value_map("Win32_Service", "StartService")
+            .unwrap();
+
+        assert!(map.contains_key(&0));
+        assert!(map.contains_key(&10));
+    }
+
+    #[test]
+    fn it_maps_method_return_values() {
+        let wmi_con = wmi_con();
+
+        let res = wmi_con.exec_method_mapped::<ServiceError>(
+            r#"Win32_Service.Name="Winmgmt""#,
+            "StartService",
+            &[],
+        );
+
+        match res {
+            Err(MethodError::ReturnCode(ServiceError::AlreadyRunning)) => {}
+            // Requires admin rights.
+            Err(MethodError::ReturnCode(ServiceError::AccessDenied)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,590 @@
+//! An owned, self-clearing [`VARIANT`], and the conversion of raw `VARIANT`s into [`Variant`]s.
+//!
+//! Besides the types WMI itself uses, the conversion also supports types which are
+//! sometimes returned by other providers:
+//!
+//! | `VARTYPE`                  | `Variant`                                                       |
+//! |----------------------------|-----------------------------------------------------------------|
+//! | `VT_BYREF \| T`            | The same as `T`                                                 |
+//! | `VT_INT` / `VT_UINT`       | `I4` / `UI4`                                                    |
+//! | `VT_ERROR`                 | `I4` (the `SCODE`)                                              |
+//! | `VT_DECIMAL`               | `I8` or `UI8` for integers which fit, `R8` otherwise            |
+//! | `VT_CY`                    | `R8`                                                            |
+//! | `VT_DATE`                  | `String`, as a WMI datetime (`yyyymmddHHMMSS.mmmmmm+000`)       |
+//! | `VT_DISPATCH`              | `Unknown`                                                       |
+//!
+use crate::{
+    safearray::safe_array_to_vec,
+    variant::{string_from_wide, IUnknownWrapper},
+    Variant, WMIError, WMIResult,
+};
+use std::mem::ManuallyDrop;
+use windows::core::ComInterface;
+use windows::Win32::Foundation::{DECIMAL, VARIANT_BOOL, VARIANT_FALSE, VARIANT_TRUE};
+use windows::Win32::System::Com::{
+    self, CY, VARENUM, VARIANT, VARIANT_0, VARIANT_0_0, VARIANT_0_0_0, VT_ARRAY, VT_BYREF,
+    VT_TYPEMASK,
+};
+use windows::Win32::System::Ole::VariantClear;
+
+/// An owned `VARIANT`, which is cleared (using `VariantClear`) when dropped.
+///
+/// ```edition2018
+/// # fn main() -> wmi::WMIResult<()> {
+/// use wmi::{safe_variant::SafeVariant, Variant};
+///
+/// let vt = SafeVariant::from_variant(&Variant::String("Hello".into()))?;
+/// assert_eq!(vt.to_variant()?, Variant::String("Hello".into()));
+/// #   Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+#[repr(transparent)]
+pub struct SafeVariant(VARIANT);
+
+impl SafeVariant {
+    /// Create an empty (`VT_EMPTY`) variant, usually to be filled by an out parameter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take ownership of a raw `VARIANT`.
+    ///
+    /// # Safety
+    ///
+    /// The `VARIANT` must be correctly initialized, and must not be cleared by anyone else.
+    pub unsafe fn from_raw(vt: VARIANT) -> Self {
+        SafeVariant(vt)
+    }
+
+    /// Create a `VARIANT` holding the value of a [`Variant`].
+    ///
+    /// Scalar values (numbers, strings and booleans) and non-empty arrays of them can be converted.
+    pub fn from_variant(value: &Variant) -> WMIResult<Self> {
+        Ok(SafeVariant(value.to_raw_variant()?))
+    }
+
+    /// The type of the held value.
+    pub fn vt(&self) -> VARENUM {
+        unsafe { self.0.Anonymous.Anonymous.vt }
+    }
+
+    pub fn as_raw(&self) -> &VARIANT {
+        &self.0
+    }
+
+    /// A pointer which can be passed as an out parameter.
+    ///
+    /// The current value is not cleared, so this should only be used on empty variants.
+    pub fn as_mut_ptr(&mut self) -> *mut VARIANT {
+        &mut self.0
+    }
+
+    /// Convert the held value into a [`Variant`].
+    pub fn to_variant(&self) -> WMIResult<Variant> {
+        unsafe { variant_from_raw(&self.0) }
+    }
+}
+
+impl std::fmt::Debug for SafeVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SafeVariant")
+            .field("vt", &self.vt().0)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for SafeVariant {
+    fn drop(&mut self) {
+        // Clearing can only fail for invalid or locked variants, in which case there is nothing to free.
+        let _ = unsafe { VariantClear(&mut self.0) };
+    }
+}
+
+/// Build a raw `VARIANT` of the given type.
+pub(crate) fn raw_variant(vt: VARENUM, value: VARIANT_0_0_0) -> VARIANT {
+    VARIANT {
+        Anonymous: VARIANT_0 {
+            Anonymous: ManuallyDrop::new(VARIANT_0_0 {
+                vt,
+                wReserved1: 0,
+                wReserved2: 0,
+                wReserved3: 0,
+                Anonymous: value,
+            }),
+        },
+    }
+}
+
+/// Convert a raw `VARIANT` into a `Variant`.
+///
+/// # Safety
+///
+/// The `VARIANT` must be correctly initialized, and any pointer it holds must be valid.
+pub(crate) unsafe fn variant_from_raw(vt: &VARIANT) -> WMIResult<Variant> {
+    let variant_type = vt.Anonymous.Anonymous.vt;
+
+    // variant_type has three 'forms':
+    // 1. A simple type like `VT_BSTR` .
+    // 2. An array of certain type like `VT_ARRAY | VT_BSTR`.
+    // 3. A reference to one of the above, like `VT_BYREF | VT_BSTR`.
+    if variant_type.0 & VT_BYREF.0 != 0 {
+        return variant_from_ref(vt, VARENUM(variant_type.0 & !VT_BYREF.0));
+    }
+
+    let value = &vt.Anonymous.Anonymous.Anonymous;
+
+    if variant_type.0 & VT_ARRAY.0 == VT_ARRAY.0 {
+        let item_type = VARENUM(variant_type.0 & VT_TYPEMASK.0);
+
+        return Ok(Variant::Array(safe_array_to_vec(
+            &*value.parray,
+            item_type,
+        )?));
+    }
+
+    // See https://msdn.microsoft.com/en-us/library/cc237865.aspx for more info.
+    let variant_value = match variant_type {
+        Com::VT_BSTR => Variant::String(string_from_wide(value.bstrVal.as_wide())?),
+        Com::VT_I1 => Variant::I1(value.cVal as _),
+        Com::VT_I2 => Variant::I2(value.iVal),
+        Com::VT_I4 | Com::VT_INT => Variant::I4(value.lVal),
+        Com::VT_I8 => Variant::I8(value.llVal),
+        Com::VT_R4 => Variant::R4(value.fltVal),
+        Com::VT_R8 => Variant::R8(value.dblVal),
+        Com::VT_BOOL => bool_variant(value.boolVal)?,
+        Com::VT_UI1 => Variant::UI1(value.bVal),
+        Com::VT_UI2 => Variant::UI2(value.uiVal),
+        Com::VT_UI4 | Com::VT_UINT => Variant::UI4(value.ulVal),
+        Com::VT_UI8 => Variant::UI8(value.ullVal),
+        Com::VT_ERROR => Variant::I4(value.scode),
+        Com::VT_CY => currency_variant(value.cyVal),
+        Com::VT_DATE => Variant::String(ole_date_to_wmi_datetime(value.date)?),
+        // The decimal overlaps the whole `VARIANT` (its first field is the reserved `vt`).
+        Com::VT_DECIMAL => decimal_variant(&vt.Anonymous.decVal),
+        Com::VT_EMPTY => Variant::Empty,
+        Com::VT_NULL => Variant::Null,
+        Com::VT_UNKNOWN => {
+            let ptr = value.punkVal.as_ref().ok_or(WMIError::NullPointerResult)?;
+            Variant::Unknown(IUnknownWrapper::new(ptr.clone()))
+        }
+        Com::VT_DISPATCH => {
+            let ptr = value.pdispVal.as_ref().ok_or(WMIError::NullPointerResult)?;
+            Variant::Unknown(IUnknownWrapper::new(ptr.cast()?))
+        }
+        _ => return Err(WMIError::ConvertError(variant_type.0)),
+    };
+
+    Ok(variant_value)
+}
+
+/// Convert a `VT_BYREF` variant, whose value is a pointer to a value of `base_type`.
+unsafe fn variant_from_ref(vt: &VARIANT, base_type: VARENUM) -> WMIResult<Variant> {
+    unsafe fn deref<'a, T>(ptr: *const T) -> WMIResult<&'a T> {
+        ptr.as_ref().ok_or(WMIError::NullPointerResult)
+    }
+
+    let value = &vt.Anonymous.Anonymous.Anonymous;
+
+    if base_type.0 & VT_ARRAY.0 == VT_ARRAY.0 {
+        let item_type = VARENUM(base_type.0 & VT_TYPEMASK.0);
+        let array = *deref(value.pparray)?;
+
+        return Ok(Variant::Array(safe_array_to_vec(deref(array)?, item_type)?));
+    }
+
+    let variant_value = match base_type {
+        Com::VT_BSTR => Variant::String(string_from_wide(deref(value.pbstrVal)?.as_wide())?),
+        Com::VT_I1 => Variant::I1(*deref(value.pcVal.0)? as _),
+        Com::VT_I2 => Variant::I2(*deref(value.piVal)?),
+        Com::VT_I4 | Com::VT_INT => Variant::I4(*deref(value.plVal)?),
+        Com::VT_I8 => Variant::I8(*deref(value.pllVal)?),
+        Com::VT_R4 => Variant::R4(*deref(value.pfltVal)?),
+        Com::VT_R8 => Variant::R8(*deref(value.pdblVal)?),
+        Com::VT_BOOL => bool_variant(*deref(value.pboolVal)?)?,
+        Com::VT_UI1 => Variant::UI1(*deref(value.pbVal)?),
+        Com::VT_UI2 => Variant::UI2(*deref(value.puiVal)?),
+        Com::VT_UI4 | Com::VT_UINT => Variant::UI4(*deref(value.pulVal)?),
+        Com::VT_UI8 => Variant::UI8(*deref(value.pullVal)?),
+        Com::VT_ERROR => Variant::I4(*deref(value.pscode)?),
+        Com::VT_CY => currency_variant(*deref(value.pcyVal)?),
+        Com::VT_DATE => Variant::String(ole_date_to_wmi_datetime(*deref(value.pdate)?)?),
+        Com::VT_DECIMAL => decimal_variant(deref(value.pdecVal)?),
+        // A reference to a variant can't itself hold a reference, so this only recurses once.
+        Com::VT_VARIANT => variant_from_raw(deref(value.pvarVal)?)?,
+        Com::VT_UNKNOWN => {
+            let ptr = deref(value.ppunkVal)?
+                .as_ref()
+                .ok_or(WMIError::NullPointerResult)?;
+            Variant::Unknown(IUnknownWrapper::new(ptr.clone()))
+        }
+        Com::VT_DISPATCH => {
+            let ptr = deref(value.ppdispVal)?
+                .as_ref()
+                .ok_or(WMIError::NullPointerResult)?;
+            Variant::Unknown(IUnknownWrapper::new(ptr.cast()?))
+        }
+        _ => return Err(WMIError::ConvertError(base_type.0 | VT_BYREF.0)),
+    };
+
+    Ok(variant_value)
+}
+
+fn bool_variant(value: VARIANT_BOOL) -> WMIResult<Variant> {
+    match value {
+        VARIANT_FALSE => Ok(Variant::Bool(false)),
+        VARIANT_TRUE => Ok(Variant::Bool(true)),
+        _ => Err(WMIError::ConvertBoolError(value.0)),
+    }
+}
+
+/// A currency is a fixed point number, scaled by 10,000.
+fn currency_variant(value: CY) -> Variant {
+    // Safety: Both fields of the union cover the same 8 bytes.
+    let scaled = unsafe { value.int64 };
+
+    Variant::R8(scaled as f64 / 10_000.0)
+}
+
+/// A decimal is a 96 bit integer, a sign and a power of ten (between 0 and 28) to divide it by.
+fn decimal_variant(value: &DECIMAL) -> Variant {
+    // Safety: All the fields of the unions are plain integers covering the same bytes.
+    let (scale, sign, low) = unsafe {
+        (
+            value.Anonymous1.Anonymous.scale,
+            value.Anonymous1.Anonymous.sign,
+            value.Anonymous2.Lo64,
+        )
+    };
+
+    let mantissa = (u128::from(value.Hi32) << 64) | u128::from(low);
+    let negative = sign & 0x80 != 0;
+
+    if scale == 0 {
+        if !negative {
+            if let Ok(n) = u64::try_from(mantissa) {
+                return Variant::UI8(n);
+            }
+        } else if let Ok(n) = i64::try_from(-(mantissa as i128)) {
+            return Variant::I8(n);
+        }
+    }
+
+    let magnitude = mantissa as f64 / 10f64.powi(scale.into());
+
+    Variant::R8(if negative { -magnitude } else { magnitude })
+}
+
+/// Convert an OLE automation date into a WMI datetime string (in UTC).
+///
+/// An OLE date is the number of days since 1899-12-30, where the fraction is the time of day
+/// (even for negative dates, so -1.25 is 1899-12-29 06:00).
+fn ole_date_to_wmi_datetime(date: f64) -> WMIResult<String> {
+    const MICROS_PER_DAY: i64 = 86_400_000_000;
+    // The number of days between 1899-12-30 and 1970-01-01.
+    const UNIX_EPOCH_OLE_DAYS: i64 = 25_569;
+
+    // OLE dates are only valid between the years 100 and 9999.
+    if !(-657_434.0..2_958_466.0).contains(&date) {
+        return Err(WMIError::ConvertVariantError(format!(
+            "OLE date {} is out of range",
+            date
+        )));
+    }
+
+    let mut days = date.trunc() as i64;
+    let mut micros = (date.fract().abs() * MICROS_PER_DAY as f64).round() as i64;
+
+    if micros >= MICROS_PER_DAY {
+        days += 1;
+        micros -= MICROS_PER_DAY;
+    }
+
+    let (year, month, day) = civil_from_days(days - UNIX_EPOCH_OLE_DAYS);
+
+    let seconds = micros / 1_000_000;
+
+    Ok(format!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}.{:06}+000",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        micros % 1_000_000,
+    ))
+}
+
+/// Convert a number of days since 1970-01-01 into a (year, month, day) of the proleptic Gregorian calendar.
+///
+/// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use windows::core::BSTR;
+    use windows::Win32::Foundation::{DECIMAL_0, DECIMAL_0_0, DECIMAL_1};
+    use windows::Win32::System::Com::CY_0;
+
+    fn decimal(mantissa: u128, scale: u8, negative: bool) -> VARIANT {
+        let dec = DECIMAL {
+            wReserved: 0,
+            Anonymous1: DECIMAL_0 {
+                Anonymous: DECIMAL_0_0 {
+                    scale,
+                    sign: if negative { 0x80 } else { 0 },
+                },
+            },
+            Hi32: (mantissa >> 64) as u32,
+            Anonymous2: DECIMAL_1 {
+                Lo64: mantissa as u64,
+            },
+        };
+
+        let mut vt = VARIANT {
+            Anonymous: VARIANT_0 { decVal: dec },
+        };
+        // `vt` overlaps the reserved field of the decimal.
+        unsafe { (*vt.Anonymous.Anonymous).vt = Com::VT_DECIMAL };
+
+        vt
+    }
+
+    fn convert(vt: &VARIANT) -> WMIResult<Variant> {
+        unsafe { variant_from_raw(vt) }
+    }
+
+    #[test]
+    fn it_converts_scalar_variants() {
+        let matrix = [
+            (
+                raw_variant(Com::VT_I1, VARIANT_0_0_0 { cVal: 0xff }),
+                Variant::I1(-1),
+            ),
+            (
+                raw_variant(Com::VT_I2, VARIANT_0_0_0 { iVal: -2 }),
+                Variant::I2(-2),
+            ),
+            (
+                raw_variant(Com::VT_I4, VARIANT_0_0_0 { lVal: -4 }),
+                Variant::I4(-4),
+            ),
+            (
+                raw_variant(Com::VT_I8, VARIANT_0_0_0 { llVal: -8 }),
+                Variant::I8(-8),
+            ),
+            (
+                raw_variant(Com::VT_INT, VARIANT_0_0_0 { intVal: -5 }),
+                Variant::I4(-5),
+            ),
+            (
+                raw_variant(Com::VT_UI1, VARIANT_0_0_0 { bVal: 1 }),
+                Variant::UI1(1),
+            ),
+            (
+                raw_variant(Com::VT_UI2, VARIANT_0_0_0 { uiVal: 2 }),
+                Variant::UI2(2),
+            ),
+            (
+                raw_variant(Com::VT_UI4, VARIANT_0_0_0 { ulVal: 4 }),
+                Variant::UI4(4),
+            ),
+            (
+                raw_variant(Com::VT_UI8, VARIANT_0_0_0 { ullVal: 8 }),
+                Variant::UI8(8),
+            ),
+            (
+                raw_variant(Com::VT_UINT, VARIANT_0_0_0 { uintVal: 5 }),
+                Variant::UI4(5),
+            ),
+            (
+                raw_variant(Com::VT_R4, VARIANT_0_0_0 { fltVal: 0.5 }),
+                Variant::R4(0.5),
+            ),
+            (
+                raw_variant(Com::VT_R8, VARIANT_0_0_0 { dblVal: 0.25 }),
+                Variant::R8(0.25),
+            ),
+            (
+                raw_variant(
+                    Com::VT_BOOL,
+                    VARIANT_0_0_0 {
+                        boolVal: VARIANT_TRUE,
+                    },
+                ),
+                Variant::Bool(true),
+            ),
+            (
+                raw_variant(Com::VT_ERROR, VARIANT_0_0_0 { scode: -2147217406 }),
+                Variant::I4(-2147217406),
+            ),
+            (
+                raw_variant(
+                    Com::VT_CY,
+                    VARIANT_0_0_0 {
+                        cyVal: CY { int64: -123_456 },
+                    },
+                ),
+                Variant::R8(-12.3456),
+            ),
+            (
+                raw_variant(Com::VT_DATE, VARIANT_0_0_0 { date: 45_000.75 }),
+                Variant::String("20230315180000.000000+000".into()),
+            ),
+            (VARIANT::default(), Variant::Empty),
+            (
+                raw_variant(Com::VT_NULL, VARIANT_0_0_0 { llVal: 0 }),
+                Variant::Null,
+            ),
+        ];
+
+        for (vt, expected) in &matrix {
+            assert_eq!(&convert(vt).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn it_converts_decimals() {
+        assert_eq!(convert(&decimal(42, 0, false)).unwrap(), Variant::UI8(42));
+        assert_eq!(convert(&decimal(42, 0, true)).unwrap(), Variant::I8(-42));
+        assert_eq!(
+            convert(&decimal(12345, 2, true)).unwrap(),
+            Variant::R8(-123.45)
+        );
+        assert_eq!(
+            convert(&decimal(1 << 80, 0, false)).unwrap(),
+            Variant::R8((1u128 << 80) as f64)
+        );
+        assert_eq!(
+            convert(&decimal(1 << 63, 0, true)).unwrap(),
+            Variant::I8(i64::MIN)
+        );
+    }
+
+    #[test]
+    fn it_converts_ole_dates() {
+        assert_eq!(
+            ole_date_to_wmi_datetime(0.0).unwrap(),
+            "18991230000000.000000+000"
+        );
+        assert_eq!(
+            ole_date_to_wmi_datetime(-1.25).unwrap(),
+            "18991229060000.000000+000"
+        );
+        assert_eq!(
+            ole_date_to_wmi_datetime(25_569.5).unwrap(),
+            "19700101120000.000000+000"
+        );
+        assert_eq!(
+            ole_date_to_wmi_datetime(36_585.0).unwrap(),
+            "20000229000000.000000+000"
+        );
+        assert!(ole_date_to_wmi_datetime(f64::NAN).is_err());
+        assert!(ole_date_to_wmi_datetime(3e6).is_err());
+    }
+
+    #[test]
+    fn it_converts_byref_variants() {
+        let mut num = -4;
+        let mut flag = VARIANT_FALSE;
+        let mut cy = CY {
+            Anonymous: CY_0 { Lo: 50_000, Hi: 0 },
+        };
+        let mut bstr = BSTR::from("Hello");
+        let mut inner = raw_variant(Com::VT_UI2, VARIANT_0_0_0 { uiVal: 7 });
+        let mut dec = unsafe { decimal(5, 0, false).Anonymous.decVal };
+
+        let byref =
+            |vt: VARENUM, value: VARIANT_0_0_0| raw_variant(VARENUM(vt.0 | VT_BYREF.0), value);
+
+        let matrix = [
+            (
+                byref(Com::VT_I4, VARIANT_0_0_0 { plVal: &mut num }),
+                Variant::I4(-4),
+            ),
+            (
+                byref(
+                    Com::VT_BOOL,
+                    VARIANT_0_0_0 {
+                        pboolVal: &mut flag,
+                    },
+                ),
+                Variant::Bool(false),
+            ),
+            (
+                byref(Com::VT_CY, VARIANT_0_0_0 { pcyVal: &mut cy }),
+                Variant::R8(5.0),
+            ),
+            (
+                byref(
+                    Com::VT_BSTR,
+                    VARIANT_0_0_0 {
+                        pbstrVal: &mut bstr,
+                    },
+                ),
+                Variant::String("Hello".into()),
+            ),
+            (
+                byref(
+                    Com::VT_VARIANT,
+                    VARIANT_0_0_0 {
+                        pvarVal: &mut inner,
+                    },
+                ),
+                Variant::UI2(7),
+            ),
+            (
+                byref(Com::VT_DECIMAL, VARIANT_0_0_0 { pdecVal: &mut dec }),
+                Variant::UI8(5),
+            ),
+        ];
+
+        for (vt, expected) in &matrix {
+            assert_eq!(&convert(vt).unwrap(), expected);
+        }
+
+        let null = byref(
+            Com::VT_I4,
+            VARIANT_0_0_0 {
+                plVal: std::ptr::null_mut(),
+            },
+        );
+        assert!(matches!(convert(&null), Err(WMIError::NullPointerResult)));
+    }
+
+    #[test]
+    fn it_rejects_unsupported_variants() {
+        let vt = raw_variant(Com::VT_RECORD, VARIANT_0_0_0 { llVal: 0 });
+
+        assert!(matches!(
+            convert(&vt),
+            Err(WMIError::ConvertError(n)) if n == Com::VT_RECORD.0
+        ));
+    }
+
+    #[test]
+    fn it_clears_owned_variants() {
+        let vt = SafeVariant::from_variant(&Variant::String("Hello".into())).unwrap();
+
+        assert_eq!(vt.vt(), Com::VT_BSTR);
+        assert_eq!(vt.to_variant().unwrap(), Variant::String("Hello".into()));
+
+        let empty = SafeVariant::new();
+        assert_eq!(empty.vt(), Com::VT_EMPTY);
+        assert_eq!(empty.to_variant().unwrap(), Variant::Empty);
+    }
+}
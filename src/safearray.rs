@@ -1,20 +1,23 @@
 use crate::{
+    safe_variant::SafeVariant,
     utils::{WMIError, WMIResult},
+    variant::{string_from_wide, IUnknownWrapper},
     Variant,
 };
-use std::{iter::Iterator, ptr::null_mut, slice};
-use windows::core::BSTR;
+use std::{ffi::c_void, iter::Iterator, mem, ptr::null_mut, slice};
+use windows::core::{IUnknown, BSTR};
 use windows::Win32::System::Com::{self, SAFEARRAY, VARENUM, VT_BSTR};
 use windows::Win32::System::Ole::{
-    SafeArrayAccessData, SafeArrayGetLBound, SafeArrayGetUBound, SafeArrayUnaccessData,
+    SafeArrayAccessData, SafeArrayCreateVector, SafeArrayDestroy, SafeArrayGetDim,
+    SafeArrayGetLBound, SafeArrayGetUBound, SafeArrayPutElement, SafeArrayUnaccessData,
 };
 
 #[derive(Debug)]
 pub struct SafeArrayAccessor<'a, T> {
     arr: &'a SAFEARRAY,
     p_data: *mut T,
-    lower_bound: i32,
-    upper_bound: i32,
+    lower_bounds: Vec<i32>,
+    shape: Vec<usize>,
 }
 
 /// An accessor to SafeArray, which:
@@ -40,25 +43,52 @@ impl<'a, T> SafeArrayAccessor<'a, T> {
     pub fn new(arr: &'a SAFEARRAY) -> WMIResult<Self> {
         let mut p_data = null_mut();
 
-        let lower_bound = unsafe { SafeArrayGetLBound(arr, 1)? };
-        let upper_bound = unsafe { SafeArrayGetUBound(arr, 1)? };
+        let dims = unsafe { SafeArrayGetDim(arr) };
+        let mut lower_bounds = Vec::with_capacity(dims as usize);
+        let mut shape = Vec::with_capacity(dims as usize);
+
+        // Dimensions are numbered from 1, and can each have an arbitrary lower bound.
+        for dim in 1..=dims {
+            let lower_bound = unsafe { SafeArrayGetLBound(arr, dim)? };
+            let upper_bound = unsafe { SafeArrayGetUBound(arr, dim)? };
+
+            lower_bounds.push(lower_bound);
+            // upper_bound is lower than lower_bound for empty dimensions.
+            shape.push((i64::from(upper_bound) - i64::from(lower_bound) + 1).max(0) as usize);
+        }
+
         unsafe { SafeArrayAccessData(arr, &mut p_data)? };
 
         Ok(Self {
             arr,
             p_data: p_data as *mut T,
-            lower_bound,
-            upper_bound,
+            lower_bounds,
+            shape,
         })
     }
 
-    /// Return a slice which can access the data of the array.
+    /// The number of elements in each dimension, starting from the first (left-most) one.
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    /// The lower bound of each dimension, starting from the first (left-most) one.
+    pub fn lower_bounds(&self) -> &[i32] {
+        &self.lower_bounds
+    }
+
+    /// Return a slice which can access all the data of the array.
+    ///
+    /// For multi-dimensional arrays the data is in column-major order:
+    /// the first dimension's index changes the fastest.
     pub fn as_slice(&self) -> &[T] {
-        // upper_bound can be -1, in which case the array is empty and we will return a 0 length slice.
-        let data_slice =
-            unsafe { slice::from_raw_parts(self.p_data, (self.upper_bound + 1) as usize) };
+        let len = self.shape.iter().product();
 
-        &data_slice[(self.lower_bound as usize)..]
+        if len == 0 {
+            return &[];
+        }
+
+        unsafe { slice::from_raw_parts(self.p_data, len) }
     }
 }
 
@@ -75,7 +105,7 @@ impl<'a, T> Drop for SafeArrayAccessor<'a, T> {
 ///
 /// The caller must ensure that the array is valid and contains only strings.
 pub fn safe_array_to_vec_of_strings(arr: &SAFEARRAY) -> WMIResult<Vec<String>> {
-    let items = safe_array_to_vec(arr, VT_BSTR)?;
+    let (items, _) = safe_array_to_flat_vec(arr, VT_BSTR)?;
 
     let string_items = items
         .into_iter()
@@ -88,11 +118,46 @@ pub fn safe_array_to_vec_of_strings(arr: &SAFEARRAY) -> WMIResult<Vec<String>> {
     Ok(string_items)
 }
 
+/// Like [`safe_array_to_vec_of_strings`], but returns the raw data of each string (which might not be valid UTF-16).
+///
+/// # Safety
+///
+/// The caller must ensure that the array is valid and contains only strings.
+pub(crate) fn safe_array_to_vec_of_wide(arr: &SAFEARRAY) -> WMIResult<Vec<Vec<u16>>> {
+    let accessor = SafeArrayAccessor::<BSTR>::new(arr)?;
+
+    Ok(accessor
+        .as_slice()
+        .iter()
+        .map(|item| item.as_wide().to_vec())
+        .collect())
+}
+
+/// Multi-dimensional arrays are converted into nested arrays, indexed by the first dimension:
+/// a 2x3 array is returned as two `Variant::Array`s of three items each.
+///
 /// # Safety
 ///
 /// The caller must ensure that the array is valid.
 pub fn safe_array_to_vec(arr: &SAFEARRAY, item_type: VARENUM) -> WMIResult<Vec<Variant>> {
-    fn copy_type_to_vec<T, F>(arr: &SAFEARRAY, variant_builder: F) -> WMIResult<Vec<Variant>>
+    let (items, shape) = safe_array_to_flat_vec(arr, item_type)?;
+
+    if shape.len() <= 1 {
+        return Ok(items);
+    }
+
+    Ok(nest_items(items, &shape))
+}
+
+/// Convert the items of the array (in column-major order), and return them with the array's shape.
+fn safe_array_to_flat_vec(
+    arr: &SAFEARRAY,
+    item_type: VARENUM,
+) -> WMIResult<(Vec<Variant>, Vec<usize>)> {
+    fn copy_type_to_vec<T, F>(
+        arr: &SAFEARRAY,
+        variant_builder: F,
+    ) -> WMIResult<(Vec<Variant>, Vec<usize>)>
     where
         T: Copy,
         F: Fn(T) -> Variant,
@@ -105,7 +170,7 @@ pub fn safe_array_to_vec(arr: &SAFEARRAY, item_type: VARENUM) -> WMIResult<Vec<V
             items.push(variant_builder(*item));
         }
 
-        Ok(items)
+        Ok((items, accessor.shape().to_vec()))
     }
 
     match item_type {
@@ -124,11 +189,220 @@ pub fn safe_array_to_vec(arr: &SAFEARRAY, item_type: VARENUM) -> WMIResult<Vec<V
             let accessor = unsafe { SafeArrayAccessor::<BSTR>::new(arr)? };
 
             for item_bstr in accessor.as_slice().iter() {
-                items.push(Variant::String(item_bstr.try_into()?));
+                items.push(Variant::String(string_from_wide(item_bstr.as_wide())?));
+            }
+            Ok((items, accessor.shape().to_vec()))
+        }
+        // Arrays of embedded objects (which are converted into `Variant::Object`s using their CIM type).
+        Com::VT_UNKNOWN | Com::VT_DISPATCH => {
+            let mut items = vec![];
+            let accessor = SafeArrayAccessor::<Option<IUnknown>>::new(arr)?;
+
+            for item in accessor.as_slice().iter() {
+                items.push(match item {
+                    Some(ptr) => Variant::Unknown(IUnknownWrapper::new(ptr.clone())),
+                    None => Variant::Null,
+                });
             }
-            Ok(items)
+            Ok((items, accessor.shape().to_vec()))
         }
         // TODO: Add support for all other types of arrays.
         _ => Err(WMIError::UnimplementedArrayItem),
     }
 }
+
+/// Create a one-dimensional array holding copies of the given scalar items, which must all have the same type.
+///
+/// Returns the type of the items and the array, which is owned by the caller
+/// (usually a `VARIANT`, which destroys the array when it is cleared).
+pub(crate) fn safe_array_from_slice(items: &[Variant]) -> WMIResult<(VARENUM, *mut SAFEARRAY)> {
+    let items = items
+        .iter()
+        .map(SafeVariant::from_variant)
+        .collect::<WMIResult<Vec<_>>>()?;
+
+    let item_type = match items.first() {
+        Some(item) => item.vt(),
+        None => {
+            return Err(WMIError::ConvertVariantError(
+                "The type of an empty array is unknown".to_owned(),
+            ))
+        }
+    };
+
+    if let Some(item) = items.iter().find(|item| item.vt() != item_type) {
+        return Err(WMIError::ConvertVariantError(format!(
+            "Array items must have the same type, found {:#X} and {:#X}",
+            item_type.0,
+            item.vt().0
+        )));
+    }
+
+    let arr = unsafe { SafeArrayCreateVector(item_type, 0, items.len() as u32) };
+
+    if arr.is_null() {
+        return Err(WMIError::NullPointerResult);
+    }
+
+    for (index, item) in items.iter().enumerate() {
+        let value = unsafe { &item.as_raw().Anonymous.Anonymous.Anonymous };
+
+        // Strings are passed as the `BSTR` itself, and other values by a pointer to them (which is also
+        // a pointer to the union). Either way, the array stores a copy of the value.
+        // The `BSTR` is copied as a raw pointer, which is null for empty strings.
+        let value = if item_type == VT_BSTR {
+            unsafe { mem::transmute_copy::<BSTR, *const c_void>(&value.bstrVal) }
+        } else {
+            value as *const _ as *const c_void
+        };
+
+        let index = index as i32;
+
+        if let Err(e) = unsafe { SafeArrayPutElement(arr, &index, value) } {
+            let _ = unsafe { SafeArrayDestroy(arr) };
+            return Err(e.into());
+        }
+    }
+
+    Ok((item_type, arr))
+}
+
+/// Arrange the items of a multi-dimensional array (in column-major order) as nested arrays,
+/// where the outer-most array is indexed by the first dimension.
+fn nest_items(items: Vec<Variant>, shape: &[usize]) -> Vec<Variant> {
+    if items.is_empty() {
+        return items;
+    }
+
+    let mut slots: Vec<Option<Variant>> = items.into_iter().map(Some).collect();
+    let mut ordered = Vec::with_capacity(slots.len());
+    let mut index = vec![0; shape.len()];
+
+    // Visit the items in row-major order (the last dimension changes the fastest).
+    for _ in 0..slots.len() {
+        let mut offset = 0;
+        let mut stride = 1;
+
+        for (&i, &len) in index.iter().zip(shape) {
+            offset += i * stride;
+            stride *= len;
+        }
+
+        ordered.push(slots[offset].take().expect("Each item is visited once"));
+
+        for (i, &len) in index.iter_mut().zip(shape).rev() {
+            *i += 1;
+
+            if *i < len {
+                break;
+            }
+
+            *i = 0;
+        }
+    }
+
+    // Group the items from the inner-most dimension outwards.
+    for &len in shape[1..].iter().rev() {
+        let mut items = ordered.into_iter();
+
+        ordered = (0..items.len() / len)
+            .map(|_| Variant::Array(items.by_ref().take(len).collect()))
+            .collect();
+    }
+
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use windows::Win32::System::Com::{SAFEARRAYBOUND, VT_I4};
+    use windows::Win32::System::Ole::{SafeArrayCreate, SafeArrayDestroy, SafeArrayPutElement};
+
+    /// Create an array of `VT_I4`, where each item is `f(indices)`.
+    fn create_array(bounds: &[SAFEARRAYBOUND], f: impl Fn(&[i32]) -> i32) -> *mut SAFEARRAY {
+        // `SafeArrayCreate` expects the bounds of the left-most dimension first.
+        let arr = unsafe { SafeArrayCreate(VT_I4, bounds.len() as u32, bounds.as_ptr()) };
+        assert!(!arr.is_null());
+
+        let mut indices: Vec<i32> = bounds.iter().map(|b| b.lLbound).collect();
+        let total: u32 = bounds.iter().map(|b| b.cElements).product();
+
+        for _ in 0..total {
+            let value = f(&indices);
+            unsafe {
+                SafeArrayPutElement(arr, indices.as_ptr(), &value as *const i32 as *const _)
+                    .unwrap()
+            };
+
+            for (i, b) in indices.iter_mut().zip(bounds) {
+                *i += 1;
+
+                if *i < b.lLbound + b.cElements as i32 {
+                    break;
+                }
+
+                *i = b.lLbound;
+            }
+        }
+
+        arr
+    }
+
+    fn bound(lower: i32, len: u32) -> SAFEARRAYBOUND {
+        SAFEARRAYBOUND {
+            cElements: len,
+            lLbound: lower,
+        }
+    }
+
+    #[test]
+    fn it_converts_arrays_with_a_non_zero_lower_bound() {
+        let arr = create_array(&[bound(5, 3)], |i| i[0]);
+
+        let items = safe_array_to_vec(unsafe { &*arr }, VT_I4).unwrap();
+        assert_eq!(items, vec![Variant::I4(5), Variant::I4(6), Variant::I4(7)]);
+
+        unsafe { SafeArrayDestroy(arr).unwrap() };
+    }
+
+    #[test]
+    fn it_converts_multi_dimensional_arrays() {
+        let arr = create_array(&[bound(0, 2), bound(1, 3)], |i| i[0] * 10 + i[1]);
+
+        let accessor = SafeArrayAccessor::<i32>::new(unsafe { &*arr }).unwrap();
+        assert_eq!(accessor.shape(), &[2, 3]);
+        assert_eq!(accessor.lower_bounds(), &[0, 1]);
+        assert_eq!(accessor.as_slice(), &[1, 11, 2, 12, 3, 13]);
+        drop(accessor);
+
+        let items = safe_array_to_vec(unsafe { &*arr }, VT_I4).unwrap();
+        assert_eq!(
+            items,
+            vec![
+                Variant::Array(vec![Variant::I4(1), Variant::I4(2), Variant::I4(3)]),
+                Variant::Array(vec![Variant::I4(11), Variant::I4(12), Variant::I4(13)]),
+            ]
+        );
+
+        unsafe { SafeArrayDestroy(arr).unwrap() };
+    }
+
+    #[test]
+    fn it_nests_items_of_three_dimensions() {
+        // A 2x1x2 array, in column-major order.
+        let items = [0, 100, 1, 101].iter().map(|&n| Variant::I4(n)).collect();
+
+        assert_eq!(
+            nest_items(items, &[2, 1, 2]),
+            vec![
+                Variant::Array(vec![Variant::Array(vec![Variant::I4(0), Variant::I4(1)])]),
+                Variant::Array(vec![Variant::Array(vec![
+                    Variant::I4(100),
+                    Variant::I4(101)
+                ])]),
+            ]
+        );
+        assert_eq!(nest_items(vec![], &[0, 3]), vec![]);
+    }
+}
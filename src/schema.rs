@@ -0,0 +1,518 @@
+//! Lightweight summaries of the classes of a namespace, for REPL and autocompletion tooling.
+use crate::{
+    connection::WMIConnection,
+    de::property_de::cim_type_name,
+    result_enumerator::{IWbemClassWrapper, QueryResultEnumerator},
+    safe_variant::SafeVariant,
+    Variant, WMIError, WMIResult,
+};
+use std::ptr;
+use windows::core::{BSTR, HSTRING, PCWSTR};
+use windows::Win32::System::Wmi::{
+    IWbemClassObject, IWbemQualifierSet, CIMTYPE_ENUMERATION, WBEM_E_NOT_FOUND, WBEM_FLAG_DEEP,
+    WBEM_FLAG_FORWARD_ONLY, WBEM_FLAG_RETURN_IMMEDIATELY,
+};
+
+/// A class of a namespace, as returned by [`WMIConnection::list_classes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassSummary {
+    pub name: String,
+    /// The direct superclass, or `None` for root classes.
+    pub superclass: Option<String>,
+}
+
+/// The schema of a class, as returned by [`WMIConnection::describe_class`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassDescription {
+    pub name: String,
+    pub superclass: Option<String>,
+    /// The (non-system) properties of the class, including inherited ones.
+    pub properties: Vec<PropertySummary>,
+    /// The names of the methods of the class, including inherited ones.
+    pub methods: Vec<String>,
+}
+
+/// A method of a class, as returned by [`WMIConnection::list_methods`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodSummary {
+    pub name: String,
+    /// Whether the method has the `Static` qualifier (and is executed on the class).
+    pub is_static: bool,
+    /// The input parameters, in order.
+    pub in_params: Vec<ParameterSummary>,
+    /// The output parameters (starting with the `ReturnValue`, if the method has one).
+    pub out_params: Vec<ParameterSummary>,
+}
+
+/// A parameter of a method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParameterSummary {
+    pub name: String,
+    /// The MOF name of the parameter's CIM type, like `uint32` or `string[]`.
+    pub cim_type: String,
+    /// Whether the parameter has the `Optional` qualifier.
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropertySummary {
+    pub name: String,
+    /// The MOF name of the property's CIM type, like `uint32` or `string[]`.
+    pub cim_type: String,
+    /// Whether the property has the `key` qualifier (and is part of the path of instances).
+    pub is_key: bool,
+}
+
+///
+/// ### Additional schema methods
+///
+impl WMIConnection {
+    /// List the classes of the namespace, sorted by name.
+    ///
+    /// If a pattern is given, only classes whose name matches it are returned.
+    /// The pattern is case insensitive, and `*` matches any number of characters.
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// # let con = WMIConnection::new(COMLibrary::new()?)?;
+    /// let classes = con.list_classes(Some("Win32_*"))?;
+    /// assert!(classes.iter().any(|class| class.name == "Win32_Process"));
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn list_classes(&self, pattern: Option<&str>) -> WMIResult<Vec<ClassSummary>> {
+        let enumerator = unsafe {
+            self.svc.CreateClassEnum(
+                &BSTR::new(),
+                WBEM_FLAG_DEEP.0 | WBEM_FLAG_FORWARD_ONLY.0 | WBEM_FLAG_RETURN_IMMEDIATELY.0,
+                self.ctx(),
+            )?
+        };
+
+        if self.needs_proxy_blanket() {
+            self.apply_proxy_blanket(&enumerator)?;
+        }
+
+        let mut classes = vec![];
+
+        for class in QueryResultEnumerator::new(self, enumerator) {
+            let class = class?;
+            let name = class.class()?;
+
+            let matches = match pattern {
+                Some(pattern) => matches_pattern(pattern, &name),
+                None => true,
+            };
+
+            if matches {
+                classes.push(ClassSummary {
+                    name,
+                    superclass: superclass(&class)?,
+                });
+            }
+        }
+
+        classes.sort_unstable_by_key(|class| class.name.to_ascii_lowercase());
+
+        Ok(classes)
+    }
+
+    /// Describe the properties and methods of a class.
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// # let con = WMIConnection::new(COMLibrary::new()?)?;
+    /// let process = con.describe_class("Win32_Process")?;
+    ///
+    /// let handle = process.properties.iter().find(|p| p.name == "Handle").unwrap();
+    /// assert!(handle.is_key);
+    /// assert!(process.methods.iter().any(|m| m == "Terminate"));
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn describe_class(&self, name: &str) -> WMIResult<ClassDescription> {
+        let class = self.get_raw_by_path(name)?;
+
+        let mut properties = vec![];
+
+        for name in class.list_properties()? {
+            properties.push(PropertySummary {
+                cim_type: cim_type_name(property_cim_type(&class, &name)?),
+                is_key: is_key(&class, &name)?,
+                name,
+            });
+        }
+
+        Ok(ClassDescription {
+            name: class.class()?,
+            superclass: superclass(&class)?,
+            properties,
+            methods: methods(&class)?,
+        })
+    }
+
+    /// List the methods of a class (including inherited ones), with their parameters.
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// # let con = WMIConnection::new(COMLibrary::new()?)?;
+    /// let methods = con.list_methods("Win32_Service")?;
+    ///
+    /// let change_start_mode = methods.iter().find(|m| m.name == "ChangeStartMode").unwrap();
+    /// assert_eq!(change_start_mode.in_params[0].name, "StartMode");
+    /// assert_eq!(change_start_mode.out_params[0].cim_type, "uint32");
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn list_methods(&self, class: &str) -> WMIResult<Vec<MethodSummary>> {
+        let class = self.get_raw_by_path(class)?;
+
+        let mut methods = vec![];
+
+        for (name, in_signature, out_signature) in method_signatures(&class)? {
+            methods.push(MethodSummary {
+                is_static: matches!(
+                    method_qualifier(&class, &name, "Static")?,
+                    Some(value) if value != Variant::Bool(false)
+                ),
+                in_params: parameters(in_signature.as_ref())?,
+                out_params: parameters(out_signature.as_ref())?,
+                name,
+            });
+        }
+
+        Ok(methods)
+    }
+
+    /// The names of the key properties of a class, which identify its instances (empty for singletons and keyless classes).
+    pub fn key_properties(&self, class: &str) -> WMIResult<Vec<String>> {
+        let class = self.get_raw_by_path(class)?;
+
+        let mut keys = vec![];
+
+        for name in class.list_properties()? {
+            if is_key(&class, &name)? {
+                keys.push(name);
+            }
+        }
+
+        Ok(keys)
+    }
+
+    /// Read a qualifier of a property of a class (like `Units` or `MaxLen`), or `None` if the property does not have it.
+    pub fn property_qualifier(
+        &self,
+        class: &str,
+        property: &str,
+        name: &str,
+    ) -> WMIResult<Option<Variant>> {
+        let class = self.get_raw_by_path(class)?;
+
+        qualifier(&class, property, name)
+    }
+}
+
+fn superclass(class: &IWbemClassWrapper) -> WMIResult<Option<String>> {
+    match class.get_property("__SUPERCLASS")? {
+        Variant::String(name) => Ok(Some(name)),
+        _ => Ok(None),
+    }
+}
+
+fn property_cim_type(class: &IWbemClassWrapper, name: &str) -> WMIResult<CIMTYPE_ENUMERATION> {
+    let name = HSTRING::from(name);
+    let mut value = SafeVariant::new();
+    let mut cim_type = 0;
+
+    unsafe {
+        class.inner.Get(
+            PCWSTR::from_raw(name.as_ptr()),
+            0,
+            value.as_mut_ptr(),
+            Some(&mut cim_type),
+            None,
+        )?;
+    }
+
+    Ok(CIMTYPE_ENUMERATION(cim_type))
+}
+
+fn is_key(class: &IWbemClassWrapper, name: &str) -> WMIResult<bool> {
+    let key = qualifier(class, name, "key")?;
+
+    Ok(matches!(key, Some(key) if key != Variant::Bool(false)))
+}
+
+/// Read a qualifier of a property, or `None` if the property does not have it.
+fn qualifier(class: &IWbemClassWrapper, name: &str, qualifier: &str) -> WMIResult<Option<Variant>> {
+    let name = HSTRING::from(name);
+
+    let qualifiers = unsafe {
+        class
+            .inner
+            .GetPropertyQualifierSet(PCWSTR::from_raw(name.as_ptr()))?
+    };
+
+    read_qualifier(&qualifiers, qualifier)
+}
+
+/// Read a qualifier of a method (like `Static`), or `None` if the method does not have it.
+pub(crate) fn method_qualifier(
+    class: &IWbemClassWrapper,
+    method: &str,
+    qualifier: &str,
+) -> WMIResult<Option<Variant>> {
+    let method = HSTRING::from(method);
+
+    let qualifiers = unsafe {
+        class
+            .inner
+            .GetMethodQualifierSet(PCWSTR::from_raw(method.as_ptr()))?
+    };
+
+    read_qualifier(&qualifiers, qualifier)
+}
+
+fn read_qualifier(qualifiers: &IWbemQualifierSet, qualifier: &str) -> WMIResult<Option<Variant>> {
+    let qualifier = HSTRING::from(qualifier);
+    let mut value = SafeVariant::new();
+
+    let result = unsafe {
+        qualifiers.Get(
+            PCWSTR::from_raw(qualifier.as_ptr()),
+            0,
+            value.as_mut_ptr(),
+            ptr::null_mut(),
+        )
+    };
+
+    match result.map_err(WMIError::from) {
+        Ok(()) => Ok(Some(value.to_variant()?)),
+        Err(WMIError::HResultError { hres }) if hres == WBEM_E_NOT_FOUND.0 => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn methods(class: &IWbemClassWrapper) -> WMIResult<Vec<String>> {
+    Ok(method_signatures(class)?
+        .into_iter()
+        .map(|(name, _, _)| name)
+        .collect())
+}
+
+type MethodSignature = (String, Option<IWbemClassWrapper>, Option<IWbemClassWrapper>);
+
+/// The methods of a class, with the classes of their input and output parameters (if they have any).
+fn method_signatures(class: &IWbemClassWrapper) -> WMIResult<Vec<MethodSignature>> {
+    let mut methods = vec![];
+
+    unsafe {
+        class.inner.BeginMethodEnumeration(0)?;
+
+        loop {
+            let mut name = BSTR::new();
+            let mut in_signature: Option<IWbemClassObject> = None;
+            let mut out_signature: Option<IWbemClassObject> = None;
+
+            // `WBEM_S_NO_MORE_DATA` is a success code, so the end is detected by the missing name.
+            let result =
+                class
+                    .inner
+                    .NextMethod(0, &mut name, &mut in_signature, &mut out_signature);
+
+            if result.is_err() || name.is_empty() {
+                break;
+            }
+
+            methods.push((
+                name.to_string(),
+                in_signature.map(IWbemClassWrapper::new),
+                out_signature.map(IWbemClassWrapper::new),
+            ));
+        }
+
+        class.inner.EndMethodEnumeration()?;
+    }
+
+    Ok(methods)
+}
+
+/// The parameters of a method signature, ordered by their `ID` qualifiers (the `ReturnValue` doesn't have one, and comes first).
+fn parameters(signature: Option<&IWbemClassWrapper>) -> WMIResult<Vec<ParameterSummary>> {
+    let signature = match signature {
+        Some(signature) => signature,
+        None => return Ok(vec![]),
+    };
+
+    let mut parameters = vec![];
+
+    for name in signature.list_properties()? {
+        let id = match qualifier(signature, &name, "ID")? {
+            Some(Variant::I4(id)) => id,
+            _ => -1,
+        };
+
+        parameters.push((
+            id,
+            ParameterSummary {
+                cim_type: cim_type_name(property_cim_type(signature, &name)?),
+                optional: matches!(
+                    qualifier(signature, &name, "Optional")?,
+                    Some(value) if value != Variant::Bool(false)
+                ),
+                name,
+            },
+        ));
+    }
+
+    parameters.sort_by_key(|(id, _)| *id);
+
+    Ok(parameters
+        .into_iter()
+        .map(|(_, parameter)| parameter)
+        .collect())
+}
+
+/// Case insensitive matching, where `*` matches any number of characters.
+pub(crate) fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_ascii_lowercase();
+    let name = name.to_ascii_lowercase();
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = parts.collect();
+    let last = match parts.pop() {
+        Some(last) => last,
+        // No wildcards, so the name must be matched exactly.
+        None => return rest.is_empty(),
+    };
+
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    #[test]
+    fn it_matches_patterns() {
+        assert!(matches_pattern("Win32_*", "Win32_Process"));
+        assert!(matches_pattern("win32_process", "Win32_Process"));
+        assert!(matches_pattern("*Net*Adapter", "Win32_NetworkAdapter"));
+        assert!(matches_pattern("*", "Win32_Process"));
+        assert!(matches_pattern("a*a", "aa"));
+
+        assert!(!matches_pattern("Win32_", "Win32_Process"));
+        assert!(!matches_pattern(
+            "*Adapter",
+            "Win32_NetworkAdapterConfiguration"
+        ));
+        assert!(!matches_pattern("a*a", "a"));
+    }
+
+    #[test]
+    fn it_lists_classes() {
+        let wmi_con = wmi_con();
+
+        let classes = wmi_con.list_classes(Some("Win32_*Process")).unwrap();
+
+        let process = classes
+            .iter()
+            .find(|class| class.name == "Win32_Process")
+            .unwrap();
+        assert_eq!(process.superclass.as_deref(), Some("CIM_Process"));
+        assert!(classes
+            .iter()
+            .all(|class| matches_pattern("Win32_*Process", &class.name)));
+
+        let all = wmi_con.list_classes(None).unwrap();
+        assert!(all.len() > classes.len());
+        assert!(all.iter().any(|class| class.superclass.is_none()));
+    }
+
+    #[test]
+    fn it_describes_classes() {
+        let wmi_con = wmi_con();
+
+        let process = wmi_con.describe_class("Win32_Process").unwrap();
+
+        assert_eq!(process.name, "Win32_Process");
+        assert_eq!(process.superclass.as_deref(), Some("CIM_Process"));
+
+        let property = |name: &str| {
+            process
+                .properties
+                .iter()
+                .find(|property| property.name == name)
+                .unwrap()
+        };
+
+        assert_eq!(property("ProcessId").cim_type, "uint32");
+        assert!(!property("ProcessId").is_key);
+        assert_eq!(property("Handle").cim_type, "string");
+        assert!(property("Handle").is_key);
+        assert_eq!(property("CreationDate").cim_type, "datetime");
+
+        assert!(process.methods.iter().any(|method| method == "Create"));
+        assert!(process.methods.iter().any(|method| method == "Terminate"));
+
+        assert!(wmi_con.describe_class("Win32_DoesNotExist").is_err());
+    }
+
+    #[test]
+    fn it_reads_key_properties() {
+        let wmi_con = wmi_con();
+
+        assert_eq!(wmi_con.key_properties("Win32_Service").unwrap(), ["Name"]);
+        assert_eq!(wmi_con.key_properties("Win32_Process").unwrap(), ["Handle"]);
+    }
+
+    #[test]
+    fn it_lists_methods() {
+        let wmi_con = wmi_con();
+
+        let methods = wmi_con.list_methods("Win32_Service").unwrap();
+        let method = |name: &str| methods.iter().find(|method| method.name == name).unwrap();
+
+        let change_start_mode = method("ChangeStartMode");
+        assert!(!change_start_mode.is_static);
+        assert_eq!(
+            change_start_mode.in_params,
+            vec![ParameterSummary {
+                name: "StartMode".to_owned(),
+                cim_type: "string".to_owned(),
+                optional: false,
+            }]
+        );
+        assert_eq!(change_start_mode.out_params[0].name, "ReturnValue");
+        assert_eq!(change_start_mode.out_params[0].cim_type, "uint32");
+
+        let create = method("Create");
+        assert!(create.is_static);
+        let names: Vec<&str> = create
+            .in_params
+            .iter()
+            .map(|param| param.name.as_str())
+            .collect();
+        assert_eq!(names[..3], ["Name", "DisplayName", "PathName"]);
+
+        let start_service = method("StartService");
+        assert!(start_service.in_params.is_empty());
+    }
+}
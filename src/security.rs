@@ -0,0 +1,548 @@
+//! Read and modify the permissions of WMI namespaces, using the `GetSD` and `SetSD` methods of `__SystemSecurity`.
+//!
+//! The security descriptors are parsed from (and written back to) their binary, self-relative format,
+//! with SIDs in their string format (see [`account`](crate::account) to resolve them into account names).
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! use wmi::security::{rights, AceType};
+//!
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! let sd = con.namespace_security()?;
+//!
+//! for ace in sd.dacl.iter().flatten() {
+//!     let remote = ace.mask & rights::WBEM_REMOTE_ACCESS != 0;
+//!     println!("{:?} {} (remote access: {})", ace.ace_type, ace.sid, remote);
+//! }
+//!
+//! // Allow a monitoring group to query this namespace remotely:
+//! // let mut sd = sd;
+//! // sd.allow("S-1-5-21-...", rights::WBEM_ENABLE | rights::WBEM_METHOD_EXECUTE | rights::WBEM_REMOTE_ACCESS, 0);
+//! // con.set_namespace_security(&sd)?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    account::is_sid, connection::WMIConnection, result_enumerator::IWbemClassWrapper, Variant,
+    WMIError, WMIResult,
+};
+use std::convert::TryFrom;
+
+/// The access rights of WMI namespaces, see
+/// [Access to WMI Namespaces](https://learn.microsoft.com/en-us/windows/win32/wmisdk/access-to-wmi-namespaces).
+pub mod rights {
+    /// Read (and enumerate) data in the namespace ("Enable Account").
+    pub const WBEM_ENABLE: u32 = 0x1;
+    /// Execute methods.
+    pub const WBEM_METHOD_EXECUTE: u32 = 0x2;
+    /// Write to classes and instances ("Full Write").
+    pub const WBEM_FULL_WRITE_REP: u32 = 0x4;
+    /// Write to static instances ("Partial Write").
+    pub const WBEM_PARTIAL_WRITE_REP: u32 = 0x8;
+    /// Write to provider instances ("Provider Write").
+    pub const WBEM_WRITE_PROVIDER: u32 = 0x10;
+    /// Access the namespace from a remote computer ("Remote Enable").
+    pub const WBEM_REMOTE_ACCESS: u32 = 0x20;
+    /// Read the security descriptor of the namespace.
+    pub const READ_CONTROL: u32 = 0x2_0000;
+    /// Modify the security descriptor of the namespace.
+    pub const WRITE_DAC: u32 = 0x4_0000;
+}
+
+/// The flags of an [`Ace`], see [ACE_HEADER](https://learn.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-ace_header).
+pub mod ace_flags {
+    pub const OBJECT_INHERIT_ACE: u8 = 0x1;
+    /// Child namespaces inherit the ACE.
+    pub const CONTAINER_INHERIT_ACE: u8 = 0x2;
+    pub const NO_PROPAGATE_INHERIT_ACE: u8 = 0x4;
+    pub const INHERIT_ONLY_ACE: u8 = 0x8;
+    /// The ACE was inherited from the parent namespace.
+    pub const INHERITED_ACE: u8 = 0x10;
+}
+
+const SE_DACL_PRESENT: u16 = 0x4;
+const SE_SACL_PRESENT: u16 = 0x10;
+const SE_SELF_RELATIVE: u16 = 0x8000;
+
+const SECURITY_DESCRIPTOR_REVISION: u8 = 1;
+const ACL_REVISION: u8 = 2;
+const SID_REVISION: u8 = 1;
+
+const HEADER_LEN: usize = 20;
+const ACL_HEADER_LEN: usize = 8;
+const ACE_HEADER_LEN: usize = 8;
+
+/// The type of an [`Ace`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AceType {
+    AccessAllowed,
+    AccessDenied,
+    SystemAudit,
+}
+
+impl AceType {
+    fn code(self) -> u8 {
+        match self {
+            AceType::AccessAllowed => 0,
+            AceType::AccessDenied => 1,
+            AceType::SystemAudit => 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for AceType {
+    type Error = WMIError;
+
+    fn try_from(code: u8) -> WMIResult<Self> {
+        match code {
+            0 => Ok(AceType::AccessAllowed),
+            1 => Ok(AceType::AccessDenied),
+            2 => Ok(AceType::SystemAudit),
+            other => Err(WMIError::InvalidSecurityDescriptor(format!(
+                "Unsupported ACE type {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// An access control entry, which allows (or denies) the access `mask` (see [`rights`]) to the account `sid`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Ace {
+    pub ace_type: AceType,
+    /// See [`ace_flags`].
+    pub flags: u8,
+    pub mask: u32,
+    /// The SID of the account, like `S-1-5-32-544` (`BUILTIN\Administrators`).
+    pub sid: String,
+}
+
+impl Ace {
+    pub fn is_inherited(&self) -> bool {
+        self.flags & ace_flags::INHERITED_ACE != 0
+    }
+}
+
+/// A security descriptor, as returned by `__SystemSecurity.GetSD`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityDescriptor {
+    /// The `SECURITY_DESCRIPTOR_CONTROL` flags, like `SE_DACL_PROTECTED` (`0x1000`).
+    /// The flags which describe the layout of the descriptor are updated when it is converted into bytes.
+    pub control: u16,
+    pub owner: Option<String>,
+    pub group: Option<String>,
+    /// `None` for a missing (or `NULL`) DACL, which allows all access.
+    pub dacl: Option<Vec<Ace>>,
+    pub sacl: Option<Vec<Ace>>,
+}
+
+impl SecurityDescriptor {
+    /// Parse a self-relative security descriptor.
+    pub fn from_bytes(bytes: &[u8]) -> WMIResult<Self> {
+        if bytes.len() < HEADER_LEN || bytes[0] != SECURITY_DESCRIPTOR_REVISION {
+            return Err(invalid("Invalid security descriptor header"));
+        }
+
+        let control = read_u16(bytes, 2)?;
+
+        if control & SE_SELF_RELATIVE == 0 {
+            return Err(invalid("The security descriptor is not self-relative"));
+        }
+
+        let offset = |at: usize| read_u32(bytes, at).map(|offset| offset as usize);
+        let (owner, group, sacl, dacl) = (offset(4)?, offset(8)?, offset(12)?, offset(16)?);
+
+        let sid_at = |offset: usize| match offset {
+            0 => Ok(None),
+            offset => parse_sid(bytes, offset).map(|(sid, _)| Some(sid)),
+        };
+
+        let acl_at = |present: u16, offset: usize| match offset {
+            0 => Ok(None),
+            _ if control & present == 0 => Ok(None),
+            offset => parse_acl(bytes, offset).map(Some),
+        };
+
+        Ok(Self {
+            control,
+            owner: sid_at(owner)?,
+            group: sid_at(group)?,
+            dacl: acl_at(SE_DACL_PRESENT, dacl)?,
+            sacl: acl_at(SE_SACL_PRESENT, sacl)?,
+        })
+    }
+
+    /// Write the security descriptor in the self-relative format.
+    pub fn to_bytes(&self) -> WMIResult<Vec<u8>> {
+        let mut control = self.control & !(SE_DACL_PRESENT | SE_SACL_PRESENT) | SE_SELF_RELATIVE;
+        let mut bytes = vec![0; HEADER_LEN];
+
+        bytes[0] = SECURITY_DESCRIPTOR_REVISION;
+
+        // Like Windows, write the SACL, DACL, owner and group (in this order) after the header.
+        let mut offsets = [0u32; 4];
+
+        if let Some(sacl) = &self.sacl {
+            control |= SE_SACL_PRESENT;
+            offsets[2] = bytes.len() as u32;
+            write_acl(&mut bytes, sacl)?;
+        }
+
+        if let Some(dacl) = &self.dacl {
+            control |= SE_DACL_PRESENT;
+            offsets[3] = bytes.len() as u32;
+            write_acl(&mut bytes, dacl)?;
+        }
+
+        if let Some(owner) = &self.owner {
+            offsets[0] = bytes.len() as u32;
+            write_sid(&mut bytes, owner)?;
+        }
+
+        if let Some(group) = &self.group {
+            offsets[1] = bytes.len() as u32;
+            write_sid(&mut bytes, group)?;
+        }
+
+        bytes[2..4].copy_from_slice(&control.to_le_bytes());
+
+        for (i, offset) in offsets.iter().enumerate() {
+            bytes[4 + i * 4..8 + i * 4].copy_from_slice(&offset.to_le_bytes());
+        }
+
+        Ok(bytes)
+    }
+
+    /// Allow the access `mask` to `sid`, by adding an ACE after the explicit ACEs of the DACL
+    /// (which keeps deny ACEs before allow ACEs, as Windows expects).
+    ///
+    /// A missing DACL is replaced by an empty one (which only allows the given access).
+    pub fn allow(&mut self, sid: &str, mask: u32, flags: u8) {
+        let dacl = self.dacl.get_or_insert_with(Vec::new);
+        let at = dacl
+            .iter()
+            .position(Ace::is_inherited)
+            .unwrap_or(dacl.len());
+
+        dacl.insert(at, ace(AceType::AccessAllowed, sid, mask, flags));
+    }
+
+    /// Deny the access `mask` to `sid`, by adding an ACE at the start of the DACL.
+    ///
+    /// A missing DACL is replaced by an empty one (which only allows the given access).
+    pub fn deny(&mut self, sid: &str, mask: u32, flags: u8) {
+        self.dacl
+            .get_or_insert_with(Vec::new)
+            .insert(0, ace(AceType::AccessDenied, sid, mask, flags));
+    }
+
+    /// Remove the explicit (not inherited) ACEs of `sid` from the DACL, returning how many were removed.
+    pub fn remove(&mut self, sid: &str) -> usize {
+        let dacl = match &mut self.dacl {
+            Some(dacl) => dacl,
+            None => return 0,
+        };
+
+        let len = dacl.len();
+        dacl.retain(|ace| ace.is_inherited() || !ace.sid.eq_ignore_ascii_case(sid));
+
+        len - dacl.len()
+    }
+}
+
+fn ace(ace_type: AceType, sid: &str, mask: u32, flags: u8) -> Ace {
+    Ace {
+        ace_type,
+        flags,
+        mask,
+        sid: sid.to_owned(),
+    }
+}
+
+fn invalid(message: &str) -> WMIError {
+    WMIError::InvalidSecurityDescriptor(message.to_owned())
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> WMIResult<u16> {
+    bytes
+        .get(at..at + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| invalid("Unexpected end of security descriptor"))
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> WMIResult<u32> {
+    bytes
+        .get(at..at + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| invalid("Unexpected end of security descriptor"))
+}
+
+/// Parse a binary SID at `at`, returning it in the string format with its length.
+fn parse_sid(bytes: &[u8], at: usize) -> WMIResult<(String, usize)> {
+    let header = bytes
+        .get(at..at + 8)
+        .ok_or_else(|| invalid("Unexpected end of SID"))?;
+
+    if header[0] != SID_REVISION {
+        return Err(invalid("Invalid SID revision"));
+    }
+
+    let sub_authorities = header[1] as usize;
+    let authority = header[2..8]
+        .iter()
+        .fold(0u64, |authority, b| authority << 8 | u64::from(*b));
+
+    let mut sid = format!("S-{}-{}", SID_REVISION, authority);
+
+    for i in 0..sub_authorities {
+        sid.push_str(&format!("-{}", read_u32(bytes, at + 8 + i * 4)?));
+    }
+
+    Ok((sid, 8 + sub_authorities * 4))
+}
+
+fn write_sid(bytes: &mut Vec<u8>, sid: &str) -> WMIResult<()> {
+    if !is_sid(sid) {
+        return Err(WMIError::InvalidSecurityDescriptor(format!(
+            "Invalid SID {:?}",
+            sid
+        )));
+    }
+
+    let mut parts = sid.split('-').skip(2);
+    let parse_err = || WMIError::InvalidSecurityDescriptor(format!("Invalid SID {:?}", sid));
+
+    let authority: u64 = parts.next().ok_or_else(parse_err)?.parse()?;
+    let sub_authorities = parts
+        .map(|part| part.parse::<u32>().map_err(|_| parse_err()))
+        .collect::<WMIResult<Vec<_>>>()?;
+
+    if authority >= 1 << 48 || sub_authorities.len() > 15 {
+        return Err(parse_err());
+    }
+
+    bytes.push(SID_REVISION);
+    bytes.push(sub_authorities.len() as u8);
+    bytes.extend_from_slice(&authority.to_be_bytes()[2..]);
+
+    for sub_authority in sub_authorities {
+        bytes.extend_from_slice(&sub_authority.to_le_bytes());
+    }
+
+    Ok(())
+}
+
+fn parse_acl(bytes: &[u8], at: usize) -> WMIResult<Vec<Ace>> {
+    let count = read_u16(bytes, at + 4)? as usize;
+    let mut offset = at + ACL_HEADER_LEN;
+    let mut aces = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        let header = bytes
+            .get(offset..offset + ACE_HEADER_LEN)
+            .ok_or_else(|| invalid("Unexpected end of ACL"))?;
+
+        let ace_type = AceType::try_from(header[0])?;
+        let flags = header[1];
+        let size = u16::from_le_bytes([header[2], header[3]]) as usize;
+        let mask = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+
+        if size < ACE_HEADER_LEN {
+            return Err(invalid("Invalid ACE size"));
+        }
+
+        let (sid, _) = parse_sid(bytes, offset + ACE_HEADER_LEN)?;
+
+        aces.push(Ace {
+            ace_type,
+            flags,
+            mask,
+            sid,
+        });
+
+        offset += size;
+    }
+
+    Ok(aces)
+}
+
+fn write_acl(bytes: &mut Vec<u8>, aces: &[Ace]) -> WMIResult<()> {
+    let start = bytes.len();
+
+    bytes.extend_from_slice(&[ACL_REVISION, 0, 0, 0]);
+    bytes.extend_from_slice(&(aces.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(&[0, 0]);
+
+    for ace in aces {
+        let ace_start = bytes.len();
+
+        bytes.extend_from_slice(&[ace.ace_type.code(), ace.flags, 0, 0]);
+        bytes.extend_from_slice(&ace.mask.to_le_bytes());
+        write_sid(bytes, &ace.sid)?;
+
+        let size = (bytes.len() - ace_start) as u16;
+        bytes[ace_start + 2..ace_start + 4].copy_from_slice(&size.to_le_bytes());
+    }
+
+    let size = (bytes.len() - start) as u16;
+    bytes[start + 2..start + 4].copy_from_slice(&size.to_le_bytes());
+
+    Ok(())
+}
+
+///
+/// ### Additional namespace security methods
+///
+impl WMIConnection {
+    /// Get the security descriptor of the connection's namespace, using `__SystemSecurity.GetSD`.
+    ///
+    /// Requires the `READ_CONTROL` right (see [`rights`]). Use [`with_namespace`](WMIConnection::with_namespace)
+    /// to read the security of another namespace.
+    /// See the [module level documentation](crate::security) for an example.
+    pub fn namespace_security(&self) -> WMIResult<SecurityDescriptor> {
+        let out = self.exec_system_security("GetSD", &[])?;
+
+        let bytes = match out.get_property("SD")? {
+            Variant::Array(items) => items
+                .into_iter()
+                .map(u8::try_from)
+                .collect::<WMIResult<Vec<_>>>()?,
+            other => {
+                return Err(WMIError::ConvertVariantError(format!(
+                    "Expected the security descriptor to be an array, got {:?}",
+                    other
+                )))
+            }
+        };
+
+        SecurityDescriptor::from_bytes(&bytes)
+    }
+
+    /// Replace the security descriptor of the connection's namespace, using `__SystemSecurity.SetSD`.
+    ///
+    /// Requires the `WRITE_DAC` right (see [`rights`]). Changes only apply to new connections.
+    pub fn set_namespace_security(&self, sd: &SecurityDescriptor) -> WMIResult<()> {
+        let bytes = sd.to_bytes()?.into_iter().map(Variant::UI1).collect();
+
+        self.exec_system_security("SetSD", &[("SD", Variant::Array(bytes))])?;
+
+        Ok(())
+    }
+
+    /// Execute a method of `__SystemSecurity`, and fail if its return value is an error.
+    fn exec_system_security(
+        &self,
+        method: &str,
+        in_params: &[(&str, Variant)],
+    ) -> WMIResult<IWbemClassWrapper> {
+        let out = self
+            .exec_method("__SystemSecurity=@", method, in_params)?
+            .ok_or(WMIError::NullPointerResult)?;
+
+        // The return values of the `__SystemSecurity` methods are `HRESULT`s.
+        match u32::try_from(out.get_property("ReturnValue")?)? {
+            0 => Ok(out),
+            hres => Err(WMIError::HResultError { hres: hres as i32 }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    /// `O:BAG:BAD:(A;CI;CCDCLCSWRPWPRCWD;;;BA)(A;CI;CCDCRP;;;NS)`, as returned by `GetSD` for `ROOT\CIMV2`.
+    fn sample() -> SecurityDescriptor {
+        SecurityDescriptor {
+            control: 0x8004,
+            owner: Some("S-1-5-32-544".to_owned()),
+            group: Some("S-1-5-32-544".to_owned()),
+            dacl: Some(vec![
+                ace(
+                    AceType::AccessAllowed,
+                    "S-1-5-32-544",
+                    0x6_003F,
+                    ace_flags::CONTAINER_INHERIT_ACE,
+                ),
+                ace(
+                    AceType::AccessAllowed,
+                    "S-1-5-20",
+                    0x13,
+                    ace_flags::CONTAINER_INHERIT_ACE,
+                ),
+            ]),
+            sacl: None,
+        }
+    }
+
+    #[test]
+    fn it_round_trips_security_descriptors() {
+        let sd = sample();
+        let bytes = sd.to_bytes().unwrap();
+
+        // The header, followed by the DACL (2 ACEs of 24 and 20 bytes) and the owner and group SIDs.
+        assert_eq!(bytes.len(), 20 + 8 + 24 + 20 + 16 + 16);
+        assert_eq!(&bytes[..4], &[1, 0, 0x04, 0x80]);
+        assert_eq!(&bytes[28..32], &[0, 2, 24, 0]);
+        assert_eq!(
+            &bytes[bytes.len() - 16..],
+            &[1, 2, 0, 0, 0, 0, 0, 5, 32, 0, 0, 0, 0x20, 2, 0, 0]
+        );
+
+        assert_eq!(SecurityDescriptor::from_bytes(&bytes).unwrap(), sd);
+    }
+
+    #[test]
+    fn it_rejects_invalid_security_descriptors() {
+        assert!(SecurityDescriptor::from_bytes(&[]).is_err());
+        assert!(SecurityDescriptor::from_bytes(&[1, 0, 0, 0]).is_err());
+
+        let mut bytes = sample().to_bytes().unwrap();
+        bytes.truncate(40);
+        assert!(SecurityDescriptor::from_bytes(&bytes).is_err());
+
+        let mut sd = sample();
+        sd.owner = Some("BA".to_owned());
+        assert!(sd.to_bytes().is_err());
+    }
+
+    #[test]
+    fn it_keeps_aces_in_canonical_order() {
+        let mut sd = sample();
+        sd.dacl.as_mut().unwrap()[1].flags |= ace_flags::INHERITED_ACE;
+
+        sd.allow("S-1-5-19", rights::WBEM_ENABLE, 0);
+        sd.deny("S-1-5-7", rights::WBEM_REMOTE_ACCESS, 0);
+
+        let sids: Vec<_> = sd
+            .dacl
+            .iter()
+            .flatten()
+            .map(|ace| ace.sid.as_str())
+            .collect();
+        assert_eq!(sids, ["S-1-5-7", "S-1-5-32-544", "S-1-5-19", "S-1-5-20"]);
+
+        assert_eq!(sd.remove("S-1-5-19"), 1);
+        assert_eq!(sd.remove("S-1-5-20"), 0);
+        assert_eq!(sd.dacl.unwrap().len(), 3);
+    }
+
+    #[test]
+    fn it_reads_namespace_security() {
+        let wmi_con = wmi_con();
+
+        // Requires administrative rights.
+        let sd = match wmi_con.namespace_security() {
+            Ok(sd) => sd,
+            Err(WMIError::HResultError { .. }) => return,
+            Err(err) => panic!("{}", err),
+        };
+
+        assert_eq!(
+            SecurityDescriptor::from_bytes(&sd.to_bytes().unwrap()).unwrap(),
+            sd
+        );
+    }
+}
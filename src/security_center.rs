@@ -0,0 +1,237 @@
+//! The security products registered with the Windows Security Center, and the status of Microsoft Defender.
+//!
+//! Security products are listed in the `ROOT\SecurityCenter2` namespace, which only exists on client
+//! editions of Windows (not on Windows Server). Their status is packed into the undocumented `productState`
+//! bitfield, which is decoded into a [`ProductState`].
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use wmi::security_center::ProductStatus;
+//!
+//! for product in con.antivirus_products()? {
+//!     let state = product.product_state;
+//!     let healthy = state.status == ProductStatus::On && state.signature_up_to_date();
+//!     println!("{}: {}", product.display_name, if healthy { "healthy" } else { "at risk" });
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{connection::WMIConnection, WMIResult};
+use serde::Deserialize;
+
+/// The namespace of the security products registered with the Windows Security Center.
+pub const SECURITY_CENTER_NAMESPACE: &str = "ROOT\\SecurityCenter2";
+/// The namespace of the Microsoft Defender provider.
+pub const DEFENDER_NAMESPACE: &str = "ROOT\\Microsoft\\Windows\\Defender";
+
+const PROVIDERS_MASK: u32 = 0x00FF_0000;
+const STATUS_MASK: u32 = 0x0000_F000;
+const OWNER_MASK: u32 = 0x0000_0F00;
+const SIGNATURE_MASK: u32 = 0x0000_00F0;
+
+/// The kinds of protection offered by a security product (the `WSC_SECURITY_PROVIDER` flags).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SecurityProviders(pub u32);
+
+impl SecurityProviders {
+    pub const FIREWALL: Self = Self(0x01);
+    pub const AUTOUPDATE_SETTINGS: Self = Self(0x02);
+    pub const ANTIVIRUS: Self = Self(0x04);
+    pub const ANTISPYWARE: Self = Self(0x08);
+    pub const INTERNET_SETTINGS: Self = Self(0x10);
+    pub const USER_ACCOUNT_CONTROL: Self = Self(0x20);
+    pub const SERVICE: Self = Self(0x40);
+
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+/// Whether a security product is running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProductStatus {
+    Off,
+    On,
+    Snoozed,
+    Expired,
+    /// A value not known to this crate.
+    Other(u32),
+}
+
+/// Whether the signatures of a security product are up to date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SignatureStatus {
+    UpToDate,
+    OutOfDate,
+    /// A value not known to this crate.
+    Other(u32),
+}
+
+/// The decoded `productState` of a security product.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProductState {
+    /// The original value.
+    pub raw: u32,
+    pub providers: SecurityProviders,
+    pub status: ProductStatus,
+    pub signature_status: SignatureStatus,
+    /// Whether the product is made by Microsoft (like Microsoft Defender).
+    pub is_microsoft: bool,
+}
+
+impl ProductState {
+    pub fn signature_up_to_date(&self) -> bool {
+        self.signature_status == SignatureStatus::UpToDate
+    }
+}
+
+impl From<u32> for ProductState {
+    fn from(raw: u32) -> Self {
+        let status = match raw & STATUS_MASK {
+            0x0000 => ProductStatus::Off,
+            0x1000 => ProductStatus::On,
+            0x2000 => ProductStatus::Snoozed,
+            0x3000 => ProductStatus::Expired,
+            other => ProductStatus::Other(other >> 12),
+        };
+
+        let signature_status = match raw & SIGNATURE_MASK {
+            0x00 => SignatureStatus::UpToDate,
+            0x10 => SignatureStatus::OutOfDate,
+            other => SignatureStatus::Other(other >> 4),
+        };
+
+        Self {
+            raw,
+            providers: SecurityProviders((raw & PROVIDERS_MASK) >> 16),
+            status,
+            signature_status,
+            is_microsoft: raw & OWNER_MASK == 0x100,
+        }
+    }
+}
+
+/// A security product registered with the Windows Security Center
+/// (from `AntiVirusProduct`, `AntiSpywareProduct` or `FirewallProduct`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityProduct {
+    pub display_name: String,
+    pub instance_guid: String,
+    pub path_to_signed_product_exe: Option<String>,
+    pub path_to_signed_reporting_exe: Option<String>,
+    #[serde(deserialize_with = "deserialize_product_state")]
+    pub product_state: ProductState,
+    /// When the state was last reported, in the RFC 1123 format (like `Mon, 12 Feb 2024 10:00:00 GMT`).
+    pub timestamp: Option<String>,
+}
+
+fn deserialize_product_state<'de, D>(deserializer: D) -> Result<ProductState, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    u32::deserialize(deserializer).map(ProductState::from)
+}
+
+/// The status of Microsoft Defender, from `MSFT_MpComputerStatus`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "MSFT_MpComputerStatus")]
+#[serde(rename_all = "PascalCase")]
+pub struct DefenderStatus {
+    pub antivirus_enabled: bool,
+    pub antispyware_enabled: bool,
+    pub real_time_protection_enabled: bool,
+    /// Whether tamper protection is enabled (only reported by recent versions of Defender).
+    pub is_tamper_protected: Option<bool>,
+    pub antivirus_signature_version: String,
+    /// A DMTF datetime, see [`datetime::raw`](crate::datetime::raw).
+    #[serde(with = "crate::datetime::raw::option")]
+    pub antivirus_signature_last_updated: Option<String>,
+    /// In days, `u32::MAX` if there was never a scan.
+    pub quick_scan_age: u32,
+    pub full_scan_age: u32,
+}
+
+///
+/// ### Additional security center methods
+///
+impl WMIConnection {
+    /// List the registered antivirus products.
+    ///
+    /// Connects to [`SECURITY_CENTER_NAMESPACE`] on the same computer,
+    /// which fails with `WBEM_E_INVALID_NAMESPACE` on Windows Server.
+    pub fn antivirus_products(&self) -> WMIResult<Vec<SecurityProduct>> {
+        self.security_products("AntiVirusProduct")
+    }
+
+    /// List the registered antispyware products, see [`antivirus_products`](Self::antivirus_products).
+    pub fn antispyware_products(&self) -> WMIResult<Vec<SecurityProduct>> {
+        self.security_products("AntiSpywareProduct")
+    }
+
+    /// List the registered firewall products, see [`antivirus_products`](Self::antivirus_products).
+    pub fn firewall_products(&self) -> WMIResult<Vec<SecurityProduct>> {
+        self.security_products("FirewallProduct")
+    }
+
+    fn security_products(&self, class: &str) -> WMIResult<Vec<SecurityProduct>> {
+        self.with_namespace(SECURITY_CENTER_NAMESPACE)?
+            .raw_query(format!("SELECT * FROM {}", class))
+    }
+
+    /// Read the status of Microsoft Defender.
+    ///
+    /// Connects to [`DEFENDER_NAMESPACE`] on the same computer.
+    pub fn defender_status(&self) -> WMIResult<DefenderStatus> {
+        self.with_namespace(DEFENDER_NAMESPACE)?.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    #[test]
+    fn it_decodes_product_states() {
+        // Microsoft Defender, enabled and up to date.
+        let state = ProductState::from(397568);
+        assert_eq!(state.status, ProductStatus::On);
+        assert_eq!(state.signature_status, SignatureStatus::UpToDate);
+        assert!(state.is_microsoft);
+        assert!(state.providers.contains(SecurityProviders::ANTIVIRUS));
+        assert!(state
+            .providers
+            .contains(SecurityProviders::AUTOUPDATE_SETTINGS));
+        assert!(!state.providers.contains(SecurityProviders::FIREWALL));
+
+        // Microsoft Defender, disabled and out of date.
+        let state = ProductState::from(393488);
+        assert_eq!(state.status, ProductStatus::Off);
+        assert!(!state.signature_up_to_date());
+
+        // A third-party antivirus, enabled.
+        let state = ProductState::from(266240);
+        assert_eq!(state.status, ProductStatus::On);
+        assert!(!state.is_microsoft);
+        assert_eq!(state.providers, SecurityProviders(0x04));
+    }
+
+    #[test]
+    fn it_lists_security_products() {
+        let wmi_con = wmi_con();
+
+        let is_server = wmi_con.os_info().unwrap().caption.contains("Server");
+
+        match wmi_con.antivirus_products() {
+            Ok(products) => {
+                assert!(products
+                    .iter()
+                    .all(|product| !product.instance_guid.is_empty()));
+            }
+            Err(err) => assert!(is_server, "{}", err),
+        }
+    }
+}
@@ -0,0 +1,354 @@
+//! Thermal zones, batteries and OEM sensors, using the classes of the `ROOT\WMI` namespace.
+//!
+//! `ROOT\WMI` exposes the data blocks of kernel drivers (like the ACPI driver), which differ from the `ROOT\CIMV2` classes:
+//!
+//! - Instances are identified by their `InstanceName` (like `ACPI\ThermalZone\THM0_0`), which is shared by all the classes
+//!   describing the same device. This is how [`Sensors::batteries`] joins `BatteryStatus` and `BatteryFullChargedCapacity`.
+//! - Temperatures are in tenths of Kelvin (`2982` is 25.05 °C), see [`TenthsKelvin`].
+//! - When no driver provides a class (like on most virtual machines), queries fail with `WBEM_E_NOT_SUPPORTED`,
+//!   which the methods of [`Sensors`] return as an empty list.
+//! - Most classes require administrative rights.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! let sensors = con.sensors()?;
+//!
+//! # // Reading the thermal zones requires administrative rights.
+//! # if let Ok(zones) = sensors.thermal_zones() {
+//! for zone in zones {
+//!     println!("{}: {:.1} °C", zone.instance_name, zone.celsius());
+//! }
+//! # }
+//!
+//! # if let Ok(batteries) = sensors.batteries() {
+//! for battery in batteries {
+//!     println!("{}: {:?}% charged", battery.instance_name, battery.charge_percent());
+//! }
+//! # }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! GPU and motherboard sensors are only exposed by OEM-specific classes (when at all),
+//! which can be read using [`Sensors::oem_readings`] or deserialized into custom structs using [`Sensors::connection`].
+use crate::{connection::WMIConnection, units::TenthsKelvin, Variant, WMIError, WMIResult};
+use serde::Deserialize;
+use std::collections::HashMap;
+use windows::Win32::System::Wmi::WBEM_E_NOT_SUPPORTED;
+
+/// The namespace of the kernel driver data blocks.
+pub const ROOT_WMI_NAMESPACE: &str = "ROOT\\WMI";
+
+/// An ACPI thermal zone, from `MSAcpi_ThermalZoneTemperature`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "MSAcpi_ThermalZoneTemperature")]
+#[serde(rename_all = "PascalCase")]
+pub struct ThermalZone {
+    /// Like `ACPI\ThermalZone\THM0_0`.
+    pub instance_name: String,
+    pub active: bool,
+    pub current_temperature: TenthsKelvin,
+    /// The temperature at which the system shuts down.
+    pub critical_trip_point: Option<TenthsKelvin>,
+    /// The temperature at which the processors are throttled.
+    pub passive_trip_point: Option<TenthsKelvin>,
+    /// The number of times the thermal zone changed since boot.
+    pub thermal_stamp: Option<u32>,
+}
+
+impl ThermalZone {
+    /// The current temperature, in degrees Celsius.
+    pub fn celsius(&self) -> f64 {
+        self.current_temperature.celsius()
+    }
+
+    /// The critical temperature, in degrees Celsius.
+    pub fn critical_celsius(&self) -> Option<f64> {
+        self.critical_trip_point.map(TenthsKelvin::celsius)
+    }
+
+    /// The passive cooling temperature, in degrees Celsius.
+    pub fn passive_celsius(&self) -> Option<f64> {
+        self.passive_trip_point.map(TenthsKelvin::celsius)
+    }
+}
+
+/// The current state of a battery, from `BatteryStatus`.
+///
+/// Capacities are in mWh, rates in mW and voltages in mV.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "BatteryStatus")]
+#[serde(rename_all = "PascalCase")]
+pub struct BatteryStatus {
+    /// Like `ACPI\PNP0C0A\1_0`.
+    pub instance_name: String,
+    pub active: bool,
+    /// Whether the computer is on AC power.
+    pub power_online: bool,
+    pub charging: bool,
+    pub discharging: bool,
+    pub critical: bool,
+    pub remaining_capacity: u32,
+    pub charge_rate: Option<u32>,
+    pub discharge_rate: Option<u32>,
+    pub voltage: Option<u32>,
+}
+
+/// The capacity of a fully charged battery (in mWh), from `BatteryFullChargedCapacity`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "BatteryFullChargedCapacity")]
+#[serde(rename_all = "PascalCase")]
+pub struct BatteryFullChargedCapacity {
+    pub instance_name: String,
+    pub active: bool,
+    pub full_charged_capacity: u32,
+}
+
+/// The manufacturer data of a battery, from `BatteryStaticData`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "BatteryStaticData")]
+#[serde(rename_all = "PascalCase")]
+pub struct BatteryStaticData {
+    pub instance_name: String,
+    pub active: bool,
+    /// The capacity of a new battery, in mWh.
+    pub designed_capacity: u32,
+    pub device_name: Option<String>,
+    pub manufacture_name: Option<String>,
+    pub serial_number: Option<String>,
+}
+
+/// A battery, combining the classes describing it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Battery {
+    pub instance_name: String,
+    pub status: BatteryStatus,
+    /// In mWh, `None` if the driver does not report it.
+    pub full_charged_capacity: Option<u32>,
+    /// In mWh, `None` if the driver does not report it.
+    pub designed_capacity: Option<u32>,
+}
+
+impl Battery {
+    /// The current charge, as a percentage of the full charged capacity.
+    pub fn charge_percent(&self) -> Option<f64> {
+        percent(self.status.remaining_capacity, self.full_charged_capacity?)
+    }
+
+    /// The full charged capacity, as a percentage of the designed capacity (the "health" of the battery).
+    pub fn health_percent(&self) -> Option<f64> {
+        percent(self.full_charged_capacity?, self.designed_capacity?)
+    }
+}
+
+fn percent(value: u32, total: u32) -> Option<f64> {
+    if total == 0 {
+        return None;
+    }
+
+    Some(f64::from(value) * 100.0 / f64::from(total))
+}
+
+/// A property of an OEM sensor class, as returned by [`Sensors::oem_readings`].
+#[derive(Debug, PartialEq)]
+pub struct SensorReading {
+    pub instance_name: String,
+    pub value: Variant,
+}
+
+/// A connection to the `ROOT\WMI` namespace, created using [`WMIConnection::sensors`].
+#[derive(Debug, Clone)]
+pub struct Sensors {
+    con: WMIConnection,
+}
+
+///
+/// ### Additional sensor methods
+///
+impl WMIConnection {
+    /// Connect to the `ROOT\WMI` namespace on the same computer.
+    ///
+    /// See the [module level documentation](crate::sensors) for an example.
+    pub fn sensors(&self) -> WMIResult<Sensors> {
+        let con = self.with_namespace(ROOT_WMI_NAMESPACE)?;
+
+        Ok(Sensors { con })
+    }
+}
+
+impl Sensors {
+    /// The connection to the `ROOT\WMI` namespace, to query other classes.
+    pub fn connection(&self) -> &WMIConnection {
+        &self.con
+    }
+
+    pub fn thermal_zones(&self) -> WMIResult<Vec<ThermalZone>> {
+        not_supported_as_empty(self.con.query())
+    }
+
+    pub fn battery_statuses(&self) -> WMIResult<Vec<BatteryStatus>> {
+        not_supported_as_empty(self.con.query())
+    }
+
+    pub fn battery_full_charged_capacities(&self) -> WMIResult<Vec<BatteryFullChargedCapacity>> {
+        not_supported_as_empty(self.con.query())
+    }
+
+    pub fn battery_static_data(&self) -> WMIResult<Vec<BatteryStaticData>> {
+        not_supported_as_empty(self.con.query())
+    }
+
+    /// List the batteries, joining their status with their capacities using their instance names.
+    pub fn batteries(&self) -> WMIResult<Vec<Battery>> {
+        let full_charged: HashMap<_, _> = self
+            .battery_full_charged_capacities()?
+            .into_iter()
+            .map(|capacity| (capacity.instance_name, capacity.full_charged_capacity))
+            .collect();
+
+        // Reading the static data fails on some drivers, which should not hide the status.
+        let designed: HashMap<_, _> = self
+            .battery_static_data()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|data| (data.instance_name, data.designed_capacity))
+            .collect();
+
+        Ok(join_batteries(
+            self.battery_statuses()?,
+            &full_charged,
+            &designed,
+        ))
+    }
+
+    /// Read a property of all the instances of an OEM sensor class (like a vendor-specific GPU temperature),
+    /// along with their instance names.
+    pub fn oem_readings(&self, class: &str, property: &str) -> WMIResult<Vec<SensorReading>> {
+        let objs: Vec<HashMap<String, Variant>> = not_supported_as_empty(
+            self.con
+                .raw_query(format!("SELECT InstanceName, {} FROM {}", property, class)),
+        )?;
+
+        objs.into_iter()
+            .map(|mut obj| {
+                let instance_name = match obj.remove("InstanceName") {
+                    Some(Variant::String(name)) => name,
+                    _ => {
+                        return Err(WMIError::ConvertVariantError(format!(
+                            "{} has no InstanceName",
+                            class
+                        )))
+                    }
+                };
+
+                Ok(SensorReading {
+                    instance_name,
+                    value: obj.remove(property).unwrap_or(Variant::Null),
+                })
+            })
+            .collect()
+    }
+}
+
+fn join_batteries(
+    statuses: Vec<BatteryStatus>,
+    full_charged: &HashMap<String, u32>,
+    designed: &HashMap<String, u32>,
+) -> Vec<Battery> {
+    statuses
+        .into_iter()
+        .map(|status| Battery {
+            instance_name: status.instance_name.clone(),
+            full_charged_capacity: full_charged.get(&status.instance_name).copied(),
+            designed_capacity: designed.get(&status.instance_name).copied(),
+            status,
+        })
+        .collect()
+}
+
+/// `ROOT\WMI` classes fail with `WBEM_E_NOT_SUPPORTED` when no driver provides them.
+fn not_supported_as_empty<T>(result: WMIResult<Vec<T>>) -> WMIResult<Vec<T>> {
+    match result {
+        Err(WMIError::HResultError { hres }) if hres == WBEM_E_NOT_SUPPORTED.0 => Ok(vec![]),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    fn status(instance_name: &str, remaining_capacity: u32) -> BatteryStatus {
+        BatteryStatus {
+            instance_name: instance_name.to_owned(),
+            active: true,
+            power_online: false,
+            charging: false,
+            discharging: true,
+            critical: false,
+            remaining_capacity,
+            charge_rate: None,
+            discharge_rate: Some(9000),
+            voltage: Some(12000),
+        }
+    }
+
+    #[test]
+    fn it_joins_battery_classes() {
+        let full_charged = HashMap::from([("ACPI\\PNP0C0A\\1_0".to_owned(), 40000)]);
+        let designed = HashMap::from([("ACPI\\PNP0C0A\\1_0".to_owned(), 50000)]);
+
+        let batteries = join_batteries(
+            vec![
+                status("ACPI\\PNP0C0A\\1_0", 10000),
+                status("ACPI\\PNP0C0A\\2_0", 10000),
+            ],
+            &full_charged,
+            &designed,
+        );
+
+        assert_eq!(batteries[0].charge_percent(), Some(25.0));
+        assert_eq!(batteries[0].health_percent(), Some(80.0));
+        assert_eq!(batteries[1].full_charged_capacity, None);
+        assert_eq!(batteries[1].charge_percent(), None);
+    }
+
+    #[test]
+    fn it_reads_sensors() {
+        let wmi_con = wmi_con();
+        let sensors = wmi_con.sensors().unwrap();
+
+        // Requires administrative rights.
+        match sensors.thermal_zones() {
+            Ok(zones) => {
+                for zone in zones {
+                    assert!(zone.instance_name.starts_with("ACPI\\"));
+                    assert!(zone.celsius() > -273.15);
+                }
+            }
+            Err(WMIError::HResultError { .. }) => {}
+            Err(err) => panic!("{}", err),
+        }
+
+        match sensors.batteries() {
+            Ok(batteries) => {
+                for battery in batteries {
+                    assert_eq!(battery.instance_name, battery.status.instance_name);
+                }
+            }
+            Err(WMIError::HResultError { .. }) => {}
+            Err(err) => panic!("{}", err),
+        }
+
+        let readings = sensors
+            .oem_readings("MSAcpi_ThermalZoneTemperature", "CurrentTemperature")
+            .unwrap_or_default();
+
+        for reading in readings {
+            assert!(matches!(reading.value, Variant::UI4(_)));
+        }
+    }
+}
@@ -0,0 +1,548 @@
+//! Serializing Rust values into [`Variant`]s, to write them as properties of WMI objects.
+//!
+//! Values are converted into the `VARIANT` types WMI expects when setting properties
+//! (see [Numbers](https://learn.microsoft.com/en-us/windows/win32/wmisdk/numbers)):
+//!
+//! | Rust                        | `Variant`                                        |
+//! |-----------------------------|--------------------------------------------------|
+//! | `bool`                      | `Bool`                                           |
+//! | `i8`, `i16`                 | `I2`                                             |
+//! | `u8`                        | `UI1`                                            |
+//! | `u16`, `i32`, `u32`         | `I4` (`u32` values are reinterpreted as signed)  |
+//! | `i64`, `u64`                | `String`                                         |
+//! | `f32` / `f64`               | `R4` / `R8`                                      |
+//! | `char`, `&str`, `String`    | `String`                                         |
+//! | `None`, `()`                | `Null`                                           |
+//! | Sequences                   | `Array` (`Null` for empty sequences)             |
+//! | Unit enum variants          | `String` (the name of the variant)               |
+//!
+//! Newtype structs (like [`Bytes`](crate::units::Bytes)) are serialized as their inner value.
+//! Note that datetimes are serialized using their `Serialize` implementation (as RFC 3339 strings),
+//! so they cannot be written as `datetime` properties.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! use serde::Serialize;
+//! use wmi::{ser::to_properties, Variant};
+//!
+//! #[derive(Serialize)]
+//! #[serde(rename_all = "PascalCase")]
+//! struct Share {
+//!     name: String,
+//!     maximum_allowed: Option<u32>,
+//! }
+//!
+//! let share = Share { name: "Docs".to_owned(), maximum_allowed: Some(10) };
+//!
+//! assert_eq!(
+//!     to_properties(&share)?,
+//!     vec![
+//!         ("Name".to_owned(), Variant::String("Docs".to_owned())),
+//!         ("MaximumAllowed".to_owned(), Variant::I4(10)),
+//!     ]
+//! );
+//! #   Ok(())
+//! # }
+//! ```
+use crate::{Variant, WMIError, WMIResult};
+use serde::ser::{
+    Impossible, Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeTuple,
+    SerializeTupleStruct, Serializer,
+};
+
+/// Serialize a value into a [`Variant`].
+pub fn to_variant<T>(value: &T) -> WMIResult<Variant>
+where
+    T: Serialize + ?Sized,
+{
+    value.serialize(VariantSerializer)
+}
+
+/// Serialize a struct (or a map with string keys) into its properties, in order.
+pub fn to_properties<T>(value: &T) -> WMIResult<Vec<(String, Variant)>>
+where
+    T: Serialize + ?Sized,
+{
+    value.serialize(PropertiesSerializer)
+}
+
+fn unsupported(what: &str) -> WMIError {
+    WMIError::SerdeError(format!("Cannot serialize {} into a Variant", what))
+}
+
+struct VariantSerializer;
+
+impl Serializer for VariantSerializer {
+    type Ok = Variant;
+    type Error = WMIError;
+
+    type SerializeSeq = ArraySerializer;
+    type SerializeTuple = ArraySerializer;
+    type SerializeTupleStruct = ArraySerializer;
+    type SerializeTupleVariant = Impossible<Variant, WMIError>;
+    type SerializeMap = Impossible<Variant, WMIError>;
+    type SerializeStruct = Impossible<Variant, WMIError>;
+    type SerializeStructVariant = Impossible<Variant, WMIError>;
+
+    fn serialize_bool(self, v: bool) -> WMIResult<Variant> {
+        Ok(Variant::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> WMIResult<Variant> {
+        Ok(Variant::I2(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> WMIResult<Variant> {
+        Ok(Variant::I2(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> WMIResult<Variant> {
+        Ok(Variant::I4(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> WMIResult<Variant> {
+        Ok(Variant::String(v.to_string()))
+    }
+
+    fn serialize_u8(self, v: u8) -> WMIResult<Variant> {
+        Ok(Variant::UI1(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> WMIResult<Variant> {
+        Ok(Variant::I4(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> WMIResult<Variant> {
+        Ok(Variant::I4(v as i32))
+    }
+
+    fn serialize_u64(self, v: u64) -> WMIResult<Variant> {
+        Ok(Variant::String(v.to_string()))
+    }
+
+    fn serialize_f32(self, v: f32) -> WMIResult<Variant> {
+        Ok(Variant::R4(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> WMIResult<Variant> {
+        Ok(Variant::R8(v))
+    }
+
+    fn serialize_char(self, v: char) -> WMIResult<Variant> {
+        Ok(Variant::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> WMIResult<Variant> {
+        Ok(Variant::String(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> WMIResult<Variant> {
+        self.collect_seq(v)
+    }
+
+    fn serialize_none(self) -> WMIResult<Variant> {
+        Ok(Variant::Null)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> WMIResult<Variant>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> WMIResult<Variant> {
+        Ok(Variant::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> WMIResult<Variant> {
+        Ok(Variant::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> WMIResult<Variant> {
+        Ok(Variant::String(variant.to_owned()))
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> WMIResult<Variant>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _value: &T,
+    ) -> WMIResult<Variant>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(unsupported(variant))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> WMIResult<ArraySerializer> {
+        Ok(ArraySerializer(Vec::with_capacity(len.unwrap_or_default())))
+    }
+
+    fn serialize_tuple(self, len: usize) -> WMIResult<ArraySerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> WMIResult<ArraySerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> WMIResult<Self::SerializeTupleVariant> {
+        Err(unsupported(variant))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> WMIResult<Self::SerializeMap> {
+        Err(unsupported("a map"))
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> WMIResult<Self::SerializeStruct> {
+        Err(unsupported(name))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> WMIResult<Self::SerializeStructVariant> {
+        Err(unsupported(variant))
+    }
+}
+
+struct ArraySerializer(Vec<Variant>);
+
+impl ArraySerializer {
+    fn end_array(self) -> WMIResult<Variant> {
+        // WMI can't tell the type of an empty array.
+        if self.0.is_empty() {
+            return Ok(Variant::Null);
+        }
+
+        Ok(Variant::Array(self.0))
+    }
+}
+
+impl SerializeSeq for ArraySerializer {
+    type Ok = Variant;
+    type Error = WMIError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> WMIResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.0.push(value.serialize(VariantSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> WMIResult<Variant> {
+        self.end_array()
+    }
+}
+
+impl SerializeTuple for ArraySerializer {
+    type Ok = Variant;
+    type Error = WMIError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> WMIResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> WMIResult<Variant> {
+        self.end_array()
+    }
+}
+
+impl SerializeTupleStruct for ArraySerializer {
+    type Ok = Variant;
+    type Error = WMIError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> WMIResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> WMIResult<Variant> {
+        self.end_array()
+    }
+}
+
+struct PropertiesSerializer;
+
+macro_rules! not_properties {
+    ($($method:ident($($arg:ty),*)),* $(,)?) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> WMIResult<Self::Ok> {
+                Err(WMIError::SerdeError(
+                    "Only structs and maps can be serialized into properties".to_owned(),
+                ))
+            }
+        )*
+    };
+}
+
+impl Serializer for PropertiesSerializer {
+    type Ok = Vec<(String, Variant)>;
+    type Error = WMIError;
+
+    type SerializeSeq = Impossible<Self::Ok, WMIError>;
+    type SerializeTuple = Impossible<Self::Ok, WMIError>;
+    type SerializeTupleStruct = Impossible<Self::Ok, WMIError>;
+    type SerializeTupleVariant = Impossible<Self::Ok, WMIError>;
+    type SerializeMap = PropertiesBuilder;
+    type SerializeStruct = PropertiesBuilder;
+    type SerializeStructVariant = Impossible<Self::Ok, WMIError>;
+
+    not_properties!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(&'static str),
+        serialize_unit_variant(&'static str, u32, &'static str),
+    );
+
+    fn serialize_some<T>(self, value: &T) -> WMIResult<Self::Ok>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> WMIResult<Self::Ok>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _value: &T,
+    ) -> WMIResult<Self::Ok>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(unsupported(variant))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> WMIResult<Self::SerializeSeq> {
+        Err(unsupported("a sequence"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> WMIResult<Self::SerializeTuple> {
+        Err(unsupported("a tuple"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> WMIResult<Self::SerializeTupleStruct> {
+        Err(unsupported(name))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> WMIResult<Self::SerializeTupleVariant> {
+        Err(unsupported(variant))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> WMIResult<PropertiesBuilder> {
+        Ok(PropertiesBuilder {
+            properties: Vec::with_capacity(len.unwrap_or_default()),
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> WMIResult<PropertiesBuilder> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> WMIResult<Self::SerializeStructVariant> {
+        Err(unsupported(variant))
+    }
+}
+
+struct PropertiesBuilder {
+    properties: Vec<(String, Variant)>,
+    next_key: Option<String>,
+}
+
+impl SerializeMap for PropertiesBuilder {
+    type Ok = Vec<(String, Variant)>;
+    type Error = WMIError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> WMIResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        match key.serialize(VariantSerializer)? {
+            Variant::String(key) => {
+                self.next_key = Some(key);
+                Ok(())
+            }
+            other => Err(WMIError::SerdeError(format!(
+                "Property names must be strings, got {:?}",
+                other
+            ))),
+        }
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> WMIResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| WMIError::SerdeError("Value serialized before its key".to_owned()))?;
+
+        self.properties
+            .push((key, value.serialize(VariantSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> WMIResult<Self::Ok> {
+        Ok(self.properties)
+    }
+}
+
+impl SerializeStruct for PropertiesBuilder {
+    type Ok = Vec<(String, Variant)>;
+    type Error = WMIError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> WMIResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.properties
+            .push((key.to_owned(), value.serialize(VariantSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> WMIResult<Self::Ok> {
+        Ok(self.properties)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::Bytes;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn it_serializes_values_into_variants() {
+        assert_eq!(to_variant(&true).unwrap(), Variant::Bool(true));
+        assert_eq!(to_variant(&-1i8).unwrap(), Variant::I2(-1));
+        assert_eq!(to_variant(&7u8).unwrap(), Variant::UI1(7));
+        assert_eq!(to_variant(&u32::MAX).unwrap(), Variant::I4(-1));
+        assert_eq!(
+            to_variant(&u64::MAX).unwrap(),
+            Variant::String("18446744073709551615".to_owned())
+        );
+        assert_eq!(
+            to_variant(&Bytes(10)).unwrap(),
+            Variant::String("10".to_owned())
+        );
+        assert_eq!(to_variant(&None::<u32>).unwrap(), Variant::Null);
+        assert_eq!(
+            to_variant(&["a", "b"]).unwrap(),
+            Variant::Array(vec![
+                Variant::String("a".to_owned()),
+                Variant::String("b".to_owned())
+            ])
+        );
+        assert_eq!(to_variant(&Vec::<u32>::new()).unwrap(), Variant::Null);
+    }
+
+    #[test]
+    fn it_serializes_structs_into_properties() {
+        #[derive(Serialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Share {
+            name: String,
+            #[serde(skip)]
+            _ignored: u32,
+            status: Status,
+        }
+
+        #[derive(Serialize)]
+        enum Status {
+            Running,
+        }
+
+        let share = Share {
+            name: "Docs".to_owned(),
+            _ignored: 0,
+            status: Status::Running,
+        };
+
+        assert_eq!(
+            to_properties(&share).unwrap(),
+            vec![
+                ("Name".to_owned(), Variant::String("Docs".to_owned())),
+                ("Status".to_owned(), Variant::String("Running".to_owned())),
+            ]
+        );
+
+        let map = BTreeMap::from([("Caption", 1u16)]);
+        assert_eq!(
+            to_properties(&map).unwrap(),
+            vec![("Caption".to_owned(), Variant::I4(1))]
+        );
+
+        assert!(to_properties(&1u32).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+//! Create and enumerate Volume Shadow Copies, using the `Win32_ShadowCopy` class.
+//!
+//! Creating shadow copies requires administrative rights, and client versions of Windows
+//! only support the [`ShadowCopyContext::ClientAccessible`] context.
+//!
+//! ```edition2018,no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use wmi::shadow_copy::ShadowCopyContext;
+//!
+//! let id = con.create_shadow_copy("C:\\", ShadowCopyContext::ClientAccessible)?;
+//! let shadow_copy = con.get_shadow_copy(&id)?;
+//!
+//! // Files can be read using paths like `\\?\GLOBALROOT\Device\HarddiskVolumeShadowCopy1\Windows\win.ini`.
+//! println!("{}", shadow_copy.device_object);
+//!
+//! con.delete_shadow_copy(&id)?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    connection::WMIConnection,
+    query::quote_and_escape_wql_str,
+    return_code::{MethodError, ShadowCopyError},
+    Variant, WMIError, WMIResult,
+};
+use serde::Deserialize;
+use std::fmt;
+
+/// A shadow copy of a volume, from `Win32_ShadowCopy`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "Win32_ShadowCopy")]
+#[serde(rename_all = "PascalCase")]
+pub struct ShadowCopy {
+    /// Like `{9A5F7F4C-7AE6-4A07-9B5B-3F1C6A3E2F10}`.
+    #[serde(rename = "ID")]
+    pub id: String,
+    /// The id of the set of shadow copies which were created together.
+    #[serde(rename = "SetID")]
+    pub set_id: String,
+    /// The id of the provider which created the shadow copy.
+    #[serde(rename = "ProviderID")]
+    pub provider_id: String,
+    /// The device of the shadow copy, like `\\?\GLOBALROOT\Device\HarddiskVolumeShadowCopy1`.
+    pub device_object: String,
+    /// The original volume, like `\\?\Volume{...}\`.
+    pub volume_name: String,
+    /// A DMTF datetime, see [`datetime::raw`](crate::datetime::raw).
+    #[serde(with = "crate::datetime::raw::option")]
+    pub install_date: Option<String>,
+    pub originating_machine: Option<String>,
+    pub service_machine: Option<String>,
+    /// Whether the shadow copy is kept after the process which created it exits.
+    pub persistent: bool,
+    pub client_accessible: bool,
+    pub no_auto_release: bool,
+    /// The number of shadow copies in the set.
+    pub count: u32,
+}
+
+/// The context in which a shadow copy is created, the `Context` parameter of `Win32_ShadowCopy.Create`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ShadowCopyContext {
+    /// A persistent shadow copy, accessible to users (the only context supported by client versions of Windows).
+    #[default]
+    ClientAccessible,
+    /// Like `ClientAccessible`, but the writers of applications are involved.
+    ClientAccessibleWriters,
+    /// A non-persistent shadow copy, for backups.
+    Backup,
+    /// A non-persistent shadow copy of a file share, for backups.
+    FileShareBackup,
+    /// A persistent shadow copy, created without the writers of applications.
+    NasRollback,
+    /// A persistent shadow copy, for restoring applications.
+    AppRollback,
+}
+
+impl ShadowCopyContext {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShadowCopyContext::ClientAccessible => "ClientAccessible",
+            ShadowCopyContext::ClientAccessibleWriters => "ClientAccessibleWriters",
+            ShadowCopyContext::Backup => "Backup",
+            ShadowCopyContext::FileShareBackup => "FileShareBackup",
+            ShadowCopyContext::NasRollback => "NASRollback",
+            ShadowCopyContext::AppRollback => "AppRollback",
+        }
+    }
+}
+
+impl fmt::Display for ShadowCopyContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+///
+/// ### Additional shadow copy methods
+///
+impl WMIConnection {
+    /// List the shadow copies of all the volumes.
+    pub fn shadow_copies(&self) -> WMIResult<Vec<ShadowCopy>> {
+        self.query()
+    }
+
+    /// List the shadow copies of a volume, given its name (like `\\?\Volume{...}\`).
+    pub fn shadow_copies_of(&self, volume_name: &str) -> WMIResult<Vec<ShadowCopy>> {
+        let query = format!(
+            "SELECT * FROM Win32_ShadowCopy WHERE VolumeName = {}",
+            quote_and_escape_wql_str(volume_name)
+        );
+
+        self.raw_query(query)
+    }
+
+    pub fn get_shadow_copy(&self, id: &str) -> WMIResult<ShadowCopy> {
+        self.get_by_path(&shadow_copy_path(id))
+    }
+
+    /// Create a shadow copy of a volume, given its drive (like `C:\`) or its name (like `\\?\Volume{...}\`),
+    /// and return the id of the new shadow copy.
+    ///
+    /// See the [module level documentation](crate::shadow_copy) for an example.
+    pub fn create_shadow_copy(
+        &self,
+        volume: &str,
+        context: ShadowCopyContext,
+    ) -> Result<String, MethodError<ShadowCopyError>> {
+        let out = self
+            .exec_method_mapped::<ShadowCopyError>(
+                "Win32_ShadowCopy",
+                "Create",
+                &[
+                    ("Volume", volume.into()),
+                    ("Context", context.as_str().into()),
+                ],
+            )?
+            .ok_or(WMIError::NullPointerResult)?;
+
+        match out.get_property("ShadowID")? {
+            Variant::String(id) => Ok(id),
+            other => Err(WMIError::ConvertVariantError(format!(
+                "Expected the ShadowID to be a string, got {:?}",
+                other
+            ))
+            .into()),
+        }
+    }
+
+    /// Delete a shadow copy, given its id.
+    pub fn delete_shadow_copy(&self, id: &str) -> WMIResult<()> {
+        self.delete_instance(&shadow_copy_path(id))
+    }
+}
+
+fn shadow_copy_path(id: &str) -> String {
+    format!("Win32_ShadowCopy.ID=\"{}\"", id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    #[test]
+    fn it_formats_shadow_copy_contexts() {
+        assert_eq!(ShadowCopyContext::default().as_str(), "ClientAccessible");
+        assert_eq!(ShadowCopyContext::NasRollback.to_string(), "NASRollback");
+        assert_eq!(
+            shadow_copy_path("{00000000-0000-0000-0000-000000000000}"),
+            r#"Win32_ShadowCopy.ID="{00000000-0000-0000-0000-000000000000}""#
+        );
+    }
+
+    #[test]
+    fn it_lists_shadow_copies() {
+        let wmi_con = wmi_con();
+
+        // Requires administrative rights.
+        let shadow_copies = match wmi_con.shadow_copies() {
+            Ok(shadow_copies) => shadow_copies,
+            Err(WMIError::HResultError { .. }) => return,
+            Err(err) => panic!("{}", err),
+        };
+
+        for shadow_copy in shadow_copies {
+            assert!(shadow_copy.device_object.contains("ShadowCopy"));
+
+            let same = wmi_con.get_shadow_copy(&shadow_copy.id).unwrap();
+            assert_eq!(same, shadow_copy);
+
+            let of_volume = wmi_con.shadow_copies_of(&shadow_copy.volume_name).unwrap();
+            assert!(of_volume.contains(&shadow_copy));
+        }
+    }
+
+    #[test]
+    fn it_maps_shadow_copy_errors() {
+        let wmi_con = wmi_con();
+
+        let res = wmi_con.create_shadow_copy(
+            "\\\\?\\Volume{00000000-0000-0000-0000-000000000000}\\",
+            ShadowCopyContext::ClientAccessible,
+        );
+
+        match res {
+            Err(MethodError::ReturnCode(err)) => assert!(matches!(
+                err,
+                ShadowCopyError::AccessDenied
+                    | ShadowCopyError::InvalidArgument
+                    | ShadowCopyError::VolumeNotFound
+            )),
+            // Requires administrative rights.
+            Err(MethodError::Wmi(WMIError::HResultError { .. })) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+}
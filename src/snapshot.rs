@@ -0,0 +1,237 @@
+//! Detect changes between two runs of the same query, when event queries are not available (or too expensive).
+//!
+//! A [`Snapshot`] keeps the results of a query by key (like the `__Path` of the instances, or a name),
+//! and [`Snapshot::diff`] lists the instances which were added, removed or changed since an older snapshot:
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, Debug, PartialEq)]
+//! #[serde(rename = "Win32_Service")]
+//! #[serde(rename_all = "PascalCase")]
+//! struct Service {
+//!     name: String,
+//!     state: String,
+//! }
+//!
+//! let before = con.snapshot(|service: &Service| service.name.clone())?;
+//! // ...
+//! let after = con.snapshot(|service: &Service| service.name.clone())?;
+//!
+//! let diff = after.diff(&before);
+//! for (old, new) in &diff.changed {
+//!     println!("{}: {} -> {}", new.name, old.state, new.state);
+//! }
+//! println!("{} added, {} removed", diff.added.len(), diff.removed.len());
+//! # Ok(())
+//! # }
+//! ```
+use crate::{connection::WMIConnection, WMIResult};
+use serde::de::DeserializeOwned;
+use std::{
+    collections::{btree_map, BTreeMap},
+    time::SystemTime,
+};
+
+/// The results of a query, by key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot<T> {
+    taken_at: SystemTime,
+    items: BTreeMap<String, T>,
+}
+
+/// The differences between two snapshots, as returned by [`Snapshot::diff`].
+///
+/// All the lists are ordered by key.
+#[derive(Debug, PartialEq)]
+pub struct SnapshotDiff<'a, T> {
+    /// Items which are only in the newer snapshot.
+    pub added: Vec<&'a T>,
+    /// Items which are only in the older snapshot.
+    pub removed: Vec<&'a T>,
+    /// Items which are in both snapshots but are not equal, as `(old, new)`.
+    pub changed: Vec<(&'a T, &'a T)>,
+}
+
+impl<T> SnapshotDiff<'_, T> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl<T> Snapshot<T> {
+    /// Create a snapshot from the results of a query.
+    ///
+    /// If several items have the same key, only the last one is kept.
+    pub fn new<I, F>(items: I, key: F) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        F: Fn(&T) -> String,
+    {
+        Self {
+            taken_at: SystemTime::now(),
+            items: items.into_iter().map(|item| (key(&item), item)).collect(),
+        }
+    }
+
+    /// When the snapshot was created.
+    pub fn taken_at(&self) -> SystemTime {
+        self.taken_at
+    }
+
+    pub fn get(&self, key: &str) -> Option<&T> {
+        self.items.get(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Iterate over the keys and items, ordered by key.
+    pub fn iter(&self) -> btree_map::Iter<'_, String, T> {
+        self.items.iter()
+    }
+
+    /// Compare this snapshot with an older one.
+    pub fn diff<'a>(&'a self, older: &'a Snapshot<T>) -> SnapshotDiff<'a, T>
+    where
+        T: PartialEq,
+    {
+        let mut diff = SnapshotDiff {
+            added: vec![],
+            removed: vec![],
+            changed: vec![],
+        };
+
+        for (key, new) in &self.items {
+            match older.items.get(key) {
+                Some(old) if old != new => diff.changed.push((old, new)),
+                Some(_) => {}
+                None => diff.added.push(new),
+            }
+        }
+
+        diff.removed = older
+            .items
+            .iter()
+            .filter(|(key, _)| !self.items.contains_key(*key))
+            .map(|(_, old)| old)
+            .collect();
+
+        diff
+    }
+
+    pub fn into_inner(self) -> BTreeMap<String, T> {
+        self.items
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Snapshot<T> {
+    type Item = (&'a String, &'a T);
+    type IntoIter = btree_map::Iter<'a, String, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+///
+/// ### Additional snapshot methods
+///
+impl WMIConnection {
+    /// Query all the objects of type T, and keep them by the key returned by `key`.
+    ///
+    /// See the [module level documentation](crate::snapshot) for an example.
+    pub fn snapshot<T, F>(&self, key: F) -> WMIResult<Snapshot<T>>
+    where
+        T: DeserializeOwned,
+        F: Fn(&T) -> String,
+    {
+        Ok(Snapshot::new(self.query()?, key))
+    }
+
+    /// Execute a WQL query, and keep the results by the key returned by `key`.
+    pub fn raw_snapshot<T, F>(&self, query: impl AsRef<str>, key: F) -> WMIResult<Snapshot<T>>
+    where
+        T: DeserializeOwned,
+        F: Fn(&T) -> String,
+    {
+        Ok(Snapshot::new(self.raw_query(query)?, key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq)]
+    struct Product {
+        name: &'static str,
+        version: &'static str,
+    }
+
+    fn snapshot(products: &[(&'static str, &'static str)]) -> Snapshot<Product> {
+        Snapshot::new(
+            products
+                .iter()
+                .map(|&(name, version)| Product { name, version }),
+            |product| product.name.to_owned(),
+        )
+    }
+
+    #[test]
+    fn it_diffs_snapshots() {
+        let older = snapshot(&[("7-Zip", "22.01"), ("Git", "2.40"), ("Python", "3.11")]);
+        let newer = snapshot(&[("Git", "2.43"), ("Python", "3.11"), ("Rust", "1.75")]);
+
+        let diff = newer.diff(&older);
+
+        assert_eq!(diff.added, vec![newer.get("Rust").unwrap()]);
+        assert_eq!(diff.removed, vec![older.get("7-Zip").unwrap()]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].0.version, "2.40");
+        assert_eq!(diff.changed[0].1.version, "2.43");
+
+        assert!(newer.diff(&newer).is_empty());
+    }
+
+    #[test]
+    fn it_keeps_the_last_item_of_a_key() {
+        let snapshot = snapshot(&[("Git", "2.40"), ("Git", "2.43")]);
+
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot.get("Git").unwrap().version, "2.43");
+    }
+
+    #[test]
+    fn it_snapshots_query_results() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(rename = "Win32_Service")]
+        #[serde(rename_all = "PascalCase")]
+        struct Service {
+            name: String,
+        }
+
+        let wmi_con = wmi_con();
+
+        let older = wmi_con
+            .snapshot(|service: &Service| service.name.clone())
+            .unwrap();
+        let newer = wmi_con
+            .snapshot(|service: &Service| service.name.clone())
+            .unwrap();
+
+        assert!(older.get("Winmgmt").is_some());
+        assert!(newer.diff(&older).changed.is_empty());
+        assert!(newer.taken_at() >= older.taken_at());
+    }
+}
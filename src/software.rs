@@ -0,0 +1,270 @@
+//! Guard against queries of `Win32_Product`, and read the installed software from the registry instead.
+//!
+//! Enumerating `Win32_Product` makes the Windows Installer run a consistency check of every installed MSI package,
+//! which is slow and can trigger repairs that reset the configuration of applications (see [KB974524]).
+//! Queries of `Win32_Product` (and getting its instances by path) therefore fail with [`WMIError::Win32ProductQuery`],
+//! unless the connection opts in using [`WMIConnection::with_win32_product_allowed`].
+//! The same applies to the WS-Management and MI connections, which opt in using the `allow_win32_product` method of their builders.
+//!
+//! [`WMIConnection::installed_software`] reads the same information from the `Uninstall` registry keys
+//! (using the `StdRegProv` provider), which is also what "Programs and Features" shows.
+//!
+//! [KB974524]: https://support.microsoft.com/en-us/help/974524
+use crate::{
+    connection::{ComTransport, WMIConnection},
+    method::class_of_path,
+    Variant, WMIError, WMIResult,
+};
+use log::warn;
+
+const HKEY_LOCAL_MACHINE: u32 = 0x80000002;
+
+/// The registry keys listing installed software, for 64-bit and 32-bit applications.
+const UNINSTALL_KEYS: &[&str] = &[
+    "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall",
+    "SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall",
+];
+
+/// An installed application, as returned by [`WMIConnection::installed_software`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledSoftware {
+    /// The full path of the registry key of the application (relative to `HKEY_LOCAL_MACHINE`).
+    pub key: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub publisher: Option<String>,
+    /// The installation date, usually in the `YYYYMMDD` format.
+    pub install_date: Option<String>,
+    pub install_location: Option<String>,
+    pub uninstall_string: Option<String>,
+}
+
+/// Check if the query references the `Win32_Product` class (outside of string literals).
+pub(crate) fn is_win32_product_query(query: &str) -> bool {
+    let mut quote = None;
+    let mut identifier = String::new();
+
+    for c in query.chars().chain(std::iter::once(' ')) {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c.is_ascii_alphanumeric() || c == '_' => {
+                identifier.push(c);
+                continue;
+            }
+            None => {}
+        }
+
+        if identifier.eq_ignore_ascii_case("Win32_Product") {
+            return true;
+        }
+
+        identifier.clear();
+    }
+
+    false
+}
+
+/// Check if the object path is the path of a `Win32_Product` instance (getting the class itself is harmless).
+pub(crate) fn is_win32_product_instance_path(object_path: &str) -> bool {
+    object_path.contains('=') && class_of_path(object_path).eq_ignore_ascii_case("Win32_Product")
+}
+
+/// Fail with [`WMIError::Win32ProductQuery`] if `Win32_Product` is referenced, and not allowed.
+pub(crate) fn check_win32_product(referenced: bool, allowed: bool) -> WMIResult<()> {
+    if !referenced {
+        return Ok(());
+    }
+
+    if !allowed {
+        return Err(WMIError::Win32ProductQuery);
+    }
+
+    warn!("Querying Win32_Product, which reconfigures every installed MSI package");
+
+    Ok(())
+}
+
+impl ComTransport {
+    pub(crate) fn check_win32_product(&self, query: &str) -> WMIResult<()> {
+        check_win32_product(is_win32_product_query(query), self.allow_win32_product)
+    }
+
+    pub(crate) fn check_win32_product_path(&self, object_path: &str) -> WMIResult<()> {
+        check_win32_product(
+            is_win32_product_instance_path(object_path),
+            self.allow_win32_product,
+        )
+    }
+}
+
+///
+/// ### Additional installed software methods
+///
+impl WMIConnection {
+    /// Create a copy of this connection which allows (or forbids) queries of `Win32_Product`.
+    ///
+    /// See the [module level documentation](crate::software) for why these are forbidden by default.
+    pub fn with_win32_product_allowed(&self, allowed: bool) -> Self {
+        let mut con = self.clone();
+        con.allow_win32_product = allowed;
+        con
+    }
+
+    /// List the installed software (both 64-bit and 32-bit), sorted by name.
+    ///
+    /// The `StdRegProv` class must be available in the namespace of the connection,
+    /// which is the case for `ROOT\CIMV2` and `ROOT\DEFAULT`.
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// # let con = WMIConnection::new(COMLibrary::new()?)?;
+    /// for software in con.installed_software()? {
+    ///     println!("{} {}", software.name, software.version.unwrap_or_default());
+    /// }
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn installed_software(&self) -> WMIResult<Vec<InstalledSoftware>> {
+        let mut installed = vec![];
+
+        for uninstall_key in UNINSTALL_KEYS {
+            for subkey in self.registry_subkeys(uninstall_key)? {
+                let key = format!("{}\\{}", uninstall_key, subkey);
+
+                // Updates and components do not have a display name.
+                let name = match self.registry_string(&key, "DisplayName")? {
+                    Some(name) => name,
+                    None => continue,
+                };
+
+                installed.push(InstalledSoftware {
+                    name,
+                    version: self.registry_string(&key, "DisplayVersion")?,
+                    publisher: self.registry_string(&key, "Publisher")?,
+                    install_date: self.registry_string(&key, "InstallDate")?,
+                    install_location: self.registry_string(&key, "InstallLocation")?,
+                    uninstall_string: self.registry_string(&key, "UninstallString")?,
+                    key,
+                });
+            }
+        }
+
+        installed.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(installed)
+    }
+
+    /// The names of the subkeys of a `HKEY_LOCAL_MACHINE` key, or nothing if the key does not exist.
+    fn registry_subkeys(&self, key: &str) -> WMIResult<Vec<String>> {
+        let out = self.exec_method(
+            "StdRegProv",
+            "EnumKey",
+            &[
+                ("hDefKey", Variant::I4(HKEY_LOCAL_MACHINE as i32)),
+                ("sSubKeyName", key.into()),
+            ],
+        )?;
+
+        let out = match out {
+            Some(out) if out.get_property("ReturnValue")? == Variant::UI4(0) => out,
+            _ => return Ok(vec![]),
+        };
+
+        match out.get_property("sNames")? {
+            Variant::Array(names) => names.into_iter().map(String::try_from).collect(),
+            _ => Ok(vec![]),
+        }
+    }
+
+    /// The value of a string (or expandable string) value of a `HKEY_LOCAL_MACHINE` key,
+    /// or `None` if it does not exist.
+    fn registry_string(&self, key: &str, value: &str) -> WMIResult<Option<String>> {
+        for method in ["GetStringValue", "GetExpandedStringValue"] {
+            let out = self.exec_method(
+                "StdRegProv",
+                method,
+                &[
+                    ("hDefKey", Variant::I4(HKEY_LOCAL_MACHINE as i32)),
+                    ("sSubKeyName", key.into()),
+                    ("sValueName", value.into()),
+                ],
+            )?;
+
+            if let Some(out) = out {
+                if out.get_property("ReturnValue")? == Variant::UI4(0) {
+                    if let Variant::String(value) = out.get_property("sValue")? {
+                        return Ok(Some(value));
+                    }
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn it_detects_win32_product_queries() {
+        assert!(is_win32_product_query("SELECT * FROM Win32_Product"));
+        assert!(is_win32_product_query(
+            "select Name from win32_product where Vendor = 'Contoso'"
+        ));
+        assert!(is_win32_product_query(
+            "ASSOCIATORS OF {Win32_Product.IdentifyingNumber='x'}"
+        ));
+        assert!(!is_win32_product_query("SELECT * FROM Win32_ProductCheck"));
+        assert!(!is_win32_product_query(
+            "SELECT * FROM Win32_Process WHERE Name = 'Win32_Product'"
+        ));
+
+        assert!(is_win32_product_instance_path(
+            r#"Win32_Product.IdentifyingNumber="{x}",Name="y",Version="1.0""#
+        ));
+        assert!(is_win32_product_instance_path(
+            r#"\\.\ROOT\CIMV2:win32_product.IdentifyingNumber="{x}""#
+        ));
+        assert!(!is_win32_product_instance_path("Win32_Product"));
+        assert!(!is_win32_product_instance_path(
+            r#"Win32_Process.Handle="Win32_Product""#
+        ));
+    }
+
+    #[test]
+    fn it_refuses_win32_product_queries() {
+        let wmi_con = wmi_con();
+
+        let result: WMIResult<Vec<HashMap<String, Variant>>> =
+            wmi_con.raw_query("SELECT Name FROM Win32_Product");
+        assert!(matches!(result, Err(WMIError::Win32ProductQuery)));
+
+        let result: WMIResult<Vec<HashMap<String, Variant>>> = wmi_con
+            .with_win32_product_allowed(true)
+            .raw_query("SELECT Name FROM Win32_Product WHERE Name = 'no-such-product'");
+        assert!(result.unwrap().is_empty());
+
+        let result = wmi_con
+            .get_raw_by_path(r#"Win32_Product.IdentifyingNumber="{x}",Name="y",Version="1.0""#);
+        assert!(matches!(result, Err(WMIError::Win32ProductQuery)));
+    }
+
+    #[test]
+    fn it_lists_installed_software() {
+        let wmi_con = wmi_con();
+
+        let installed = wmi_con.installed_software().unwrap();
+
+        assert!(installed.iter().all(|software| !software.name.is_empty()));
+        assert!(installed
+            .iter()
+            .all(|software| software.key.starts_with("SOFTWARE\\")));
+    }
+}
@@ -0,0 +1,617 @@
+//! Programs which run automatically (startup commands and scheduled tasks), for auditing persistence mechanisms.
+//!
+//! Startup commands come from `Win32_StartupCommand` (the `Run` registry keys and the Startup folders).
+//! Scheduled tasks come from `MSFT_ScheduledTask` (in the [`TASK_SCHEDULER_NAMESPACE`] namespace),
+//! and legacy `at` jobs from `Win32_ScheduledJob`. The triggers of both are normalized into [`TaskTrigger`]s.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! for command in con.startup_commands()? {
+//!     println!("{:?} {} ({})", command.scope(), command.command, command.location);
+//! }
+//!
+//! for task in con.scheduled_tasks()? {
+//!     for trigger in &task.triggers {
+//!         println!("{}{}: {}", task.task_path, task.task_name, trigger);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{connection::WMIConnection, WMIResult};
+use serde::Deserialize;
+use std::fmt;
+
+/// The namespace of the Task Scheduler provider.
+pub const TASK_SCHEDULER_NAMESPACE: &str = "ROOT\\Microsoft\\Windows\\TaskScheduler";
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+/// A program which runs when a user logs on, from `Win32_StartupCommand`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "Win32_StartupCommand")]
+#[serde(rename_all = "PascalCase")]
+pub struct StartupCommand {
+    pub name: String,
+    /// The command line, like `"C:\Program Files\App\app.exe" --minimized`.
+    pub command: String,
+    /// Like `HKLM\SOFTWARE\Microsoft\Windows\CurrentVersion\Run`, `Startup` or `Common Startup`.
+    pub location: String,
+    /// Like `Public` (for all users), `NT AUTHORITY\SYSTEM` or `CONTOSO\user`.
+    pub user: Option<String>,
+    #[serde(rename = "UserSID")]
+    pub user_sid: Option<String>,
+}
+
+/// Whether a startup command runs for all users or for a single one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StartupScope {
+    Machine,
+    User,
+}
+
+impl StartupCommand {
+    pub fn scope(&self) -> StartupScope {
+        let location = self.location.to_ascii_uppercase();
+
+        if location.starts_with("HKLM") || location.starts_with("COMMON ") {
+            StartupScope::Machine
+        } else {
+            StartupScope::User
+        }
+    }
+}
+
+/// The state of a scheduled task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TaskState {
+    Unknown,
+    Disabled,
+    Queued,
+    Ready,
+    Running,
+}
+
+impl From<u32> for TaskState {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => TaskState::Disabled,
+            2 => TaskState::Queued,
+            3 => TaskState::Ready,
+            4 => TaskState::Running,
+            _ => TaskState::Unknown,
+        }
+    }
+}
+
+/// What starts a scheduled task.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TriggerKind {
+    Boot,
+    /// When `user_id` (or any user, if `None`) logs on.
+    Logon {
+        user_id: Option<String>,
+    },
+    /// Once, at the start boundary.
+    Once,
+    Daily {
+        days_interval: u16,
+    },
+    Weekly {
+        weeks_interval: u16,
+        /// A bitmask of the days, where Sunday is `1` and Saturday is `64`.
+        days_of_week: u16,
+    },
+    Monthly {
+        /// A bitmask of the days of the month, where the 1st is `1`.
+        days_of_month: u32,
+    },
+    Idle,
+    /// When an event matching the (XPath) subscription is logged.
+    Event {
+        subscription: Option<String>,
+    },
+    /// When a session is connected, disconnected, locked or unlocked (see `TASK_SESSION_STATE_CHANGE_TYPE`).
+    SessionStateChange {
+        state_change: u32,
+    },
+    /// When the task is created or modified.
+    Registration,
+    /// A trigger class not known to this crate.
+    Other(String),
+}
+
+/// A normalized trigger, of a scheduled task or of a legacy job.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TaskTrigger {
+    pub kind: TriggerKind,
+    pub enabled: bool,
+    /// When the trigger is activated, like `2024-03-14T10:15:00` (with an optional UTC offset).
+    pub start_boundary: Option<String>,
+    pub end_boundary: Option<String>,
+    /// How often the task is repeated after it is triggered, as an ISO 8601 duration (like `PT1H`).
+    pub repetition_interval: Option<String>,
+}
+
+impl TaskTrigger {
+    /// The names of the days of a [`TriggerKind::Weekly`] trigger.
+    pub fn weekdays(&self) -> Vec<&'static str> {
+        match &self.kind {
+            TriggerKind::Weekly { days_of_week, .. } => WEEKDAYS
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| days_of_week & (1 << i) != 0)
+                .map(|(_, day)| *day)
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+impl fmt::Display for TaskTrigger {
+    /// Describes the trigger, like `Weekly every 1 week(s) on Monday, Friday from 2024-03-14T10:15:00`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            TriggerKind::Boot => write!(f, "At startup")?,
+            TriggerKind::Logon { user_id: None } => write!(f, "At logon of any user")?,
+            TriggerKind::Logon {
+                user_id: Some(user_id),
+            } => write!(f, "At logon of {}", user_id)?,
+            TriggerKind::Once => write!(f, "Once")?,
+            TriggerKind::Daily { days_interval } => {
+                write!(f, "Daily every {} day(s)", days_interval)?
+            }
+            TriggerKind::Weekly { weeks_interval, .. } => write!(
+                f,
+                "Weekly every {} week(s) on {}",
+                weeks_interval,
+                self.weekdays().join(", ")
+            )?,
+            TriggerKind::Monthly { days_of_month } => {
+                let days: Vec<String> = (0..31)
+                    .filter(|day| days_of_month & (1 << day) != 0)
+                    .map(|day| (day + 1).to_string())
+                    .collect();
+
+                write!(f, "Monthly on day(s) {}", days.join(", "))?
+            }
+            TriggerKind::Idle => write!(f, "When idle")?,
+            TriggerKind::Event { subscription } => write!(
+                f,
+                "On event {}",
+                subscription.as_deref().unwrap_or_default()
+            )?,
+            TriggerKind::SessionStateChange { state_change } => {
+                write!(f, "On session state change {}", state_change)?
+            }
+            TriggerKind::Registration => write!(f, "At task creation")?,
+            TriggerKind::Other(class) => write!(f, "{}", class)?,
+        }
+
+        if let Some(start_boundary) = &self.start_boundary {
+            write!(f, " from {}", start_boundary)?;
+        }
+
+        if let Some(interval) = &self.repetition_interval {
+            write!(f, ", repeated every {}", interval)?;
+        }
+
+        if !self.enabled {
+            write!(f, " (disabled)")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An action of a scheduled task.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TaskAction {
+    /// Run a program.
+    Exec {
+        execute: String,
+        arguments: Option<String>,
+        working_directory: Option<String>,
+    },
+    /// Run a COM handler, by its CLSID.
+    ComHandler { class_id: Option<String> },
+    /// An action class not known to this crate (like the deprecated e-mail and message actions).
+    Other(String),
+}
+
+/// A scheduled task, from `MSFT_ScheduledTask`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledTask {
+    /// The folder of the task, like `\Microsoft\Windows\Defrag\`.
+    pub task_path: String,
+    pub task_name: String,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    pub state: TaskState,
+    /// The user (or group) the task runs as, like `SYSTEM` or `Users`.
+    pub principal: Option<String>,
+    /// Whether the task runs with the highest privileges of its principal.
+    pub run_elevated: bool,
+    pub actions: Vec<TaskAction>,
+    pub triggers: Vec<TaskTrigger>,
+}
+
+/// A legacy job created by `at.exe` or `Win32_ScheduledJob.Create`, from `Win32_ScheduledJob`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledJob {
+    pub job_id: u32,
+    pub command: String,
+    /// The user who created the job.
+    pub owner: Option<String>,
+    pub trigger: TaskTrigger,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename = "MSFT_ScheduledTask")]
+#[serde(rename_all = "PascalCase")]
+struct RawTask {
+    task_path: String,
+    task_name: String,
+    author: Option<String>,
+    description: Option<String>,
+    state: Option<u32>,
+    principal: Option<RawPrincipal>,
+    actions: Option<Vec<RawAction>>,
+    triggers: Option<Vec<RawTrigger>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename = "MSFT_TaskPrincipal")]
+#[serde(rename_all = "PascalCase")]
+struct RawPrincipal {
+    user_id: Option<String>,
+    group_id: Option<String>,
+    /// `1` for `TASK_RUNLEVEL_HIGHEST`.
+    run_level: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename = "MSFT_TaskAction")]
+#[serde(rename_all = "PascalCase")]
+struct RawAction {
+    #[serde(rename = "__Class")]
+    class: String,
+    execute: Option<String>,
+    arguments: Option<String>,
+    working_directory: Option<String>,
+    class_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename = "MSFT_TaskTrigger")]
+#[serde(rename_all = "PascalCase")]
+struct RawTrigger {
+    #[serde(rename = "__Class")]
+    class: String,
+    enabled: Option<bool>,
+    start_boundary: Option<String>,
+    end_boundary: Option<String>,
+    repetition: Option<RawRepetition>,
+    days_interval: Option<u16>,
+    weeks_interval: Option<u16>,
+    days_of_week: Option<u16>,
+    user_id: Option<String>,
+    subscription: Option<String>,
+    state_change: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename = "MSFT_TaskRepetitionPattern")]
+#[serde(rename_all = "PascalCase")]
+struct RawRepetition {
+    interval: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename = "Win32_ScheduledJob")]
+#[serde(rename_all = "PascalCase")]
+struct RawJob {
+    job_id: u32,
+    command: String,
+    owner: Option<String>,
+    /// A DMTF time, like `********123000.000000-420`.
+    start_time: Option<String>,
+    run_repeatedly: Option<bool>,
+    /// A bitmask of the days, where Monday is `1` and Sunday is `64`.
+    days_of_week: Option<u32>,
+    days_of_month: Option<u32>,
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.is_empty())
+}
+
+impl From<RawTrigger> for TaskTrigger {
+    fn from(raw: RawTrigger) -> Self {
+        let kind = match raw.class.as_str() {
+            "MSFT_TaskBootTrigger" => TriggerKind::Boot,
+            "MSFT_TaskLogonTrigger" => TriggerKind::Logon {
+                user_id: non_empty(raw.user_id),
+            },
+            "MSFT_TaskTimeTrigger" => TriggerKind::Once,
+            "MSFT_TaskDailyTrigger" => TriggerKind::Daily {
+                days_interval: raw.days_interval.unwrap_or(1),
+            },
+            "MSFT_TaskWeeklyTrigger" => TriggerKind::Weekly {
+                weeks_interval: raw.weeks_interval.unwrap_or(1),
+                days_of_week: raw.days_of_week.unwrap_or_default(),
+            },
+            "MSFT_TaskIdleTrigger" => TriggerKind::Idle,
+            "MSFT_TaskEventTrigger" => TriggerKind::Event {
+                subscription: non_empty(raw.subscription),
+            },
+            "MSFT_TaskSessionStateChangeTrigger" => TriggerKind::SessionStateChange {
+                state_change: raw.state_change.unwrap_or_default(),
+            },
+            "MSFT_TaskRegistrationTrigger" => TriggerKind::Registration,
+            _ => TriggerKind::Other(raw.class),
+        };
+
+        TaskTrigger {
+            kind,
+            enabled: raw.enabled.unwrap_or(true),
+            start_boundary: non_empty(raw.start_boundary),
+            end_boundary: non_empty(raw.end_boundary),
+            repetition_interval: raw
+                .repetition
+                .and_then(|repetition| non_empty(repetition.interval)),
+        }
+    }
+}
+
+impl From<RawAction> for TaskAction {
+    fn from(raw: RawAction) -> Self {
+        match raw.class.as_str() {
+            "MSFT_TaskExecAction" => TaskAction::Exec {
+                execute: raw.execute.unwrap_or_default(),
+                arguments: non_empty(raw.arguments),
+                working_directory: non_empty(raw.working_directory),
+            },
+            "MSFT_TaskComHandlerAction" => TaskAction::ComHandler {
+                class_id: non_empty(raw.class_id),
+            },
+            _ => TaskAction::Other(raw.class),
+        }
+    }
+}
+
+impl From<RawTask> for ScheduledTask {
+    fn from(raw: RawTask) -> Self {
+        let (principal, run_elevated) = match raw.principal {
+            Some(principal) => (
+                non_empty(principal.user_id).or_else(|| non_empty(principal.group_id)),
+                principal.run_level == Some(1),
+            ),
+            None => (None, false),
+        };
+
+        ScheduledTask {
+            task_path: raw.task_path,
+            task_name: raw.task_name,
+            author: non_empty(raw.author),
+            description: non_empty(raw.description),
+            state: raw.state.unwrap_or_default().into(),
+            principal,
+            run_elevated,
+            actions: raw
+                .actions
+                .unwrap_or_default()
+                .into_iter()
+                .map(TaskAction::from)
+                .collect(),
+            triggers: raw
+                .triggers
+                .unwrap_or_default()
+                .into_iter()
+                .map(TaskTrigger::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<RawJob> for ScheduledJob {
+    fn from(raw: RawJob) -> Self {
+        // Legacy jobs number the days from Monday, unlike the Task Scheduler (which starts from Sunday).
+        let days_of_week = raw
+            .days_of_week
+            .map(|days| (((days & 0x3F) << 1) | ((days >> 6) & 1)) as u16)
+            .unwrap_or_default();
+
+        let kind = match (raw.run_repeatedly, days_of_week, raw.days_of_month) {
+            (Some(true), days_of_week, _) if days_of_week != 0 => TriggerKind::Weekly {
+                weeks_interval: 1,
+                days_of_week,
+            },
+            (Some(true), _, Some(days_of_month)) if days_of_month != 0 => {
+                TriggerKind::Monthly { days_of_month }
+            }
+            _ => TriggerKind::Once,
+        };
+
+        // Only the time of day is set, like `********123000.000000-420`.
+        let start_boundary = raw.start_time.and_then(|start_time| {
+            let time = start_time.get(8..14)?;
+            Some(format!("{}:{}:{}", &time[..2], &time[2..4], &time[4..]))
+        });
+
+        ScheduledJob {
+            job_id: raw.job_id,
+            command: raw.command,
+            owner: non_empty(raw.owner),
+            trigger: TaskTrigger {
+                kind,
+                enabled: true,
+                start_boundary,
+                end_boundary: None,
+                repetition_interval: None,
+            },
+        }
+    }
+}
+
+///
+/// ### Additional startup and scheduled task methods
+///
+impl WMIConnection {
+    /// List the programs which run when users log on.
+    ///
+    /// See the [module level documentation](crate::startup) for an example.
+    pub fn startup_commands(&self) -> WMIResult<Vec<StartupCommand>> {
+        self.query()
+    }
+
+    /// List the scheduled tasks, connecting to the [`TASK_SCHEDULER_NAMESPACE`] namespace on the same computer.
+    pub fn scheduled_tasks(&self) -> WMIResult<Vec<ScheduledTask>> {
+        let tasks: Vec<RawTask> = self.with_namespace(TASK_SCHEDULER_NAMESPACE)?.query()?;
+
+        Ok(tasks.into_iter().map(ScheduledTask::from).collect())
+    }
+
+    /// List the legacy jobs created by `at.exe` (which are not listed by [`scheduled_tasks`](Self::scheduled_tasks)).
+    pub fn scheduled_jobs(&self) -> WMIResult<Vec<ScheduledJob>> {
+        let jobs: Vec<RawJob> = self.query()?;
+
+        Ok(jobs.into_iter().map(ScheduledJob::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    fn trigger(class: &str) -> RawTrigger {
+        RawTrigger {
+            class: class.to_owned(),
+            enabled: Some(true),
+            start_boundary: Some("2024-03-14T10:15:00".to_owned()),
+            end_boundary: Some(String::new()),
+            repetition: None,
+            days_interval: None,
+            weeks_interval: None,
+            days_of_week: None,
+            user_id: None,
+            subscription: None,
+            state_change: None,
+        }
+    }
+
+    #[test]
+    fn it_normalizes_task_triggers() {
+        let mut raw = trigger("MSFT_TaskWeeklyTrigger");
+        raw.weeks_interval = Some(2);
+        raw.days_of_week = Some(0b10_0010);
+        raw.repetition = Some(RawRepetition {
+            interval: Some("PT1H".to_owned()),
+        });
+
+        let weekly = TaskTrigger::from(raw);
+        assert_eq!(
+            weekly.kind,
+            TriggerKind::Weekly {
+                weeks_interval: 2,
+                days_of_week: 0b10_0010
+            }
+        );
+        assert_eq!(weekly.end_boundary, None);
+        assert_eq!(weekly.weekdays(), ["Monday", "Friday"]);
+        assert_eq!(
+            weekly.to_string(),
+            "Weekly every 2 week(s) on Monday, Friday from 2024-03-14T10:15:00, repeated every PT1H"
+        );
+
+        let mut raw = trigger("MSFT_TaskLogonTrigger");
+        raw.enabled = Some(false);
+        raw.start_boundary = None;
+        raw.user_id = Some(String::new());
+        assert_eq!(
+            TaskTrigger::from(raw).to_string(),
+            "At logon of any user (disabled)"
+        );
+
+        assert_eq!(
+            TaskTrigger::from(trigger("MSFT_TaskCustomTrigger")).kind,
+            TriggerKind::Other("MSFT_TaskCustomTrigger".to_owned())
+        );
+    }
+
+    #[test]
+    fn it_normalizes_legacy_jobs() {
+        let job = ScheduledJob::from(RawJob {
+            job_id: 1,
+            command: "backup.cmd".to_owned(),
+            owner: None,
+            start_time: Some("********123000.000000-420".to_owned()),
+            run_repeatedly: Some(true),
+            // Monday and Sunday.
+            days_of_week: Some(1 | 64),
+            days_of_month: None,
+        });
+
+        assert_eq!(job.trigger.weekdays(), ["Sunday", "Monday"]);
+        assert_eq!(job.trigger.start_boundary.as_deref(), Some("12:30:00"));
+
+        let job = ScheduledJob::from(RawJob {
+            job_id: 2,
+            command: "report.cmd".to_owned(),
+            owner: None,
+            start_time: None,
+            run_repeatedly: Some(true),
+            days_of_week: None,
+            days_of_month: Some(1 | (1 << 14)),
+        });
+
+        assert_eq!(job.trigger.to_string(), "Monthly on day(s) 1, 15");
+    }
+
+    #[test]
+    fn it_classifies_startup_commands() {
+        let command = |location: &str| StartupCommand {
+            name: "app".to_owned(),
+            command: "app.exe".to_owned(),
+            location: location.to_owned(),
+            user: None,
+            user_sid: None,
+        };
+
+        assert_eq!(
+            command("HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Run").scope(),
+            StartupScope::Machine
+        );
+        assert_eq!(command("Common Startup").scope(), StartupScope::Machine);
+        assert_eq!(
+            command("HKU\\S-1-5-21-1-2-3-1001\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Run")
+                .scope(),
+            StartupScope::User
+        );
+        assert_eq!(command("Startup").scope(), StartupScope::User);
+    }
+
+    #[test]
+    fn it_lists_startup_items() {
+        let wmi_con = wmi_con();
+
+        wmi_con.startup_commands().unwrap();
+        wmi_con.scheduled_jobs().unwrap();
+
+        let tasks = wmi_con.scheduled_tasks().unwrap();
+        assert!(tasks
+            .iter()
+            .any(|task| task.task_path.starts_with("\\Microsoft\\Windows\\")));
+    }
+}
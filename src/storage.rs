@@ -0,0 +1,457 @@
+//! Disks, partitions and volumes, using the Storage Management API (`ROOT\Microsoft\Windows\Storage`),
+//! which is also used by the `Get-Disk`, `Get-Partition` and `Get-Volume` cmdlets.
+//!
+//! When a method of these classes fails, the provider describes the error using an `MSFT_StorageExtendedStatus`
+//! object in the `ExtendedStatus` output parameter, which is returned as [`WMIError::ExtendedStatusError`].
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! let storage = con.storage()?;
+//!
+//! for disk in storage.disks()? {
+//!     println!("Disk {} ({:?}): {} bytes", disk.number, disk.friendly_name, disk.size);
+//!
+//!     for partition in storage.partitions(&disk)? {
+//!         println!("  Partition {} ({:?}): {} bytes", partition.partition_number, partition.drive_letter, partition.size);
+//!     }
+//! }
+//!
+//! for volume in storage.volumes()? {
+//!     println!("{:?} {:?}: {} of {} bytes free", volume.drive_letter, volume.file_system, volume.size_remaining, volume.size);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    connection::WMIConnection, result_enumerator::IWbemClassWrapper, Variant, WMIError, WMIResult,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+
+/// The namespace of the Storage Management API.
+pub const STORAGE_NAMESPACE: &str = "ROOT\\Microsoft\\Windows\\Storage";
+
+/// The partition style of a disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PartitionStyle {
+    /// The disk is not initialized.
+    Raw,
+    Mbr,
+    Gpt,
+    /// A value not known to this crate.
+    Other(u16),
+}
+
+impl From<u16> for PartitionStyle {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => PartitionStyle::Raw,
+            1 => PartitionStyle::Mbr,
+            2 => PartitionStyle::Gpt,
+            other => PartitionStyle::Other(other),
+        }
+    }
+}
+
+impl From<PartitionStyle> for u16 {
+    fn from(value: PartitionStyle) -> Self {
+        match value {
+            PartitionStyle::Raw => 0,
+            PartitionStyle::Mbr => 1,
+            PartitionStyle::Gpt => 2,
+            PartitionStyle::Other(other) => other,
+        }
+    }
+}
+
+fn deserialize_partition_style<'de, D>(deserializer: D) -> Result<PartitionStyle, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    u16::deserialize(deserializer).map(PartitionStyle::from)
+}
+
+/// Drive letters are `char16` properties, which are `0` when there is no drive letter.
+fn deserialize_drive_letter<'de, D>(deserializer: D) -> Result<Option<char>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let letter = Option::<u16>::deserialize(deserializer)?;
+
+    Ok(letter
+        .filter(|letter| *letter != 0)
+        .and_then(|letter| char::from_u32(letter.into())))
+}
+
+/// A disk, from `MSFT_Disk`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "MSFT_Disk")]
+#[serde(rename_all = "PascalCase")]
+pub struct Disk {
+    #[serde(rename = "__Path")]
+    pub path: String,
+    #[serde(rename = "ObjectId")]
+    pub object_id: String,
+    /// The number used by `\\.\PhysicalDriveN`.
+    pub number: u32,
+    pub friendly_name: Option<String>,
+    pub serial_number: Option<String>,
+    pub size: u64,
+    pub allocated_size: Option<u64>,
+    #[serde(deserialize_with = "deserialize_partition_style")]
+    pub partition_style: PartitionStyle,
+    pub number_of_partitions: Option<u32>,
+    pub is_offline: bool,
+    pub is_read_only: bool,
+    pub is_boot: Option<bool>,
+    pub is_system: Option<bool>,
+    /// `0` (healthy), `1` (warning) or `2` (unhealthy).
+    pub health_status: Option<u16>,
+    /// Like `11` for SATA, `7` for USB or `17` for NVMe.
+    pub bus_type: Option<u16>,
+}
+
+/// A partition, from `MSFT_Partition`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "MSFT_Partition")]
+#[serde(rename_all = "PascalCase")]
+pub struct Partition {
+    #[serde(rename = "__Path")]
+    pub path: String,
+    #[serde(rename = "ObjectId")]
+    pub object_id: String,
+    pub disk_number: u32,
+    pub partition_number: u32,
+    #[serde(deserialize_with = "deserialize_drive_letter")]
+    pub drive_letter: Option<char>,
+    /// The drive letter and mount points of the partition (like `C:\`), and its volume path.
+    #[serde(default)]
+    pub access_paths: Vec<String>,
+    /// The offset of the partition on the disk, in bytes.
+    pub offset: u64,
+    pub size: u64,
+    /// The partition type of a GPT partition (a GUID, like `{ebd0a0a2-b9e5-4433-87c0-68b6b72699c7}`).
+    pub gpt_type: Option<String>,
+    /// The partition type of an MBR partition (like `7` for NTFS).
+    pub mbr_type: Option<u16>,
+    pub is_boot: Option<bool>,
+    pub is_system: Option<bool>,
+    pub is_hidden: Option<bool>,
+    pub is_read_only: Option<bool>,
+    pub is_offline: Option<bool>,
+}
+
+/// A volume, from `MSFT_Volume`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "MSFT_Volume")]
+#[serde(rename_all = "PascalCase")]
+pub struct Volume {
+    #[serde(rename = "__Path")]
+    pub path: String,
+    #[serde(rename = "ObjectId")]
+    pub object_id: String,
+    /// Like `\\?\Volume{...}\`.
+    #[serde(rename = "Path")]
+    pub volume_path: Option<String>,
+    #[serde(deserialize_with = "deserialize_drive_letter")]
+    pub drive_letter: Option<char>,
+    pub file_system_label: Option<String>,
+    /// Like `NTFS` or `ReFS`, empty if the volume is not formatted.
+    pub file_system: Option<String>,
+    pub size: u64,
+    pub size_remaining: u64,
+    /// `0` (healthy), `1` (warning) or `2` (unhealthy).
+    pub health_status: Option<u16>,
+    /// Like `3` for fixed volumes or `2` for removable ones.
+    pub drive_type: Option<u32>,
+}
+
+/// The error object returned by the storage methods, from `MSFT_StorageExtendedStatus`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "MSFT_StorageExtendedStatus")]
+#[serde(rename_all = "PascalCase")]
+pub struct ExtendedStatus {
+    /// A description of the error, like `The disk is offline.`.
+    pub message: Option<String>,
+    #[serde(rename = "MessageID")]
+    pub message_id: Option<String>,
+    #[serde(rename = "CIMStatusCode")]
+    pub cim_status_code: Option<u32>,
+}
+
+/// A connection to the storage namespace, created using [`WMIConnection::storage`].
+#[derive(Debug, Clone)]
+pub struct Storage {
+    con: WMIConnection,
+}
+
+///
+/// ### Additional storage methods
+///
+impl WMIConnection {
+    /// Connect to the storage namespace on the same computer.
+    ///
+    /// See the [module level documentation](crate::storage) for an example.
+    pub fn storage(&self) -> WMIResult<Storage> {
+        let con = self.with_namespace(STORAGE_NAMESPACE)?;
+
+        Ok(Storage { con })
+    }
+}
+
+impl Storage {
+    /// The connection to the storage namespace, to query other classes (like `MSFT_PhysicalDisk`).
+    pub fn connection(&self) -> &WMIConnection {
+        &self.con
+    }
+
+    pub fn disks(&self) -> WMIResult<Vec<Disk>> {
+        self.con.query()
+    }
+
+    /// List the partitions of a disk.
+    pub fn partitions(&self, disk: &Disk) -> WMIResult<Vec<Partition>> {
+        self.con.raw_query(format!(
+            "SELECT * FROM MSFT_Partition WHERE DiskNumber = {}",
+            disk.number
+        ))
+    }
+
+    pub fn volumes(&self) -> WMIResult<Vec<Volume>> {
+        self.con.query()
+    }
+
+    /// The volume of a partition, if it has one.
+    pub fn volume_of(&self, partition: &Partition) -> WMIResult<Option<Volume>> {
+        #[derive(Deserialize)]
+        struct MSFT_PartitionToVolume {}
+
+        let volumes = self
+            .con
+            .associators::<Volume, MSFT_PartitionToVolume>(&partition.path)?;
+
+        Ok(volumes.into_iter().next())
+    }
+
+    /// Bring a disk online.
+    pub fn online(&self, disk: &Disk) -> WMIResult<()> {
+        self.exec(&disk.path, "Online", &[])?;
+
+        Ok(())
+    }
+
+    /// Take a disk offline.
+    pub fn offline(&self, disk: &Disk) -> WMIResult<()> {
+        self.exec(&disk.path, "Offline", &[])?;
+
+        Ok(())
+    }
+
+    pub fn set_read_only(&self, disk: &Disk, read_only: bool) -> WMIResult<()> {
+        self.exec(
+            &disk.path,
+            "SetAttributes",
+            &[("IsReadOnly", Variant::Bool(read_only))],
+        )?;
+
+        Ok(())
+    }
+
+    /// Initialize a raw disk with the given partition style.
+    pub fn initialize(&self, disk: &Disk, partition_style: PartitionStyle) -> WMIResult<()> {
+        let partition_style = u16::from(partition_style);
+
+        self.exec(
+            &disk.path,
+            "Initialize",
+            &[("PartitionStyle", Variant::I4(partition_style.into()))],
+        )?;
+
+        Ok(())
+    }
+
+    /// Create a partition on a disk, using all of its free space if `size` is `None`.
+    ///
+    /// If `assign_drive_letter` is set, the next available drive letter is assigned to the partition.
+    pub fn create_partition(
+        &self,
+        disk: &Disk,
+        size: Option<u64>,
+        assign_drive_letter: bool,
+    ) -> WMIResult<Partition> {
+        let size = match size {
+            // 64-bit integers are passed as strings.
+            Some(size) => ("Size", Variant::String(size.to_string())),
+            None => ("UseMaximumSize", Variant::Bool(true)),
+        };
+
+        let out = self.exec(
+            &disk.path,
+            "CreatePartition",
+            &[
+                size,
+                ("AssignDriveLetter", Variant::Bool(assign_drive_letter)),
+            ],
+        )?;
+
+        embedded_object(&out, "CreatedPartition")
+    }
+
+    /// Resize a partition (and its file system, if it supports it) to `size` bytes.
+    pub fn resize(&self, partition: &Partition, size: u64) -> WMIResult<()> {
+        self.exec(
+            &partition.path,
+            "Resize",
+            &[("Size", Variant::String(size.to_string()))],
+        )?;
+
+        Ok(())
+    }
+
+    /// Delete a partition.
+    pub fn delete_partition(&self, partition: &Partition) -> WMIResult<()> {
+        self.exec(&partition.path, "DeleteObject", &[])?;
+
+        Ok(())
+    }
+
+    /// Format a volume (using a quick format), returning it with its new properties.
+    pub fn format(
+        &self,
+        volume: &Volume,
+        file_system: &str,
+        file_system_label: Option<&str>,
+    ) -> WMIResult<Volume> {
+        let mut in_params = vec![("FileSystem", Variant::from(file_system))];
+
+        if let Some(label) = file_system_label {
+            in_params.push(("FileSystemLabel", Variant::from(label)));
+        }
+
+        let out = self.exec(&volume.path, "Format", &in_params)?;
+
+        embedded_object(&out, "FormattedVolume")
+    }
+
+    /// Execute a method, and fail with the provider's extended status if its return value is an error.
+    fn exec(
+        &self,
+        object_path: &str,
+        method: &str,
+        in_params: &[(&str, Variant)],
+    ) -> WMIResult<IWbemClassWrapper> {
+        let out = self
+            .con
+            .exec_method(object_path, method, in_params)?
+            .ok_or(WMIError::NullPointerResult)?;
+
+        match u32::try_from(out.get_property("ReturnValue")?)? {
+            0 => Ok(out),
+            return_value => Err(extended_status_error(method, return_value, &out)),
+        }
+    }
+}
+
+/// Deserialize an embedded object output parameter.
+fn embedded_object<T>(out: &IWbemClassWrapper, name: &str) -> WMIResult<T>
+where
+    T: DeserializeOwned,
+{
+    match out.get_property(name)? {
+        Variant::Object(object) => object.into_desr(),
+        other => Err(WMIError::ConvertVariantError(format!(
+            "Expected {} to be an object, got {:?}",
+            name, other
+        ))),
+    }
+}
+
+/// Build the error of a failed method, using the message of its `ExtendedStatus` (if it returned one).
+fn extended_status_error(method: &str, return_value: u32, out: &IWbemClassWrapper) -> WMIError {
+    let status = match out.get_property("ExtendedStatus") {
+        Ok(Variant::Object(object)) => object.into_desr::<ExtendedStatus>().ok(),
+        _ => None,
+    };
+
+    match status.and_then(|status| status.message) {
+        Some(message) => WMIError::ExtendedStatusError {
+            method: method.to_owned(),
+            return_value,
+            message,
+        },
+        None => WMIError::MethodFailed {
+            method: method.to_owned(),
+            return_value,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    #[test]
+    fn it_maps_partition_styles() {
+        assert_eq!(PartitionStyle::from(2), PartitionStyle::Gpt);
+        assert_eq!(PartitionStyle::from(7), PartitionStyle::Other(7));
+        assert_eq!(u16::from(PartitionStyle::Mbr), 1);
+        assert_eq!(u16::from(PartitionStyle::Other(7)), 7);
+    }
+
+    #[test]
+    fn it_lists_disks_partitions_and_volumes() {
+        let wmi_con = wmi_con();
+        let storage = wmi_con.storage().unwrap();
+
+        let disks = storage.disks().unwrap();
+        assert!(!disks.is_empty());
+
+        let boot_disk = disks
+            .iter()
+            .find(|disk| disk.is_boot == Some(true))
+            .unwrap();
+        let partitions = storage.partitions(boot_disk).unwrap();
+        assert!(partitions
+            .iter()
+            .all(|partition| partition.disk_number == boot_disk.number));
+
+        let system_drive = std::env::var("SystemDrive").unwrap();
+        let system_letter = system_drive.chars().next();
+
+        let system_partition = partitions
+            .iter()
+            .find(|partition| partition.drive_letter == system_letter)
+            .unwrap();
+
+        let volume = storage.volume_of(system_partition).unwrap().unwrap();
+        assert_eq!(volume.drive_letter, system_letter);
+        assert!(volume.size_remaining <= volume.size);
+    }
+
+    #[test]
+    fn it_returns_the_extended_status_of_failed_methods() {
+        let wmi_con = wmi_con();
+        let storage = wmi_con.storage().unwrap();
+
+        let disk = storage
+            .disks()
+            .unwrap()
+            .into_iter()
+            .find(|disk| disk.is_boot == Some(true))
+            .unwrap();
+
+        // The boot disk is already initialized (or the call requires administrative rights).
+        match storage.initialize(&disk, PartitionStyle::Gpt) {
+            Err(WMIError::ExtendedStatusError {
+                method, message, ..
+            }) => {
+                assert_eq!(method, "Initialize");
+                assert!(!message.is_empty());
+            }
+            Err(WMIError::MethodFailed { .. }) | Err(WMIError::HResultError { .. }) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! One-call snapshots of common system information, assembled from multiple classes.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! let info = con.system_info()?;
+//!
+//! println!("{} ({})", info.os.caption, info.os.version);
+//! println!("{} of {} bytes free", info.memory.free_physical, info.memory.total_physical);
+//! for disk in &info.disks {
+//!     println!("{}: {:?} bytes free", disk.device_id, disk.free_space);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The snapshot is read using concurrent async queries, so it is faster than calling each of the methods in turn.
+use crate::{
+    connection::WMIConnection,
+    units::{Bytes, Kilobytes},
+    WMIError, WMIResult,
+};
+use futures::{executor::block_on, try_join};
+use serde::Deserialize;
+
+/// From `Win32_OperatingSystem`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "Win32_OperatingSystem")]
+#[serde(rename_all = "PascalCase")]
+pub struct OsInfo {
+    /// The name of the OS, like `Microsoft Windows 11 Pro`.
+    pub caption: String,
+    pub version: String,
+    pub build_number: String,
+    /// Like `64-bit`.
+    pub os_architecture: Option<String>,
+    /// The name of the computer.
+    #[serde(rename = "CSName")]
+    pub cs_name: String,
+    pub registered_user: Option<String>,
+    /// A DMTF datetime, see [`datetime::raw`](crate::datetime::raw).
+    #[serde(with = "crate::datetime::raw")]
+    pub last_boot_up_time: String,
+}
+
+/// From `Win32_Processor`, one for every processor socket.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "Win32_Processor")]
+#[serde(rename_all = "PascalCase")]
+pub struct CpuInfo {
+    #[serde(rename = "DeviceID")]
+    pub device_id: String,
+    pub name: String,
+    pub manufacturer: Option<String>,
+    pub number_of_cores: u32,
+    pub number_of_logical_processors: u32,
+    /// In MHz.
+    pub max_clock_speed: u32,
+}
+
+/// From `Win32_ComputerSystem` and `Win32_OperatingSystem`. All values are in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryInfo {
+    pub total_physical: u64,
+    pub free_physical: u64,
+    /// The physical memory and the page files.
+    pub total_virtual: u64,
+    pub free_virtual: u64,
+}
+
+/// From `Win32_LogicalDisk`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "Win32_LogicalDisk")]
+#[serde(rename_all = "PascalCase")]
+pub struct DiskInfo {
+    /// The drive letter, like `C:`.
+    #[serde(rename = "DeviceID")]
+    pub device_id: String,
+    /// `2` for removable disks, `3` for local disks, `4` for network drives and `5` for optical drives.
+    pub drive_type: u32,
+    pub file_system: Option<String>,
+    pub volume_name: Option<String>,
+    /// In bytes, `None` if there is no media (like an empty optical drive).
+    pub size: Option<u64>,
+    pub free_space: Option<u64>,
+}
+
+/// From `Win32_NetworkAdapterConfiguration`, for adapters with IP enabled.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "Win32_NetworkAdapterConfiguration")]
+#[serde(rename_all = "PascalCase")]
+pub struct NetworkAdapterInfo {
+    pub interface_index: u32,
+    pub description: String,
+    #[serde(rename = "MACAddress")]
+    pub mac_address: Option<String>,
+    /// Both IPv4 and IPv6 addresses.
+    #[serde(rename = "IPAddress")]
+    pub ip_address: Option<Vec<String>>,
+    #[serde(rename = "DefaultIPGateway")]
+    pub default_ip_gateway: Option<Vec<String>>,
+    #[serde(rename = "DNSServerSearchOrder")]
+    pub dns_server_search_order: Option<Vec<String>>,
+    #[serde(rename = "DHCPEnabled")]
+    pub dhcp_enabled: bool,
+}
+
+/// All of the system information, as returned by [`WMIConnection::system_info`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemInfo {
+    pub os: OsInfo,
+    pub cpus: Vec<CpuInfo>,
+    pub memory: MemoryInfo,
+    pub disks: Vec<DiskInfo>,
+    pub network_adapters: Vec<NetworkAdapterInfo>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Win32_OperatingSystem")]
+#[serde(rename_all = "PascalCase")]
+struct OsMemory {
+    free_physical_memory: Kilobytes,
+    total_virtual_memory_size: Kilobytes,
+    free_virtual_memory: Kilobytes,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Win32_ComputerSystem")]
+#[serde(rename_all = "PascalCase")]
+struct ComputerSystemMemory {
+    total_physical_memory: Bytes,
+}
+
+const NETWORK_ADAPTERS_QUERY: &str = "SELECT InterfaceIndex, Description, MACAddress, IPAddress, DefaultIPGateway, DNSServerSearchOrder, DHCPEnabled \
+     FROM Win32_NetworkAdapterConfiguration WHERE IPEnabled = TRUE";
+
+fn memory_info(os: &[OsMemory], cs: &[ComputerSystemMemory]) -> WMIResult<MemoryInfo> {
+    let (os, cs) = match (os.first(), cs.first()) {
+        (Some(os), Some(cs)) => (os, cs),
+        _ => return Err(WMIError::ResultEmpty),
+    };
+
+    Ok(MemoryInfo {
+        total_physical: cs.total_physical_memory.0,
+        free_physical: os.free_physical_memory.bytes().0,
+        total_virtual: os.total_virtual_memory_size.bytes().0,
+        free_virtual: os.free_virtual_memory.bytes().0,
+    })
+}
+
+///
+/// ### Additional system information methods
+///
+impl WMIConnection {
+    pub fn os_info(&self) -> WMIResult<OsInfo> {
+        self.get()
+    }
+
+    pub fn cpu_info(&self) -> WMIResult<Vec<CpuInfo>> {
+        self.query()
+    }
+
+    pub fn memory_info(&self) -> WMIResult<MemoryInfo> {
+        memory_info(&self.query()?, &self.query()?)
+    }
+
+    pub fn disks(&self) -> WMIResult<Vec<DiskInfo>> {
+        self.query()
+    }
+
+    pub fn network_adapters(&self) -> WMIResult<Vec<NetworkAdapterInfo>> {
+        self.raw_query(NETWORK_ADAPTERS_QUERY)
+    }
+
+    /// Read all of the system information, using concurrent queries.
+    ///
+    /// See the [module level documentation](crate::sysinfo) for an example.
+    pub fn system_info(&self) -> WMIResult<SystemInfo> {
+        block_on(self.async_system_info())
+    }
+
+    /// Async version of [`system_info`](Self::system_info).
+    pub async fn async_system_info(&self) -> WMIResult<SystemInfo> {
+        let (os, cpus, os_memory, cs_memory, disks, network_adapters) = try_join!(
+            self.async_query::<OsInfo>(),
+            self.async_query::<CpuInfo>(),
+            self.async_query::<OsMemory>(),
+            self.async_query::<ComputerSystemMemory>(),
+            self.async_query::<DiskInfo>(),
+            self.async_raw_query::<NetworkAdapterInfo>(NETWORK_ADAPTERS_QUERY),
+        )?;
+
+        Ok(SystemInfo {
+            os: os.into_iter().next().ok_or(WMIError::ResultEmpty)?,
+            cpus,
+            memory: memory_info(&os_memory, &cs_memory)?,
+            disks,
+            network_adapters,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::fixtures::*;
+
+    #[test]
+    fn it_reads_system_info() {
+        let wmi_con = wmi_con();
+
+        let os = wmi_con.os_info().unwrap();
+        assert!(os.caption.starts_with("Microsoft Windows"));
+
+        let cpus = wmi_con.cpu_info().unwrap();
+        assert!(!cpus.is_empty());
+        assert!(cpus[0].number_of_logical_processors >= cpus[0].number_of_cores);
+
+        let memory = wmi_con.memory_info().unwrap();
+        assert!(memory.free_physical < memory.total_physical);
+
+        let disks = wmi_con.disks().unwrap();
+        assert!(disks.iter().any(|disk| disk.device_id == "C:"));
+
+        wmi_con.network_adapters().unwrap();
+    }
+
+    #[test]
+    fn it_reads_a_system_snapshot() {
+        let wmi_con = wmi_con();
+
+        let info = wmi_con.system_info().unwrap();
+
+        assert_eq!(info.os, wmi_con.os_info().unwrap());
+        assert_eq!(info.cpus.len(), wmi_con.cpu_info().unwrap().len());
+        assert!(info.memory.total_virtual >= info.memory.free_virtual);
+        assert!(!info.disks.is_empty());
+    }
+}
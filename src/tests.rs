@@ -1,4 +1,5 @@
 use crate::{COMLibrary, WMIConnection, WMIError, WMIResult};
+use windows::core::{ComInterface, IUnknown, Interface};
 
 pub mod fixtures {
     use super::*;
@@ -15,6 +16,17 @@ pub mod fixtures {
     }
 }
 
+/// Return the current reference count of a COM object.
+pub fn ref_count(iface: &impl ComInterface) -> u32 {
+    let unknown: IUnknown = iface.cast().unwrap();
+
+    // `AddRef` and `Release` return the new reference count (not counting the `unknown` we hold).
+    unsafe {
+        (unknown.vtable().AddRef)(unknown.as_raw());
+        (unknown.vtable().Release)(unknown.as_raw()) - 1
+    }
+}
+
 pub fn start_test_program() {
     std::process::Command::new("C:\\Windows\\System32\\cmd.exe")
         .args(["timeout", "1"])
@@ -0,0 +1,216 @@
+//! WMI timer events, using the `__IntervalTimerInstruction` and `__AbsoluteTimerInstruction` classes.
+//!
+//! A timer instruction is an instance created in the namespace of the connection, which makes WMI deliver
+//! a `__TimerEvent` with the same `TimerId` at an interval (or once, at a given time). The events are received
+//! like any other event, so they can be consumed alongside other subscriptions:
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use std::time::Duration;
+//!
+//! let timer = con.create_interval_timer("heartbeat", Duration::from_secs(1))?;
+//!
+//! for event in con.timer_events(&timer)?.take(2) {
+//!     println!("Timer fired {} times", event?.num_fired_events);
+//! }
+//!
+//! // Timer instructions are kept by WMI until they are deleted.
+//! con.delete_timer(&timer)?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    connection::WMIConnection, datetime::raw::is_dmtf_datetime, query::quote_and_escape_wql_str,
+    Variant, WMIError, WMIResult,
+};
+use futures::Stream;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// A timer instruction created using [`WMIConnection::create_interval_timer`] or [`WMIConnection::create_absolute_timer`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TimerInstruction {
+    /// The path of the instruction, to delete it.
+    pub path: String,
+    pub timer_id: String,
+}
+
+/// An event delivered by a timer, from `__TimerEvent`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "__TimerEvent")]
+#[serde(rename_all = "PascalCase")]
+pub struct TimerEvent {
+    pub timer_id: String,
+    /// The number of events which were fired since the last one was delivered
+    /// (more than `1` if the consumer was too slow, or `SkipIfPassed` was not set).
+    pub num_fired_events: u32,
+}
+
+///
+/// ### Additional timer methods
+///
+impl WMIConnection {
+    /// Create (or replace) a timer which fires every `interval` (with a millisecond precision).
+    ///
+    /// See the [module level documentation](crate::timer) for an example.
+    pub fn create_interval_timer(
+        &self,
+        timer_id: &str,
+        interval: Duration,
+    ) -> WMIResult<TimerInstruction> {
+        let millis = u32::try_from(interval.as_millis())
+            .map_err(|_| WMIError::ConvertLengthError(interval.as_millis() as u64))?;
+
+        self.create_timer(
+            "__IntervalTimerInstruction",
+            timer_id,
+            // `uint32` properties are set using signed values.
+            ("IntervalBetweenEvents", Variant::I4(millis as i32)),
+        )
+    }
+
+    /// Create (or replace) a timer which fires once, at a DMTF datetime (like `20240101120000.000000+000`,
+    /// see [`datetime::raw`](crate::datetime::raw)).
+    pub fn create_absolute_timer(
+        &self,
+        timer_id: &str,
+        event_datetime: &str,
+    ) -> WMIResult<TimerInstruction> {
+        if !is_dmtf_datetime(event_datetime) {
+            return Err(WMIError::ConvertDatetimeError(event_datetime.to_owned()));
+        }
+
+        self.create_timer(
+            "__AbsoluteTimerInstruction",
+            timer_id,
+            ("EventDateTime", Variant::from(event_datetime)),
+        )
+    }
+
+    fn create_timer(
+        &self,
+        class: &str,
+        timer_id: &str,
+        (property, value): (&str, Variant),
+    ) -> WMIResult<TimerInstruction> {
+        let instruction = self.spawn_instance(class)?;
+
+        instruction.put_property("TimerId", timer_id)?;
+        instruction.put_property("SkipIfPassed", false)?;
+        instruction.put_property(property, value)?;
+
+        self.put_instance(&instruction)?;
+
+        Ok(TimerInstruction {
+            path: format!("{}.TimerId={}", class, quote_and_escape_wql_str(timer_id)),
+            timer_id: timer_id.to_owned(),
+        })
+    }
+
+    /// Delete a timer instruction, which stops its events.
+    pub fn delete_timer(&self, timer: &TimerInstruction) -> WMIResult<()> {
+        self.delete_instance(&timer.path)
+    }
+
+    /// Subscribe to the events of a timer.
+    pub fn timer_events<'a>(
+        &'a self,
+        timer: &TimerInstruction,
+    ) -> WMIResult<impl Iterator<Item = WMIResult<TimerEvent>> + 'a> {
+        self.raw_notification(timer_events_query(timer))
+    }
+
+    /// Subscribe to the events of a timer, and return a stream.
+    pub fn async_timer_events(
+        &self,
+        timer: &TimerInstruction,
+    ) -> WMIResult<impl Stream<Item = WMIResult<TimerEvent>>> {
+        self.async_raw_notification(timer_events_query(timer))
+    }
+}
+
+fn timer_events_query(timer: &TimerInstruction) -> String {
+    format!(
+        "SELECT * FROM __TimerEvent WHERE TimerId = {}",
+        quote_and_escape_wql_str(&timer.timer_id)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use futures::StreamExt;
+
+    #[test]
+    fn it_builds_timer_paths_and_queries() {
+        let wmi_con = wmi_con();
+
+        assert!(matches!(
+            wmi_con.create_absolute_timer("wmi-rs-test", "2024-01-01"),
+            Err(WMIError::ConvertDatetimeError(_))
+        ));
+
+        let timer = TimerInstruction {
+            path: String::new(),
+            timer_id: r#"a "quoted" id"#.to_owned(),
+        };
+
+        assert_eq!(
+            timer_events_query(&timer),
+            r#"SELECT * FROM __TimerEvent WHERE TimerId = "a \"quoted\" id""#
+        );
+    }
+
+    #[test]
+    fn it_receives_interval_timer_events() {
+        let wmi_con = wmi_con();
+
+        let timer = match wmi_con.create_interval_timer("wmi-rs-test", Duration::from_millis(200)) {
+            Ok(timer) => timer,
+            // Creating timer instructions can require administrative rights.
+            Err(WMIError::HResultError { .. }) => return,
+            Err(err) => panic!("{}", err),
+        };
+
+        assert_eq!(
+            timer.path,
+            r#"__IntervalTimerInstruction.TimerId="wmi-rs-test""#
+        );
+
+        let events: Vec<_> = wmi_con.timer_events(&timer).unwrap().take(2).collect();
+
+        wmi_con.delete_timer(&timer).unwrap();
+
+        for event in events {
+            let event = event.unwrap();
+            assert_eq!(event.timer_id, "wmi-rs-test");
+            assert!(event.num_fired_events >= 1);
+        }
+    }
+
+    #[async_std::test]
+    async fn async_it_receives_interval_timer_events() {
+        let wmi_con = wmi_con();
+
+        let timer =
+            match wmi_con.create_interval_timer("wmi-rs-async-test", Duration::from_millis(200)) {
+                Ok(timer) => timer,
+                Err(WMIError::HResultError { .. }) => return,
+                Err(err) => panic!("{}", err),
+            };
+
+        let event = wmi_con
+            .async_timer_events(&timer)
+            .unwrap()
+            .next()
+            .await
+            .unwrap();
+
+        wmi_con.delete_timer(&timer).unwrap();
+
+        assert_eq!(event.unwrap().timer_id, "wmi-rs-async-test");
+    }
+}
@@ -0,0 +1,230 @@
+//! The Trusted Platform Module (TPM) of the computer and its state, using the `Win32_Tpm` class.
+//!
+//! Like BitLocker, the `ROOT\CIMV2\Security\MicrosoftTpm` namespace requires administrative rights,
+//! and only accepts connections using the `RPC_C_AUTHN_LEVEL_PKT_PRIVACY` authentication level
+//! (which [`WMIConnection::tpm`] sets).
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! let tpm = con.tpm()?;
+//!
+//! match tpm.info()? {
+//!     Some(info) => {
+//!         println!("TPM {} by {:?}", info.spec_version.as_deref().unwrap_or("?"), info.manufacturer_id_txt);
+//!         println!("{:?}", tpm.status(&info)?);
+//!     }
+//!     None => println!("No TPM"),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{connection::WMIConnection, WMIError, WMIResult};
+use serde::{de::DeserializeOwned, Deserialize};
+
+/// The namespace of the TPM provider.
+pub const TPM_NAMESPACE: &str = "ROOT\\CIMV2\\Security\\MicrosoftTpm";
+
+/// The TPM of the computer, from `Win32_Tpm`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename = "Win32_Tpm")]
+#[serde(rename_all = "PascalCase")]
+pub struct TpmInfo {
+    #[serde(rename = "__Path")]
+    pub path: String,
+    /// The state of the TPM when the provider was loaded, see [`Tpm::status`] for the current state.
+    #[serde(rename = "IsActivated_InitialValue")]
+    pub is_activated_initial_value: Option<bool>,
+    #[serde(rename = "IsEnabled_InitialValue")]
+    pub is_enabled_initial_value: Option<bool>,
+    #[serde(rename = "IsOwned_InitialValue")]
+    pub is_owned_initial_value: Option<bool>,
+    /// The ASCII vendor id, packed into an integer (like `0x494E5443` for `INTC`).
+    pub manufacturer_id: Option<u32>,
+    /// Like `INTC`.
+    pub manufacturer_id_txt: Option<String>,
+    pub manufacturer_version: Option<String>,
+    pub manufacturer_version_info: Option<String>,
+    /// Like `2.0, 0, 1.38`: the version of the specification, its level and its revision.
+    pub spec_version: Option<String>,
+    pub physical_presence_version_info: Option<String>,
+}
+
+impl TpmInfo {
+    /// The major version of the TPM specification implemented by the TPM (`1` for TPM 1.2, `2` for TPM 2.0).
+    pub fn major_version(&self) -> Option<u32> {
+        let spec_version = self.spec_version.as_deref()?;
+        let version = spec_version.split(',').next()?.trim();
+
+        version.split('.').next()?.parse().ok()
+    }
+}
+
+/// The current state of a TPM, as returned by its `IsEnabled`, `IsActivated`, `IsOwned` and `IsReady` methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TpmStatus {
+    pub is_enabled: bool,
+    pub is_activated: bool,
+    pub is_owned: bool,
+    /// Whether the TPM is ready for attestation (and to be used by BitLocker).
+    pub is_ready: bool,
+}
+
+/// The output parameters of the `Is*` methods of `Win32_Tpm`.
+///
+/// Each method only returns one of the flags, so the others are `None`
+/// (the `ReturnValue` is checked by [`Tpm::exec`]).
+#[derive(Debug, Deserialize)]
+#[serde(rename = "__PARAMETERS")]
+#[serde(rename_all = "PascalCase")]
+struct StateOut {
+    is_enabled: Option<bool>,
+    is_activated: Option<bool>,
+    is_owned: Option<bool>,
+    is_ready: Option<bool>,
+}
+
+/// A connection to the TPM namespace, created using [`WMIConnection::tpm`].
+#[derive(Debug, Clone)]
+pub struct Tpm {
+    con: WMIConnection,
+}
+
+///
+/// ### Additional TPM methods
+///
+impl WMIConnection {
+    /// Connect to the TPM namespace on the same computer.
+    ///
+    /// See the [module level documentation](crate::tpm) for an example.
+    pub fn tpm(&self) -> WMIResult<Tpm> {
+        let mut con = self.with_namespace(TPM_NAMESPACE)?;
+        con.require_packet_privacy()?;
+
+        Ok(Tpm { con })
+    }
+}
+
+impl Tpm {
+    /// The connection to the TPM namespace, to call other methods of `Win32_Tpm`.
+    pub fn connection(&self) -> &WMIConnection {
+        &self.con
+    }
+
+    /// The TPM of the computer, or `None` if it does not have one (or it is disabled in the firmware).
+    pub fn info(&self) -> WMIResult<Option<TpmInfo>> {
+        let tpms: Vec<TpmInfo> = self.con.query()?;
+
+        Ok(tpms.into_iter().next())
+    }
+
+    pub fn is_enabled(&self, tpm: &TpmInfo) -> WMIResult<bool> {
+        let out: StateOut = self.exec(tpm, "IsEnabled")?;
+
+        flag(out.is_enabled, "IsEnabled")
+    }
+
+    pub fn is_activated(&self, tpm: &TpmInfo) -> WMIResult<bool> {
+        let out: StateOut = self.exec(tpm, "IsActivated")?;
+
+        flag(out.is_activated, "IsActivated")
+    }
+
+    pub fn is_owned(&self, tpm: &TpmInfo) -> WMIResult<bool> {
+        let out: StateOut = self.exec(tpm, "IsOwned")?;
+
+        flag(out.is_owned, "IsOwned")
+    }
+
+    pub fn is_ready(&self, tpm: &TpmInfo) -> WMIResult<bool> {
+        let out: StateOut = self.exec(tpm, "IsReady")?;
+
+        flag(out.is_ready, "IsReady")
+    }
+
+    /// Read the current state of the TPM (unlike the `*_InitialValue` properties of [`TpmInfo`]).
+    pub fn status(&self, tpm: &TpmInfo) -> WMIResult<TpmStatus> {
+        Ok(TpmStatus {
+            is_enabled: self.is_enabled(tpm)?,
+            is_activated: self.is_activated(tpm)?,
+            is_owned: self.is_owned(tpm)?,
+            is_ready: self.is_ready(tpm)?,
+        })
+    }
+
+    /// Execute a method of the TPM, and deserialize its output parameters.
+    fn exec<T>(&self, tpm: &TpmInfo, method: &str) -> WMIResult<T>
+    where
+        T: DeserializeOwned,
+    {
+        let out = self
+            .con
+            .exec_method(&tpm.path, method, &[])?
+            .ok_or(WMIError::NullPointerResult)?;
+
+        // The return values of the TPM methods are `HRESULT`s (like `TPM_E_DEACTIVATED`).
+        match u32::try_from(out.get_property("ReturnValue")?)? {
+            0 => out.into_desr(),
+            hres => Err(WMIError::HResultError { hres: hres as i32 }),
+        }
+    }
+}
+
+fn flag(value: Option<bool>, method: &str) -> WMIResult<bool> {
+    value.ok_or_else(|| WMIError::ConvertVariantError(format!("{} did not return a value", method)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    fn info(spec_version: Option<&str>) -> TpmInfo {
+        TpmInfo {
+            path: String::new(),
+            is_activated_initial_value: None,
+            is_enabled_initial_value: None,
+            is_owned_initial_value: None,
+            manufacturer_id: None,
+            manufacturer_id_txt: None,
+            manufacturer_version: None,
+            manufacturer_version_info: None,
+            spec_version: spec_version.map(str::to_owned),
+            physical_presence_version_info: None,
+        }
+    }
+
+    #[test]
+    fn it_parses_spec_versions() {
+        assert_eq!(info(Some("2.0, 0, 1.38")).major_version(), Some(2));
+        assert_eq!(info(Some("1.2, 2, 3")).major_version(), Some(1));
+        assert_eq!(info(Some("")).major_version(), None);
+        assert_eq!(info(None).major_version(), None);
+    }
+
+    #[test]
+    fn it_reads_the_tpm_status() {
+        let wmi_con = wmi_con();
+
+        // Requires administrative rights.
+        let tpm = match wmi_con.tpm() {
+            Ok(tpm) => tpm,
+            Err(WMIError::HResultError { .. }) => return,
+            Err(err) => panic!("{}", err),
+        };
+
+        let info = match tpm.info() {
+            Ok(Some(info)) => info,
+            Ok(None) | Err(WMIError::HResultError { .. }) => return,
+            Err(err) => panic!("{}", err),
+        };
+
+        let status = tpm.status(&info).unwrap();
+
+        // A TPM which is ready must also be enabled and activated.
+        if status.is_ready {
+            assert!(status.is_enabled && status.is_activated);
+        }
+    }
+}
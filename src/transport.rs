@@ -0,0 +1,204 @@
+//! The [`WbemTransport`] trait, which decouples query building and deserialization from the protocol used to run queries.
+//!
+//! A [`WMIConnection`](crate::WMIConnection) is generic over its transport: [`ComTransport`](crate::ComTransport) (COM/DCOM, the default),
+//! `WsManTransport` (with the `wsman` feature), `MiTransport` (with the `mi` feature) and [`MockTransport`] (for tests).
+//! Queries return the same types for all of them, so code which only runs queries can be written once:
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! use serde::Deserialize;
+//! use wmi::{MockTransport, Variant, WMIConnection, WbemTransport};
+//!
+//! #[derive(Deserialize, Debug)]
+//! #[serde(rename = "Win32_OperatingSystem")]
+//! #[serde(rename_all = "PascalCase")]
+//! struct OperatingSystem {
+//!     caption: String,
+//! }
+//!
+//! fn os_caption<T: WbemTransport>(con: &WMIConnection<T>) -> wmi::WMIResult<String> {
+//!     Ok(con.get::<OperatingSystem>()?.caption)
+//! }
+//!
+//! let mock = WMIConnection::from_transport(MockTransport::new().with_instance(
+//!     "Win32_OperatingSystem",
+//!     [("Caption", Variant::from("Microsoft Windows 11 Pro"))],
+//! ));
+//! assert_eq!(os_caption(&mock)?, "Microsoft Windows 11 Pro");
+//!
+//! # #[cfg(not(feature = "test"))]
+//! let con = WMIConnection::new(wmi::COMLibrary::new()?)?;
+//! # #[cfg(feature = "test")]
+//! # let con = wmi::tests::fixtures::wmi_con();
+//! println!("{}", os_caption(&con)?);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The other methods of [`WMIConnection`](crate::WMIConnection) (like notifications, method calls and writing instances)
+//! are only available for the COM transport.
+use crate::{
+    de::{
+        options::DeserializeOptions,
+        owned::{Object, Value, ValueDeserializer},
+    },
+    query::{query_class, select_projection},
+    Variant, WMIError, WMIResult,
+};
+use serde::de::DeserializeOwned;
+use windows::Win32::System::Wmi::WBEM_E_INVALID_QUERY;
+
+/// A protocol which can run WQL queries, see the [module level documentation](crate::transport).
+///
+/// Transports only run free-text queries: the other query methods of [`WMIConnection`](crate::WMIConnection) build their queries
+/// from the deserialized type, and then call [`raw_query`](WbemTransport::raw_query).
+pub trait WbemTransport {
+    /// Execute a WQL query, and deserialize the results, see [`WMIConnection::raw_query`](crate::WMIConnection::raw_query).
+    fn raw_query<T>(&self, query: &str) -> WMIResult<Vec<T>>
+    where
+        T: DeserializeOwned;
+}
+
+/// Deserialize owned objects, as returned by transports other than COM.
+pub(crate) fn deserialize_objects<T>(
+    objects: impl IntoIterator<Item = Object>,
+    query: &str,
+    options: &DeserializeOptions,
+) -> WMIResult<Vec<T>>
+where
+    T: DeserializeOwned,
+{
+    let projection = select_projection(query);
+
+    objects
+        .into_iter()
+        .map(|object| {
+            let object = match &projection {
+                Some(projection) => object.project(projection),
+                None => object,
+            };
+
+            T::deserialize(ValueDeserializer::new(Value::Object(object), options))
+        })
+        .collect()
+}
+
+/// A transport which returns predefined instances, to test code which is generic over [`WbemTransport`].
+///
+/// Queries return all the instances of the class they select from (the `WHERE` clause is not evaluated).
+/// Queries without a `FROM` clause fail with `WBEM_E_INVALID_QUERY`, like they do in WMI.
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    instances: Vec<Object>,
+}
+
+impl Clone for MockTransport {
+    fn clone(&self) -> Self {
+        Self {
+            instances: self.instances.iter().map(Object::duplicate).collect(),
+        }
+    }
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an instance of `class` with the given properties.
+    pub fn with_instance<I, K>(mut self, class: &str, properties: I) -> Self
+    where
+        I: IntoIterator<Item = (K, Variant)>,
+        K: Into<String>,
+    {
+        self.instances.push(Object {
+            class: class.to_owned(),
+            properties: properties
+                .into_iter()
+                .map(|(name, value)| (name.into(), Value::Variant(value)))
+                .collect(),
+        });
+        self
+    }
+}
+
+impl WbemTransport for MockTransport {
+    fn raw_query<T>(&self, query: &str) -> WMIResult<Vec<T>>
+    where
+        T: DeserializeOwned,
+    {
+        let class = query_class(query).ok_or(WMIError::HResultError {
+            hres: WBEM_E_INVALID_QUERY.0,
+        })?;
+
+        let instances = self
+            .instances
+            .iter()
+            .filter(|object| object.class.eq_ignore_ascii_case(class))
+            .map(Object::duplicate);
+
+        deserialize_objects(instances, query, &DeserializeOptions::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests::fixtures::*, WMIConnection};
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename = "Win32_Process")]
+    #[serde(rename_all = "PascalCase")]
+    struct Process {
+        name: String,
+        process_id: u32,
+        executable_path: Option<String>,
+    }
+
+    fn processes<T: WbemTransport>(con: &WMIConnection<T>) -> WMIResult<Vec<Process>> {
+        con.query()
+    }
+
+    #[test]
+    fn it_queries_a_mock_transport() {
+        let mock = WMIConnection::from_transport(
+            MockTransport::new()
+                .with_instance(
+                    "Win32_Process",
+                    [
+                        ("Name", Variant::from("System")),
+                        ("ProcessId", Variant::UI4(4)),
+                        ("ExecutablePath", Variant::Null),
+                    ],
+                )
+                .with_instance("Win32_Service", [("Name", Variant::from("Spooler"))]),
+        );
+
+        assert_eq!(
+            processes(&mock).unwrap(),
+            vec![Process {
+                name: "System".to_owned(),
+                process_id: 4,
+                executable_path: None,
+            }]
+        );
+
+        let tuples: Vec<(u32, String)> = mock
+            .raw_query("SELECT ProcessId, Name FROM Win32_Process")
+            .unwrap();
+        assert_eq!(tuples, vec![(4, "System".to_owned())]);
+
+        assert!(matches!(
+            mock.raw_query::<Process>("SELECT * WHERE Name = 'x'"),
+            Err(WMIError::HResultError { hres }) if hres == WBEM_E_INVALID_QUERY.0
+        ));
+    }
+
+    #[test]
+    fn it_queries_the_com_transport() {
+        let wmi_con = wmi_con();
+
+        assert!(!processes(&wmi_con).unwrap().is_empty());
+    }
+}
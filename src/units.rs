@@ -0,0 +1,298 @@
+//! Numeric wrappers which carry the unit of a property, like [`Kilobytes`] or [`TenthsKelvin`].
+//!
+//! WMI classes are inconsistent about units: `Win32_ComputerSystem.TotalPhysicalMemory` is in bytes,
+//! but `Win32_OperatingSystem.FreePhysicalMemory` is in kilobytes. Using the wrappers as field types
+//! makes the unit part of the struct, and conversions explicit:
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use serde::Deserialize;
+//! use wmi::units::{Bytes, Kilobytes};
+//!
+//! #[derive(Deserialize)]
+//! #[serde(rename = "Win32_OperatingSystem")]
+//! #[serde(rename_all = "PascalCase")]
+//! struct Memory {
+//!     free_physical_memory: Kilobytes,
+//! }
+//!
+//! let memory: Memory = con.get()?;
+//! let free = Bytes::from(memory.free_physical_memory);
+//! println!("{} bytes free", free.0);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A field can also be kept as a plain number of bytes using `#[serde(deserialize_with = "wmi::units::kilobytes_as_bytes")]`.
+//!
+//! The unit of a property is usually described by its `Units` qualifier,
+//! which can be read using [`WMIConnection::property_unit`].
+use crate::{connection::WMIConnection, Variant, WMIResult};
+use serde::{Deserialize, Serialize};
+use std::{fmt, time::Duration};
+
+/// A size in bytes.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Deserialize, Serialize,
+)]
+#[serde(transparent)]
+pub struct Bytes(pub u64);
+
+/// A size in kilobytes (1024 bytes).
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Deserialize, Serialize,
+)]
+#[serde(transparent)]
+pub struct Kilobytes(pub u64);
+
+/// A size in megabytes (1024 * 1024 bytes).
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Deserialize, Serialize,
+)]
+#[serde(transparent)]
+pub struct Megabytes(pub u64);
+
+/// A duration in milliseconds.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Deserialize, Serialize,
+)]
+#[serde(transparent)]
+pub struct Milliseconds(pub u64);
+
+/// A temperature in tenths of Kelvin, as reported by ACPI (`2982` is 25.05 °C).
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Deserialize, Serialize,
+)]
+#[serde(transparent)]
+pub struct TenthsKelvin(pub u32);
+
+impl Kilobytes {
+    pub fn bytes(self) -> Bytes {
+        Bytes(self.0.saturating_mul(1024))
+    }
+}
+
+impl Megabytes {
+    pub fn bytes(self) -> Bytes {
+        Bytes(self.0.saturating_mul(1024 * 1024))
+    }
+}
+
+impl From<Kilobytes> for Bytes {
+    fn from(value: Kilobytes) -> Self {
+        value.bytes()
+    }
+}
+
+impl From<Megabytes> for Bytes {
+    fn from(value: Megabytes) -> Self {
+        value.bytes()
+    }
+}
+
+impl Milliseconds {
+    pub fn as_duration(self) -> Duration {
+        Duration::from_millis(self.0)
+    }
+}
+
+impl From<Milliseconds> for Duration {
+    fn from(value: Milliseconds) -> Self {
+        value.as_duration()
+    }
+}
+
+impl TenthsKelvin {
+    pub fn kelvin(self) -> f64 {
+        f64::from(self.0) / 10.0
+    }
+
+    pub fn celsius(self) -> f64 {
+        self.kelvin() - 273.15
+    }
+
+    pub fn fahrenheit(self) -> f64 {
+        self.celsius() * 9.0 / 5.0 + 32.0
+    }
+}
+
+impl fmt::Display for TenthsKelvin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1} °C", self.celsius())
+    }
+}
+
+/// Deserialize a number of kilobytes into a number of bytes, for use with `#[serde(deserialize_with)]`.
+pub fn kilobytes_as_bytes<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Kilobytes::deserialize(deserializer).map(|kb| kb.bytes().0)
+}
+
+/// Deserialize a number of megabytes into a number of bytes, for use with `#[serde(deserialize_with)]`.
+pub fn megabytes_as_bytes<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Megabytes::deserialize(deserializer).map(|mb| mb.bytes().0)
+}
+
+/// The unit of a property, from its `Units` qualifier.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Unit {
+    Bytes,
+    Kilobytes,
+    Megabytes,
+    Milliseconds,
+    Seconds,
+    TenthsKelvin,
+    /// A unit not known to this crate, like `Hertz` or `Percent`.
+    Other(String),
+}
+
+impl Unit {
+    /// Parse the value of a `Units` qualifier (like `KiloBytes` or `Tenths of degrees Kelvin`).
+    pub fn from_qualifier(units: &str) -> Self {
+        let normalized: String = units
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_ascii_lowercase();
+
+        match normalized.as_str() {
+            "bytes" => Unit::Bytes,
+            "kilobytes" => Unit::Kilobytes,
+            "megabytes" => Unit::Megabytes,
+            "milliseconds" => Unit::Milliseconds,
+            "seconds" => Unit::Seconds,
+            "tenthsofdegreeskelvin" | "tenthsofkelvin" => Unit::TenthsKelvin,
+            _ => Unit::Other(units.to_owned()),
+        }
+    }
+
+    /// The number of bytes in one of this unit, or `None` if this is not a unit of size.
+    pub fn bytes_factor(&self) -> Option<u64> {
+        match self {
+            Unit::Bytes => Some(1),
+            Unit::Kilobytes => Some(1024),
+            Unit::Megabytes => Some(1024 * 1024),
+            _ => None,
+        }
+    }
+}
+
+///
+/// ### Additional unit methods
+///
+impl WMIConnection {
+    /// Read the unit of a property of a class from its `Units` qualifier, or `None` if it has none.
+    ///
+    /// ```edition2018
+    /// # fn main() -> wmi::WMIResult<()> {
+    /// # use wmi::*;
+    /// # let con = WMIConnection::new(COMLibrary::new()?)?;
+    /// use wmi::units::Unit;
+    ///
+    /// let unit = con.property_unit("Win32_OperatingSystem", "FreePhysicalMemory")?;
+    /// assert_eq!(unit, Some(Unit::Kilobytes));
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn property_unit(&self, class: &str, property: &str) -> WMIResult<Option<Unit>> {
+        let units = self.property_qualifier(class, property, "Units")?;
+
+        Ok(units.and_then(|units| match units {
+            Variant::String(units) => Some(Unit::from_qualifier(&units)),
+            _ => None,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    #[test]
+    fn it_converts_units() {
+        assert_eq!(Bytes::from(Kilobytes(2)), Bytes(2048));
+        assert_eq!(Bytes::from(Megabytes(1)), Bytes(1024 * 1024));
+        assert_eq!(Kilobytes(u64::MAX).bytes(), Bytes(u64::MAX));
+        assert_eq!(
+            Milliseconds(1500).as_duration(),
+            Duration::from_millis(1500)
+        );
+
+        assert!((TenthsKelvin(2732).celsius() - 0.05).abs() < 1e-9);
+        assert!((TenthsKelvin(3732).fahrenheit() - 212.09).abs() < 1e-9);
+        assert_eq!(TenthsKelvin(3000).to_string(), "26.9 °C");
+    }
+
+    #[test]
+    fn it_parses_units_qualifiers() {
+        assert_eq!(Unit::from_qualifier("KiloBytes"), Unit::Kilobytes);
+        assert_eq!(Unit::from_qualifier("Bytes"), Unit::Bytes);
+        assert_eq!(
+            Unit::from_qualifier("Tenths of degrees Kelvin"),
+            Unit::TenthsKelvin
+        );
+        assert_eq!(
+            Unit::from_qualifier("Hertz"),
+            Unit::Other("Hertz".to_owned())
+        );
+
+        assert_eq!(Unit::Kilobytes.bytes_factor(), Some(1024));
+        assert_eq!(Unit::Seconds.bytes_factor(), None);
+    }
+
+    #[test]
+    fn it_deserializes_units() {
+        #[derive(Deserialize)]
+        #[serde(rename = "Win32_OperatingSystem")]
+        #[serde(rename_all = "PascalCase")]
+        struct Memory {
+            free_physical_memory: Kilobytes,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename = "Win32_OperatingSystem")]
+        #[serde(rename_all = "PascalCase")]
+        struct MemoryInBytes {
+            #[serde(deserialize_with = "kilobytes_as_bytes")]
+            free_physical_memory: u64,
+        }
+
+        let wmi_con = wmi_con();
+
+        let memory: Memory = wmi_con.get().unwrap();
+        let memory_in_bytes: MemoryInBytes = wmi_con.get().unwrap();
+
+        assert!(memory.free_physical_memory.0 > 0);
+        assert!(memory_in_bytes.free_physical_memory >= 1024);
+    }
+
+    #[test]
+    fn it_reads_units_qualifiers() {
+        let wmi_con = wmi_con();
+
+        assert_eq!(
+            wmi_con
+                .property_unit("Win32_OperatingSystem", "FreePhysicalMemory")
+                .unwrap(),
+            Some(Unit::Kilobytes)
+        );
+        assert_eq!(
+            wmi_con
+                .property_unit("Win32_ComputerSystem", "TotalPhysicalMemory")
+                .unwrap(),
+            Some(Unit::Bytes)
+        );
+        assert_eq!(
+            wmi_con.property_unit("Win32_Process", "Name").unwrap(),
+            None
+        );
+    }
+}
@@ -31,6 +31,10 @@ pub enum WMIError {
     ConvertDatetimeError(String),
     #[error("Expected {0:?} to be at 25 chars")]
     ConvertDurationError(String),
+    #[error("Expected {0:?} to be an IP address")]
+    ConvertIpAddrError(String),
+    #[error("Expected {0:?} to be an install date")]
+    ConvertInstallDateError(String),
     #[error("Length {0} was too long to convert")]
     ConvertLengthError(u64),
     #[error("{0}")]
@@ -45,6 +49,84 @@ pub enum WMIError {
     UnimplementedArrayItem,
     #[error("Invalid variant {0} during deserialization")]
     InvalidDeserializationVariantError(String),
+    #[error(transparent)]
+    PropertyDeserializationError(Box<PropertyError>),
+    #[error("Properties {properties:?} of {class:?} are not mapped by any field")]
+    UnmappedPropertiesError {
+        class: String,
+        properties: Vec<String>,
+    },
+    #[error("Properties {properties:?} are not defined by {class:?}, and were not added in a later schema version")]
+    MissingPropertiesError {
+        class: String,
+        properties: Vec<String>,
+    },
+    #[error("Timed out while waiting for results")]
+    Timeout,
+    #[error("Invalid namespace {0:?}: {1}")]
+    InvalidNamespace(String, String),
+    #[error("Expected {0:?} to be an absolute path on a local drive, like `C:\\Logs`")]
+    InvalidWatchPath(String),
+    #[error("Access to registry key {0:?} was denied (some keys, like HKEY_LOCAL_MACHINE\\SECURITY, can only be watched by an elevated process or the LocalSystem account)")]
+    RegistryAccessDenied(String),
+    #[error("Registry key {0:?} does not exist")]
+    RegistryKeyNotFound(String),
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+    #[error("Method {0:?} does not take input parameters")]
+    NoInputParameters(String),
+    #[error("Querying Win32_Product reconfigures every installed MSI package, use `installed_software` instead (or opt in using `allow_win32_product`)")]
+    Win32ProductQuery,
+    #[error("The MDM Bridge provider can only be used by the LocalSystem account (for example, from a service or using `psexec -s`)")]
+    MdmRequiresLocalSystem,
+    #[error("Method {method:?} of {class:?} is static, and must be executed on the class")]
+    StaticMethod { class: String, method: String },
+    #[error("Method {method:?} of {class:?} is not static, and must be executed on an instance")]
+    NotStaticMethod { class: String, method: String },
+    #[error("Method {method:?} failed with return value {return_value}")]
+    MethodFailed { method: String, return_value: u32 },
+    #[error("Method {method:?} failed with return value {return_value}: {message}")]
+    ExtendedStatusError {
+        method: String,
+        return_value: u32,
+        message: String,
+    },
+    #[error("Job {path:?} failed with error code {error_code}: {description}")]
+    JobFailed {
+        path: String,
+        error_code: u32,
+        description: String,
+    },
+    #[error("Invalid security descriptor: {0}")]
+    InvalidSecurityDescriptor(String),
+    #[error("No generic credentials named {0:?} were found in the Credential Manager")]
+    CredentialNotFound(String),
+    #[cfg(feature = "arrow")]
+    #[error(transparent)]
+    ArrowError(#[from] arrow::error::ArrowError),
+    #[cfg(feature = "polars")]
+    #[error(transparent)]
+    PolarsError(#[from] polars::error::PolarsError),
+    #[cfg(feature = "mi")]
+    #[error("MI call failed with MI_Result {result}: {message}")]
+    MiError { result: u32, message: String },
+    #[cfg(feature = "wsman")]
+    #[error("Invalid WS-Management response: {0}")]
+    InvalidWsManResponse(String),
+}
+
+/// The details of a property which could not be deserialized.
+#[derive(Debug, Error)]
+#[error("Cannot deserialize property {property:?} of {class:?} (CIM type {cim_type}, variant {variant_type}) into {expected}: {message}")]
+pub struct PropertyError {
+    pub class: String,
+    pub property: String,
+    /// The MOF name of the property's CIM type, like `uint32` or `string[]`.
+    pub cim_type: String,
+    pub variant_type: &'static str,
+    /// The Rust type which was requested by the deserialized struct.
+    pub expected: &'static str,
+    pub message: String,
 }
 
 impl From<windows::core::Error> for WMIError {
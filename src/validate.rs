@@ -0,0 +1,263 @@
+//! Check structs against the live schema of their classes, to catch drift before it reaches production.
+//!
+//! This is meant to be used in tests which run on a Windows development machine,
+//! using the [`validate_struct!`](crate::validate_struct) macro:
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, Debug)]
+//! #[serde(rename = "Win32_OperatingSystem")]
+//! #[serde(rename_all = "PascalCase")]
+//! struct OperatingSystem {
+//!     caption: String,
+//!     number_of_processes: u32,
+//! }
+//!
+//! wmi::validate_struct!(OperatingSystem);
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    connection::WMIConnection,
+    de::{
+        meta::{struct_name_and_fields, ALL_PROPERTIES},
+        options::NumericCoercion,
+    },
+    query::build_query,
+    WMIError, WMIResult,
+};
+use serde::de;
+use std::fmt;
+
+/// The maximum number of instances which are deserialized to check the types of the fields.
+const MAX_CHECKED_INSTANCES: usize = 16;
+
+/// The result of [`WMIConnection::validate_struct`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub class: String,
+    /// Fields which are not properties of the class.
+    pub missing: Vec<String>,
+    /// Fields whose type cannot hold the values of their property.
+    pub mismatches: Vec<PropertyMismatch>,
+    /// The number of instances which were deserialized to check the types of the fields.
+    ///
+    /// Types can only be checked for properties which are not null, so this can be `0` for classes without instances
+    /// (and is always `0` if fields are missing).
+    pub instances_checked: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropertyMismatch {
+    pub property: String,
+    /// The MOF name of the property's CIM type, like `uint32` or `string[]`.
+    pub cim_type: String,
+    /// The Rust type of the field.
+    pub expected: &'static str,
+    pub message: String,
+}
+
+impl ValidationReport {
+    /// Whether the struct matches the class.
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.mismatches.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_ok() {
+            return write!(
+                f,
+                "{} matches the schema ({} instances checked)",
+                self.class, self.instances_checked
+            );
+        }
+
+        writeln!(f, "{} does not match the schema:", self.class)?;
+
+        for field in &self.missing {
+            writeln!(f, "  {}: not a property of the class", field)?;
+        }
+
+        for mismatch in &self.mismatches {
+            writeln!(
+                f,
+                "  {}: {} cannot be deserialized into {}: {}",
+                mismatch.property, mismatch.cim_type, mismatch.expected, mismatch.message
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+///
+/// ### Additional validation methods
+///
+impl WMIConnection {
+    /// Check the fields of `T` against the schema of its class.
+    ///
+    /// Every field must be a property of the class, and the values of a few instances must be deserializable
+    /// into the types of the fields without losing information (see [`NumericCoercion::WidenOnly`]).
+    pub fn validate_struct<T>(&self) -> WMIResult<ValidationReport>
+    where
+        T: de::DeserializeOwned,
+    {
+        let (class_name, fields) = struct_name_and_fields::<T>()?;
+        let class = self.describe_class(class_name)?;
+
+        let mut report = ValidationReport {
+            class: class.name,
+            missing: vec![],
+            mismatches: vec![],
+            instances_checked: 0,
+        };
+
+        if fields != ALL_PROPERTIES {
+            report.missing = fields
+                .iter()
+                .filter(|field| !field.starts_with("__"))
+                .filter(|field| {
+                    !class
+                        .properties
+                        .iter()
+                        .any(|property| property.name.eq_ignore_ascii_case(field))
+                })
+                .map(|field| field.to_string())
+                .collect();
+        }
+
+        // The query would fail anyway.
+        if !report.missing.is_empty() {
+            return Ok(report);
+        }
+
+        let options = self
+            .de_options
+            .clone()
+            .numeric_coercion(NumericCoercion::WidenOnly);
+
+        let enumerator = self.exec_query_native_wrapper(build_query::<T>(None)?)?;
+
+        for item in enumerator.take(MAX_CHECKED_INSTANCES) {
+            match item?.into_desr_with_options::<T>(&options, None) {
+                Ok(_) => {}
+                Err(WMIError::PropertyDeserializationError(err)) => {
+                    if !report.mismatches.iter().any(|m| m.property == err.property) {
+                        report.mismatches.push(PropertyMismatch {
+                            property: err.property,
+                            cim_type: err.cim_type,
+                            expected: err.expected,
+                            message: err.message,
+                        });
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+
+            report.instances_checked += 1;
+        }
+
+        Ok(report)
+    }
+}
+
+/// Check that a struct matches the live schema of its class, panicking with a report if it does not.
+///
+/// Uses a new local connection to `ROOT\CIMV2`, unless a connection is given as the first argument.
+/// See [`WMIConnection::validate_struct`] for the details of the checks.
+///
+/// ```edition2018
+/// # fn main() -> wmi::WMIResult<()> {
+/// # use wmi::*;
+/// # use serde::Deserialize;
+/// #[derive(Deserialize)]
+/// struct Win32_Process {
+///     Name: String,
+///     ProcessId: u32,
+/// }
+///
+/// let con = WMIConnection::new(COMLibrary::new()?)?;
+/// wmi::validate_struct!(con, Win32_Process);
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! validate_struct {
+    ($t:ty) => {{
+        let con = $crate::WMIConnection::new(
+            $crate::COMLibrary::new().expect("Failed to initialize COM"),
+        )
+        .expect("Failed to connect to WMI");
+
+        $crate::validate_struct!(con, $t);
+    }};
+    ($con:expr, $t:ty) => {{
+        let report =
+            $crate::WMIConnection::validate_struct::<$t>(&$con).expect("Failed to validate struct");
+
+        assert!(report.is_ok(), "{}", report);
+    }};
+}
+
+#[allow(non_snake_case)]
+#[allow(non_camel_case_types)]
+#[cfg(test)]
+mod tests {
+    use crate::tests::fixtures::*;
+    use serde::Deserialize;
+
+    #[test]
+    fn it_reports_missing_fields() {
+        let wmi_con = wmi_con();
+
+        #[allow(dead_code)]
+        #[derive(Deserialize, Debug)]
+        struct Win32_OperatingSystem {
+            Caption: String,
+            NoSuchField: String,
+        }
+
+        let report = wmi_con.validate_struct::<Win32_OperatingSystem>().unwrap();
+
+        assert!(!report.is_ok());
+        assert_eq!(report.missing, ["NoSuchField"]);
+        assert_eq!(report.instances_checked, 0);
+    }
+
+    #[test]
+    fn it_reports_type_mismatches() {
+        let wmi_con = wmi_con();
+
+        #[allow(dead_code)]
+        #[derive(Deserialize, Debug)]
+        struct Win32_OperatingSystem {
+            Caption: String,
+            NumberOfProcesses: u8,
+        }
+
+        let report = wmi_con.validate_struct::<Win32_OperatingSystem>().unwrap();
+
+        assert_eq!(report.mismatches.len(), 1);
+        assert_eq!(report.mismatches[0].property, "NumberOfProcesses");
+        assert_eq!(report.mismatches[0].cim_type, "uint32");
+        assert!(report.to_string().contains("NumberOfProcesses"));
+    }
+
+    #[test]
+    fn it_validates_matching_structs() {
+        let wmi_con = wmi_con();
+
+        #[allow(dead_code)]
+        #[derive(Deserialize, Debug)]
+        struct Win32_OperatingSystem {
+            Caption: String,
+            NumberOfProcesses: u64,
+        }
+
+        crate::validate_struct!(wmi_con, Win32_OperatingSystem);
+    }
+}
@@ -1,15 +1,30 @@
 use crate::{
-    result_enumerator::IWbemClassWrapper, safearray::safe_array_to_vec, WMIError, WMIResult,
+    result_enumerator::IWbemClassWrapper,
+    safe_variant::{raw_variant, variant_from_raw},
+    safearray::safe_array_from_slice,
+    WMIError, WMIResult,
 };
-use serde::Serialize;
-use std::convert::TryFrom;
+use serde::{ser::SerializeSeq, Serialize, Serializer};
+use std::{convert::TryFrom, mem::ManuallyDrop, string::FromUtf16Error};
 use windows::core::{ComInterface, IUnknown, BSTR};
 use windows::Win32::Foundation::{VARIANT_FALSE, VARIANT_TRUE};
-use windows::Win32::System::Com::{self, VARENUM, VARIANT, VT_ARRAY, VT_TYPEMASK};
+use windows::Win32::System::Com::{self, VARIANT, VARIANT_0_0_0};
 use windows::Win32::System::Wmi::{self, IWbemClassObject, CIMTYPE_ENUMERATION};
 
-#[derive(Debug, PartialEq, Serialize)]
-#[serde(untagged)]
+/// A value read from (or written to) a WMI object.
+///
+/// Variants serialize to their inner value, without a tag, so they are written as plain values
+/// by binary formats (like MessagePack or CBOR) as well as by JSON:
+/// * `Empty`, `Null` and `Unknown` are serialized as unit (`null`).
+/// * Non-empty arrays of `UI1` (like `uint8[]` properties) are serialized as bytes,
+///   which formats without a bytes type (like JSON) write as a sequence of numbers.
+/// * Datetimes and references are serialized as their DMTF datetime and object path strings, as returned by WMI.
+/// * Objects are serialized as maps, in the order of the properties of their class.
+///
+/// Since the type of the variant is not written, deserializing a `Variant` relies on `deserialize_any`,
+/// so it only works with self-describing formats (which all of the above are), and numbers are not always
+/// read back as the same variant (bytes are read back as an array of `UI1`).
+#[derive(Debug, PartialEq)]
 pub enum Variant {
     Empty,
     Null,
@@ -72,6 +87,21 @@ macro_rules! cast_num {
     };
 }
 
+/// Convert the data of a `BSTR` (which is read using its length prefix) into a `String`.
+///
+/// Most values returned by WMI are ASCII, which are converted in bulk
+/// (both loops are simple enough to be vectorized) before falling back to a full UTF-16 decoding.
+pub(crate) fn string_from_wide(wide: &[u16]) -> Result<String, FromUtf16Error> {
+    if wide.iter().all(|&unit| unit < 0x80) {
+        let bytes: Vec<u8> = wide.iter().map(|&unit| unit as u8).collect();
+
+        // Safety: All the bytes are ASCII, which is valid UTF-8.
+        return Ok(unsafe { String::from_utf8_unchecked(bytes) });
+    }
+
+    String::from_utf16(wide)
+}
+
 impl Variant {
     /// Create a `Variant` instance from a raw `VARIANT`.
     ///
@@ -79,100 +109,56 @@ impl Variant {
     ///
     /// This function is unsafe as it is the caller's responsibility to ensure that the VARIANT is correctly initialized.
     pub fn from_variant(vt: &VARIANT) -> WMIResult<Variant> {
-        let variant_type = unsafe { vt.Anonymous.Anonymous.vt };
-
-        // variant_type has two 'forms':
-        // 1. A simple type like `VT_BSTR` .
-        // 2. An array of certain type like `VT_ARRAY | VT_BSTR`.
-        if variant_type.0 & VT_ARRAY.0 == VT_ARRAY.0 {
-            let array = unsafe { vt.Anonymous.Anonymous.Anonymous.parray };
-
-            let item_type = VARENUM(variant_type.0 & VT_TYPEMASK.0);
-
-            return Ok(Variant::Array(unsafe {
-                safe_array_to_vec(&*array, item_type)?
-            }));
-        }
-
-        // See https://msdn.microsoft.com/en-us/library/cc237865.aspx for more info.
-        // Rust can infer the return type of `vt.*Val()` calls,
-        // but it's easier to read when the type is named explicitly.
-        let variant_value = match variant_type {
-            Com::VT_BSTR => {
-                let bstr_ptr: &BSTR = unsafe { &vt.Anonymous.Anonymous.Anonymous.bstrVal };
-
-                Variant::String(bstr_ptr.try_into()?)
-            }
-            Com::VT_I1 => {
-                let num = unsafe { vt.Anonymous.Anonymous.Anonymous.cVal };
-
-                Variant::I1(num as _)
-            }
-            Com::VT_I2 => {
-                let num: i16 = unsafe { vt.Anonymous.Anonymous.Anonymous.iVal };
-
-                Variant::I2(num)
-            }
-            Com::VT_I4 => {
-                let num: i32 = unsafe { vt.Anonymous.Anonymous.Anonymous.lVal };
-
-                Variant::I4(num)
-            }
-            Com::VT_I8 => {
-                let num: i64 = unsafe { vt.Anonymous.Anonymous.Anonymous.llVal };
-
-                Variant::I8(num)
-            }
-            Com::VT_R4 => {
-                let num: f32 = unsafe { vt.Anonymous.Anonymous.Anonymous.fltVal };
-
-                Variant::R4(num)
-            }
-            Com::VT_R8 => {
-                let num: f64 = unsafe { vt.Anonymous.Anonymous.Anonymous.dblVal };
-
-                Variant::R8(num)
-            }
-            Com::VT_BOOL => {
-                let value = unsafe { vt.Anonymous.Anonymous.Anonymous.boolVal };
-
-                match value {
-                    VARIANT_FALSE => Variant::Bool(false),
-                    VARIANT_TRUE => Variant::Bool(true),
-                    _ => return Err(WMIError::ConvertBoolError(value.0)),
-                }
-            }
-            Com::VT_UI1 => {
-                let num: u8 = unsafe { vt.Anonymous.Anonymous.Anonymous.bVal };
-
-                Variant::UI1(num)
-            }
-            Com::VT_UI2 => {
-                let num: u16 = unsafe { vt.Anonymous.Anonymous.Anonymous.uiVal };
-
-                Variant::UI2(num)
-            }
-            Com::VT_UI4 => {
-                let num: u32 = unsafe { vt.Anonymous.Anonymous.Anonymous.ulVal };
-
-                Variant::UI4(num)
-            }
-            Com::VT_UI8 => {
-                let num: u64 = unsafe { vt.Anonymous.Anonymous.Anonymous.ullVal };
+        unsafe { variant_from_raw(vt) }
+    }
 
-                Variant::UI8(num)
+    /// Create a raw `VARIANT` from this `Variant`.
+    ///
+    /// Scalar values (numbers, strings and booleans) and non-empty arrays of them can be converted.
+    /// The caller is responsible for calling `VariantClear` on the result.
+    pub fn to_raw_variant(&self) -> WMIResult<VARIANT> {
+        let vt = match self {
+            Variant::Empty => VARIANT::default(),
+            Variant::Null => raw_variant(Com::VT_NULL, VARIANT_0_0_0 { llVal: 0 }),
+            Variant::String(s) => raw_variant(
+                Com::VT_BSTR,
+                VARIANT_0_0_0 {
+                    bstrVal: ManuallyDrop::new(BSTR::from(s)),
+                },
+            ),
+            Variant::I1(n) => raw_variant(Com::VT_I1, VARIANT_0_0_0 { cVal: *n as u8 }),
+            Variant::I2(n) => raw_variant(Com::VT_I2, VARIANT_0_0_0 { iVal: *n }),
+            Variant::I4(n) => raw_variant(Com::VT_I4, VARIANT_0_0_0 { lVal: *n }),
+            Variant::I8(n) => raw_variant(Com::VT_I8, VARIANT_0_0_0 { llVal: *n }),
+            Variant::R4(f) => raw_variant(Com::VT_R4, VARIANT_0_0_0 { fltVal: *f }),
+            Variant::R8(f) => raw_variant(Com::VT_R8, VARIANT_0_0_0 { dblVal: *f }),
+            Variant::Bool(b) => raw_variant(
+                Com::VT_BOOL,
+                VARIANT_0_0_0 {
+                    boolVal: if *b { VARIANT_TRUE } else { VARIANT_FALSE },
+                },
+            ),
+            Variant::UI1(n) => raw_variant(Com::VT_UI1, VARIANT_0_0_0 { bVal: *n }),
+            Variant::UI2(n) => raw_variant(Com::VT_UI2, VARIANT_0_0_0 { uiVal: *n }),
+            Variant::UI4(n) => raw_variant(Com::VT_UI4, VARIANT_0_0_0 { ulVal: *n }),
+            Variant::UI8(n) => raw_variant(Com::VT_UI8, VARIANT_0_0_0 { ullVal: *n }),
+            Variant::Array(items) => {
+                let (item_type, array) = safe_array_from_slice(items)?;
+
+                raw_variant(
+                    Com::VARENUM(Com::VT_ARRAY.0 | item_type.0),
+                    VARIANT_0_0_0 { parray: array },
+                )
             }
-            Com::VT_EMPTY => Variant::Empty,
-            Com::VT_NULL => Variant::Null,
-            Com::VT_UNKNOWN => {
-                let unk = unsafe { &vt.Anonymous.Anonymous.Anonymous.punkVal };
-                let ptr = unk.as_ref().ok_or(WMIError::NullPointerResult)?;
-                Variant::Unknown(IUnknownWrapper::new(ptr.clone()))
+            other => {
+                return Err(WMIError::ConvertVariantError(format!(
+                    "Variant {:?} cannot be turned into a VARIANT",
+                    other
+                )))
             }
-            _ => return Err(WMIError::ConvertError(variant_type.0)),
         };
 
-        Ok(variant_value)
+        Ok(vt)
     }
 
     /// Convert the variant it to a specific type.
@@ -267,37 +253,124 @@ impl Variant {
 
         Ok(converted_variant)
     }
+
+    /// Copy the variant. COM objects are not copied, but shared (with an added reference).
+    ///
+    /// `Variant` doesn't implement `Clone`, since a shared object can be modified through any of its copies.
+    pub(crate) fn duplicate(&self) -> Self {
+        match self {
+            Variant::Empty => Variant::Empty,
+            Variant::Null => Variant::Null,
+            Variant::String(s) => Variant::String(s.clone()),
+            Variant::I1(n) => Variant::I1(*n),
+            Variant::I2(n) => Variant::I2(*n),
+            Variant::I4(n) => Variant::I4(*n),
+            Variant::I8(n) => Variant::I8(*n),
+            Variant::R4(f) => Variant::R4(*f),
+            Variant::R8(f) => Variant::R8(*f),
+            Variant::Bool(b) => Variant::Bool(*b),
+            Variant::UI1(n) => Variant::UI1(*n),
+            Variant::UI2(n) => Variant::UI2(*n),
+            Variant::UI4(n) => Variant::UI4(*n),
+            Variant::UI8(n) => Variant::UI8(*n),
+            Variant::Array(variants) => {
+                Variant::Array(variants.iter().map(Variant::duplicate).collect())
+            }
+            Variant::Unknown(u) => Variant::Unknown(IUnknownWrapper::new(u.inner.clone())),
+            Variant::Object(o) => Variant::Object(o.clone()),
+        }
+    }
 }
 
 /// A wrapper around the [`IUnknown`] interface. \
 /// Used to retrive [`IWbemClassObject`][winapi::um::Wmi::IWbemClassObject]
 ///
-#[repr(transparent)]
+/// The wrapper is `repr(transparent)` over [`IUnknown`] unless the `leak-check` feature is enabled.
+///
+#[cfg_attr(not(feature = "leak-check"), repr(transparent))]
 #[derive(Debug, PartialEq, Eq)]
 pub struct IUnknownWrapper {
     inner: IUnknown,
+    #[cfg(feature = "leak-check")]
+    _tracked: crate::leak_check::Tracked,
 }
 
 impl IUnknownWrapper {
     /// Wrapps around a non-null pointer to IUnknown
     ///
     pub fn new(ptr: IUnknown) -> Self {
-        IUnknownWrapper { inner: ptr }
+        IUnknownWrapper {
+            inner: ptr,
+            #[cfg(feature = "leak-check")]
+            _tracked: crate::leak_check::Tracked::new(),
+        }
     }
 
     pub fn to_wbem_class_obj(&self) -> WMIResult<IWbemClassWrapper> {
-        Ok(IWbemClassWrapper {
-            inner: self.inner.cast::<IWbemClassObject>()?,
-        })
+        Ok(IWbemClassWrapper::new(
+            self.inner.cast::<IWbemClassObject>()?,
+        ))
+    }
+}
+
+impl Serialize for Variant {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Variant::Empty | Variant::Null => serializer.serialize_unit(),
+            Variant::String(s) => serializer.serialize_str(s),
+            Variant::I1(n) => serializer.serialize_i8(*n),
+            Variant::I2(n) => serializer.serialize_i16(*n),
+            Variant::I4(n) => serializer.serialize_i32(*n),
+            Variant::I8(n) => serializer.serialize_i64(*n),
+            Variant::R4(n) => serializer.serialize_f32(*n),
+            Variant::R8(n) => serializer.serialize_f64(*n),
+            Variant::Bool(b) => serializer.serialize_bool(*b),
+            Variant::UI1(n) => serializer.serialize_u8(*n),
+            Variant::UI2(n) => serializer.serialize_u16(*n),
+            Variant::UI4(n) => serializer.serialize_u32(*n),
+            Variant::UI8(n) => serializer.serialize_u64(*n),
+            Variant::Array(items) => match as_bytes(items) {
+                Some(bytes) => serializer.serialize_bytes(&bytes),
+                None => {
+                    let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                    for item in items {
+                        seq.serialize_element(item)?;
+                    }
+                    seq.end()
+                }
+            },
+            Variant::Unknown(unknown) => unknown.serialize(serializer),
+            Variant::Object(obj) => obj.serialize(serializer),
+        }
     }
 }
 
+/// The bytes of an array, if it is a non-empty array of `UI1`s.
+///
+/// Empty arrays have no item type, so they are always serialized as (empty) sequences.
+fn as_bytes(items: &[Variant]) -> Option<Vec<u8>> {
+    if items.is_empty() {
+        return None;
+    }
+
+    items
+        .iter()
+        .map(|item| match item {
+            Variant::UI1(n) => Some(*n),
+            _ => None,
+        })
+        .collect()
+}
+
 impl Serialize for IUnknownWrapper {
     /// IUnknownWrapper serializaes to `()`, since it should have been converted into [Variant::Object]
     ///
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
-        S: serde::Serializer,
+        S: Serializer,
     {
         serializer.serialize_unit()
     }
@@ -322,6 +395,35 @@ macro_rules! impl_try_from_variant {
     };
 }
 
+macro_rules! impl_from_type {
+    ($target_type:ty, $variant_type:ident) => {
+        impl From<$target_type> for Variant {
+            fn from(value: $target_type) -> Self {
+                Variant::$variant_type(value)
+            }
+        }
+    };
+}
+
+impl_from_type!(String, String);
+impl_from_type!(i8, I1);
+impl_from_type!(i16, I2);
+impl_from_type!(i32, I4);
+impl_from_type!(i64, I8);
+impl_from_type!(u8, UI1);
+impl_from_type!(u16, UI2);
+impl_from_type!(u32, UI4);
+impl_from_type!(u64, UI8);
+impl_from_type!(f32, R4);
+impl_from_type!(f64, R8);
+impl_from_type!(bool, Bool);
+
+impl From<&str> for Variant {
+    fn from(value: &str) -> Self {
+        Variant::String(value.to_owned())
+    }
+}
+
 impl_try_from_variant!(String, String);
 impl_try_from_variant!(i8, I1);
 impl_try_from_variant!(i16, I2);
@@ -479,12 +581,27 @@ mod tests {
         assert_eq!(converted, Variant::R8(1.0));
     }
 
+    #[test]
+    fn it_converts_wide_strings() {
+        let ascii: Vec<u16> = "Win32_Process".encode_utf16().collect();
+        assert_eq!(string_from_wide(&ascii).unwrap(), "Win32_Process");
+
+        let unicode: Vec<u16> = "C:\\Users\\Zoë\\😀".encode_utf16().collect();
+        assert_eq!(string_from_wide(&unicode).unwrap(), "C:\\Users\\Zoë\\😀");
+
+        assert_eq!(string_from_wide(&[]).unwrap(), "");
+        string_from_wide(&[0x61, 0xD800]).unwrap_err();
+    }
+
     #[test]
     fn it_convert_into_cim_char16() {
         let cim_type = Wmi::CIM_CHAR16;
         let variant = Variant::UI2(67);
         let converted = variant.convert_into_cim_type(cim_type).unwrap();
         assert_eq!(converted, Variant::String("C".to_string()));
+
+        let ch: char = serde::Deserialize::deserialize(converted).unwrap();
+        assert_eq!(ch, 'C');
     }
 
     #[test]
@@ -532,6 +649,38 @@ mod tests {
         assert_eq!(converted, Variant::Array(vec![Variant::UI1(1)]));
     }
 
+    #[test]
+    fn it_converts_to_raw_variant_and_back() {
+        let variants = [
+            Variant::Null,
+            Variant::from("text"),
+            Variant::from(-1i8),
+            Variant::from(-2i16),
+            Variant::from(-3i32),
+            Variant::from(-4i64),
+            Variant::from(1u8),
+            Variant::from(2u16),
+            Variant::from(3u32),
+            Variant::from(4u64),
+            Variant::from(1.5f32),
+            Variant::from(2.5f64),
+            Variant::from(true),
+            Variant::Array(vec![Variant::UI1(1), Variant::UI1(2)]),
+            Variant::Array(vec![Variant::from("a"), Variant::from("b")]),
+        ];
+
+        for variant in variants {
+            let mut vt = variant.to_raw_variant().unwrap();
+            assert_eq!(Variant::from_variant(&vt).unwrap(), variant);
+            unsafe { windows::Win32::System::Ole::VariantClear(&mut vt).unwrap() };
+        }
+
+        assert!(Variant::Array(vec![]).to_raw_variant().is_err());
+        assert!(Variant::Array(vec![Variant::UI1(1), Variant::from("a")])
+            .to_raw_variant()
+            .is_err());
+    }
+
     #[test]
     fn it_convert_an_empty_into_cim_type_array() {
         let cim_type = CIMTYPE_ENUMERATION(Wmi::CIM_STRING.0 | Wmi::CIM_FLAG_ARRAY.0);
@@ -543,4 +692,41 @@ mod tests {
         let converted = variant.convert_into_cim_type(cim_type).unwrap();
         assert_eq!(converted, Variant::Array(vec![]));
     }
+
+    #[test]
+    fn it_serializes_without_tags() {
+        let variant = Variant::Array(vec![Variant::UI4(1), Variant::String("a".to_string())]);
+        assert_eq!(serde_json::to_string(&variant).unwrap(), r#"[1,"a"]"#);
+        assert_eq!(serde_json::to_string(&Variant::Empty).unwrap(), "null");
+
+        let mut cbor = vec![];
+        ciborium::ser::into_writer(&variant, &mut cbor).unwrap();
+        let value: ciborium::value::Value = ciborium::de::from_reader(cbor.as_slice()).unwrap();
+        assert_eq!(
+            value,
+            ciborium::value::Value::Array(vec![1.into(), "a".into()])
+        );
+    }
+
+    #[test]
+    fn it_serializes_byte_arrays_as_bytes() {
+        let bytes = Variant::Array(vec![Variant::UI1(0), Variant::UI1(255)]);
+
+        // JSON has no bytes type, so the output is unchanged.
+        assert_eq!(serde_json::to_string(&bytes).unwrap(), "[0,255]");
+
+        let mut cbor = vec![];
+        ciborium::ser::into_writer(&bytes, &mut cbor).unwrap();
+        let value: ciborium::value::Value = ciborium::de::from_reader(cbor.as_slice()).unwrap();
+        assert_eq!(value, ciborium::value::Value::Bytes(vec![0, 255]));
+
+        // Bytes are read back as an array of `UI1`.
+        let variant: Variant = ciborium::de::from_reader(cbor.as_slice()).unwrap();
+        assert_eq!(variant, bytes);
+
+        let mut cbor = vec![];
+        ciborium::ser::into_writer(&Variant::Array(vec![]), &mut cbor).unwrap();
+        let value: ciborium::value::Value = ciborium::de::from_reader(cbor.as_slice()).unwrap();
+        assert_eq!(value, ciborium::value::Value::Array(vec![]));
+    }
 }
@@ -0,0 +1,315 @@
+//! Query structs whose classes gained properties over time, on systems which don't have them yet.
+//!
+//! Classes like `Win32_OperatingSystem` gain properties in newer versions of Windows, and a query selecting
+//! a property which is not defined on the queried system fails. A struct implementing [`Versioned`] lists
+//! the fields which were added after the first version of its schema, and [`WMIConnection::query_versioned`]
+//! only selects the fields defined by the class, leaves the others to their defaults,
+//! and reports which fields were defaulted:
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use serde::Deserialize;
+//! use wmi::versioned::Versioned;
+//!
+//! #[derive(Deserialize, Debug)]
+//! #[serde(rename = "Win32_OperatingSystem")]
+//! #[serde(rename_all = "PascalCase")]
+//! struct OperatingSystem {
+//!     caption: String,
+//!     // Added in the second version of the schema.
+//!     #[serde(default)]
+//!     product_type: Option<u32>,
+//!     // Added in the third version of the schema, but not defined by any version of Windows (yet).
+//!     #[serde(default)]
+//!     future_property: Option<String>,
+//! }
+//!
+//! impl Versioned for OperatingSystem {
+//!     const SCHEMA_VERSION: u32 = 3;
+//!     const ADDED_FIELDS: &'static [(&'static str, u32)] = &[("ProductType", 2), ("FutureProperty", 3)];
+//! }
+//!
+//! let results = con.query_versioned::<OperatingSystem>()?;
+//!
+//! assert_eq!(results.defaulted, ["FutureProperty"]);
+//! assert_eq!(results.schema_version, 2);
+//! assert!(results.rows[0].future_property.is_none());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Fields which are not listed in [`Versioned::ADDED_FIELDS`] are required, and the query fails with
+//! [`WMIError::MissingPropertiesError`] if they are not defined by the class.
+use crate::{
+    connection::WMIConnection,
+    de::{meta::ALL_PROPERTIES, wbem_class_de::Deserializer},
+    query::{get_query_segments, FilterValue},
+    WMIError, WMIResult,
+};
+use serde::de;
+use std::{collections::HashMap, sync::Arc};
+
+/// A struct whose fields follow a versioned schema of its class.
+pub trait Versioned {
+    /// The version of the schema the struct was written against.
+    const SCHEMA_VERSION: u32;
+
+    /// The fields (using their serialized names) which were added after the first version of the schema,
+    /// with the version they were added in.
+    ///
+    /// These fields must have a default, like `Option` fields or fields with `#[serde(default)]`.
+    const ADDED_FIELDS: &'static [(&'static str, u32)];
+}
+
+/// The results of [`WMIConnection::query_versioned`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionedResults<T> {
+    pub rows: Vec<T>,
+    /// The highest version of the schema which is fully supported by the class:
+    /// [`Versioned::SCHEMA_VERSION`], or the version before the earliest defaulted field was added.
+    pub schema_version: u32,
+    /// The fields which are not defined by the class, and were left to their defaults.
+    pub defaulted: Vec<&'static str>,
+}
+
+impl<T> VersionedResults<T> {
+    /// Whether all the fields were read from the class.
+    pub fn is_complete(&self) -> bool {
+        self.defaulted.is_empty()
+    }
+}
+
+/// How the fields of a versioned struct map to the properties of its class.
+#[derive(Debug, PartialEq)]
+struct ResolvedFields {
+    selected: Vec<&'static str>,
+    defaulted: Vec<&'static str>,
+    missing: Vec<&'static str>,
+    schema_version: u32,
+}
+
+fn resolve_fields<T: Versioned>(fields: &[&'static str], properties: &[String]) -> ResolvedFields {
+    let mut resolved = ResolvedFields {
+        selected: vec![],
+        defaulted: vec![],
+        missing: vec![],
+        schema_version: T::SCHEMA_VERSION,
+    };
+
+    for field in fields {
+        // System properties are defined by every class.
+        let defined = field.starts_with("__")
+            || properties
+                .iter()
+                .any(|property| property.eq_ignore_ascii_case(field));
+
+        if defined {
+            resolved.selected.push(field);
+            continue;
+        }
+
+        match T::ADDED_FIELDS
+            .iter()
+            .find(|(added, _)| added.eq_ignore_ascii_case(field))
+        {
+            Some((_, version)) => {
+                resolved.defaulted.push(field);
+                resolved.schema_version = resolved.schema_version.min(version.saturating_sub(1));
+            }
+            None => resolved.missing.push(field),
+        }
+    }
+
+    resolved
+}
+
+///
+/// ### Additional versioned schema methods
+///
+impl WMIConnection {
+    /// Query the properties of type T (its fields) which are defined by its class, and default the others.
+    ///
+    /// See the [module level documentation](crate::versioned) for an example.
+    pub fn query_versioned<T>(&self) -> WMIResult<VersionedResults<T>>
+    where
+        T: de::DeserializeOwned + Versioned,
+    {
+        self.filtered_query_versioned(&HashMap::new())
+    }
+
+    /// Like [`query_versioned`](WMIConnection::query_versioned), with filters (see [`WMIConnection::filtered_query`]).
+    ///
+    /// Filtering on a defaulted field fails, like filtering on any property which is not defined by the class.
+    pub fn filtered_query_versioned<T>(
+        &self,
+        filters: &HashMap<String, FilterValue>,
+    ) -> WMIResult<VersionedResults<T>>
+    where
+        T: de::DeserializeOwned + Versioned,
+    {
+        let (class, fields, optional_where_clause) = get_query_segments::<T>(Some(filters))?;
+
+        // All the properties of the class are selected anyway.
+        if fields == ALL_PROPERTIES {
+            return Ok(VersionedResults {
+                rows: self.filtered_query(filters)?,
+                schema_version: T::SCHEMA_VERSION,
+                defaulted: vec![],
+            });
+        }
+
+        let properties: Vec<String> = self
+            .describe_class(class)?
+            .properties
+            .into_iter()
+            .map(|property| property.name)
+            .collect();
+
+        let resolved = resolve_fields::<T>(fields, &properties);
+
+        if !resolved.missing.is_empty() {
+            return Err(WMIError::MissingPropertiesError {
+                class: class.to_owned(),
+                properties: resolved.missing.iter().map(|s| s.to_string()).collect(),
+            });
+        }
+
+        let projection = if resolved.selected.is_empty() {
+            "__CLASS".to_owned()
+        } else {
+            resolved.selected.join(",")
+        };
+
+        let query = format!(
+            "SELECT {} FROM {} {}",
+            projection, class, optional_where_clause
+        );
+
+        let absent: Arc<[String]> = resolved
+            .defaulted
+            .iter()
+            .map(|field| field.to_string())
+            .collect();
+
+        let mut rows = vec![];
+
+        for item in self.exec_query_native_wrapper(query)? {
+            let mut deserializer = Deserializer::from_wbem_class_obj(item?)
+                .with_options(self.de_options.clone())
+                .with_absent_fields(absent.clone());
+
+            rows.push(T::deserialize(&mut deserializer)?);
+        }
+
+        Ok(VersionedResults {
+            rows,
+            schema_version: resolved.schema_version,
+            defaulted: resolved.defaulted,
+        })
+    }
+}
+
+#[allow(non_snake_case)]
+#[allow(non_camel_case_types)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug)]
+    struct Win32_OperatingSystem {
+        Caption: String,
+        #[serde(default)]
+        BuildNumber: String,
+        #[serde(default)]
+        NoSuchProperty_v3: Option<u32>,
+        #[serde(default)]
+        NoSuchProperty_v4: Vec<String>,
+    }
+
+    impl Versioned for Win32_OperatingSystem {
+        const SCHEMA_VERSION: u32 = 4;
+        const ADDED_FIELDS: &'static [(&'static str, u32)] = &[
+            ("BuildNumber", 2),
+            ("NoSuchProperty_v3", 3),
+            ("NoSuchProperty_v4", 4),
+        ];
+    }
+
+    #[test]
+    fn it_resolves_versioned_fields() {
+        let properties = vec!["Caption".to_owned(), "buildnumber".to_owned()];
+
+        let resolved = resolve_fields::<Win32_OperatingSystem>(
+            &[
+                "Caption",
+                "BuildNumber",
+                "NoSuchProperty_v3",
+                "NoSuchProperty_v4",
+                "__PATH",
+            ],
+            &properties,
+        );
+
+        assert_eq!(resolved.selected, ["Caption", "BuildNumber", "__PATH"]);
+        assert_eq!(
+            resolved.defaulted,
+            ["NoSuchProperty_v3", "NoSuchProperty_v4"]
+        );
+        assert!(resolved.missing.is_empty());
+        assert_eq!(resolved.schema_version, 2);
+
+        let resolved = resolve_fields::<Win32_OperatingSystem>(&["Caption", "Other"], &properties);
+        assert_eq!(resolved.missing, ["Other"]);
+        assert_eq!(resolved.schema_version, 4);
+    }
+
+    #[test]
+    fn it_defaults_fields_added_in_later_versions() {
+        let wmi_con = wmi_con();
+
+        let results = wmi_con.query_versioned::<Win32_OperatingSystem>().unwrap();
+
+        assert_eq!(
+            results.defaulted,
+            ["NoSuchProperty_v3", "NoSuchProperty_v4"]
+        );
+        assert_eq!(results.schema_version, 2);
+        assert!(!results.is_complete());
+
+        let os = &results.rows[0];
+        assert!(os.Caption.contains("Microsoft Windows"));
+        assert_ne!(os.BuildNumber, "");
+        assert_eq!(os.NoSuchProperty_v3, None);
+        assert!(os.NoSuchProperty_v4.is_empty());
+    }
+
+    #[test]
+    fn it_fails_on_missing_required_fields() {
+        #[derive(Deserialize, Debug)]
+        struct Win32_OperatingSystem {
+            #[allow(dead_code)]
+            NoSuchProperty: Option<String>,
+        }
+
+        impl Versioned for Win32_OperatingSystem {
+            const SCHEMA_VERSION: u32 = 1;
+            const ADDED_FIELDS: &'static [(&'static str, u32)] = &[];
+        }
+
+        let wmi_con = wmi_con();
+
+        let res = wmi_con.query_versioned::<Win32_OperatingSystem>();
+
+        match res {
+            Err(WMIError::MissingPropertiesError { class, properties }) => {
+                assert_eq!(class, "Win32_OperatingSystem");
+                assert_eq!(properties, ["NoSuchProperty"]);
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Watch a property of the instances of a class, receiving its old and new values when it changes.
+//!
+//! This subscribes to the `__InstanceModificationEvent`s of the class for which the property changed
+//! (WMI polls the instances at the given interval), and extracts the values from their
+//! `PreviousInstance` and `TargetInstance`:
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! use serde::Deserialize;
+//! use std::time::Duration;
+//!
+//! #[derive(Deserialize, Debug)]
+//! #[serde(rename_all = "PascalCase")]
+//! struct Battery {
+//!     #[serde(rename = "DeviceID")]
+//!     device_id: String,
+//! }
+//!
+//! let changes = con.watch_property::<Battery, u16>(
+//!     "Win32_Battery",
+//!     "EstimatedChargeRemaining",
+//!     Duration::from_secs(10),
+//! )?;
+//!
+//! for change in changes {
+//!     let change = change?;
+//!     println!("{}: {}% -> {}%", change.instance.device_id, change.old, change.new);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    de::{meta::validate_identifier, options::DeserializeOptions},
+    query::quote_and_escape_wql_str,
+    result_enumerator::IWbemClassWrapper,
+    Variant, WMIConnection, WMIError, WMIResult,
+};
+use futures::{Stream, StreamExt};
+use serde::de;
+use std::time::Duration;
+
+/// A change of a watched property, see [`WMIConnection::watch_property`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropertyChange<T, V> {
+    /// The instance after the change.
+    pub instance: T,
+    pub old: V,
+    pub new: V,
+}
+
+///
+/// ### Additional property watch methods
+///
+impl WMIConnection {
+    /// Subscribe to the changes of a property of the instances of a class, polled every `interval`.
+    ///
+    /// Each change contains the instance after the change (deserialized into `T`),
+    /// and the previous and new values of the property (deserialized into `V`).
+    ///
+    /// See the [module level documentation](crate::watch) for an example.
+    pub fn watch_property<'a, T, V>(
+        &'a self,
+        class: &str,
+        property: &str,
+        interval: Duration,
+    ) -> WMIResult<impl Iterator<Item = WMIResult<PropertyChange<T, V>>> + 'a>
+    where
+        T: de::DeserializeOwned + 'a,
+        V: de::DeserializeOwned + 'a,
+    {
+        let query = watch_query(class, property, interval)?;
+        let property = property.to_owned();
+
+        Ok(self
+            .notification_native_wrapper(query)?
+            .map(move |event| property_change(event?, &property, &self.de_options)))
+    }
+
+    /// Like [`watch_property`](WMIConnection::watch_property), returning a stream of changes.
+    pub fn async_watch_property<T, V>(
+        &self,
+        class: &str,
+        property: &str,
+        interval: Duration,
+    ) -> WMIResult<impl Stream<Item = WMIResult<PropertyChange<T, V>>>>
+    where
+        T: de::DeserializeOwned,
+        V: de::DeserializeOwned,
+    {
+        let query = watch_query(class, property, interval)?;
+        let property = property.to_owned();
+        let options = self.de_options.clone();
+
+        Ok(self
+            .async_notification_native_wrapper(query)?
+            .map(move |event| property_change(event?, &property, &options)))
+    }
+}
+
+/// The event query for the modifications of the property.
+fn watch_query(class: &str, property: &str, interval: Duration) -> WMIResult<String> {
+    validate_identifier::<WMIError>(class)?;
+    validate_identifier::<WMIError>(property)?;
+
+    Ok(format!(
+        "SELECT * FROM __InstanceModificationEvent WITHIN {} WHERE TargetInstance ISA {} AND TargetInstance.{property} <> PreviousInstance.{property}",
+        interval.as_secs_f64(),
+        quote_and_escape_wql_str(class),
+        property = property,
+    ))
+}
+
+fn property_change<T, V>(
+    event: IWbemClassWrapper,
+    property: &str,
+    options: &DeserializeOptions,
+) -> WMIResult<PropertyChange<T, V>>
+where
+    T: de::DeserializeOwned,
+    V: de::DeserializeOwned,
+{
+    let previous = embedded_instance(&event, "PreviousInstance")?;
+    let target = embedded_instance(&event, "TargetInstance")?;
+
+    // Deserialize the property alone, using a projection.
+    let projection = [property.to_owned()];
+    let (old,) = previous.into_desr_with_options::<(V,)>(options, Some(&projection))?;
+    let (new,) = target
+        .clone()
+        .into_desr_with_options::<(V,)>(options, Some(&projection))?;
+
+    Ok(PropertyChange {
+        instance: target.into_desr_with_options(options, None)?,
+        old,
+        new,
+    })
+}
+
+fn embedded_instance(event: &IWbemClassWrapper, property: &str) -> WMIResult<IWbemClassWrapper> {
+    match event.get_property(property)? {
+        Variant::Object(instance) => Ok(instance),
+        other => Err(WMIError::ConvertVariantError(format!(
+            "Expected {} to be an object, got {:?}",
+            property, other
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use serde::Deserialize;
+
+    #[test]
+    fn it_builds_watch_queries() {
+        assert_eq!(
+            watch_query("Win32_Battery", "EstimatedChargeRemaining", Duration::from_secs(5)).unwrap(),
+            "SELECT * FROM __InstanceModificationEvent WITHIN 5 WHERE TargetInstance ISA \"Win32_Battery\" \
+            AND TargetInstance.EstimatedChargeRemaining <> PreviousInstance.EstimatedChargeRemaining"
+        );
+
+        assert!(watch_query("Win32_Battery", "x = 1 OR 1", Duration::from_secs(5)).is_err());
+    }
+
+    #[test]
+    fn it_watches_property_changes() {
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "PascalCase")]
+        struct LocalTime {
+            minute: u32,
+        }
+
+        let wmi_con = wmi_con();
+
+        let mut changes = wmi_con
+            .watch_property::<LocalTime, u32>(
+                "Win32_LocalTime",
+                "Second",
+                Duration::from_millis(500),
+            )
+            .unwrap();
+
+        let change = changes.next().unwrap().unwrap();
+
+        assert_ne!(change.old, change.new);
+        assert!(change.new < 60);
+        assert!(change.instance.minute < 60);
+    }
+}
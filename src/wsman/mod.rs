@@ -0,0 +1,341 @@
+//! Query WMI over WS-Management (WinRM), like PowerShell's `Get-CimInstance`.
+//!
+//! WinRM uses HTTP (port 5985) or HTTPS (port 5986), which is often allowed in environments
+//! where DCOM (used by the default [`ComTransport`](crate::ComTransport)) is blocked by firewalls.
+//! The remote computer must have WinRM enabled (for example, using `winrm quickconfig`).
+//!
+//! Results are deserialized using the same serde surface as the COM transport:
+//! the same structs, maps, tuples and enums can be used with both. Since WS-Management does not carry
+//! the CIM types of properties, scalar values are parsed into the type requested by the deserialized struct,
+//! and deserializing into an untyped value (like `HashMap<String, Variant>`) returns strings.
+//! Datetimes and intervals are converted to the DMTF format, so [`WMIDateTime`](crate::WMIDateTime) and
+//! [`WMIDuration`](crate::WMIDuration) work as usual. System properties (like `__Path`) are not returned.
+//! The [`DeserializeOptions`] of the connection are set using [`WsManConnectionBuilder::deserialize_options`].
+//!
+//! ```edition2018,no_run
+//! # fn main() -> wmi::WMIResult<()> {
+//! use serde::Deserialize;
+//! use wmi::{COMLibrary, Credentials, WMIConnection};
+//!
+//! #[derive(Deserialize, Debug)]
+//! #[serde(rename = "Win32_OperatingSystem")]
+//! #[serde(rename_all = "PascalCase")]
+//! struct OperatingSystem {
+//!     caption: String,
+//!     free_physical_memory: u64,
+//! }
+//!
+//! let credentials = Credentials::new("CONTOSO\\inventory", String::from("hunter2"));
+//! let con = WMIConnection::wsman_builder()
+//!     .server("server01")
+//!     .https(true)
+//!     .credentials(credentials)
+//!     .build(COMLibrary::new()?)?;
+//!
+//! let os: OperatingSystem = con.get()?;
+//! println!("{:?}", os);
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    connection::{COMLibrary, WMIConnection},
+    de::{options::DeserializeOptions, owned::Object},
+    safe_variant::SafeVariant,
+    software::{check_win32_product, is_win32_product_query},
+    transport::{deserialize_objects, WbemTransport},
+    Credentials, Variant, WMIResult,
+};
+use log::debug;
+use serde::de::DeserializeOwned;
+use windows::core::{ComInterface, BSTR};
+use windows::Win32::Foundation::VARIANT_FALSE;
+use windows::Win32::System::Com::{CoCreateInstance, IDispatch, CLSCTX_INPROC_SERVER};
+use windows::Win32::System::RemoteManagement::{
+    IWSMan, IWSManConnectionOptions, IWSManEnumerator, IWSManSession, WSMan,
+    WSManFlagCredUsernamePassword, WSManFlagReturnObject, WSManFlagSkipCACheck,
+    WSManFlagSkipCNCheck, WSManFlagUTF8, WSManFlagUseBasic, WSManFlagUseCredSsp,
+    WSManFlagUseKerberos, WSManFlagUseNegotiate,
+};
+
+mod xml;
+
+use xml::Element;
+
+/// The default port of WinRM over HTTP.
+pub const WSMAN_HTTP_PORT: u16 = 5985;
+/// The default port of WinRM over HTTPS.
+pub const WSMAN_HTTPS_PORT: u16 = 5986;
+
+/// The filter dialect used to run WQL queries.
+const WQL_DIALECT: &str = "http://schemas.microsoft.com/wbem/wsman/1/WQL";
+/// The prefix of the resource URIs of WMI classes.
+const WMI_RESOURCE_PREFIX: &str = "http://schemas.microsoft.com/wbem/wsman/1/wmi/";
+
+/// The authentication mechanism used by WinRM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WsManAuthentication {
+    /// Kerberos or NTLM (the default).
+    Negotiate,
+    Kerberos,
+    /// Sends the password in clear text, so should only be used with HTTPS.
+    Basic,
+    /// Delegates the credentials to the remote computer, which must allow it.
+    CredSsp,
+}
+
+impl WsManAuthentication {
+    fn flag(self) -> i32 {
+        match self {
+            WsManAuthentication::Negotiate => WSManFlagUseNegotiate.0,
+            WsManAuthentication::Kerberos => WSManFlagUseKerberos.0,
+            WsManAuthentication::Basic => WSManFlagUseBasic.0,
+            WsManAuthentication::CredSsp => WSManFlagUseCredSsp.0,
+        }
+    }
+}
+
+/// A connection to WMI over WS-Management, see the [module level documentation](crate::wsman).
+pub type WsManConnection = WMIConnection<WsManTransport>;
+
+/// The transport of a [`WsManConnection`].
+#[derive(Clone, Debug)]
+pub struct WsManTransport {
+    _com_con: COMLibrary,
+    session: IWSManSession,
+    namespace: String,
+    allow_win32_product: bool,
+    de_options: DeserializeOptions,
+}
+
+///
+/// ### Additional WS-Management methods
+///
+impl WMIConnection<WsManTransport> {
+    /// Creates a connection to the `ROOT\CIMV2` namespace of the local computer over WS-Management.
+    pub fn wsman(com_lib: COMLibrary) -> WMIResult<Self> {
+        Self::wsman_builder().build(com_lib)
+    }
+
+    pub fn wsman_builder() -> WsManConnectionBuilder {
+        WsManConnectionBuilder::default()
+    }
+}
+
+impl WsManTransport {
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    fn enumerate(&self, query: &str) -> WMIResult<Vec<Object>> {
+        let resource_uri = SafeVariant::from_variant(&Variant::String(format!(
+            "{}{}/*",
+            WMI_RESOURCE_PREFIX,
+            self.namespace.replace('\\', "/").to_lowercase()
+        )))?;
+
+        debug!("Enumerating {:?} over WS-Management", query);
+
+        let enumerator: IWSManEnumerator = unsafe {
+            self.session.Enumerate(
+                // A shallow copy, which is still owned (and cleared) by `resource_uri`.
+                std::ptr::read(resource_uri.as_raw()),
+                &BSTR::from(query),
+                &BSTR::from(WQL_DIALECT),
+                WSManFlagReturnObject.0,
+            )?
+        }
+        .cast()?;
+
+        let mut objects = vec![];
+
+        while unsafe { enumerator.AtEndOfStream()? } == VARIANT_FALSE {
+            let item = unsafe { enumerator.ReadItem()? };
+            let element = Element::parse(&item.to_string())?;
+
+            objects.push(Object::from_element(&element));
+        }
+
+        Ok(objects)
+    }
+}
+
+impl WbemTransport for WsManTransport {
+    fn raw_query<T>(&self, query: &str) -> WMIResult<Vec<T>>
+    where
+        T: DeserializeOwned,
+    {
+        check_win32_product(is_win32_product_query(query), self.allow_win32_product)?;
+
+        let objects = self.enumerate(query)?;
+
+        deserialize_objects(objects, query, &self.de_options)
+    }
+}
+
+/// A builder for a [`WsManConnection`], created using [`WMIConnection::wsman_builder`].
+///
+/// By default, connects to the `ROOT\CIMV2` namespace of the local computer over HTTP, as the current user.
+#[derive(Debug, Default)]
+pub struct WsManConnectionBuilder {
+    server: Option<String>,
+    port: Option<u16>,
+    https: bool,
+    namespace: Option<String>,
+    credentials: Option<Credentials>,
+    authentication: Option<WsManAuthentication>,
+    skip_certificate_checks: bool,
+    allow_win32_product: bool,
+    de_options: DeserializeOptions,
+}
+
+impl WsManConnectionBuilder {
+    /// The remote computer to connect to.
+    pub fn server(mut self, server: &str) -> Self {
+        self.server = Some(server.to_owned());
+        self
+    }
+
+    /// The port of the WinRM listener. Defaults to [`WSMAN_HTTP_PORT`] or [`WSMAN_HTTPS_PORT`].
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Connect using HTTPS. The messages are encrypted even when using HTTP (unless using [`WsManAuthentication::Basic`]).
+    pub fn https(mut self, https: bool) -> Self {
+        self.https = https;
+        self
+    }
+
+    /// The namespace path to connect to (a string or a [`Namespace`](crate::Namespace)).
+    pub fn namespace(mut self, namespace_path: impl AsRef<str>) -> Self {
+        self.namespace = Some(namespace_path.as_ref().to_owned());
+        self
+    }
+
+    /// The credentials to use, see [`Credentials`].
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    pub fn authentication(mut self, authentication: WsManAuthentication) -> Self {
+        self.authentication = Some(authentication);
+        self
+    }
+
+    /// Do not validate the certificate authority and the common name of the server's certificate (for HTTPS).
+    pub fn skip_certificate_checks(mut self, skip: bool) -> Self {
+        self.skip_certificate_checks = skip;
+        self
+    }
+
+    /// Allow queries of `Win32_Product`, see the [`software`](crate::software) module.
+    pub fn allow_win32_product(mut self, allowed: bool) -> Self {
+        self.allow_win32_product = allowed;
+        self
+    }
+
+    /// The options used when deserializing results, see [`DeserializeOptions`].
+    pub fn deserialize_options(mut self, options: DeserializeOptions) -> Self {
+        self.de_options = options;
+        self
+    }
+
+    /// Create a WinRM session. The connection itself is only made by the first query.
+    pub fn build(self, com_lib: COMLibrary) -> WMIResult<WsManConnection> {
+        let (scheme, default_port) = match self.https {
+            true => ("https", WSMAN_HTTPS_PORT),
+            false => ("http", WSMAN_HTTP_PORT),
+        };
+
+        let connection = format!(
+            "{}://{}:{}/wsman",
+            scheme,
+            self.server.as_deref().unwrap_or("localhost"),
+            self.port.unwrap_or(default_port)
+        );
+
+        let mut flags = WSManFlagUTF8.0
+            | self
+                .authentication
+                .unwrap_or(WsManAuthentication::Negotiate)
+                .flag();
+
+        if self.skip_certificate_checks {
+            flags |= WSManFlagSkipCACheck.0 | WSManFlagSkipCNCheck.0;
+        }
+
+        debug!("Calling CoCreateInstance for CLSID_WSMan");
+
+        let wsman: IWSMan = unsafe { CoCreateInstance(&WSMan, None, CLSCTX_INPROC_SERVER)? };
+
+        let options: Option<IDispatch> = match &self.credentials {
+            Some(credentials) => {
+                flags |= WSManFlagCredUsernamePassword.0;
+
+                let options: IWSManConnectionOptions =
+                    unsafe { wsman.CreateConnectionOptions()? }.cast()?;
+
+                unsafe { options.SetUserName(&BSTR::from(credentials.full_user()))? };
+                credentials
+                    .with_password_bstr(|password| unsafe { options.SetPassword(password) })?;
+
+                Some(options.cast()?)
+            }
+            None => None,
+        };
+
+        debug!("Creating a WS-Management session to {}", connection);
+
+        let session: IWSManSession =
+            unsafe { wsman.CreateSession(&BSTR::from(connection), flags, options.as_ref())? }
+                .cast()?;
+
+        Ok(WMIConnection::from_transport(WsManTransport {
+            _com_con: com_lib,
+            session,
+            namespace: self.namespace.unwrap_or_else(|| "ROOT\\CIMV2".to_owned()),
+            allow_win32_product: self.allow_win32_product,
+            de_options: self.de_options,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+    use crate::WMIError;
+    use serde::Deserialize;
+
+    #[test]
+    fn it_matches_the_com_connection() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(rename = "Win32_OperatingSystem")]
+        #[serde(rename_all = "PascalCase")]
+        struct OperatingSystem {
+            caption: String,
+            number_of_processes: u32,
+            #[serde(with = "crate::datetime::raw")]
+            last_boot_up_time: String,
+        }
+
+        let wmi_con = wmi_con();
+        let com_lib = COMLibrary::without_security().unwrap();
+        let wsman_con = WMIConnection::wsman(com_lib).unwrap();
+
+        // WinRM might not be enabled.
+        let os: OperatingSystem = match wsman_con.get() {
+            Ok(os) => os,
+            Err(WMIError::HResultError { .. }) => return,
+            Err(err) => panic!("{}", err),
+        };
+
+        let expected: OperatingSystem = wmi_con.get().unwrap();
+        assert_eq!(os.caption, expected.caption);
+        assert!(crate::datetime::raw::is_dmtf_datetime(
+            &os.last_boot_up_time
+        ));
+    }
+}
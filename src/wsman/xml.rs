@@ -0,0 +1,515 @@
+//! A minimal XML parser for the items returned by WS-Management enumerations,
+//! and their conversion into owned [`Value`]s.
+//!
+//! Only the subset of XML used by WinRM is supported: elements, attributes, text, CDATA sections,
+//! comments and the predefined (and numeric) entities. Namespace prefixes are removed from names.
+use crate::{
+    de::owned::{Object, Value},
+    WMIError, WMIResult,
+};
+
+/// The deepest nesting of elements which is parsed, since the parser (and the conversion into values) is recursive.
+const MAX_DEPTH: usize = 128;
+
+/// An XML element. Names are local (without their namespace prefix).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct Element {
+    pub(crate) name: String,
+    pub(crate) attributes: Vec<(String, String)>,
+    pub(crate) children: Vec<Element>,
+    pub(crate) text: String,
+}
+
+impl Element {
+    pub(crate) fn parse(xml: &str) -> WMIResult<Element> {
+        let mut parser = Parser {
+            xml,
+            pos: 0,
+            depth: 0,
+        };
+
+        parser.skip_misc()?;
+        let root = parser.element()?;
+        parser.skip_misc()?;
+
+        if parser.pos != xml.len() {
+            return Err(parser.error("unexpected content after the root element"));
+        }
+
+        Ok(root)
+    }
+
+    pub(crate) fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attr, _)| attr == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+struct Parser<'a> {
+    xml: &'a str,
+    pos: usize,
+    /// The number of elements which are being parsed.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.xml[self.pos..]
+    }
+
+    fn error(&self, message: &str) -> WMIError {
+        WMIError::InvalidWsManResponse(format!("{} (at offset {})", message, self.pos))
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Move past the next `end`.
+    fn skip_past(&mut self, end: &str) -> WMIResult<&'a str> {
+        let rest = self.rest();
+        let idx = rest
+            .find(end)
+            .ok_or_else(|| self.error(&format!("expected {:?}", end)))?;
+
+        self.pos += idx + end.len();
+
+        Ok(&rest[..idx])
+    }
+
+    /// Skip whitespace, the XML declaration, processing instructions, comments and doctypes.
+    fn skip_misc(&mut self) -> WMIResult<()> {
+        loop {
+            self.skip_whitespace();
+
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<!DOCTYPE") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> WMIResult<&'a str> {
+        let rest = self.rest();
+        let len = rest
+            .find(|ch: char| ch.is_whitespace() || matches!(ch, '/' | '>' | '='))
+            .unwrap_or(rest.len());
+
+        if len == 0 {
+            return Err(self.error("expected a name"));
+        }
+
+        self.pos += len;
+
+        Ok(&rest[..len])
+    }
+
+    fn element(&mut self) -> WMIResult<Element> {
+        if !self.rest().starts_with('<') {
+            return Err(self.error("expected an element"));
+        }
+        self.pos += 1;
+
+        let name = self.name()?;
+        let mut element = Element {
+            name: local_name(name).to_owned(),
+            ..Element::default()
+        };
+
+        loop {
+            self.skip_whitespace();
+
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                return Ok(element);
+            }
+
+            if self.rest().starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+
+            let attr = self.name()?;
+            self.skip_whitespace();
+
+            if !self.rest().starts_with('=') {
+                return Err(self.error("expected '='"));
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+
+            let quote = match self.rest().chars().next() {
+                Some(quote @ ('"' | '\'')) => quote,
+                _ => return Err(self.error("expected a quoted attribute value")),
+            };
+            self.pos += 1;
+
+            let value = self.skip_past(if quote == '"' { "\"" } else { "'" })?;
+
+            // Namespace declarations are not needed, since prefixes are removed.
+            if attr != "xmlns" && !attr.starts_with("xmlns:") {
+                element
+                    .attributes
+                    .push((local_name(attr).to_owned(), unescape(value)?));
+            }
+        }
+
+        loop {
+            let rest = self.rest();
+
+            if rest.starts_with("</") {
+                self.pos += 2;
+                let end = self.name()?;
+                self.skip_whitespace();
+
+                if end != name || !self.rest().starts_with('>') {
+                    return Err(self.error(&format!("expected </{}>", name)));
+                }
+                self.pos += 1;
+
+                return Ok(element);
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.pos += 9;
+                let data = self.skip_past("]]>")?;
+                element.text.push_str(data);
+            } else if rest.starts_with('<') {
+                if self.depth == MAX_DEPTH {
+                    return Err(self.error("elements are nested too deeply"));
+                }
+
+                self.depth += 1;
+                let child = self.element()?;
+                self.depth -= 1;
+
+                element.children.push(child);
+            } else if rest.is_empty() {
+                return Err(self.error(&format!("expected </{}>", name)));
+            } else {
+                let len = rest.find('<').unwrap_or(rest.len());
+                element.text.push_str(&unescape(&rest[..len])?);
+                self.pos += len;
+            }
+        }
+    }
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit_once(':').map_or(name, |(_, local)| local)
+}
+
+fn unescape(text: &str) -> WMIResult<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = rest.find(';').ok_or_else(|| {
+            WMIError::InvalidWsManResponse(format!("Unterminated entity in {:?}", text))
+        })?;
+
+        let ch = match &rest[1..end] {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            entity => match entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+            {
+                Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                None => entity
+                    .strip_prefix('#')
+                    .and_then(|dec| dec.parse().ok())
+                    .and_then(char::from_u32),
+            },
+        };
+
+        let ch = ch.ok_or_else(|| {
+            WMIError::InvalidWsManResponse(format!("Unknown entity {:?}", &rest[..=end]))
+        })?;
+
+        unescaped.push(ch);
+        rest = &rest[end + 1..];
+    }
+
+    unescaped.push_str(rest);
+
+    Ok(unescaped)
+}
+
+impl Object {
+    pub(crate) fn from_element(element: &Element) -> Self {
+        // Embedded instances carry their class as `xsi:type="p:Class_Type"`.
+        let class = element
+            .attribute("type")
+            .map(|ty| {
+                let ty = local_name(ty);
+                ty.strip_suffix("_Type").unwrap_or(ty)
+            })
+            .unwrap_or(&element.name);
+
+        let mut properties: Vec<(String, Value)> = Vec::with_capacity(element.children.len());
+
+        for child in &element.children {
+            let value = Value::from_element(child);
+
+            match properties.iter_mut().find(|(name, _)| *name == child.name) {
+                Some((_, Value::Array(items))) => items.push(value),
+                Some((_, existing)) => {
+                    let first = std::mem::replace(existing, Value::Null);
+                    *existing = Value::Array(vec![first, value]);
+                }
+                None => properties.push((child.name.clone(), value)),
+            }
+        }
+
+        Object {
+            class: class.to_owned(),
+            properties,
+        }
+    }
+}
+
+impl Value {
+    fn from_element(element: &Element) -> Self {
+        if element.attribute("nil") == Some("true") {
+            return Value::Null;
+        }
+
+        match element.children.as_slice() {
+            [] => Value::Text(element.text.clone()),
+            [child] if child.children.is_empty() => match child.name.as_str() {
+                "Datetime" => {
+                    Value::Text(datetime_to_dmtf(&child.text).unwrap_or_else(|| child.text.clone()))
+                }
+                "Interval" => {
+                    Value::Text(interval_to_dmtf(&child.text).unwrap_or_else(|| child.text.clone()))
+                }
+                "Date" | "Time" => Value::Text(child.text.clone()),
+                _ => Value::Object(Object::from_element(element)),
+            },
+            _ => Value::Object(Object::from_element(element)),
+        }
+    }
+}
+
+/// Convert an `xs:dateTime` (like `2024-03-01T10:00:00.5+02:00`) into a DMTF datetime
+/// (like `20240301100000.500000+120`), which is how datetimes are returned by the COM API.
+pub(crate) fn datetime_to_dmtf(iso: &str) -> Option<String> {
+    let (date, time) = iso.trim().split_once('T')?;
+
+    let mut date_parts = date.splitn(3, '-');
+    let year: u32 = date_parts.next()?.parse().ok()?;
+    let month: u32 = date_parts.next()?.parse().ok()?;
+    let day: u32 = date_parts.next()?.parse().ok()?;
+
+    let (time, offset) = match time.strip_suffix('Z') {
+        Some(time) => (time, 0),
+        None => match time.rfind(['+', '-']) {
+            Some(idx) => {
+                let (hours, minutes) = time[idx + 1..].split_once(':')?;
+                let minutes = hours.parse::<i32>().ok()? * 60 + minutes.parse::<i32>().ok()?;
+                let sign = if time[idx..].starts_with('-') { -1 } else { 1 };
+
+                (&time[..idx], sign * minutes)
+            }
+            None => (time, 0),
+        },
+    };
+
+    let (hms, fraction) = time.split_once('.').unwrap_or((time, ""));
+
+    let mut hms_parts = hms.splitn(3, ':');
+    let hour: u32 = hms_parts.next()?.parse().ok()?;
+    let minute: u32 = hms_parts.next()?.parse().ok()?;
+    let second: u32 = hms_parts.next()?.parse().ok()?;
+
+    let micros = parse_micros(fraction)?;
+
+    Some(format!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}.{:06}{}{:03}",
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+        micros,
+        if offset < 0 { '-' } else { '+' },
+        offset.abs()
+    ))
+}
+
+/// Convert an `xs:duration` (like `P1DT2H3M4.5S`) into a DMTF interval (like `00000001020304.500000:000`).
+pub(crate) fn interval_to_dmtf(duration: &str) -> Option<String> {
+    let rest = duration.trim().strip_prefix('P')?;
+    let (date, time) = rest.split_once('T').unwrap_or((rest, ""));
+
+    let days: u64 = match date {
+        "" => 0,
+        date => date.strip_suffix('D')?.parse().ok()?,
+    };
+
+    let (mut hours, mut minutes, mut seconds, mut micros) = (0u64, 0u64, 0u64, 0u32);
+    let mut rest = time;
+
+    while !rest.is_empty() {
+        let idx = rest.find(|ch: char| ch.is_ascii_alphabetic())?;
+        let number = &rest[..idx];
+
+        match &rest[idx..=idx] {
+            "H" => hours = number.parse().ok()?,
+            "M" => minutes = number.parse().ok()?,
+            "S" => {
+                let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+                seconds = whole.parse().ok()?;
+                micros = parse_micros(fraction)?;
+            }
+            _ => return None,
+        }
+
+        rest = &rest[idx + 1..];
+    }
+
+    // Normalize overflowing components (like `PT36H`).
+    minutes += seconds / 60;
+    hours += minutes / 60;
+    let days = days + hours / 24;
+
+    Some(format!(
+        "{:08}{:02}{:02}{:02}.{:06}:000",
+        days,
+        hours % 24,
+        minutes % 60,
+        seconds % 60,
+        micros
+    ))
+}
+
+/// Parse up to 6 digits of a fraction of a second into microseconds.
+fn parse_micros(fraction: &str) -> Option<u32> {
+    if !fraction.bytes().all(|ch| ch.is_ascii_digit()) {
+        return None;
+    }
+
+    let digits = &fraction[..fraction.len().min(6)];
+
+    if digits.is_empty() {
+        return Some(0);
+    }
+
+    Some(digits.parse::<u32>().ok()? * 10u32.pow(6 - digits.len() as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OS_ITEM: &str = r#"<p:Win32_OperatingSystem xmlns:p="http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_OperatingSystem" xmlns:cim="http://schemas.dmtf.org/wbem/wscim/1/common" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+        <p:Caption>Microsoft Windows 11 Pro &amp; more</p:Caption>
+        <p:Debug>false</p:Debug>
+        <p:LastBootUpTime><cim:Datetime>2024-03-01T10:00:00.5+02:00</cim:Datetime></p:LastBootUpTime>
+        <p:MUILanguages>en-US</p:MUILanguages>
+        <p:MUILanguages>he-IL</p:MUILanguages>
+        <p:OtherTypeDescription xsi:nil="true"/>
+    </p:Win32_OperatingSystem>"#;
+
+    #[test]
+    fn it_parses_elements() {
+        let root = Element::parse(&format!(
+            "<?xml version=\"1.0\"?><!-- comment -->{}",
+            OS_ITEM
+        ))
+        .unwrap();
+
+        assert_eq!(root.name, "Win32_OperatingSystem");
+        assert_eq!(root.children.len(), 6);
+        assert_eq!(root.children[0].text, "Microsoft Windows 11 Pro & more");
+        assert_eq!(root.children[5].attribute("nil"), Some("true"));
+
+        assert!(Element::parse("<a><b></a>").is_err());
+        assert!(Element::parse("<a>&bogus;</a>").is_err());
+        assert_eq!(
+            Element::parse("<a>&#x41;&#66;<![CDATA[<C>]]></a>")
+                .unwrap()
+                .text,
+            "AB<C>"
+        );
+
+        let nested = |depth: usize| format!("{}{}", "<a>".repeat(depth), "</a>".repeat(depth));
+        assert!(Element::parse(&nested(MAX_DEPTH + 1)).is_ok());
+        assert!(matches!(
+            Element::parse(&nested(100_000)),
+            Err(WMIError::InvalidWsManResponse(_))
+        ));
+    }
+
+    #[test]
+    fn it_converts_elements_to_objects() {
+        let object = Object::from_element(&Element::parse(OS_ITEM).unwrap());
+
+        assert_eq!(object.class, "Win32_OperatingSystem");
+        assert_eq!(
+            object.properties,
+            vec![
+                (
+                    "Caption".to_owned(),
+                    Value::Text("Microsoft Windows 11 Pro & more".to_owned())
+                ),
+                ("Debug".to_owned(), Value::Text("false".to_owned())),
+                (
+                    "LastBootUpTime".to_owned(),
+                    Value::Text("20240301100000.500000+120".to_owned())
+                ),
+                (
+                    "MUILanguages".to_owned(),
+                    Value::Array(vec![
+                        Value::Text("en-US".to_owned()),
+                        Value::Text("he-IL".to_owned())
+                    ])
+                ),
+                ("OtherTypeDescription".to_owned(), Value::Null),
+            ]
+        );
+
+        let projected = object.project(&["Debug".to_owned(), "caption".to_owned()]);
+        assert_eq!(projected.properties[0].1, Value::Text("false".to_owned()));
+        assert_eq!(projected.properties.len(), 2);
+    }
+
+    #[test]
+    fn it_converts_datetimes_and_intervals() {
+        assert_eq!(
+            datetime_to_dmtf("2024-03-01T10:00:00Z").as_deref(),
+            Some("20240301100000.000000+000")
+        );
+        assert_eq!(
+            datetime_to_dmtf("2024-03-01T10:00:00.1234567-05:30").as_deref(),
+            Some("20240301100000.123456-330")
+        );
+        assert_eq!(datetime_to_dmtf("yesterday"), None);
+
+        assert_eq!(
+            interval_to_dmtf("P1DT2H3M4.5S").as_deref(),
+            Some("00000001020304.500000:000")
+        );
+        assert_eq!(
+            interval_to_dmtf("PT36H").as_deref(),
+            Some("00000001120000.000000:000")
+        );
+        assert_eq!(interval_to_dmtf("P1Y"), None);
+    }
+}
@@ -0,0 +1,84 @@
+//! Render objects as CIM-XML, using `IWbemObjectTextSrc`.
+//!
+//! The XML is generated by WMI from the native object (including properties which are not deserialized),
+//! so it can be used to archive the exact output of a provider, or to exchange objects with CIM tooling.
+//!
+//! ```edition2018
+//! # fn main() -> wmi::WMIResult<()> {
+//! # use wmi::*;
+//! # let con = WMIConnection::new(COMLibrary::new()?)?;
+//! let os = con.get_raw_by_path("Win32_OperatingSystem=@")?;
+//!
+//! let xml = os.to_xml()?;
+//! assert!(xml.starts_with("<INSTANCE"));
+//! # Ok(())
+//! # }
+//! ```
+use crate::{result_enumerator::IWbemClassWrapper, WMIResult};
+use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
+use windows::Win32::System::Wmi::{
+    IWbemObjectTextSrc, WbemObjectTextSrc, WMI_OBJ_TEXT, WMI_OBJ_TEXT_CIM_DTD_2_0,
+    WMI_OBJ_TEXT_WMI_DTD_2_0,
+};
+
+/// The XML format used by [`IWbemClassWrapper::to_xml_with_format`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum XmlFormat {
+    /// The DMTF CIM DTD 2.0 (CIM-XML).
+    #[default]
+    CimDtd20,
+    /// The WMI extension of the CIM DTD 2.0, which also includes WMI specific data (like the system properties).
+    WmiDtd20,
+}
+
+impl XmlFormat {
+    fn as_raw(self) -> WMI_OBJ_TEXT {
+        match self {
+            XmlFormat::CimDtd20 => WMI_OBJ_TEXT_CIM_DTD_2_0,
+            XmlFormat::WmiDtd20 => WMI_OBJ_TEXT_WMI_DTD_2_0,
+        }
+    }
+}
+
+impl IWbemClassWrapper {
+    /// Render the object (an instance or a class) as CIM-XML, using the CIM DTD 2.0.
+    ///
+    /// See the [module level documentation](crate::xml) for an example.
+    pub fn to_xml(&self) -> WMIResult<String> {
+        self.to_xml_with_format(XmlFormat::CimDtd20)
+    }
+
+    /// Render the object as XML, using the given DTD.
+    pub fn to_xml_with_format(&self, format: XmlFormat) -> WMIResult<String> {
+        let text_src: IWbemObjectTextSrc =
+            unsafe { CoCreateInstance(&WbemObjectTextSrc, None, CLSCTX_INPROC_SERVER)? };
+
+        let text = unsafe { text_src.GetText(0, &self.inner, format.as_raw().0 as u32, None)? };
+
+        Ok(text.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::*;
+
+    #[test]
+    fn it_renders_objects_as_xml() {
+        let wmi_con = wmi_con();
+
+        let os = wmi_con.get_raw_by_path("Win32_OperatingSystem=@").unwrap();
+
+        let xml = os.to_xml().unwrap();
+        assert!(xml.starts_with("<INSTANCE"));
+        assert!(xml.contains(r#"CLASSNAME="Win32_OperatingSystem""#));
+        assert!(xml.contains(r#"NAME="Caption""#));
+
+        let wmi_xml = os.to_xml_with_format(XmlFormat::WmiDtd20).unwrap();
+        assert!(wmi_xml.starts_with("<INSTANCE"));
+
+        let class = wmi_con.get_raw_by_path("Win32_OperatingSystem").unwrap();
+        assert!(class.to_xml().unwrap().starts_with("<CLASS"));
+    }
+}